@@ -31,14 +31,14 @@ fn main() {
 
     if let Event::WindowEvent { event, .. } = event {
       match event {
-        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+        WindowEvent::CloseRequested { .. } => *control_flow = ControlFlow::Exit,
         WindowEvent::ModifiersChanged(new_state) => {
           // update our local modifier state
           modifiers = new_state;
         }
         // catch only pressed event
         WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
-          if hotkey.matches(&modifiers, &event.physical_key) {
+          if hotkey.matches(modifiers, event.physical_key) {
             println!(
               "KeyEvent:  `Shift` + `1` | logical_key: {:?}",
               &event.logical_key
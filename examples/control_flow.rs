@@ -53,7 +53,7 @@ fn main() {
         }
       }
       Event::WindowEvent { event, .. } => match event {
-        WindowEvent::CloseRequested => {
+        WindowEvent::CloseRequested { .. } => {
           close_requested = true;
         }
         WindowEvent::KeyboardInput {
@@ -42,7 +42,7 @@ fn main() {
         }
       }
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event: WindowEvent::CloseRequested { .. },
         ..
       } => *control_flow = ControlFlow::Exit,
       _ => (),
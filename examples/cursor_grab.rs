@@ -6,7 +6,7 @@ use tao::{
   event::{DeviceEvent, ElementState, Event, KeyEvent, WindowEvent},
   event_loop::{ControlFlow, EventLoop},
   keyboard::{Key, ModifiersState},
-  window::WindowBuilder,
+  window::{CursorGrabMode, WindowBuilder},
 };
 
 #[allow(clippy::single_match)]
@@ -26,7 +26,7 @@ fn main() {
 
     match event {
       Event::WindowEvent { event, .. } => match event {
-        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+        WindowEvent::CloseRequested { .. } => *control_flow = ControlFlow::Exit,
         WindowEvent::KeyboardInput {
           event:
             KeyEvent {
@@ -41,7 +41,16 @@ fn main() {
           match key {
             Key::Escape => *control_flow = ControlFlow::Exit,
             Key::Character(ch) => match ch.to_lowercase().as_str() {
-              "g" => window.set_cursor_grab(!modifiers.shift_key()).unwrap(),
+              "g" | "l" => {
+                let grab_mode = match (ch.to_lowercase().as_str(), modifiers.shift_key()) {
+                  (_, true) => CursorGrabMode::None,
+                  ("g", false) => CursorGrabMode::Confined,
+                  _ => CursorGrabMode::Locked,
+                };
+                if let Err(e) = window.set_cursor_grab(grab_mode) {
+                  eprintln!("Failed to grab the cursor: {}", e);
+                }
+              }
               "h" => window.set_cursor_visible(modifiers.shift_key()),
               _ => (),
             },
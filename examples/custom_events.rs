@@ -42,7 +42,7 @@ fn main() {
     match event {
       Event::UserEvent(event) => println!("user event: {:?}", event),
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event: WindowEvent::CloseRequested { .. },
         ..
       } => *control_flow = ControlFlow::Exit,
       _ => (),
@@ -27,7 +27,7 @@ fn main() {
     Event::WindowEvent {
       event, window_id, ..
     } => match event {
-      WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+      WindowEvent::CloseRequested { .. } => *control_flow = ControlFlow::Exit,
       WindowEvent::MouseInput {
         state: ElementState::Pressed,
         button: MouseButton::Left,
@@ -51,11 +51,11 @@ fn main() {
         event:
           KeyEvent {
             state: ElementState::Released,
-            logical_key,
+            logical_key: Key::Character("x"),
             ..
           },
         ..
-      } if logical_key == Key::Character("x") => {
+      } => {
         switched = !switched;
         name_windows(entered_id, switched, &window_1, &window_2);
         println!("Switched!")
@@ -43,7 +43,7 @@ fn main() {
 
     match event {
       Event::WindowEvent { event, .. } => match event {
-        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+        WindowEvent::CloseRequested { .. } => *control_flow = ControlFlow::Exit,
         WindowEvent::KeyboardInput {
           event:
             KeyEvent {
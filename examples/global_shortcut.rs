@@ -44,7 +44,7 @@ fn main() {
 
     match event {
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event: WindowEvent::CloseRequested { .. },
         window_id,
         ..
       } if window_id == window.id() => *control_flow = ControlFlow::Exit,
@@ -19,7 +19,7 @@ fn main() {
     .build(&event_loop)
     .unwrap();
 
-  let mut close_requested = false;
+  let mut close_response = None;
 
   event_loop.run(move |event, _, control_flow| {
     *control_flow = ControlFlow::Wait;
@@ -27,7 +27,7 @@ fn main() {
     match event {
       Event::WindowEvent { event, .. } => {
         match event {
-          WindowEvent::CloseRequested => {
+          WindowEvent::CloseRequested { response } => {
             // `CloseRequested` is sent when the close button on the window is pressed (or
             // through whatever other mechanisms the window manager provides for closing a
             // window). If you don't handle this event, the close button won't actually do
@@ -35,15 +35,23 @@ fn main() {
 
             // A common thing to do here is prompt the user if they have unsaved work.
             // Creating a proper dialog box for that is far beyond the scope of this
-            // example, so here we'll just respond to the Y and N keys.
+            // example, so here we'll just respond to the Y and N keys. The response is kept
+            // until then, it can also be sent to another thread or an async task.
             println!("Are you ready to bid your window farewell? [Y/N]");
-            close_requested = true;
+            close_response = Some(response);
 
             // In applications where you can safely close the window without further
             // action from the user, this is generally where you'd handle cleanup before
             // closing the window. How to close the window is detailed in the handler for
             // the Y key.
           }
+          WindowEvent::Destroyed => {
+            // For a single-window application like this, you'd normally just break out of
+            // the event loop once the window is closed. If you wanted to keep running the
+            // event loop (i.e. if it's a multi-window application), you need to drop the
+            // window here.
+            *control_flow = ControlFlow::Exit;
+          }
           WindowEvent::KeyboardInput {
             event:
               KeyEvent {
@@ -57,22 +65,19 @@ fn main() {
             // See the `key_binding` example
             match char {
               "y" => {
-                if close_requested {
+                if let Some(response) = close_response.take() {
                   // This is where you'll want to do any cleanup you need.
                   println!("Buh-bye!");
 
-                  // For a single-window application like this, you'd normally just
-                  // break out of the event loop here. If you wanted to keep running the
-                  // event loop (i.e. if it's a multi-window application), you need to
-                  // drop the window. That closes it, and results in `Destroyed` being
-                  // sent.
-                  *control_flow = ControlFlow::Exit;
+                  // Allowing the request closes the window, and results in `Destroyed`
+                  // being sent. Dropping the window closes it as well.
+                  response.allow();
                 }
               }
               "n" => {
-                if close_requested {
+                if let Some(response) = close_response.take() {
                   println!("Your window will continue to stay by your side.");
-                  close_requested = false;
+                  response.deny();
                 }
               }
               _ => (),
@@ -25,7 +25,7 @@ fn main() {
 
     match event {
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event: WindowEvent::CloseRequested { .. },
         ..
       } => *control_flow = ControlFlow::Exit,
       _ => (),
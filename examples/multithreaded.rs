@@ -12,7 +12,7 @@ fn main() {
     event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{Key, ModifiersState},
-    window::{CursorIcon, Fullscreen, WindowBuilder},
+    window::{CursorGrabMode, CursorIcon, Fullscreen, WindowBuilder},
   };
 
   const WINDOW_COUNT: usize = 3;
@@ -40,7 +40,7 @@ fn main() {
             // We need to update our chosen video mode if the window
             // was moved to an another monitor, so that the window
             // appears on this monitor instead when we go fullscreen
-            let previous_video_mode = video_modes.iter().cloned().nth(video_mode_id);
+            let previous_video_mode = video_modes.iter().nth(video_mode_id).cloned();
             video_modes = window.current_monitor().unwrap().video_modes().collect();
             video_mode_id = video_mode_id.min(video_modes.len());
             let video_mode = video_modes.iter().nth(video_mode_id);
@@ -87,7 +87,12 @@ fn main() {
                   )),
                   (false, _) => None,
                 }),
-                "g" => window.set_cursor_grab(state).unwrap(),
+                "g" => window
+                  .set_cursor_grab(match state {
+                    true => CursorGrabMode::Confined,
+                    false => CursorGrabMode::None,
+                  })
+                  .unwrap(),
                 "h" => window.set_cursor_visible(!state),
                 "i" => {
                   println!("Info:");
@@ -160,7 +165,7 @@ fn main() {
       Event::WindowEvent {
         event, window_id, ..
       } => match event {
-        WindowEvent::CloseRequested
+        WindowEvent::CloseRequested { .. }
         | WindowEvent::Destroyed
         | WindowEvent::KeyboardInput {
           event:
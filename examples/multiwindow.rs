@@ -28,7 +28,7 @@ fn main() {
     } = event
     {
       match event {
-        WindowEvent::CloseRequested => {
+        WindowEvent::CloseRequested { .. } => {
           println!("Window {:?} has received the signal to close", window_id);
 
           // This drops the window, causing it to close.
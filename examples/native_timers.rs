@@ -0,0 +1,52 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use simple_logger::SimpleLogger;
+use tao::{
+  event::{Event, StartCause, WindowEvent},
+  event_loop::{ControlFlow, EventLoop, TimerId},
+  window::WindowBuilder,
+};
+
+const FAST_TIMER: TimerId = TimerId(0);
+const SLOW_TIMER: TimerId = TimerId(1);
+
+#[allow(clippy::single_match)]
+fn main() {
+  SimpleLogger::new().init().unwrap();
+  let event_loop = EventLoop::new();
+
+  let _window = WindowBuilder::new()
+    .with_title("A fantastic window!")
+    .build(&event_loop)
+    .unwrap();
+
+  let mut fast_ticks = 0;
+
+  event_loop.run(move |event, event_loop, control_flow| {
+    *control_flow = ControlFlow::Wait;
+
+    match event {
+      Event::NewEvents(StartCause::Init) => {
+        event_loop.set_timer(Duration::from_millis(250), FAST_TIMER);
+        event_loop.set_timer(Duration::from_secs(1), SLOW_TIMER);
+      }
+      Event::Timer(FAST_TIMER) => {
+        fast_ticks += 1;
+        println!("Fast timer: {}", fast_ticks);
+        if fast_ticks == 10 {
+          event_loop.cancel_timer(FAST_TIMER);
+          println!("Fast timer cancelled");
+        }
+      }
+      Event::Timer(SLOW_TIMER) => println!("Slow timer"),
+      Event::WindowEvent {
+        event: WindowEvent::CloseRequested { .. },
+        ..
+      } => *control_flow = ControlFlow::Exit,
+      _ => (),
+    }
+  });
+}
@@ -22,16 +22,14 @@ fn main() {
   let mut windows = HashMap::new();
   let main_window = WindowBuilder::new().build(&event_loop).unwrap();
 
+  let builder = WindowBuilder::new().with_inner_size(LogicalSize::new(200, 200));
+  // `WindowBuilder::with_parent_window` takes precedence over the platform methods.
   #[cfg(target_os = "macos")]
-  let parent_window = main_window.ns_window();
+  let builder = WindowBuilderExtMacOS::with_parent_window(builder, main_window.ns_window());
   #[cfg(target_os = "windows")]
-  let parent_window = main_window.hwnd() as HWND;
+  let builder = WindowBuilderExtWindows::with_parent_window(builder, main_window.hwnd() as HWND);
 
-  let child_window = WindowBuilder::new()
-    .with_parent_window(parent_window)
-    .with_inner_size(LogicalSize::new(200, 200))
-    .build(&event_loop)
-    .unwrap();
+  let child_window = builder.build(&event_loop).unwrap();
 
   windows.insert(child_window.id(), child_window);
   windows.insert(main_window.id(), main_window);
@@ -43,7 +41,7 @@ fn main() {
       Event::NewEvents(StartCause::Init) => println!("TAO application started!"),
       Event::WindowEvent {
         event, window_id, ..
-      } if event == WindowEvent::CloseRequested => {
+      } if matches!(event, WindowEvent::CloseRequested { .. }) => {
         println!("Window {:?} has received the signal to close", window_id);
         // This drop the window, causing it to close.
         windows.remove(&window_id);
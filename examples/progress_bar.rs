@@ -0,0 +1,62 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use simple_logger::SimpleLogger;
+use tao::{
+  event::{ElementState, Event, KeyEvent, WindowEvent},
+  event_loop::{ControlFlow, EventLoop},
+  keyboard::Key,
+  window::{ProgressState, WindowBuilder},
+};
+
+#[allow(clippy::single_match)]
+fn main() {
+  SimpleLogger::new().init().unwrap();
+  let event_loop = EventLoop::new();
+
+  let window = WindowBuilder::new()
+    .with_title("Press 1-5 to change the progress state, +/- to change the progress")
+    .build(&event_loop)
+    .unwrap();
+
+  let mut state = ProgressState::Normal;
+  let mut progress: u64 = 0;
+
+  event_loop.run(move |event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+
+    match event {
+      Event::WindowEvent {
+        event: WindowEvent::CloseRequested { .. },
+        ..
+      } => *control_flow = ControlFlow::Exit,
+      Event::WindowEvent {
+        event:
+          WindowEvent::KeyboardInput {
+            event:
+              KeyEvent {
+                logical_key: Key::Character(key_str),
+                state: ElementState::Released,
+                ..
+              },
+            ..
+          },
+        ..
+      } => {
+        match key_str {
+          "1" => state = ProgressState::None,
+          "2" => state = ProgressState::Normal,
+          "3" => state = ProgressState::Indeterminate,
+          "4" => state = ProgressState::Paused,
+          "5" => state = ProgressState::Error,
+          "+" => progress = (progress + 10).min(100),
+          "-" => progress = progress.saturating_sub(10),
+          _ => return,
+        }
+        println!("Progress: {:?} {}%", state, progress);
+        window.set_progress_bar(state, Some(progress));
+      }
+      _ => (),
+    }
+  });
+}
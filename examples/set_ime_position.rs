@@ -3,9 +3,10 @@
 
 use simple_logger::SimpleLogger;
 use tao::{
-  dpi::PhysicalPosition,
-  event::{ElementState, Event, WindowEvent},
+  dpi::{LogicalSize, PhysicalPosition},
+  event::{ElementState, Event, KeyEvent, WindowEvent},
   event_loop::{ControlFlow, EventLoop},
+  keyboard::KeyCode,
   window::WindowBuilder,
 };
 
@@ -18,7 +19,9 @@ fn main() {
 
   println!("Ime position will system default");
   println!("Click to set ime position to cursor's");
+  println!("Press F2 to toggle the ime");
 
+  let mut ime_allowed = true;
   let mut cursor_position = PhysicalPosition::new(0.0, 0.0);
   event_loop.run(move |event, _, control_flow| {
     *control_flow = ControlFlow::Wait;
@@ -42,10 +45,33 @@ fn main() {
           "Setting ime position to {}, {}",
           cursor_position.x, cursor_position.y
         );
-        window.set_ime_position(cursor_position);
+        window.set_ime_cursor_area(cursor_position, LogicalSize::new(10, 20));
       }
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event:
+          WindowEvent::KeyboardInput {
+            event:
+              KeyEvent {
+                physical_key: KeyCode::F2,
+                state: ElementState::Released,
+                ..
+              },
+            ..
+          },
+        ..
+      } => {
+        ime_allowed = !ime_allowed;
+        println!("Ime allowed: {}", ime_allowed);
+        window.set_ime_allowed(ime_allowed);
+      }
+      Event::WindowEvent {
+        event: WindowEvent::Ime(ime),
+        ..
+      } => {
+        println!("{:?}", ime);
+      }
+      Event::WindowEvent {
+        event: WindowEvent::CloseRequested { .. },
         ..
       } => {
         *control_flow = ControlFlow::Exit;
@@ -118,7 +118,7 @@ fn main() {
       Event::WindowEvent {
         event, window_id, ..
       } => {
-        if event == WindowEvent::CloseRequested {
+        if matches!(event, WindowEvent::CloseRequested { .. }) {
           let mut open_new_window_element = open_new_window_element.clone();
           // Remove window from our hashmap
           windows.remove(&window_id);
@@ -96,7 +96,7 @@ fn main() {
       Event::WindowEvent {
         event, window_id, ..
       } => {
-        if event == WindowEvent::CloseRequested {
+        if matches!(event, WindowEvent::CloseRequested { .. }) {
           println!("Window {:?} has received the signal to close", window_id);
           // Remove window from our hashmap
           windows.remove(&window_id);
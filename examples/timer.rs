@@ -35,7 +35,7 @@ fn main() {
         println!("\nTimer\n");
       }
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event: WindowEvent::CloseRequested { .. },
         ..
       } => *control_flow = ControlFlow::Exit,
       _ => (),
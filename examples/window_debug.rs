@@ -128,7 +128,7 @@ fn main() {
         _ => (),
       },
       Event::WindowEvent {
-        event: WindowEvent::CloseRequested,
+        event: WindowEvent::CloseRequested { .. },
         window_id,
         ..
       } if window_id == window.id() => *control_flow = ControlFlow::Exit,
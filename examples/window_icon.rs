@@ -40,7 +40,7 @@ fn main() {
     if let Event::WindowEvent { event, .. } = event {
       use tao::event::WindowEvent::*;
       match event {
-        CloseRequested => *control_flow = ControlFlow::Exit,
+        CloseRequested { .. } => *control_flow = ControlFlow::Exit,
         DroppedFile(path) => {
           window.set_window_icon(Some(load_icon(&path)));
         }
@@ -0,0 +1,110 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! The count of the consecutive clicks of the mouse buttons, for the platforms which only report
+//! the presses: a press follows the previous one when it's within the double-click time and
+//! distance of the system.
+
+use instant::Instant;
+use std::time::Duration;
+
+use crate::event::MouseButton;
+
+/// The double-click time of GTK, used where the system doesn't have one.
+#[allow(dead_code)]
+pub(crate) const DEFAULT_INTERVAL: Duration = Duration::from_millis(400);
+/// The double-click distance of GTK in logical pixels, used where the system doesn't have one.
+#[allow(dead_code)]
+pub(crate) const DEFAULT_DISTANCE: f64 = 5.0;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ClickCounter {
+  /// The button, the time and the position of the last press, with its count.
+  last_press: Option<(MouseButton, Instant, (f64, f64), u32)>,
+}
+
+impl ClickCounter {
+  /// Counts a press, which follows the last one when it's of the same button, at most `interval`
+  /// after it, and at most `distance` away from it on each axis.
+  pub(crate) fn press(
+    &mut self,
+    button: MouseButton,
+    time: Instant,
+    position: (f64, f64),
+    interval: Duration,
+    distance: (f64, f64),
+  ) -> u32 {
+    let count = match self.last_press {
+      Some((last_button, last_time, last_position, count))
+        if last_button == button
+          && time >= last_time
+          && time - last_time <= interval
+          && (position.0 - last_position.0).abs() <= distance.0
+          && (position.1 - last_position.1).abs() <= distance.1 =>
+      {
+        count + 1
+      }
+      _ => 1,
+    };
+    self.last_press = Some((button, time, position, count));
+    count
+  }
+
+  /// The count of a release, which is the one of the last press of its button.
+  pub(crate) fn release(&self, button: MouseButton) -> u32 {
+    match self.last_press {
+      Some((last_button, .., count)) if last_button == button => count,
+      _ => 1,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const INTERVAL: Duration = Duration::from_millis(500);
+  const DISTANCE: (f64, f64) = (4.0, 2.0);
+
+  fn press(counter: &mut ClickCounter, time: Instant, position: (f64, f64)) -> u32 {
+    counter.press(MouseButton::Left, time, position, INTERVAL, DISTANCE)
+  }
+
+  #[test]
+  fn counts_the_presses_within_the_interval() {
+    let mut counter = ClickCounter::default();
+    let start = Instant::now();
+    assert_eq!(press(&mut counter, start, (10.0, 10.0)), 1);
+    assert_eq!(press(&mut counter, start + INTERVAL, (10.0, 10.0)), 2);
+    assert_eq!(counter.release(MouseButton::Left), 2);
+    assert_eq!(press(&mut counter, start + INTERVAL * 2, (10.0, 10.0)), 3);
+
+    // The interval is measured from the previous press.
+    let late = start + INTERVAL * 3 + Duration::from_millis(1);
+    assert_eq!(press(&mut counter, late, (10.0, 10.0)), 1);
+  }
+
+  #[test]
+  fn distance_is_checked_on_each_axis() {
+    let mut counter = ClickCounter::default();
+    let start = Instant::now();
+    press(&mut counter, start, (10.0, 10.0));
+    assert_eq!(press(&mut counter, start, (14.0, 12.0)), 2);
+    assert_eq!(press(&mut counter, start, (10.0, 10.0)), 3);
+    assert_eq!(press(&mut counter, start, (10.0, 12.5)), 1);
+    assert_eq!(press(&mut counter, start, (14.5, 12.5)), 1);
+  }
+
+  #[test]
+  fn another_button_resets_the_count() {
+    let mut counter = ClickCounter::default();
+    let start = Instant::now();
+    press(&mut counter, start, (0.0, 0.0));
+    press(&mut counter, start, (0.0, 0.0));
+    let count = counter.press(MouseButton::Right, start, (0.0, 0.0), INTERVAL, DISTANCE);
+    assert_eq!(count, 1);
+    assert_eq!(counter.release(MouseButton::Left), 1);
+    assert_eq!(counter.release(MouseButton::Right), 1);
+    assert_eq!(press(&mut counter, start, (0.0, 0.0)), 1);
+  }
+}
@@ -6,6 +6,7 @@
 //! ## Platform-specific
 //!
 //! - **Android / iOS:** Unsupported
+//! - **Linux:** Unsupported with the Wayland backend
 //!
 //! ```rust,ignore
 //! let mut cliboard = Clipboard::new();
@@ -26,6 +27,7 @@ impl Clipboard {
   /// ## Platform-specific
   ///
   /// - **Android / iOS:** Unsupported
+  /// - **Linux:** Unsupported with the Wayland backend
   pub fn new() -> Self {
     Self::default()
   }
@@ -35,6 +37,7 @@ impl Clipboard {
   /// ## Platform-specific
   ///
   /// - **Android / iOS:** Unsupported
+  /// - **Linux:** Unsupported with the Wayland backend
   pub fn write_text(&mut self, s: impl AsRef<str>) {
     self.0.write_text(s);
   }
@@ -44,6 +47,7 @@ impl Clipboard {
   /// ## Platform-specific
   ///
   /// - **Android / iOS:** Unsupported
+  /// - **Linux:** Unsupported with the Wayland backend
   pub fn read_text(&self) -> Option<String> {
     self.0.read_text()
   }
@@ -63,7 +67,7 @@ pub(crate) struct ClipboardFormat {
 impl ClipboardFormat {
   #[cfg(any(target_os = "macos", target_os = "ios"))]
   pub const TEXT: &'static str = "public.utf8-plain-text";
-  #[cfg(any(target_os = "windows", target_os = "android"))]
+  #[cfg(any(target_os = "windows", target_os = "android", target_arch = "wasm32"))]
   pub const TEXT: &'static str = "text/plain";
   #[cfg(any(
     target_os = "linux",
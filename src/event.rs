@@ -26,6 +26,10 @@
 //!     }
 //!     event_handler(RedrawEventsCleared, ..., &mut control_flow);
 //!
+//!     if control_flow waits {
+//!         event_handler(Idle { .. }, ..., &mut control_flow);
+//!     }
+//!
 //!     start_cause = wait_if_necessary(control_flow);
 //! }
 //!
@@ -37,12 +41,13 @@
 //!
 //! [event_loop_run]: crate::event_loop::EventLoop::run
 use instant::Instant;
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf};
 
 use crate::{
   accelerator::AcceleratorId,
   dpi::{PhysicalPosition, PhysicalSize},
-  keyboard::{self, ModifiersState},
+  event_loop::{ControlFlow, TimerId},
+  keyboard::{self, KeyboardLayout, ModifiersState},
   menu::{MenuId, MenuType},
   platform_impl,
   window::{Theme, WindowId},
@@ -67,6 +72,16 @@ pub enum Event<'a, T: 'static> {
   WindowEvent {
     window_id: WindowId,
     event: WindowEvent<'a>,
+    /// When the event happened, e.g. when the key of a `KeyboardInput` was pressed rather than
+    /// when the event was dispatched.
+    ///
+    /// ## Platform-specific
+    ///
+    /// The time of the native event is used when there is one, i.e. `GetMessageTime` on Windows,
+    /// the time of the GDK event on Linux, the timestamp of the `NSEvent` on macOS, the one of
+    /// the `UITouch` on iOS, and the one of the input event on Android. Otherwise, it's the time
+    /// the OS notified `tao` of the event.
+    timestamp: Instant,
   },
 
   /// Emitted when the OS sends an event to a device.
@@ -74,6 +89,8 @@ pub enum Event<'a, T: 'static> {
   DeviceEvent {
     device_id: DeviceId,
     event: DeviceEvent,
+    /// When the event happened. See the `timestamp` of `Event::WindowEvent`.
+    timestamp: Instant,
   },
 
   /// Emitted when an event is sent from [`EventLoopProxy::send_event`](crate::event_loop::EventLoopProxy::send_event)
@@ -107,11 +124,27 @@ pub enum Event<'a, T: 'static> {
   /// - **iOS / Android:** Unsupported.
   GlobalShortcutEvent(AcceleratorId),
 
-  /// Emitted when the application has been suspended.
-  Suspended,
+  /// Emitted when a timer started with
+  /// [`EventLoopWindowTarget::set_timer`](crate::event_loop::EventLoopWindowTarget::set_timer)
+  /// fires.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  Timer(TimerId),
 
-  /// Emitted when the application has been resumed.
-  Resumed,
+  /// Emitted when the application has been suspended, e.g. so renderers and timers can be
+  /// throttled until it is resumed.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Only emitted when the system goes to sleep.
+  /// - **Linux:** Only emitted when all the windows are hidden.
+  /// - **iOS / Android:** Only emitted when the application goes to the background.
+  Suspended(SuspendReason),
+
+  /// Emitted when the application has been resumed, with the reason it was suspended.
+  Resumed(SuspendReason),
 
   /// Emitted when all of the event loop's input events have been processed and redraw processing
   /// is about to begin.
@@ -138,6 +171,11 @@ pub enum Event<'a, T: 'static> {
   ///
   /// Mainly of interest to applications with mostly-static graphics that avoid redrawing unless
   /// something changes, like most non-game GUIs.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** While the user resizes a window, it's emitted right after
+  ///   `WindowEvent::Resized`, so the window is redrawn at its new size before it's shown.
   RedrawRequested(WindowId),
 
   /// Emitted after all `RedrawRequested` events have been processed and control flow is about to
@@ -148,6 +186,45 @@ pub enum Event<'a, T: 'static> {
   /// tasks have been completed.
   RedrawEventsCleared,
 
+  /// Emitted after `RedrawEventsCleared` when the event loop is about to wait for new events,
+  /// i.e. when the control flow is `Wait` or `WaitUntil`.
+  ///
+  /// This event is useful to do lazy work only when the application is otherwise idle, like an
+  /// autosave. `requested_resume` is the time the event loop resumes at if no event arrives
+  /// before, with `ControlFlow::WaitUntil`. The control flow can still be changed, e.g. to
+  /// `ControlFlow::Poll` to keep working in small chunks.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Emitted after `MainEventsCleared`.
+  /// - **iOS:** Emitted before the `RedrawRequested` events of the windows drawn by the system.
+  Idle { requested_resume: Option<Instant> },
+
+  /// Emitted when the OS asks the whole application to quit, e.g. when the user ends the session
+  /// or quits it from the dock on macOS.
+  ///
+  /// The event loop exits like with `ControlFlow::Exit` unless `prevent` is set to `true`, so the
+  /// application can save its state first, or keep running, e.g. until the user answers an
+  /// "unsaved changes" dialog of one of its windows.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Emitted by `WM_QUERYENDSESSION`, when the user logs off or shuts down the
+  ///   system. Preventing it makes Windows tell the user that the application blocks the shutdown.
+  /// - **macOS:** Emitted by `applicationShouldTerminate:`, e.g. on `Cmd+Q` or from the dock.
+  /// - **Linux:** Emitted when the session manager asks to end the session. The end of the
+  ///   session is inhibited until the event is handled.
+  /// - **iOS / Android:** Unsupported.
+  ExitRequested { prevent: &'a mut bool },
+
+  /// Emitted when the user switches to another keyboard layout.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Only supported on X11 and with the `wayland` feature.
+  /// - **iOS / Android:** Unsupported.
+  KeyboardLayoutChanged(KeyboardLayout),
+
   /// Emitted when the event loop is being shut down.
   ///
   /// This is irreversable - if this event is emitted, it is guaranteed to be the last event that
@@ -159,22 +236,39 @@ impl<T: Clone> Clone for Event<'static, T> {
   fn clone(&self) -> Self {
     use self::Event::*;
     match self {
-      WindowEvent { window_id, event } => WindowEvent {
+      WindowEvent {
+        window_id,
+        event,
+        timestamp,
+      } => WindowEvent {
         window_id: *window_id,
         event: event.clone(),
+        timestamp: *timestamp,
       },
       UserEvent(event) => UserEvent(event.clone()),
-      DeviceEvent { device_id, event } => DeviceEvent {
+      DeviceEvent {
+        device_id,
+        event,
+        timestamp,
+      } => DeviceEvent {
         device_id: *device_id,
         event: event.clone(),
+        timestamp: *timestamp,
       },
       NewEvents(cause) => NewEvents(*cause),
       MainEventsCleared => MainEventsCleared,
       RedrawRequested(wid) => RedrawRequested(*wid),
       RedrawEventsCleared => RedrawEventsCleared,
+      Idle { requested_resume } => Idle {
+        requested_resume: *requested_resume,
+      },
+      ExitRequested { .. } => {
+        unreachable!("Static event can't be about an exit request")
+      }
       LoopDestroyed => LoopDestroyed,
-      Suspended => Suspended,
-      Resumed => Resumed,
+      Suspended(reason) => Suspended(*reason),
+      Resumed(reason) => Resumed(*reason),
+      KeyboardLayoutChanged(layout) => KeyboardLayoutChanged(layout.clone()),
       MenuEvent {
         window_id,
         menu_id,
@@ -194,6 +288,7 @@ impl<T: Clone> Clone for Event<'static, T> {
         position: *position,
       },
       GlobalShortcutEvent(accelerator_id) => GlobalShortcutEvent(*accelerator_id),
+      Timer(timer_id) => Timer(*timer_id),
     }
   }
 }
@@ -203,15 +298,34 @@ impl<'a, T> Event<'a, T> {
     use self::Event::*;
     match self {
       UserEvent(_) => Err(self),
-      WindowEvent { window_id, event } => Ok(WindowEvent { window_id, event }),
-      DeviceEvent { device_id, event } => Ok(DeviceEvent { device_id, event }),
+      WindowEvent {
+        window_id,
+        event,
+        timestamp,
+      } => Ok(WindowEvent {
+        window_id,
+        event,
+        timestamp,
+      }),
+      DeviceEvent {
+        device_id,
+        event,
+        timestamp,
+      } => Ok(DeviceEvent {
+        device_id,
+        event,
+        timestamp,
+      }),
       NewEvents(cause) => Ok(NewEvents(cause)),
       MainEventsCleared => Ok(MainEventsCleared),
       RedrawRequested(wid) => Ok(RedrawRequested(wid)),
       RedrawEventsCleared => Ok(RedrawEventsCleared),
+      Idle { requested_resume } => Ok(Idle { requested_resume }),
+      ExitRequested { prevent } => Ok(ExitRequested { prevent }),
       LoopDestroyed => Ok(LoopDestroyed),
-      Suspended => Ok(Suspended),
-      Resumed => Ok(Resumed),
+      Suspended(reason) => Ok(Suspended(reason)),
+      Resumed(reason) => Ok(Resumed(reason)),
+      KeyboardLayoutChanged(layout) => Ok(KeyboardLayoutChanged(layout)),
       MenuEvent {
         window_id,
         menu_id,
@@ -231,6 +345,7 @@ impl<'a, T> Event<'a, T> {
         position,
       }),
       GlobalShortcutEvent(accelerator_id) => Ok(GlobalShortcutEvent(accelerator_id)),
+      Timer(timer_id) => Ok(Timer(timer_id)),
     }
   }
 
@@ -239,18 +354,35 @@ impl<'a, T> Event<'a, T> {
   pub fn to_static(self) -> Option<Event<'static, T>> {
     use self::Event::*;
     match self {
-      WindowEvent { window_id, event } => event
-        .to_static()
-        .map(|event| WindowEvent { window_id, event }),
+      WindowEvent {
+        window_id,
+        event,
+        timestamp,
+      } => event.to_static().map(|event| WindowEvent {
+        window_id,
+        event,
+        timestamp,
+      }),
       UserEvent(event) => Some(UserEvent(event)),
-      DeviceEvent { device_id, event } => Some(DeviceEvent { device_id, event }),
+      DeviceEvent {
+        device_id,
+        event,
+        timestamp,
+      } => Some(DeviceEvent {
+        device_id,
+        event,
+        timestamp,
+      }),
       NewEvents(cause) => Some(NewEvents(cause)),
       MainEventsCleared => Some(MainEventsCleared),
       RedrawRequested(wid) => Some(RedrawRequested(wid)),
       RedrawEventsCleared => Some(RedrawEventsCleared),
+      Idle { requested_resume } => Some(Idle { requested_resume }),
+      ExitRequested { .. } => None,
       LoopDestroyed => Some(LoopDestroyed),
-      Suspended => Some(Suspended),
-      Resumed => Some(Resumed),
+      Suspended(reason) => Some(Suspended(reason)),
+      Resumed(reason) => Some(Resumed(reason)),
+      KeyboardLayoutChanged(layout) => Some(KeyboardLayoutChanged(layout)),
       MenuEvent {
         window_id,
         menu_id,
@@ -270,10 +402,41 @@ impl<'a, T> Event<'a, T> {
         position,
       }),
       GlobalShortcutEvent(accelerator_id) => Some(GlobalShortcutEvent(accelerator_id)),
+      Timer(timer_id) => Some(Timer(timer_id)),
+    }
+  }
+
+  /// Returns the `Idle` event to emit before waiting with `control_flow`, if it waits.
+  pub(crate) fn idle(control_flow: ControlFlow) -> Option<Event<'static, T>> {
+    match control_flow {
+      ControlFlow::Wait => Some(Event::Idle {
+        requested_resume: None,
+      }),
+      ControlFlow::WaitUntil(requested_resume) => Some(Event::Idle {
+        requested_resume: Some(requested_resume),
+      }),
+      ControlFlow::Poll | ControlFlow::ExitWithCode(_) => None,
     }
   }
 }
 
+/// Describes the reason the application was suspended, see [`Event::Suspended`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SuspendReason {
+  /// The application went to the background.
+  ///
+  /// On Android, the native window is destroyed until the application is resumed.
+  Background,
+  /// None of the windows of the application are visible anymore, e.g. they are all minimized,
+  /// hidden or covered by other windows.
+  ///
+  /// On macOS, the application becomes eligible for App Nap.
+  Hidden,
+  /// The system is going to sleep.
+  Sleep,
+}
+
 /// Describes the reason the event loop is resuming.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
@@ -314,7 +477,12 @@ pub enum WindowEvent<'a> {
   Moved(PhysicalPosition<i32>),
 
   /// The window has been requested to close.
-  CloseRequested,
+  ///
+  /// The window stays open until `response` is allowed or the [`Window`] is dropped, see
+  /// [`CloseResponse`].
+  ///
+  /// [`Window`]: crate::window::Window
+  CloseRequested { response: CloseResponse },
 
   /// The window has been destroyed.
   Destroyed,
@@ -337,14 +505,52 @@ pub enum WindowEvent<'a> {
   /// hovered.
   HoveredFileCancelled,
 
-  /// The window received a unicode character.
-  ReceivedImeText(String),
+  /// An input method editor (IME) composed text in the window, see [`Ime`].
+  ///
+  /// The keys typed into an IME are still sent as `KeyboardInput` events, without `text`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The keys consumed by an IME aren't sent as `KeyboardInput` events.
+  /// - **Web / Android / iOS:** Unsupported.
+  Ime(Ime),
+
+  /// Text was entered into the window while its text input is started, see
+  /// [`Window::start_text_input`].
+  ///
+  /// This contains the text of the pressed keys, without the control characters and the keys
+  /// pressed with <kbd>Ctrl</kbd> or the logo key, and the text committed by an IME. Unlike the
+  /// `text` of `KeyboardInput`, this can be inserted as is, the shortcuts being left to the
+  /// `KeyboardInput` events.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Android / iOS:** Unsupported.
+  ///
+  /// [`Window::start_text_input`]: crate::window::Window::start_text_input
+  ReceivedText(String),
 
   /// The window gained or lost focus.
   ///
   /// The parameter is true if the window has gained focus, and false if it has lost focus.
   Focused(bool),
 
+  /// The system asked the window to move the keyboard focus, e.g. with Tab or Shift+Tab, so the
+  /// focus can be moved among the widgets of the window or passed on to the next window.
+  ///
+  /// The event is sent along with the `KeyboardInput` of the key, if any.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Only sent when the focus is moved by `IsDialogMessage`, e.g. for a child
+  ///   window in a dialog.
+  /// - **macOS:** Only sent for Tab and Shift+Tab, while the IME is allowed.
+  /// - **Web:** Only sent for Tab and Shift+Tab.
+  /// - **Linux:** The arrows also move the focus, when the widgets of the window don't handle them.
+  ///   Unsupported with the `wayland` feature.
+  /// - **iOS / Android:** Unsupported.
+  FocusTraversal(FocusDirection),
+
   /// An event from the keyboard has been received.
   ///
   /// ## Platform-specific
@@ -390,10 +596,23 @@ pub enum WindowEvent<'a> {
   CursorLeft { device_id: DeviceId },
 
   /// A mouse wheel movement or touchpad scroll occurred.
+  ///
+  /// The `phase` of a touchpad scroll is `Started` when the fingers start scrolling and `Ended`
+  /// when they are lifted. The wheels only scroll with `Moved`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** The scroll continues with its `momentum` after the fingers are lifted, from
+  ///   `Started` to `Ended` again.
+  /// - **Windows / Web:** The phase is always `Moved`.
+  /// - **Linux (GTK):** The touchpad scrolls don't start with `Started`.
   MouseWheel {
     device_id: DeviceId,
     delta: MouseScrollDelta,
     phase: TouchPhase,
+    /// Whether the scroll is the inertia of a touchpad scroll which ended, rather than scrolling
+    /// by the user, e.g. to rubber-band at the edges of the content only when the user scrolls.
+    momentum: bool,
     #[deprecated = "Deprecated in favor of WindowEvent::ModifiersChanged"]
     modifiers: ModifiersState,
   },
@@ -403,21 +622,76 @@ pub enum WindowEvent<'a> {
     device_id: DeviceId,
     state: ElementState,
     button: MouseButton,
+    /// The number of consecutive clicks of the button, 2 for a double-click, counted with the
+    /// double-click time and distance of the system. A release has the count of its press.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Web / Linux (Wayland backend):** The 400 ms and 5 pixels of GTK are used.
+    click_count: u32,
     #[deprecated = "Deprecated in favor of WindowEvent::ModifiersChanged"]
     modifiers: ModifiersState,
   },
 
   /// Touchpad pressure event.
   ///
-  /// At the moment, only supported on Apple forcetouch-capable macbooks.
   /// The parameters are: pressure level (value between 0 and 1 representing how hard the touchpad
-  /// is being pressed) and stage (integer representing the click level).
+  /// is being pressed) and stage (integer representing the click level: 0 when the touchpad isn't
+  /// clicked, 1 for a normal click and 2 for a force click). An event with a pressure and a stage
+  /// of 0 is sent when the touchpad is released.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only supported on Force Touch trackpads, while the left button is pressed.
+  /// - **Windows / Linux / iOS / Android / Web:** Unsupported.
   TouchpadPressure {
     device_id: DeviceId,
     pressure: f32,
     stage: i64,
   },
 
+  /// Two-finger pinch gesture, usually used to zoom.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / Linux:** Only sent for touchpads.
+  /// - **Windows / iOS / Android / Web:** Unsupported.
+  PinchGesture {
+    device_id: DeviceId,
+    /// The change of the magnification since the last event, positive when zooming in and
+    /// negative when zooming out.
+    delta: f64,
+    phase: TouchPhase,
+  },
+
+  /// Two-finger rotation gesture.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / Linux:** Only sent for touchpads.
+  /// - **Windows / iOS / Android / Web:** Unsupported.
+  RotationGesture {
+    device_id: DeviceId,
+    /// The rotation since the last event in degrees, positive when counterclockwise.
+    delta: f32,
+    phase: TouchPhase,
+  },
+
+  /// Multi-finger swipe gesture.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only sent once a swipe is recognized, with the phase [`TouchPhase::Ended`] and a
+  ///   delta of `-1.0`, `0.0` or `1.0` on each axis giving its direction.
+  /// - **Linux:** Only sent for touchpad swipes with three fingers or more.
+  /// - **Windows / iOS / Android / Web:** Unsupported.
+  SwipeGesture {
+    device_id: DeviceId,
+    /// The motion of the fingers since the last event.
+    delta: PhysicalPosition<f64>,
+    phase: TouchPhase,
+  },
+
   /// Motion on some analog axis. May report data redundant to other, more specific events.
   AxisMotion {
     device_id: DeviceId,
@@ -426,8 +700,53 @@ pub enum WindowEvent<'a> {
   },
 
   /// Touch event has been received
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Unsupported, the trackpads send gestures instead.
   Touch(Touch),
 
+  /// The touches of a frame, sent after their `Touch` events.
+  ///
+  /// The contacts which changed at the same time, e.g. the fingers of a pinch moving together, are
+  /// grouped in a frame, in the order of their `Touch` events. A contact keeps its id in the
+  /// frames until it ends, so the frames can be matched against each other to recognize gestures.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Every touch is a frame of its own with GTK, which doesn't group them.
+  /// - **Web:** Every touch is a frame of its own, since the pointer events aren't grouped.
+  /// - **macOS:** Unsupported.
+  TouchFrame(Vec<Touch>),
+
+  /// Pen event has been received.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Sent instead of `Touch` for the pens.
+  /// - **macOS / Linux / Web:** The pens also move the cursor and send `MouseInput`.
+  /// - **Linux:** Unsupported with the `wayland` feature.
+  /// - **iOS / Android:** Unsupported.
+  PenInput(PenInput),
+
+  /// A pen came within the range of the surface (`in_range` is `true`), or went out of its range.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows / Web:** Sent when the pen in range enters the window or leaves it.
+  /// - **Linux:** Unsupported with the `wayland` feature.
+  /// - **iOS / Android:** Unsupported.
+  PenProximity { device_id: DeviceId, in_range: bool },
+
+  /// A pen moved above the surface, within its range but without touching it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / Linux / Web:** The pens also move the cursor and send `CursorMoved`.
+  /// - **Linux:** Unsupported with the `wayland` feature.
+  /// - **iOS / Android:** Unsupported.
+  PenHover(PenHover),
+
   /// The window's scale factor has changed.
   ///
   /// The following user actions can cause DPI changes:
@@ -453,21 +772,88 @@ pub enum WindowEvent<'a> {
   ///
   /// At the moment this is only supported on Windows.
   ThemeChanged(Theme),
+
+  /// The window has been occluded (completely hidden from view), or is visible again.
+  ///
+  /// Applications might wish to pause rendering while the window is occluded.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Only sent when the window is hidden, minimized or cloaked, e.g. when it is
+  ///   on another virtual desktop, not when other windows cover it.
+  /// - **Linux:** Only sent when the window is hidden or minimized.
+  /// - **iOS / Android:** Unsupported.
+  Occluded(bool),
+
+  /// The touch keyboard of the system covers this part of the inner area of the window, or `None`
+  /// once it no longer covers the window.
+  ///
+  /// Applications may scroll their focused text field out of the way of the keyboard.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Requires Windows 8 or later.
+  /// - **macOS / Linux / iOS / Android / Web:** Unsupported.
+  TouchKeyboardOcclusion(Option<Rectangle>),
+
+  /// The user minimized the window and it was hidden instead, because it was created with
+  /// [`WindowBuilder::with_minimize_to_tray`] or [`Window::set_minimize_to_tray`] was called.
+  ///
+  /// [`WindowBuilder::with_minimize_to_tray`]: crate::window::WindowBuilder::with_minimize_to_tray
+  /// [`Window::set_minimize_to_tray`]: crate::window::Window::set_minimize_to_tray
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  MinimizedToTray,
+
+  /// The window is entering or exiting fullscreen.
+  ///
+  /// The window may be resized several times during a transition, e.g. while macOS animates it
+  /// or while Windows changes the video mode, so applications might wish to defer expensive
+  /// layouts until the transition is over.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** [`FullscreenTransition::Entering`] and [`FullscreenTransition::Exiting`] are
+  ///   only sent for [`Window::set_fullscreen`], not when the window manager toggles fullscreen.
+  /// - **iOS / Android:** Unsupported.
+  ///
+  /// [`Window::set_fullscreen`]: crate::window::Window::set_fullscreen
+  FullscreenTransition(FullscreenTransition),
+
+  /// The user clicked the "+" button of the tab bar of the window, to open a new tab in its tab
+  /// group.
+  ///
+  /// The new window should be created with the same tabbing identifier, and it's added to the
+  /// tab group of this window.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only sent for windows created with
+  ///   `WindowBuilderExtMacOS::with_tabbing_identifier` or moved to a tab group with
+  ///   `WindowExtMacOS::set_tabbing_identifier`.
+  /// - **Windows / Linux / iOS / Android:** Unsupported.
+  NewTabRequested,
 }
 
 impl Clone for WindowEvent<'static> {
   fn clone(&self) -> Self {
     use self::WindowEvent::*;
-    return match self {
+    match self {
       Resized(size) => Resized(*size),
       Moved(pos) => Moved(*pos),
-      CloseRequested => CloseRequested,
+      CloseRequested { response } => CloseRequested {
+        response: response.clone(),
+      },
       Destroyed => Destroyed,
       DroppedFile(file) => DroppedFile(file.clone()),
       HoveredFile(file) => HoveredFile(file.clone()),
       HoveredFileCancelled => HoveredFileCancelled,
-      ReceivedImeText(c) => ReceivedImeText(c.clone()),
+      Ime(ime) => Ime(ime.clone()),
+      ReceivedText(text) => ReceivedText(text.clone()),
       Focused(f) => Focused(*f),
+      FocusTraversal(direction) => FocusTraversal(*direction),
       KeyboardInput {
         device_id,
         event,
@@ -500,11 +886,13 @@ impl Clone for WindowEvent<'static> {
         device_id,
         delta,
         phase,
+        momentum,
         modifiers,
       } => MouseWheel {
         device_id: *device_id,
         delta: *delta,
         phase: *phase,
+        momentum: *momentum,
         modifiers: *modifiers,
       },
       #[allow(deprecated)]
@@ -512,11 +900,13 @@ impl Clone for WindowEvent<'static> {
         device_id,
         state,
         button,
+        click_count,
         modifiers,
       } => MouseInput {
         device_id: *device_id,
         state: *state,
         button: *button,
+        click_count: *click_count,
         modifiers: *modifiers,
       },
       TouchpadPressure {
@@ -528,6 +918,33 @@ impl Clone for WindowEvent<'static> {
         pressure: *pressure,
         stage: *stage,
       },
+      PinchGesture {
+        device_id,
+        delta,
+        phase,
+      } => PinchGesture {
+        device_id: *device_id,
+        delta: *delta,
+        phase: *phase,
+      },
+      RotationGesture {
+        device_id,
+        delta,
+        phase,
+      } => RotationGesture {
+        device_id: *device_id,
+        delta: *delta,
+        phase: *phase,
+      },
+      SwipeGesture {
+        device_id,
+        delta,
+        phase,
+      } => SwipeGesture {
+        device_id: *device_id,
+        delta: *delta,
+        phase: *phase,
+      },
       AxisMotion {
         device_id,
         axis,
@@ -538,11 +955,26 @@ impl Clone for WindowEvent<'static> {
         value: *value,
       },
       Touch(touch) => Touch(*touch),
+      TouchFrame(touches) => TouchFrame(touches.clone()),
+      PenInput(pen) => PenInput(*pen),
+      PenProximity {
+        device_id,
+        in_range,
+      } => PenProximity {
+        device_id: *device_id,
+        in_range: *in_range,
+      },
+      PenHover(pen) => PenHover(*pen),
       ThemeChanged(theme) => ThemeChanged(*theme),
+      Occluded(occluded) => Occluded(*occluded),
+      TouchKeyboardOcclusion(area) => TouchKeyboardOcclusion(*area),
+      MinimizedToTray => MinimizedToTray,
+      FullscreenTransition(transition) => FullscreenTransition(*transition),
+      NewTabRequested => NewTabRequested,
       ScaleFactorChanged { .. } => {
         unreachable!("Static event can't be about scale factor changing")
       }
-    };
+    }
   }
 }
 
@@ -552,13 +984,15 @@ impl<'a> WindowEvent<'a> {
     match self {
       Resized(size) => Some(Resized(size)),
       Moved(position) => Some(Moved(position)),
-      CloseRequested => Some(CloseRequested),
+      CloseRequested { response } => Some(CloseRequested { response }),
       Destroyed => Some(Destroyed),
       DroppedFile(file) => Some(DroppedFile(file)),
       HoveredFile(file) => Some(HoveredFile(file)),
       HoveredFileCancelled => Some(HoveredFileCancelled),
-      ReceivedImeText(c) => Some(ReceivedImeText(c)),
+      Ime(ime) => Some(Ime(ime)),
+      ReceivedText(text) => Some(ReceivedText(text)),
       Focused(focused) => Some(Focused(focused)),
+      FocusTraversal(direction) => Some(FocusTraversal(direction)),
       KeyboardInput {
         device_id,
         event,
@@ -586,11 +1020,13 @@ impl<'a> WindowEvent<'a> {
         device_id,
         delta,
         phase,
+        momentum,
         modifiers,
       } => Some(MouseWheel {
         device_id,
         delta,
         phase,
+        momentum,
         modifiers,
       }),
       #[allow(deprecated)]
@@ -598,11 +1034,13 @@ impl<'a> WindowEvent<'a> {
         device_id,
         state,
         button,
+        click_count,
         modifiers,
       } => Some(MouseInput {
         device_id,
         state,
         button,
+        click_count,
         modifiers,
       }),
       TouchpadPressure {
@@ -614,6 +1052,33 @@ impl<'a> WindowEvent<'a> {
         pressure,
         stage,
       }),
+      PinchGesture {
+        device_id,
+        delta,
+        phase,
+      } => Some(PinchGesture {
+        device_id,
+        delta,
+        phase,
+      }),
+      RotationGesture {
+        device_id,
+        delta,
+        phase,
+      } => Some(RotationGesture {
+        device_id,
+        delta,
+        phase,
+      }),
+      SwipeGesture {
+        device_id,
+        delta,
+        phase,
+      } => Some(SwipeGesture {
+        device_id,
+        delta,
+        phase,
+      }),
       AxisMotion {
         device_id,
         axis,
@@ -624,12 +1089,79 @@ impl<'a> WindowEvent<'a> {
         value,
       }),
       Touch(touch) => Some(Touch(touch)),
+      TouchFrame(touches) => Some(TouchFrame(touches)),
+      PenInput(pen) => Some(PenInput(pen)),
+      PenProximity {
+        device_id,
+        in_range,
+      } => Some(PenProximity {
+        device_id,
+        in_range,
+      }),
+      PenHover(pen) => Some(PenHover(pen)),
       ThemeChanged(theme) => Some(ThemeChanged(theme)),
+      Occluded(occluded) => Some(Occluded(occluded)),
+      TouchKeyboardOcclusion(area) => Some(TouchKeyboardOcclusion(area)),
+      MinimizedToTray => Some(MinimizedToTray),
+      FullscreenTransition(transition) => Some(FullscreenTransition(transition)),
+      NewTabRequested => Some(NewTabRequested),
       ScaleFactorChanged { .. } => None,
     }
   }
 }
 
+/// The answer to a [`WindowEvent::CloseRequested`].
+///
+/// The window isn't closed when the user asks for it, so the application can confirm it first,
+/// e.g. with an "unsaved changes" dialog that's answered asynchronously. The response can be sent
+/// to any thread and kept until the user answers. Dropping it denies the request.
+///
+/// Allowing the request closes the native window and sends [`WindowEvent::Destroyed`], after
+/// which the [`Window`] should be dropped. Allowing it after the window was destroyed does nothing.
+///
+/// Two responses are equal if they're for the same window id, even if they were created for
+/// different close requests.
+///
+/// [`Window`]: crate::window::Window
+#[derive(Clone)]
+pub struct CloseResponse {
+  window_id: WindowId,
+  handle: platform_impl::CloseHandle,
+}
+
+impl CloseResponse {
+  pub(crate) fn new(window_id: WindowId, handle: platform_impl::CloseHandle) -> Self {
+    CloseResponse { window_id, handle }
+  }
+
+  /// Returns the id of the window that was requested to close.
+  pub fn window_id(&self) -> WindowId {
+    self.window_id
+  }
+
+  /// Closes the window.
+  pub fn allow(self) {
+    self.handle.close();
+  }
+
+  /// Keeps the window open, which is the same as dropping the response.
+  pub fn deny(self) {}
+}
+
+impl fmt::Debug for CloseResponse {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CloseResponse")
+      .field("window_id", &self.window_id)
+      .finish()
+  }
+}
+
+impl PartialEq for CloseResponse {
+  fn eq(&self, other: &Self) -> bool {
+    self.window_id == other.window_id
+  }
+}
+
 /// Identifier of an input device.
 ///
 /// Whenever you receive an event arising from a particular input device, this event contains a `DeviceId` which
@@ -650,6 +1182,19 @@ impl DeviceId {
   }
 }
 
+/// The identity of an input device, given by
+/// [`EventLoopWindowTarget::device_info`](crate::event_loop::EventLoopWindowTarget::device_info).
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceInfo {
+  /// The name of the product, e.g. "SpaceMouse Compact".
+  pub name: Option<String>,
+  /// The USB vendor ID of the device.
+  pub vendor_id: Option<u16>,
+  /// The USB product ID of the device.
+  pub product_id: Option<u16>,
+}
+
 /// Represents raw hardware events that are not associated with any particular window.
 ///
 /// Useful for interactions that diverge significantly from a conventional 2D GUI, such as 3D camera or first-person
@@ -661,12 +1206,32 @@ impl DeviceId {
 #[non_exhaustive]
 #[derive(Clone, Debug, PartialEq)]
 pub enum DeviceEvent {
+  /// A device was connected, or was already connected when the event loop started.
+  ///
+  /// Besides the mice and keyboards, the HID devices such as joysticks, jog wheels and 3D mice
+  /// emit their axes with `Motion` and their buttons with `Button`. Their identity is given by
+  /// [`EventLoopWindowTarget::device_info`](crate::event_loop::EventLoopWindowTarget::device_info).
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only emitted for the HID devices, the mice and keyboards having a single id.
+  /// - **Linux:** Only emitted for the evdev devices which are neither mice, keyboards,
+  ///   touchpads, tablets nor gamepads, and which the user can read. Unsupported with the
+  ///   `wayland` feature.
+  /// - **iOS / Android / Web:** Unsupported.
   Added,
+  /// A device was disconnected.
   Removed,
 
   /// Change in physical position of a pointing device.
   ///
   /// This represents raw, unfiltered physical motion. Not to be confused with `WindowEvent::CursorMoved`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** The motion of the mice from IOKit, before the acceleration of the cursor. The
+  ///   deltas of the mouse events are used instead if the HID manager can't be opened.
+  /// - **Linux:** The motion from XInput 2, before the acceleration. Unsupported on Wayland.
   #[non_exhaustive]
   MouseMotion {
     /// (x, y) change in position in unspecified units.
@@ -684,12 +1249,20 @@ pub enum DeviceEvent {
   /// Motion on some analog axis.  This event will be reported for all arbitrary input devices
   /// that tao supports on this platform, including mouse devices.  If the device is a mouse
   /// device then this will be reported alongside the MouseMotion event.
+  ///
+  /// For the HID devices, the axis is the usage of the axis in the generic desktop page, e.g.
+  /// `0x30` for X, with its logical value. On Linux, it's the code and the value of the
+  /// `EV_ABS` or `EV_REL` event of evdev, the relative axes giving deltas.
   #[non_exhaustive]
   Motion {
     axis: AxisId,
     value: f64,
   },
 
+  /// A button was pressed or released.
+  ///
+  /// For the HID devices, the button is the usage of the button in the button page, which
+  /// numbers the buttons from 1. On Linux, it's the code of the `EV_KEY` event of evdev.
   #[non_exhaustive]
   Button {
     button: ButtonId,
@@ -738,11 +1311,11 @@ pub struct KeyEvent {
   /// This has two use cases:
   /// - Allows querying whether the current input is a Dead key.
   /// - Allows handling key-bindings on platforms which don't
-  /// support `key_without_modifiers`.
+  ///   support `key_without_modifiers`.
   ///
   /// ## Platform-specific
   /// - **Web:** Dead keys might be reported as the real key instead
-  /// of `Dead` depending on the browser/OS.
+  ///   of `Dead` depending on the browser/OS.
   ///
   pub logical_key: keyboard::Key<'static>,
 
@@ -769,12 +1342,58 @@ pub struct KeyEvent {
 
   pub location: keyboard::KeyLocation,
   pub state: ElementState,
+
+  /// Whether this press is a repeat of a held key, sent by the auto-repeat of the system.
+  ///
+  /// The delay and rate of the repeats are returned by
+  /// [`EventLoopWindowTarget::key_repeat_info`](crate::event_loop::EventLoopWindowTarget::key_repeat_info).
   pub repeat: bool,
 
+  /// The part this keypress takes in a dead key or compose sequence, `None` if it isn't in one.
+  ///
+  /// The keys composing a sequence are reported with `ComposeState::Composing` and no `text`.
+  /// The key finishing it is reported with `ComposeState::Composed` and the composed character
+  /// as its `text`, instead of sending the character as a separate event.
+  ///
+  /// ## Platform-specific
+  /// - **Android:** Unsupported, this is always `None`.
+  pub compose_state: Option<ComposeState>,
+
   pub(crate) platform_specific: platform_impl::KeyEventExtra,
 }
 
-#[cfg(not(any(target_os = "android", target_os = "ios")))]
+impl KeyEvent {
+  /// Whether this is the keypress of a dead key, modifying the character typed by the next key.
+  pub fn is_dead(&self) -> bool {
+    matches!(self.logical_key, keyboard::Key::Dead(_))
+  }
+
+  /// Returns the text entered by this keypress, sent with `WindowEvent::ReceivedText`, or `None`
+  /// if it's released, composing or a shortcut made with `modifiers`.
+  #[cfg(not(any(target_os = "android", target_os = "ios")))]
+  pub(crate) fn received_text(&self, modifiers: ModifiersState) -> Option<String> {
+    // <kbd>Ctrl</kbd>+<kbd>Alt</kbd> is <kbd>AltGr</kbd> on Windows.
+    let is_shortcut = (modifiers.control_key() && !modifiers.alt_key()) || modifiers.super_key();
+    if self.state == ElementState::Released
+      || self.compose_state == Some(ComposeState::Composing)
+      || is_shortcut
+    {
+      return None;
+    }
+    let text = self
+      .text?
+      .chars()
+      .filter(|c| !c.is_control())
+      .collect::<String>();
+    if text.is_empty() {
+      None
+    } else {
+      Some(text)
+    }
+  }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 impl KeyEvent {
   /// Identical to `KeyEvent::text` but this is affected by <kbd>Ctrl</kbd>.
   ///
@@ -798,7 +1417,7 @@ impl KeyEvent {
   }
 }
 
-#[cfg(any(target_os = "android", target_os = "ios"))]
+#[cfg(any(target_os = "android", target_os = "ios", target_arch = "wasm32"))]
 impl KeyEvent {
   /// Identical to `KeyEvent::text`.
   pub fn text_with_all_modifiers(&self) -> Option<&str> {
@@ -811,6 +1430,99 @@ impl KeyEvent {
   }
 }
 
+/// The part a keypress takes in a dead key or compose sequence, see [`KeyEvent::compose_state`].
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ComposeState {
+  /// The key started or continued a sequence, without typing text yet.
+  Composing,
+  /// The key finished a sequence, the composed text being the `text` of the event.
+  Composed,
+  /// The key can't be combined with the sequence, which is cancelled.
+  ///
+  /// Depending on the platform, the `text` of the event is the one of the dead keys followed by
+  /// the one of this key, or only the one of this key, if any.
+  Cancelled,
+}
+
+/// The composition of text by an input method editor, see [`WindowEvent::Ime`].
+///
+/// A composition sends `Enabled`, then `Preedit` events as the text is edited and `Commit` events
+/// as it's typed into the window, and finishes with `Disabled`. The text being edited should be
+/// drawn in place of the cursor, an empty `Preedit` clearing it.
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ime {
+  /// The IME started composing text.
+  Enabled,
+  /// The text being composed changed.
+  #[non_exhaustive]
+  Preedit {
+    text: String,
+    /// The byte range of the cursor in `text`, an empty range being a caret. `None` hides the
+    /// cursor.
+    cursor: Option<(usize, usize)>,
+    /// The ranges of `text` to underline, in the order of the text.
+    underlines: Vec<ImeUnderline>,
+  },
+  /// The IME typed text into the window.
+  Commit(String),
+  /// The IME stopped composing text.
+  Disabled,
+}
+
+/// An underlined range of the text being composed, see [`Ime::Preedit`].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImeUnderline {
+  /// The byte range of the underline in the text.
+  pub range: (usize, usize),
+  /// Whether the range is the one the IME is converting, usually drawn with a thicker line.
+  pub is_target: bool,
+}
+
+impl Ime {
+  /// The preedit of `text`, underlined as a whole with the caret at its end.
+  pub(crate) fn preedit(text: String) -> Self {
+    let (cursor, underlines) = if text.is_empty() {
+      (None, Vec::new())
+    } else {
+      let range = (0, text.len());
+      (
+        Some((text.len(), text.len())),
+        vec![ImeUnderline {
+          range,
+          is_target: false,
+        }],
+      )
+    };
+    Ime::Preedit {
+      text,
+      cursor,
+      underlines,
+    }
+  }
+}
+
+/// The stage of a fullscreen transition, see [`WindowEvent::FullscreenTransition`].
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FullscreenTransition {
+  /// The window started entering fullscreen.
+  Entering,
+  /// The window is fullscreen.
+  Entered,
+  /// The window started exiting fullscreen.
+  Exiting,
+  /// The window is no longer fullscreen.
+  ///
+  /// This is also sent when entering fullscreen failed.
+  Exited,
+}
+
 /// Describes touch-screen input state.
 #[non_exhaustive]
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
@@ -885,12 +1597,63 @@ pub struct Touch {
   ///
   /// ## Platform-specific
   ///
-  /// - Only available on **iOS** 9.0+ and **Windows** 8+.
+  /// - Only available on **iOS** 9.0+, **Windows** 8+ and **Web**.
   pub force: Option<Force>,
-  /// Unique identifier of a finger.
+  /// Unique identifier of a finger, the same for all the events of the finger until it's lifted.
   pub id: u64,
 }
 
+/// Represents a pen event
+///
+/// A `Started` event is generated when the pen touches the surface, followed by `Moved` events
+/// while it moves or its state changes, and an `Ended` event when it is lifted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PenInput {
+  pub device_id: DeviceId,
+  pub phase: TouchPhase,
+  pub location: PhysicalPosition<f64>,
+  /// How hard the pen is pressed, between 0.0 and 1.0. May be `None` if the pen does not report
+  /// it.
+  pub pressure: Option<f64>,
+  /// The tilt of the pen in degrees, between -90.0 and 90.0, along the X axis (positive to the
+  /// right) and the Y axis (positive towards the user). May be `None` if the pen does not report
+  /// it.
+  pub tilt: Option<(f64, f64)>,
+  /// The clockwise rotation of the pen around its axis in degrees, between 0.0 and 360.0. May be
+  /// `None` if the pen does not report it.
+  pub twist: Option<f64>,
+  /// Whether the barrel button of the pen is pressed.
+  pub barrel_button: bool,
+  /// Whether the pen is upside down, touching the surface with its eraser.
+  pub inverted: bool,
+  /// Whether the pen erases, with its eraser or an eraser button.
+  pub eraser: bool,
+}
+
+/// Represents a pen hovering above the surface
+///
+/// The hover of a pen is sent between its `PenProximity` events, until it touches the surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PenHover {
+  pub device_id: DeviceId,
+  pub location: PhysicalPosition<f64>,
+  /// The distance of the pen from the surface, between 0.0 and 1.0 at the edge of its range. May be
+  /// `None` if the pen does not report it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Only reported by the tablets with a distance axis.
+  /// - **Windows / macOS / Web:** Always `None`.
+  pub distance: Option<f64>,
+  /// The tilt of the pen in degrees, like the one of `PenInput`. May be `None` if the pen does
+  /// not report it.
+  pub tilt: Option<(f64, f64)>,
+  /// Whether the barrel button of the pen is pressed.
+  pub barrel_button: bool,
+  /// Whether the pen is upside down, its eraser towards the surface.
+  pub inverted: bool,
+}
+
 /// Describes the force of a touch event
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -955,6 +1718,21 @@ pub type AxisId = u32;
 /// Identifier for a specific button on some device.
 pub type ButtonId = u32;
 
+/// The direction of a [`WindowEvent::FocusTraversal`].
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FocusDirection {
+  /// To the next widget in the focus chain, with Tab.
+  Forward,
+  /// To the previous widget in the focus chain, with Shift+Tab.
+  Backward,
+  Up,
+  Down,
+  Left,
+  Right,
+}
+
 /// Describes the input state of a key.
 #[non_exhaustive]
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
@@ -972,6 +1750,18 @@ pub enum MouseButton {
   Left,
   Right,
   Middle,
+  /// The side button navigating back in the history, e.g. in browsers and file managers.
+  Back,
+  /// The side button navigating forward in the history.
+  Forward,
+  /// Another button, numbered by the platform.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The number of the X button.
+  /// - **macOS:** The `buttonNumber` of the event.
+  /// - **Linux:** The number of the X11 button, or the evdev code of the button on Wayland.
+  /// - **Web:** The `button` of the pointer event.
   Other(u16),
 }
 
@@ -985,6 +1775,14 @@ pub enum MouseScrollDelta {
   ///
   /// Positive values indicate movement forward
   /// (away from the user) or rightwards.
+  ///
+  /// The amounts are fractional for the high-resolution wheels, which scroll by less than a line
+  /// at a time.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows / Linux (GTK):** The touchpads scroll by fractions of lines too, since the
+  ///   platform doesn't give pixels.
   LineDelta(f32, f32),
   /// Amount in pixels to scroll in the horizontal and
   /// vertical direction.
@@ -13,9 +13,24 @@
 //! [event_loop_proxy]: crate::event_loop::EventLoopProxy
 //! [send_event]: crate::event_loop::EventLoopProxy::send_event
 use instant::Instant;
-use std::{error, fmt, ops::Deref};
+use std::{
+  error, fmt,
+  future::Future,
+  ops::Deref,
+  pin::Pin,
+  sync::{Arc, Condvar, Mutex},
+  task::{Context, Poll, Waker},
+  time::Duration,
+};
 
-use crate::{event::Event, monitor::MonitorHandle, platform_impl};
+use crate::{
+  error::OsError,
+  event::{DeviceId, DeviceInfo, Event, WindowEvent},
+  keyboard::{Key, KeyRepeatInfo, LockKey, ModifiersState},
+  monitor::MonitorHandle,
+  platform_impl, redraw,
+  virtual_desktop::VirtualDesktop,
+};
 
 /// Provides a way to retrieve events from the system and from the windows that were registered to
 /// the events loop.
@@ -32,6 +47,7 @@ use crate::{event::Event, monitor::MonitorHandle, platform_impl};
 ///
 pub struct EventLoop<T: 'static> {
   pub(crate) event_loop: platform_impl::EventLoop<T>,
+  pub(crate) event_hook: Option<EventHook<T>>,
   pub(crate) _marker: ::std::marker::PhantomData<*mut ()>, // Not Send nor Sync
 }
 
@@ -65,7 +81,7 @@ impl<T> fmt::Debug for EventLoopWindowTarget<T> {
 ///
 /// ## Persistency
 /// Almost every change is persistent between multiple calls to the event loop closure within a
-/// given run loop. The only exception to this is `Exit` which, once set, cannot be unset. Changes
+/// given run loop. The only exception to this is `ExitWithCode` which, once set, cannot be unset. Changes
 /// are **not** persistent between multiple calls to `run_return` - issuing a new call will reset
 /// the control flow to `Poll`.
 ///
@@ -80,11 +96,50 @@ pub enum ControlFlow {
   Wait,
   /// When the current loop iteration finishes, suspend the thread until either another event
   /// arrives or the given time is reached.
+  ///
+  /// The event loop never resumes before the given time, and usually less than a millisecond
+  /// after it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Before Windows 10 1803, the event loop may resume up to the interval of the
+  ///   system timer after the given time, which is 15.6ms by default. See
+  ///   [`EventLoopWindowTarget::set_timer_resolution`] to shorten it.
+  /// - **macOS / iOS:** The system may defer the wake up when the application is in the
+  ///   background, e.g. with App Nap.
   WaitUntil(Instant),
   /// Send a `LoopDestroyed` event and stop the event loop. This variant is *sticky* - once set,
-  /// `control_flow` cannot be changed from `Exit`, and any future attempts to do so will result
-  /// in the `control_flow` parameter being reset to `Exit`.
-  Exit,
+  /// `control_flow` cannot be changed from `ExitWithCode`, and any future attempts to do so will
+  /// result in the `control_flow` parameter being reset to `ExitWithCode`.
+  ///
+  /// The code is the exit code of the process with `EventLoop::run`, and is returned by
+  /// `run_return`. The [`Exit`] constant is a shortcut for this with the code 0.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Android:** The exit code of the process is unused.
+  /// - **iOS:** Unsupported, the event loop can't exit.
+  /// - **Linux / macOS:** Only the 8 least significant bits of the exit code of the process are
+  ///   kept, e.g. `-1` ends up as `255`. See [`std::process::exit`].
+  ///
+  /// [`Exit`]: ControlFlow::Exit
+  ExitWithCode(i32),
+}
+
+impl ControlFlow {
+  /// Alias for [`ExitWithCode`]`(0)`.
+  ///
+  /// [`ExitWithCode`]: ControlFlow::ExitWithCode
+  #[allow(non_upper_case_globals)]
+  pub const Exit: Self = Self::ExitWithCode(0);
+
+  /// Returns the exit code if the event loop is exiting.
+  pub(crate) fn exit_code(self) -> Option<i32> {
+    match self {
+      ControlFlow::ExitWithCode(code) => Some(code),
+      _ => None,
+    }
+  }
 }
 
 impl Default for ControlFlow {
@@ -104,12 +159,28 @@ impl EventLoop<()> {
   /// in the relevant `platform` module if the target platform supports creating an event loop on
   /// any thread.
   ///
+  /// Another event loop can be created once the previous one was dropped, e.g. to create and
+  /// destroy the UI of a plugin several times in the lifetime of the process.
+  ///
   /// ## Platform-specific
   ///
   /// - **iOS:** Can only be called on the main thread.
+  /// - **macOS:** Only one event loop can exist at a time, see [`EventLoop::try_new`].
+  /// - **Windows:** Several event loops can exist at once on different threads, see
+  ///   `EventLoopExtWindows::new_any_thread`.
   pub fn new() -> EventLoop<()> {
     EventLoop::<()>::with_user_event()
   }
+
+  /// Like [`EventLoop::new`], but returns an error instead of panicking if the event loop can't
+  /// be created.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Returns an error if another event loop exists.
+  pub fn try_new() -> Result<EventLoop<()>, OsError> {
+    EventLoop::<()>::try_with_user_event()
+  }
 }
 
 impl Default for EventLoop<()> {
@@ -129,10 +200,23 @@ impl<T> EventLoop<T> {
   pub fn with_user_event() -> EventLoop<T> {
     EventLoop {
       event_loop: platform_impl::EventLoop::new(),
+      event_hook: None,
       _marker: ::std::marker::PhantomData,
     }
   }
 
+  /// Like [`EventLoop::with_user_event`], but returns an error instead of panicking if the event
+  /// loop can't be created.
+  ///
+  /// All caveats documented in [`EventLoop::try_new`] apply to this function.
+  pub fn try_with_user_event() -> Result<EventLoop<T>, OsError> {
+    Ok(EventLoop {
+      event_loop: platform_impl::EventLoop::try_new()?,
+      event_hook: None,
+      _marker: ::std::marker::PhantomData,
+    })
+  }
+
   /// Hijacks the calling thread and initializes the tao event loop with the provided
   /// closure. Since the closure is `'static`, it must be a `move` closure if it needs to
   /// access any data from the calling context.
@@ -144,11 +228,39 @@ impl<T> EventLoop<T> {
   ///
   /// [`ControlFlow`]: crate::event_loop::ControlFlow
   #[inline]
-  pub fn run<F>(self, event_handler: F) -> !
+  pub fn run<F>(mut self, mut event_handler: F) -> !
   where
     F: 'static + FnMut(Event<'_, T>, &EventLoopWindowTarget<T>, &mut ControlFlow),
   {
-    self.event_loop.run(event_handler)
+    let mut event_hook = self.event_hook.take();
+    self
+      .event_loop
+      .run(move |event, window_target, control_flow| {
+        if should_dispatch(&mut event_hook, &event) {
+          event_handler(event, window_target, control_flow)
+        }
+      })
+  }
+
+  /// Sets a hook that is called with every event before the event handler, replacing the
+  /// previous one.
+  ///
+  /// The hook sees the events first and decides whether the event handler gets them with the
+  /// returned [`HookAction`], e.g. to record analytics or to intercept some shortcuts for a whole
+  /// application without changing its event handler. `Event::LoopDestroyed` is always dispatched.
+  ///
+  /// The hook applies to [`run`](Self::run), and to the event loops run with the extensions of
+  /// [`platform`](crate::platform) like `run_return`.
+  pub fn set_event_hook<H>(&mut self, hook: H)
+  where
+    H: 'static + FnMut(&Event<'_, T>) -> HookAction,
+  {
+    self.event_hook = Some(Box::new(hook));
+  }
+
+  /// Removes the hook set with [`set_event_hook`](Self::set_event_hook).
+  pub fn remove_event_hook(&mut self) {
+    self.event_hook = None;
   }
 
   /// Creates an `EventLoopProxy` that can be used to dispatch user events to the main event loop.
@@ -157,6 +269,16 @@ impl<T> EventLoop<T> {
       event_loop_proxy: self.event_loop.create_proxy(),
     }
   }
+
+  /// Sets the number of user events of `priority` that can wait to be dispatched, or lifts the
+  /// limit with `None`, which is the default.
+  ///
+  /// Once the queue of a priority is full, [`EventLoopProxy::send_event_with_priority`] returns
+  /// [`SendEventError::Full`] with the event instead of queueing it, so the producer can drop or
+  /// coalesce its events. [`EventLoopProxy::send_event`] doesn't take the limit into account.
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.event_loop.set_user_event_capacity(priority, capacity)
+  }
 }
 
 impl<T> Deref for EventLoop<T> {
@@ -184,8 +306,223 @@ impl<T> EventLoopWindowTarget<T> {
   pub fn primary_monitor(&self) -> Option<MonitorHandle> {
     self.p.primary_monitor()
   }
+
+  /// Returns the virtual desktops of the user, in the order they are shown by the system.
+  ///
+  /// See the [`virtual_desktop`](crate::virtual_desktop) module for the platform support.
+  #[inline]
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    self
+      .p
+      .virtual_desktops()
+      .into_iter()
+      .map(|inner| VirtualDesktop { inner })
+      .collect()
+  }
+
+  /// Returns the virtual desktop the user is currently on.
+  ///
+  /// Returns `None` if it can't be identified.
+  #[inline]
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    self
+      .p
+      .current_virtual_desktop()
+      .map(|inner| VirtualDesktop { inner })
+  }
+
+  /// Returns the key typed by the physical key of `scancode` in the current keyboard layout,
+  /// without modifiers, e.g. to show "Press Z" for the key of `KeyCode::KeyW` on an AZERTY
+  /// keyboard.
+  ///
+  /// The scancodes are the ones of [`KeyCode::to_scancode`](crate::keyboard::KeyCode::to_scancode).
+  /// Returns `None` if the key is unknown or types nothing in the layout.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The layout is only known on X11 and with the `wayland` feature, the first one
+  ///   is used otherwise.
+  /// - **iOS / Android / Web:** Unsupported.
+  #[inline]
+  pub fn key_from_scancode(&self, scancode: u32) -> Option<Key<'static>> {
+    self.p.key_from_scancode(scancode)
+  }
+
+  /// Returns the scancode of the physical key typing `key` in the current keyboard layout,
+  /// without modifiers, the reverse of
+  /// [`key_from_scancode`](EventLoopWindowTarget::key_from_scancode).
+  ///
+  /// The characters are matched as typed without modifiers, e.g. `Key::Character("a")` rather
+  /// than `"A"`. Returns `None` if no key types it.
+  #[inline]
+  pub fn scancode_from_key(&self, key: &Key<'_>) -> Option<u32> {
+    self.p.scancode_from_key(key)
+  }
+
+  /// Returns the auto-repeat of the held keys set in the system settings, whose repeats are sent
+  /// with [`KeyEvent::repeat`](crate::event::KeyEvent::repeat).
+  ///
+  /// Returns `None` if it's unknown.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Only known on X11 and with the `wayland` feature.
+  /// - **iOS / Android / Web:** Unsupported.
+  #[inline]
+  pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+    self.p.key_repeat_info()
+  }
+
+  /// Returns the modifiers currently held on the keyboard.
+  ///
+  /// Unlike tracking the `ModifiersChanged` events, this is right even if the modifiers changed
+  /// while no window had the focus.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** On Wayland with the `wayland` feature, these are the modifiers sent to the last
+  ///   focused window.
+  /// - **iOS / Android / Web:** Unsupported, this is always empty.
+  #[inline]
+  pub fn modifiers_state(&self) -> ModifiersState {
+    self.p.modifiers_state()
+  }
+
+  /// Returns whether a lock key is on, or `None` if it's unknown.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only `LockKey::CapsLock` is known.
+  /// - **iOS / Android / Web:** Unsupported.
+  #[inline]
+  pub fn lock_key_state(&self, key: LockKey) -> Option<bool> {
+    self.p.lock_key_state(key)
+  }
+
+  /// Starts a timer that emits `Event::Timer(id)` every `interval`, until it's cancelled with
+  /// [`EventLoopWindowTarget::cancel_timer`].
+  ///
+  /// Setting a timer with the `id` of a running timer restarts it with the new interval.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The interval is rounded up to milliseconds, and is at least 10ms.
+  /// - **Linux:** The interval is rounded to milliseconds.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    self.p.set_timer(interval, id)
+  }
+
+  /// Cancels the timer started with `id`. Does nothing if there is no such timer.
+  #[inline]
+  pub fn cancel_timer(&self, id: TimerId) {
+    self.p.cancel_timer(id)
+  }
+
+  /// Requests a minimum resolution for the timers of the system, or restores the default one with
+  /// `None`, e.g. while an animation is running.
+  ///
+  /// A finer resolution makes the timers of [`set_timer`](Self::set_timer) and the sleeps of the
+  /// application more precise, at the expense of the power consumption of the whole system.
+  /// `ControlFlow::WaitUntil` is precise without it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The resolution is rounded up to milliseconds, and restored when the event
+  ///   loop is dropped.
+  /// - **macOS / Linux / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_timer_resolution(&self, resolution: Option<Duration>) {
+    self.p.set_timer_resolution(resolution)
+  }
+
+  /// Sets when the device events are filtered out, i.e. when `Event::DeviceEvent` isn't emitted.
+  ///
+  /// Filtering the device events out when they aren't used saves the overhead of receiving them
+  /// from the system. The default is [`DeviceEventFilter::Never`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only `DeviceEvent::MouseMotion` and the events of the HID devices are received
+  ///   while the application isn't focused, the other device events are only received when it is.
+  /// - **Linux:** Only `DeviceEvent::MouseMotion` is emitted, on X11, along with the events of
+  ///   the evdev devices.
+  /// - **iOS / Android:** Unsupported, since device events aren't emitted.
+  #[inline]
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    self.p.set_device_event_filter(filter)
+  }
+
+  /// Returns the name and the USB ids of a connected device, see [`DeviceEvent::Added`].
+  ///
+  /// Returns `None` once the device is removed, or when the platform can't identify it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The name is only known for the HID devices, mice and keyboards included.
+  /// - **macOS:** Only the HID devices are identified.
+  /// - **Linux:** Only the evdev devices are identified, see [`DeviceEvent::Added`].
+  /// - **iOS / Android / Web:** Unsupported.
+  ///
+  /// [`DeviceEvent::Added`]: crate::event::DeviceEvent::Added
+  #[inline]
+  pub fn device_info(&self, device_id: DeviceId) -> Option<DeviceInfo> {
+    self.p.device_info(device_id.0)
+  }
+}
+
+/// When the device events are filtered out, see
+/// [`EventLoopWindowTarget::set_device_event_filter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DeviceEventFilter {
+  /// The device events are always filtered out.
+  Always,
+  /// The device events are filtered out when no window of the application is focused.
+  Unfocused,
+  /// The device events are never filtered out.
+  #[default]
+  Never,
+}
+
+/// What happens to an event after the hook set with [`EventLoop::set_event_hook`] saw it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookAction {
+  /// The event is dispatched to the event handler.
+  Dispatch,
+  /// The event is swallowed, the event handler doesn't get it.
+  Swallow,
+}
+
+pub(crate) type EventHook<T> = Box<dyn FnMut(&Event<'_, T>) -> HookAction>;
+
+/// Calls the event hook with `event`, and returns whether the event handler gets it.
+///
+/// Every event goes through this function before the event handler, so it also records the
+/// `RedrawRequested` and `Destroyed` events for the coalescing of the redraws.
+pub(crate) fn should_dispatch<T>(
+  event_hook: &mut Option<EventHook<T>>,
+  event: &Event<'_, T>,
+) -> bool {
+  match event {
+    Event::RedrawRequested(window_id) => redraw::redrawn(*window_id),
+    Event::WindowEvent {
+      window_id,
+      event: WindowEvent::Destroyed,
+      ..
+    } => redraw::destroyed(*window_id),
+    _ => (),
+  }
+  match event_hook {
+    Some(hook) => hook(event) == HookAction::Dispatch || matches!(event, Event::LoopDestroyed),
+    None => true,
+  }
 }
 
+/// Identifies a timer started with [`EventLoopWindowTarget::set_timer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimerId(pub u32);
+
 /// Used to send custom events to `EventLoop`.
 pub struct EventLoopProxy<T: 'static> {
   event_loop_proxy: platform_impl::EventLoopProxy<T>,
@@ -208,6 +545,85 @@ impl<T: 'static> EventLoopProxy<T> {
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
     self.event_loop_proxy.send_event(event)
   }
+
+  /// Send an event to the `EventLoop` from which this proxy was created with the given priority.
+  ///
+  /// The queued user events are dispatched from the highest priority to the lowest, and in the
+  /// order they were sent within a priority. The events sent with [`send_event`] have the
+  /// `Normal` priority.
+  ///
+  /// Returns an `Err` with the event if the associated `EventLoop` no longer exists, or if the
+  /// queue of `priority` is full, see [`EventLoop::set_user_event_capacity`].
+  ///
+  /// [`send_event`]: Self::send_event
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self
+      .event_loop_proxy
+      .send_event_with_priority(event, priority)
+  }
+
+  /// Sends a request to the `EventLoop` from which this proxy was created, and returns the
+  /// [`Response`] to wait for.
+  ///
+  /// `event` builds the user event carrying the [`Responder`] of the request, e.g. a variant of
+  /// the user event type wrapping it. The event handler answers with [`Responder::respond`], so
+  /// another thread can get data that is only available on the thread of the event loop, like
+  /// the bounds of a window or the list of monitors.
+  ///
+  /// ```no_run
+  /// use tao::{
+  ///   event::Event,
+  ///   event_loop::{ControlFlow, EventLoop, Responder},
+  ///   monitor::MonitorHandle,
+  /// };
+  ///
+  /// enum UserEvent {
+  ///   Monitors(Responder<Vec<String>>),
+  /// }
+  ///
+  /// let event_loop = EventLoop::<UserEvent>::with_user_event();
+  /// let proxy = event_loop.create_proxy();
+  ///
+  /// std::thread::spawn(move || {
+  ///   if let Ok(response) = proxy.request(UserEvent::Monitors) {
+  ///     println!("{:?}", response.recv());
+  ///   }
+  /// });
+  ///
+  /// event_loop.run(move |event, event_loop, control_flow| {
+  ///   *control_flow = ControlFlow::Wait;
+  ///   if let Event::UserEvent(UserEvent::Monitors(responder)) = event {
+  ///     let names = event_loop
+  ///       .available_monitors()
+  ///       .filter_map(|monitor: MonitorHandle| monitor.name())
+  ///       .collect();
+  ///     responder.respond(names);
+  ///   }
+  /// });
+  /// ```
+  ///
+  /// Returns an `Err` with the event if the associated `EventLoop` no longer exists.
+  pub fn request<R, F>(&self, event: F) -> Result<Response<R>, EventLoopClosed<T>>
+  where
+    F: FnOnce(Responder<R>) -> T,
+  {
+    let (responder, response) = response_channel();
+    self.send_event(event(responder))?;
+    Ok(response)
+  }
+
+  /// Wakes the `EventLoop` from which this proxy was created up without a user event. This emits
+  /// `NewEvents` and `MainEventsCleared`, e.g. to poll a future that became ready on another
+  /// thread.
+  ///
+  /// Does nothing if the associated `EventLoop` no longer exists.
+  pub fn wake_up(&self) {
+    self.event_loop_proxy.wake_up()
+  }
 }
 
 impl<T: 'static> fmt::Debug for EventLoopProxy<T> {
@@ -228,3 +644,266 @@ impl<T> fmt::Display for EventLoopClosed<T> {
 }
 
 impl<T: fmt::Debug> error::Error for EventLoopClosed<T> {}
+
+/// Answers a request sent with [`EventLoopProxy::request`].
+///
+/// Dropping it without responding cancels the request.
+pub struct Responder<R> {
+  slot: Arc<ResponseSlot<R>>,
+  responded: bool,
+}
+
+impl<R> Responder<R> {
+  /// Sends `value` to the [`Response`] of the request.
+  pub fn respond(mut self, value: R) {
+    self.slot.state.lock().unwrap().value = Some(value);
+    self.responded = true;
+    // `Drop` wakes the response up.
+  }
+}
+
+impl<R> Drop for Responder<R> {
+  fn drop(&mut self) {
+    let mut state = self.slot.state.lock().unwrap();
+    state.canceled = !self.responded;
+    if let Some(waker) = state.waker.take() {
+      waker.wake();
+    }
+    self.slot.ready.notify_all();
+  }
+}
+
+impl<R> fmt::Debug for Responder<R> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad("Responder { .. }")
+  }
+}
+
+/// The response to a request sent with [`EventLoopProxy::request`].
+///
+/// It can be waited for on the requesting thread with [`recv`](Self::recv), or awaited since it is
+/// a `Future`.
+pub struct Response<R> {
+  slot: Arc<ResponseSlot<R>>,
+}
+
+impl<R> Response<R> {
+  /// Blocks the current thread until the event loop responds.
+  ///
+  /// Returns an `Err` if the request was dropped without a response, e.g. because the event loop
+  /// exited.
+  pub fn recv(self) -> Result<R, Canceled> {
+    let mut state = self.slot.state.lock().unwrap();
+    loop {
+      if let Some(result) = state.take() {
+        return result;
+      }
+      state = self.slot.ready.wait(state).unwrap();
+    }
+  }
+
+  /// Blocks the current thread until the event loop responds or `timeout` elapses.
+  ///
+  /// Returns `Ok(None)` if the event loop didn't respond in time.
+  pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<R>, Canceled> {
+    let deadline = Instant::now() + timeout;
+    let mut state = self.slot.state.lock().unwrap();
+    loop {
+      if let Some(result) = state.take() {
+        return result.map(Some);
+      }
+      let now = Instant::now();
+      if now >= deadline {
+        return Ok(None);
+      }
+      state = self
+        .slot
+        .ready
+        .wait_timeout(state, deadline - now)
+        .unwrap()
+        .0;
+    }
+  }
+
+  /// Returns the response if the event loop responded already, without blocking.
+  pub fn try_recv(&self) -> Result<Option<R>, Canceled> {
+    self.slot.state.lock().unwrap().take().transpose()
+  }
+}
+
+impl<R> Future for Response<R> {
+  type Output = Result<R, Canceled>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let mut state = self.slot.state.lock().unwrap();
+    match state.take() {
+      Some(result) => Poll::Ready(result),
+      None => {
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+      }
+    }
+  }
+}
+
+impl<R> fmt::Debug for Response<R> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad("Response { .. }")
+  }
+}
+
+struct ResponseSlot<R> {
+  state: Mutex<ResponseState<R>>,
+  ready: Condvar,
+}
+
+fn response_channel<R>() -> (Responder<R>, Response<R>) {
+  let slot = Arc::new(ResponseSlot {
+    state: Mutex::new(ResponseState {
+      value: None,
+      canceled: false,
+      waker: None,
+    }),
+    ready: Condvar::new(),
+  });
+  let responder = Responder {
+    slot: slot.clone(),
+    responded: false,
+  };
+  (responder, Response { slot })
+}
+
+struct ResponseState<R> {
+  value: Option<R>,
+  /// Whether the `Responder` was dropped without responding.
+  canceled: bool,
+  waker: Option<Waker>,
+}
+
+impl<R> ResponseState<R> {
+  fn take(&mut self) -> Option<Result<R, Canceled>> {
+    match self.value.take() {
+      Some(value) => Some(Ok(value)),
+      None if self.canceled => Some(Err(Canceled)),
+      None => None,
+    }
+  }
+}
+
+/// The error that is returned when the [`Responder`] of a request was dropped without responding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("The request was dropped without a response")
+  }
+}
+
+impl error::Error for Canceled {}
+
+/// The priority of a user event sent with [`EventLoopProxy::send_event_with_priority`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+  /// For events that can wait, e.g. telemetry.
+  Low,
+  /// The priority of the events sent with [`EventLoopProxy::send_event`].
+  #[default]
+  Normal,
+  /// For events that must be handled before the others, e.g. input forwarded from another thread.
+  High,
+}
+
+/// The error that is returned when [`EventLoopProxy::send_event_with_priority`] can't queue an
+/// event. Contains the original event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SendEventError<T> {
+  /// The `EventLoop` no longer exists.
+  Closed(T),
+  /// The queue of the priority of the event is full.
+  Full(T),
+}
+
+impl<T> SendEventError<T> {
+  /// Returns the event that couldn't be sent.
+  pub fn into_inner(self) -> T {
+    match self {
+      SendEventError::Closed(event) | SendEventError::Full(event) => event,
+    }
+  }
+}
+
+impl<T> fmt::Display for SendEventError<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SendEventError::Closed(_) => f.write_str("Tried to wake up a closed `EventLoop`"),
+      SendEventError::Full(_) => f.write_str("The user event queue of the `EventLoop` is full"),
+    }
+  }
+}
+
+impl<T: fmt::Debug> error::Error for SendEventError<T> {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    task::Wake,
+    thread,
+  };
+
+  #[test]
+  fn response_receives_the_value() {
+    let (responder, response) = response_channel();
+    assert_eq!(response.try_recv(), Ok(None));
+    let thread = thread::spawn(move || responder.respond(42));
+    assert_eq!(response.recv(), Ok(42));
+    thread.join().unwrap();
+  }
+
+  #[test]
+  fn dropping_the_responder_cancels_the_response() {
+    let (responder, response) = response_channel::<u32>();
+    drop(responder);
+    assert_eq!(response.try_recv(), Err(Canceled));
+    assert_eq!(response.recv_timeout(Duration::from_secs(1)), Err(Canceled));
+    assert_eq!(response.recv(), Err(Canceled));
+  }
+
+  #[test]
+  fn recv_timeout_expires() {
+    let (responder, response) = response_channel();
+    let start = Instant::now();
+    let timeout = Duration::from_millis(20);
+    assert_eq!(response.recv_timeout(timeout), Ok(None));
+    assert!(start.elapsed() >= timeout);
+
+    // The response still arrives afterwards.
+    responder.respond("late");
+    assert_eq!(response.recv_timeout(timeout), Ok(Some("late")));
+  }
+
+  struct FlagWaker(AtomicBool);
+
+  impl Wake for FlagWaker {
+    fn wake(self: Arc<Self>) {
+      self.0.store(true, Ordering::SeqCst);
+    }
+  }
+
+  #[test]
+  fn future_wakes_its_waker() {
+    let (responder, mut response) = response_channel();
+    let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    assert_eq!(Pin::new(&mut response).poll(&mut cx), Poll::Pending);
+    assert!(!flag.0.load(Ordering::SeqCst));
+
+    responder.respond(7);
+    assert!(flag.0.load(Ordering::SeqCst));
+    assert_eq!(Pin::new(&mut response).poll(&mut cx), Poll::Ready(Ok(7)));
+  }
+}
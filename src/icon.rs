@@ -84,7 +84,7 @@ mod constructors {
     /// The length of `rgba` must be divisible by 4, and `width * height` must equal
     /// `rgba.len() / 4`. Otherwise, this will return a `BadIcon` error.
     pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
-      if rgba.len() % PIXEL_SIZE != 0 {
+      if !rgba.len().is_multiple_of(PIXEL_SIZE) {
         return Err(BadIcon::ByteCountNotDivisibleBy4 {
           byte_count: rgba.len(),
         });
@@ -69,11 +69,12 @@
 //
 // --------- END OF W3C SHORT NOTICE ---------------------------------------------------------------
 
-use std::{fmt, str::FromStr};
+use std::{fmt, str::FromStr, time::Duration};
 
 use crate::{
   error::OsError,
   platform_impl::{
+    current_keyboard_layout as platform_current_keyboard_layout,
     keycode_from_scancode as platform_keycode_from_scancode,
     keycode_to_scancode as platform_keycode_to_scancode,
   },
@@ -516,7 +517,17 @@ pub enum KeyCode {
   /// Sometimes labelled <kbd>Calculator</kbd> on the keyboard
   LaunchApp2,
   LaunchMail,
+  /// Found on the media remotes.
+  MediaFastForward,
+  /// The <kbd>Pause</kbd> key of the media remotes, unlike [`KeyCode::MediaPlayPause`].
+  MediaPause,
+  /// The <kbd>Play</kbd> key of the media remotes, unlike [`KeyCode::MediaPlayPause`].
+  MediaPlay,
   MediaPlayPause,
+  /// Found on the media remotes.
+  MediaRecord,
+  /// Found on the media remotes.
+  MediaRewind,
   MediaSelect,
   MediaStop,
   MediaTrackNext,
@@ -770,6 +781,12 @@ impl FromStr for KeyCode {
       "MEDIANEXTTRACK" => KeyCode::MediaTrackNext,
       "MEDIAPREVIOUSTRACK" => KeyCode::MediaTrackPrevious,
       "MEDIAPLAYPAUSE" => KeyCode::MediaPlayPause,
+      "MEDIAPLAY" => KeyCode::MediaPlay,
+      "MEDIAPAUSE" => KeyCode::MediaPause,
+      "MEDIASTOP" => KeyCode::MediaStop,
+      "MEDIARECORD" => KeyCode::MediaRecord,
+      "MEDIAREWIND" => KeyCode::MediaRewind,
+      "MEDIAFASTFORWARD" => KeyCode::MediaFastForward,
       "LAUNCHMAIL" => KeyCode::LaunchMail,
 
       "SUSPEND" => KeyCode::Suspend,
@@ -1578,6 +1595,17 @@ impl<'a> From<&'a str> for Key<'a> {
   }
 }
 
+/// A key toggling a lock, whose state is returned by
+/// [`EventLoopWindowTarget::lock_key_state`](crate::event_loop::EventLoopWindowTarget::lock_key_state).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LockKey {
+  CapsLock,
+  NumLock,
+  ScrollLock,
+}
+
 /// Represents the location of a physical key.
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -1588,3 +1616,68 @@ pub enum KeyLocation {
   Right,
   Numpad,
 }
+
+/// A keyboard layout, e.g. to show the shortcuts of the application with the keys the user types.
+///
+/// The current layout is returned by [`Keyboard::current_layout`], and its changes are sent as [`Event::KeyboardLayoutChanged`](crate::event::Event::KeyboardLayoutChanged).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyboardLayout {
+  pub(crate) id: String,
+}
+
+impl KeyboardLayout {
+  /// Returns the identifier of the layout given by the OS.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The input locale identifier (`HKL`) in hexadecimal, e.g. `04090409` for US
+  ///   English.
+  /// - **macOS:** The id of the input source, e.g. `com.apple.keylayout.US`.
+  /// - **Linux:** The name of the XKB layout, e.g. `English (US)`.
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+}
+
+/// The keyboard of the system.
+#[derive(Debug)]
+pub struct Keyboard {
+  _private: (),
+}
+
+impl Keyboard {
+  /// Returns the keyboard layout the user currently types with.
+  ///
+  /// Returns `None` if it can't be identified. See
+  /// [`Event::KeyboardLayoutChanged`](crate::event::Event::KeyboardLayoutChanged) for the platform
+  /// support.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Returns the layout of the calling thread, which is the one of the event loop
+  ///   when called from its thread.
+  /// - **Linux:** Must be called on the main thread, after the event loop was created.
+  pub fn current_layout() -> Option<KeyboardLayout> {
+    platform_current_keyboard_layout()
+  }
+}
+
+/// The auto-repeat of the held keys, returned by
+/// [`EventLoopWindowTarget::key_repeat_info`](crate::event_loop::EventLoopWindowTarget::key_repeat_info).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyRepeatInfo {
+  pub(crate) delay: Duration,
+  pub(crate) rate: f64,
+}
+
+impl KeyRepeatInfo {
+  /// Returns the time a key is held before it's repeated.
+  pub fn repeat_delay(&self) -> Duration {
+    self.delay
+  }
+
+  /// Returns the number of repeats per second of a held key, `0.0` if the keys aren't repeated.
+  pub fn repeat_rate(&self) -> f64 {
+    self.rate
+  }
+}
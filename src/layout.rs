@@ -0,0 +1,260 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers to arrange windows on the monitors, e.g. to give the new documents of an application
+//! a sensible default placement.
+//!
+//! The offsets are in logical pixels and are scaled by the monitor the windows are on, so an
+//! arrangement looks the same on every monitor.
+//!
+//! The whole area of a monitor is used, which may include the taskbar, the dock or the panels of
+//! the desktop.
+//!
+//! ## Platform-specific
+//!
+//! - **iOS / Android:** Unsupported.
+use crate::{
+  dpi::{PhysicalPosition, PhysicalSize},
+  monitor::MonitorHandle,
+  window::Window,
+};
+
+/// The offset in logical pixels between two cascaded windows.
+pub const CASCADE_OFFSET: f64 = 32.0;
+
+/// How [`tile`] arranges windows.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tiling {
+  /// The windows are placed side by side, each taking the full height of the monitor.
+  Columns,
+  /// The windows are stacked on top of each other, each taking the full width of the monitor.
+  Rows,
+  /// The windows are placed in a grid with as many columns as rows, or one more column.
+  Grid,
+}
+
+/// Returns the position of a window of `size` cascaded from `from`: [`CASCADE_OFFSET`] down and
+/// to the right of it.
+///
+/// If the window wouldn't fit on the monitor of `from` anymore, the cascade starts over at the
+/// top-left corner of that monitor.
+///
+/// Returns `None` if the position of `from` can't be determined.
+pub fn cascade_position(from: &Window, size: PhysicalSize<u32>) -> Option<PhysicalPosition<i32>> {
+  let position = from.outer_position().ok()?;
+
+  let monitor = match from.current_monitor() {
+    Some(monitor) => monitor,
+    None => {
+      let offset = logical_to_physical(CASCADE_OFFSET, from.scale_factor());
+      return Some(PhysicalPosition::new(
+        position.x + offset,
+        position.y + offset,
+      ));
+    }
+  };
+
+  let offset = logical_to_physical(CASCADE_OFFSET, monitor.scale_factor());
+  Some(cascade(
+    position,
+    size,
+    offset,
+    monitor.position(),
+    monitor.size(),
+  ))
+}
+
+/// The position `offset` down and to the right of `position`, or the origin of the monitor if
+/// the window wouldn't fit on it there.
+fn cascade(
+  position: PhysicalPosition<i32>,
+  size: PhysicalSize<u32>,
+  offset: i32,
+  origin: PhysicalPosition<i32>,
+  monitor_size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+  let next = PhysicalPosition::new(position.x + offset, position.y + offset);
+  let fits = next.x + size.width as i32 <= origin.x + monitor_size.width as i32
+    && next.y + size.height as i32 <= origin.y + monitor_size.height as i32;
+
+  if fits {
+    next
+  } else {
+    origin
+  }
+}
+
+/// Returns the position that centers a window of `size` on `monitor`.
+///
+/// The window is aligned to the top-left corner of the monitor if it is larger than it.
+pub fn center_position(size: PhysicalSize<u32>, monitor: &MonitorHandle) -> PhysicalPosition<i32> {
+  center(size, monitor.position(), monitor.size())
+}
+
+fn center(
+  size: PhysicalSize<u32>,
+  origin: PhysicalPosition<i32>,
+  monitor_size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+  let x = (monitor_size.width as i32 - size.width as i32).max(0) / 2;
+  let y = (monitor_size.height as i32 - size.height as i32).max(0) / 2;
+  PhysicalPosition::new(origin.x + x, origin.y + y)
+}
+
+/// Arranges `windows` on `monitor` so they fill it without overlapping.
+///
+/// The windows are placed in the order of the slice, from left to right and from top to bottom.
+/// Their decorations are taken into account, so it's their outer frames that fill the monitor.
+pub fn tile(windows: &[&Window], monitor: &MonitorHandle, tiling: Tiling) {
+  let cells = tile_cells(
+    windows.len() as u32,
+    tiling,
+    monitor.position(),
+    monitor.size(),
+  );
+  for (window, (position, size)) in windows.iter().zip(cells) {
+    let outer_size = window.outer_size();
+    let inner_size = window.inner_size();
+    let frame_width = outer_size.width.saturating_sub(inner_size.width);
+    let frame_height = outer_size.height.saturating_sub(inner_size.height);
+
+    window.set_outer_position(position);
+    window.set_inner_size(PhysicalSize::new(
+      size.width.saturating_sub(frame_width),
+      size.height.saturating_sub(frame_height),
+    ));
+  }
+}
+
+/// The outer frames of `count` tiled windows, in the order they're placed.
+fn tile_cells(
+  count: u32,
+  tiling: Tiling,
+  origin: PhysicalPosition<i32>,
+  size: PhysicalSize<u32>,
+) -> Vec<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+  if count == 0 {
+    return Vec::new();
+  }
+
+  let (columns, rows) = match tiling {
+    Tiling::Columns => (count, 1),
+    Tiling::Rows => (1, count),
+    Tiling::Grid => {
+      let columns = (count as f64).sqrt().ceil() as u32;
+      (columns, count.div_ceil(columns))
+    }
+  };
+
+  // The edges are computed from the monitor size so the rounding never leaves a gap.
+  let column_edge = |column: u32| origin.x + (size.width * column / columns) as i32;
+  let row_edge = |row: u32| origin.y + (size.height * row / rows) as i32;
+
+  (0..count)
+    .map(|index| {
+      let (column, row) = (index % columns, index / columns);
+      let (left, right) = (column_edge(column), column_edge(column + 1));
+      let (top, bottom) = (row_edge(row), row_edge(row + 1));
+      (
+        PhysicalPosition::new(left, top),
+        PhysicalSize::new((right - left) as u32, (bottom - top) as u32),
+      )
+    })
+    .collect()
+}
+
+fn logical_to_physical(value: f64, scale_factor: f64) -> i32 {
+  (value * scale_factor).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ORIGIN: PhysicalPosition<i32> = PhysicalPosition { x: 100, y: 50 };
+  const MONITOR: PhysicalSize<u32> = PhysicalSize {
+    width: 1000,
+    height: 600,
+  };
+
+  fn cell(x: i32, y: i32, width: u32, height: u32) -> (PhysicalPosition<i32>, PhysicalSize<u32>) {
+    (
+      PhysicalPosition::new(x, y),
+      PhysicalSize::new(width, height),
+    )
+  }
+
+  #[test]
+  fn cascade_moves_down_and_right() {
+    let size = PhysicalSize::new(400, 300);
+    assert_eq!(
+      cascade(PhysicalPosition::new(150, 80), size, 32, ORIGIN, MONITOR),
+      PhysicalPosition::new(182, 112)
+    );
+    // Exactly fits against the bottom-right corner.
+    assert_eq!(
+      cascade(PhysicalPosition::new(668, 318), size, 32, ORIGIN, MONITOR),
+      PhysicalPosition::new(700, 350)
+    );
+  }
+
+  #[test]
+  fn cascade_starts_over_when_the_window_doesnt_fit() {
+    let size = PhysicalSize::new(400, 300);
+    assert_eq!(
+      cascade(PhysicalPosition::new(669, 80), size, 32, ORIGIN, MONITOR),
+      ORIGIN
+    );
+    assert_eq!(
+      cascade(PhysicalPosition::new(150, 319), size, 32, ORIGIN, MONITOR),
+      ORIGIN
+    );
+  }
+
+  #[test]
+  fn center_on_the_monitor() {
+    assert_eq!(
+      center(PhysicalSize::new(400, 300), ORIGIN, MONITOR),
+      PhysicalPosition::new(400, 200)
+    );
+    // A larger window is aligned to the top-left corner.
+    assert_eq!(
+      center(PhysicalSize::new(1200, 300), ORIGIN, MONITOR),
+      PhysicalPosition::new(100, 200)
+    );
+  }
+
+  #[test]
+  fn tile_in_columns_and_rows() {
+    assert_eq!(
+      tile_cells(2, Tiling::Columns, ORIGIN, MONITOR),
+      [cell(100, 50, 500, 600), cell(600, 50, 500, 600)]
+    );
+    assert_eq!(
+      tile_cells(3, Tiling::Rows, ORIGIN, MONITOR),
+      [
+        cell(100, 50, 1000, 200),
+        cell(100, 250, 1000, 200),
+        cell(100, 450, 1000, 200)
+      ]
+    );
+    assert!(tile_cells(0, Tiling::Grid, ORIGIN, MONITOR).is_empty());
+  }
+
+  #[test]
+  fn tile_in_a_grid_without_gaps() {
+    // 5 windows take 3 columns and 2 rows, and the rounding leaves no gap between them.
+    let cells = tile_cells(5, Tiling::Grid, ORIGIN, MONITOR);
+    assert_eq!(
+      cells,
+      [
+        cell(100, 50, 333, 300),
+        cell(433, 50, 333, 300),
+        cell(766, 50, 334, 300),
+        cell(100, 350, 333, 300),
+        cell(433, 350, 333, 300),
+      ]
+    );
+  }
+}
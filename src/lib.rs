@@ -66,7 +66,7 @@
 //!
 //!     match event {
 //!         Event::WindowEvent {
-//!             event: WindowEvent::CloseRequested,
+//!             event: WindowEvent::CloseRequested { .. },
 //!             ..
 //!         } => {
 //!             println!("The close button was pressed; stopping");
@@ -158,6 +158,14 @@ extern crate bitflags;
 #[macro_use]
 extern crate objc;
 
+#[cfg(any(
+  test,
+  all(
+    not(feature = "headless"),
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+  )
+))]
+mod click_count;
 pub mod clipboard;
 pub mod dpi;
 #[macro_use]
@@ -168,9 +176,11 @@ pub mod event_loop;
 pub mod global_shortcut;
 mod icon;
 pub mod keyboard;
+pub mod layout;
 pub mod menu;
 pub mod monitor;
 mod platform_impl;
+mod redraw;
 #[cfg(any(
   target_os = "windows",
   target_os = "macos",
@@ -182,6 +192,10 @@ mod platform_impl;
 ))]
 #[cfg(feature = "tray")]
 pub mod system_tray;
+#[cfg(feature = "test")]
+pub mod test;
+mod user_event_queue;
+pub mod virtual_desktop;
 pub mod window;
 
 pub mod platform;
@@ -32,6 +32,7 @@ use crate::{
 ///
 /// ## Platform-specific
 ///
+/// **Linux:** Unsupported with the Wayland backend, the menus aren't shown.
 pub struct ContextMenu(pub(crate) Menu);
 /// Object that allows you to create a `MenuBar`, menu.
 ///
@@ -39,6 +40,7 @@ pub struct ContextMenu(pub(crate) Menu);
 ///
 /// **macOs:** The menu will show in the **Menu Bar**.
 /// **Linux / Windows:** The menu will be show at the top of the window.
+/// **Linux:** Unsupported with the Wayland backend, the menus aren't shown.
 pub struct MenuBar(pub(crate) Menu);
 
 /// A custom menu item.
@@ -86,6 +86,18 @@ impl VideoMode {
     self.video_mode.refresh_rate()
   }
 
+  /// Returns the refresh rate of this video mode in millihertz, e.g. `59940` for
+  /// a 59.94 Hz display.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows / iOS / Android:** The system only reports whole hertz, so this is
+  ///   always a multiple of 1000.
+  #[inline]
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.video_mode.refresh_rate_millihertz()
+  }
+
   /// Returns the monitor that this video mode is valid for. Each monitor has
   /// a separate set of valid video modes.
   #[inline]
@@ -0,0 +1,184 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(
+  feature = "headless",
+  any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+
+//! The virtual backend enabled by the `headless` feature, to test the event handling of an
+//! application without a display server, e.g. in CI.
+//!
+//! The backend replaces the one of the OS. Its windows only exist in memory: their setters change
+//! their state and send the events a window manager would send, e.g. `WindowEvent::Resized` after
+//! `Window::set_inner_size`, and `Window::request_redraw` sends `Event::RedrawRequested`. There is
+//! a single monitor of 1920x1080 pixels with a scale factor of 1, and the clipboard is shared by
+//! the process.
+//!
+//! The input of the user is simulated with an [`EventInjector`]:
+//!
+//! ```no_run
+//! use tao::{
+//!   event::{Event, WindowEvent},
+//!   event_loop::{ControlFlow, EventLoop},
+//!   platform::headless::EventLoopWindowTargetExtHeadless,
+//!   window::WindowBuilder,
+//! };
+//!
+//! let event_loop = EventLoop::new();
+//! let window = WindowBuilder::new().build(&event_loop).unwrap();
+//! event_loop.event_injector().request_close(window.id());
+//!
+//! event_loop.run(move |event, _, control_flow| {
+//!   *control_flow = ControlFlow::Wait;
+//!   if let Event::WindowEvent {
+//!     event: WindowEvent::CloseRequested { response },
+//!     ..
+//!   } = event
+//!   {
+//!     response.allow();
+//!     *control_flow = ControlFlow::Exit;
+//!   }
+//! });
+//! ```
+//!
+//! The event loop can be created and run on any thread, so tests can run in parallel.
+
+use instant::Instant;
+use std::sync::Arc;
+
+use crate::{
+  event::{CloseResponse, DeviceEvent, DeviceId, ElementState, Event, KeyEvent, WindowEvent},
+  event_loop::EventLoopWindowTarget,
+  keyboard::{Key, KeyCode, KeyLocation},
+  platform_impl::{self, Backend, CloseHandle, KeyEventExtra},
+  window::WindowId,
+};
+
+/// Additional methods on `EventLoopWindowTarget` that are specific to the headless backend.
+pub trait EventLoopWindowTargetExtHeadless {
+  /// Returns an injector of events into this event loop.
+  fn event_injector(&self) -> EventInjector;
+}
+
+impl<T> EventLoopWindowTargetExtHeadless for EventLoopWindowTarget<T> {
+  fn event_injector(&self) -> EventInjector {
+    EventInjector {
+      backend: self.p.backend.clone(),
+    }
+  }
+}
+
+/// Sends synthetic events to an event loop, like the OS would after an input of the user.
+///
+/// The events are sent in the next iteration of the event loop, which is woken up if it's waiting.
+/// The injector can be cloned and sent to other threads.
+///
+/// The window events update the state of their window when they are injected, e.g. the size of a
+/// window is the one of an injected `WindowEvent::Resized` right away.
+#[derive(Debug, Clone)]
+pub struct EventInjector {
+  backend: Arc<Backend>,
+}
+
+impl EventInjector {
+  /// Returns the id of the virtual device of the injected input events.
+  pub fn device_id(&self) -> DeviceId {
+    DeviceId(platform_impl::DeviceId::dummy())
+  }
+
+  /// Sends `event` to the event loop.
+  ///
+  /// Returns the event back if it's an `Event::UserEvent`, which is sent with an
+  /// [`EventLoopProxy`](crate::event_loop::EventLoopProxy) instead.
+  pub fn inject_event<T>(&self, event: Event<'static, T>) -> Result<(), Event<'static, T>> {
+    self.backend.inject(event.map_nonuser_event()?);
+    Ok(())
+  }
+
+  /// Sends `event` to the window with the id `window_id`.
+  pub fn inject_window_event(&self, window_id: WindowId, event: WindowEvent<'static>) {
+    self.backend.inject(Event::WindowEvent {
+      window_id,
+      event,
+      timestamp: Instant::now(),
+    });
+  }
+
+  /// Sends `event` from the virtual device.
+  ///
+  /// The event is dropped if the device events are filtered out with
+  /// `DeviceEventFilter::Always`. The backend has no focus of its own, so the other filters let
+  /// every event through.
+  pub fn inject_device_event(&self, event: DeviceEvent) {
+    self.backend.inject(Event::DeviceEvent {
+      device_id: self.device_id(),
+      event,
+      timestamp: Instant::now(),
+    });
+  }
+
+  /// Sends a key press or release to the window with the id `window_id`.
+  ///
+  /// The text of the key is the one of `logical_key` when it's pressed and is a character.
+  pub fn inject_keyboard_input(
+    &self,
+    window_id: WindowId,
+    physical_key: KeyCode,
+    logical_key: Key<'static>,
+    state: ElementState,
+  ) {
+    let text = match (&logical_key, state) {
+      (Key::Character(text), ElementState::Pressed) => Some(*text),
+      _ => None,
+    };
+    let event = KeyEvent {
+      physical_key,
+      logical_key: logical_key.clone(),
+      text,
+      location: KeyLocation::Standard,
+      state,
+      repeat: false,
+      compose_state: None,
+      platform_specific: KeyEventExtra {
+        text_with_all_modifiers: text,
+        key_without_modifiers: logical_key,
+      },
+    };
+    self.inject_window_event(
+      window_id,
+      WindowEvent::KeyboardInput {
+        device_id: self.device_id(),
+        event,
+        is_synthetic: false,
+      },
+    );
+  }
+
+  /// Sends `WindowEvent::CloseRequested` to the window with the id `window_id`, like when the
+  /// user clicks its close button.
+  ///
+  /// Allowing the request sends `WindowEvent::Destroyed`.
+  pub fn request_close(&self, window_id: WindowId) {
+    let handle = CloseHandle::new(self.backend.clone(), window_id.0);
+    self.inject_window_event(
+      window_id,
+      WindowEvent::CloseRequested {
+        response: CloseResponse::new(window_id, handle),
+      },
+    );
+  }
+
+  /// Sends `Event::ExitRequested`, like when the user ends the session.
+  pub fn request_exit(&self) {
+    self.backend.request_exit();
+  }
+}
@@ -1,17 +1,17 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-#![cfg(target_os = "macos")]
+#![cfg(all(not(feature = "headless"), target_os = "macos"))]
 
 use std::os::raw::c_void;
 
 use crate::{
-  dpi::LogicalSize,
+  dpi::{LogicalPosition, LogicalSize},
   event_loop::{EventLoop, EventLoopWindowTarget},
   menu::CustomMenuItem,
   monitor::MonitorHandle,
-  platform_impl::{get_aux_state_mut, Parent},
-  window::{Window, WindowBuilder},
+  platform_impl::{get_aux_state_mut, Parent, VisualEffect},
+  window::{Color, ProgressState, UserAttentionType, Window, WindowBuilder},
 };
 
 #[cfg(feature = "tray")]
@@ -48,6 +48,57 @@ pub trait WindowExtMacOS {
 
   /// Sets whether or not the window has shadow.
   fn set_has_shadow(&self, has_shadow: bool);
+
+  /// Draws an `NSVisualEffectView` with the given properties behind the content of the window,
+  /// replacing any effect set previously.
+  ///
+  /// The window should be transparent for the effect to be visible.
+  fn set_vibrancy(
+    &self,
+    material: NSVisualEffectMaterial,
+    blending_mode: NSVisualEffectBlendingMode,
+    state: NSVisualEffectState,
+  );
+
+  /// Removes the `NSVisualEffectView` drawn behind the content of the window, if any.
+  fn remove_vibrancy(&self);
+
+  /// Moves the close, minimize and zoom buttons so that the close button is `inset` away from
+  /// the top left corner of the window.
+  ///
+  /// The position is kept when the window is resized and when it exits fullscreen.
+  fn set_traffic_light_inset(&self, inset: LogicalPosition<f64>);
+
+  /// Tints the titlebar with `color`, or restores the titlebar of the system when `None`.
+  ///
+  /// The titlebar is made transparent and the background color of the window shows through
+  /// it, so `color` is also drawn behind the parts of the content that aren't opaque.
+  fn set_titlebar_color(&self, color: Option<Color>);
+
+  /// Returns the identifier of the tab group of the window.
+  fn tabbing_identifier(&self) -> String;
+
+  /// Moves the window to the tab group `identifier`, see
+  /// [`WindowBuilderExtMacOS::with_tabbing_identifier`].
+  fn set_tabbing_identifier(&self, identifier: &str);
+
+  /// Selects the tab after the one of the window in its tab group, or the first tab if it's the
+  /// last one.
+  fn select_next_tab(&self);
+
+  /// Selects the tab before the one of the window in its tab group, or the last tab if it's the
+  /// first one.
+  fn select_previous_tab(&self);
+
+  /// Merges all the windows with the same tabbing identifier into tabs of the window.
+  fn merge_all_windows(&self);
+
+  /// Enables or disables the animations of the window, e.g. when it is zoomed, restored or
+  /// ordered in and out. They are enabled by default.
+  ///
+  /// Disabling them lets windows with a custom titlebar play their own animations without the
+  /// system animating the window as well.
+  fn set_transition_animations(&self, enabled: bool);
 }
 
 impl WindowExtMacOS for Window {
@@ -80,6 +131,61 @@ impl WindowExtMacOS for Window {
   fn set_has_shadow(&self, has_shadow: bool) {
     self.window.set_has_shadow(has_shadow)
   }
+
+  #[inline]
+  fn set_vibrancy(
+    &self,
+    material: NSVisualEffectMaterial,
+    blending_mode: NSVisualEffectBlendingMode,
+    state: NSVisualEffectState,
+  ) {
+    self.window.set_vibrancy(material, blending_mode, state)
+  }
+
+  #[inline]
+  fn remove_vibrancy(&self) {
+    self.window.remove_vibrancy()
+  }
+
+  #[inline]
+  fn set_traffic_light_inset(&self, inset: LogicalPosition<f64>) {
+    self.window.set_traffic_light_inset(inset)
+  }
+
+  #[inline]
+  fn set_titlebar_color(&self, color: Option<Color>) {
+    self.window.set_titlebar_color(color)
+  }
+
+  #[inline]
+  fn tabbing_identifier(&self) -> String {
+    self.window.tabbing_identifier()
+  }
+
+  #[inline]
+  fn set_tabbing_identifier(&self, identifier: &str) {
+    self.window.set_tabbing_identifier(identifier)
+  }
+
+  #[inline]
+  fn select_next_tab(&self) {
+    self.window.select_next_tab()
+  }
+
+  #[inline]
+  fn select_previous_tab(&self) {
+    self.window.select_previous_tab()
+  }
+
+  #[inline]
+  fn merge_all_windows(&self) {
+    self.window.merge_all_windows()
+  }
+
+  #[inline]
+  fn set_transition_animations(&self, enabled: bool) {
+    self.window.set_transition_animations(enabled)
+  }
 }
 
 /// Corresponds to `NSApplicationActivationPolicy`.
@@ -293,6 +399,106 @@ impl NativeImage {
   }
 }
 
+/// Corresponds to `NSVisualEffectMaterial`.
+///
+/// Some materials are only available on recent versions of macOS; older versions fall back to
+/// their closest equivalent.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(isize)]
+pub enum NSVisualEffectMaterial {
+  /// Corresponds to `NSVisualEffectMaterialAppearanceBased`.
+  ///
+  /// Deprecated since macOS 10.14, use a semantic material instead.
+  AppearanceBased = 0,
+  /// Corresponds to `NSVisualEffectMaterialLight`.
+  ///
+  /// Deprecated since macOS 10.14, use a semantic material instead.
+  Light = 1,
+  /// Corresponds to `NSVisualEffectMaterialDark`.
+  ///
+  /// Deprecated since macOS 10.14, use a semantic material instead.
+  Dark = 2,
+  /// Corresponds to `NSVisualEffectMaterialTitlebar`.
+  Titlebar = 3,
+  /// Corresponds to `NSVisualEffectMaterialSelection`.
+  Selection = 4,
+  /// Corresponds to `NSVisualEffectMaterialMenu`.
+  Menu = 5,
+  /// Corresponds to `NSVisualEffectMaterialPopover`.
+  Popover = 6,
+  /// Corresponds to `NSVisualEffectMaterialSidebar`.
+  Sidebar = 7,
+  /// Corresponds to `NSVisualEffectMaterialMediumLight`.
+  ///
+  /// Deprecated since macOS 10.14, use a semantic material instead.
+  MediumLight = 8,
+  /// Corresponds to `NSVisualEffectMaterialUltraDark`.
+  ///
+  /// Deprecated since macOS 10.14, use a semantic material instead.
+  UltraDark = 9,
+  /// Corresponds to `NSVisualEffectMaterialHeaderView`.
+  HeaderView = 10,
+  /// Corresponds to `NSVisualEffectMaterialSheet`.
+  Sheet = 11,
+  /// Corresponds to `NSVisualEffectMaterialWindowBackground`.
+  WindowBackground = 12,
+  /// Corresponds to `NSVisualEffectMaterialHUDWindow`.
+  HudWindow = 13,
+  /// Corresponds to `NSVisualEffectMaterialFullScreenUI`.
+  FullScreenUI = 15,
+  /// Corresponds to `NSVisualEffectMaterialToolTip`.
+  Tooltip = 17,
+  /// Corresponds to `NSVisualEffectMaterialContentBackground`.
+  ContentBackground = 18,
+  /// Corresponds to `NSVisualEffectMaterialUnderWindowBackground`.
+  UnderWindowBackground = 21,
+  /// Corresponds to `NSVisualEffectMaterialUnderPageBackground`.
+  UnderPageBackground = 22,
+}
+
+impl Default for NSVisualEffectMaterial {
+  fn default() -> Self {
+    NSVisualEffectMaterial::WindowBackground
+  }
+}
+
+/// Corresponds to `NSVisualEffectBlendingMode`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(isize)]
+pub enum NSVisualEffectBlendingMode {
+  /// Corresponds to `NSVisualEffectBlendingModeBehindWindow`.
+  BehindWindow = 0,
+  /// Corresponds to `NSVisualEffectBlendingModeWithinWindow`.
+  WithinWindow = 1,
+}
+
+impl Default for NSVisualEffectBlendingMode {
+  fn default() -> Self {
+    NSVisualEffectBlendingMode::BehindWindow
+  }
+}
+
+/// Corresponds to `NSVisualEffectState`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(isize)]
+pub enum NSVisualEffectState {
+  /// Corresponds to `NSVisualEffectStateFollowsWindowActiveState`.
+  FollowsWindowActiveState = 0,
+  /// Corresponds to `NSVisualEffectStateActive`.
+  Active = 1,
+  /// Corresponds to `NSVisualEffectStateInactive`.
+  Inactive = 2,
+}
+
+impl Default for NSVisualEffectState {
+  fn default() -> Self {
+    NSVisualEffectState::FollowsWindowActiveState
+  }
+}
+
 /// Additional methods on `WindowBuilder` that are specific to MacOS.
 ///
 /// **Note:** Properties dealing with the titlebar will be overwritten by the `with_decorations` method
@@ -305,6 +511,9 @@ impl NativeImage {
 ///  - `with_fullsize_content_view`
 pub trait WindowBuilderExtMacOS {
   /// Sets a parent to the window to be created.
+  ///
+  /// [`WindowBuilder::with_parent_window`] takes precedence in method calls, so this must be
+  /// called as `WindowBuilderExtMacOS::with_parent_window(builder, ns_window)`.
   fn with_parent_window(self, parent: *mut c_void) -> WindowBuilder;
   /// Enables click-and-drag behavior for the entire window, not just the titlebar.
   fn with_movable_by_window_background(self, movable_by_window_background: bool) -> WindowBuilder;
@@ -322,6 +531,24 @@ pub trait WindowBuilderExtMacOS {
   fn with_resize_increments(self, increments: LogicalSize<f64>) -> WindowBuilder;
   fn with_disallow_hidpi(self, disallow_hidpi: bool) -> WindowBuilder;
   fn with_has_shadow(self, has_shadow: bool) -> WindowBuilder;
+  /// Draws an `NSVisualEffectView` behind the content of the window.
+  /// The window should be transparent for the effect to be visible.
+  fn with_vibrancy(
+    self,
+    material: NSVisualEffectMaterial,
+    blending_mode: NSVisualEffectBlendingMode,
+    state: NSVisualEffectState,
+  ) -> WindowBuilder;
+  /// Moves the close, minimize and zoom buttons so that the close button is `inset` away from
+  /// the top left corner of the window. Useful with a transparent titlebar and a custom one.
+  fn with_traffic_light_inset(self, inset: LogicalPosition<f64>) -> WindowBuilder;
+  /// Groups the window with the other windows of the same `identifier`, which the system shows
+  /// as tabs of a single window when the user prefers tabs, or through the "Merge All Windows"
+  /// item of the "Window" menu.
+  ///
+  /// The tab bar of these windows has a "+" button which sends
+  /// [`WindowEvent::NewTabRequested`](crate::event::WindowEvent::NewTabRequested).
+  fn with_tabbing_identifier(self, identifier: &str) -> WindowBuilder;
 }
 
 impl WindowBuilderExtMacOS for WindowBuilder {
@@ -387,6 +614,29 @@ impl WindowBuilderExtMacOS for WindowBuilder {
     self.platform_specific.has_shadow = has_shadow;
     self
   }
+
+  #[inline]
+  fn with_vibrancy(
+    mut self,
+    material: NSVisualEffectMaterial,
+    blending_mode: NSVisualEffectBlendingMode,
+    state: NSVisualEffectState,
+  ) -> WindowBuilder {
+    self.platform_specific.vibrancy = Some(VisualEffect::new(material, blending_mode, state));
+    self
+  }
+
+  #[inline]
+  fn with_traffic_light_inset(mut self, inset: LogicalPosition<f64>) -> WindowBuilder {
+    self.platform_specific.traffic_light_inset = Some(inset);
+    self
+  }
+
+  #[inline]
+  fn with_tabbing_identifier(mut self, identifier: &str) -> WindowBuilder {
+    self.platform_specific.tabbing_identifier = Some(identifier.to_string());
+    self
+  }
 }
 
 pub trait EventLoopExtMacOS {
@@ -447,6 +697,16 @@ pub trait EventLoopWindowTargetExtMacOS {
   fn hide_application(&self);
   /// Hide the other applications. In most applications this is typically triggered with Command+Option-H.
   fn hide_other_applications(&self);
+  /// Sets the progress indicator drawn on the dock icon, without going through a window.
+  ///
+  /// See [`Window::set_progress_bar`].
+  fn set_dock_progress(&self, state: ProgressState, progress: Option<u64>);
+  /// Bounces the dock icon, once for [`UserAttentionType::Informational`] or until the
+  /// application is activated for [`UserAttentionType::Critical`]. A new request cancels the
+  /// previous one, and `None` cancels the bounce in progress.
+  ///
+  /// The dock icon doesn't bounce while the application is active.
+  fn request_dock_attention(&self, request_type: Option<UserAttentionType>);
 }
 
 impl<T> EventLoopWindowTargetExtMacOS for EventLoopWindowTarget<T> {
@@ -461,6 +721,14 @@ impl<T> EventLoopWindowTargetExtMacOS for EventLoopWindowTarget<T> {
     let app: cocoa::base::id = unsafe { msg_send![cls, sharedApplication] };
     unsafe { msg_send![app, hideOtherApplications: 0] }
   }
+
+  fn set_dock_progress(&self, state: ProgressState, progress: Option<u64>) {
+    self.p.set_progress_bar(state, progress.map(|p| p.min(100)))
+  }
+
+  fn request_dock_attention(&self, request_type: Option<UserAttentionType>) {
+    self.p.request_user_attention(request_type)
+  }
 }
 
 #[cfg(feature = "tray")]
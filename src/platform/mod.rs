@@ -9,18 +9,30 @@
 //!  - `ios`
 //!  - `macos`
 //!  - `unix`
+//!  - `web`
 //!  - `windows`
 //!
+//! With the `headless` feature, the OS-specific module of a desktop platform is replaced by
+//! `headless`.
+//!
 //! And the following platform-specific module:
 //!
 //! - `global_shortcut` (available on `windows`, `unix`, `macos`)
+//! - `pump_events` (available on `windows`, `unix`, and `macos`)
+//! - `run_async` (available on `windows`, `unix`, `macos`, and `android` with the `async` feature)
 //! - `run_return` (available on `windows`, `unix`, `macos`, and `android`)
+//! - `unix_signals` (available on `unix` and `macos`)
 //!
 //! However only the module corresponding to the platform you're compiling to will be available.
 
 pub mod android;
+pub mod headless;
 pub mod ios;
 pub mod macos;
+pub mod pump_events;
+pub mod run_async;
 pub mod run_return;
 pub mod unix;
+pub mod unix_signals;
+pub mod web;
 pub mod windows;
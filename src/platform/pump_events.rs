@@ -0,0 +1,71 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(any(
+  target_os = "windows",
+  target_os = "macos",
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+
+use std::time::Duration;
+
+use crate::{
+  event::Event,
+  event_loop::{should_dispatch, ControlFlow, EventLoop, EventLoopWindowTarget},
+};
+
+/// The state of the event loop after [`EventLoopExtPumpEvents::pump_events`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PumpStatus {
+  /// The event loop is still running, and `pump_events` should be called again.
+  Continue,
+  /// The event loop exited with the exit code of `ControlFlow::ExitWithCode`, and
+  /// `Event::LoopDestroyed` was sent. Pumping the events again starts the event loop over with
+  /// `StartCause::Init`.
+  Exit(i32),
+}
+
+/// Additional methods on `EventLoop` to drive it from an external loop, e.g. the main loop of a
+/// game engine or of a plugin host.
+pub trait EventLoopExtPumpEvents {
+  /// A type provided by the user that can be passed through `Event::UserEvent`.
+  type UserEvent;
+
+  /// Dispatches the pending events of the event loop to `event_handler`, and returns.
+  ///
+  /// If there are no pending events, it waits for new events for up to `timeout` first, or until
+  /// there are new events if `timeout` is `None`. A timeout of zero never blocks. The control
+  /// flow still decides which events are sent, like `StartCause::ResumeTimeReached` once a
+  /// `ControlFlow::WaitUntil` deadline is reached, but it doesn't make this function wait.
+  ///
+  /// # Caveats
+  /// Like [`run_return`], this function doesn't return while the OS runs its own event loop, e.g.
+  /// while a window is getting resized on Windows or macOS.
+  ///
+  /// [`run_return`]: crate::platform::run_return::EventLoopExtRunReturn::run_return
+  fn pump_events<F>(&mut self, timeout: Option<Duration>, event_handler: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, Self::UserEvent>, &EventLoopWindowTarget<Self::UserEvent>, &mut ControlFlow);
+}
+
+impl<T> EventLoopExtPumpEvents for EventLoop<T> {
+  type UserEvent = T;
+
+  fn pump_events<F>(&mut self, timeout: Option<Duration>, mut event_handler: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, Self::UserEvent>, &EventLoopWindowTarget<Self::UserEvent>, &mut ControlFlow),
+  {
+    let event_hook = &mut self.event_hook;
+    self
+      .event_loop
+      .pump_events(timeout, |event, window_target, control_flow| {
+        if should_dispatch(event_hook, &event) {
+          event_handler(event, window_target, control_flow)
+        }
+      })
+  }
+}
@@ -0,0 +1,224 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(
+  feature = "async",
+  any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "android",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::VecDeque,
+  future::Future,
+  pin::Pin,
+  ptr,
+  rc::Rc,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  task::{Context, Poll, Wake, Waker},
+};
+
+use futures_core::Stream;
+
+use crate::{
+  event::Event,
+  event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+  platform::run_return::EventLoopExtRunReturn,
+};
+
+/// Additional methods on `EventLoop` to drive an application with `async` code.
+pub trait EventLoopExtAsync {
+  /// A type provided by the user that can be passed through `Event::UserEvent`.
+  type UserEvent: 'static;
+
+  /// Runs the event loop until the future returned by `f` completes.
+  ///
+  /// The future receives the events of the event loop through an [`EventStream`], and is polled
+  /// on the thread of the event loop whenever it is woken up: when it awaits the next event, or
+  /// when a future it awaits completes on another thread, e.g. a request made with `tokio`. Its
+  /// waker wakes the event loop up with [`EventLoopProxy::wake_up`].
+  ///
+  /// The control flow of the event loop is `ControlFlow::Wait` until the future completes, then
+  /// the event loop exits like with `ControlFlow::Exit` and this function returns.
+  ///
+  /// # Caveats
+  /// The caveats of [`run_return`] apply to this function, since it runs the event loop with it.
+  ///
+  /// [`run_return`]: crate::platform::run_return::EventLoopExtRunReturn::run_return
+  fn run_async<F, Fut>(&mut self, f: F)
+  where
+    F: FnOnce(EventStream<Self::UserEvent>) -> Fut,
+    Fut: Future<Output = ()>;
+}
+
+impl<T: Send> EventLoopExtAsync for EventLoop<T> {
+  type UserEvent = T;
+
+  fn run_async<F, Fut>(&mut self, f: F)
+  where
+    F: FnOnce(EventStream<T>) -> Fut,
+    Fut: Future<Output = ()>,
+  {
+    let shared = Rc::new(Shared {
+      events: RefCell::new(VecDeque::new()),
+      closed: Cell::new(false),
+      waker: RefCell::new(None),
+      window_target: Cell::new(ptr::null()),
+    });
+    let mut future = Some(Box::pin(f(EventStream {
+      shared: shared.clone(),
+    })));
+
+    let loop_waker = Arc::new(LoopWaker {
+      woken: AtomicBool::new(true),
+      in_event_handler: AtomicBool::new(false),
+      proxy: Mutex::new(self.create_proxy()),
+    });
+    let waker = Waker::from(loop_waker.clone());
+
+    self.run_return(move |event, window_target, control_flow| {
+      *control_flow = ControlFlow::Wait;
+      loop_waker.in_event_handler.store(true, Ordering::SeqCst);
+
+      match event {
+        Event::LoopDestroyed => shared.closed.set(true),
+        event => {
+          // `ScaleFactorChanged` is left out, since its new size has to be set synchronously.
+          if let Some(event) = event.to_static() {
+            shared.events.borrow_mut().push_back(event);
+          }
+        }
+      }
+      if !shared.events.borrow().is_empty() || shared.closed.get() {
+        if let Some(waker) = shared.waker.borrow_mut().take() {
+          waker.wake();
+        }
+      }
+
+      if loop_waker.woken.swap(false, Ordering::SeqCst) {
+        if let Some(fut) = future.as_mut() {
+          shared
+            .window_target
+            .set(window_target as *const _ as *const ());
+          let poll = fut.as_mut().poll(&mut Context::from_waker(&waker));
+          shared.window_target.set(ptr::null());
+          if poll.is_ready() {
+            future = None;
+          }
+        }
+      }
+      if future.is_none() {
+        *control_flow = ControlFlow::Exit;
+      }
+
+      loop_waker.in_event_handler.store(false, Ordering::SeqCst);
+      // Catches the wakes from other threads while the event handler ran.
+      if loop_waker.woken.load(Ordering::SeqCst) {
+        loop_waker.wake_up();
+      }
+    });
+  }
+}
+
+/// A stream of the events of an event loop driven by [`EventLoopExtAsync::run_async`].
+///
+/// The events with a reference, i.e. `WindowEvent::ScaleFactorChanged`, aren't part of the stream.
+/// The stream ends after `Event::LoopDestroyed`, which isn't part of it either.
+pub struct EventStream<T: 'static> {
+  shared: Rc<Shared<T>>,
+}
+
+impl<T: 'static> EventStream<T> {
+  /// Returns a future that resolves to the next event, or `None` once the event loop exited.
+  ///
+  /// This is the `next` of `StreamExt` of the `futures` crate, without depending on it.
+  pub fn next_event(&mut self) -> NextEvent<'_, T> {
+    NextEvent { stream: self }
+  }
+
+  /// Calls `f` with the window target of the event loop, e.g. to create a window.
+  pub fn with_window_target<R>(&self, f: impl FnOnce(&EventLoopWindowTarget<T>) -> R) -> R {
+    let window_target = self.shared.window_target.get() as *const EventLoopWindowTarget<T>;
+    assert!(
+      !window_target.is_null(),
+      "The window target is only available while the future of `run_async` is polled."
+    );
+    // Safety: the pointer is only set while the event handler of `run_async` polls the future,
+    // during which the window target it was given stays borrowed.
+    f(unsafe { &*window_target })
+  }
+}
+
+impl<T: 'static> Stream for EventStream<T> {
+  type Item = Event<'static, T>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    if let Some(event) = self.shared.events.borrow_mut().pop_front() {
+      return Poll::Ready(Some(event));
+    }
+    if self.shared.closed.get() {
+      return Poll::Ready(None);
+    }
+    *self.shared.waker.borrow_mut() = Some(cx.waker().clone());
+    Poll::Pending
+  }
+}
+
+/// The future returned by [`EventStream::next_event`].
+pub struct NextEvent<'a, T: 'static> {
+  stream: &'a mut EventStream<T>,
+}
+
+impl<'a, T: 'static> Future for NextEvent<'a, T> {
+  type Output = Option<Event<'static, T>>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    Pin::new(&mut *self.stream).poll_next(cx)
+  }
+}
+
+struct Shared<T: 'static> {
+  events: RefCell<VecDeque<Event<'static, T>>>,
+  closed: Cell<bool>,
+  waker: RefCell<Option<Waker>>,
+  /// The `EventLoopWindowTarget<T>` given to the event handler while the future is polled.
+  window_target: Cell<*const ()>,
+}
+
+/// Wakes the future of `run_async` up, waking the event loop up if it's woken from outside of the
+/// event handler.
+struct LoopWaker<T: 'static> {
+  woken: AtomicBool,
+  in_event_handler: AtomicBool,
+  proxy: Mutex<EventLoopProxy<T>>,
+}
+
+impl<T: 'static> LoopWaker<T> {
+  fn wake_up(&self) {
+    self.proxy.lock().unwrap().wake_up();
+  }
+}
+
+impl<T: Send + 'static> Wake for LoopWaker<T> {
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    // The event handler polls the future before returning if it's woken while running.
+    if !self.woken.swap(true, Ordering::SeqCst) && !self.in_event_handler.load(Ordering::SeqCst) {
+      self.wake_up();
+    }
+  }
+}
@@ -1,11 +1,20 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-#![cfg(any(target_os = "windows", target_os = "macos", target_os = "android",))]
+#![cfg(any(
+  target_os = "windows",
+  target_os = "macos",
+  target_os = "android",
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
 
 use crate::{
   event::Event,
-  event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+  event_loop::{should_dispatch, ControlFlow, EventLoop, EventLoopWindowTarget},
 };
 
 /// Additional methods on `EventLoop` to return control flow to the caller.
@@ -16,7 +25,8 @@ pub trait EventLoopExtRunReturn {
   /// Initializes the `tao` event loop.
   ///
   /// Unlike `run`, this function accepts non-`'static` (i.e. non-`move`) closures and returns
-  /// control flow to the caller when `control_flow` is set to `ControlFlow::Exit`.
+  /// control flow to the caller when `control_flow` is set to `ControlFlow::ExitWithCode`, with
+  /// its exit code.
   ///
   /// # Caveats
   /// Despite its appearance at first glance, this is *not* a perfect replacement for
@@ -27,7 +37,7 @@ pub trait EventLoopExtRunReturn {
   /// underlying OS APIs, which cannot be hidden by `tao` without severe stability repercussions.
   ///
   /// You are strongly encouraged to use `run`, unless the use of this is absolutely necessary.
-  fn run_return<F>(&mut self, event_handler: F)
+  fn run_return<F>(&mut self, event_handler: F) -> i32
   where
     F: FnMut(Event<'_, Self::UserEvent>, &EventLoopWindowTarget<Self::UserEvent>, &mut ControlFlow);
 }
@@ -35,10 +45,17 @@ pub trait EventLoopExtRunReturn {
 impl<T> EventLoopExtRunReturn for EventLoop<T> {
   type UserEvent = T;
 
-  fn run_return<F>(&mut self, event_handler: F)
+  fn run_return<F>(&mut self, mut event_handler: F) -> i32
   where
     F: FnMut(Event<'_, Self::UserEvent>, &EventLoopWindowTarget<Self::UserEvent>, &mut ControlFlow),
   {
-    self.event_loop.run_return(event_handler)
+    let event_hook = &mut self.event_hook;
+    self
+      .event_loop
+      .run_return(|event, window_target, control_flow| {
+        if should_dispatch(event_hook, &event) {
+          event_handler(event, window_target, control_flow)
+        }
+      })
   }
 }
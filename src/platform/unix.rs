@@ -1,12 +1,16 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-#![cfg(any(
-  target_os = "linux",
-  target_os = "dragonfly",
-  target_os = "freebsd",
-  target_os = "netbsd",
-  target_os = "openbsd"
+#![cfg(all(
+  not(feature = "headless"),
+  not(feature = "wayland"),
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
 ))]
 
 pub use crate::platform_impl::hit_test;
@@ -18,6 +22,7 @@ pub trait WindowExtUnix {
   fn gtk_window(&self) -> &gtk::ApplicationWindow;
 
   /// Whether to show the window icon in the taskbar or not.
+  #[deprecated = "Deprecated in favor of Window::set_skip_taskbar"]
   fn set_skip_taskbar(&self, skip: bool);
 }
 
@@ -27,7 +32,7 @@ impl WindowExtUnix for Window {
   }
 
   fn set_skip_taskbar(&self, skip: bool) {
-    self.window.set_skip_taskbar(skip);
+    Window::set_skip_taskbar(self, skip);
   }
 }
 
@@ -0,0 +1,77 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(any(
+  target_os = "macos",
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+
+use crate::event_loop::EventLoop;
+
+/// A signal asking the process to terminate.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitSignal {
+  /// `SIGTERM`, e.g. sent by a service manager or by `kill`.
+  Terminate,
+  /// `SIGINT`, e.g. sent by `Ctrl+C` in a terminal.
+  Interrupt,
+  /// `SIGHUP`, e.g. sent when the terminal is closed.
+  Hangup,
+}
+
+impl ExitSignal {
+  #[cfg_attr(feature = "headless", allow(dead_code))]
+  pub(crate) fn signum(self) -> i32 {
+    match self {
+      ExitSignal::Terminate => libc::SIGTERM,
+      ExitSignal::Interrupt => libc::SIGINT,
+      ExitSignal::Hangup => libc::SIGHUP,
+    }
+  }
+}
+
+/// The event an [`ExitSignal`] is turned into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignalAction<T> {
+  /// Sends `Event::ExitRequested`, so the event loop exits unless the exit is prevented.
+  ExitRequested,
+  /// Sends `Event::UserEvent` with a clone of the user event, so the application decides what to
+  /// do.
+  UserEvent(T),
+}
+
+/// Additional methods on `EventLoop` to shut down gracefully when the process is asked to
+/// terminate, e.g. by a service manager or a terminal.
+pub trait EventLoopExtUnixSignals {
+  /// A type provided by the user that can be passed through `Event::UserEvent`.
+  type UserEvent;
+
+  /// Turns `signal` into an event instead of terminating the process, or restores its default
+  /// action with `None`.
+  ///
+  /// The signals are received process-wide, so they shouldn't be handled by other code of the
+  /// process at the same time.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Headless:** Unsupported, the signals keep their default action.
+  fn set_signal_action(
+    &mut self,
+    signal: ExitSignal,
+    action: Option<SignalAction<Self::UserEvent>>,
+  );
+}
+
+impl<T: Clone> EventLoopExtUnixSignals for EventLoop<T> {
+  type UserEvent = T;
+
+  #[inline]
+  fn set_signal_action(&mut self, signal: ExitSignal, action: Option<SignalAction<T>>) {
+    self.event_loop.set_signal_action(signal, action)
+  }
+}
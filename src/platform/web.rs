@@ -0,0 +1,38 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(target_arch = "wasm32")]
+
+use web_sys::HtmlCanvasElement;
+
+use crate::window::{Window, WindowBuilder};
+
+/// Additional methods on `Window` that are specific to the web.
+pub trait WindowExtWeb {
+  /// Returns the `<canvas>` element the window draws to.
+  fn canvas(&self) -> HtmlCanvasElement;
+}
+
+impl WindowExtWeb for Window {
+  #[inline]
+  fn canvas(&self) -> HtmlCanvasElement {
+    self.window.canvas()
+  }
+}
+
+/// Additional methods on `WindowBuilder` that are specific to the web.
+pub trait WindowBuilderExtWeb {
+  /// Uses a `<canvas>` element already in the page instead of appending a new one to its
+  /// `<body>`.
+  ///
+  /// The canvas is left in the page when the window is dropped.
+  fn with_canvas(self, canvas: Option<HtmlCanvasElement>) -> WindowBuilder;
+}
+
+impl WindowBuilderExtWeb for WindowBuilder {
+  #[inline]
+  fn with_canvas(mut self, canvas: Option<HtmlCanvasElement>) -> WindowBuilder {
+    self.platform_specific.canvas = canvas;
+    self
+  }
+}
@@ -1,18 +1,18 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-#![cfg(target_os = "windows")]
+#![cfg(all(not(feature = "headless"), target_os = "windows"))]
 
 use std::{os::raw::c_void, path::Path};
 
 pub use crate::platform_impl::hit_test;
 use crate::{
-  dpi::PhysicalSize,
+  dpi::{PhysicalSize, Position, Size},
   event::DeviceId,
   event_loop::EventLoop,
   monitor::MonitorHandle,
   platform_impl::{EventLoop as WindowsEventLoop, Parent, WinIcon},
-  window::{BadIcon, Icon, Theme, Window, WindowBuilder},
+  window::{BadIcon, Color, Icon, Theme, Window, WindowBuilder},
 };
 use libc;
 use winapi::{
@@ -27,6 +27,11 @@ use winapi::{
 pub trait EventLoopExtWindows {
   /// Creates an event loop off of the main thread.
   ///
+  /// This is useful for libraries that are loaded in a host application which owns the main
+  /// thread, like a plugin. The event loop only handles the windows created on its own thread, so
+  /// several of them can run at once on different threads, and it can be created again after it
+  /// was dropped.
+  ///
   /// # `Window` caveats
   ///
   /// Note that any `Window` created on the new thread will be destroyed when the thread
@@ -48,6 +53,20 @@ pub trait EventLoopExtWindows {
   fn new_dpi_unaware_any_thread() -> Self
   where
     Self: Sized;
+
+  /// Sets a hook that sees the messages of the event loop thread before they are translated and
+  /// dispatched, replacing the previous one.
+  ///
+  /// The hook returns `true` if it consumed the message, which is then neither translated nor
+  /// dispatched, e.g. after a successful `TranslateAcceleratorW` or `IsDialogMessageW`. This is
+  /// needed by some third-party SDKs that have to see the messages of the application.
+  ///
+  /// The messages dispatched by the modal loops of the system, e.g. while a window is moved or
+  /// resized, or while a menu is open, don't go through the hook.
+  fn with_msg_hook<F>(self, msg_hook: F) -> Self
+  where
+    Self: Sized,
+    F: FnMut(*const winuser::MSG) -> bool + 'static;
 }
 
 impl<T> EventLoopExtWindows for EventLoop<T> {
@@ -55,6 +74,7 @@ impl<T> EventLoopExtWindows for EventLoop<T> {
   fn new_any_thread() -> Self {
     EventLoop {
       event_loop: WindowsEventLoop::new_any_thread(),
+      event_hook: None,
       _marker: ::std::marker::PhantomData,
     }
   }
@@ -63,6 +83,7 @@ impl<T> EventLoopExtWindows for EventLoop<T> {
   fn new_dpi_unaware() -> Self {
     EventLoop {
       event_loop: WindowsEventLoop::new_dpi_unaware(),
+      event_hook: None,
       _marker: ::std::marker::PhantomData,
     }
   }
@@ -71,9 +92,19 @@ impl<T> EventLoopExtWindows for EventLoop<T> {
   fn new_dpi_unaware_any_thread() -> Self {
     EventLoop {
       event_loop: WindowsEventLoop::new_dpi_unaware_any_thread(),
+      event_hook: None,
       _marker: ::std::marker::PhantomData,
     }
   }
+
+  #[inline]
+  fn with_msg_hook<F>(mut self, msg_hook: F) -> Self
+  where
+    F: FnMut(*const winuser::MSG) -> bool + 'static,
+  {
+    self.event_loop.set_msg_hook(Box::new(msg_hook));
+    self
+  }
 }
 
 /// Additional methods on `Window` that are specific to Windows.
@@ -103,6 +134,13 @@ pub trait WindowExtWindows {
   /// This sets `ICON_BIG`. A good ceiling here is 256x256.
   fn set_taskbar_icon(&self, taskbar_icon: Option<Icon>);
 
+  /// Sets a small overlay icon, like an unread badge, on the bottom right of the taskbar button.
+  /// Providing `None` removes the overlay icon.
+  ///
+  /// The overlay is rendered at 16x16, it's recommended to account for screen scaling and
+  /// provide a multiple of that, i.e. 32x32.
+  fn set_taskbar_overlay_icon(&self, icon: Option<Icon>);
+
   /// Returns the current window theme.
   fn theme(&self) -> Theme;
 
@@ -117,7 +155,60 @@ pub trait WindowExtWindows {
   fn begin_resize_drag(&self, edge: isize);
 
   /// Whether to show the window icon in the taskbar or not.
+  #[deprecated = "Deprecated in favor of Window::set_skip_taskbar"]
   fn set_skip_taskbar(&self, skip: bool);
+
+  /// Sets the rounded corner preference of the window.
+  ///
+  /// This is a no-op on versions of Windows prior to Windows 11.
+  fn set_corner_preference(&self, preference: CornerPreference);
+
+  /// Cloaks or uncloaks the window. A cloaked window keeps being visible and painted, but
+  /// DWM doesn't draw it on screen. See [`WindowBuilderExtWindows::with_cloaked`].
+  fn set_cloaked(&self, cloaked: bool);
+
+  /// Sets the area of the client area, given as its position and size, that acts as the
+  /// maximize button of a custom titlebar, or `None` to remove it.
+  ///
+  /// Hovering the area shows the snap layouts flyout on Windows 11, and clicking it maximizes
+  /// or restores the window. Like [`DragRegionKind::MaximizeButton`] regions, it only applies
+  /// to undecorated windows, and takes precedence over the drag regions.
+  ///
+  /// [`DragRegionKind::MaximizeButton`]: crate::window::DragRegionKind::MaximizeButton
+  fn set_snap_layout_button_rect(&self, rect: Option<(Position, Size)>);
+
+  /// Sets the background color of the titlebar, or restores the color of the system theme when
+  /// `None`. The alpha channel is ignored.
+  ///
+  /// This is a no-op on versions of Windows prior to Windows 11.
+  fn set_titlebar_color(&self, color: Option<Color>);
+
+  /// Sets the color of the title, or restores the color of the system theme when `None`. The
+  /// alpha channel is ignored.
+  ///
+  /// This is a no-op on versions of Windows prior to Windows 11.
+  fn set_titlebar_text_color(&self, color: Option<Color>);
+
+  /// Enables or disables the transition animations of DWM, e.g. when the window is maximized,
+  /// restored, minimized or shown. They are enabled by default.
+  ///
+  /// Disabling them lets windows with a custom titlebar play their own animations without the
+  /// system animating the window as well.
+  fn set_transition_animations(&self, enabled: bool);
+
+  /// Shows the touch keyboard of the system, e.g. when a text field drawn by the application
+  /// gets the focus. [`WindowEvent::TouchKeyboardOcclusion`] tells which part of the window the
+  /// keyboard covers.
+  ///
+  /// The keyboard is only shown automatically for the text controls of the system, and in tablet
+  /// mode or without a physical keyboard attached.
+  ///
+  /// [`WindowEvent::TouchKeyboardOcclusion`]: crate::event::WindowEvent::TouchKeyboardOcclusion
+  fn show_touch_keyboard(&self);
+
+  /// Hides the touch keyboard of the system, shown by [`Self::show_touch_keyboard`] or by the
+  /// user.
+  fn hide_touch_keyboard(&self);
 }
 
 impl WindowExtWindows for Window {
@@ -143,6 +234,11 @@ impl WindowExtWindows for Window {
     self.window.set_taskbar_icon(taskbar_icon)
   }
 
+  #[inline]
+  fn set_taskbar_overlay_icon(&self, icon: Option<Icon>) {
+    self.window.set_overlay_icon(icon)
+  }
+
   #[inline]
   fn theme(&self) -> Theme {
     self.window.theme()
@@ -174,7 +270,77 @@ impl WindowExtWindows for Window {
 
   #[inline]
   fn set_skip_taskbar(&self, skip: bool) {
-    self.window.set_skip_taskbar(skip, true);
+    Window::set_skip_taskbar(self, skip);
+  }
+
+  #[inline]
+  fn set_corner_preference(&self, preference: CornerPreference) {
+    self.window.set_corner_preference(preference);
+  }
+
+  #[inline]
+  fn set_cloaked(&self, cloaked: bool) {
+    self.window.set_cloaked(cloaked);
+  }
+
+  #[inline]
+  fn set_snap_layout_button_rect(&self, rect: Option<(Position, Size)>) {
+    self.window.set_snap_layout_button_rect(rect);
+  }
+
+  #[inline]
+  fn set_titlebar_color(&self, color: Option<Color>) {
+    self.window.set_titlebar_color(color);
+  }
+
+  #[inline]
+  fn set_titlebar_text_color(&self, color: Option<Color>) {
+    self.window.set_titlebar_text_color(color);
+  }
+
+  #[inline]
+  fn set_transition_animations(&self, enabled: bool) {
+    self.window.set_transition_animations(enabled);
+  }
+
+  #[inline]
+  fn show_touch_keyboard(&self) {
+    self.window.show_touch_keyboard();
+  }
+
+  #[inline]
+  fn hide_touch_keyboard(&self) {
+    self.window.hide_touch_keyboard();
+  }
+}
+
+/// The rounded corner preference of a window on Windows 11.
+///
+/// Corresponds to `DWM_WINDOW_CORNER_PREFERENCE`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CornerPreference {
+  /// Let the system decide whether or not to round the corners of the window.
+  ///
+  /// Corresponds to `DWMWCP_DEFAULT`.
+  Default,
+  /// Never round the corners of the window.
+  ///
+  /// Corresponds to `DWMWCP_DONOTROUND`.
+  DoNotRound,
+  /// Round the corners of the window if appropriate.
+  ///
+  /// Corresponds to `DWMWCP_ROUND`.
+  Round,
+  /// Round the corners of the window if appropriate, with a small radius.
+  ///
+  /// Corresponds to `DWMWCP_ROUNDSMALL`.
+  RoundSmall,
+}
+
+impl Default for CornerPreference {
+  fn default() -> Self {
+    CornerPreference::Default
   }
 }
 
@@ -185,6 +351,9 @@ pub trait WindowBuilderExtWindows {
   /// A child window has the WS_CHILD style and is confined to the client area of its parent window.
   ///
   /// For more information, see <https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#child-windows>
+  ///
+  /// [`WindowBuilder::with_parent_window`] takes precedence in method calls, so this must be
+  /// called as `WindowBuilderExtWindows::with_parent_window(builder, hwnd)`.
   fn with_parent_window(self, parent: HWND) -> WindowBuilder;
 
   /// Set an owner to the window to be created. Can be used to create a dialog box, for example.
@@ -228,6 +397,27 @@ pub trait WindowBuilderExtWindows {
 
   /// Whether to create the window icon with the taskbar icon or not.
   fn with_skip_taskbar(self, skip: bool) -> WindowBuilder;
+
+  /// Creates the window cloaked with `DWMWA_CLOAK`: it is shown and painted as usual, but isn't
+  /// drawn on screen until [`WindowExtWindows::set_cloaked(false)`](WindowExtWindows::set_cloaked)
+  /// is called.
+  ///
+  /// This avoids the white flash of a window that is shown before its content is ready.
+  fn with_cloaked(self, cloaked: bool) -> WindowBuilder;
+
+  /// Sets the name of the window class, `"Window Class"` by default.
+  ///
+  /// Tools that identify applications by their window class, like AutoHotkey, can then tell the
+  /// windows of the application apart from the windows of other tao applications. The class is
+  /// registered by tao, so the name must not be used by another window class of the process.
+  fn with_class_name(self, class_name: &str) -> WindowBuilder;
+
+  /// Adds raw `WS_*` styles and `WS_EX_*` extended styles to the ones set by tao.
+  ///
+  /// The styles are kept when tao updates the styles of the window, e.g. when it's made
+  /// resizable. Styles that conflict with the state of the window, like `WS_CHILD` on a
+  /// top-level window, lead to undefined behavior.
+  fn with_window_styles(self, style: u32, ex_style: u32) -> WindowBuilder;
 }
 
 impl WindowBuilderExtWindows for WindowBuilder {
@@ -278,6 +468,24 @@ impl WindowBuilderExtWindows for WindowBuilder {
     self.platform_specific.skip_taskbar = skip;
     self
   }
+
+  #[inline]
+  fn with_cloaked(mut self, cloaked: bool) -> WindowBuilder {
+    self.platform_specific.cloaked = cloaked;
+    self
+  }
+
+  #[inline]
+  fn with_class_name(mut self, class_name: &str) -> WindowBuilder {
+    self.platform_specific.class_name = class_name.to_string();
+    self
+  }
+
+  #[inline]
+  fn with_window_styles(mut self, style: u32, ex_style: u32) -> WindowBuilder {
+    self.platform_specific.extra_styles = (style, ex_style);
+    self
+  }
 }
 
 /// Additional methods on `MonitorHandle` that are specific to Windows.
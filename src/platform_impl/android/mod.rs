@@ -6,10 +6,12 @@ use crate::{
   accelerator::Accelerator,
   dpi::{PhysicalPosition, PhysicalSize, Position, Size},
   error, event,
-  event_loop::{self, ControlFlow},
+  event_loop::{self, ControlFlow, Priority, SendEventError},
   keyboard::{Key, KeyCode, KeyLocation, NativeKeyCode},
   menu::{CustomMenuItem, MenuId, MenuItem, MenuType},
-  monitor, window,
+  monitor,
+  user_event_queue::UserEventQueue,
+  window,
 };
 use ndk::{
   configuration::Configuration,
@@ -21,7 +23,10 @@ use ndk_sys::AKeyEvent_getKeyCode;
 use std::{
   collections::VecDeque,
   convert::TryInto,
-  sync::{Arc, Mutex, RwLock},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock,
+  },
   time::{Duration, Instant},
 };
 
@@ -30,8 +35,23 @@ pub use clipboard::Clipboard;
 
 lazy_static! {
   static ref CONFIG: RwLock<Configuration> = RwLock::new(Configuration::new());
+  /// The looper of the event loop, woken up by the redraw requests.
+  static ref LOOPER: Mutex<Option<ForeignLooper>> = Mutex::new(None);
+  /// When the redraw asked with `Window::request_redraw` is due.
+  static ref REDRAW_AT: Mutex<Option<Instant>> = Mutex::new(None);
 }
 
+/// The time until the requested redraw is due, if any.
+fn redraw_delay() -> Option<Duration> {
+  REDRAW_AT
+    .lock()
+    .unwrap()
+    .map(|at| at.saturating_duration_since(Instant::now()))
+}
+
+/// Set with `Window::set_mouse_coalescing`.
+static MOUSE_COALESCING: AtomicBool = AtomicBool::new(true);
+
 enum EventSource {
   Callback,
   InputQueue,
@@ -51,6 +71,21 @@ fn poll(poll: Poll) -> Option<EventSource> {
   }
 }
 
+/// Converts the time of an input event, in nanoseconds of `CLOCK_MONOTONIC`, to an `Instant`.
+fn event_timestamp(event_time: i64) -> Instant {
+  let now = Instant::now();
+  let mut ts = libc::timespec {
+    tv_sec: 0,
+    tv_nsec: 0,
+  };
+  if unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) } != 0 {
+    return now;
+  }
+  let uptime = ts.tv_sec as i64 * 1_000_000_000 + ts.tv_nsec as i64;
+  let age = Duration::from_nanos(uptime.saturating_sub(event_time).max(0) as u64);
+  now.checked_sub(age).unwrap_or(now)
+}
+
 // todo: implement android menubar
 #[derive(Debug, Clone)]
 pub struct MenuItemAttributes;
@@ -107,7 +142,7 @@ impl MenuItemAttributes {
 
 pub struct EventLoop<T: 'static> {
   window_target: event_loop::EventLoopWindowTarget<T>,
-  user_queue: Arc<Mutex<VecDeque<T>>>,
+  user_queue: Arc<UserEventQueue<T>>,
   first_event: Option<EventSource>,
   start_cause: event::StartCause,
   looper: ThreadLooper,
@@ -116,16 +151,21 @@ pub struct EventLoop<T: 'static> {
 
 macro_rules! call_event_handler {
   ( $event_handler:expr, $window_target:expr, $cf:expr, $event:expr ) => {{
-    if $cf != ControlFlow::Exit {
-      $event_handler($event, $window_target, &mut $cf);
+    if let ControlFlow::ExitWithCode(code) = $cf {
+      $event_handler($event, $window_target, &mut ControlFlow::ExitWithCode(code));
     } else {
-      $event_handler($event, $window_target, &mut ControlFlow::Exit);
+      $event_handler($event, $window_target, &mut $cf);
     }
   }};
 }
 
 impl<T: 'static> EventLoop<T> {
+  pub fn try_new() -> Result<Self, error::OsError> {
+    Ok(Self::new())
+  }
+
   pub fn new() -> Self {
+    *LOOPER.lock().unwrap() = ForeignLooper::for_thread();
     Self {
       window_target: event_loop::EventLoopWindowTarget {
         p: EventLoopWindowTarget {
@@ -133,7 +173,7 @@ impl<T: 'static> EventLoop<T> {
         },
         _marker: std::marker::PhantomData,
       },
-      user_queue: Default::default(),
+      user_queue: Arc::new(UserEventQueue::new()),
       first_event: None,
       start_cause: event::StartCause::Init,
       looper: ThreadLooper::for_thread().unwrap(),
@@ -146,11 +186,11 @@ impl<T: 'static> EventLoop<T> {
     F:
       'static + FnMut(event::Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
   {
-    self.run_return(event_handler);
-    ::std::process::exit(0);
+    let exit_code = self.run_return(event_handler);
+    ::std::process::exit(exit_code);
   }
 
-  pub fn run_return<F>(&mut self, mut event_handler: F)
+  pub fn run_return<F>(&mut self, mut event_handler: F) -> i32
   where
     F: FnMut(event::Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
   {
@@ -174,7 +214,7 @@ impl<T: 'static> EventLoop<T> {
               event_handler,
               self.window_target(),
               control_flow,
-              event::Event::Resumed
+              event::Event::Resumed(event::SuspendReason::Background)
             );
           }
           Event::WindowResized => resized = true,
@@ -184,7 +224,7 @@ impl<T: 'static> EventLoop<T> {
               event_handler,
               self.window_target(),
               control_flow,
-              event::Event::Suspended
+              event::Event::Suspended(event::SuspendReason::Background)
             );
           }
           Event::Pause => self.running = false,
@@ -203,6 +243,7 @@ impl<T: 'static> EventLoop<T> {
                   new_inner_size: &mut size,
                   scale_factor,
                 },
+                timestamp: Instant::now(),
               };
               call_event_handler!(event_handler, self.window_target(), control_flow, event);
             }
@@ -215,6 +256,7 @@ impl<T: 'static> EventLoop<T> {
               event::Event::WindowEvent {
                 window_id: window::WindowId(WindowId),
                 event: event::WindowEvent::Focused(true),
+                timestamp: Instant::now(),
               }
             );
           }
@@ -226,6 +268,7 @@ impl<T: 'static> EventLoop<T> {
               event::Event::WindowEvent {
                 window_id: window::WindowId(WindowId),
                 event: event::WindowEvent::Focused(false),
+                timestamp: Instant::now(),
               }
             );
           }
@@ -265,20 +308,71 @@ impl<T: 'static> EventLoop<T> {
                         }
                       };
 
+                      // The moves batched into this one, oldest first, if coalescing is disabled.
+                      if phase == event::TouchPhase::Moved
+                        && !MOUSE_COALESCING.load(Ordering::Relaxed)
+                      {
+                        for historical in motion_event.history() {
+                          let mut frame = Vec::new();
+                          let timestamp = event_timestamp(historical.event_time());
+                          for pointer in historical.pointers() {
+                            let touch = event::Touch {
+                              device_id,
+                              phase,
+                              location: PhysicalPosition {
+                                x: pointer.x() as _,
+                                y: pointer.y() as _,
+                              },
+                              id: pointer.pointer_id() as u64,
+                              force: None,
+                            };
+                            frame.push(touch);
+                            let event = event::Event::WindowEvent {
+                              window_id,
+                              event: event::WindowEvent::Touch(touch),
+                              timestamp,
+                            };
+                            call_event_handler!(
+                              event_handler,
+                              self.window_target(),
+                              control_flow,
+                              event
+                            );
+                          }
+                          let event = event::Event::WindowEvent {
+                            window_id,
+                            event: event::WindowEvent::TouchFrame(frame),
+                            timestamp,
+                          };
+                          call_event_handler!(
+                            event_handler,
+                            self.window_target(),
+                            control_flow,
+                            event
+                          );
+                        }
+                      }
+
+                      // A motion event holds the pointers of a frame.
+                      let mut frame = Vec::new();
+                      let timestamp = event_timestamp(motion_event.event_time());
                       for pointer in pointers {
                         let location = PhysicalPosition {
                           x: pointer.x() as _,
                           y: pointer.y() as _,
                         };
+                        let touch = event::Touch {
+                          device_id,
+                          phase,
+                          location,
+                          id: pointer.pointer_id() as u64,
+                          force: None,
+                        };
+                        frame.push(touch);
                         let event = event::Event::WindowEvent {
                           window_id,
-                          event: event::WindowEvent::Touch(event::Touch {
-                            device_id,
-                            phase,
-                            location,
-                            id: pointer.pointer_id() as u64,
-                            force: None,
-                          }),
+                          event: event::WindowEvent::Touch(touch),
+                          timestamp,
                         };
                         call_event_handler!(
                           event_handler,
@@ -287,6 +381,12 @@ impl<T: 'static> EventLoop<T> {
                           event
                         );
                       }
+                      let event = event::Event::WindowEvent {
+                        window_id,
+                        event: event::WindowEvent::TouchFrame(frame),
+                        timestamp,
+                      };
+                      call_event_handler!(event_handler, self.window_target(), control_flow, event);
                     }
                   }
                   InputEvent::KeyEvent(key) => {
@@ -303,8 +403,9 @@ impl<T: 'static> EventLoop<T> {
                     let keycode = keycode_u32
                       .try_into()
                       .unwrap_or(ndk::event::Keycode::Unknown);
-                    let physical_key =
-                      KeyCode::Unidentified(NativeKeyCode::Android(keycode.into()));
+                    let physical_key = keycode_to_physical(keycode).unwrap_or(
+                      KeyCode::Unidentified(NativeKeyCode::Android(keycode.into())),
+                    );
                     let native = NativeKeyCode::Android(keycode_u32);
                     let logical_key = keycode_to_logical(keycode, native);
                     // TODO: maybe use getUnicodeChar to get the logical key
@@ -320,10 +421,12 @@ impl<T: 'static> EventLoop<T> {
                           location: keycode_to_location(keycode),
                           repeat: key.repeat_count() > 0,
                           text: None,
+                          compose_state: None,
                           platform_specific: KeyEventExtra {},
                         },
                         is_synthetic: false,
                       },
+                      timestamp: event_timestamp(key.event_time()),
                     };
                     call_event_handler!(event_handler, self.window_target(), control_flow, event);
                   }
@@ -334,8 +437,7 @@ impl<T: 'static> EventLoop<T> {
           }
         }
         Some(EventSource::User) => {
-          let mut user_queue = self.user_queue.lock().unwrap();
-          while let Some(event) = user_queue.pop_front() {
+          while let Some(event) = self.user_queue.pop() {
             call_event_handler!(
               event_handler,
               self.window_target(),
@@ -359,10 +461,19 @@ impl<T: 'static> EventLoop<T> {
         let event = event::Event::WindowEvent {
           window_id: window::WindowId(WindowId),
           event: event::WindowEvent::Resized(size),
+          timestamp: Instant::now(),
         };
         call_event_handler!(event_handler, self.window_target(), control_flow, event);
       }
 
+      {
+        let mut redraw_at = REDRAW_AT.lock().unwrap();
+        if redraw_at.map_or(false, |at| at <= Instant::now()) {
+          *redraw_at = None;
+          redraw = true;
+        }
+      }
+
       if redraw && self.running {
         let event = event::Event::RedrawRequested(window::WindowId(WindowId));
         call_event_handler!(event_handler, self.window_target(), control_flow, event);
@@ -375,8 +486,12 @@ impl<T: 'static> EventLoop<T> {
         event::Event::RedrawEventsCleared
       );
 
+      if let Some(event) = event::Event::idle(control_flow) {
+        call_event_handler!(event_handler, self.window_target(), control_flow, event);
+      }
+
       match control_flow {
-        ControlFlow::Exit => {
+        ControlFlow::ExitWithCode(exit_code) => {
           self.first_event = poll(
             self
               .looper
@@ -387,7 +502,7 @@ impl<T: 'static> EventLoop<T> {
             start: Instant::now(),
             requested_resume: None,
           };
-          break 'event_loop;
+          break 'event_loop exit_code;
         }
         ControlFlow::Poll => {
           self.first_event = poll(
@@ -399,7 +514,11 @@ impl<T: 'static> EventLoop<T> {
           self.start_cause = event::StartCause::Poll;
         }
         ControlFlow::Wait => {
-          self.first_event = poll(self.looper.poll_all().unwrap());
+          // Waits for the delayed redraw, if any.
+          self.first_event = match redraw_delay() {
+            Some(delay) => poll(self.looper.poll_all_timeout(delay).unwrap()),
+            None => poll(self.looper.poll_all().unwrap()),
+          };
           self.start_cause = event::StartCause::WaitCancelled {
             start: Instant::now(),
             requested_resume: None,
@@ -412,8 +531,9 @@ impl<T: 'static> EventLoop<T> {
           } else {
             instant - start
           };
+          let duration = redraw_delay().map_or(duration, |delay| delay.min(duration));
           self.first_event = poll(self.looper.poll_all_timeout(duration).unwrap());
-          self.start_cause = if self.first_event.is_some() {
+          self.start_cause = if self.first_event.is_some() || Instant::now() < instant {
             event::StartCause::WaitCancelled {
               start,
               requested_resume: Some(instant),
@@ -439,19 +559,46 @@ impl<T: 'static> EventLoop<T> {
       looper: ForeignLooper::for_thread().expect("called from event loop thread"),
     }
   }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.user_queue.set_capacity(priority, capacity);
+  }
 }
 
 pub struct EventLoopProxy<T: 'static> {
-  queue: Arc<Mutex<VecDeque<T>>>,
+  queue: Arc<UserEventQueue<T>>,
   looper: ForeignLooper,
 }
 
 impl<T> EventLoopProxy<T> {
   pub fn send_event(&self, event: T) -> Result<(), event_loop::EventLoopClosed<T>> {
-    self.queue.lock().unwrap().push_back(event);
+    self
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| event_loop::EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.queue.push(event, priority, bounded)?;
     self.looper.wake();
     Ok(())
   }
+
+  pub fn wake_up(&self) {
+    self.looper.wake();
+  }
 }
 
 impl<T> Clone for EventLoopProxy<T> {
@@ -479,6 +626,46 @@ impl<T: 'static> EventLoopWindowTarget<T> {
     v.push_back(MonitorHandle);
     v
   }
+
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    Vec::new()
+  }
+
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn key_from_scancode(&self, _scancode: u32) -> Option<crate::keyboard::Key<'static>> {
+    None
+  }
+
+  pub fn scancode_from_key(&self, _key: &crate::keyboard::Key<'_>) -> Option<u32> {
+    None
+  }
+
+  pub fn key_repeat_info(&self) -> Option<crate::keyboard::KeyRepeatInfo> {
+    None
+  }
+
+  pub fn modifiers_state(&self) -> crate::keyboard::ModifiersState {
+    crate::keyboard::ModifiersState::empty()
+  }
+
+  pub fn lock_key_state(&self, _key: crate::keyboard::LockKey) -> Option<bool> {
+    None
+  }
+
+  pub fn set_timer(&self, _interval: Duration, _id: event_loop::TimerId) {}
+
+  pub fn cancel_timer(&self, _id: event_loop::TimerId) {}
+
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  pub fn set_device_event_filter(&self, _filter: event_loop::DeviceEventFilter) {}
+
+  pub fn device_info(&self, _device_id: DeviceId) -> Option<crate::event::DeviceInfo> {
+    None
+  }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -493,6 +680,17 @@ impl WindowId {
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct DeviceId;
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VirtualDesktop;
+
+// Windows can't be closed by the user on Android.
+#[derive(Clone)]
+pub struct CloseHandle;
+
+impl CloseHandle {
+  pub fn close(&self) {}
+}
+
 impl DeviceId {
   pub fn dummy() -> Self {
     DeviceId
@@ -541,9 +739,22 @@ impl Window {
   }
 
   pub fn request_redraw(&self) {
-    // TODO
+    self.request_redraw_after(Duration::default());
+  }
+
+  pub fn request_redraw_after(&self, delay: Duration) {
+    let at = Instant::now() + delay;
+    {
+      let mut redraw_at = REDRAW_AT.lock().unwrap();
+      *redraw_at = Some(redraw_at.map_or(at, |redraw_at| redraw_at.min(at)));
+    }
+    if let Some(looper) = LOOPER.lock().unwrap().as_ref() {
+      looper.wake();
+    }
   }
 
+  pub fn set_redraw_pacing(&self, _pacing: window::RedrawPacing) {}
+
   pub fn inner_position(&self) -> Result<PhysicalPosition<i32>, error::NotSupportedError> {
     Err(error::NotSupportedError::new())
   }
@@ -572,27 +783,47 @@ impl Window {
 
   pub fn set_max_inner_size(&self, _: Option<Size>) {}
 
+  pub fn set_aspect_ratio(&self, _: Option<window::AspectRatio>) {}
+
+  pub fn set_resizable_edges(&self, _: window::Edges) {}
+
+  pub fn set_titlebar_buttons(&self, _: window::TitlebarButtons) {}
+
   pub fn set_title(&self, _title: &str) {}
 
   pub fn set_menu(&self, _menu: Option<Menu>) {}
 
   pub fn set_visible(&self, _visibility: bool) {}
 
+  pub fn show_without_activating(&self) {}
+
   pub fn set_focus(&self) {
     //FIXME: implementation goes here
     warn!("set_focus not yet implemented on Android");
   }
 
+  pub fn raise(&self) {}
+
+  pub fn set_above(&self, _window: &Window) {}
+
+  pub fn set_below(&self, _window: &Window) {}
+
   pub fn set_resizable(&self, _resizeable: bool) {}
 
   pub fn set_minimized(&self, _minimized: bool) {}
 
   pub fn set_maximized(&self, _maximized: bool) {}
 
+  pub fn set_maximized_on(&self, _monitor: monitor::MonitorHandle) {}
+
   pub fn is_maximized(&self) -> bool {
     false
   }
 
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    None
+  }
+
   pub fn is_visible(&self) -> bool {
     log::warn!("`Window::is_visible` is ignored on android");
     false
@@ -608,24 +839,81 @@ impl Window {
     false
   }
 
+  pub fn set_enabled(&self, _enabled: bool) {}
+
+  pub fn is_enabled(&self) -> bool {
+    true
+  }
+
   pub fn set_fullscreen(&self, _monitor: Option<window::Fullscreen>) {
     warn!("Cannot set fullscreen on Android");
   }
 
+  pub fn set_fullscreen_video_mode(
+    &self,
+    _video_mode: monitor::VideoMode,
+  ) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
   pub fn fullscreen(&self) -> Option<window::Fullscreen> {
     None
   }
 
+  pub fn set_kiosk(&self, _kiosk: bool) {}
+
   pub fn set_decorations(&self, _decorations: bool) {}
 
-  pub fn set_always_on_top(&self, _always_on_top: bool) {}
+  pub fn set_shadow(&self, _shadow: bool) {}
+
+  pub fn set_opacity(&self, _opacity: f32) {}
+
+  pub fn set_content_protected(&self, _protected: bool) {}
+
+  pub fn set_owner(&self, _owner: Option<WindowId>) {}
+
+  pub fn set_window_level(&self, _level: window::WindowLevel) {}
+
+  pub fn set_visible_on_all_workspaces(&self, _visible: bool) {}
+
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    true
+  }
+
+  pub fn move_to_virtual_desktop(&self, _: &VirtualDesktop) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
+  pub fn set_skip_taskbar(&self, _skip: bool) {}
+
+  pub fn set_minimize_to_tray(&self, _minimize_to_tray: bool) {}
+
+  pub fn set_theme(&self, _theme: Option<window::Theme>) {}
+
+  pub fn set_background_effect(&self, _effect: Option<window::Effect>) {}
 
   pub fn set_window_icon(&self, _window_icon: Option<crate::icon::Icon>) {}
 
-  pub fn set_ime_position(&self, _position: Position) {}
+  pub fn set_ime_cursor_area(&self, _position: Position, _size: Size) {}
+
+  pub fn set_ime_allowed(&self, _allowed: bool) {}
+
+  pub fn set_text_input(&self, _active: bool) {}
 
   pub fn request_user_attention(&self, _request_type: Option<window::UserAttentionType>) {}
 
+  pub fn set_progress_bar(&self, _state: window::ProgressState, _progress: Option<u64>) {}
+
+  pub fn set_badge_count(&self, _count: Option<u64>) {}
+
   pub fn hide_menu(&self) {}
 
   pub fn show_menu(&self) {}
@@ -635,6 +923,12 @@ impl Window {
     false
   }
 
+  pub fn capture_image(&self) -> Result<window::WindowImage, error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
   pub fn set_cursor_icon(&self, _: window::CursorIcon) {}
 
   pub fn set_cursor_position(&self, _: Position) -> Result<(), error::ExternalError> {
@@ -643,12 +937,44 @@ impl Window {
     ))
   }
 
-  pub fn set_cursor_grab(&self, _: bool) -> Result<(), error::ExternalError> {
+  pub fn set_cursor_grab(
+    &self,
+    _: crate::window::CursorGrabMode,
+  ) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
+  #[cfg(feature = "test")]
+  pub fn inject_event(&self, _: crate::test::SyntheticEvent) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
+  pub fn set_mouse_capture(&self, _: bool) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
+  pub fn set_keyboard_grab(&self, _: bool) -> Result<(), error::ExternalError> {
     Err(error::ExternalError::NotSupported(
       error::NotSupportedError::new(),
     ))
   }
 
+  pub fn set_ignore_cursor_events(&self, _: bool) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
+  pub fn set_mouse_coalescing(&self, enabled: bool) {
+    MOUSE_COALESCING.store(enabled, Ordering::Relaxed);
+  }
+
   pub fn set_cursor_visible(&self, _: bool) {}
 
   pub fn drag_window(&self) -> Result<(), error::ExternalError> {
@@ -657,6 +983,8 @@ impl Window {
     ))
   }
 
+  pub fn set_drag_region(&self, _regions: Vec<window::DragRegion>) {}
+
   pub fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
     let a_native_window = if let Some(native_window) = ndk_glue::native_window().as_ref() {
       unsafe { native_window.ptr().as_mut() as *mut _ as *mut _ }
@@ -757,6 +1085,10 @@ impl VideoMode {
     self.refresh_rate
   }
 
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate as u32 * 1000
+  }
+
   pub fn monitor(&self) -> monitor::MonitorHandle {
     monitor::MonitorHandle {
       inner: self.monitor.clone(),
@@ -764,6 +1096,27 @@ impl VideoMode {
   }
 }
 
+/// The physical key of the media and volume keys, whose position doesn't depend on the layout.
+fn keycode_to_physical(keycode: ndk::event::Keycode) -> Option<KeyCode> {
+  use ndk::event::Keycode::*;
+
+  Some(match keycode {
+    VolumeUp => KeyCode::AudioVolumeUp,
+    VolumeDown => KeyCode::AudioVolumeDown,
+    VolumeMute => KeyCode::AudioVolumeMute,
+    MediaPlayPause => KeyCode::MediaPlayPause,
+    MediaPlay => KeyCode::MediaPlay,
+    MediaPause => KeyCode::MediaPause,
+    MediaStop => KeyCode::MediaStop,
+    MediaNext => KeyCode::MediaTrackNext,
+    MediaPrevious => KeyCode::MediaTrackPrevious,
+    MediaRecord => KeyCode::MediaRecord,
+    MediaRewind => KeyCode::MediaRewind,
+    MediaFastForward => KeyCode::MediaFastForward,
+    _ => return None,
+  })
+}
+
 fn keycode_to_logical(keycode: ndk::event::Keycode, native: NativeKeyCode) -> Key<'static> {
   use ndk::event::Keycode::*;
 
@@ -1146,3 +1499,7 @@ pub fn keycode_to_scancode(_code: KeyCode) -> Option<u32> {
 pub fn keycode_from_scancode(_scancode: u32) -> KeyCode {
   KeyCode::Unidentified(NativeKeyCode::Unidentified)
 }
+
+pub fn current_keyboard_layout() -> Option<crate::keyboard::KeyboardLayout> {
+  None
+}
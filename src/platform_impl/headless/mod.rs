@@ -0,0 +1,1409 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! A virtual backend that doesn't need a display server, used instead of the backend of the OS
+//! with the `headless` feature.
+//!
+//! The windows only exist as state in memory, and the events come from the windows themselves,
+//! e.g. `Resized` after `set_inner_size`, or from an `EventInjector`.
+
+#[cfg(feature = "tray")]
+use crate::system_tray::SystemTray as RootSystemTray;
+use crate::{
+  accelerator::{Accelerator, AcceleratorId},
+  dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+  error,
+  event::{Event, Ime, StartCause, WindowEvent},
+  event_loop::{
+    self, ControlFlow, DeviceEventFilter, EventLoopClosed, Priority, SendEventError, TimerId,
+  },
+  global_shortcut::{GlobalShortcut as RootGlobalShortcut, ShortcutManagerError},
+  keyboard::{Key, KeyCode, ModifiersState, NativeKeyCode},
+  menu::{CustomMenuItem, MenuId, MenuItem, MenuType},
+  monitor,
+  platform::pump_events::PumpStatus,
+  user_event_queue::UserEventQueue,
+  window,
+};
+use std::{
+  cell::{Cell, RefCell},
+  collections::{HashMap, VecDeque},
+  fmt,
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Condvar, Mutex, Weak,
+  },
+  time::{Duration, Instant},
+};
+
+mod clipboard;
+pub use clipboard::Clipboard;
+
+pub(crate) use crate::icon::NoIcon as PlatformIcon;
+
+/// The size of the windows created without an inner size.
+const DEFAULT_INNER_SIZE: PhysicalSize<u32> = PhysicalSize {
+  width: 800,
+  height: 600,
+};
+
+/// The state shared by the event loop with its windows, proxies and injectors.
+pub(crate) struct Backend {
+  pending: Mutex<Pending>,
+  signal: Condvar,
+  windows: Mutex<HashMap<WindowId, Weak<Mutex<WindowState>>>>,
+}
+
+/// The id of the next window, counted across the event loops since the ids are global to the
+/// process, e.g. for the redraws of `crate::redraw`.
+static NEXT_WINDOW_ID: AtomicU32 = AtomicU32::new(0);
+
+/// The events waiting for the next iteration of the event loop.
+struct Pending {
+  /// The events of the "OS", which never are `Event::UserEvent`.
+  events: VecDeque<Event<'static, ()>>,
+  redraws: Vec<WindowId>,
+  /// The redraws delayed by the throttle of their window, with the time they're due.
+  delayed_redraws: Vec<(Instant, WindowId)>,
+  /// Whether `Event::ExitRequested` is to be sent.
+  exit_requested: bool,
+  woken: bool,
+}
+
+impl Pending {
+  fn is_empty(&self) -> bool {
+    self.events.is_empty() && self.redraws.is_empty() && !self.exit_requested && !self.woken
+  }
+
+  /// Moves the delayed redraws due at `now` to `redraws`.
+  fn take_due_redraws(&mut self, now: Instant) {
+    let redraws = &mut self.redraws;
+    self.delayed_redraws.retain(|&(due, window_id)| {
+      if due > now {
+        return true;
+      }
+      if !redraws.contains(&window_id) {
+        redraws.push(window_id);
+      }
+      false
+    });
+  }
+
+  fn next_delayed_redraw(&self) -> Option<Instant> {
+    self.delayed_redraws.iter().map(|&(due, _)| due).min()
+  }
+}
+
+impl Backend {
+  fn new() -> Self {
+    Backend {
+      pending: Mutex::new(Pending {
+        events: VecDeque::new(),
+        redraws: Vec::new(),
+        delayed_redraws: Vec::new(),
+        exit_requested: false,
+        woken: false,
+      }),
+      signal: Condvar::new(),
+      windows: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Queues `event`, applying it to the state of its window first, e.g. the size of `Resized`.
+  pub(crate) fn inject(&self, event: Event<'static, ()>) {
+    let mut received_text = None;
+    if let Event::WindowEvent {
+      window_id, event, ..
+    } = &event
+    {
+      if let Some(state) = self.window_state(window_id.0) {
+        received_text = state
+          .lock()
+          .unwrap()
+          .apply(event)
+          .map(|text| (window_id.0, text));
+      }
+    }
+    self.push(event);
+    if let Some((window_id, text)) = received_text {
+      self.push_window_event(window_id, WindowEvent::ReceivedText(text));
+    }
+  }
+
+  fn push(&self, event: Event<'static, ()>) {
+    self.pending.lock().unwrap().events.push_back(event);
+    self.signal.notify_one();
+  }
+
+  fn push_window_event(&self, window_id: WindowId, event: WindowEvent<'static>) {
+    self.push(Event::WindowEvent {
+      window_id: window::WindowId(window_id),
+      event,
+      timestamp: Instant::now(),
+    });
+  }
+
+  pub(crate) fn request_exit(&self) {
+    self.pending.lock().unwrap().exit_requested = true;
+    self.signal.notify_one();
+  }
+
+  fn request_redraw(&self, window_id: WindowId) {
+    let mut pending = self.pending.lock().unwrap();
+    if !pending.redraws.contains(&window_id) {
+      pending.redraws.push(window_id);
+    }
+    self.signal.notify_one();
+  }
+
+  fn request_redraw_after(&self, window_id: WindowId, delay: Duration) {
+    let mut pending = self.pending.lock().unwrap();
+    pending
+      .delayed_redraws
+      .push((Instant::now() + delay, window_id));
+    self.signal.notify_one();
+  }
+
+  fn wake_up(&self) {
+    self.pending.lock().unwrap().woken = true;
+    self.signal.notify_one();
+  }
+
+  fn window_state(&self, window_id: WindowId) -> Option<Arc<Mutex<WindowState>>> {
+    self
+      .windows
+      .lock()
+      .unwrap()
+      .get(&window_id)
+      .and_then(Weak::upgrade)
+  }
+}
+
+impl fmt::Debug for Backend {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad("Backend { .. }")
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct MenuItemAttributes;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct KeyEventExtra {
+  pub text_with_all_modifiers: Option<&'static str>,
+  pub key_without_modifiers: Key<'static>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Menu;
+
+impl Default for Menu {
+  fn default() -> Self {
+    Menu::new()
+  }
+}
+
+impl Menu {
+  pub fn new() -> Self {
+    Menu {}
+  }
+  pub fn new_popup_menu() -> Self {
+    Self::new()
+  }
+  pub fn add_item(
+    &mut self,
+    _menu_id: MenuId,
+    _title: &str,
+    _accelerator: Option<Accelerator>,
+    _enabled: bool,
+    _selected: bool,
+    _menu_type: MenuType,
+  ) -> CustomMenuItem {
+    CustomMenuItem(MenuItemAttributes {})
+  }
+  pub fn add_submenu(&mut self, _title: &str, _enabled: bool, _submenu: Menu) {}
+  pub fn add_native_item(
+    &mut self,
+    _item: MenuItem,
+    _menu_type: MenuType,
+  ) -> Option<CustomMenuItem> {
+    None
+  }
+}
+
+impl MenuItemAttributes {
+  pub fn id(self) -> MenuId {
+    MenuId::EMPTY
+  }
+  pub fn set_enabled(&mut self, _is_enabled: bool) {}
+  pub fn set_title(&mut self, _title: &str) {}
+  pub fn set_selected(&mut self, _is_selected: bool) {}
+  pub fn set_icon(&mut self, _icon: Vec<u8>) {}
+}
+
+pub struct EventLoop<T: 'static> {
+  window_target: event_loop::EventLoopWindowTarget<T>,
+  user_events: Arc<UserEventQueue<T>>,
+  control_flow: ControlFlow,
+  running: bool,
+}
+
+impl<T: 'static> EventLoop<T> {
+  pub fn try_new() -> Result<Self, error::OsError> {
+    Ok(Self::new())
+  }
+
+  pub fn new() -> Self {
+    Self {
+      window_target: event_loop::EventLoopWindowTarget {
+        p: EventLoopWindowTarget {
+          backend: Arc::new(Backend::new()),
+          timers: RefCell::new(HashMap::new()),
+          device_event_filter: Cell::new(DeviceEventFilter::default()),
+          _marker: std::marker::PhantomData,
+        },
+        _marker: std::marker::PhantomData,
+      },
+      user_events: Arc::new(UserEventQueue::new()),
+      control_flow: ControlFlow::default(),
+      running: false,
+    }
+  }
+
+  pub fn run<F>(mut self, event_handler: F) -> !
+  where
+    F: 'static + FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    let exit_code = self.run_return(event_handler);
+    ::std::process::exit(exit_code);
+  }
+
+  pub fn run_return<F>(&mut self, mut event_handler: F) -> i32
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    loop {
+      if let PumpStatus::Exit(exit_code) = self.pump_events(None, &mut event_handler) {
+        return exit_code;
+      }
+    }
+  }
+
+  pub fn pump_events<F>(&mut self, timeout: Option<Duration>, mut event_handler: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    let start_cause = if self.running {
+      match self.wait(timeout) {
+        Some(start_cause) => start_cause,
+        None => return PumpStatus::Continue,
+      }
+    } else {
+      self.running = true;
+      self.control_flow = ControlFlow::default();
+      StartCause::Init
+    };
+
+    self.dispatch_events(start_cause, &mut event_handler);
+
+    if let Some(exit_code) = self.control_flow.exit_code() {
+      self.call_event_handler(&mut event_handler, Event::LoopDestroyed);
+      self.running = false;
+      PumpStatus::Exit(exit_code)
+    } else {
+      PumpStatus::Continue
+    }
+  }
+
+  /// Waits for new events as the control flow asks, for up to `timeout`.
+  ///
+  /// Returns `None` if `timeout` elapsed first.
+  fn wait(&self, timeout: Option<Duration>) -> Option<StartCause> {
+    let start = Instant::now();
+    let requested_resume = match self.control_flow {
+      ControlFlow::Poll | ControlFlow::ExitWithCode(_) => return Some(StartCause::Poll),
+      ControlFlow::Wait => None,
+      ControlFlow::WaitUntil(requested_resume) => Some(requested_resume),
+    };
+    let next_timer = self.window_target.p.next_timer();
+
+    let backend = &self.window_target.p.backend;
+    let mut pending = backend.pending.lock().unwrap();
+    loop {
+      let now = Instant::now();
+      pending.take_due_redraws(now);
+      if !pending.is_empty() || next_timer.is_some_and(|timer| now >= timer) {
+        return Some(StartCause::WaitCancelled {
+          start,
+          requested_resume,
+        });
+      }
+      if let Some(requested_resume) = requested_resume.filter(|resume| now >= *resume) {
+        return Some(StartCause::ResumeTimeReached {
+          start,
+          requested_resume,
+        });
+      }
+      let deadline = [
+        requested_resume,
+        next_timer,
+        pending.next_delayed_redraw(),
+        timeout.map(|timeout| start + timeout),
+      ]
+      .iter()
+      .flatten()
+      .min()
+      .copied();
+      pending = match deadline {
+        Some(deadline) if now >= deadline => return None,
+        Some(deadline) => {
+          backend
+            .signal
+            .wait_timeout(pending, deadline - now)
+            .unwrap()
+            .0
+        }
+        None => backend.signal.wait(pending).unwrap(),
+      };
+    }
+  }
+
+  fn dispatch_events<F>(&mut self, start_cause: StartCause, event_handler: &mut F)
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    self.call_event_handler(event_handler, Event::NewEvents(start_cause));
+
+    for id in self.window_target.p.take_due_timers() {
+      self.call_event_handler(event_handler, Event::Timer(id));
+    }
+
+    let (events, exit_requested) = {
+      let mut pending = self.window_target.p.backend.pending.lock().unwrap();
+      pending.woken = false;
+      (
+        std::mem::take(&mut pending.events),
+        std::mem::take(&mut pending.exit_requested),
+      )
+    };
+    let device_event_filter = self.window_target.p.device_event_filter.get();
+    for event in events {
+      if let Event::DeviceEvent { .. } = event {
+        if device_event_filter == DeviceEventFilter::Always {
+          continue;
+        }
+      }
+      if let Ok(event) = event.map_nonuser_event() {
+        self.call_event_handler(event_handler, event);
+      }
+    }
+
+    while let Some(event) = self.user_events.pop() {
+      self.call_event_handler(event_handler, Event::UserEvent(event));
+    }
+
+    if exit_requested && self.control_flow.exit_code().is_none() {
+      let mut prevent = false;
+      self.call_event_handler(
+        event_handler,
+        Event::ExitRequested {
+          prevent: &mut prevent,
+        },
+      );
+      if !prevent && self.control_flow.exit_code().is_none() {
+        self.control_flow = ControlFlow::Exit;
+      }
+    }
+
+    self.call_event_handler(event_handler, Event::MainEventsCleared);
+
+    let redraws = {
+      let mut pending = self.window_target.p.backend.pending.lock().unwrap();
+      pending.take_due_redraws(Instant::now());
+      std::mem::take(&mut pending.redraws)
+    };
+    for window_id in redraws {
+      // The redraws requested before the window was destroyed are dropped with it.
+      let destroyed = self
+        .window_target
+        .p
+        .backend
+        .window_state(window_id)
+        .is_none_or(|state| state.lock().unwrap().destroyed);
+      if destroyed {
+        continue;
+      }
+      self.call_event_handler(
+        event_handler,
+        Event::RedrawRequested(window::WindowId(window_id)),
+      );
+    }
+
+    self.call_event_handler(event_handler, Event::RedrawEventsCleared);
+
+    if let Some(event) = Event::idle(self.control_flow) {
+      self.call_event_handler(event_handler, event);
+    }
+  }
+
+  /// Calls `event_handler`, keeping the control flow at `ControlFlow::ExitWithCode` once it's set.
+  fn call_event_handler<F>(&mut self, event_handler: &mut F, event: Event<'_, T>)
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    if self.control_flow.exit_code().is_none() {
+      event_handler(event, &self.window_target, &mut self.control_flow);
+    } else {
+      let mut control_flow = self.control_flow;
+      event_handler(event, &self.window_target, &mut control_flow);
+    }
+  }
+
+  pub fn window_target(&self) -> &event_loop::EventLoopWindowTarget<T> {
+    &self.window_target
+  }
+
+  #[cfg(any(
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  pub fn set_signal_action(
+    &mut self,
+    _signal: crate::platform::unix_signals::ExitSignal,
+    _action: Option<crate::platform::unix_signals::SignalAction<T>>,
+  ) {
+  }
+
+  pub fn create_proxy(&self) -> EventLoopProxy<T> {
+    EventLoopProxy {
+      user_events: self.user_events.clone(),
+      backend: self.window_target.p.backend.clone(),
+    }
+  }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.user_events.set_capacity(priority, capacity);
+  }
+}
+
+impl<T: 'static> Drop for EventLoop<T> {
+  fn drop(&mut self) {
+    self.user_events.close();
+  }
+}
+
+pub struct EventLoopProxy<T: 'static> {
+  user_events: Arc<UserEventQueue<T>>,
+  backend: Arc<Backend>,
+}
+
+impl<T> EventLoopProxy<T> {
+  pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+    self
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.user_events.push(event, priority, bounded)?;
+    self.backend.wake_up();
+    Ok(())
+  }
+
+  pub fn wake_up(&self) {
+    self.backend.wake_up();
+  }
+}
+
+impl<T> Clone for EventLoopProxy<T> {
+  fn clone(&self) -> Self {
+    EventLoopProxy {
+      user_events: self.user_events.clone(),
+      backend: self.backend.clone(),
+    }
+  }
+}
+
+struct Timer {
+  interval: Duration,
+  deadline: Instant,
+}
+
+pub struct EventLoopWindowTarget<T: 'static> {
+  pub(crate) backend: Arc<Backend>,
+  timers: RefCell<HashMap<TimerId, Timer>>,
+  device_event_filter: Cell<DeviceEventFilter>,
+  _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: 'static> EventLoopWindowTarget<T> {
+  pub fn primary_monitor(&self) -> Option<monitor::MonitorHandle> {
+    Some(monitor::MonitorHandle {
+      inner: MonitorHandle,
+    })
+  }
+
+  pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
+    let mut v = VecDeque::with_capacity(1);
+    v.push_back(MonitorHandle);
+    v
+  }
+
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    vec![VirtualDesktop]
+  }
+
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    Some(VirtualDesktop)
+  }
+
+  pub fn key_from_scancode(&self, _scancode: u32) -> Option<crate::keyboard::Key<'static>> {
+    None
+  }
+
+  pub fn scancode_from_key(&self, _key: &crate::keyboard::Key<'_>) -> Option<u32> {
+    None
+  }
+
+  pub fn key_repeat_info(&self) -> Option<crate::keyboard::KeyRepeatInfo> {
+    None
+  }
+
+  pub fn modifiers_state(&self) -> crate::keyboard::ModifiersState {
+    crate::keyboard::ModifiersState::empty()
+  }
+
+  pub fn lock_key_state(&self, _key: crate::keyboard::LockKey) -> Option<bool> {
+    None
+  }
+
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    let timer = Timer {
+      interval,
+      deadline: Instant::now() + interval,
+    };
+    self.timers.borrow_mut().insert(id, timer);
+  }
+
+  pub fn cancel_timer(&self, id: TimerId) {
+    self.timers.borrow_mut().remove(&id);
+  }
+
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    self.device_event_filter.set(filter);
+  }
+
+  pub fn device_info(&self, _device_id: DeviceId) -> Option<crate::event::DeviceInfo> {
+    None
+  }
+
+  fn next_timer(&self) -> Option<Instant> {
+    self
+      .timers
+      .borrow()
+      .values()
+      .map(|timer| timer.deadline)
+      .min()
+  }
+
+  /// Returns the timers that are due, in the order of their deadlines, and restarts them.
+  fn take_due_timers(&self) -> Vec<TimerId> {
+    let now = Instant::now();
+    let mut timers = self.timers.borrow_mut();
+    let mut due = timers
+      .iter_mut()
+      .filter(|(_, timer)| timer.deadline <= now)
+      .map(|(id, timer)| {
+        let deadline = timer.deadline;
+        // A timer that fell behind skips the ticks it missed instead of firing them in a burst.
+        timer.deadline = (deadline + timer.interval).max(now);
+        (deadline, *id)
+      })
+      .collect::<Vec<_>>();
+    due.sort();
+    due.into_iter().map(|(_, id)| id).collect()
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WindowId(u32);
+
+impl WindowId {
+  pub fn dummy() -> Self {
+    WindowId(u32::MAX)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeviceId;
+
+impl DeviceId {
+  pub fn dummy() -> Self {
+    DeviceId
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VirtualDesktop;
+
+/// Destroys a window once its `CloseResponse` is allowed.
+#[derive(Clone)]
+pub struct CloseHandle {
+  backend: Arc<Backend>,
+  window_id: WindowId,
+}
+
+impl CloseHandle {
+  pub(crate) fn new(backend: Arc<Backend>, window_id: WindowId) -> Self {
+    CloseHandle { backend, window_id }
+  }
+
+  pub fn close(&self) {
+    if let Some(state) = self.backend.window_state(self.window_id) {
+      let mut state = state.lock().unwrap();
+      if !state.destroyed {
+        state.destroyed = true;
+        state.visible = false;
+        self
+          .backend
+          .push_window_event(self.window_id, WindowEvent::Destroyed);
+      }
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PlatformSpecificWindowBuilderAttributes;
+
+/// The state of a window, changed by its setters and by the injected events.
+struct WindowState {
+  title: String,
+  inner_size: PhysicalSize<u32>,
+  position: PhysicalPosition<i32>,
+  min_inner_size: Option<Size>,
+  max_inner_size: Option<Size>,
+  visible: bool,
+  resizable: bool,
+  decorated: bool,
+  maximized: bool,
+  minimized: bool,
+  focused: bool,
+  enabled: bool,
+  menu_visible: bool,
+  fullscreen: Option<window::Fullscreen>,
+  destroyed: bool,
+  /// Whether the IME is composing, between the injected `Ime::Enabled` and `Ime::Disabled`.
+  ime_enabled: bool,
+  /// The modifiers of the last injected `ModifiersChanged`.
+  modifiers: ModifiersState,
+  text_input: bool,
+}
+
+impl WindowState {
+  /// Returns the text typed by an injected key in the text input mode.
+  fn apply(&mut self, event: &WindowEvent<'_>) -> Option<String> {
+    match event {
+      WindowEvent::Resized(size) => self.inner_size = *size,
+      WindowEvent::Moved(position) => self.position = *position,
+      WindowEvent::Focused(focused) => self.focused = *focused,
+      WindowEvent::Destroyed => self.destroyed = true,
+      WindowEvent::Ime(Ime::Enabled) => self.ime_enabled = true,
+      WindowEvent::Ime(Ime::Disabled) => self.ime_enabled = false,
+      WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+      WindowEvent::KeyboardInput { event, .. } if self.text_input => {
+        return event.received_text(self.modifiers)
+      }
+      _ => (),
+    }
+    None
+  }
+
+  /// Clamps `size` to the minimum and maximum inner sizes of the window.
+  fn clamp(&self, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let scale_factor = MonitorHandle.scale_factor();
+    let mut size = size;
+    if let Some(min_size) = self.min_inner_size {
+      let min_size = min_size.to_physical::<u32>(scale_factor);
+      size.width = size.width.max(min_size.width);
+      size.height = size.height.max(min_size.height);
+    }
+    if let Some(max_size) = self.max_inner_size {
+      let max_size = max_size.to_physical::<u32>(scale_factor);
+      size.width = size.width.min(max_size.width);
+      size.height = size.height.min(max_size.height);
+    }
+    size
+  }
+}
+
+pub struct Window {
+  window_id: WindowId,
+  state: Arc<Mutex<WindowState>>,
+  backend: Arc<Backend>,
+}
+
+impl Window {
+  pub fn new<T: 'static>(
+    event_loop_window_target: &EventLoopWindowTarget<T>,
+    attributes: window::WindowAttributes,
+    _pl_attribs: PlatformSpecificWindowBuilderAttributes,
+  ) -> Result<Self, error::OsError> {
+    let backend = event_loop_window_target.backend.clone();
+    let window_id = WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed));
+    let scale_factor = MonitorHandle.scale_factor();
+
+    let mut state = WindowState {
+      title: attributes.title,
+      inner_size: DEFAULT_INNER_SIZE,
+      position: attributes
+        .position
+        .map_or_else(Default::default, |position| {
+          position.to_physical(scale_factor)
+        }),
+      min_inner_size: attributes.min_inner_size,
+      max_inner_size: attributes.max_inner_size,
+      visible: attributes.visible,
+      resizable: attributes.resizable,
+      decorated: attributes.decorations,
+      maximized: attributes.maximized,
+      minimized: false,
+      focused: false,
+      enabled: true,
+      menu_visible: attributes.window_menu.is_some(),
+      fullscreen: attributes.fullscreen,
+      destroyed: false,
+      ime_enabled: false,
+      modifiers: ModifiersState::empty(),
+      text_input: false,
+    };
+    state.inner_size = state.clamp(
+      attributes
+        .inner_size
+        .map_or(DEFAULT_INNER_SIZE, |size| size.to_physical(scale_factor)),
+    );
+
+    let state = Arc::new(Mutex::new(state));
+    backend
+      .windows
+      .lock()
+      .unwrap()
+      .insert(window_id, Arc::downgrade(&state));
+
+    Ok(Window {
+      window_id,
+      state,
+      backend,
+    })
+  }
+
+  pub fn id(&self) -> WindowId {
+    self.window_id
+  }
+
+  pub fn primary_monitor(&self) -> Option<monitor::MonitorHandle> {
+    Some(monitor::MonitorHandle {
+      inner: MonitorHandle,
+    })
+  }
+
+  pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
+    let mut v = VecDeque::with_capacity(1);
+    v.push_back(MonitorHandle);
+    v
+  }
+
+  pub fn current_monitor(&self) -> Option<monitor::MonitorHandle> {
+    Some(monitor::MonitorHandle {
+      inner: MonitorHandle,
+    })
+  }
+
+  pub fn scale_factor(&self) -> f64 {
+    MonitorHandle.scale_factor()
+  }
+
+  // A destroyed window is never redrawn, like a native window.
+  pub fn request_redraw(&self) {
+    if !self.state.lock().unwrap().destroyed {
+      self.backend.request_redraw(self.window_id);
+    }
+  }
+
+  pub fn request_redraw_after(&self, delay: Duration) {
+    if !self.state.lock().unwrap().destroyed {
+      self.backend.request_redraw_after(self.window_id, delay);
+    }
+  }
+
+  pub fn set_redraw_pacing(&self, _pacing: window::RedrawPacing) {}
+
+  pub fn inner_position(&self) -> Result<PhysicalPosition<i32>, error::NotSupportedError> {
+    Ok(self.state.lock().unwrap().position)
+  }
+
+  pub fn outer_position(&self) -> Result<PhysicalPosition<i32>, error::NotSupportedError> {
+    Ok(self.state.lock().unwrap().position)
+  }
+
+  pub fn set_outer_position(&self, position: Position) {
+    let position = position.to_physical(self.scale_factor());
+    let mut state = self.state.lock().unwrap();
+    if state.position != position {
+      state.position = position;
+      self
+        .backend
+        .push_window_event(self.window_id, WindowEvent::Moved(position));
+    }
+  }
+
+  pub fn inner_size(&self) -> PhysicalSize<u32> {
+    self.state.lock().unwrap().inner_size
+  }
+
+  pub fn set_inner_size(&self, size: Size) {
+    let mut state = self.state.lock().unwrap();
+    let size = state.clamp(size.to_physical(self.scale_factor()));
+    if state.inner_size != size {
+      state.inner_size = size;
+      self
+        .backend
+        .push_window_event(self.window_id, WindowEvent::Resized(size));
+    }
+  }
+
+  // The windows have no frame.
+  pub fn outer_size(&self) -> PhysicalSize<u32> {
+    self.inner_size()
+  }
+
+  pub fn set_min_inner_size(&self, min_size: Option<Size>) {
+    self.state.lock().unwrap().min_inner_size = min_size;
+  }
+
+  pub fn set_max_inner_size(&self, max_size: Option<Size>) {
+    self.state.lock().unwrap().max_inner_size = max_size;
+  }
+
+  pub fn set_aspect_ratio(&self, _: Option<window::AspectRatio>) {}
+
+  pub fn set_resizable_edges(&self, _: window::Edges) {}
+
+  pub fn set_titlebar_buttons(&self, _: window::TitlebarButtons) {}
+
+  pub fn set_title(&self, title: &str) {
+    self.state.lock().unwrap().title = title.to_owned();
+  }
+
+  pub fn set_menu(&self, menu: Option<Menu>) {
+    self.state.lock().unwrap().menu_visible = menu.is_some();
+  }
+
+  pub fn set_visible(&self, visible: bool) {
+    self.state.lock().unwrap().visible = visible;
+  }
+
+  pub fn show_without_activating(&self) {
+    self.set_visible(true);
+  }
+
+  pub fn set_focus(&self) {
+    let mut state = self.state.lock().unwrap();
+    if !state.focused {
+      state.focused = true;
+      self
+        .backend
+        .push_window_event(self.window_id, WindowEvent::Focused(true));
+    }
+  }
+
+  pub fn raise(&self) {}
+
+  pub fn set_above(&self, _window: &Window) {}
+
+  pub fn set_below(&self, _window: &Window) {}
+
+  pub fn set_resizable(&self, resizable: bool) {
+    self.state.lock().unwrap().resizable = resizable;
+  }
+
+  pub fn set_minimized(&self, minimized: bool) {
+    self.state.lock().unwrap().minimized = minimized;
+  }
+
+  pub fn set_maximized(&self, maximized: bool) {
+    self.state.lock().unwrap().maximized = maximized;
+  }
+
+  pub fn set_maximized_on(&self, _monitor: monitor::MonitorHandle) {
+    self.set_maximized(true);
+  }
+
+  pub fn is_maximized(&self) -> bool {
+    self.state.lock().unwrap().maximized
+  }
+
+  // Maximizing does not change the geometry of a headless window.
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    None
+  }
+
+  pub fn is_visible(&self) -> bool {
+    self.state.lock().unwrap().visible
+  }
+
+  pub fn is_resizable(&self) -> bool {
+    self.state.lock().unwrap().resizable
+  }
+
+  pub fn is_decorated(&self) -> bool {
+    self.state.lock().unwrap().decorated
+  }
+
+  pub fn set_enabled(&self, enabled: bool) {
+    self.state.lock().unwrap().enabled = enabled;
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.state.lock().unwrap().enabled
+  }
+
+  pub fn set_fullscreen(&self, fullscreen: Option<window::Fullscreen>) {
+    self.state.lock().unwrap().fullscreen = fullscreen;
+  }
+
+  pub fn set_fullscreen_video_mode(
+    &self,
+    video_mode: monitor::VideoMode,
+  ) -> Result<(), error::ExternalError> {
+    self.set_fullscreen(Some(window::Fullscreen::Exclusive(video_mode)));
+    Ok(())
+  }
+
+  pub fn fullscreen(&self) -> Option<window::Fullscreen> {
+    self.state.lock().unwrap().fullscreen.clone()
+  }
+
+  pub fn set_kiosk(&self, _kiosk: bool) {}
+
+  pub fn set_decorations(&self, decorations: bool) {
+    self.state.lock().unwrap().decorated = decorations;
+  }
+
+  pub fn set_shadow(&self, _shadow: bool) {}
+
+  pub fn set_opacity(&self, _opacity: f32) {}
+
+  pub fn set_content_protected(&self, _protected: bool) {}
+
+  pub fn set_owner(&self, _owner: Option<WindowId>) {}
+
+  pub fn set_window_level(&self, _level: window::WindowLevel) {}
+
+  pub fn set_visible_on_all_workspaces(&self, _visible: bool) {}
+
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    Some(VirtualDesktop)
+  }
+
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    true
+  }
+
+  pub fn move_to_virtual_desktop(&self, _: &VirtualDesktop) -> Result<(), error::ExternalError> {
+    Ok(())
+  }
+
+  pub fn set_skip_taskbar(&self, _skip: bool) {}
+
+  pub fn set_minimize_to_tray(&self, _minimize_to_tray: bool) {}
+
+  pub fn set_theme(&self, _theme: Option<window::Theme>) {}
+
+  pub fn set_background_effect(&self, _effect: Option<window::Effect>) {}
+
+  pub fn set_window_icon(&self, _window_icon: Option<crate::icon::Icon>) {}
+
+  pub fn set_ime_cursor_area(&self, _position: Position, _size: Size) {}
+
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    let mut state = self.state.lock().unwrap();
+    if !allowed && state.ime_enabled {
+      state.ime_enabled = false;
+      drop(state);
+      // The composition in progress is cancelled.
+      for event in [Ime::preedit(String::new()), Ime::Disabled] {
+        self
+          .backend
+          .push_window_event(self.window_id, WindowEvent::Ime(event));
+      }
+    }
+  }
+
+  pub fn set_text_input(&self, active: bool) {
+    self.state.lock().unwrap().text_input = active;
+  }
+
+  pub fn request_user_attention(&self, _request_type: Option<window::UserAttentionType>) {}
+
+  pub fn set_progress_bar(&self, _state: window::ProgressState, _progress: Option<u64>) {}
+
+  pub fn set_badge_count(&self, _count: Option<u64>) {}
+
+  pub fn hide_menu(&self) {
+    self.state.lock().unwrap().menu_visible = false;
+  }
+
+  pub fn show_menu(&self) {
+    self.state.lock().unwrap().menu_visible = true;
+  }
+
+  pub fn is_menu_visible(&self) -> bool {
+    self.state.lock().unwrap().menu_visible
+  }
+
+  /// Nothing is drawn to the virtual windows, so their content is transparent.
+  pub fn capture_image(&self) -> Result<window::WindowImage, error::ExternalError> {
+    let size = self.inner_size();
+    let rgba = vec![0; (size.width * size.height * 4) as usize];
+    Ok(window::WindowImage::new(size.width, size.height, rgba))
+  }
+
+  pub fn set_cursor_icon(&self, _: window::CursorIcon) {}
+
+  pub fn set_cursor_position(&self, _: Position) -> Result<(), error::ExternalError> {
+    Ok(())
+  }
+
+  pub fn set_cursor_grab(
+    &self,
+    _: crate::window::CursorGrabMode,
+  ) -> Result<(), error::ExternalError> {
+    Ok(())
+  }
+
+  /// Sends the window events of `event`, which the backend has no input to translate it from. The
+  /// logical key of the keys is unidentified.
+  #[cfg(feature = "test")]
+  #[allow(deprecated)]
+  pub fn inject_event(
+    &self,
+    event: crate::test::SyntheticEvent,
+  ) -> Result<(), error::ExternalError> {
+    use crate::{
+      event::{DeviceId as RootDeviceId, KeyEvent, MouseScrollDelta, Touch, TouchPhase},
+      keyboard::{KeyLocation, ModifiersState},
+      test::SyntheticEvent,
+    };
+
+    let device_id = RootDeviceId(DeviceId::dummy());
+    let events = match event {
+      SyntheticEvent::Key {
+        physical_key,
+        state,
+      } => {
+        let logical_key = Key::Unidentified(NativeKeyCode::Unidentified);
+        vec![WindowEvent::KeyboardInput {
+          device_id,
+          event: KeyEvent {
+            physical_key,
+            logical_key: logical_key.clone(),
+            text: None,
+            location: KeyLocation::Standard,
+            state,
+            repeat: false,
+            compose_state: None,
+            platform_specific: KeyEventExtra {
+              text_with_all_modifiers: None,
+              key_without_modifiers: logical_key,
+            },
+          },
+          is_synthetic: false,
+        }]
+      }
+      SyntheticEvent::CursorMoved { position } => vec![WindowEvent::CursorMoved {
+        device_id,
+        position,
+        modifiers: ModifiersState::empty(),
+      }],
+      SyntheticEvent::MouseInput {
+        button,
+        state,
+        position,
+      } => vec![
+        WindowEvent::CursorMoved {
+          device_id,
+          position,
+          modifiers: ModifiersState::empty(),
+        },
+        WindowEvent::MouseInput {
+          device_id,
+          state,
+          button,
+          click_count: 1,
+          modifiers: ModifiersState::empty(),
+        },
+      ],
+      SyntheticEvent::MouseWheel { lines } => vec![WindowEvent::MouseWheel {
+        device_id,
+        delta: MouseScrollDelta::LineDelta(lines.0, lines.1),
+        phase: TouchPhase::Moved,
+        momentum: false,
+        modifiers: ModifiersState::empty(),
+      }],
+      SyntheticEvent::Touch {
+        id,
+        phase,
+        location,
+      } => {
+        let touch = Touch {
+          device_id,
+          phase,
+          location,
+          force: None,
+          id,
+        };
+        vec![
+          WindowEvent::Touch(touch),
+          WindowEvent::TouchFrame(vec![touch]),
+        ]
+      }
+    };
+    for event in events {
+      self.backend.inject(Event::WindowEvent {
+        window_id: window::WindowId(self.window_id),
+        event,
+        timestamp: Instant::now(),
+      });
+    }
+    Ok(())
+  }
+
+  pub fn set_mouse_capture(&self, _: bool) -> Result<(), error::ExternalError> {
+    Ok(())
+  }
+
+  pub fn set_keyboard_grab(&self, _: bool) -> Result<(), error::ExternalError> {
+    Ok(())
+  }
+
+  pub fn set_ignore_cursor_events(&self, _: bool) -> Result<(), error::ExternalError> {
+    Ok(())
+  }
+
+  pub fn set_cursor_visible(&self, _: bool) {}
+
+  pub fn set_mouse_coalescing(&self, _: bool) {}
+
+  pub fn drag_window(&self) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
+  pub fn set_drag_region(&self, _regions: Vec<window::DragRegion>) {}
+
+  pub fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+    empty_window_handle()
+  }
+}
+
+// There is no native window, so the handle of the OS is empty.
+#[cfg(target_os = "windows")]
+fn empty_window_handle() -> raw_window_handle::RawWindowHandle {
+  raw_window_handle::RawWindowHandle::Windows(raw_window_handle::windows::WindowsHandle::empty())
+}
+
+#[cfg(target_os = "macos")]
+fn empty_window_handle() -> raw_window_handle::RawWindowHandle {
+  raw_window_handle::RawWindowHandle::MacOS(raw_window_handle::macos::MacOSHandle::empty())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn empty_window_handle() -> raw_window_handle::RawWindowHandle {
+  raw_window_handle::RawWindowHandle::Xlib(raw_window_handle::unix::XlibHandle::empty())
+}
+
+impl Drop for Window {
+  fn drop(&mut self) {
+    self.backend.windows.lock().unwrap().remove(&self.window_id);
+    if !self.state.lock().unwrap().destroyed {
+      self
+        .backend
+        .push_window_event(self.window_id, WindowEvent::Destroyed);
+    }
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct OsError;
+
+impl fmt::Display for OsError {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+    write!(fmt, "Headless backend error")
+  }
+}
+
+/// The only monitor, a full HD display with a scale factor of 1.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MonitorHandle;
+
+impl MonitorHandle {
+  pub fn name(&self) -> Option<String> {
+    Some("Headless".to_owned())
+  }
+
+  pub fn size(&self) -> PhysicalSize<u32> {
+    PhysicalSize::new(1920, 1080)
+  }
+
+  pub fn position(&self) -> PhysicalPosition<i32> {
+    (0, 0).into()
+  }
+
+  pub fn scale_factor(&self) -> f64 {
+    1.0
+  }
+
+  pub fn video_modes(&self) -> impl Iterator<Item = monitor::VideoMode> {
+    std::iter::once(monitor::VideoMode {
+      video_mode: VideoMode {
+        size: self.size().into(),
+        bit_depth: 32,
+        refresh_rate: 60,
+        monitor: self.clone(),
+      },
+    })
+  }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VideoMode {
+  size: (u32, u32),
+  bit_depth: u16,
+  refresh_rate: u16,
+  monitor: MonitorHandle,
+}
+
+impl VideoMode {
+  pub fn size(&self) -> PhysicalSize<u32> {
+    self.size.into()
+  }
+
+  pub fn bit_depth(&self) -> u16 {
+    self.bit_depth
+  }
+
+  pub fn refresh_rate(&self) -> u16 {
+    self.refresh_rate
+  }
+
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate as u32 * 1000
+  }
+
+  pub fn monitor(&self) -> monitor::MonitorHandle {
+    monitor::MonitorHandle {
+      inner: self.monitor.clone(),
+    }
+  }
+}
+
+// The global shortcuts are only recorded, `Event::GlobalShortcutEvent` is injected by the tests.
+#[derive(Debug)]
+pub struct ShortcutManager;
+
+impl ShortcutManager {
+  pub(crate) fn new<T>(_window_target: &event_loop::EventLoopWindowTarget<T>) -> Self {
+    ShortcutManager
+  }
+
+  pub(crate) fn register(
+    &mut self,
+    accelerator: Accelerator,
+  ) -> Result<RootGlobalShortcut, ShortcutManagerError> {
+    Ok(RootGlobalShortcut(GlobalShortcut { accelerator }))
+  }
+
+  pub(crate) fn unregister_all(&mut self) -> Result<(), ShortcutManagerError> {
+    Ok(())
+  }
+
+  pub(crate) fn unregister(
+    &self,
+    _shortcut: RootGlobalShortcut,
+  ) -> Result<(), ShortcutManagerError> {
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalShortcut {
+  pub(crate) accelerator: Accelerator,
+}
+
+impl GlobalShortcut {
+  pub fn id(&self) -> AcceleratorId {
+    self.accelerator.clone().id()
+  }
+}
+
+#[cfg(feature = "tray")]
+pub struct SystemTrayBuilder;
+
+#[cfg(feature = "tray")]
+impl SystemTrayBuilder {
+  // The icon is a `PathBuf` on Linux and bytes elsewhere, and is ignored.
+  #[inline]
+  pub fn new<I>(_icon: I, _tray_menu: Option<Menu>) -> Self {
+    SystemTrayBuilder
+  }
+
+  #[inline]
+  pub fn build<T: 'static>(
+    self,
+    _window_target: &event_loop::EventLoopWindowTarget<T>,
+  ) -> Result<RootSystemTray, error::OsError> {
+    Ok(RootSystemTray(SystemTray))
+  }
+}
+
+#[cfg(feature = "tray")]
+pub struct SystemTray;
+
+#[cfg(feature = "tray")]
+impl SystemTray {
+  pub fn set_icon<I>(&mut self, _icon: I) {}
+
+  pub fn set_menu(&mut self, _tray_menu: &Menu) {}
+}
+
+pub fn keycode_to_scancode(_code: KeyCode) -> Option<u32> {
+  None
+}
+
+pub fn keycode_from_scancode(_scancode: u32) -> KeyCode {
+  KeyCode::Unidentified(NativeKeyCode::Unidentified)
+}
+
+pub fn current_keyboard_layout() -> Option<crate::keyboard::KeyboardLayout> {
+  None
+}
@@ -299,7 +299,7 @@ impl AppState {
         };
         (waiting_event_handler, event)
       }
-      (ControlFlow::Exit, _) => bug!("unexpected `ControlFlow` `Exit`"),
+      (ControlFlow::ExitWithCode(_), _) => bug!("unexpected `ControlFlow` `Exit`"),
       s => bug!("`EventHandler` unexpectedly woke up {:?}", s),
     };
 
@@ -450,7 +450,7 @@ impl AppState {
         });
         self.waker.start()
       }
-      (_, ControlFlow::Exit) => {
+      (_, ControlFlow::ExitWithCode(_)) => {
         // https://developer.apple.com/library/archive/qa/qa1561/_index.html
         // it is not possible to quit an iOS app gracefully and programatically
         warn!("`ControlFlow::Exit` ignored on iOS");
@@ -811,6 +811,9 @@ pub unsafe fn handle_main_events_cleared() {
   if !redraw_events.is_empty() {
     redraw_events.push(EventWrapper::StaticEvent(Event::RedrawEventsCleared));
   }
+  if let Some(event) = Event::idle(this.control_flow) {
+    redraw_events.push(EventWrapper::StaticEvent(event));
+  }
   drop(this);
 
   handle_nonuser_events(redraw_events);
@@ -866,6 +869,7 @@ fn handle_hidpi_proxy(
       scale_factor,
       new_inner_size,
     },
+    timestamp: Instant::now(),
   };
   event_handler.handle_nonuser_event(event, &mut control_flow);
   let (view, screen_frame) = get_view_and_screen_frame(window_id);
@@ -7,16 +7,21 @@ use std::{
   fmt::{self, Debug},
   marker::PhantomData,
   mem, ptr,
+  sync::Arc,
+  time::Duration,
 };
 
-use crossbeam_channel::{self as channel, Receiver, Sender};
-
 use crate::{
   dpi::LogicalSize,
+  error::OsError as RootOsError,
   event::Event,
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootEventLoopWindowTarget},
+  event_loop::{
+    ControlFlow, DeviceEventFilter, EventLoopClosed,
+    EventLoopWindowTarget as RootEventLoopWindowTarget, Priority, SendEventError, TimerId,
+  },
   monitor::MonitorHandle as RootMonitorHandle,
   platform::ios::Idiom,
+  user_event_queue::UserEventQueue,
 };
 
 use crate::platform_impl::platform::{
@@ -29,7 +34,7 @@ use crate::platform_impl::platform::{
     CFRunLoopSourceInvalidate, CFRunLoopSourceRef, CFRunLoopSourceSignal, CFRunLoopWakeUp,
     NSStringRust, UIApplicationMain, UIUserInterfaceIdiom,
   },
-  monitor, view, MonitorHandle,
+  monitor, view, MonitorHandle, VirtualDesktop,
 };
 
 #[non_exhaustive]
@@ -50,8 +55,7 @@ pub enum EventProxy {
 }
 
 pub struct EventLoopWindowTarget<T: 'static> {
-  receiver: Receiver<T>,
-  sender_to_clone: Sender<T>,
+  user_events: Arc<UserEventQueue<T>>,
 }
 
 impl<T: 'static> EventLoopWindowTarget<T> {
@@ -66,6 +70,51 @@ impl<T: 'static> EventLoopWindowTarget<T> {
 
     Some(RootMonitorHandle { inner: monitor })
   }
+
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    Vec::new()
+  }
+
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn key_from_scancode(&self, _scancode: u32) -> Option<crate::keyboard::Key<'static>> {
+    None
+  }
+
+  pub fn scancode_from_key(&self, _key: &crate::keyboard::Key<'_>) -> Option<u32> {
+    None
+  }
+
+  pub fn key_repeat_info(&self) -> Option<crate::keyboard::KeyRepeatInfo> {
+    None
+  }
+
+  pub fn modifiers_state(&self) -> crate::keyboard::ModifiersState {
+    crate::keyboard::ModifiersState::empty()
+  }
+
+  pub fn lock_key_state(&self, _key: crate::keyboard::LockKey) -> Option<bool> {
+    None
+  }
+
+  pub fn set_timer(&self, _interval: Duration, _id: TimerId) {
+    warn!("`EventLoopWindowTarget::set_timer` is ignored on iOS")
+  }
+
+  pub fn cancel_timer(&self, _id: TimerId) {}
+
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  pub fn set_device_event_filter(&self, _filter: DeviceEventFilter) {}
+
+  pub fn device_info(
+    &self,
+    _device_id: crate::platform_impl::DeviceId,
+  ) -> Option<crate::event::DeviceInfo> {
+    None
+  }
 }
 
 pub struct EventLoop<T: 'static> {
@@ -73,6 +122,10 @@ pub struct EventLoop<T: 'static> {
 }
 
 impl<T: 'static> EventLoop<T> {
+  pub fn try_new() -> Result<Self, RootOsError> {
+    Ok(Self::new())
+  }
+
   pub fn new() -> EventLoop<T> {
     static mut SINGLETON_INIT: bool = false;
     unsafe {
@@ -86,16 +139,13 @@ impl<T: 'static> EventLoop<T> {
       view::create_delegate_class();
     }
 
-    let (sender_to_clone, receiver) = channel::unbounded();
-
     // this line sets up the main run loop before `UIApplicationMain`
     setup_control_flow_observers();
 
     EventLoop {
       window_target: RootEventLoopWindowTarget {
         p: EventLoopWindowTarget {
-          receiver,
-          sender_to_clone,
+          user_events: Arc::new(UserEventQueue::new()),
         },
         _marker: PhantomData,
       },
@@ -131,7 +181,15 @@ impl<T: 'static> EventLoop<T> {
   }
 
   pub fn create_proxy(&self) -> EventLoopProxy<T> {
-    EventLoopProxy::new(self.window_target.p.sender_to_clone.clone())
+    EventLoopProxy::new(self.window_target.p.user_events.clone())
+  }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self
+      .window_target
+      .p
+      .user_events
+      .set_capacity(priority, capacity);
   }
 
   pub fn window_target(&self) -> &RootEventLoopWindowTarget<T> {
@@ -148,7 +206,7 @@ impl<T: 'static> EventLoop<T> {
 }
 
 pub struct EventLoopProxy<T> {
-  sender: Sender<T>,
+  user_events: Arc<UserEventQueue<T>>,
   source: CFRunLoopSourceRef,
 }
 
@@ -156,7 +214,7 @@ unsafe impl<T: Send> Send for EventLoopProxy<T> {}
 
 impl<T> Clone for EventLoopProxy<T> {
   fn clone(&self) -> EventLoopProxy<T> {
-    EventLoopProxy::new(self.sender.clone())
+    EventLoopProxy::new(self.user_events.clone())
   }
 }
 
@@ -170,7 +228,7 @@ impl<T> Drop for EventLoopProxy<T> {
 }
 
 impl<T> EventLoopProxy<T> {
-  fn new(sender: Sender<T>) -> EventLoopProxy<T> {
+  fn new(user_events: Arc<UserEventQueue<T>>) -> EventLoopProxy<T> {
     unsafe {
       // just wake up the eventloop
       extern "C" fn event_loop_proxy_handler(_: *mut c_void) {}
@@ -185,15 +243,34 @@ impl<T> EventLoopProxy<T> {
       CFRunLoopAddSource(rl, source, kCFRunLoopCommonModes);
       CFRunLoopWakeUp(rl);
 
-      EventLoopProxy { sender, source }
+      EventLoopProxy {
+        user_events,
+        source,
+      }
     }
   }
 
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
     self
-      .sender
-      .send(event)
-      .map_err(|channel::SendError(x)| EventLoopClosed(x))?;
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.user_events.push(event, priority, bounded)?;
     unsafe {
       // let the main thread know there's a new event
       CFRunLoopSourceSignal(self.source);
@@ -202,6 +279,12 @@ impl<T> EventLoopProxy<T> {
     }
     Ok(())
   }
+
+  pub fn wake_up(&self) {
+    unsafe {
+      CFRunLoopWakeUp(CFRunLoopGetMain());
+    }
+  }
 }
 
 fn setup_control_flow_observers() {
@@ -334,7 +417,7 @@ where
   }
 
   fn handle_user_events(&mut self, control_flow: &mut ControlFlow) {
-    for event in self.event_loop.p.receiver.try_iter() {
+    while let Some(event) = self.event_loop.p.user_events.pop() {
       (self.f)(Event::UserEvent(event), &self.event_loop, control_flow);
     }
   }
@@ -7,3 +7,7 @@ pub fn keycode_to_scancode(_code: KeyCode) -> Option<u32> {
 pub fn keycode_from_scancode(_scancode: u32) -> KeyCode {
   KeyCode::Unidentified(NativeKeyCode::Unidentified)
 }
+
+pub fn current_keyboard_layout() -> Option<crate::keyboard::KeyboardLayout> {
+  None
+}
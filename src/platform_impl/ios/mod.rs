@@ -89,7 +89,7 @@ use std::fmt;
 pub use self::{
   clipboard::Clipboard,
   event_loop::{EventLoop, EventLoopProxy, EventLoopWindowTarget},
-  keycode::{keycode_from_scancode, keycode_to_scancode},
+  keycode::{current_keyboard_layout, keycode_from_scancode, keycode_to_scancode},
   monitor::{MonitorHandle, VideoMode},
   window::{PlatformSpecificWindowBuilderAttributes, Window, WindowId},
 };
@@ -150,6 +150,17 @@ impl MenuItemAttributes {
   pub fn set_icon(&mut self, _icon: Vec<u8>) {}
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VirtualDesktop;
+
+// Windows can't be closed by the user on iOS.
+#[derive(Clone)]
+pub struct CloseHandle;
+
+impl CloseHandle {
+  pub fn close(&self) {}
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DeviceId {
   uiscreen: ffi::id,
@@ -1,7 +1,10 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashMap;
+use std::{
+  collections::HashMap,
+  time::{Duration, Instant},
+};
 
 use objc::{
   declare::ClassDecl,
@@ -10,14 +13,14 @@ use objc::{
 
 use crate::{
   dpi::PhysicalPosition,
-  event::{DeviceId as RootDeviceId, Event, Force, Touch, TouchPhase, WindowEvent},
+  event::{DeviceId as RootDeviceId, Event, Force, SuspendReason, Touch, TouchPhase, WindowEvent},
   platform::ios::MonitorHandleExtIOS,
   platform_impl::platform::{
     app_state::{self, OSCapabilities},
     event_loop::{self, EventProxy, EventWrapper},
     ffi::{
-      id, nil, CGFloat, CGPoint, CGRect, UIForceTouchCapability, UIInterfaceOrientationMask,
-      UIRectEdge, UITouchPhase, UITouchType,
+      id, nil, CGFloat, CGPoint, CGRect, NSUInteger, UIForceTouchCapability,
+      UIInterfaceOrientationMask, UIRectEdge, UITouchPhase, UITouchType,
     },
     window::PlatformSpecificWindowBuilderAttributes,
     DeviceId,
@@ -83,6 +86,21 @@ macro_rules! add_property {
     };
 }
 
+/// Converts the timestamp of `touch`, in seconds since the system started, to an `Instant`.
+unsafe fn touch_timestamp(touch: id) -> Instant {
+  let now = Instant::now();
+  let timestamp: f64 = msg_send![touch, timestamp];
+  let process_info: id = msg_send![class!(NSProcessInfo), processInfo];
+  let uptime: f64 = msg_send![process_info, systemUptime];
+  let elapsed = uptime - timestamp;
+  if !elapsed.is_finite() || elapsed <= 0.0 {
+    return now;
+  }
+  now
+    .checked_sub(Duration::from_secs_f64(elapsed))
+    .unwrap_or(now)
+}
+
 // requires main thread
 unsafe fn get_view_class(root_view_class: &'static Class) -> &'static Class {
   static mut CLASSES: Option<HashMap<*const Class, &'static Class>> = None;
@@ -149,6 +167,7 @@ unsafe fn get_view_class(root_view_class: &'static Class) -> &'static Class {
         app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::WindowEvent {
           window_id: RootWindowId(window.into()),
           event: WindowEvent::Resized(size),
+          timestamp: Instant::now(),
         }));
       }
     }
@@ -203,52 +222,30 @@ unsafe fn get_view_class(root_view_class: &'static Class) -> &'static Class {
             Event::WindowEvent {
               window_id: RootWindowId(window.into()),
               event: WindowEvent::Resized(size.to_physical(scale_factor)),
+              timestamp: Instant::now(),
             },
           ))),
         );
       }
     }
 
-    extern "C" fn handle_touches(object: &Object, _: Sel, touches: id, _: id) {
+    extern "C" fn handle_touches(object: &Object, _: Sel, touches: id, event: id) {
       unsafe {
         let window: id = msg_send![object, window];
         assert!(!window.is_null());
         let uiscreen: id = msg_send![window, screen];
         let touches_enum: id = msg_send![touches, objectEnumerator];
         let mut touch_events = Vec::new();
+        // The touches of the callback are a frame, with the last sample of each touch.
+        let mut frame = Vec::new();
+        let mut frame_timestamp = Instant::now();
         let os_supports_force = app_state::os_capabilities().force_touch;
+        let coalesced_touches: BOOL = *object.get_ivar("_coalescedTouches");
         loop {
           let touch: id = msg_send![touches_enum, nextObject];
           if touch == nil {
             break;
           }
-          let logical_location: CGPoint = msg_send![touch, locationInView: nil];
-          let touch_type: UITouchType = msg_send![touch, type];
-          let force = if os_supports_force {
-            let trait_collection: id = msg_send![object, traitCollection];
-            let touch_capability: UIForceTouchCapability =
-              msg_send![trait_collection, forceTouchCapability];
-            // Both the OS _and_ the device need to be checked for force touch support.
-            if touch_capability == UIForceTouchCapability::Available {
-              let force: CGFloat = msg_send![touch, force];
-              let max_possible_force: CGFloat = msg_send![touch, maximumPossibleForce];
-              let altitude_angle: Option<f64> = if touch_type == UITouchType::Pencil {
-                let angle: CGFloat = msg_send![touch, altitudeAngle];
-                Some(angle as _)
-              } else {
-                None
-              };
-              Some(Force::Calibrated {
-                force: force as _,
-                max_possible_force: max_possible_force as _,
-                altitude_angle,
-              })
-            } else {
-              None
-            }
-          } else {
-            None
-          };
           let touch_id = touch as u64;
           let phase: UITouchPhase = msg_send![touch, phase];
           let phase = match phase {
@@ -260,22 +257,86 @@ unsafe fn get_view_class(root_view_class: &'static Class) -> &'static Class {
             _ => panic!("unexpected touch phase: {:?}", phase as i32),
           };
 
-          let physical_location = {
-            let scale_factor: CGFloat = msg_send![object, contentScaleFactor];
-            PhysicalPosition::from_logical::<(f64, f64), f64>(
-              (logical_location.x as _, logical_location.y as _),
-              scale_factor,
-            )
+          // With `Window::set_mouse_coalescing(false)`, each move coalesced into this one is sent,
+          // the last coalesced touch being the touch itself.
+          let samples = if coalesced_touches == YES && phase == TouchPhase::Moved {
+            let coalesced: id = msg_send![event, coalescedTouchesForTouch: touch];
+            let count: NSUInteger = if coalesced == nil {
+              0
+            } else {
+              msg_send![coalesced, count]
+            };
+            (0..count)
+              .map(|i| -> id { msg_send![coalesced, objectAtIndex: i] })
+              .collect()
+          } else {
+            Vec::new()
           };
-          touch_events.push(EventWrapper::StaticEvent(Event::WindowEvent {
-            window_id: RootWindowId(window.into()),
-            event: WindowEvent::Touch(Touch {
+          let samples = if samples.is_empty() {
+            vec![touch]
+          } else {
+            samples
+          };
+
+          let mut last_sample = None;
+          for sample in samples {
+            let logical_location: CGPoint = msg_send![sample, locationInView: nil];
+            let touch_type: UITouchType = msg_send![sample, type];
+            let force = if os_supports_force {
+              let trait_collection: id = msg_send![object, traitCollection];
+              let touch_capability: UIForceTouchCapability =
+                msg_send![trait_collection, forceTouchCapability];
+              // Both the OS _and_ the device need to be checked for force touch support.
+              if touch_capability == UIForceTouchCapability::Available {
+                let force: CGFloat = msg_send![sample, force];
+                let max_possible_force: CGFloat = msg_send![sample, maximumPossibleForce];
+                let altitude_angle: Option<f64> = if touch_type == UITouchType::Pencil {
+                  let angle: CGFloat = msg_send![sample, altitudeAngle];
+                  Some(angle as _)
+                } else {
+                  None
+                };
+                Some(Force::Calibrated {
+                  force: force as _,
+                  max_possible_force: max_possible_force as _,
+                  altitude_angle,
+                })
+              } else {
+                None
+              }
+            } else {
+              None
+            };
+
+            let physical_location = {
+              let scale_factor: CGFloat = msg_send![object, contentScaleFactor];
+              PhysicalPosition::from_logical::<(f64, f64), f64>(
+                (logical_location.x as _, logical_location.y as _),
+                scale_factor,
+              )
+            };
+            let event = Touch {
               device_id: RootDeviceId(DeviceId { uiscreen }),
               id: touch_id,
               location: physical_location,
               force,
               phase,
-            }),
+            };
+            frame_timestamp = touch_timestamp(sample);
+            touch_events.push(EventWrapper::StaticEvent(Event::WindowEvent {
+              window_id: RootWindowId(window.into()),
+              event: WindowEvent::Touch(event),
+              timestamp: frame_timestamp,
+            }));
+            last_sample = Some(event);
+          }
+          frame.extend(last_sample);
+        }
+        if !frame.is_empty() {
+          touch_events.push(EventWrapper::StaticEvent(Event::WindowEvent {
+            window_id: RootWindowId(window.into()),
+            event: WindowEvent::TouchFrame(frame),
+            timestamp: frame_timestamp,
           }));
         }
         app_state::handle_nonuser_events(touch_events);
@@ -285,6 +346,8 @@ unsafe fn get_view_class(root_view_class: &'static Class) -> &'static Class {
     let mut decl = ClassDecl::new(&format!("TaoUIView{}", ID), root_view_class)
       .expect("Failed to declare class `TaoUIView`");
     ID += 1;
+    // `YES` when `Window::set_mouse_coalescing` is disabled.
+    decl.add_ivar::<BOOL>("_coalescedTouches");
     decl.add_method(
       sel!(drawRect:),
       draw_rect as extern "C" fn(&Object, Sel, CGRect),
@@ -399,6 +462,7 @@ unsafe fn get_window_class() -> &'static Class {
         app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::WindowEvent {
           window_id: RootWindowId(object.into()),
           event: WindowEvent::Focused(true),
+          timestamp: Instant::now(),
         }));
         let () = msg_send![super(object, class!(UIWindow)), becomeKeyWindow];
       }
@@ -409,6 +473,7 @@ unsafe fn get_window_class() -> &'static Class {
         app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::WindowEvent {
           window_id: RootWindowId(object.into()),
           event: WindowEvent::Focused(false),
+          timestamp: Instant::now(),
         }));
         let () = msg_send![super(object, class!(UIWindow)), resignKeyWindow];
       }
@@ -555,11 +620,19 @@ pub fn create_delegate_class() {
   }
 
   extern "C" fn did_become_active(_: &Object, _: Sel, _: id) {
-    unsafe { app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::Resumed)) }
+    unsafe {
+      app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::Resumed(
+        SuspendReason::Background,
+      )))
+    }
   }
 
   extern "C" fn will_resign_active(_: &Object, _: Sel, _: id) {
-    unsafe { app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::Suspended)) }
+    unsafe {
+      app_state::handle_nonuser_event(EventWrapper::StaticEvent(Event::Suspended(
+        SuspendReason::Background,
+      )))
+    }
   }
 
   extern "C" fn will_enter_foreground(_: &Object, _: Sel, _: id) {}
@@ -581,6 +654,7 @@ pub fn create_delegate_class() {
           events.push(EventWrapper::StaticEvent(Event::WindowEvent {
             window_id: RootWindowId(window.into()),
             event: WindowEvent::Destroyed,
+            timestamp: Instant::now(),
           }));
         }
       }
@@ -5,6 +5,7 @@ use raw_window_handle::{ios::IOSHandle, RawWindowHandle};
 use std::{
   collections::VecDeque,
   ops::{Deref, DerefMut},
+  time::{Duration, Instant},
 };
 
 use objc::runtime::{Class, Object, BOOL, NO, YES};
@@ -14,18 +15,22 @@ use crate::{
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
   event::{Event, WindowEvent},
   icon::Icon,
-  monitor::MonitorHandle as RootMonitorHandle,
+  monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
   platform::ios::{MonitorHandleExtIOS, ScreenEdge, ValidOrientations},
   platform_impl::platform::{
     app_state,
     event_loop::{self, EventProxy, EventWrapper},
     ffi::{
-      id, CGFloat, CGPoint, CGRect, CGSize, UIEdgeInsets, UIInterfaceOrientationMask, UIRectEdge,
-      UIScreenOverscanCompensation,
+      id, CGFloat, CGPoint, CGRect, CGSize, NSInteger, UIEdgeInsets, UIInterfaceOrientationMask,
+      UIRectEdge, UIScreenOverscanCompensation,
     },
-    monitor, view, EventLoopWindowTarget, Menu, MonitorHandle,
+    monitor, view, EventLoopWindowTarget, Menu, MonitorHandle, VirtualDesktop,
+  },
+  window::{
+    AspectRatio, CursorGrabMode, CursorIcon, DragRegion, Edges, Effect, Fullscreen, ProgressState,
+    RedrawPacing, Theme, TitlebarButtons, UserAttentionType, WindowAttributes,
+    WindowId as RootWindowId, WindowImage, WindowLevel,
   },
-  window::{CursorIcon, Fullscreen, UserAttentionType, WindowAttributes, WindowId as RootWindowId},
 };
 
 pub struct Inner {
@@ -65,11 +70,27 @@ impl Inner {
     }
   }
 
+  pub fn show_without_activating(&self) {
+    self.set_visible(true)
+  }
+
   pub fn set_focus(&self) {
     //FIXME: implementation goes here
     warn!("set_focus not yet implemented on iOS");
   }
 
+  pub fn raise(&self) {
+    warn!("`Window::raise` is ignored on iOS")
+  }
+
+  pub fn set_above(&self, _window: &Inner) {
+    warn!("`Window::set_above` is ignored on iOS")
+  }
+
+  pub fn set_below(&self, _window: &Inner) {
+    warn!("`Window::set_below` is ignored on iOS")
+  }
+
   pub fn request_redraw(&self) {
     unsafe {
       if self.gl_or_metal_backed {
@@ -88,6 +109,15 @@ impl Inner {
     }
   }
 
+  pub fn request_redraw_after(&self, _delay: Duration) {
+    debug!("`Window::set_redraw_throttle` is ignored on iOS");
+    self.request_redraw();
+  }
+
+  pub fn set_redraw_pacing(&self, _pacing: RedrawPacing) {
+    debug!("`Window::set_redraw_pacing` is ignored on iOS")
+  }
+
   pub fn inner_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
     unsafe {
       let safe_area = self.safe_area_screen_space();
@@ -165,6 +195,18 @@ impl Inner {
     warn!("`Window::set_max_inner_size` is ignored on iOS")
   }
 
+  pub fn set_aspect_ratio(&self, _aspect_ratio: Option<AspectRatio>) {
+    warn!("`Window::set_aspect_ratio` is ignored on iOS")
+  }
+
+  pub fn set_resizable_edges(&self, _edges: Edges) {
+    warn!("`Window::set_resizable_edges` is ignored on iOS")
+  }
+
+  pub fn set_titlebar_buttons(&self, _buttons: TitlebarButtons) {
+    warn!("`Window::set_titlebar_buttons` is ignored on iOS")
+  }
+
   pub fn set_resizable(&self, _resizable: bool) {
     warn!("`Window::set_resizable` is ignored on iOS")
   }
@@ -184,7 +226,24 @@ impl Inner {
     Err(ExternalError::NotSupported(NotSupportedError::new()))
   }
 
-  pub fn set_cursor_grab(&self, _grab: bool) -> Result<(), ExternalError> {
+  pub fn set_cursor_grab(&self, _mode: CursorGrabMode) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  #[cfg(feature = "test")]
+  pub fn inject_event(&self, _event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_mouse_capture(&self, _capture: bool) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_keyboard_grab(&self, _grab: bool) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_ignore_cursor_events(&self, _ignore: bool) -> Result<(), ExternalError> {
     Err(ExternalError::NotSupported(NotSupportedError::new()))
   }
 
@@ -192,10 +251,21 @@ impl Inner {
     debug!("`Window::set_cursor_visible` is ignored on iOS")
   }
 
+  pub fn set_mouse_coalescing(&self, enabled: bool) {
+    unsafe {
+      let coalesced_touches = if enabled { NO } else { YES };
+      (*self.view).set_ivar::<BOOL>("_coalescedTouches", coalesced_touches);
+    }
+  }
+
   pub fn drag_window(&self) -> Result<(), ExternalError> {
     Err(ExternalError::NotSupported(NotSupportedError::new()))
   }
 
+  pub fn set_drag_region(&self, _regions: Vec<DragRegion>) {
+    warn!("`Window::set_drag_region` is ignored on iOS")
+  }
+
   pub fn set_minimized(&self, _minimized: bool) {
     warn!("`Window::set_minimized` is ignored on iOS")
   }
@@ -204,11 +274,19 @@ impl Inner {
     warn!("`Window::set_maximized` is ignored on iOS")
   }
 
+  pub fn set_maximized_on(&self, _monitor: RootMonitorHandle) {
+    warn!("`Window::set_maximized_on` is ignored on iOS")
+  }
+
   pub fn is_maximized(&self) -> bool {
     warn!("`Window::is_maximized` is ignored on iOS");
     false
   }
 
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    None
+  }
+
   pub fn is_visible(&self) -> bool {
     log::warn!("`Window::is_visible` is ignored on iOS");
     false
@@ -224,6 +302,20 @@ impl Inner {
     false
   }
 
+  pub fn set_enabled(&self, enabled: bool) {
+    unsafe {
+      let enabled = if enabled { YES } else { NO };
+      let () = msg_send![self.window, setUserInteractionEnabled: enabled];
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    unsafe {
+      let enabled: BOOL = msg_send![self.window, isUserInteractionEnabled];
+      enabled == YES
+    }
+  }
+
   pub fn set_fullscreen(&self, monitor: Option<Fullscreen>) {
     unsafe {
       let uiscreen = match monitor {
@@ -260,6 +352,11 @@ impl Inner {
     }
   }
 
+  pub fn set_fullscreen_video_mode(&self, video_mode: RootVideoMode) -> Result<(), ExternalError> {
+    self.set_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
+    Ok(())
+  }
+
   pub fn fullscreen(&self) -> Option<Fullscreen> {
     unsafe {
       let monitor = self.current_monitor_inner();
@@ -280,26 +377,98 @@ impl Inner {
     }
   }
 
+  pub fn set_kiosk(&self, _kiosk: bool) {
+    warn!("`Window::set_kiosk` is ignored on iOS")
+  }
+
   pub fn set_decorations(&self, _decorations: bool) {
     warn!("`Window::set_decorations` is ignored on iOS")
   }
 
-  pub fn set_always_on_top(&self, _always_on_top: bool) {
-    warn!("`Window::set_always_on_top` is ignored on iOS")
+  pub fn set_shadow(&self, _shadow: bool) {
+    warn!("`Window::set_shadow` is ignored on iOS")
+  }
+
+  pub fn set_opacity(&self, _opacity: f32) {
+    warn!("`Window::set_opacity` is ignored on iOS")
+  }
+
+  pub fn set_content_protected(&self, _protected: bool) {
+    warn!("`Window::set_content_protected` is ignored on iOS")
+  }
+
+  pub fn set_owner(&self, _owner: Option<WindowId>) {
+    warn!("`WindowGroup` is ignored on iOS")
+  }
+
+  pub fn set_window_level(&self, _level: WindowLevel) {
+    warn!("`Window::set_window_level` is ignored on iOS")
+  }
+
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    true
+  }
+
+  pub fn move_to_virtual_desktop(&self, _desktop: &VirtualDesktop) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_visible_on_all_workspaces(&self, _visible: bool) {
+    warn!("`Window::set_visible_on_all_workspaces` is ignored on iOS")
+  }
+
+  pub fn set_skip_taskbar(&self, _skip: bool) {
+    warn!("`Window::set_skip_taskbar` is ignored on iOS")
+  }
+
+  pub fn set_theme(&self, _theme: Option<Theme>) {
+    warn!("`Window::set_theme` is ignored on iOS")
+  }
+
+  pub fn set_minimize_to_tray(&self, _minimize_to_tray: bool) {
+    warn!("`Window::set_minimize_to_tray` is ignored on iOS")
+  }
+
+  pub fn set_background_effect(&self, _effect: Option<Effect>) {
+    warn!("`Window::set_background_effect` is ignored on iOS")
   }
 
   pub fn set_window_icon(&self, _icon: Option<Icon>) {
     warn!("`Window::set_window_icon` is ignored on iOS")
   }
 
-  pub fn set_ime_position(&self, _position: Position) {
-    warn!("`Window::set_ime_position` is ignored on iOS")
+  pub fn set_ime_cursor_area(&self, _position: Position, _size: Size) {
+    warn!("`Window::set_ime_cursor_area` is ignored on iOS")
+  }
+
+  pub fn set_ime_allowed(&self, _allowed: bool) {
+    warn!("`Window::set_ime_allowed` is ignored on iOS")
+  }
+
+  pub fn set_text_input(&self, _active: bool) {
+    warn!("`Window::start_text_input` is ignored on iOS")
   }
 
   pub fn request_user_attention(&self, _request_type: Option<UserAttentionType>) {
     warn!("`Window::request_user_attention` is ignored on iOS")
   }
 
+  pub fn set_badge_count(&self, count: Option<u64>) {
+    unsafe {
+      let app: id = msg_send![class!(UIApplication), sharedApplication];
+      let count = count.unwrap_or(0) as NSInteger;
+      let () = msg_send![app, setApplicationIconBadgeNumber: count];
+    }
+  }
+
+  pub fn set_progress_bar(&self, _state: ProgressState, _progress: Option<u64>) {
+    warn!("`Window::set_progress_bar` is ignored on iOS")
+  }
+
   pub fn hide_menu(&self) {
     warn!("`Window::hide_menu` is ignored on iOS")
   }
@@ -313,6 +482,10 @@ impl Inner {
     false
   }
 
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
   // Allow directly accessing the current monitor internally without unwrapping.
   fn current_monitor_inner(&self) -> RootMonitorHandle {
     unsafe {
@@ -398,8 +571,8 @@ impl Window {
     if let Some(_) = window_attributes.max_inner_size {
       warn!("`WindowAttributes::max_inner_size` is ignored on iOS");
     }
-    if window_attributes.always_on_top {
-      warn!("`WindowAttributes::always_on_top` is unsupported on iOS");
+    if window_attributes.window_level != WindowLevel::Normal {
+      warn!("`WindowAttributes::window_level` is unsupported on iOS");
     }
     // TODO: transparency, visible
 
@@ -482,6 +655,7 @@ impl Window {
             Event::WindowEvent {
               window_id: RootWindowId(window.into()),
               event: WindowEvent::Resized(size.to_physical(scale_factor)),
+              timestamp: Instant::now(),
             },
           ))),
         );
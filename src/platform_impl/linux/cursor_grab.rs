@@ -0,0 +1,189 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The grabs of the pointer and the keyboard on X11, since GDK can't confine the pointer to a window.
+// The pointer is locked by confining it to an input-only window of a single pixel, its motion still
+// being reported by the raw motion of XInput 2. The mouse is captured by grabbing the pointer
+// without confining it, which the grab of the cursor replaces until it's released.
+use std::{cell::Cell, ptr};
+
+use glib::translate::ToGlibPtr;
+use x11_dl::xlib;
+
+use super::keyboard_layout::xdisplay;
+use crate::window::CursorGrabMode;
+
+extern "C" {
+  fn gdk_x11_window_get_xid(window: *mut gdk_sys::GdkWindow) -> xlib::Window;
+}
+
+thread_local! {
+  // The pointer is grabbed by a single window at a time, so there's a single window locking it.
+  static LOCK_WINDOW: Cell<xlib::Window> = Cell::new(0);
+  // Whether the cursor is grabbed, and the window capturing the mouse.
+  static CURSOR_GRABBED: Cell<bool> = Cell::new(false);
+  static CAPTURE_WINDOW: Cell<xlib::Window> = Cell::new(0);
+}
+
+/// Grabs the pointer for `window`, or releases it. Returns whether the pointer could be grabbed.
+pub fn set_cursor_grab(window: &gdk::Window, mode: CursorGrabMode) -> bool {
+  let (xlib, xdisplay) = match xdisplay(&window.display()) {
+    Some(xdisplay) => xdisplay,
+    None => return false,
+  };
+  unsafe {
+    (xlib.XUngrabPointer)(xdisplay, xlib::CurrentTime);
+    let lock_window = LOCK_WINDOW.with(|lock_window| lock_window.replace(0));
+    if lock_window != 0 {
+      (xlib.XDestroyWindow)(xdisplay, lock_window);
+    }
+
+    let xid = gdk_x11_window_get_xid(window.to_glib_none().0);
+    CURSOR_GRABBED.with(|grabbed| grabbed.set(mode != CursorGrabMode::None));
+    let confine_to = match mode {
+      CursorGrabMode::None => {
+        let capture_window = CAPTURE_WINDOW.with(Cell::get);
+        let captured = capture_window == 0 || grab_pointer(xlib, xdisplay, capture_window, 0);
+        (xlib.XFlush)(xdisplay);
+        return captured;
+      }
+      CursorGrabMode::Confined => xid,
+      CursorGrabMode::Locked => {
+        let (x, y) = locked_position(xlib, xdisplay, xid, window);
+        let lock_window = (xlib.XCreateWindow)(
+          xdisplay,
+          xid,
+          x,
+          y,
+          1,
+          1,
+          0,
+          0,
+          xlib::InputOnly as u32,
+          ptr::null_mut(),
+          0,
+          ptr::null_mut(),
+        );
+        (xlib.XMapWindow)(xdisplay, lock_window);
+        LOCK_WINDOW.with(|cell| cell.set(lock_window));
+        lock_window
+      }
+    };
+    let grabbed = grab_pointer(xlib, xdisplay, xid, confine_to);
+    if !grabbed {
+      CURSOR_GRABBED.with(|grabbed| grabbed.set(false));
+    }
+    grabbed
+  }
+}
+
+/// Captures the pointer for `window`, so that it's reported outside of the window, or releases it.
+/// Returns whether the pointer could be captured.
+pub fn set_mouse_capture(window: &gdk::Window, capture: bool) -> bool {
+  let (xlib, xdisplay) = match xdisplay(&window.display()) {
+    Some(xdisplay) => xdisplay,
+    None => return false,
+  };
+  unsafe {
+    let xid = gdk_x11_window_get_xid(window.to_glib_none().0);
+    let was_capturing = CAPTURE_WINDOW.with(|capture_window| {
+      let was_capturing = capture_window.get() == xid;
+      if capture {
+        capture_window.set(xid);
+      } else if was_capturing {
+        capture_window.set(0);
+      }
+      was_capturing
+    });
+    // The grab of the cursor already reports the pointer outside of the window.
+    if CURSOR_GRABBED.with(Cell::get) {
+      return true;
+    }
+    if capture {
+      grab_pointer(xlib, xdisplay, xid, 0)
+    } else {
+      if was_capturing {
+        (xlib.XUngrabPointer)(xdisplay, xlib::CurrentTime);
+        (xlib.XFlush)(xdisplay);
+      }
+      true
+    }
+  }
+}
+
+unsafe fn grab_pointer(
+  xlib: &xlib::Xlib,
+  xdisplay: *mut xlib::Display,
+  xid: xlib::Window,
+  confine_to: xlib::Window,
+) -> bool {
+  let event_mask = xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask;
+  (xlib.XGrabPointer)(
+    xdisplay,
+    xid,
+    xlib::True,
+    event_mask as u32,
+    xlib::GrabModeAsync,
+    xlib::GrabModeAsync,
+    confine_to,
+    0,
+    xlib::CurrentTime,
+  ) == xlib::GrabSuccess
+}
+
+/// Grabs the keyboard for `window`, so that the key combinations of the window manager are sent to
+/// it, or releases it. Returns whether the keyboard could be grabbed.
+pub fn set_keyboard_grab(window: &gdk::Window, grab: bool) -> bool {
+  let (xlib, xdisplay) = match xdisplay(&window.display()) {
+    Some(xdisplay) => xdisplay,
+    None => return false,
+  };
+  unsafe {
+    if !grab {
+      (xlib.XUngrabKeyboard)(xdisplay, xlib::CurrentTime);
+      (xlib.XFlush)(xdisplay);
+      return true;
+    }
+    let xid = gdk_x11_window_get_xid(window.to_glib_none().0);
+    (xlib.XGrabKeyboard)(
+      xdisplay,
+      xid,
+      xlib::True,
+      xlib::GrabModeAsync,
+      xlib::GrabModeAsync,
+      xlib::CurrentTime,
+    ) == xlib::GrabSuccess
+  }
+}
+
+/// The position of the pointer in the window, or its center when the pointer is outside of it.
+unsafe fn locked_position(
+  xlib: &xlib::Xlib,
+  xdisplay: *mut xlib::Display,
+  xid: xlib::Window,
+  window: &gdk::Window,
+) -> (i32, i32) {
+  let scale_factor = window.scale_factor();
+  let (width, height) = (
+    window.width() * scale_factor,
+    window.height() * scale_factor,
+  );
+  let (mut root, mut child) = (0, 0);
+  let (mut root_x, mut root_y, mut x, mut y, mut mask) = (0, 0, 0, 0, 0);
+  let on_screen = (xlib.XQueryPointer)(
+    xdisplay,
+    xid,
+    &mut root,
+    &mut child,
+    &mut root_x,
+    &mut root_y,
+    &mut x,
+    &mut y,
+    &mut mask,
+  );
+  if on_screen == xlib::False || !(0..width).contains(&x) || !(0..height).contains(&y) {
+    (width / 2, height / 2)
+  } else {
+    (x, y)
+  }
+}
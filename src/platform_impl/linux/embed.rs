@@ -0,0 +1,54 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// Embedding in the X11 windows of other toolkits, by reparenting the GDK window into a foreign
+// GDK window that wraps them. Wayland has no protocol for it.
+use std::os::raw::c_ulong;
+
+use glib::translate::{from_glib_full, ToGlibPtr};
+use gtk::prelude::*;
+use raw_window_handle::RawWindowHandle;
+
+use super::virtual_desktop::is_x11;
+
+extern "C" {
+  fn gdk_x11_window_foreign_new_for_display(
+    display: *mut gdk_sys::GdkDisplay,
+    window: c_ulong,
+  ) -> *mut gdk_sys::GdkWindow;
+}
+
+pub fn embed_in_foreign_parent(
+  window: &gtk::ApplicationWindow,
+  parent: RawWindowHandle,
+  (x, y): (i32, i32),
+) {
+  let xid = match parent {
+    RawWindowHandle::Xlib(handle) => handle.window,
+    RawWindowHandle::Xcb(handle) => handle.window as c_ulong,
+    _ => 0,
+  };
+  let display = window.display();
+  if xid == 0 || !is_x11(&display) {
+    log::warn!("Only X11 windows can be the foreign parent of a window");
+    return;
+  }
+
+  // The GDK window only exists once the window is realized.
+  window.realize();
+  let gdk_window = match window.window() {
+    Some(gdk_window) => gdk_window,
+    None => return,
+  };
+
+  let foreign_parent: Option<gdk::Window> = unsafe {
+    from_glib_full(gdk_x11_window_foreign_new_for_display(
+      display.to_glib_none().0,
+      xid,
+    ))
+  };
+  match foreign_parent {
+    Some(foreign_parent) => gdk_window.reparent(&foreign_parent, x, y),
+    None => log::warn!("Failed to wrap the foreign parent {:#x} of a window", xid),
+  }
+}
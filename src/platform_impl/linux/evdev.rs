@@ -0,0 +1,291 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The input devices which GDK doesn't expose, such as the joysticks, jog wheels and 3D mice, read
+// from their evdev nodes. A thread polls the nodes which the user can read, and watches
+// `/dev/input` for the devices being connected or disconnected.
+use std::{
+  collections::HashMap,
+  ffi::{OsStr, OsString},
+  fs::{self, File, OpenOptions},
+  io::{self, Read},
+  iter, mem,
+  os::unix::{
+    ffi::OsStrExt,
+    fs::OpenOptionsExt,
+    io::{AsRawFd, FromRawFd},
+  },
+  path::Path,
+  thread,
+};
+
+use crate::event::{DeviceEvent, DeviceInfo, ElementState};
+
+const INPUT_DIR: &str = "/dev/input";
+
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const KEY_MAX: usize = 0x2ff;
+const KEY_A: usize = 30;
+const BTN_LEFT: usize = 0x110;
+const BTN_GAMEPAD: usize = 0x130;
+const BTN_TOOL_PEN: usize = 0x140;
+const BTN_TOUCH: usize = 0x14a;
+
+pub enum EvdevMessage {
+  Added(usize, DeviceInfo),
+  Removed(usize),
+  Event(usize, DeviceEvent),
+}
+
+/// Starts sending the devices and their events to `evdev_tx`. The devices are numbered from 1, the
+/// id 0 being the one of the X11 pointer.
+pub fn spawn_evdev_thread(evdev_tx: glib::Sender<EvdevMessage>) {
+  thread::spawn(move || {
+    let inotify = match watch_input_dir() {
+      Ok(inotify) => inotify,
+      Err(e) => {
+        log::warn!("Failed to watch {}: {}", INPUT_DIR, e);
+        return;
+      }
+    };
+    let mut devices = Devices {
+      evdev_tx,
+      opened: HashMap::new(),
+      next_id: 1,
+    };
+    if let Ok(entries) = fs::read_dir(INPUT_DIR) {
+      for entry in entries.flatten() {
+        if !devices.open(&entry.file_name()) {
+          return;
+        }
+      }
+    }
+    let _ = devices.run(inotify);
+  });
+}
+
+struct Devices {
+  evdev_tx: glib::Sender<EvdevMessage>,
+  /// The opened devices by the name of their node, with their id.
+  opened: HashMap<OsString, (File, usize)>,
+  next_id: usize,
+}
+
+impl Devices {
+  /// Polls the devices and the directory until the receiver is dropped with the event loop.
+  fn run(&mut self, mut inotify: File) -> Option<()> {
+    loop {
+      let names: Vec<OsString> = self.opened.keys().cloned().collect();
+      let mut fds: Vec<libc::pollfd> = iter::once(inotify.as_raw_fd())
+        .chain(names.iter().map(|name| self.opened[name].0.as_raw_fd()))
+        .map(|fd| libc::pollfd {
+          fd,
+          events: libc::POLLIN,
+          revents: 0,
+        })
+        .collect();
+      if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) } < 0 {
+        if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+          continue;
+        }
+        return None;
+      }
+
+      for (name, fd) in names.iter().zip(&fds[1..]) {
+        if fd.revents != 0 && !self.read_events(name) {
+          return None;
+        }
+      }
+      if fds[0].revents != 0 {
+        for (mask, name) in read_inotify_events(&mut inotify)? {
+          let sent = if mask & libc::IN_DELETE != 0 {
+            self.close(&name)
+          } else {
+            // The nodes are readable once udev gave their permissions, after their creation.
+            self.open(&name)
+          };
+          if !sent {
+            return None;
+          }
+        }
+      }
+    }
+  }
+
+  /// Opens a device unless it's opened already, returning whether the receiver is still alive.
+  fn open(&mut self, name: &OsStr) -> bool {
+    if !name.as_bytes().starts_with(b"event") || self.opened.contains_key(name) {
+      return true;
+    }
+    let file = match OpenOptions::new()
+      .read(true)
+      .custom_flags(libc::O_NONBLOCK)
+      .open(Path::new(INPUT_DIR).join(name))
+    {
+      Ok(file) => file,
+      Err(_) => return true,
+    };
+    let info = match device_info(&file) {
+      Some(info) => info,
+      None => return true,
+    };
+    let id = self.next_id;
+    self.next_id += 1;
+    self.opened.insert(name.to_owned(), (file, id));
+    self.send(EvdevMessage::Added(id, info))
+  }
+
+  fn close(&mut self, name: &OsStr) -> bool {
+    match self.opened.remove(name) {
+      Some((_, id)) => self.send(EvdevMessage::Removed(id)),
+      None => true,
+    }
+  }
+
+  fn read_events(&mut self, name: &OsStr) -> bool {
+    let (file, id) = match self.opened.get_mut(name) {
+      Some((file, id)) => (file, *id),
+      None => return true,
+    };
+    let mut events = [unsafe { mem::zeroed::<libc::input_event>() }; 64];
+    let read = unsafe {
+      let buffer =
+        std::slice::from_raw_parts_mut(events.as_mut_ptr() as *mut u8, mem::size_of_val(&events));
+      file.read(buffer)
+    };
+    let len = match read {
+      Ok(read) => read / mem::size_of::<libc::input_event>(),
+      Err(e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+      // The device was disconnected, before its node is deleted.
+      Err(_) => return self.close(name),
+    };
+    events[..len]
+      .iter()
+      .filter_map(device_event)
+      .all(|event| self.send(EvdevMessage::Event(id, event)))
+  }
+
+  fn send(&self, message: EvdevMessage) -> bool {
+    self.evdev_tx.send(message).is_ok()
+  }
+}
+
+fn device_event(event: &libc::input_event) -> Option<DeviceEvent> {
+  match event.type_ {
+    EV_ABS | EV_REL => Some(DeviceEvent::Motion {
+      axis: event.code as _,
+      value: event.value as f64,
+    }),
+    // The value 2 is the autorepeat of the key.
+    EV_KEY if event.value == 0 || event.value == 1 => Some(DeviceEvent::Button {
+      button: event.code as _,
+      state: if event.value == 1 {
+        ElementState::Pressed
+      } else {
+        ElementState::Released
+      },
+    }),
+    _ => None,
+  }
+}
+
+fn watch_input_dir() -> io::Result<File> {
+  unsafe {
+    let fd = libc::inotify_init1(libc::IN_CLOEXEC);
+    if fd < 0 {
+      return Err(io::Error::last_os_error());
+    }
+    let inotify = File::from_raw_fd(fd);
+    let path = b"/dev/input\0";
+    let mask = libc::IN_CREATE | libc::IN_ATTRIB | libc::IN_DELETE;
+    if libc::inotify_add_watch(fd, path.as_ptr() as _, mask) < 0 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(inotify)
+  }
+}
+
+/// The masks of the events of the directory, with the names of their files.
+fn read_inotify_events(inotify: &mut File) -> Option<Vec<(u32, OsString)>> {
+  let mut buffer = [0u64; 512];
+  let buffer = unsafe {
+    std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, mem::size_of_val(&buffer))
+  };
+  let len = inotify.read(buffer).ok()?;
+  let header_size = mem::size_of::<libc::inotify_event>();
+  let mut events = Vec::new();
+  let mut offset = 0;
+  while offset + header_size <= len {
+    let event = unsafe { &*(buffer.as_ptr().add(offset) as *const libc::inotify_event) };
+    let name = &buffer[offset + header_size..offset + header_size + event.len as usize];
+    let name = &name[..name.iter().position(|&c| c == 0).unwrap_or(name.len())];
+    events.push((event.mask, OsStr::from_bytes(name).to_owned()));
+    offset += header_size + event.len as usize;
+  }
+  Some(events)
+}
+
+// `_IOC(_IOC_READ, 'E', nr, size)`, the requests of evdev which read into a buffer.
+fn eviocg(nr: u32, size: usize) -> u32 {
+  2 << 30 | (size as u32) << 16 | (b'E' as u32) << 8 | nr
+}
+
+unsafe fn ioctl_read<T>(file: &File, nr: u32, buffer: &mut T) -> bool {
+  let request = eviocg(nr, mem::size_of::<T>());
+  libc::ioctl(file.as_raw_fd(), request as _, buffer as *mut T) >= 0
+}
+
+fn has_bit(bits: &[u8], bit: usize) -> bool {
+  bits
+    .get(bit / 8)
+    .map_or(false, |byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// The identity of a device, unless it's a mouse, a keyboard, a touchpad, a tablet, a gamepad, or a
+/// device without axes.
+fn device_info(file: &File) -> Option<DeviceInfo> {
+  unsafe {
+    // EVIOCGBIT(0), the types of the events.
+    let mut types = [0u8; 4];
+    if !ioctl_read(file, 0x20, &mut types)
+      || !(has_bit(&types, EV_ABS as usize) || has_bit(&types, EV_REL as usize))
+    {
+      return None;
+    }
+    // EVIOCGBIT(EV_KEY), the keys and the buttons.
+    let mut keys = [0u8; KEY_MAX / 8 + 1];
+    if has_bit(&types, EV_KEY as usize) && !ioctl_read(file, 0x20 + EV_KEY as u32, &mut keys) {
+      return None;
+    }
+    if [KEY_A, BTN_LEFT, BTN_GAMEPAD, BTN_TOOL_PEN, BTN_TOUCH]
+      .iter()
+      .any(|&key| has_bit(&keys, key))
+    {
+      return None;
+    }
+
+    // EVIOCGID
+    let mut id: libc::input_id = mem::zeroed();
+    let ids = if ioctl_read(file, 0x02, &mut id) && id.vendor != 0 {
+      (Some(id.vendor), Some(id.product))
+    } else {
+      (None, None)
+    };
+    // EVIOCGNAME
+    let mut name = [0u8; 256];
+    let name = if ioctl_read(file, 0x06, &mut name) {
+      let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+      Some(String::from_utf8_lossy(&name[..len]).into_owned())
+    } else {
+      None
+    };
+
+    Some(DeviceInfo {
+      name,
+      vendor_id: ids.0,
+      product_id: ids.1,
+    })
+  }
+}
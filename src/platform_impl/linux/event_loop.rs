@@ -2,37 +2,68 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-  cell::RefCell,
-  collections::{HashSet, VecDeque},
+  cell::{Cell, RefCell},
+  collections::{HashMap, HashSet, VecDeque},
   error::Error,
   process,
   rc::Rc,
-  sync::{mpsc::SendError, Mutex},
-  time::Instant,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
 };
 
-use gdk::{Cursor, CursorType, EventKey, EventMask, WindowEdge, WindowState};
+use gdk::{
+  AxisUse, Cursor, CursorType, EventKey, EventMask, EventType, InputSource, ModifierType,
+  WindowEdge, WindowState,
+};
 use gio::{prelude::*, Cancellable};
-use glib::{source::Priority, Continue, MainContext};
-use gtk::{prelude::*, AboutDialog, ApplicationWindow, Inhibit};
+use glib::{source::Priority, translate::ToGlibPtr, Continue, MainContext};
+use gtk::{prelude::*, AboutDialog, DirectionType, Inhibit};
 
 use crate::{
   accelerator::AcceleratorId,
+  click_count::{ClickCounter, DEFAULT_DISTANCE, DEFAULT_INTERVAL},
   dpi::{PhysicalPosition, PhysicalSize},
-  event::{DeviceId as RootDeviceId, ElementState, Event, MouseButton, StartCause, WindowEvent},
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
-  keyboard::ModifiersState,
+  error::OsError as RootOsError,
+  event::{
+    CloseResponse, DeviceEvent, DeviceId as RootDeviceId, DeviceInfo, ElementState, Event,
+    FocusDirection, FullscreenTransition, Ime, MouseButton, MouseScrollDelta, PenHover, PenInput,
+    StartCause, SuspendReason, Touch, TouchPhase, WindowEvent,
+  },
+  event_loop::{
+    ControlFlow, DeviceEventFilter, EventLoopClosed, EventLoopWindowTarget as RootELW,
+    Priority as UserEventPriority, SendEventError, TimerId,
+  },
+  keyboard::{Key, KeyRepeatInfo, LockKey, ModifiersState},
   menu::{MenuItem, MenuType},
   monitor::MonitorHandle as RootMonitorHandle,
+  platform::{
+    pump_events::PumpStatus,
+    unix_signals::{ExitSignal, SignalAction},
+  },
   platform_impl::platform::window::hit_test,
-  window::{CursorIcon, WindowId as RootWindowId},
+  user_event_queue::UserEventQueue,
+  window::{
+    CursorIcon, Edges, Fullscreen, Theme, TitlebarButtons, WindowId as RootWindowId, WindowLevel,
+  },
 };
 
+#[cfg(target_os = "linux")]
+use super::evdev::{self, EvdevMessage};
 use super::{
-  keyboard,
+  cursor_grab,
+  ime::ImeContext,
+  keyboard, keyboard_layout,
   monitor::MonitorHandle,
-  window::{WindowId, WindowRequest},
-  DeviceId,
+  raw_input,
+  virtual_desktop::{self, VirtualDesktop},
+  window::{
+    apply_decorations, update_launcher_count, update_launcher_progress, GeometryHints, WindowId,
+    WindowRequest,
+  },
+  CloseHandle, DeviceId, OsError,
 };
 
 pub struct EventLoopWindowTarget<T> {
@@ -42,8 +73,16 @@ pub struct EventLoopWindowTarget<T> {
   pub(crate) app: gtk::Application,
   /// Window Ids of the application
   pub(crate) windows: Rc<RefCell<HashSet<WindowId>>>,
+  /// Size constraints of the windows, merged into their geometry hints
+  pub(crate) geometry_hints: Rc<RefCell<HashMap<WindowId, GeometryHints>>>,
   /// Window requests sender
   pub(crate) window_requests_tx: glib::Sender<(WindowId, WindowRequest)>,
+  /// Sources of the running timers
+  timers: Rc<RefCell<HashMap<TimerId, glib::SourceId>>>,
+  /// When the device events are filtered out
+  device_event_filter: Rc<Cell<DeviceEventFilter>>,
+  /// Connected evdev devices by id
+  evdev_devices: Rc<RefCell<HashMap<usize, DeviceInfo>>>,
   _marker: std::marker::PhantomData<T>,
 }
 
@@ -70,17 +109,106 @@ impl<T> EventLoopWindowTarget<T> {
     let handle = MonitorHandle::new(&self.display, number);
     Some(RootMonitorHandle { inner: handle })
   }
+
+  #[inline]
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    virtual_desktop::virtual_desktops(&self.display)
+  }
+
+  #[inline]
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    virtual_desktop::current_virtual_desktop(&self.display)
+  }
+
+  pub fn key_from_scancode(&self, scancode: u32) -> Option<Key<'static>> {
+    if scancode > u16::MAX as u32 {
+      return None;
+    }
+    let group = keyboard_layout::current_group(&self.display).unwrap_or(0);
+    keyboard::key_from_scancode(scancode as u16, group)
+  }
+
+  pub fn scancode_from_key(&self, key: &Key<'_>) -> Option<u32> {
+    let group = keyboard_layout::current_group(&self.display).unwrap_or(0);
+    keyboard::scancode_from_key(key, group)
+  }
+
+  pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+    keyboard_layout::key_repeat_info(&self.display)
+  }
+
+  pub fn modifiers_state(&self) -> ModifiersState {
+    gdk::Keymap::for_display(&self.display).map_or_else(ModifiersState::empty, |keymap| {
+      keyboard::get_modifiers_state(ModifierType::from_bits_truncate(keymap.modifier_state()))
+    })
+  }
+
+  pub fn lock_key_state(&self, key: LockKey) -> Option<bool> {
+    let keymap = gdk::Keymap::for_display(&self.display)?;
+    Some(match key {
+      LockKey::CapsLock => keymap.caps_lock_state(),
+      LockKey::NumLock => keymap.num_lock_state(),
+      LockKey::ScrollLock => keymap.scroll_lock_state(),
+    })
+  }
+
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    let window_requests_tx = self.window_requests_tx.clone();
+    let source = glib::timeout_add_local(interval, move || {
+      if let Err(e) = window_requests_tx.send((WindowId::dummy(), WindowRequest::Timer(id))) {
+        log::warn!("Fail to send timer request: {}", e);
+      }
+      Continue(true)
+    });
+    if let Some(previous) = self.timers.borrow_mut().insert(id, source) {
+      previous.remove();
+    }
+  }
+
+  pub fn cancel_timer(&self, id: TimerId) {
+    if let Some(source) = self.timers.borrow_mut().remove(&id) {
+      source.remove();
+    }
+  }
+
+  #[inline]
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  #[inline]
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    self.device_event_filter.set(filter);
+  }
+
+  pub fn device_info(&self, device_id: DeviceId) -> Option<DeviceInfo> {
+    self.evdev_devices.borrow().get(&device_id.0).cloned()
+  }
 }
 
 pub struct EventLoop<T: 'static> {
   /// Window target.
   window_target: RootELW<T>,
-  /// User event sender for EventLoopProxy
-  user_event_tx: glib::Sender<T>,
-  /// User event receiver
-  user_event_rx: glib::Receiver<T>,
-  /// Window requests receiver
-  window_requests_rx: glib::Receiver<(WindowId, WindowRequest)>,
+  /// User events sent by the EventLoopProxy
+  user_events: Arc<UserEventQueue<T>>,
+  /// Sender notifying the event loop of a new user event, for EventLoopProxy
+  user_event_tx: glib::Sender<()>,
+  /// User event notification receiver, taken when the event loop runs for the first time
+  user_event_rx: Option<glib::Receiver<()>>,
+  /// Window requests receiver, taken when the event loop runs for the first time
+  window_requests_rx: Option<glib::Receiver<(WindowId, WindowRequest)>>,
+  /// Events waiting to be dispatched to the callback
+  events: Rc<Mutex<Vec<Event<'static, T>>>>,
+  /// Whether an `EventLoopProxy` woke the event loop up without a user event
+  woken: Arc<AtomicBool>,
+  /// `Event::ExitRequested` waiting to be dispatched to the callback
+  exit_request: Rc<ExitRequest>,
+  /// Sources of the signals turned into events
+  signal_sources: HashMap<ExitSignal, glib::SourceId>,
+  /// Control flow set by the callback
+  control_flow: ControlFlow,
+  /// Whether the event loop runs, i.e. `StartCause::Init` was sent and `Event::LoopDestroyed` wasn't
+  running: bool,
+  /// Sources attached to the main context, removed when the event loop is dropped
+  sources: Vec<glib::SourceId>,
 }
 
 impl<T: 'static> EventLoop<T> {
@@ -89,11 +217,42 @@ impl<T: 'static> EventLoop<T> {
     EventLoop::new_gtk().expect("Failed to initialize any backend!")
   }
 
+  pub fn try_new() -> Result<EventLoop<T>, RootOsError> {
+    assert_is_main_thread("new_any_thread");
+    EventLoop::new_gtk().map_err(|error| {
+      log::warn!("Failed to initialize the GTK backend: {}", error);
+      os_error!(OsError)
+    })
+  }
+
   fn new_gtk() -> Result<EventLoop<T>, Box<dyn Error>> {
     let app = gtk::Application::new(None, gio::ApplicationFlags::empty());
+    // Registering with the session manager makes it emit `query-end` before ending the session.
+    app.set_register_session(true);
     let cancellable: Option<&Cancellable> = None;
     app.register(cancellable)?;
 
+    let woken = Arc::new(AtomicBool::new(false));
+    let exit_request = Rc::new(ExitRequest::default());
+    let app_ = app.downgrade();
+    let woken_ = woken.clone();
+    let exit_request_ = exit_request.clone();
+    app.connect_local("query-end", false, move |_| {
+      if let Some(app) = app_.upgrade() {
+        if exit_request_.inhibit.get().is_none() {
+          let cookie = app.inhibit(
+            None::<&gtk::Window>,
+            gtk::ApplicationInhibitFlags::LOGOUT,
+            Some("Waiting for the application to exit"),
+          );
+          exit_request_.inhibit.set(Some(cookie));
+          exit_request_.requested.set(true);
+          woken_.store(true, Ordering::Release);
+        }
+      }
+      None
+    })?;
+
     // Create event loop window target.
     let (window_requests_tx, window_requests_rx) = glib::MainContext::channel(Priority::default());
     let display = gdk::Display::default()
@@ -102,7 +261,11 @@ impl<T: 'static> EventLoop<T> {
       display,
       app,
       windows: Rc::new(RefCell::new(HashSet::new())),
+      geometry_hints: Rc::new(RefCell::new(HashMap::new())),
       window_requests_tx,
+      timers: Rc::new(RefCell::new(HashMap::new())),
+      device_event_filter: Rc::new(Cell::new(DeviceEventFilter::default())),
+      evdev_devices: Rc::new(RefCell::new(HashMap::new())),
       _marker: std::marker::PhantomData,
     };
 
@@ -115,30 +278,114 @@ impl<T: 'static> EventLoop<T> {
         p: window_target,
         _marker: std::marker::PhantomData,
       },
+      user_events: Arc::new(UserEventQueue::new()),
       user_event_tx,
-      user_event_rx,
-      window_requests_rx,
+      user_event_rx: Some(user_event_rx),
+      window_requests_rx: Some(window_requests_rx),
+      events: Rc::new(Mutex::new(Vec::new())),
+      woken,
+      exit_request,
+      signal_sources: HashMap::new(),
+      control_flow: ControlFlow::default(),
+      running: false,
+      sources: Vec::new(),
     };
 
     Ok(event_loop)
   }
 
   #[inline]
-  pub fn run<F>(self, callback: F) -> !
+  pub fn run<F>(mut self, callback: F) -> !
   where
     F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow) + 'static,
   {
-    self.run_return(callback);
-    process::exit(0)
+    let exit_code = self.run_return(callback);
+    process::exit(exit_code)
   }
 
-  pub(crate) fn run_return<F>(self, mut callback: F)
+  pub fn run_return<F>(&mut self, mut callback: F) -> i32
   where
-    F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow) + 'static,
+    F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
+  {
+    self.start();
+    loop {
+      if let Some(exit_code) = self.dispatch_events(&mut callback) {
+        return exit_code;
+      }
+      match self.control_flow {
+        ControlFlow::WaitUntil(deadline) => main_iteration_until(deadline),
+        _ => {
+          gtk::main_iteration();
+        }
+      }
+    }
+  }
+
+  pub fn pump_events<F>(&mut self, timeout: Option<Duration>, mut callback: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
   {
-    let mut control_flow = ControlFlow::default();
-    let window_target = self.window_target;
-    let (event_tx, event_rx) = glib::MainContext::channel::<Event<'_, T>>(Priority::default());
+    if !self.running {
+      self.start();
+    }
+
+    if self.events.lock().unwrap().is_empty()
+      && !self.woken.load(Ordering::Acquire)
+      && !gtk::events_pending()
+    {
+      let resume = match self.control_flow {
+        ControlFlow::WaitUntil(deadline) => Some(deadline),
+        _ => None,
+      };
+      let deadline = match timeout.map(|timeout| Instant::now() + timeout) {
+        Some(deadline) => Some(resume.map_or(deadline, |resume| resume.min(deadline))),
+        None => resume,
+      };
+      match (timeout, deadline) {
+        (Some(timeout), _) if timeout == Duration::from_secs(0) => (),
+        (_, Some(deadline)) => main_iteration_until(deadline),
+        (_, None) => {
+          gtk::main_iteration_do(true);
+        }
+      }
+    }
+    while gtk::events_pending() {
+      gtk::main_iteration_do(false);
+    }
+
+    match self.dispatch_events(&mut callback) {
+      Some(exit_code) => PumpStatus::Exit(exit_code),
+      None => PumpStatus::Continue,
+    }
+  }
+
+  /// Starts the event loop, attaching the event sources to the main context the first time.
+  fn start(&mut self) {
+    let context = MainContext::default();
+    context.push_thread_default();
+
+    match (self.user_event_rx.take(), self.window_requests_rx.take()) {
+      (Some(user_event_rx), Some(window_requests_rx)) => {
+        self.sources = self.attach_event_sources(&context, user_event_rx, window_requests_rx)
+      }
+      // The sources are still attached from a previous run.
+      _ => self
+        .events
+        .lock()
+        .unwrap()
+        .push(Event::NewEvents(StartCause::Init)),
+    }
+    self.running = true;
+  }
+
+  fn attach_event_sources(
+    &self,
+    context: &MainContext,
+    user_event_rx: glib::Receiver<()>,
+    window_requests_rx: glib::Receiver<(WindowId, WindowRequest)>,
+  ) -> Vec<glib::SourceId> {
+    let window_target = &self.window_target;
+    let (event_tx, event_rx) = glib::MainContext::channel::<Event<'static, T>>(Priority::default());
 
     // Send StartCause::Init event
     let event_tx_ = event_tx.clone();
@@ -149,628 +396,1335 @@ impl<T: 'static> EventLoop<T> {
     });
     window_target.p.app.activate();
 
-    let context = MainContext::default();
-    context.push_thread_default();
-
     // User event
     let event_tx_ = event_tx.clone();
-    self.user_event_rx.attach(Some(&context), move |event| {
-      if let Err(e) = event_tx_.send(Event::UserEvent(event)) {
-        log::warn!("Failed to send user event to event channel: {}", e);
+    let user_events = self.user_events.clone();
+    let user_event_source = user_event_rx.attach(Some(context), move |()| {
+      // Each notification takes the pending event of the highest priority.
+      if let Some(event) = user_events.pop() {
+        if let Err(e) = event_tx_.send(Event::UserEvent(event)) {
+          log::warn!("Failed to send user event to event channel: {}", e);
+        }
       }
       Continue(true)
     });
 
+    // Keyboard layout
+    if let Some(keymap) = gdk::Keymap::for_display(&window_target.p.display) {
+      let event_tx_ = event_tx.clone();
+      let display = window_target.p.display.clone();
+      let keyboard_layout = RefCell::new(keyboard_layout::current_keyboard_layout(&display));
+      // The layout is the group of the XKB state, which changes with the modifiers.
+      keymap.connect_state_changed(move |_| {
+        let layout = keyboard_layout::current_keyboard_layout(&display);
+        if *keyboard_layout.borrow() == layout {
+          return;
+        }
+        *keyboard_layout.borrow_mut() = layout.clone();
+        if let Some(layout) = layout {
+          if let Err(e) = event_tx_.send(Event::KeyboardLayoutChanged(layout)) {
+            log::warn!(
+              "Failed to send keyboard layout event to event channel: {}",
+              e
+            );
+          }
+        }
+      });
+    }
+
     // Window Request
     let app = window_target.p.app.clone();
     let window_requests_tx = window_target.p.window_requests_tx.clone();
-    self
-      .window_requests_rx
-      .attach(Some(&context), move |(id, request)| {
-        if let Some(window) = app.window_by_id(id.0) {
-          match request {
-            WindowRequest::Title(title) => window.set_title(&title),
-            WindowRequest::Position((x, y)) => window.move_(x, y),
-            WindowRequest::Size((w, h)) => window.resize(w, h),
-            WindowRequest::MinSize((min_width, min_height)) => window
-              .set_geometry_hints::<ApplicationWindow>(
-                None,
-                Some(&gdk::Geometry {
-                  min_width,
-                  min_height,
-                  max_width: 0,
-                  max_height: 0,
-                  base_width: 0,
-                  base_height: 0,
-                  width_inc: 0,
-                  height_inc: 0,
-                  min_aspect: 0f64,
-                  max_aspect: 0f64,
-                  win_gravity: gdk::Gravity::Center,
-                }),
-                gdk::WindowHints::MIN_SIZE,
-              ),
-            WindowRequest::MaxSize((max_width, max_height)) => window
-              .set_geometry_hints::<ApplicationWindow>(
-                None,
-                Some(&gdk::Geometry {
-                  min_width: 0,
-                  min_height: 0,
-                  max_width,
-                  max_height,
-                  base_width: 0,
-                  base_height: 0,
-                  width_inc: 0,
-                  height_inc: 0,
-                  min_aspect: 0f64,
-                  max_aspect: 0f64,
-                  win_gravity: gdk::Gravity::Center,
-                }),
-                gdk::WindowHints::MAX_SIZE,
-              ),
-            WindowRequest::Visible(visible) => {
-              if visible {
-                window.show_all();
-              } else {
-                window.hide();
+    // Whether `Event::Suspended` was sent because all the windows are hidden.
+    let app_hidden = Rc::new(Cell::new(false));
+    // The input methods of the windows, created with their events.
+    let mut ime_contexts = HashMap::new();
+    // The windows grabbing the keyboard while they're focused.
+    let keyboard_grabs = Rc::new(RefCell::new(HashSet::new()));
+    let geometry_hints = window_target.p.geometry_hints.clone();
+    let window_requests_source = window_requests_rx.attach(Some(context), move |(id, request)| {
+      if let Some(window) = app.window_by_id(id.0) {
+        match request {
+          WindowRequest::Title(title) => window.set_title(&title),
+          WindowRequest::Position((x, y)) => window.move_(x, y),
+          WindowRequest::Size((w, h)) => window.resize(w, h),
+          WindowRequest::MinSize(min_size) => {
+            let mut geometry_hints = geometry_hints.borrow_mut();
+            let hints = geometry_hints.entry(id).or_default();
+            hints.min_size = min_size;
+            hints.apply(&window);
+          }
+          WindowRequest::MaxSize(max_size) => {
+            let mut geometry_hints = geometry_hints.borrow_mut();
+            let hints = geometry_hints.entry(id).or_default();
+            hints.max_size = max_size;
+            hints.apply(&window);
+          }
+          WindowRequest::AspectRatio(aspect_ratio) => {
+            let mut geometry_hints = geometry_hints.borrow_mut();
+            let hints = geometry_hints.entry(id).or_default();
+            hints.aspect_ratio = aspect_ratio;
+            hints.apply(&window);
+          }
+          WindowRequest::ResizableEdges(edges) => {
+            let mut geometry_hints = geometry_hints.borrow_mut();
+            let hints = geometry_hints.entry(id).or_default();
+            hints.resizable_edges = edges;
+            hints.apply(&window);
+          }
+          WindowRequest::TitlebarButtons(buttons) => {
+            window.set_deletable(buttons.contains(TitlebarButtons::CLOSE));
+            // The window manager reads the allowed functions from the GDK window.
+            window.realize();
+            if let Some(gdk_window) = window.window() {
+              // Along with `ALL`, the functions are the ones to remove
+              let mut functions = gdk::WMFunction::ALL;
+              if !buttons.contains(TitlebarButtons::MINIMIZE) {
+                functions |= gdk::WMFunction::MINIMIZE;
+              }
+              if !buttons.contains(TitlebarButtons::MAXIMIZE) {
+                functions |= gdk::WMFunction::MAXIMIZE;
+              }
+              if !buttons.contains(TitlebarButtons::CLOSE) {
+                functions |= gdk::WMFunction::CLOSE;
+              }
+              gdk_window.set_functions(functions);
+            }
+          }
+          WindowRequest::Visible(visible) => {
+            if visible {
+              window.show_all();
+            } else {
+              window.hide();
+            }
+          }
+          WindowRequest::ShowWithoutActivating => {
+            window.set_focus_on_map(false);
+            window.show_all();
+            window.set_focus_on_map(true);
+          }
+          WindowRequest::Focus => {
+            // FIXME: replace with present_with_timestamp
+            window.present();
+          }
+          WindowRequest::Raise => {
+            if let Some(gdk_window) = window.window() {
+              gdk_window.raise();
+            }
+          }
+          WindowRequest::Restack(sibling, above) => {
+            let sibling = app
+              .window_by_id(sibling.0)
+              .and_then(|sibling| sibling.window());
+            if let (Some(gdk_window), Some(sibling)) = (window.window(), sibling) {
+              gdk_window.restack(Some(&sibling), above);
+            }
+          }
+          WindowRequest::Resizable(resizable) => window.set_resizable(resizable),
+          WindowRequest::Minimized(minimized) => {
+            if minimized {
+              window.iconify();
+            } else {
+              window.deiconify();
+            }
+          }
+          WindowRequest::Maximized(maximized) => {
+            if maximized {
+              window.maximize();
+            } else {
+              window.unmaximize();
+            }
+          }
+          WindowRequest::MaximizedOn(monitor) => {
+            // Window managers ignore the position of maximized windows
+            if window.is_maximized() {
+              window.unmaximize();
+            }
+            let position = monitor.position();
+            window.move_(position.x, position.y);
+            window.maximize();
+          }
+          WindowRequest::DragWindow => {
+            let display = window.display();
+            if let Some(cursor) = display
+              .default_seat()
+              .and_then(|device_manager| device_manager.pointer())
+            {
+              let (_, x, y) = cursor.position();
+              window.begin_move_drag(1, x, y, 0);
+            }
+          }
+          WindowRequest::Fullscreen(fullscreen) => {
+            // `Entered` and `Exited` are sent from the `window-state-event` once the window
+            // manager is done.
+            let is_fullscreen = window
+              .window()
+              .map(|w| w.state().contains(WindowState::FULLSCREEN))
+              .unwrap_or(false);
+            let transition = match (is_fullscreen, fullscreen.is_some()) {
+              (false, true) => Some(FullscreenTransition::Entering),
+              (true, false) => Some(FullscreenTransition::Exiting),
+              _ => None,
+            };
+            if let Some(transition) = transition {
+              if let Err(e) = event_tx.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::FullscreenTransition(transition),
+                timestamp: Instant::now(),
+              }) {
+                log::warn!(
+                  "Failed to send fullscreen transition event to event channel: {}",
+                  e
+                );
               }
             }
-            WindowRequest::Focus => {
-              // FIXME: replace with present_with_timestamp
-              window.present();
+
+            match fullscreen {
+              Some(Fullscreen::Borderless(Some(monitor))) => window
+                .fullscreen_on_monitor(&window.display().default_screen(), monitor.inner.number()),
+              Some(_) => window.fullscreen(),
+              None => window.unfullscreen(),
             }
-            WindowRequest::Resizable(resizable) => window.set_resizable(resizable),
-            WindowRequest::Minimized(minimized) => {
-              if minimized {
-                window.iconify();
-              } else {
-                window.deiconify();
+          }
+          WindowRequest::InputGrab(grab) => {
+            if let Some(seat) = window.display().default_seat() {
+              if !grab {
+                seat.ungrab();
+              } else if let Some(gdk_window) = window.window() {
+                // The events of the other windows of the application are still delivered to
+                // them, like the ones of dialogs.
+                let status = seat.grab(
+                  &gdk_window,
+                  gdk::SeatCapabilities::ALL,
+                  true,
+                  None,
+                  None,
+                  None,
+                );
+                if status != gdk::GrabStatus::Success {
+                  log::warn!("Fail to grab the input of the window: {:?}", status);
+                }
               }
             }
-            WindowRequest::Maximized(maximized) => {
-              if maximized {
-                window.maximize();
+          }
+          WindowRequest::Decorations {
+            decorations,
+            shadow,
+          } => apply_decorations(&window, decorations, shadow),
+          WindowRequest::Enabled(enabled) => window.set_sensitive(enabled),
+          WindowRequest::WindowLevel(level) => {
+            window.set_keep_above(level > WindowLevel::Normal);
+            window.set_keep_below(level == WindowLevel::AlwaysOnBottom);
+          }
+          WindowRequest::VirtualDesktop(desktop) => {
+            if let Some(gdk_window) = window.window() {
+              virtual_desktop::move_to_virtual_desktop(&gdk_window, desktop);
+            }
+          }
+          WindowRequest::VisibleOnAllWorkspaces(visible) => {
+            if visible {
+              window.stick();
+            } else {
+              window.unstick();
+            }
+          }
+          WindowRequest::BackgroundEffect(effect) => {
+            // Only KWin supports blurring the background, through an X11 property listing
+            // the regions to blur. An empty region means the whole window.
+            if let Some(gdk_window) = window.window() {
+              let property = gdk::Atom::intern("_KDE_NET_WM_BLUR_BEHIND_REGION");
+              if effect.is_some() {
+                gdk::property_change(
+                  &gdk_window,
+                  &property,
+                  &gdk::Atom::intern("CARDINAL"),
+                  32,
+                  gdk::PropMode::Replace,
+                  gdk::ChangeData::ULongs(&[]),
+                );
               } else {
-                window.unmaximize();
+                gdk::property_delete(&gdk_window, &property);
               }
             }
-            WindowRequest::DragWindow => {
+          }
+          WindowRequest::WindowIcon(window_icon) => {
+            if let Some(icon) = window_icon {
+              window.set_icon(Some(&icon.inner.into()));
+            }
+          }
+          WindowRequest::UserAttention(request_type) => {
+            window.set_urgency_hint(request_type.is_some())
+          }
+          WindowRequest::ProgressBar((state, progress)) => {
+            if let Err(e) = update_launcher_progress(state, progress) {
+              log::warn!("Failed to update launcher entry: {}", e);
+            }
+          }
+          WindowRequest::BadgeCount(count) => {
+            if let Err(e) = update_launcher_count(count) {
+              log::warn!("Failed to update launcher entry: {}", e);
+            }
+          }
+          WindowRequest::SetSkipTaskbar(skip) => {
+            window.set_skip_taskbar_hint(skip);
+            window.set_skip_pager_hint(skip);
+          }
+          WindowRequest::Theme(theme) => {
+            // The settings are shared by every window of the screen.
+            if let Some(settings) = window.settings() {
+              match theme {
+                Some(theme) => settings.set_gtk_application_prefer_dark_theme(theme == Theme::Dark),
+                None => settings.reset_property("gtk-application-prefer-dark-theme"),
+              }
+            }
+          }
+          WindowRequest::CursorIcon(cursor) => {
+            if let Some(gdk_window) = window.window() {
               let display = window.display();
-              if let Some(cursor) = display
-                .default_seat()
-                .and_then(|device_manager| device_manager.pointer())
+              match cursor {
+                Some(cr) => gdk_window.set_cursor(
+                  Cursor::from_name(
+                    &display,
+                    match cr {
+                      CursorIcon::Crosshair => "crosshair",
+                      CursorIcon::Hand => "pointer",
+                      CursorIcon::Arrow => "crosshair",
+                      CursorIcon::Move => "move",
+                      CursorIcon::Text => "text",
+                      CursorIcon::Wait => "wait",
+                      CursorIcon::Help => "help",
+                      CursorIcon::Progress => "progress",
+                      CursorIcon::NotAllowed => "not-allowed",
+                      CursorIcon::ContextMenu => "context-menu",
+                      CursorIcon::Cell => "cell",
+                      CursorIcon::VerticalText => "vertical-text",
+                      CursorIcon::Alias => "alias",
+                      CursorIcon::Copy => "copy",
+                      CursorIcon::NoDrop => "no-drop",
+                      CursorIcon::Grab => "grab",
+                      CursorIcon::Grabbing => "grabbing",
+                      CursorIcon::AllScroll => "all-scroll",
+                      CursorIcon::ZoomIn => "zoom-in",
+                      CursorIcon::ZoomOut => "zoom-out",
+                      CursorIcon::EResize => "e-resize",
+                      CursorIcon::NResize => "n-resize",
+                      CursorIcon::NeResize => "ne-resize",
+                      CursorIcon::NwResize => "nw-resize",
+                      CursorIcon::SResize => "s-resize",
+                      CursorIcon::SeResize => "se-resize",
+                      CursorIcon::SwResize => "sw-resize",
+                      CursorIcon::WResize => "w-resize",
+                      CursorIcon::EwResize => "ew-resize",
+                      CursorIcon::NsResize => "ns-resize",
+                      CursorIcon::NeswResize => "nesw-resize",
+                      CursorIcon::NwseResize => "nwse-resize",
+                      CursorIcon::ColResize => "col-resize",
+                      CursorIcon::RowResize => "row-resize",
+                      CursorIcon::Default => "default",
+                    },
+                  )
+                  .as_ref(),
+                ),
+                None => gdk_window.set_cursor(Some(&Cursor::for_display(
+                  &display,
+                  CursorType::BlankCursor,
+                ))),
+              }
+            };
+          }
+          WindowRequest::Opacity(opacity) => window.set_opacity(opacity),
+          WindowRequest::CursorGrab(mode) => {
+            if let Some(gdk_window) = window.window() {
+              if !cursor_grab::set_cursor_grab(&gdk_window, mode) {
+                log::warn!("Failed to grab the cursor");
+              }
+            }
+          }
+          WindowRequest::KeyboardGrab(grab) => {
+            if grab {
+              keyboard_grabs.borrow_mut().insert(id);
+            } else {
+              keyboard_grabs.borrow_mut().remove(&id);
+            }
+            // The keyboard is grabbed again whenever the window is focused.
+            if let Some(gdk_window) = window.window() {
+              if (!grab || window.is_active()) && !cursor_grab::set_keyboard_grab(&gdk_window, grab)
               {
-                let (_, x, y) = cursor.position();
-                window.begin_move_drag(1, x, y, 0);
+                log::warn!("Failed to grab the keyboard");
               }
             }
-            WindowRequest::Fullscreen(fullscreen) => match fullscreen {
-              Some(_) => window.fullscreen(),
-              None => window.unfullscreen(),
-            },
-            WindowRequest::Decorations(decorations) => window.set_decorated(decorations),
-            WindowRequest::AlwaysOnTop(always_on_top) => window.set_keep_above(always_on_top),
-            WindowRequest::WindowIcon(window_icon) => {
-              if let Some(icon) = window_icon {
-                window.set_icon(Some(&icon.inner.into()));
-              }
-            }
-            WindowRequest::UserAttention(request_type) => {
-              window.set_urgency_hint(request_type.is_some())
-            }
-            WindowRequest::SetSkipTaskbar(skip) => window.set_skip_taskbar_hint(skip),
-            WindowRequest::CursorIcon(cursor) => {
-              if let Some(gdk_window) = window.window() {
-                let display = window.display();
-                match cursor {
-                  Some(cr) => gdk_window.set_cursor(
-                    Cursor::from_name(
-                      &display,
-                      match cr {
-                        CursorIcon::Crosshair => "crosshair",
-                        CursorIcon::Hand => "pointer",
-                        CursorIcon::Arrow => "crosshair",
-                        CursorIcon::Move => "move",
-                        CursorIcon::Text => "text",
-                        CursorIcon::Wait => "wait",
-                        CursorIcon::Help => "help",
-                        CursorIcon::Progress => "progress",
-                        CursorIcon::NotAllowed => "not-allowed",
-                        CursorIcon::ContextMenu => "context-menu",
-                        CursorIcon::Cell => "cell",
-                        CursorIcon::VerticalText => "vertical-text",
-                        CursorIcon::Alias => "alias",
-                        CursorIcon::Copy => "copy",
-                        CursorIcon::NoDrop => "no-drop",
-                        CursorIcon::Grab => "grab",
-                        CursorIcon::Grabbing => "grabbing",
-                        CursorIcon::AllScroll => "all-scroll",
-                        CursorIcon::ZoomIn => "zoom-in",
-                        CursorIcon::ZoomOut => "zoom-out",
-                        CursorIcon::EResize => "e-resize",
-                        CursorIcon::NResize => "n-resize",
-                        CursorIcon::NeResize => "ne-resize",
-                        CursorIcon::NwResize => "nw-resize",
-                        CursorIcon::SResize => "s-resize",
-                        CursorIcon::SeResize => "se-resize",
-                        CursorIcon::SwResize => "sw-resize",
-                        CursorIcon::WResize => "w-resize",
-                        CursorIcon::EwResize => "ew-resize",
-                        CursorIcon::NsResize => "ns-resize",
-                        CursorIcon::NeswResize => "nesw-resize",
-                        CursorIcon::NwseResize => "nwse-resize",
-                        CursorIcon::ColResize => "col-resize",
-                        CursorIcon::RowResize => "row-resize",
-                        CursorIcon::Default => "default",
-                      },
-                    )
-                    .as_ref(),
-                  ),
-                  None => gdk_window.set_cursor(Some(&Cursor::for_display(
-                    &display,
-                    CursorType::BlankCursor,
-                  ))),
+          }
+          WindowRequest::MouseCapture(capture) => {
+            if let Some(gdk_window) = window.window() {
+              if !cursor_grab::set_mouse_capture(&gdk_window, capture) {
+                log::warn!("Failed to capture the mouse");
+              }
+            }
+          }
+          #[cfg(feature = "test")]
+          WindowRequest::InjectEvent(event) => {
+            if let Some(gdk_window) = window.window() {
+              super::synthetic_input::inject_event(&gdk_window, event);
+            }
+          }
+          WindowRequest::CursorIgnoreEvents(ignore) => {
+            if ignore {
+              // An empty input shape lets all pointer events pass through the window
+              window.input_shape_combine_region(Some(&cairo::Region::create()));
+            } else {
+              window.input_shape_combine_region(None);
+            }
+          }
+          WindowRequest::MouseCoalescing(enabled) => {
+            if let Some(gdk_window) = window.window() {
+              gdk_window.set_event_compression(enabled);
+            }
+          }
+          WindowRequest::Owner(owner) => match owner.and_then(|owner| app.window_by_id(owner.0)) {
+            Some(owner) => {
+              window.set_transient_for(Some(&owner));
+              if !owner.has_group() {
+                gtk::WindowGroup::new().add_window(&owner);
+              }
+              if let Some(group) = owner.group() {
+                group.add_window(&window);
+              }
+            }
+            None => {
+              window.set_transient_for(None::<&gtk::Window>);
+              if window.has_group() {
+                if let Some(group) = window.group() {
+                  group.remove_window(&window);
                 }
-              };
+              }
             }
-            WindowRequest::WireUpEvents => {
-              // resizing `decorations: false` aka borderless
+          },
+          WindowRequest::WireUpEvents {
+            resizable_borders,
+            resizable_edges,
+            minimize_to_tray,
+            text_input,
+          } => {
+            // resizing `decorations: false` aka borderless
+            if resizable_borders {
               window.add_events(EventMask::POINTER_MOTION_MASK | EventMask::BUTTON_MOTION_MASK);
-              window.connect_motion_notify_event(|window, event| {
-                if window.is_decorated() && window.is_resizable() {
+              let resizable_edges = resizable_edges.clone();
+              // Only reset the cursor when it leaves an edge, so it doesn't override the
+              // cursor icon of the window everywhere else.
+              let on_edge = Cell::new(false);
+              window.connect_motion_notify_event(move |window, event| {
+                if !window.is_decorated() && window.is_resizable() {
                   if let Some(window) = window.window() {
                     let (cx, cy) = event.root();
-                    let edge = hit_test(&window, cx, cy);
-                    // FIXME: calling `window.begin_resize_drag` seems to revert the cursor back to normal style
-                    window.set_cursor(
-                      Cursor::from_name(
-                        &window.display(),
-                        match edge {
-                          WindowEdge::North => "n-resize",
-                          WindowEdge::South => "s-resize",
-                          WindowEdge::East => "e-resize",
-                          WindowEdge::West => "w-resize",
-                          WindowEdge::NorthWest => "nw-resize",
-                          WindowEdge::NorthEast => "ne-resize",
-                          WindowEdge::SouthEast => "se-resize",
-                          WindowEdge::SouthWest => "sw-resize",
-                          _ => "default",
-                        },
-                      )
-                      .as_ref(),
-                    );
+                    let edges = Edges::from_bits_truncate(resizable_edges.load(Ordering::Acquire));
+                    let edge = hit_test(&window, cx, cy, edges);
+                    let cursor = match edge {
+                      WindowEdge::North => "n-resize",
+                      WindowEdge::South => "s-resize",
+                      WindowEdge::East => "e-resize",
+                      WindowEdge::West => "w-resize",
+                      WindowEdge::NorthWest => "nw-resize",
+                      WindowEdge::NorthEast => "ne-resize",
+                      WindowEdge::SouthEast => "se-resize",
+                      WindowEdge::SouthWest => "sw-resize",
+                      _ => "default",
+                    };
+                    let is_on_edge = cursor != "default";
+                    let was_on_edge = on_edge.replace(is_on_edge);
+                    if is_on_edge || was_on_edge {
+                      // FIXME: calling `window.begin_resize_drag` seems to revert the cursor back to normal style
+                      window.set_cursor(Cursor::from_name(&window.display(), cursor).as_ref());
+                    }
                   }
                 }
                 Inhibit(false)
               });
-              window.connect_button_press_event(|window, event| {
-                if window.is_decorated() && window.is_resizable() {
-                  if event.button() == 1 {
-                    if let Some(window) = window.window() {
-                      let (cx, cy) = event.root();
-                      let result = hit_test(&window, cx, cy);
-
-                      // we ignore the `__Unknown` variant so the window receives the click correctly if it is not on the edges.
-                      match result {
-                        WindowEdge::__Unknown(_) => (),
-                        _ => {
-                          window.begin_resize_drag(result, 1, cx as i32, cy as i32, event.time())
-                        }
-                      }
+            }
+            window.connect_button_press_event(move |window, event| {
+              if resizable_borders && !window.is_decorated() && window.is_resizable() {
+                if event.button() == 1 {
+                  if let Some(window) = window.window() {
+                    let (cx, cy) = event.root();
+                    let edges = Edges::from_bits_truncate(resizable_edges.load(Ordering::Acquire));
+                    let result = hit_test(&window, cx, cy, edges);
+
+                    // we ignore the `__Unknown` variant so the window receives the click correctly if it is not on the edges.
+                    match result {
+                      WindowEdge::__Unknown(_) => (),
+                      _ => window.begin_resize_drag(result, 1, cx as i32, cy as i32, event.time()),
                     }
                   }
                 }
+              }
 
-                Inhibit(false)
-              });
+              Inhibit(false)
+            });
 
-              let tx_clone = event_tx.clone();
-              window.connect_delete_event(move |_, _| {
-                if let Err(e) = tx_clone.send(Event::WindowEvent {
-                  window_id: RootWindowId(id),
-                  event: WindowEvent::CloseRequested,
-                }) {
-                  log::warn!("Failed to send window close event to event channel: {}", e);
-                }
-                Inhibit(true)
-              });
+            let tx_clone = event_tx.clone();
+            let requests_tx = window_requests_tx.clone();
+            window.connect_delete_event(move |_, _| {
+              let response =
+                CloseResponse::new(RootWindowId(id), CloseHandle::new(id, requests_tx.clone()));
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::CloseRequested { response },
+                timestamp: Instant::now(),
+              }) {
+                log::warn!("Failed to send window close event to event channel: {}", e);
+              }
+              Inhibit(true)
+            });
 
-              let tx_clone = event_tx.clone();
-              window.connect_configure_event(move |_, event| {
-                let (x, y) = event.position();
-                if let Err(e) = tx_clone.send(Event::WindowEvent {
-                  window_id: RootWindowId(id),
-                  event: WindowEvent::Moved(PhysicalPosition::new(x, y)),
-                }) {
-                  log::warn!("Failed to send window moved event to event channel: {}", e);
-                }
+            let tx_clone = event_tx.clone();
+            window.connect_configure_event(move |_, event| {
+              let (x, y) = event.position();
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::Moved(PhysicalPosition::new(x, y)),
+                timestamp: Instant::now(),
+              }) {
+                log::warn!("Failed to send window moved event to event channel: {}", e);
+              }
 
-                let (w, h) = event.size();
-                if let Err(e) = tx_clone.send(Event::WindowEvent {
-                  window_id: RootWindowId(id),
-                  event: WindowEvent::Resized(PhysicalSize::new(w, h)),
-                }) {
-                  log::warn!(
-                    "Failed to send window resized event to event channel: {}",
-                    e
-                  );
-                }
-                false
-              });
+              let (w, h) = event.size();
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::Resized(PhysicalSize::new(w, h)),
+                timestamp: Instant::now(),
+              }) {
+                log::warn!(
+                  "Failed to send window resized event to event channel: {}",
+                  e
+                );
+              }
+              false
+            });
 
-              let tx_clone = event_tx.clone();
-              window.connect_window_state_event(move |_window, event| {
-                let state = event.new_window_state();
+            let tx_clone = event_tx.clone();
+            let app_hidden = app_hidden.clone();
+            window.connect_window_state_event(move |window, event| {
+              let state = event.new_window_state();
 
+              // The window manager can't be stopped from minimizing the window, so it is
+              // restored and hidden right away instead.
+              if event.changed_mask().contains(WindowState::ICONIFIED)
+                && state.contains(WindowState::ICONIFIED)
+                && minimize_to_tray.load(Ordering::Acquire)
+              {
+                window.deiconify();
+                window.hide();
                 if let Err(e) = tx_clone.send(Event::WindowEvent {
                   window_id: RootWindowId(id),
-                  event: WindowEvent::Focused(state.contains(WindowState::FOCUSED)),
+                  event: WindowEvent::MinimizedToTray,
+                  timestamp: Instant::now(),
                 }) {
                   log::warn!(
-                    "Failed to send window focused event to event channel: {}",
+                    "Failed to send window minimized to tray event to event channel: {}",
                     e
                   );
                 }
-                Inhibit(false)
-              });
+              }
+
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::Focused(state.contains(WindowState::FOCUSED)),
+                timestamp: Instant::now(),
+              }) {
+                log::warn!(
+                  "Failed to send window focused event to event channel: {}",
+                  e
+                );
+              }
 
-              let tx_clone = event_tx.clone();
-              window.connect_destroy_event(move |_, _| {
+              if event.changed_mask().contains(WindowState::FULLSCREEN) {
+                let transition = if state.contains(WindowState::FULLSCREEN) {
+                  FullscreenTransition::Entered
+                } else {
+                  FullscreenTransition::Exited
+                };
                 if let Err(e) = tx_clone.send(Event::WindowEvent {
                   window_id: RootWindowId(id),
-                  event: WindowEvent::Destroyed,
+                  event: WindowEvent::FullscreenTransition(transition),
+                  timestamp: Instant::now(),
                 }) {
                   log::warn!(
-                    "Failed to send window destroyed event to event channel: {}",
+                    "Failed to send fullscreen transition event to event channel: {}",
                     e
                   );
                 }
-                Inhibit(false)
-              });
+              }
 
-              let tx_clone = event_tx.clone();
-              window.connect_enter_notify_event(move |_, _| {
+              // GTK doesn't report windows covered by other windows, only the ones that are
+              // minimized or hidden.
+              let hidden = WindowState::ICONIFIED | WindowState::WITHDRAWN;
+              if event.changed_mask().intersects(hidden) {
                 if let Err(e) = tx_clone.send(Event::WindowEvent {
                   window_id: RootWindowId(id),
-                  event: WindowEvent::CursorEntered {
-                    // FIXME: currently we use a dummy device id, find if we can get device id from gtk
-                    device_id: RootDeviceId(DeviceId(0)),
-                  },
+                  event: WindowEvent::Occluded(state.intersects(hidden)),
+                  timestamp: Instant::now(),
                 }) {
                   log::warn!(
-                    "Failed to send cursor entered event to event channel: {}",
+                    "Failed to send window occluded event to event channel: {}",
                     e
                   );
                 }
-                Inhibit(false)
-              });
 
-              let tx_clone = event_tx.clone();
-              window.connect_motion_notify_event(move |window, _| {
-                let display = window.display();
-                if let Some(cursor) = display
-                  .default_seat()
-                  .and_then(|device_manager| device_manager.pointer())
-                {
-                  let (_, x, y) = cursor.position();
-                  if let Err(e) = tx_clone.send(Event::WindowEvent {
-                    window_id: RootWindowId(id),
-                    event: WindowEvent::CursorMoved {
-                      position: PhysicalPosition::new(x as f64, y as f64),
-                      // FIXME: currently we use a dummy device id, find if we can get device id from gtk
-                      device_id: RootDeviceId(DeviceId(0)),
-                      // this field is depracted so it is fine to pass empty state
-                      modifiers: ModifiersState::empty(),
-                    },
-                  }) {
-                    log::warn!("Failed to send cursor moved event to event channel: {}", e);
+                let all_hidden = window.application().map_or(false, |app| {
+                  app.windows().iter().all(|window| {
+                    window
+                      .window()
+                      .map_or(true, |window| window.state().intersects(hidden))
+                  })
+                });
+                if all_hidden != app_hidden.get() {
+                  app_hidden.set(all_hidden);
+                  let event = if all_hidden {
+                    Event::Suspended(SuspendReason::Hidden)
+                  } else {
+                    Event::Resumed(SuspendReason::Hidden)
+                  };
+                  if let Err(e) = tx_clone.send(event) {
+                    log::warn!("Failed to send suspended event to event channel: {}", e);
                   }
                 }
-                Inhibit(false)
-              });
+              }
+              Inhibit(false)
+            });
 
-              let tx_clone = event_tx.clone();
-              window.connect_leave_notify_event(move |_, _| {
+            let tx_clone = event_tx.clone();
+            window.connect_destroy_event(move |_, _| {
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::Destroyed,
+                timestamp: Instant::now(),
+              }) {
+                log::warn!(
+                  "Failed to send window destroyed event to event channel: {}",
+                  e
+                );
+              }
+              Inhibit(false)
+            });
+
+            let tx_clone = event_tx.clone();
+            window.connect_enter_notify_event(move |_, event| {
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::CursorEntered {
+                  // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                  device_id: RootDeviceId(DeviceId(0)),
+                },
+                timestamp: event_timestamp(event.time()),
+              }) {
+                log::warn!(
+                  "Failed to send cursor entered event to event channel: {}",
+                  e
+                );
+              }
+              Inhibit(false)
+            });
+
+            let tx_clone = event_tx.clone();
+            window.connect_motion_notify_event(move |_, event| {
+              // The position of the event rather than the current one of the pointer, since the
+              // motion events aren't compressed while `Window::set_mouse_coalescing` is disabled.
+              let (x, y) = event.root();
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::CursorMoved {
+                  position: PhysicalPosition::new(x, y),
+                  // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                  device_id: RootDeviceId(DeviceId(0)),
+                  // this field is depracted so it is fine to pass empty state
+                  modifiers: ModifiersState::empty(),
+                },
+                timestamp: event_timestamp(event.time()),
+              }) {
+                log::warn!("Failed to send cursor moved event to event channel: {}", e);
+              }
+              Inhibit(false)
+            });
+
+            let tx_clone = event_tx.clone();
+            window.connect_leave_notify_event(move |_, event| {
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::CursorLeft {
+                  // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                  device_id: RootDeviceId(DeviceId(0)),
+                },
+                timestamp: event_timestamp(event.time()),
+              }) {
+                log::warn!("Failed to send cursor left event to event channel: {}", e);
+              }
+              Inhibit(false)
+            });
+
+            let click_counter = Rc::new(RefCell::new(ClickCounter::default()));
+
+            let tx_clone = event_tx.clone();
+            let press_click_counter = click_counter.clone();
+            window.connect_button_press_event(move |window, event| {
+              // The `2BUTTON_PRESS` and `3BUTTON_PRESS` events follow the presses of the
+              // double-clicks and the triple-clicks, which are counted instead.
+              if event.event_type() != gdk::EventType::ButtonPress {
+                return Inhibit(false);
+              }
+              let button = event.button();
+              let button = match button {
+                1 => MouseButton::Left,
+                2 => MouseButton::Middle,
+                3 => MouseButton::Right,
+                8 => MouseButton::Back,
+                9 => MouseButton::Forward,
+                _ => MouseButton::Other(button as u16),
+              };
+              let (interval, distance) = match window.settings() {
+                Some(settings) => (
+                  Duration::from_millis(settings.gtk_double_click_time() as u64),
+                  settings.gtk_double_click_distance() as f64,
+                ),
+                None => (DEFAULT_INTERVAL, DEFAULT_DISTANCE),
+              };
+              let click_count = press_click_counter.borrow_mut().press(
+                button,
+                event_timestamp(event.time()),
+                event.position(),
+                interval,
+                (distance, distance),
+              );
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::MouseInput {
+                  button,
+                  state: ElementState::Pressed,
+                  click_count,
+                  // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                  device_id: RootDeviceId(DeviceId(0)),
+                  // this field is depracted so it is fine to pass empty state
+                  modifiers: ModifiersState::empty(),
+                },
+                timestamp: event_timestamp(event.time()),
+              }) {
+                log::warn!(
+                  "Failed to send mouse input preseed event to event channel: {}",
+                  e
+                );
+              }
+              Inhibit(false)
+            });
+
+            let tx_clone = event_tx.clone();
+            window.connect_button_release_event(move |_, event| {
+              let button = event.button();
+              let button = match button {
+                1 => MouseButton::Left,
+                2 => MouseButton::Middle,
+                3 => MouseButton::Right,
+                8 => MouseButton::Back,
+                9 => MouseButton::Forward,
+                _ => MouseButton::Other(button as u16),
+              };
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::MouseInput {
+                  button,
+                  state: ElementState::Released,
+                  click_count: click_counter.borrow().release(button),
+                  // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                  device_id: RootDeviceId(DeviceId(0)),
+                  // this field is depracted so it is fine to pass empty state
+                  modifiers: ModifiersState::empty(),
+                },
+                timestamp: event_timestamp(event.time()),
+              }) {
+                log::warn!(
+                  "Failed to send mouse input released event to event channel: {}",
+                  e
+                );
+              }
+              Inhibit(false)
+            });
+
+            window.add_events(EventMask::SCROLL_MASK | EventMask::SMOOTH_SCROLL_MASK);
+            let tx_clone = event_tx.clone();
+            window.connect_event(move |_, event| {
+              if let Some((delta, phase)) = scroll_delta(event) {
                 if let Err(e) = tx_clone.send(Event::WindowEvent {
                   window_id: RootWindowId(id),
-                  event: WindowEvent::CursorLeft {
-                    // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                  event: WindowEvent::MouseWheel {
                     device_id: RootDeviceId(DeviceId(0)),
+                    delta,
+                    phase,
+                    momentum: false,
+                    // this field is depracted so it is fine to pass empty state
+                    modifiers: ModifiersState::empty(),
                   },
+                  timestamp: event_timestamp(event.time()),
                 }) {
-                  log::warn!("Failed to send cursor left event to event channel: {}", e);
+                  log::warn!("Failed to send mouse wheel event to event channel: {}", e);
                 }
-                Inhibit(false)
-              });
+              }
+              Inhibit(false)
+            });
 
-              let tx_clone = event_tx.clone();
-              window.connect_button_press_event(move |_, event| {
-                let button = event.button();
+            window.add_events(EventMask::TOUCHPAD_GESTURE_MASK);
+            let tx_clone = event_tx.clone();
+            // The scale of the current pinch relative to its start.
+            let pinch_scale = Cell::new(1.0);
+            window.connect_event(move |window, event| {
+              let scale_factor = window.scale_factor() as f64;
+              for gesture in gesture_events(event, scale_factor, &pinch_scale) {
                 if let Err(e) = tx_clone.send(Event::WindowEvent {
                   window_id: RootWindowId(id),
-                  event: WindowEvent::MouseInput {
-                    button: match button {
-                      1 => MouseButton::Left,
-                      2 => MouseButton::Middle,
-                      3 => MouseButton::Right,
-                      _ => MouseButton::Other(button as u16),
-                    },
-                    state: ElementState::Pressed,
-                    // FIXME: currently we use a dummy device id, find if we can get device id from gtk
-                    device_id: RootDeviceId(DeviceId(0)),
-                    // this field is depracted so it is fine to pass empty state
-                    modifiers: ModifiersState::empty(),
-                  },
+                  event: gesture,
+                  timestamp: event_timestamp(event.time()),
                 }) {
-                  log::warn!(
-                    "Failed to send mouse input preseed event to event channel: {}",
-                    e
-                  );
+                  log::warn!("Failed to send gesture event to event channel: {}", e);
                 }
-                Inhibit(false)
-              });
+              }
+              Inhibit(false)
+            });
 
-              let tx_clone = event_tx.clone();
-              window.connect_button_release_event(move |_, event| {
-                let button = event.button();
+            window.add_events(EventMask::PROXIMITY_IN_MASK | EventMask::PROXIMITY_OUT_MASK);
+            let tx_clone = event_tx.clone();
+            window.connect_event(move |window, event| {
+              let scale_factor = window.scale_factor() as f64;
+              let pen = pen_input(event, scale_factor)
+                .map(WindowEvent::PenInput)
+                .or_else(|| pen_hover(event, scale_factor).map(WindowEvent::PenHover))
+                .or_else(|| pen_proximity(event));
+              if let Some(pen) = pen {
                 if let Err(e) = tx_clone.send(Event::WindowEvent {
                   window_id: RootWindowId(id),
-                  event: WindowEvent::MouseInput {
-                    button: match button {
-                      1 => MouseButton::Left,
-                      2 => MouseButton::Middle,
-                      3 => MouseButton::Right,
-                      _ => MouseButton::Other(button as u16),
-                    },
-                    state: ElementState::Released,
-                    // FIXME: currently we use a dummy device id, find if we can get device id from gtk
-                    device_id: RootDeviceId(DeviceId(0)),
-                    // this field is depracted so it is fine to pass empty state
-                    modifiers: ModifiersState::empty(),
-                  },
+                  event: pen,
+                  timestamp: event_timestamp(event.time()),
                 }) {
-                  log::warn!(
-                    "Failed to send mouse input released event to event channel: {}",
-                    e
-                  );
+                  log::warn!("Failed to send pen input event to event channel: {}", e);
                 }
-                Inhibit(false)
-              });
-
-              let tx_clone = event_tx.clone();
-              let keyboard_handler = Rc::new(move |event_key: EventKey, element_state| {
-                // if we have a modifier lets send it
-                let mut mods = keyboard::get_modifiers(event_key.clone());
-                if !mods.is_empty() {
-                  // if we release the modifier tell the world
-                  if ElementState::Released == element_state {
-                    mods = ModifiersState::empty();
-                  }
+              }
+              Inhibit(false)
+            });
 
+            window.add_events(EventMask::TOUCH_MASK);
+            let tx_clone = event_tx.clone();
+            window.connect_event(move |window, event| {
+              if let Some(touch) = touch(event, window.scale_factor() as f64) {
+                // GDK doesn't group the touches, so every touch is a frame of its own.
+                let timestamp = event_timestamp(event.time());
+                for event in vec![
+                  WindowEvent::Touch(touch),
+                  WindowEvent::TouchFrame(vec![touch]),
+                ] {
                   if let Err(e) = tx_clone.send(Event::WindowEvent {
                     window_id: RootWindowId(id),
-                    event: WindowEvent::ModifiersChanged(mods),
+                    event,
+                    timestamp,
                   }) {
-                    log::warn!(
-                      "Failed to send modifiers changed event to event channel: {}",
-                      e
-                    );
-                  } else {
-                    // stop here we don't want to send the key event
-                    // as we emit the `ModifiersChanged`
-                    return Continue(true);
+                    log::warn!("Failed to send touch event to event channel: {}", e);
                   }
                 }
+              }
+              Inhibit(false)
+            });
 
-                // todo: implement repeat?
-                let event = keyboard::make_key_event(&event_key, false, None, element_state);
+            let tx_clone = event_tx.clone();
+            let text_input_clone = text_input.clone();
+            let ime = Rc::new(ImeContext::new(&window, move |ime| {
+              let text = match &ime {
+                Ime::Commit(text) if text_input_clone.load(Ordering::Acquire) => Some(text.clone()),
+                _ => None,
+              };
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::Ime(ime),
+                timestamp: Instant::now(),
+              }) {
+                log::warn!("Failed to send IME event to event channel: {}", e);
+              }
+              if let Some(text) = text {
+                if let Err(e) = tx_clone.send(Event::WindowEvent {
+                  window_id: RootWindowId(id),
+                  event: WindowEvent::ReceivedText(text),
+                  timestamp: Instant::now(),
+                }) {
+                  log::warn!("Failed to send received text event to event channel: {}", e);
+                }
+              }
+            }));
+            ime_contexts.insert(id, ime.clone());
 
-                if let Some(event) = event {
-                  if let Err(e) = tx_clone.send(Event::WindowEvent {
-                    window_id: RootWindowId(id),
-                    event: WindowEvent::KeyboardInput {
-                      // FIXME: currently we use a dummy device id, find if we can get device id from gtk
-                      device_id: RootDeviceId(DeviceId(0)),
-                      event,
-                      is_synthetic: false,
-                    },
-                  }) {
-                    log::warn!("Failed to send keyboard event to event channel: {}", e);
+            let tx_clone = event_tx.clone();
+            let compose = keyboard::ComposeContext::new();
+            // The held keys, whose presses sent again by the auto-repeat are repeats.
+            let pressed_keys = Rc::new(RefCell::new(HashSet::new()));
+            let pressed_keys_clone = pressed_keys.clone();
+            let keyboard_grabs_clone = keyboard_grabs.clone();
+            window.connect_focus_out_event(move |window, _| {
+              // The keys released while the window is unfocused aren't sent to it.
+              pressed_keys_clone.borrow_mut().clear();
+              if keyboard_grabs_clone.borrow().contains(&id) {
+                if let Some(gdk_window) = window.window() {
+                  cursor_grab::set_keyboard_grab(&gdk_window, false);
+                }
+              }
+              Inhibit(false)
+            });
+            let keyboard_grabs_clone = keyboard_grabs.clone();
+            window.connect_focus_in_event(move |window, _| {
+              if keyboard_grabs_clone.borrow().contains(&id) {
+                if let Some(gdk_window) = window.window() {
+                  if !cursor_grab::set_keyboard_grab(&gdk_window, true) {
+                    log::warn!("Failed to grab the keyboard");
                   }
                 }
-                Continue(true)
-              });
+              }
+              Inhibit(false)
+            });
+            // The key bindings of the window move the focus with the keys its widgets don't handle.
+            let tx_clone = event_tx.clone();
+            window.connect_move_focus(move |_, direction| {
+              let direction = match direction {
+                DirectionType::TabForward => FocusDirection::Forward,
+                DirectionType::TabBackward => FocusDirection::Backward,
+                DirectionType::Up => FocusDirection::Up,
+                DirectionType::Down => FocusDirection::Down,
+                DirectionType::Left => FocusDirection::Left,
+                DirectionType::Right => FocusDirection::Right,
+                _ => return,
+              };
+              if let Err(e) = tx_clone.send(Event::WindowEvent {
+                window_id: RootWindowId(id),
+                event: WindowEvent::FocusTraversal(direction),
+                timestamp: event_timestamp(gtk::current_event_time()),
+              }) {
+                log::warn!(
+                  "Failed to send focus traversal event to event channel: {}",
+                  e
+                );
+              }
+            });
+            let keyboard_handler = Rc::new(move |event_key: EventKey, element_state| {
+              // The keys typed into an IME only compose its text.
+              if ime.feed(&event_key) {
+                return Continue(true);
+              }
 
-              let handler = keyboard_handler.clone();
-              window.connect_key_press_event(move |_, event_key| {
-                handler(event_key.to_owned(), ElementState::Pressed);
-                Inhibit(false)
-              });
+              // if we have a modifier lets send it
+              let mut mods = keyboard::get_modifiers(event_key.clone());
+              if !mods.is_empty() {
+                // if we release the modifier tell the world
+                if ElementState::Released == element_state {
+                  mods = ModifiersState::empty();
+                }
 
-              let handler = keyboard_handler.clone();
-              window.connect_key_release_event(move |_, event_key| {
-                handler(event_key.to_owned(), ElementState::Released);
-                Inhibit(false)
-              });
-            }
-            WindowRequest::Redraw => window.queue_draw(),
-            WindowRequest::Menu(m) => match m {
-              (None, Some(menu_id)) => {
-                if let Err(e) = event_tx.send(Event::MenuEvent {
-                  window_id: Some(RootWindowId(id)),
-                  menu_id,
-                  origin: MenuType::MenuBar,
+                if let Err(e) = tx_clone.send(Event::WindowEvent {
+                  window_id: RootWindowId(id),
+                  event: WindowEvent::ModifiersChanged(mods),
+                  timestamp: event_timestamp(event_key.time()),
                 }) {
-                  log::warn!("Failed to send menu event to event channel: {}", e);
-                }
-              }
-              (Some(MenuItem::About(_)), None) => {
-                let about = AboutDialog::new();
-                about.show_all();
-                app.add_window(&about);
-              }
-              (Some(MenuItem::Hide), None) => window.hide(),
-              (Some(MenuItem::CloseWindow), None) => window.close(),
-              (Some(MenuItem::Quit), None) => {
-                if let Err(e) = event_tx.send(Event::LoopDestroyed) {
                   log::warn!(
-                    "Failed to send loop destroyed event to event channel: {}",
+                    "Failed to send modifiers changed event to event channel: {}",
                     e
                   );
+                } else {
+                  // stop here we don't want to send the key event
+                  // as we emit the `ModifiersChanged`
+                  return Continue(true);
                 }
               }
-              (Some(MenuItem::EnterFullScreen), None) => {
-                let state = window.window().unwrap().state();
-                if state.contains(WindowState::FULLSCREEN) {
-                  window.unfullscreen();
+
+              let scancode = event_key.hardware_keycode();
+              let is_repeat = if element_state == ElementState::Pressed {
+                !pressed_keys.borrow_mut().insert(scancode)
+              } else {
+                pressed_keys.borrow_mut().remove(&scancode);
+                false
+              };
+              let mut event = keyboard::make_key_event(&event_key, is_repeat, None, element_state);
+              if element_state == ElementState::Pressed {
+                compose.feed(&event_key, event.as_mut());
+              }
+
+              if let Some(event) = event {
+                let text = if text_input.load(Ordering::Acquire) {
+                  event.received_text(keyboard::get_modifiers_state(event_key.state()))
                 } else {
-                  window.fullscreen();
+                  None
+                };
+                if let Err(e) = tx_clone.send(Event::WindowEvent {
+                  window_id: RootWindowId(id),
+                  event: WindowEvent::KeyboardInput {
+                    // FIXME: currently we use a dummy device id, find if we can get device id from gtk
+                    device_id: RootDeviceId(DeviceId(0)),
+                    event,
+                    is_synthetic: false,
+                  },
+                  timestamp: event_timestamp(event_key.time()),
+                }) {
+                  log::warn!("Failed to send keyboard event to event channel: {}", e);
                 }
-              }
-              (Some(MenuItem::Minimize), None) => window.iconify(),
-              _ => {}
-            },
-            WindowRequest::SetMenu((window_menu, accel_group, mut menubar)) => {
-              if let Some(window_menu) = window_menu {
-                // remove all existing elements as we overwrite
-                // but we keep same menubar reference
-                for i in menubar.children() {
-                  menubar.remove(&i);
+                if let Some(text) = text {
+                  if let Err(e) = tx_clone.send(Event::WindowEvent {
+                    window_id: RootWindowId(id),
+                    event: WindowEvent::ReceivedText(text),
+                    timestamp: event_timestamp(event_key.time()),
+                  }) {
+                    log::warn!("Failed to send received text event to event channel: {}", e);
+                  }
                 }
-                // create all new elements
-                window_menu.generate_menu(&mut menubar, &window_requests_tx, &accel_group, id);
-                // make sure all newly added elements are visible
-                menubar.show_all();
               }
+              Continue(true)
+            });
+
+            let handler = keyboard_handler.clone();
+            window.connect_key_press_event(move |_, event_key| {
+              handler(event_key.to_owned(), ElementState::Pressed);
+              Inhibit(false)
+            });
+
+            let handler = keyboard_handler.clone();
+            window.connect_key_release_event(move |_, event_key| {
+              handler(event_key.to_owned(), ElementState::Released);
+              Inhibit(false)
+            });
+          }
+          // The requests are coalesced by `Window::request_redraw`, so each one emits an event.
+          WindowRequest::Redraw => {
+            window.queue_draw();
+            if let Err(e) = event_tx.send(Event::RedrawRequested(RootWindowId(id))) {
+              log::warn!(
+                "Failed to send redraw requested event to event channel: {}",
+                e
+              );
             }
-            WindowRequest::GlobalHotKey(_hotkey_id) => {}
           }
-        } else if id == WindowId::dummy() {
-          match request {
-            WindowRequest::GlobalHotKey(hotkey_id) => {
-              if let Err(e) = event_tx.send(Event::GlobalShortcutEvent(AcceleratorId(hotkey_id))) {
-                log::warn!("Failed to send global hotkey event to event channel: {}", e);
+          WindowRequest::VSyncRedraw => {
+            let event_tx = event_tx.clone();
+            window.add_tick_callback(move |_, _| {
+              if let Err(e) = event_tx.send(Event::RedrawRequested(RootWindowId(id))) {
+                log::warn!(
+                  "Failed to send redraw requested event to event channel: {}",
+                  e
+                );
               }
+              Continue(false)
+            });
+          }
+          // Unlike `close`, `destroy` doesn't emit `delete-event` and request it again.
+          WindowRequest::Close => {
+            ime_contexts.remove(&id);
+            geometry_hints.borrow_mut().remove(&id);
+            keyboard_grabs.borrow_mut().remove(&id);
+            // Otherwise the pointer would be grabbed again for the destroyed window.
+            if let Some(gdk_window) = window.window() {
+              cursor_grab::set_mouse_capture(&gdk_window, false);
             }
-            WindowRequest::Menu((None, Some(menu_id))) => {
+            unsafe { window.destroy() }
+          }
+          WindowRequest::ImeAllowed(allowed) => {
+            if let Some(ime) = ime_contexts.get(&id) {
+              ime.set_allowed(allowed);
+            }
+          }
+          WindowRequest::ImeCursorArea((x, y, width, height)) => {
+            if let Some(ime) = ime_contexts.get(&id) {
+              ime.set_cursor_area(x, y, width, height);
+            }
+          }
+          WindowRequest::Menu(m) => match m {
+            (None, Some(menu_id)) => {
               if let Err(e) = event_tx.send(Event::MenuEvent {
-                window_id: None,
+                window_id: Some(RootWindowId(id)),
                 menu_id,
-                origin: MenuType::ContextMenu,
+                origin: MenuType::MenuBar,
               }) {
-                log::warn!("Failed to send status bar event to event channel: {}", e);
+                log::warn!("Failed to send menu event to event channel: {}", e);
               }
             }
+            (Some(MenuItem::About(_)), None) => {
+              let about = AboutDialog::new();
+              about.show_all();
+              app.add_window(&about);
+            }
+            (Some(MenuItem::Hide), None) => window.hide(),
+            (Some(MenuItem::CloseWindow), None) => window.close(),
+            (Some(MenuItem::Quit), None) => {
+              if let Err(e) = event_tx.send(Event::LoopDestroyed) {
+                log::warn!(
+                  "Failed to send loop destroyed event to event channel: {}",
+                  e
+                );
+              }
+            }
+            (Some(MenuItem::EnterFullScreen), None) => {
+              let state = window.window().unwrap().state();
+              if state.contains(WindowState::FULLSCREEN) {
+                window.unfullscreen();
+              } else {
+                window.fullscreen();
+              }
+            }
+            (Some(MenuItem::Minimize), None) => window.iconify(),
             _ => {}
+          },
+          WindowRequest::SetMenu((window_menu, accel_group, mut menubar)) => {
+            if let Some(window_menu) = window_menu {
+              // remove all existing elements as we overwrite
+              // but we keep same menubar reference
+              for i in menubar.children() {
+                menubar.remove(&i);
+              }
+              // create all new elements
+              window_menu.generate_menu(&mut menubar, &window_requests_tx, &accel_group, id);
+              // make sure all newly added elements are visible
+              menubar.show_all();
+            }
           }
+          WindowRequest::GlobalHotKey(_hotkey_id) => {}
+          WindowRequest::Timer(_) => {}
         }
-        Continue(true)
-      });
+      } else if id == WindowId::dummy() {
+        match request {
+          WindowRequest::GlobalHotKey(hotkey_id) => {
+            if let Err(e) = event_tx.send(Event::GlobalShortcutEvent(AcceleratorId(hotkey_id))) {
+              log::warn!("Failed to send global hotkey event to event channel: {}", e);
+            }
+          }
+          WindowRequest::Timer(timer_id) => {
+            if let Err(e) = event_tx.send(Event::Timer(timer_id)) {
+              log::warn!("Failed to send timer event to event channel: {}", e);
+            }
+          }
+          WindowRequest::Menu((None, Some(menu_id))) => {
+            if let Err(e) = event_tx.send(Event::MenuEvent {
+              window_id: None,
+              menu_id,
+              origin: MenuType::ContextMenu,
+            }) {
+              log::warn!("Failed to send status bar event to event channel: {}", e);
+            }
+          }
+          _ => {}
+        }
+      }
+      Continue(true)
+    });
 
     // Event control flow
-    let events = Rc::new(Mutex::new(Vec::new()));
-    let events_ = events.clone();
-    event_rx.attach(Some(&context), move |event| {
+    let events_ = self.events.clone();
+    let event_source = event_rx.attach(Some(context), move |event| {
       let mut e = events_.lock().unwrap();
       e.push(event);
       Continue(true)
     });
 
-    loop {
-      match control_flow {
-        ControlFlow::Exit => {
-          callback(Event::LoopDestroyed, &window_target, &mut control_flow);
-          break;
-        }
-        ControlFlow::Wait => {
-          let mut e = events.lock().unwrap();
-          if !e.is_empty() {
-            callback(
-              Event::NewEvents(StartCause::WaitCancelled {
-                start: Instant::now(),
-                requested_resume: None,
-              }),
-              &window_target,
-              &mut control_flow,
-            );
+    // Raw mouse motion
+    let (motion_tx, motion_rx) = glib::MainContext::channel(Priority::default());
+    raw_input::spawn_raw_motion_thread(&window_target.p.display, motion_tx);
+    let events_ = self.events.clone();
+    let app = window_target.p.app.clone();
+    let device_event_filter = window_target.p.device_event_filter.clone();
+    let motion_source = motion_rx.attach(Some(context), move |delta| {
+      if !device_events_filtered(device_event_filter.get(), &app) {
+        events_.lock().unwrap().push(Event::DeviceEvent {
+          device_id: RootDeviceId(DeviceId(0)),
+          event: DeviceEvent::MouseMotion { delta },
+          timestamp: Instant::now(),
+        });
+      }
+      Continue(true)
+    });
 
-            for event in e.drain(..) {
-              match event {
-                Event::LoopDestroyed => control_flow = ControlFlow::Exit,
-                _ => callback(event, &window_target, &mut control_flow),
-              }
-            }
+    let mut sources = vec![
+      user_event_source,
+      window_requests_source,
+      event_source,
+      motion_source,
+    ];
+    sources.extend(self.attach_evdev_source(context));
+    sources
+  }
 
-            if control_flow != ControlFlow::Exit {
-              callback(Event::MainEventsCleared, &window_target, &mut control_flow);
-            }
-          }
+  /// Attaches the source of the devices from evdev.
+  #[cfg(target_os = "linux")]
+  fn attach_evdev_source(&self, context: &MainContext) -> Option<glib::SourceId> {
+    let window_target = &self.window_target;
+    let (evdev_tx, evdev_rx) = glib::MainContext::channel(Priority::default());
+    evdev::spawn_evdev_thread(evdev_tx);
+    let events_ = self.events.clone();
+    let app = window_target.p.app.clone();
+    let device_event_filter = window_target.p.device_event_filter.clone();
+    let evdev_devices = window_target.p.evdev_devices.clone();
+    Some(evdev_rx.attach(Some(context), move |message| {
+      let (id, event) = match message {
+        EvdevMessage::Added(id, info) => {
+          evdev_devices.borrow_mut().insert(id, info);
+          (id, DeviceEvent::Added)
         }
-        ControlFlow::WaitUntil(requested_resume) => {
-          let mut e = events.lock().unwrap();
-          let start = Instant::now();
-          if start >= requested_resume {
-            callback(
-              Event::NewEvents(StartCause::ResumeTimeReached {
-                start,
-                requested_resume,
-              }),
-              &window_target,
-              &mut control_flow,
-            );
+        EvdevMessage::Removed(id) => {
+          evdev_devices.borrow_mut().remove(&id);
+          (id, DeviceEvent::Removed)
+        }
+        EvdevMessage::Event(id, event) => (id, event),
+      };
+      // The connections are only filtered out when all the device events are, like on Windows.
+      let filtered = match event {
+        DeviceEvent::Added | DeviceEvent::Removed => {
+          device_event_filter.get() == DeviceEventFilter::Always
+        }
+        _ => device_events_filtered(device_event_filter.get(), &app),
+      };
+      if !filtered {
+        events_.lock().unwrap().push(Event::DeviceEvent {
+          device_id: RootDeviceId(DeviceId(id)),
+          event,
+          timestamp: Instant::now(),
+        });
+      }
+      Continue(true)
+    }));
+  }
 
-            for event in e.drain(..) {
-              match event {
-                Event::LoopDestroyed => control_flow = ControlFlow::Exit,
-                _ => callback(event, &window_target, &mut control_flow),
-              }
-            }
+  #[cfg(not(target_os = "linux"))]
+  fn attach_evdev_source(&self, _context: &MainContext) -> Option<glib::SourceId> {
+    None
+  }
 
-            if control_flow != ControlFlow::Exit {
-              callback(Event::MainEventsCleared, &window_target, &mut control_flow);
-            }
-          } else if !e.is_empty() {
-            callback(
-              Event::NewEvents(StartCause::WaitCancelled {
-                start,
-                requested_resume: Some(requested_resume),
-              }),
-              &window_target,
-              &mut control_flow,
-            );
+  /// Dispatches the pending events according to the control flow, and returns the exit code if
+  /// the event loop exited.
+  fn dispatch_events<F>(&mut self, callback: &mut F) -> Option<i32>
+  where
+    F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
+  {
+    let window_target = &self.window_target;
+    let control_flow = &mut self.control_flow;
+    let events = &self.events;
+    let exit_request = &self.exit_request;
+    let woken = self.woken.swap(false, Ordering::AcqRel);
+    // Whether the events were dispatched in `Wait` or `WaitUntil`, before waiting again.
+    let mut cleared = false;
+    // The redraws, which are dispatched after `MainEventsCleared`.
+    let mut redraws = Vec::new();
+    match *control_flow {
+      ControlFlow::ExitWithCode(_) => (),
+      ControlFlow::Wait => {
+        let mut e = events.lock().unwrap();
+        if !e.is_empty() || woken {
+          callback(
+            Event::NewEvents(StartCause::WaitCancelled {
+              start: Instant::now(),
+              requested_resume: None,
+            }),
+            window_target,
+            control_flow,
+          );
 
-            for event in e.drain(..) {
-              match event {
-                Event::LoopDestroyed => control_flow = ControlFlow::Exit,
-                _ => callback(event, &window_target, &mut control_flow),
-              }
+          for event in e.drain(..) {
+            match event {
+              Event::LoopDestroyed => *control_flow = ControlFlow::Exit,
+              Event::RedrawRequested(_) => redraws.push(event),
+              _ => callback(event, window_target, control_flow),
             }
+          }
+          dispatch_exit_request(exit_request, callback, window_target, control_flow);
 
-            if control_flow != ControlFlow::Exit {
-              callback(Event::MainEventsCleared, &window_target, &mut control_flow);
-            }
+          if control_flow.exit_code().is_none() {
+            callback(Event::MainEventsCleared, window_target, control_flow);
+            cleared = true;
           }
         }
-        ControlFlow::Poll => {
-          let mut e = events.lock().unwrap();
+      }
+      ControlFlow::WaitUntil(requested_resume) => {
+        let mut e = events.lock().unwrap();
+        let start = Instant::now();
+        if start >= requested_resume {
+          callback(
+            Event::NewEvents(StartCause::ResumeTimeReached {
+              start,
+              requested_resume,
+            }),
+            window_target,
+            control_flow,
+          );
+
+          for event in e.drain(..) {
+            match event {
+              Event::LoopDestroyed => *control_flow = ControlFlow::Exit,
+              Event::RedrawRequested(_) => redraws.push(event),
+              _ => callback(event, window_target, control_flow),
+            }
+          }
+          dispatch_exit_request(exit_request, callback, window_target, control_flow);
+
+          if control_flow.exit_code().is_none() {
+            callback(Event::MainEventsCleared, window_target, control_flow);
+            cleared = true;
+          }
+        } else if !e.is_empty() || woken {
           callback(
-            Event::NewEvents(StartCause::Poll),
-            &window_target,
-            &mut control_flow,
+            Event::NewEvents(StartCause::WaitCancelled {
+              start,
+              requested_resume: Some(requested_resume),
+            }),
+            window_target,
+            control_flow,
           );
+
           for event in e.drain(..) {
             match event {
-              Event::LoopDestroyed => control_flow = ControlFlow::Exit,
-              _ => callback(event, &window_target, &mut control_flow),
+              Event::LoopDestroyed => *control_flow = ControlFlow::Exit,
+              Event::RedrawRequested(_) => redraws.push(event),
+              _ => callback(event, window_target, control_flow),
             }
           }
-          callback(Event::MainEventsCleared, &window_target, &mut control_flow);
+          dispatch_exit_request(exit_request, callback, window_target, control_flow);
+
+          if control_flow.exit_code().is_none() {
+            callback(Event::MainEventsCleared, window_target, control_flow);
+            cleared = true;
+          }
         }
       }
+      ControlFlow::Poll => {
+        let mut e = events.lock().unwrap();
+        callback(
+          Event::NewEvents(StartCause::Poll),
+          window_target,
+          control_flow,
+        );
+        for event in e.drain(..) {
+          match event {
+            Event::LoopDestroyed => *control_flow = ControlFlow::Exit,
+            Event::RedrawRequested(_) => redraws.push(event),
+            _ => callback(event, window_target, control_flow),
+          }
+        }
+        dispatch_exit_request(exit_request, callback, window_target, control_flow);
+        callback(Event::MainEventsCleared, window_target, control_flow);
+      }
+    }
+
+    for event in redraws {
+      if control_flow.exit_code().is_none() {
+        callback(event, window_target, control_flow);
+      }
+    }
+
+    if cleared {
+      if let Some(event) = Event::idle(*control_flow) {
+        callback(event, window_target, control_flow);
+      }
+    }
 
-      gtk::main_iteration();
+    if let Some(exit_code) = control_flow.exit_code() {
+      callback(Event::LoopDestroyed, window_target, control_flow);
+      *control_flow = ControlFlow::default();
+      self.running = false;
+      MainContext::default().pop_thread_default();
+      return Some(exit_code);
     }
-    context.pop_thread_default();
+    None
   }
 
   #[inline]
@@ -778,24 +1732,81 @@ impl<T: 'static> EventLoop<T> {
     &self.window_target
   }
 
+  pub fn set_signal_action(&mut self, signal: ExitSignal, action: Option<SignalAction<T>>)
+  where
+    T: Clone,
+  {
+    if let Some(source) = self.signal_sources.remove(&signal) {
+      source.remove();
+    }
+    let source = match action {
+      Some(SignalAction::ExitRequested) => {
+        let exit_request = self.exit_request.clone();
+        let woken = self.woken.clone();
+        glib::unix_signal_add_local(signal.signum(), move || {
+          exit_request.requested.set(true);
+          woken.store(true, Ordering::Release);
+          Continue(true)
+        })
+      }
+      Some(SignalAction::UserEvent(event)) => {
+        let proxy = self.create_proxy();
+        glib::unix_signal_add_local(signal.signum(), move || {
+          if let Err(e) = proxy.send_event(event.clone()) {
+            log::warn!("Failed to send signal event to event loop: {}", e);
+          }
+          Continue(true)
+        })
+      }
+      None => return,
+    };
+    self.signal_sources.insert(signal, source);
+  }
+
   /// Creates an `EventLoopProxy` that can be used to dispatch user events to the main event loop.
   pub fn create_proxy(&self) -> EventLoopProxy<T> {
     EventLoopProxy {
+      user_events: self.user_events.clone(),
       user_event_tx: self.user_event_tx.clone(),
+      woken: self.woken.clone(),
     }
   }
+
+  pub fn set_user_event_capacity(&self, priority: UserEventPriority, capacity: Option<usize>) {
+    self.user_events.set_capacity(priority, capacity);
+  }
 }
 
 /// Used to send custom events to `EventLoop`.
 #[derive(Debug)]
 pub struct EventLoopProxy<T: 'static> {
-  user_event_tx: glib::Sender<T>,
+  user_events: Arc<UserEventQueue<T>>,
+  user_event_tx: glib::Sender<()>,
+  woken: Arc<AtomicBool>,
+}
+
+impl<T: 'static> Drop for EventLoop<T> {
+  fn drop(&mut self) {
+    self.user_events.close();
+    // Detaches the event loop from the main context, so that another one can be created.
+    for source in self.sources.drain(..) {
+      source.remove();
+    }
+    for (_, source) in self.signal_sources.drain() {
+      source.remove();
+    }
+    if self.running {
+      MainContext::default().pop_thread_default();
+    }
+  }
 }
 
 impl<T: 'static> Clone for EventLoopProxy<T> {
   fn clone(&self) -> Self {
     Self {
+      user_events: self.user_events.clone(),
       user_event_tx: self.user_event_tx.clone(),
+      woken: self.woken.clone(),
     }
   }
 }
@@ -808,9 +1819,311 @@ impl<T: 'static> EventLoopProxy<T> {
   /// Returns an `Err` if the associated `EventLoop` no longer exists.
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
     self
-      .user_event_tx
-      .send(event)
-      .map_err(|SendError(error)| EventLoopClosed(error))
+      .send_user_event(event, UserEventPriority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: UserEventPriority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: UserEventPriority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.user_events.push(event, priority, bounded)?;
+    // Only fails once the event loop is dropped, which discards the queued events anyway.
+    let _ = self.user_event_tx.send(());
+    Ok(())
+  }
+
+  pub fn wake_up(&self) {
+    self.woken.store(true, Ordering::Release);
+    MainContext::default().wakeup();
+  }
+}
+
+/// Whether the device events are dropped, according to the filter and the focus of the windows.
+fn device_events_filtered(filter: DeviceEventFilter, app: &gtk::Application) -> bool {
+  match filter {
+    DeviceEventFilter::Always => true,
+    DeviceEventFilter::Unfocused => !app.windows().iter().any(|window| window.is_active()),
+    DeviceEventFilter::Never => false,
+  }
+}
+
+/// Runs an iteration of the main loop, blocking until an event is pending or until `deadline`.
+///
+/// GLib waits with a millisecond precision, rounding up, so the timeout is rounded down and the
+/// rest of the wait is spun on, to resume right after `deadline` instead of up to 1ms after it.
+fn main_iteration_until(deadline: Instant) {
+  let now = Instant::now();
+  if now >= deadline {
+    gtk::main_iteration_do(false);
+    return;
+  }
+
+  let timeout = Duration::from_millis((deadline - now).as_millis() as u64);
+  let fired = Rc::new(Cell::new(false));
+  let fired_ = fired.clone();
+  let source = glib::timeout_add_local(timeout, move || {
+    fired_.set(true);
+    Continue(false)
+  });
+  gtk::main_iteration_do(true);
+  if !fired.get() {
+    source.remove();
+    return;
+  }
+  while Instant::now() < deadline {
+    std::hint::spin_loop();
+  }
+}
+
+/// The oldest time of a GDK event that is converted to an `Instant`. The time of an older event
+/// is likely not from the monotonic clock.
+const MAX_EVENT_AGE: Duration = Duration::from_secs(10);
+
+/// Converts the time of a GDK event, in milliseconds, to an `Instant`.
+///
+/// X11 and Wayland stamp the events with the monotonic clock, the one of `glib::monotonic_time`.
+/// The events without a time, or with a time from another clock, e.g. of a remote X server, are
+/// stamped with the current time instead.
+fn event_timestamp(time: u32) -> Instant {
+  let now = Instant::now();
+  let now_ms = (glib::monotonic_time() / 1000) as u32;
+  let elapsed = Duration::from_millis(now_ms.wrapping_sub(time) as u64);
+  if time == 0 || elapsed > MAX_EVENT_AGE {
+    return now;
+  }
+  now.checked_sub(elapsed).unwrap_or(now)
+}
+
+/// Converts a touchpad gesture event to the gesture events of the window.
+///
+/// GDK sends the scale of a pinch relative to its start, so the one of the previous event is kept
+/// in `pinch_scale` to send the change.
+fn gesture_events(
+  event: &gdk::Event,
+  scale_factor: f64,
+  pinch_scale: &Cell<f64>,
+) -> Vec<WindowEvent<'static>> {
+  let device_id = RootDeviceId(DeviceId(0));
+  let phase = |phase: i8| match phase as gdk_sys::GdkTouchpadGesturePhase {
+    gdk_sys::GDK_TOUCHPAD_GESTURE_PHASE_BEGIN => TouchPhase::Started,
+    gdk_sys::GDK_TOUCHPAD_GESTURE_PHASE_END => TouchPhase::Ended,
+    gdk_sys::GDK_TOUCHPAD_GESTURE_PHASE_CANCEL => TouchPhase::Cancelled,
+    _ => TouchPhase::Moved,
+  };
+  let event: *const gdk_sys::GdkEvent = event.to_glib_none().0;
+  unsafe {
+    match (*event).type_ {
+      gdk_sys::GDK_TOUCHPAD_PINCH => {
+        let pinch = &(*event).touchpad_pinch;
+        let phase = phase(pinch.phase);
+        let scale = if phase == TouchPhase::Started {
+          1.0
+        } else {
+          pinch.scale
+        };
+        let delta = scale - pinch_scale.replace(scale);
+        vec![
+          WindowEvent::PinchGesture {
+            device_id,
+            delta,
+            phase,
+          },
+          // GDK gives the clockwise angle in radians.
+          WindowEvent::RotationGesture {
+            device_id,
+            delta: -pinch.angle_delta.to_degrees() as f32,
+            phase,
+          },
+        ]
+      }
+      gdk_sys::GDK_TOUCHPAD_SWIPE => {
+        let swipe = &(*event).touchpad_swipe;
+        vec![WindowEvent::SwipeGesture {
+          device_id,
+          delta: PhysicalPosition::new(swipe.dx * scale_factor, swipe.dy * scale_factor),
+          phase: phase(swipe.phase),
+        }]
+      }
+      _ => Vec::new(),
+    }
+  }
+}
+
+/// Converts a scroll event to the delta of `WindowEvent::MouseWheel`.
+///
+/// The smooth deltas of GDK are fractions of lines, positive downwards, for the touchpads too.
+fn scroll_delta(event: &gdk::Event) -> Option<(MouseScrollDelta, TouchPhase)> {
+  let event: *mut gdk_sys::GdkEvent = event.to_glib_none().0 as *mut _;
+  unsafe {
+    if (*event).type_ != gdk_sys::GDK_SCROLL
+      // The discrete scroll of a wheel is emulated along with its smooth scroll.
+      || gdk_sys::gdk_event_get_pointer_emulated(event) != 0
+    {
+      return None;
+    }
+    let scroll = &(*event).scroll;
+    let (x, y) = match scroll.direction {
+      gdk_sys::GDK_SCROLL_SMOOTH => (scroll.delta_x, -scroll.delta_y),
+      gdk_sys::GDK_SCROLL_UP => (0.0, 1.0),
+      gdk_sys::GDK_SCROLL_DOWN => (0.0, -1.0),
+      gdk_sys::GDK_SCROLL_LEFT => (-1.0, 0.0),
+      gdk_sys::GDK_SCROLL_RIGHT => (1.0, 0.0),
+      _ => return None,
+    };
+    // `is_stop` is a bit field.
+    let phase = if scroll.is_stop & 1 != 0 {
+      TouchPhase::Ended
+    } else {
+      TouchPhase::Moved
+    };
+    Some((MouseScrollDelta::LineDelta(x as f32, y as f32), phase))
+  }
+}
+
+/// Converts a touch event to a `Touch`, the sequence of the event identifying its contact.
+fn touch(event: &gdk::Event, scale_factor: f64) -> Option<Touch> {
+  let phase = match event.event_type() {
+    EventType::TouchBegin => TouchPhase::Started,
+    EventType::TouchUpdate => TouchPhase::Moved,
+    EventType::TouchEnd => TouchPhase::Ended,
+    EventType::TouchCancel => TouchPhase::Cancelled,
+    _ => return None,
+  };
+  let (x, y) = event.coords()?;
+  let event: *const gdk_sys::GdkEvent = event.to_glib_none().0;
+  let sequence = unsafe { (*event).touch.sequence };
+  Some(Touch {
+    device_id: RootDeviceId(DeviceId(0)),
+    phase,
+    location: PhysicalPosition::new(x * scale_factor, y * scale_factor),
+    force: None,
+    id: sequence as u64,
+  })
+}
+
+/// Converts the button and motion events of a pen touching the window to `PenInput`.
+///
+/// GDK gives the rotation between 0 and 1.
+fn pen_input(event: &gdk::Event, scale_factor: f64) -> Option<PenInput> {
+  let device = event.source_device()?;
+  let inverted = match device.source() {
+    InputSource::Pen => false,
+    InputSource::Eraser => true,
+    _ => return None,
+  };
+  let state = event.state().unwrap_or_else(ModifierType::empty);
+  let phase = match event.event_type() {
+    EventType::ButtonPress if event.button() == Some(1) => TouchPhase::Started,
+    EventType::MotionNotify if state.contains(ModifierType::BUTTON1_MASK) => TouchPhase::Moved,
+    EventType::ButtonRelease if event.button() == Some(1) => TouchPhase::Ended,
+    _ => return None,
+  };
+  let (x, y) = event.coords()?;
+  Some(PenInput {
+    device_id: RootDeviceId(DeviceId(0)),
+    phase,
+    location: PhysicalPosition::new(x * scale_factor, y * scale_factor),
+    pressure: event.axis(AxisUse::Pressure),
+    tilt: pen_tilt(event),
+    twist: event
+      .axis(AxisUse::Rotation)
+      .map(|rotation| rotation * 360.0),
+    barrel_button: state.intersects(ModifierType::BUTTON2_MASK | ModifierType::BUTTON3_MASK),
+    inverted,
+    eraser: inverted,
+  })
+}
+
+/// Converts the motion events of a pen above the window to `PenHover`.
+///
+/// GDK gives the distance between 0 and 1.
+fn pen_hover(event: &gdk::Event, scale_factor: f64) -> Option<PenHover> {
+  let device = event.source_device()?;
+  let inverted = match device.source() {
+    InputSource::Pen => false,
+    InputSource::Eraser => true,
+    _ => return None,
+  };
+  let state = event.state().unwrap_or_else(ModifierType::empty);
+  if event.event_type() != EventType::MotionNotify || state.contains(ModifierType::BUTTON1_MASK) {
+    return None;
+  }
+  let (x, y) = event.coords()?;
+  Some(PenHover {
+    device_id: RootDeviceId(DeviceId(0)),
+    location: PhysicalPosition::new(x * scale_factor, y * scale_factor),
+    distance: event.axis(AxisUse::Distance),
+    tilt: pen_tilt(event),
+    barrel_button: state.intersects(ModifierType::BUTTON2_MASK | ModifierType::BUTTON3_MASK),
+    inverted,
+  })
+}
+
+/// The tilt of a pen in degrees, from the axes of GDK between -1 and 1.
+fn pen_tilt(event: &gdk::Event) -> Option<(f64, f64)> {
+  match (event.axis(AxisUse::Xtilt), event.axis(AxisUse::Ytilt)) {
+    (None, None) => None,
+    (x, y) => Some((x.unwrap_or(0.0) * 90.0, y.unwrap_or(0.0) * 90.0)),
+  }
+}
+
+/// Converts the proximity events of the pens to `PenProximity`.
+fn pen_proximity(event: &gdk::Event) -> Option<WindowEvent<'static>> {
+  let in_range = match event.event_type() {
+    EventType::ProximityIn => true,
+    EventType::ProximityOut => false,
+    _ => return None,
+  };
+  Some(WindowEvent::PenProximity {
+    device_id: RootDeviceId(DeviceId(0)),
+    in_range,
+  })
+}
+
+/// A pending `Event::ExitRequested`.
+#[derive(Default)]
+struct ExitRequest {
+  /// Whether `Event::ExitRequested` is to be sent, for the session manager or for a signal.
+  requested: Cell<bool>,
+  /// Cookie inhibiting the end of the session until `Event::ExitRequested` is dispatched.
+  inhibit: Cell<Option<u32>>,
+}
+
+/// Sends `Event::ExitRequested` if it was requested, and exits unless the callback prevents it.
+fn dispatch_exit_request<T, F>(
+  exit_request: &ExitRequest,
+  callback: &mut F,
+  window_target: &RootELW<T>,
+  control_flow: &mut ControlFlow,
+) where
+  F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
+{
+  if exit_request.requested.take() && control_flow.exit_code().is_none() {
+    let mut prevent = false;
+    callback(
+      Event::ExitRequested {
+        prevent: &mut prevent,
+      },
+      window_target,
+      control_flow,
+    );
+    if !prevent && control_flow.exit_code().is_none() {
+      *control_flow = ControlFlow::Exit;
+    }
+  }
+  if let Some(cookie) = exit_request.inhibit.take() {
+    window_target.p.app.uninhibit(cookie);
   }
 }
 
@@ -355,7 +355,12 @@ fn get_x11_scancode_from_hotkey(key: KeyCode) -> Option<u32> {
     KeyCode::PrintScreen => keysym::XK_Print,
     KeyCode::ScrollLock => keysym::XK_Scroll_Lock,
     KeyCode::Pause => keysym::XF86XK_AudioPlay,
+    KeyCode::MediaPlayPause | KeyCode::MediaPlay => keysym::XF86XK_AudioPlay,
+    KeyCode::MediaPause => keysym::XF86XK_AudioPause,
     KeyCode::MediaStop => keysym::XF86XK_AudioStop,
+    KeyCode::MediaRecord => keysym::XF86XK_AudioRecord,
+    KeyCode::MediaRewind => keysym::XF86XK_AudioRewind,
+    KeyCode::MediaFastForward => keysym::XF86XK_AudioForward,
     KeyCode::MediaTrackNext => keysym::XF86XK_AudioNext,
     KeyCode::MediaTrackPrevious => keysym::XF86XK_AudioPrev,
     KeyCode::AudioVolumeDown => keysym::XF86XK_AudioLowerVolume,
@@ -0,0 +1,184 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{cell::Cell, rc::Rc};
+
+use gdk::EventKey;
+use gtk::{pango, prelude::*, IMMulticontext, Inhibit, Window};
+
+use crate::event::{Ime, ImeUnderline};
+
+/// The input method of a window, sending the text composed by the IME of the system.
+///
+/// The simple input method of GTK is left to `keyboard::ComposeContext`, which reports its dead
+/// keys and compose sequences with the key events instead.
+pub(crate) struct ImeContext {
+  context: IMMulticontext,
+  send: Rc<dyn Fn(Ime)>,
+  allowed: Rc<Cell<bool>>,
+  focused: Rc<Cell<bool>>,
+  preediting: Rc<Cell<bool>>,
+  /// Whether a key is being fed, a commit outside of a composition being the text of the key.
+  feeding: Rc<Cell<bool>>,
+}
+
+impl ImeContext {
+  pub(crate) fn new(window: &Window, send: impl Fn(Ime) + 'static) -> Self {
+    let context = IMMulticontext::new();
+    let send: Rc<dyn Fn(Ime)> = Rc::new(send);
+    let allowed = Rc::new(Cell::new(true));
+    let focused = Rc::new(Cell::new(false));
+    let preediting = Rc::new(Cell::new(false));
+    let feeding = Rc::new(Cell::new(false));
+
+    let context_clone = context.clone();
+    let set_client_window = move |window: &Window| {
+      context_clone.set_client_window(window.window().as_ref());
+    };
+    set_client_window(window);
+    window.connect_realize(set_client_window);
+
+    let context_clone = context.clone();
+    let allowed_clone = allowed.clone();
+    let focused_clone = focused.clone();
+    window.connect_focus_in_event(move |_, _| {
+      focused_clone.set(true);
+      if allowed_clone.get() {
+        context_clone.focus_in();
+      }
+      Inhibit(false)
+    });
+    let context_clone = context.clone();
+    let focused_clone = focused.clone();
+    window.connect_focus_out_event(move |_, _| {
+      focused_clone.set(false);
+      context_clone.focus_out();
+      Inhibit(false)
+    });
+
+    let send_clone = send.clone();
+    let preediting_clone = preediting.clone();
+    context.connect_preedit_start(move |_| {
+      preediting_clone.set(true);
+      send_clone(Ime::Enabled);
+    });
+    let send_clone = send.clone();
+    context.connect_preedit_changed(move |context| {
+      let (text, attributes, cursor) = context.preedit_string();
+      send_clone(preedit(text.to_string(), &attributes, cursor));
+    });
+    let send_clone = send.clone();
+    let preediting_clone = preediting.clone();
+    context.connect_preedit_end(move |_| {
+      if preediting_clone.replace(false) {
+        send_clone(Ime::preedit(String::new()));
+        send_clone(Ime::Disabled);
+      }
+    });
+    let send_clone = send.clone();
+    let preediting_clone = preediting.clone();
+    let feeding_clone = feeding.clone();
+    context.connect_commit(move |_, text| {
+      if preediting_clone.get() {
+        send_clone(Ime::Commit(text.to_owned()));
+      } else if !feeding_clone.get() {
+        // The text inserted without typing, e.g. from an on-screen keyboard.
+        send_clone(Ime::Enabled);
+        send_clone(Ime::Commit(text.to_owned()));
+        send_clone(Ime::Disabled);
+      }
+    });
+
+    ImeContext {
+      context,
+      send,
+      allowed,
+      focused,
+      preediting,
+      feeding,
+    }
+  }
+
+  /// Allows or disallows the IME, the composition in progress being cancelled.
+  pub(crate) fn set_allowed(&self, allowed: bool) {
+    if self.allowed.replace(allowed) == allowed {
+      return;
+    }
+    if allowed {
+      if self.focused.get() {
+        self.context.focus_in();
+      }
+    } else {
+      self.context.reset();
+      self.context.focus_out();
+      // Some input methods don't end the preedit when reset.
+      if self.preediting.replace(false) {
+        (self.send)(Ime::preedit(String::new()));
+        (self.send)(Ime::Disabled);
+      }
+    }
+  }
+
+  /// Sets the area of the text being edited, in logical coordinates of the window.
+  pub(crate) fn set_cursor_area(&self, x: i32, y: i32, width: i32, height: i32) {
+    self.context.set_cursor_location(&gdk::Rectangle {
+      x,
+      y,
+      width,
+      height,
+    });
+  }
+
+  /// Feeds a key to the IME, returning `true` if it was consumed by a composition.
+  pub(crate) fn feed(&self, key: &EventKey) -> bool {
+    if !self.allowed.get() {
+      return false;
+    }
+    let is_simple = self.context.context_id().map_or(true, |id| {
+      id.is_empty() || id.as_str() == "gtk-im-context-simple"
+    });
+    if is_simple {
+      return false;
+    }
+    let was_preediting = self.preediting.get();
+    self.feeding.set(true);
+    let is_filtered = self.context.filter_keypress(key);
+    self.feeding.set(false);
+    is_filtered && (was_preediting || self.preediting.get())
+  }
+}
+
+/// Builds the preedit of `text`, `cursor` being in characters.
+fn preedit(text: String, attributes: &pango::AttrList, cursor: i32) -> Ime {
+  if text.is_empty() {
+    return Ime::preedit(text);
+  }
+  let cursor = text
+    .char_indices()
+    .nth(cursor.max(0) as usize)
+    .map_or(text.len(), |(offset, _)| offset);
+
+  // The IMEs underline the clauses, the one being converted being highlighted.
+  let mut underlines = Vec::new();
+  if let Some(mut iterator) = attributes.iterator() {
+    loop {
+      let (start, end) = iterator.range();
+      let range = (start.max(0) as usize, (end.max(0) as usize).min(text.len()));
+      if range.0 < range.1 && iterator.get(pango::AttrType::Underline).is_some() {
+        underlines.push(ImeUnderline {
+          range,
+          is_target: iterator.get(pango::AttrType::Background).is_some(),
+        });
+      }
+      if !iterator.next_style_change() {
+        break;
+      }
+    }
+  }
+
+  Ime::Preedit {
+    text,
+    cursor: Some((cursor, cursor)),
+    underlines,
+  }
+}
@@ -1,14 +1,18 @@
-use super::KeyEventExtra;
+use super::{keycode::keysym_to_dead_key, KeyEventExtra};
 use crate::{
-  event::{ElementState, KeyEvent},
+  event::{ComposeState, ElementState, KeyEvent},
   keyboard::{Key, KeyCode, KeyLocation, ModifiersState, NativeKeyCode},
 };
-use gdk::{keys::constants::*, EventKey};
+use gdk::{keys::constants::*, EventKey, ModifierType};
+use gtk::prelude::*;
 use std::{
+  cell::{Cell, RefCell},
   collections::HashSet,
   ffi::c_void,
   os::raw::{c_int, c_uint},
-  ptr, slice,
+  ptr,
+  rc::Rc,
+  slice,
   sync::Mutex,
 };
 
@@ -97,7 +101,19 @@ pub(crate) fn raw_key_to_key(gdk_key: RawKey) -> Option<Key<'static>> {
     // KP_Separator? What does it map to?
     KP_Tab => Some(Key::Tab),
     KP_Up => Some(Key::ArrowUp),
-    // TODO: more mappings (media etc)
+
+    AudioPlay => Some(Key::MediaPlayPause),
+    AudioPause => Some(Key::MediaPause),
+    AudioStop => Some(Key::MediaStop),
+    AudioNext => Some(Key::MediaTrackNext),
+    AudioPrev => Some(Key::MediaTrackPrevious),
+    AudioRecord => Some(Key::MediaRecord),
+    AudioRewind => Some(Key::MediaRewind),
+    AudioForward => Some(Key::MediaFastForward),
+    AudioRaiseVolume => Some(Key::AudioVolumeUp),
+    AudioLowerVolume => Some(Key::AudioVolumeDown),
+    AudioMute => Some(Key::AudioVolumeMute),
+    // TODO: more mappings
     _ => None,
   }
 }
@@ -158,6 +174,25 @@ pub(crate) fn get_modifiers(key: EventKey) -> ModifiersState {
   result
 }
 
+/// Returns the modifiers held during a key event, from its state.
+pub(crate) fn get_modifiers_state(state: ModifierType) -> ModifiersState {
+  let mut result = ModifiersState::empty();
+  result.set(
+    ModifiersState::SHIFT,
+    state.contains(ModifierType::SHIFT_MASK),
+  );
+  result.set(
+    ModifiersState::CONTROL,
+    state.contains(ModifierType::CONTROL_MASK),
+  );
+  result.set(ModifiersState::ALT, state.contains(ModifierType::MOD1_MASK));
+  result.set(
+    ModifiersState::SUPER,
+    state.intersects(ModifierType::SUPER_MASK | ModifierType::META_MASK),
+  );
+  result
+}
+
 pub(crate) fn make_key_event(
   key: &EventKey,
   is_repeat: bool,
@@ -169,38 +204,22 @@ pub(crate) fn make_key_event(
   // a keyval (keysym in X) is a "logical" key name, such as GDK_Enter, GDK_a, GDK_space, etc.
   let keyval_without_modifiers = key.keyval();
   let keyval_with_modifiers =
-    hardware_keycode_to_keyval(scancode).unwrap_or_else(|| keyval_without_modifiers.clone());
-  // get unicode value, with and without modifiers
+    hardware_keycode_to_keyval(scancode, 0).unwrap_or_else(|| keyval_without_modifiers.clone());
+  // get unicode value without modifiers
   let text_without_modifiers = keyval_with_modifiers.to_unicode();
-  let text_with_modifiers = keyval_without_modifiers.to_unicode();
   // get physical key from the scancode (keycode)
   let physical_key = key_override.unwrap_or_else(|| KeyCode::from_scancode(scancode as u32));
 
-  // extract key without modifier
-  let key_without_modifiers = raw_key_to_key(keyval_with_modifiers.clone()).unwrap_or_else(|| {
-    if let Some(key) = text_without_modifiers {
-      if key >= ' ' && key != '\x7f' {
-        Key::Character(insert_or_get_key_str(key.to_string()))
-      } else {
-        Key::Unidentified(NativeKeyCode::Gtk(scancode))
-      }
-    } else {
-      Key::Unidentified(NativeKeyCode::Gtk(scancode))
-    }
-  });
+  use glib::translate::IntoGlib;
+  // extract key without modifier, a dead key being the character of its accent
+  let key_without_modifiers = match keysym_to_dead_key(keyval_with_modifiers.clone().into_glib()) {
+    Some(Key::Dead(Some(accent))) => Key::Character(insert_or_get_key_str(accent.to_string())),
+    _ => keyval_to_key(keyval_with_modifiers.clone(), scancode),
+  };
 
-  // extract the logical key
-  let logical_key = raw_key_to_key(keyval_without_modifiers).unwrap_or_else(|| {
-    if let Some(key) = text_with_modifiers {
-      if key >= ' ' && key != '\x7f' {
-        Key::Character(insert_or_get_key_str(key.to_string()))
-      } else {
-        Key::Unidentified(NativeKeyCode::Gtk(scancode))
-      }
-    } else {
-      Key::Unidentified(NativeKeyCode::Gtk(scancode))
-    }
-  });
+  // extract the logical key, the dead keys having no text
+  let logical_key = keysym_to_dead_key(keyval_without_modifiers.clone().into_glib())
+    .unwrap_or_else(|| keyval_to_key(keyval_without_modifiers, scancode));
 
   // make sure we have a valid key
   if !matches!(key_without_modifiers, Key::Unidentified(_)) {
@@ -214,6 +233,7 @@ pub(crate) fn make_key_event(
       repeat: is_repeat,
       state,
       text: text_with_all_modifiers,
+      compose_state: None,
       platform_specific: KeyEventExtra {
         text_with_all_modifiers,
         key_without_modifiers,
@@ -225,9 +245,90 @@ pub(crate) fn make_key_event(
   None
 }
 
+/// Combines the dead keys and compose sequences typed in a window, with the simple input method
+/// of GTK.
+pub(crate) struct ComposeContext {
+  context: gtk::IMContextSimple,
+  committed: Rc<RefCell<Option<String>>>,
+  composing: Cell<bool>,
+}
+
+impl ComposeContext {
+  pub(crate) fn new() -> Self {
+    let context = gtk::IMContextSimple::new();
+    let committed = Rc::new(RefCell::new(None));
+    let committed_clone = committed.clone();
+    context.connect_commit(move |_, text| {
+      *committed_clone.borrow_mut() = Some(text.to_owned());
+    });
+    ComposeContext {
+      context,
+      committed,
+      composing: Cell::new(false),
+    }
+  }
+
+  /// Feeds a pressed key to the input method, storing the part it takes in a sequence in `event`.
+  ///
+  /// The keys without an event are fed too, to not break the sequences they're part of.
+  pub(crate) fn feed(&self, key: &EventKey, event: Option<&mut KeyEvent>) {
+    // The input method commits the text of the keys outside of sequences too.
+    let is_filtered = self.context.filter_keypress(key);
+    let committed = self.committed.borrow_mut().take();
+    let (compose_state, text) = match committed {
+      None if is_filtered => {
+        self.composing.set(true);
+        (ComposeState::Composing, None)
+      }
+      Some(text) if self.composing.replace(false) => {
+        // The keys that couldn't be combined are committed together.
+        let compose_state = if text.chars().count() == 1 {
+          ComposeState::Composed
+        } else {
+          ComposeState::Cancelled
+        };
+        (compose_state, Some(insert_or_get_key_str(text)))
+      }
+      _ => return,
+    };
+    if let Some(event) = event {
+      event.compose_state = Some(compose_state);
+      event.text = text;
+    }
+  }
+}
+
+/// Returns the key of a keyval, or of its text if it has no name.
+fn keyval_to_key(keyval: RawKey, scancode: u16) -> Key<'static> {
+  let text = keyval.to_unicode();
+  raw_key_to_key(keyval).unwrap_or_else(|| match text {
+    Some(key) if key >= ' ' && key != '\x7f' => {
+      Key::Character(insert_or_get_key_str(key.to_string()))
+    }
+    _ => Key::Unidentified(NativeKeyCode::Gtk(scancode)),
+  })
+}
+
+/// Returns the key of a hardware keycode in the layout of `group`, without modifiers.
+pub(crate) fn key_from_scancode(scancode: u16, group: u8) -> Option<Key<'static>> {
+  match keyval_to_key(hardware_keycode_to_keyval(scancode, group)?, scancode) {
+    Key::Unidentified(_) => None,
+    key => Some(key),
+  }
+}
+
+/// Returns the hardware keycode of the key typing `key` in the layout of `group`, without
+/// modifiers.
+pub(crate) fn scancode_from_key(key: &Key<'_>, group: u8) -> Option<u32> {
+  // The X keycodes are between 8 and 255, with the numpad after the keys typing the same.
+  (8..=255)
+    .find(|&scancode| key_from_scancode(scancode, group).as_ref() == Some(key))
+    .map(u32::from)
+}
+
 /// Map a hardware keycode to a keyval by performing a lookup in the keymap and finding the
-/// keyval with the lowest group and level
-fn hardware_keycode_to_keyval(keycode: u16) -> Option<RawKey> {
+/// keyval of the lowest level in `group`
+fn hardware_keycode_to_keyval(keycode: u16, group: u8) -> Option<RawKey> {
   use glib::translate::FromGlib;
   unsafe {
     let keymap = gdk_sys::gdk_keymap_get_default();
@@ -250,7 +351,7 @@ fn hardware_keycode_to_keyval(keycode: u16) -> Option<RawKey> {
       let keys_slice = slice::from_raw_parts(keys, nkeys as usize);
 
       let resolved_keyval = keys_slice.iter().enumerate().find_map(|(id, gdk_keymap)| {
-        if gdk_keymap.group == 0 && gdk_keymap.level == 0 {
+        if gdk_keymap.group == c_int::from(group) && gdk_keymap.level == 0 {
           Some(RawKey::from_glib(keyvals_slice[id]))
         } else {
           None
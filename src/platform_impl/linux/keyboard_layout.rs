@@ -0,0 +1,102 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The XKB layouts of X11, named by the group names of the keyboard. GDK doesn't expose them, and
+// Wayland only sends them in the keymap given to GDK. The auto-repeat of the keyboard is
+// queried from XKB too.
+use std::{ffi::CStr, mem, time::Duration};
+
+use glib::translate::ToGlibPtr;
+use x11_dl::xlib;
+
+use super::virtual_desktop::is_x11;
+use crate::keyboard::{KeyRepeatInfo, KeyboardLayout};
+
+extern "C" {
+  fn gdk_x11_display_get_xdisplay(display: *mut gdk_sys::GdkDisplay) -> *mut xlib::Display;
+}
+
+// Defined by XKB.h, which x11-dl doesn't include.
+const XKB_USE_CORE_KBD: u32 = 0x0100;
+const XKB_GROUP_NAMES_MASK: u32 = 1 << 12;
+
+lazy_static! {
+  static ref XLIB: Option<xlib::Xlib> = xlib::Xlib::open().ok();
+}
+
+/// Returns the X display and the Xlib functions to query it, on X11.
+pub fn xdisplay(display: &gdk::Display) -> Option<(&'static xlib::Xlib, *mut xlib::Display)> {
+  if !is_x11(display) {
+    return None;
+  }
+  let xlib = XLIB.as_ref()?;
+  let xdisplay = unsafe { gdk_x11_display_get_xdisplay(display.to_glib_none().0) };
+  Some((xlib, xdisplay))
+}
+
+/// Returns the current group of the core keyboard, i.e. the index of its layout.
+pub fn current_group(display: &gdk::Display) -> Option<u8> {
+  let (xlib, xdisplay) = xdisplay(display)?;
+  unsafe {
+    let mut state: xlib::XkbStateRec = mem::zeroed();
+    if (xlib.XkbGetState)(xdisplay, XKB_USE_CORE_KBD, &mut state) != xlib::Success as i32 {
+      return None;
+    }
+    Some(state.group)
+  }
+}
+
+/// Returns the auto-repeat of the core keyboard, set by the X server.
+pub fn key_repeat_info(display: &gdk::Display) -> Option<KeyRepeatInfo> {
+  let (xlib, xdisplay) = xdisplay(display)?;
+  let (mut delay, mut interval) = (0, 0);
+  unsafe {
+    if (xlib.XkbGetAutoRepeatRate)(xdisplay, XKB_USE_CORE_KBD, &mut delay, &mut interval)
+      == xlib::False
+    {
+      return None;
+    }
+  }
+  Some(KeyRepeatInfo {
+    delay: Duration::from_millis(delay as u64),
+    rate: if interval > 0 {
+      1000.0 / interval as f64
+    } else {
+      0.0
+    },
+  })
+}
+
+/// Returns the layout of the current group of the core keyboard.
+pub fn current_keyboard_layout(display: &gdk::Display) -> Option<KeyboardLayout> {
+  let (xlib, xdisplay) = xdisplay(display)?;
+  let group = current_group(display)?;
+
+  unsafe {
+    let keyboard = (xlib.XkbAllocKeyboard)();
+    if keyboard.is_null() {
+      return None;
+    }
+    let mut layout = None;
+    if (xlib.XkbGetNames)(xdisplay, XKB_GROUP_NAMES_MASK, keyboard) == xlib::Success as i32
+      && !(*keyboard).names.is_null()
+    {
+      let atom = (*(*keyboard).names)
+        .groups
+        .get(group as usize)
+        .copied()
+        .unwrap_or(0);
+      if atom != 0 {
+        let name = (xlib.XGetAtomName)(xdisplay, atom);
+        if !name.is_null() {
+          layout = Some(KeyboardLayout {
+            id: CStr::from_ptr(name).to_string_lossy().into_owned(),
+          });
+          (xlib.XFree)(name as *mut _);
+        }
+      }
+    }
+    (xlib.XkbFreeKeyboard)(keyboard, 0, xlib::True);
+    layout
+  }
+}
@@ -1,4 +1,4 @@
-use crate::keyboard::{KeyCode, NativeKeyCode};
+use crate::keyboard::{Key, KeyCode, NativeKeyCode};
 
 pub fn keycode_to_scancode(code: KeyCode) -> Option<u32> {
   // See `from_scancode` for more info
@@ -128,7 +128,12 @@ pub fn keycode_to_scancode(code: KeyCode) -> Option<u32> {
     KeyCode::LaunchApp1 => Some(0x0098),
     KeyCode::LaunchApp2 => Some(0x0094),
     KeyCode::LaunchMail => Some(0x00A3),
+    KeyCode::MediaFastForward => Some(0x00D8),
+    KeyCode::MediaPause => Some(0x00D1),
+    KeyCode::MediaPlay => Some(0x00D0),
     KeyCode::MediaPlayPause => Some(0x00AC),
+    KeyCode::MediaRecord => Some(0x00AF),
+    KeyCode::MediaRewind => Some(0x00B0),
     KeyCode::MediaSelect => Some(0x00B3),
     KeyCode::MediaStop => Some(0x00AE),
     KeyCode::MediaTrackNext => Some(0x00AB),
@@ -288,10 +293,43 @@ pub fn keycode_from_scancode(scancode: u32) -> KeyCode {
     0x00AC => KeyCode::MediaPlayPause,
     0x00AD => KeyCode::MediaTrackPrevious,
     0x00AE => KeyCode::MediaStop,
+    0x00AF => KeyCode::MediaRecord,
+    0x00B0 => KeyCode::MediaRewind,
     0x00B3 => KeyCode::MediaSelect,
     0x00B4 => KeyCode::BrowserHome,
     0x00B5 => KeyCode::BrowserRefresh,
+    // KEY_PLAYCD, KEY_PAUSECD, KEY_PLAY and KEY_FASTFORWARD of evdev.
+    0x00D0 => KeyCode::MediaPlay,
+    0x00D1 => KeyCode::MediaPause,
+    0x00D7 => KeyCode::MediaPlay,
+    0x00D8 => KeyCode::MediaFastForward,
     0x00E1 => KeyCode::BrowserSearch,
     _ => KeyCode::Unidentified(NativeKeyCode::Gtk(scancode as u16)),
   }
 }
+
+/// Returns the dead key of a keysym, with the spacing version of its accent when it has one.
+///
+/// The keyvals of GDK are the same as the X keysyms.
+pub fn keysym_to_dead_key(keysym: u32) -> Option<Key<'static>> {
+  let accent = match keysym {
+    0xFE50 => '`',
+    0xFE51 => '\u{B4}',
+    0xFE52 => '^',
+    0xFE53 => '~',
+    0xFE54 => '\u{AF}',
+    0xFE55 => '\u{2D8}',
+    0xFE56 => '\u{2D9}',
+    0xFE57 => '\u{A8}',
+    0xFE58 => '\u{2DA}',
+    0xFE59 => '\u{2DD}',
+    0xFE5A => '\u{2C7}',
+    0xFE5B => '\u{B8}',
+    0xFE5C => '\u{2DB}',
+    0xFE5D => '\u{37A}',
+    // The other accents and the dead letters of `dead_a` to `dead_longsolidusoverlay`.
+    0xFE5E..=0xFE93 => return Some(Key::Dead(None)),
+    _ => return None,
+  };
+  Some(Key::Dead(Some(accent)))
+}
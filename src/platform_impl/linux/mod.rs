@@ -10,14 +10,24 @@
 ))]
 
 mod clipboard;
+mod cursor_grab;
+mod embed;
+#[cfg(target_os = "linux")]
+mod evdev;
 mod event_loop;
 mod global_shortcut;
+mod ime;
 mod keyboard;
+mod keyboard_layout;
 mod keycode;
 mod menu;
 mod monitor;
+mod raw_input;
+#[cfg(feature = "test")]
+mod synthetic_input;
 #[cfg(feature = "tray")]
 mod system_tray;
+mod virtual_desktop;
 mod window;
 
 #[cfg(feature = "tray")]
@@ -30,9 +40,16 @@ pub use self::{
 };
 pub use event_loop::{EventLoop, EventLoopProxy, EventLoopWindowTarget};
 pub use monitor::{MonitorHandle, VideoMode};
-pub use window::{hit_test, PlatformIcon, Window, WindowId};
+pub use virtual_desktop::VirtualDesktop;
+pub use window::{hit_test, CloseHandle, PlatformIcon, Window, WindowId};
 
-use crate::keyboard::Key;
+use crate::keyboard::{Key, KeyboardLayout};
+
+/// Returns the layout of the default display, which must be used on the main thread like the other
+/// GDK functions.
+pub fn current_keyboard_layout() -> Option<KeyboardLayout> {
+  gdk::Display::default().and_then(|display| keyboard_layout::current_keyboard_layout(&display))
+}
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct KeyEventExtra {
@@ -6,7 +6,7 @@ use crate::{
   monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MonitorHandle {
   monitor: gdk::Monitor,
   // We have to store the monitor number in GdkScreen despite
@@ -21,6 +21,11 @@ impl MonitorHandle {
     Self { monitor, number }
   }
 
+  #[inline]
+  pub(crate) fn number(&self) -> i32 {
+    self.number
+  }
+
   #[inline]
   pub fn name(&self) -> Option<String> {
     self.monitor.model().map(|s| s.as_str().to_string())
@@ -49,9 +54,23 @@ impl MonitorHandle {
     self.monitor.scale_factor() as f64
   }
 
+  /// GDK only reports the current video mode of the monitor.
   #[inline]
   pub fn video_modes(&self) -> Box<dyn Iterator<Item = RootVideoMode>> {
-    todo!()
+    let bit_depth = self
+      .monitor
+      .display()
+      .default_screen()
+      .system_visual()
+      .map_or(24, |visual| visual.depth() as u16);
+    Box::new(std::iter::once(RootVideoMode {
+      video_mode: VideoMode {
+        size: self.size().into(),
+        bit_depth,
+        refresh_rate_millihertz: self.monitor.refresh_rate() as u32,
+        monitor: self.clone(),
+      },
+    }))
   }
 }
 
@@ -59,26 +78,38 @@ unsafe impl Send for MonitorHandle {}
 unsafe impl Sync for MonitorHandle {}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct VideoMode;
+pub struct VideoMode {
+  size: (u32, u32),
+  bit_depth: u16,
+  refresh_rate_millihertz: u32,
+  monitor: MonitorHandle,
+}
 
 impl VideoMode {
   #[inline]
   pub fn size(&self) -> PhysicalSize<u32> {
-    todo!()
+    self.size.into()
   }
 
   #[inline]
   pub fn bit_depth(&self) -> u16 {
-    todo!()
+    self.bit_depth
   }
 
   #[inline]
   pub fn refresh_rate(&self) -> u16 {
-    todo!()
+    ((self.refresh_rate_millihertz + 500) / 1000) as u16
+  }
+
+  #[inline]
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate_millihertz
   }
 
   #[inline]
   pub fn monitor(&self) -> RootMonitorHandle {
-    todo!()
+    RootMonitorHandle {
+      inner: self.monitor.clone(),
+    }
   }
 }
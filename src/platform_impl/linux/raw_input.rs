@@ -0,0 +1,133 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The raw motion of the mouse, from XInput 2 which GDK doesn't expose. Its events are received on
+// a connection of their own, by a thread waiting for them, since GDK selects the events of the
+// root window on its connection for itself.
+use std::{ffi::CString, mem, thread};
+
+use x11_dl::{xinput2, xlib};
+
+use super::virtual_desktop::is_x11;
+
+/// Starts sending the unaccelerated motion of the mouse to `motion_tx`, on X11.
+pub fn spawn_raw_motion_thread(display: &gdk::Display, motion_tx: glib::Sender<(f64, f64)>) {
+  if !is_x11(display) {
+    return;
+  }
+  let name = match CString::new(display.name().as_str()) {
+    Ok(name) => name,
+    Err(_) => return,
+  };
+
+  thread::spawn(move || {
+    let (xlib, xinput2) = match (xlib::Xlib::open(), xinput2::XInput2::open()) {
+      (Ok(xlib), Ok(xinput2)) => (xlib, xinput2),
+      _ => return,
+    };
+    unsafe {
+      let xdisplay = (xlib.XOpenDisplay)(name.as_ptr());
+      if xdisplay.is_null() {
+        log::warn!("Failed to open the X display to receive the raw mouse motion");
+        return;
+      }
+      if let Some(opcode) = select_raw_motion(&xlib, &xinput2, xdisplay) {
+        receive_raw_motion(&xlib, xdisplay, opcode, &motion_tx);
+      }
+      (xlib.XCloseDisplay)(xdisplay);
+    }
+  });
+}
+
+/// Selects the raw motion events of the master pointers, returning the opcode of XInput.
+unsafe fn select_raw_motion(
+  xlib: &xlib::Xlib,
+  xinput2: &xinput2::XInput2,
+  xdisplay: *mut xlib::Display,
+) -> Option<i32> {
+  let (mut opcode, mut event, mut error) = (0, 0, 0);
+  if (xlib.XQueryExtension)(
+    xdisplay,
+    b"XInputExtension\0".as_ptr() as *const _,
+    &mut opcode,
+    &mut event,
+    &mut error,
+  ) == xlib::False
+  {
+    return None;
+  }
+  let (mut major, mut minor) = (2, 0);
+  if (xinput2.XIQueryVersion)(xdisplay, &mut major, &mut minor) != xlib::Success as i32 {
+    return None;
+  }
+
+  let mut mask = [0u8; (xinput2::XI_LASTEVENT as usize >> 3) + 1];
+  mask[xinput2::XI_RawMotion as usize >> 3] |= 1 << (xinput2::XI_RawMotion & 7);
+  let mut event_mask = xinput2::XIEventMask {
+    deviceid: xinput2::XIAllMasterDevices,
+    mask_len: mask.len() as i32,
+    mask: mask.as_mut_ptr(),
+  };
+  let root = (xlib.XDefaultRootWindow)(xdisplay);
+  (xinput2.XISelectEvents)(xdisplay, root, &mut event_mask, 1);
+  Some(opcode)
+}
+
+/// Sends the raw motion events until the receiver is dropped with the event loop.
+unsafe fn receive_raw_motion(
+  xlib: &xlib::Xlib,
+  xdisplay: *mut xlib::Display,
+  opcode: i32,
+  motion_tx: &glib::Sender<(f64, f64)>,
+) {
+  let mut event: xlib::XEvent = mem::zeroed();
+  loop {
+    (xlib.XNextEvent)(xdisplay, &mut event);
+    let cookie = &mut event.generic_event_cookie;
+    if cookie.type_ != xlib::GenericEvent
+      || cookie.extension != opcode
+      || (xlib.XGetEventData)(xdisplay, cookie) == xlib::False
+    {
+      continue;
+    }
+    let delta = if cookie.evtype == xinput2::XI_RawMotion {
+      raw_motion_delta(&*(cookie.data as *const xinput2::XIRawEvent))
+    } else {
+      None
+    };
+    (xlib.XFreeEventData)(xdisplay, cookie);
+
+    if let Some(delta) = delta {
+      if motion_tx.send(delta).is_err() {
+        return;
+      }
+    }
+  }
+}
+
+/// The motion along the X and Y axes, which are the first valuators, before the acceleration.
+unsafe fn raw_motion_delta(event: &xinput2::XIRawEvent) -> Option<(f64, f64)> {
+  if event.valuators.mask.is_null() || event.valuators.mask_len < 1 {
+    return None;
+  }
+  // The values are only given for the valuators set in the mask.
+  let mask = *event.valuators.mask;
+  let mut values = event.raw_values;
+  let mut delta = (0.0, 0.0);
+  for axis in 0..2 {
+    if mask & (1 << axis) != 0 {
+      let value = *values;
+      values = values.add(1);
+      if axis == 0 {
+        delta.0 = value;
+      } else {
+        delta.1 = value;
+      }
+    }
+  }
+  if delta == (0.0, 0.0) {
+    None
+  } else {
+    Some(delta)
+  }
+}
@@ -0,0 +1,200 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The synthetic input of `Window::inject_event`, put on the queue of GDK as the events of the seat,
+// so the window handles them like the events of the display server. The keys are translated by the
+// keymap of the display, and the modifiers and the buttons pressed by the synthetic events are kept
+// for the next ones.
+use std::{cell::Cell, ptr};
+
+use glib::translate::ToGlibPtr;
+
+use crate::{
+  dpi::PhysicalPosition,
+  event::{ElementState, MouseButton, TouchPhase},
+  keyboard::KeyCode,
+  test::SyntheticEvent,
+};
+
+thread_local! {
+  // The modifiers and the buttons pressed by the synthetic events.
+  static STATE: Cell<gdk_sys::GdkModifierType> = Cell::new(0);
+}
+
+/// Puts a synthetic event for `window` on the queue of GDK. The keys must have a scancode.
+pub fn inject_event(window: &gdk::Window, event: SyntheticEvent) {
+  unsafe {
+    let gdk_window: *mut gdk_sys::GdkWindow = window.to_glib_none().0;
+    let seat = gdk_sys::gdk_display_get_default_seat(gdk_sys::gdk_window_get_display(gdk_window));
+    let pointer = gdk_sys::gdk_seat_get_pointer(seat);
+    // The time of the events of GDK is in milliseconds of the monotonic clock.
+    let time = (glib::monotonic_time() / 1000) as u32;
+    let state = STATE.with(Cell::get);
+
+    let gdk_event = match event {
+      SyntheticEvent::Key {
+        physical_key,
+        state: key_state,
+      } => {
+        let scancode = match physical_key.to_scancode() {
+          Some(scancode) => scancode,
+          None => return,
+        };
+        let pressed = key_state == ElementState::Pressed;
+        let gdk_event = gdk_sys::gdk_event_new(if pressed {
+          gdk_sys::GDK_KEY_PRESS
+        } else {
+          gdk_sys::GDK_KEY_RELEASE
+        });
+        let key = &mut (*gdk_event).key;
+        key.time = time;
+        key.state = state;
+        key.hardware_keycode = scancode as u16;
+        let keymap =
+          gdk_sys::gdk_keymap_get_for_display(gdk_sys::gdk_window_get_display(gdk_window));
+        gdk_sys::gdk_keymap_translate_keyboard_state(
+          keymap,
+          scancode,
+          state,
+          0,
+          &mut key.keyval,
+          ptr::null_mut(),
+          ptr::null_mut(),
+          ptr::null_mut(),
+        );
+        if let Some(mask) = modifier_mask(physical_key) {
+          set_state(mask, pressed);
+        }
+        gdk_sys::gdk_event_set_device(gdk_event, gdk_sys::gdk_seat_get_keyboard(seat));
+        gdk_event
+      }
+      SyntheticEvent::CursorMoved { position } => {
+        let gdk_event = gdk_sys::gdk_event_new(gdk_sys::GDK_MOTION_NOTIFY);
+        let motion = &mut (*gdk_event).motion;
+        motion.time = time;
+        motion.state = state;
+        let (x, y, x_root, y_root) = coords(gdk_window, position);
+        motion.x = x;
+        motion.y = y;
+        motion.x_root = x_root;
+        motion.y_root = y_root;
+        gdk_event
+      }
+      SyntheticEvent::MouseInput {
+        button,
+        state: button_state,
+        position,
+      } => {
+        let pressed = button_state == ElementState::Pressed;
+        let gdk_event = gdk_sys::gdk_event_new(if pressed {
+          gdk_sys::GDK_BUTTON_PRESS
+        } else {
+          gdk_sys::GDK_BUTTON_RELEASE
+        });
+        let button = match button {
+          MouseButton::Left => 1,
+          MouseButton::Middle => 2,
+          MouseButton::Right => 3,
+          MouseButton::Back => 8,
+          MouseButton::Forward => 9,
+          MouseButton::Other(button) => button as u32,
+        };
+        let gdk_button = &mut (*gdk_event).button;
+        gdk_button.time = time;
+        gdk_button.state = state;
+        gdk_button.button = button;
+        let (x, y, x_root, y_root) = coords(gdk_window, position);
+        gdk_button.x = x;
+        gdk_button.y = y;
+        gdk_button.x_root = x_root;
+        gdk_button.y_root = y_root;
+        if (1..=5).contains(&button) {
+          set_state(gdk_sys::GDK_BUTTON1_MASK << (button - 1), pressed);
+        }
+        gdk_event
+      }
+      SyntheticEvent::MouseWheel { lines } => {
+        let gdk_event = gdk_sys::gdk_event_new(gdk_sys::GDK_SCROLL);
+        let scroll = &mut (*gdk_event).scroll;
+        scroll.time = time;
+        scroll.state = state;
+        scroll.direction = gdk_sys::GDK_SCROLL_SMOOTH;
+        // The smooth scroll of GDK is positive downward.
+        scroll.delta_x = lines.0 as f64;
+        scroll.delta_y = -lines.1 as f64;
+        gdk_sys::gdk_window_get_device_position_double(
+          gdk_window,
+          pointer,
+          &mut scroll.x,
+          &mut scroll.y,
+          ptr::null_mut(),
+        );
+        gdk_event
+      }
+      SyntheticEvent::Touch {
+        id,
+        phase,
+        location,
+      } => {
+        let gdk_event = gdk_sys::gdk_event_new(match phase {
+          TouchPhase::Started => gdk_sys::GDK_TOUCH_BEGIN,
+          TouchPhase::Moved => gdk_sys::GDK_TOUCH_UPDATE,
+          TouchPhase::Ended => gdk_sys::GDK_TOUCH_END,
+          TouchPhase::Cancelled => gdk_sys::GDK_TOUCH_CANCEL,
+        });
+        let touch = &mut (*gdk_event).touch;
+        touch.time = time;
+        touch.state = state;
+        // The id of the touches is the address of their sequence.
+        touch.sequence = id as usize as *mut gdk_sys::GdkEventSequence;
+        let (x, y, x_root, y_root) = coords(gdk_window, location);
+        touch.x = x;
+        touch.y = y;
+        touch.x_root = x_root;
+        touch.y_root = y_root;
+        gdk_event
+      }
+    };
+
+    // The event keeps a reference to its window, released when it's freed.
+    (*gdk_event).any.window = window.to_glib_full();
+    if !matches!(event, SyntheticEvent::Key { .. }) {
+      gdk_sys::gdk_event_set_device(gdk_event, pointer);
+    }
+    gdk_sys::gdk_event_set_source_device(gdk_event, gdk_sys::gdk_event_get_device(gdk_event));
+    gdk_sys::gdk_event_put(gdk_event);
+    gdk_sys::gdk_event_free(gdk_event);
+  }
+}
+
+fn modifier_mask(key: KeyCode) -> Option<gdk_sys::GdkModifierType> {
+  match key {
+    KeyCode::ShiftLeft | KeyCode::ShiftRight => Some(gdk_sys::GDK_SHIFT_MASK),
+    KeyCode::ControlLeft | KeyCode::ControlRight => Some(gdk_sys::GDK_CONTROL_MASK),
+    KeyCode::AltLeft | KeyCode::AltRight => Some(gdk_sys::GDK_MOD1_MASK),
+    KeyCode::SuperLeft | KeyCode::SuperRight => Some(gdk_sys::GDK_SUPER_MASK),
+    _ => None,
+  }
+}
+
+fn set_state(mask: gdk_sys::GdkModifierType, pressed: bool) {
+  STATE.with(|state| {
+    if pressed {
+      state.set(state.get() | mask)
+    } else {
+      state.set(state.get() & !mask)
+    }
+  });
+}
+
+/// The logical coordinates of a physical position in the window and on the screen.
+unsafe fn coords(
+  gdk_window: *mut gdk_sys::GdkWindow,
+  position: PhysicalPosition<f64>,
+) -> (f64, f64, f64, f64) {
+  let scale_factor = gdk_sys::gdk_window_get_scale_factor(gdk_window) as f64;
+  let (x, y) = (position.x / scale_factor, position.y / scale_factor);
+  let (mut origin_x, mut origin_y) = (0, 0);
+  gdk_sys::gdk_window_get_origin(gdk_window, &mut origin_x, &mut origin_y);
+  (x, y, origin_x as f64 + x, origin_y as f64 + y)
+}
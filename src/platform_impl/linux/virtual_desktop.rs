@@ -0,0 +1,62 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The virtual desktops of X11 window managers, through the EWMH helpers of GDK. Wayland has no
+// protocol for them.
+use glib::translate::ToGlibPtr;
+use gtk::prelude::*;
+
+extern "C" {
+  fn gdk_x11_screen_get_number_of_desktops(screen: *mut gdk_sys::GdkScreen) -> u32;
+  fn gdk_x11_screen_get_current_desktop(screen: *mut gdk_sys::GdkScreen) -> u32;
+  fn gdk_x11_window_get_desktop(window: *mut gdk_sys::GdkWindow) -> u32;
+  fn gdk_x11_window_move_to_desktop(window: *mut gdk_sys::GdkWindow, desktop: u32);
+}
+
+// `_NET_WM_DESKTOP` of the windows shown on every desktop.
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+/// The index of a virtual desktop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VirtualDesktop(pub(crate) u32);
+
+pub fn is_x11(display: &gdk::Display) -> bool {
+  display.type_().name() == "GdkX11Display"
+}
+
+pub fn virtual_desktops(display: &gdk::Display) -> Vec<VirtualDesktop> {
+  if !is_x11(display) {
+    return Vec::new();
+  }
+
+  let screen = display.default_screen();
+  let count = unsafe { gdk_x11_screen_get_number_of_desktops(screen.to_glib_none().0) };
+  (0..count).map(VirtualDesktop).collect()
+}
+
+pub fn current_virtual_desktop(display: &gdk::Display) -> Option<VirtualDesktop> {
+  if !is_x11(display) {
+    return None;
+  }
+
+  let screen = display.default_screen();
+  let desktop = unsafe { gdk_x11_screen_get_current_desktop(screen.to_glib_none().0) };
+  Some(VirtualDesktop(desktop))
+}
+
+pub fn window_virtual_desktop(window: &gdk::Window) -> Option<VirtualDesktop> {
+  if !is_x11(&window.display()) {
+    return None;
+  }
+
+  match unsafe { gdk_x11_window_get_desktop(window.to_glib_none().0) } {
+    ALL_DESKTOPS => None,
+    desktop => Some(VirtualDesktop(desktop)),
+  }
+}
+
+pub fn move_to_virtual_desktop(window: &gdk::Window, desktop: VirtualDesktop) {
+  if is_x11(&window.display()) {
+    unsafe { gdk_x11_window_move_to_desktop(window.to_glib_none().0, desktop.0) };
+  }
+}
@@ -2,28 +2,42 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-  cell::RefCell,
+  cell::{Cell, RefCell},
   collections::VecDeque,
   rc::Rc,
-  sync::atomic::{AtomicBool, AtomicI32, Ordering},
+  sync::{
+    atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering},
+    Arc,
+  },
+  time::Duration,
 };
 
-use gdk::{WindowEdge, WindowState};
+use gdk::{WindowEdge, WindowState, WindowTypeHint};
 use gdk_pixbuf::{Colorspace, Pixbuf};
+use glib::{Continue, ToVariant, VariantDict};
 use gtk::{prelude::*, AccelGroup, ApplicationWindow, Orientation};
 
 use crate::{
-  dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+  dpi::{LogicalPosition, PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
+  event_loop::TimerId,
   icon::{BadIcon, Icon},
   menu::{MenuId, MenuItem},
-  monitor::MonitorHandle as RootMonitorHandle,
-  window::{CursorIcon, Fullscreen, UserAttentionType, WindowAttributes, BORDERLESS_RESIZE_INSET},
+  monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
+  window::{
+    AspectRatio, CursorGrabMode, CursorIcon, DragRegion, DragRegionKind, Edges, Effect, Fullscreen,
+    ProgressState, RedrawPacing, ResizeDirection, Theme, TitlebarButtons, UserAttentionType,
+    WindowAttributes, WindowImage, WindowLevel, WindowType, BORDERLESS_RESIZE_INSET,
+  },
 };
 
 use super::{
-  event_loop::EventLoopWindowTarget, menu, monitor::MonitorHandle,
-  PlatformSpecificWindowBuilderAttributes,
+  embed,
+  event_loop::EventLoopWindowTarget,
+  menu,
+  monitor::MonitorHandle,
+  virtual_desktop::{self, VirtualDesktop},
+  OsError, PlatformSpecificWindowBuilderAttributes,
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -90,8 +104,17 @@ pub struct Window {
   position: Rc<(AtomicI32, AtomicI32)>,
   size: Rc<(AtomicI32, AtomicI32)>,
   maximized: Rc<AtomicBool>,
+  /// The position and the size of the window the last time it was not maximized.
+  normal_rect: Rc<Cell<(PhysicalPosition<i32>, PhysicalSize<u32>)>>,
   minimized: Rc<AtomicBool>,
   fullscreen: RefCell<Option<Fullscreen>>,
+  drag_regions: Rc<RefCell<Vec<DragRegion>>>,
+  resizable_edges: Arc<AtomicU32>,
+  minimize_to_tray: Arc<AtomicBool>,
+  text_input: Arc<AtomicBool>,
+  redraw_pacing: Cell<RedrawPacing>,
+  decorations: Cell<bool>,
+  shadow: Cell<bool>,
 }
 
 impl Window {
@@ -126,40 +149,20 @@ impl Window {
     }
 
     // Set Min/Max Size
-    let geom_mask = (if attributes.min_inner_size.is_some() {
-      gdk::WindowHints::MIN_SIZE
-    } else {
-      gdk::WindowHints::empty()
-    }) | (if attributes.max_inner_size.is_some() {
-      gdk::WindowHints::MAX_SIZE
-    } else {
-      gdk::WindowHints::empty()
-    });
-    let (min_width, min_height) = attributes
-      .min_inner_size
-      .map(|size| size.to_logical::<f64>(win_scale_factor as f64).into())
-      .unwrap_or_default();
-    let (max_width, max_height) = attributes
-      .max_inner_size
-      .map(|size| size.to_logical::<f64>(win_scale_factor as f64).into())
-      .unwrap_or_default();
-    window.set_geometry_hints::<ApplicationWindow>(
-      None,
-      Some(&gdk::Geometry {
-        min_width,
-        min_height,
-        max_width,
-        max_height,
-        base_width: 0,
-        base_height: 0,
-        width_inc: 0,
-        height_inc: 0,
-        min_aspect: 0f64,
-        max_aspect: 0f64,
-        win_gravity: gdk::Gravity::Center,
-      }),
-      geom_mask,
-    );
+    let geometry_hints = GeometryHints {
+      min_size: attributes
+        .min_inner_size
+        .map(|size| size.to_logical::<i32>(win_scale_factor as f64).into()),
+      max_size: attributes
+        .max_inner_size
+        .map(|size| size.to_logical::<i32>(win_scale_factor as f64).into()),
+      ..Default::default()
+    };
+    geometry_hints.apply(&window);
+    event_loop_window_target
+      .geometry_hints
+      .borrow_mut()
+      .insert(window_id, geometry_hints);
 
     // Set Position
     if let Some(position) = attributes.position {
@@ -198,6 +201,23 @@ impl Window {
 
     // Rest attributes
     window.set_title(&attributes.title);
+    if let Some(parent) = attributes
+      .parent_window
+      .and_then(|parent| app.window_by_id((parent.0).0))
+    {
+      window.set_transient_for(Some(&parent));
+      window.set_destroy_with_parent(true);
+    }
+    window.set_modal(attributes.modal);
+    window.set_accept_focus(attributes.focusable);
+    window.set_focus_on_map(attributes.focusable);
+    window.set_type_hint(match attributes.window_type {
+      WindowType::Utility => WindowTypeHint::Utility,
+      WindowType::Splash => WindowTypeHint::Splashscreen,
+      WindowType::Tooltip => WindowTypeHint::Tooltip,
+      WindowType::Popup => WindowTypeHint::PopupMenu,
+      WindowType::Normal => WindowTypeHint::Normal,
+    });
     // TODO set it with Fullscreen enum
     if attributes.fullscreen.is_some() {
       window.fullscreen();
@@ -208,11 +228,20 @@ impl Window {
     window.set_visible(attributes.visible);
     window.set_decorated(attributes.decorations);
 
-    window.set_keep_above(attributes.always_on_top);
+    window.set_keep_above(attributes.window_level > WindowLevel::Normal);
+    window.set_keep_below(attributes.window_level == WindowLevel::AlwaysOnBottom);
     if let Some(icon) = attributes.window_icon {
       window.set_icon(Some(&icon.inner.into()));
     }
 
+    if let Some(parent) = attributes.foreign_parent {
+      let position = attributes
+        .position
+        .map(|position| position.to_physical::<i32>(win_scale_factor as f64).into())
+        .unwrap_or_default();
+      embed::embed_in_foreign_parent(&window, parent, position);
+    }
+
     if attributes.visible {
       window.show_all();
     } else {
@@ -227,6 +256,17 @@ impl Window {
     let size: Rc<(AtomicI32, AtomicI32)> = Rc::new((w_size.0.into(), w_size.1.into()));
     let size_clone = size.clone();
 
+    let w_max = window.is_maximized();
+    let maximized: Rc<AtomicBool> = Rc::new(w_max.into());
+    let max_clone = maximized.clone();
+
+    let normal_rect = Rc::new(Cell::new((
+      PhysicalPosition::new(w_pos.0, w_pos.1),
+      PhysicalSize::new(w_size.0 as u32, w_size.1 as u32),
+    )));
+    let normal_rect_clone = normal_rect.clone();
+    let configure_max_clone = maximized.clone();
+
     window.connect_configure_event(move |_, event| {
       let (x, y) = event.position();
       position_clone.0.store(x, Ordering::Release);
@@ -236,12 +276,12 @@ impl Window {
       size_clone.0.store(w as i32, Ordering::Release);
       size_clone.1.store(h as i32, Ordering::Release);
 
+      if !configure_max_clone.load(Ordering::Acquire) {
+        normal_rect_clone.set((PhysicalPosition::new(x, y), PhysicalSize::new(w, h)));
+      }
+
       false
     });
-
-    let w_max = window.is_maximized();
-    let maximized: Rc<AtomicBool> = Rc::new(w_max.into());
-    let max_clone = maximized.clone();
     let minimized = Rc::new(AtomicBool::new(false));
     let min_clone = minimized.clone();
 
@@ -258,7 +298,95 @@ impl Window {
       scale_factor_clone.store(window.scale_factor(), Ordering::Release);
     });
 
-    if let Err(e) = window_requests_tx.send((window_id, WindowRequest::WireUpEvents)) {
+    let drag_regions: Rc<RefCell<Vec<DragRegion>>> = Default::default();
+    let drag_regions_clone = drag_regions.clone();
+    let resizable_edges = Arc::new(AtomicU32::new(Edges::all().bits()));
+    let resizable_edges_clone = resizable_edges.clone();
+    let resizable_borders = attributes.resizable_borders;
+    window.connect_button_press_event(move |window, event| {
+      if window.is_decorated()
+        || event.button() != 1
+        || event.event_type() != gdk::EventType::ButtonPress
+      {
+        return Inhibit(false);
+      }
+
+      let (root_x, root_y) = event.root();
+      let (origin_x, origin_y) = match window.window() {
+        // The resize borders take precedence over the drag regions
+        Some(gdk_window)
+          if resizable_borders
+            && window.is_resizable()
+            && !matches!(
+              hit_test(
+                &gdk_window,
+                root_x,
+                root_y,
+                Edges::from_bits_truncate(resizable_edges_clone.load(Ordering::Acquire))
+              ),
+              WindowEdge::__Unknown(_)
+            ) =>
+        {
+          return Inhibit(false)
+        }
+        Some(gdk_window) => {
+          let (_, x, y) = gdk_window.origin();
+          (x as f64, y as f64)
+        }
+        None => return Inhibit(false),
+      };
+      let scale_factor = window.scale_factor() as f64;
+      let position =
+        LogicalPosition::new(root_x - origin_x, root_y - origin_y).to_physical::<f64>(scale_factor);
+
+      let kind = drag_regions_clone
+        .borrow()
+        .iter()
+        .rev()
+        .find(|region| region.contains(position, scale_factor))
+        .map(|region| region.kind);
+      let (root_x, root_y) = (root_x as i32, root_y as i32);
+      match kind {
+        Some(DragRegionKind::Caption) => window.begin_move_drag(1, root_x, root_y, event.time()),
+        Some(DragRegionKind::Resize(direction)) => {
+          let edge = match direction {
+            ResizeDirection::East => WindowEdge::East,
+            ResizeDirection::North => WindowEdge::North,
+            ResizeDirection::NorthEast => WindowEdge::NorthEast,
+            ResizeDirection::NorthWest => WindowEdge::NorthWest,
+            ResizeDirection::South => WindowEdge::South,
+            ResizeDirection::SouthEast => WindowEdge::SouthEast,
+            ResizeDirection::SouthWest => WindowEdge::SouthWest,
+            ResizeDirection::West => WindowEdge::West,
+          };
+          window.begin_resize_drag(edge, 1, root_x, root_y, event.time());
+        }
+        Some(DragRegionKind::MinimizeButton) => window.iconify(),
+        Some(DragRegionKind::MaximizeButton) => {
+          if window.is_maximized() {
+            window.unmaximize();
+          } else {
+            window.maximize();
+          }
+        }
+        Some(DragRegionKind::CloseButton) => window.close(),
+        None => return Inhibit(false),
+      }
+
+      Inhibit(true)
+    });
+
+    let minimize_to_tray = Arc::new(AtomicBool::new(attributes.minimize_to_tray));
+    let text_input = Arc::new(AtomicBool::new(false));
+    if let Err(e) = window_requests_tx.send((
+      window_id,
+      WindowRequest::WireUpEvents {
+        resizable_borders: attributes.resizable_borders,
+        resizable_edges: resizable_edges.clone(),
+        minimize_to_tray: minimize_to_tray.clone(),
+        text_input: text_input.clone(),
+      },
+    )) {
       log::warn!("Fail to send wire up events request: {}", e);
     }
 
@@ -274,11 +402,23 @@ impl Window {
       position,
       size,
       maximized,
+      normal_rect,
       minimized,
       fullscreen: RefCell::new(attributes.fullscreen),
+      drag_regions,
+      resizable_edges,
+      minimize_to_tray,
+      text_input,
+      redraw_pacing: Cell::new(RedrawPacing::default()),
+      decorations: Cell::new(attributes.decorations),
+      shadow: Cell::new(false),
     };
 
-    win.set_skip_taskbar(pl_attribs.skip_taskbar);
+    // Splash screens never have a taskbar entry
+    win.set_skip_taskbar(pl_attribs.skip_taskbar || attributes.window_type == WindowType::Splash);
+    if attributes.titlebar_buttons != TitlebarButtons::all() {
+      win.set_titlebar_buttons(attributes.titlebar_buttons);
+    }
 
     Ok(win)
   }
@@ -294,12 +434,37 @@ impl Window {
   pub fn request_redraw(&self) {
     if let Err(e) = self
       .window_requests_tx
-      .send((self.window_id, WindowRequest::Redraw))
+      .send((self.window_id, self.redraw_request()))
     {
       log::warn!("Fail to send redraw request: {}", e);
     }
   }
 
+  pub fn request_redraw_after(&self, delay: Duration) {
+    let window_requests_tx = self.window_requests_tx.clone();
+    let window_id = self.window_id;
+    let mut request = Some(self.redraw_request());
+    glib::timeout_add_local(delay, move || {
+      if let Some(request) = request.take() {
+        if let Err(e) = window_requests_tx.send((window_id, request)) {
+          log::warn!("Fail to send redraw request: {}", e);
+        }
+      }
+      Continue(false)
+    });
+  }
+
+  fn redraw_request(&self) -> WindowRequest {
+    match self.redraw_pacing.get() {
+      RedrawPacing::VSync => WindowRequest::VSyncRedraw,
+      RedrawPacing::Immediate => WindowRequest::Redraw,
+    }
+  }
+
+  pub fn set_redraw_pacing(&self, pacing: RedrawPacing) {
+    self.redraw_pacing.set(pacing);
+  }
+
   pub fn inner_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
     let (x, y) = &*self.position;
     Ok(PhysicalPosition::new(
@@ -360,27 +525,52 @@ impl Window {
   }
 
   pub fn set_min_inner_size<S: Into<Size>>(&self, min_size: Option<S>) {
-    if let Some(size) = min_size {
-      let (min_width, min_height) = size.into().to_logical::<i32>(self.scale_factor()).into();
+    let min_size = min_size.map(|size| size.into().to_logical::<i32>(self.scale_factor()).into());
 
-      if let Err(e) = self.window_requests_tx.send((
-        self.window_id,
-        WindowRequest::MinSize((min_width, min_height)),
-      )) {
-        log::warn!("Fail to send min size request: {}", e);
-      }
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::MinSize(min_size)))
+    {
+      log::warn!("Fail to send min size request: {}", e);
     }
   }
   pub fn set_max_inner_size<S: Into<Size>>(&self, max_size: Option<S>) {
-    if let Some(size) = max_size {
-      let (max_width, max_height) = size.into().to_logical::<i32>(self.scale_factor()).into();
+    let max_size = max_size.map(|size| size.into().to_logical::<i32>(self.scale_factor()).into());
 
-      if let Err(e) = self.window_requests_tx.send((
-        self.window_id,
-        WindowRequest::MaxSize((max_width, max_height)),
-      )) {
-        log::warn!("Fail to send max size request: {}", e);
-      }
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::MaxSize(max_size)))
+    {
+      log::warn!("Fail to send max size request: {}", e);
+    }
+  }
+
+  pub fn set_aspect_ratio(&self, aspect_ratio: Option<AspectRatio>) {
+    if let Err(e) = self.window_requests_tx.send((
+      self.window_id,
+      WindowRequest::AspectRatio(aspect_ratio.map(|a| a.ratio())),
+    )) {
+      log::warn!("Fail to send aspect ratio request: {}", e);
+    }
+  }
+
+  pub fn set_resizable_edges(&self, edges: Edges) {
+    self.resizable_edges.store(edges.bits(), Ordering::Release);
+
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::ResizableEdges(edges)))
+    {
+      log::warn!("Fail to send resizable edges request: {}", e);
+    }
+  }
+
+  pub fn set_titlebar_buttons(&self, buttons: TitlebarButtons) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::TitlebarButtons(buttons)))
+    {
+      log::warn!("Fail to send titlebar buttons request: {}", e);
     }
   }
 
@@ -411,6 +601,15 @@ impl Window {
     }
   }
 
+  pub fn show_without_activating(&self) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::ShowWithoutActivating))
+    {
+      log::warn!("Fail to send show without activating request: {}", e);
+    }
+  }
+
   pub fn set_focus(&self) {
     if !self.minimized.load(Ordering::Acquire) && self.window.get_visible() {
       if let Err(e) = self
@@ -422,6 +621,36 @@ impl Window {
     }
   }
 
+  pub fn raise(&self) {
+    if !self.minimized.load(Ordering::Acquire) && self.window.get_visible() {
+      if let Err(e) = self
+        .window_requests_tx
+        .send((self.window_id, WindowRequest::Raise))
+      {
+        log::warn!("Fail to send raise request: {}", e);
+      }
+    }
+  }
+
+  pub fn set_above(&self, window: &Window) {
+    self.restack(window, true);
+  }
+
+  pub fn set_below(&self, window: &Window) {
+    self.restack(window, false);
+  }
+
+  fn restack(&self, sibling: &Window, above: bool) {
+    if !self.minimized.load(Ordering::Acquire) && self.window.get_visible() {
+      if let Err(e) = self.window_requests_tx.send((
+        self.window_id,
+        WindowRequest::Restack(sibling.window_id, above),
+      )) {
+        log::warn!("Fail to send restack request: {}", e);
+      }
+    }
+  }
+
   pub fn set_resizable(&self, resizable: bool) {
     if let Err(e) = self
       .window_requests_tx
@@ -449,16 +678,42 @@ impl Window {
     }
   }
 
+  pub fn set_maximized_on(&self, monitor: RootMonitorHandle) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::MaximizedOn(monitor.inner)))
+    {
+      log::warn!("Fail to send maximized on request: {}", e);
+    }
+  }
+
   pub fn is_maximized(&self) -> bool {
     self.maximized.load(Ordering::Acquire)
   }
 
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    Some(self.normal_rect.get())
+  }
+
   pub fn is_resizable(&self) -> bool {
     self.window.is_resizable()
   }
 
   pub fn is_decorated(&self) -> bool {
-    self.window.is_decorated()
+    self.decorations.get()
+  }
+
+  pub fn set_enabled(&self, enabled: bool) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::Enabled(enabled)))
+    {
+      log::warn!("Fail to send enabled request: {}", e);
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.window.is_sensitive()
   }
 
   #[inline]
@@ -476,6 +731,10 @@ impl Window {
     Ok(())
   }
 
+  pub fn set_drag_region(&self, regions: Vec<DragRegion>) {
+    *self.drag_regions.borrow_mut() = regions;
+  }
+
   pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
     self.fullscreen.replace(fullscreen.clone());
     if let Err(e) = self
@@ -486,25 +745,129 @@ impl Window {
     }
   }
 
+  pub fn set_fullscreen_video_mode(&self, _video_mode: RootVideoMode) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
   pub fn fullscreen(&self) -> Option<Fullscreen> {
     self.fullscreen.borrow().clone()
   }
 
-  pub fn set_decorations(&self, decorations: bool) {
+  pub fn set_kiosk(&self, kiosk: bool) {
+    self.set_fullscreen(if kiosk {
+      Some(Fullscreen::Borderless(None))
+    } else {
+      None
+    });
+    self.set_window_level(if kiosk {
+      WindowLevel::Floating
+    } else {
+      WindowLevel::Normal
+    });
+
     if let Err(e) = self
       .window_requests_tx
-      .send((self.window_id, WindowRequest::Decorations(decorations)))
+      .send((self.window_id, WindowRequest::InputGrab(kiosk)))
     {
+      log::warn!("Fail to send input grab request: {}", e);
+    }
+  }
+
+  pub fn set_decorations(&self, decorations: bool) {
+    self.decorations.set(decorations);
+    self.send_decorations_request();
+  }
+
+  pub fn set_shadow(&self, shadow: bool) {
+    self.shadow.set(shadow);
+    self.send_decorations_request();
+  }
+
+  fn send_decorations_request(&self) {
+    let request = WindowRequest::Decorations {
+      decorations: self.decorations.get(),
+      shadow: self.shadow.get(),
+    };
+    if let Err(e) = self.window_requests_tx.send((self.window_id, request)) {
       log::warn!("Fail to send decorations request: {}", e);
     }
   }
 
-  pub fn set_always_on_top(&self, always_on_top: bool) {
+  pub fn set_content_protected(&self, _protected: bool) {}
+
+  pub fn set_opacity(&self, opacity: f32) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::Opacity(opacity as f64)))
+    {
+      log::warn!("Fail to send opacity request: {}", e);
+    }
+  }
+
+  pub fn set_owner(&self, owner: Option<WindowId>) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::Owner(owner)))
+    {
+      log::warn!("Fail to send owner request: {}", e);
+    }
+  }
+
+  pub fn set_window_level(&self, level: WindowLevel) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::WindowLevel(level)))
+    {
+      log::warn!("Fail to send window level request: {}", e);
+    }
+  }
+
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    self
+      .window
+      .window()
+      .and_then(|gdk_window| virtual_desktop::window_virtual_desktop(&gdk_window))
+  }
+
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    match (
+      self.virtual_desktop(),
+      virtual_desktop::current_virtual_desktop(&self.window.display()),
+    ) {
+      (Some(desktop), Some(current)) => desktop == current,
+      _ => true,
+    }
+  }
+
+  pub fn move_to_virtual_desktop(&self, desktop: &VirtualDesktop) -> Result<(), ExternalError> {
+    if !virtual_desktop::is_x11(&self.window.display()) {
+      return Err(ExternalError::NotSupported(NotSupportedError::new()));
+    }
+
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::VirtualDesktop(*desktop)))
+    {
+      log::warn!("Fail to send virtual desktop request: {}", e);
+    }
+    Ok(())
+  }
+
+  pub fn set_visible_on_all_workspaces(&self, visible: bool) {
+    if let Err(e) = self.window_requests_tx.send((
+      self.window_id,
+      WindowRequest::VisibleOnAllWorkspaces(visible),
+    )) {
+      log::warn!("Fail to send visible on all workspaces request: {}", e);
+    }
+  }
+
+  pub fn set_background_effect(&self, effect: Option<Effect>) {
     if let Err(e) = self
       .window_requests_tx
-      .send((self.window_id, WindowRequest::AlwaysOnTop(always_on_top)))
+      .send((self.window_id, WindowRequest::BackgroundEffect(effect)))
     {
-      log::warn!("Fail to send always on top request: {}", e);
+      log::warn!("Fail to send background effect request: {}", e);
     }
   }
 
@@ -517,8 +880,30 @@ impl Window {
     }
   }
 
-  pub fn set_ime_position<P: Into<Position>>(&self, _position: P) {
-    todo!()
+  pub fn set_ime_cursor_area(&self, position: Position, size: Size) {
+    let scale_factor = self.scale_factor();
+    let (x, y) = position.to_logical::<i32>(scale_factor).into();
+    let (width, height) = size.to_logical::<i32>(scale_factor).into();
+
+    if let Err(e) = self.window_requests_tx.send((
+      self.window_id,
+      WindowRequest::ImeCursorArea((x, y, width, height)),
+    )) {
+      log::warn!("Fail to send IME cursor area request: {}", e);
+    }
+  }
+
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::ImeAllowed(allowed)))
+    {
+      log::warn!("Fail to send IME allowed request: {}", e);
+    }
+  }
+
+  pub fn set_text_input(&self, active: bool) {
+    self.text_input.store(active, Ordering::Release);
   }
 
   pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
@@ -530,6 +915,24 @@ impl Window {
     }
   }
 
+  pub fn set_progress_bar(&self, state: ProgressState, progress: Option<u64>) {
+    if let Err(e) = self.window_requests_tx.send((
+      self.window_id,
+      WindowRequest::ProgressBar((state, progress)),
+    )) {
+      log::warn!("Fail to send progress bar request: {}", e);
+    }
+  }
+
+  pub fn set_badge_count(&self, count: Option<u64>) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::BadgeCount(count)))
+    {
+      log::warn!("Fail to send badge count request: {}", e);
+    }
+  }
+
   pub fn hide_menu(&self) {
     self.menu_bar.hide();
   }
@@ -542,6 +945,35 @@ impl Window {
     self.menu_bar.get_visible()
   }
 
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    let gdk_window = self
+      .window
+      .window()
+      .ok_or_else(|| ExternalError::Os(os_error!(OsError)))?;
+    // The size is in logical pixels, the pixbuf is scaled to the physical size of the window.
+    let pixbuf = gdk_window
+      .pixbuf(0, 0, gdk_window.width(), gdk_window.height())
+      .ok_or_else(|| ExternalError::Os(os_error!(OsError)))?;
+
+    let width = pixbuf.width() as usize;
+    let height = pixbuf.height() as usize;
+    let rowstride = pixbuf.rowstride() as usize;
+    let n_channels = pixbuf.n_channels() as usize;
+    let has_alpha = pixbuf.has_alpha();
+    // The pixbuf is owned by us, so nothing else can write its pixels while we read them.
+    let pixels = unsafe { pixbuf.pixels() };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in pixels.chunks(rowstride).take(height) {
+      for pixel in row[..width * n_channels].chunks_exact(n_channels) {
+        let alpha = if has_alpha { pixel[3] } else { 255 };
+        rgba.extend_from_slice(&[pixel[0], pixel[1], pixel[2], alpha]);
+      }
+    }
+
+    Ok(WindowImage::new(width as u32, height as u32, rgba))
+  }
+
   pub fn set_cursor_icon(&self, cursor: CursorIcon) {
     if let Err(e) = self
       .window_requests_tx
@@ -555,8 +987,63 @@ impl Window {
     todo!()
   }
 
-  pub fn set_cursor_grab(&self, _grab: bool) -> Result<(), ExternalError> {
-    todo!()
+  pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+    if !virtual_desktop::is_x11(&self.window.display()) {
+      return Err(ExternalError::NotSupported(NotSupportedError::new()));
+    }
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::CursorGrab(mode)))
+    {
+      log::warn!("Fail to send cursor grab request: {}", e);
+    }
+
+    Ok(())
+  }
+
+  #[cfg(feature = "test")]
+  pub fn inject_event(&self, event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    if let crate::test::SyntheticEvent::Key { physical_key, .. } = event {
+      if physical_key.to_scancode().is_none() {
+        return Err(ExternalError::NotSupported(NotSupportedError::new()));
+      }
+    }
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::InjectEvent(event)))
+    {
+      log::warn!("Fail to send inject event request: {}", e);
+    }
+
+    Ok(())
+  }
+
+  pub fn set_mouse_capture(&self, capture: bool) -> Result<(), ExternalError> {
+    if !virtual_desktop::is_x11(&self.window.display()) {
+      return Err(ExternalError::NotSupported(NotSupportedError::new()));
+    }
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::MouseCapture(capture)))
+    {
+      log::warn!("Fail to send mouse capture request: {}", e);
+    }
+
+    Ok(())
+  }
+
+  pub fn set_keyboard_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    if !virtual_desktop::is_x11(&self.window.display()) {
+      return Err(ExternalError::NotSupported(NotSupportedError::new()));
+    }
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::KeyboardGrab(grab)))
+    {
+      log::warn!("Fail to send keyboard grab request: {}", e);
+    }
+
+    Ok(())
   }
 
   pub fn set_cursor_visible(&self, visible: bool) {
@@ -573,6 +1060,26 @@ impl Window {
     }
   }
 
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::CursorIgnoreEvents(ignore)))
+    {
+      log::warn!("Fail to send cursor ignore events request: {}", e);
+    }
+
+    Ok(())
+  }
+
+  pub fn set_mouse_coalescing(&self, enabled: bool) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::MouseCoalescing(enabled)))
+    {
+      log::warn!("Fail to send mouse coalescing request: {}", e);
+    }
+  }
+
   pub fn current_monitor(&self) -> Option<RootMonitorHandle> {
     let screen = self.window.display().default_screen();
     let window = self.window.window().unwrap();
@@ -608,7 +1115,7 @@ impl Window {
     todo!()
   }
 
-  pub(crate) fn set_skip_taskbar(&self, skip: bool) {
+  pub fn set_skip_taskbar(&self, skip: bool) {
     if let Err(e) = self
       .window_requests_tx
       .send((self.window_id, WindowRequest::SetSkipTaskbar(skip)))
@@ -616,6 +1123,21 @@ impl Window {
       log::warn!("Fail to send skip taskbar request: {}", e);
     }
   }
+
+  pub fn set_theme(&self, theme: Option<Theme>) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::Theme(theme)))
+    {
+      log::warn!("Fail to send theme request: {}", e);
+    }
+  }
+
+  pub fn set_minimize_to_tray(&self, minimize_to_tray: bool) {
+    self
+      .minimize_to_tray
+      .store(minimize_to_tray, Ordering::Release);
+  }
 }
 
 // We need GtkWindow to initialize WebView, so we have to keep it in the field.
@@ -623,34 +1145,149 @@ impl Window {
 unsafe impl Send for Window {}
 unsafe impl Sync for Window {}
 
+/// The size constraints of a window.
+///
+/// GTK replaces all the geometry hints of a window at once, so they are kept together and always
+/// applied merged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GeometryHints {
+  /// The minimum size, in logical pixels.
+  pub(crate) min_size: Option<(i32, i32)>,
+  /// The maximum size, in logical pixels.
+  pub(crate) max_size: Option<(i32, i32)>,
+  pub(crate) aspect_ratio: Option<f64>,
+  pub(crate) resizable_edges: Edges,
+}
+
+impl Default for GeometryHints {
+  fn default() -> Self {
+    GeometryHints {
+      min_size: None,
+      max_size: None,
+      aspect_ratio: None,
+      resizable_edges: Edges::all(),
+    }
+  }
+}
+
+impl GeometryHints {
+  pub(crate) fn apply<W: IsA<gtk::Window>>(&self, window: &W) {
+    let mut hints = gdk::WindowHints::empty();
+    let (mut min_width, mut min_height) = self.min_size.unwrap_or((0, 0));
+    let (mut max_width, mut max_height) = self.max_size.unwrap_or((i32::MAX, i32::MAX));
+    if self.min_size.is_some() {
+      hints |= gdk::WindowHints::MIN_SIZE;
+    }
+    if self.max_size.is_some() {
+      hints |= gdk::WindowHints::MAX_SIZE;
+    }
+
+    // The window manager only knows about the size of the window, so we can only
+    // fix the width or the height when none of their edges are resizable.
+    let (width, height) = window.size();
+    if !self.resizable_edges.intersects(Edges::HORIZONTAL) {
+      min_width = width;
+      max_width = width;
+      hints |= gdk::WindowHints::MIN_SIZE | gdk::WindowHints::MAX_SIZE;
+    }
+    if !self.resizable_edges.intersects(Edges::VERTICAL) {
+      min_height = height;
+      max_height = height;
+      hints |= gdk::WindowHints::MIN_SIZE | gdk::WindowHints::MAX_SIZE;
+    }
+
+    let aspect = self.aspect_ratio.unwrap_or(0f64);
+    if self.aspect_ratio.is_some() {
+      hints |= gdk::WindowHints::ASPECT;
+    }
+
+    window.set_geometry_hints::<ApplicationWindow>(
+      None,
+      Some(&gdk::Geometry {
+        min_width,
+        min_height,
+        max_width,
+        max_height,
+        base_width: 0,
+        base_height: 0,
+        width_inc: 0,
+        height_inc: 0,
+        min_aspect: aspect,
+        max_aspect: aspect,
+        win_gravity: gdk::Gravity::Center,
+      }),
+      hints,
+    );
+  }
+}
+
 #[non_exhaustive]
 pub enum WindowRequest {
   Title(String),
   Position((i32, i32)),
   Size((i32, i32)),
-  MinSize((i32, i32)),
-  MaxSize((i32, i32)),
+  MinSize(Option<(i32, i32)>),
+  MaxSize(Option<(i32, i32)>),
+  AspectRatio(Option<f64>),
   Visible(bool),
+  ShowWithoutActivating,
   Focus,
+  Raise,
+  TitlebarButtons(TitlebarButtons),
+  Restack(WindowId, bool),
   Resizable(bool),
   Minimized(bool),
   Maximized(bool),
+  MaximizedOn(MonitorHandle),
   DragWindow,
   Fullscreen(Option<Fullscreen>),
-  Decorations(bool),
-  AlwaysOnTop(bool),
+  InputGrab(bool),
+  Close,
+  Decorations {
+    decorations: bool,
+    shadow: bool,
+  },
+  Enabled(bool),
+  Opacity(f64),
+  WindowLevel(WindowLevel),
+  VisibleOnAllWorkspaces(bool),
+  VirtualDesktop(VirtualDesktop),
+  BackgroundEffect(Option<Effect>),
   WindowIcon(Option<Icon>),
   UserAttention(Option<UserAttentionType>),
+  ProgressBar((ProgressState, Option<u64>)),
+  BadgeCount(Option<u64>),
   SetSkipTaskbar(bool),
+  Theme(Option<Theme>),
   CursorIcon(Option<CursorIcon>),
-  WireUpEvents,
+  CursorGrab(CursorGrabMode),
+  KeyboardGrab(bool),
+  MouseCapture(bool),
+  #[cfg(feature = "test")]
+  InjectEvent(crate::test::SyntheticEvent),
+  CursorIgnoreEvents(bool),
+  MouseCoalescing(bool),
+  Owner(Option<WindowId>),
+  ResizableEdges(Edges),
+  ImeAllowed(bool),
+  ImeCursorArea((i32, i32, i32, i32)),
+  WireUpEvents {
+    resizable_borders: bool,
+    resizable_edges: Arc<AtomicU32>,
+    minimize_to_tray: Arc<AtomicBool>,
+    /// Whether the entered text is sent with `ReceivedText`.
+    text_input: Arc<AtomicBool>,
+  },
   Redraw,
+  /// Requests a redraw on the next frame of the frame clock of the window.
+  VSyncRedraw,
   Menu((Option<MenuItem>, Option<MenuId>)),
   SetMenu((Option<menu::Menu>, AccelGroup, gtk::MenuBar)),
   GlobalHotKey(u16),
+  Timer(TimerId),
 }
 
-pub fn hit_test(window: &gdk::Window, cx: f64, cy: f64) -> WindowEdge {
+pub fn hit_test(window: &gdk::Window, cx: f64, cy: f64, edges: Edges) -> WindowEdge {
   let (left, top) = window.position();
   let (w, h) = (window.width(), window.height());
   let (right, bottom) = (left + w, top + h);
@@ -670,6 +1307,8 @@ pub fn hit_test(window: &gdk::Window, cx: f64, cy: f64) -> WindowEdge {
     | (RIGHT * (if cx >= (right - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
     | (TOP * (if cy < (top + BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
     | (BOTTOM * (if cy >= (bottom - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }));
+  // The bits of `Edges` are the same as the constants above.
+  let result = result & edges.bits() as i32;
 
   match result {
     LEFT => WindowEdge::West,
@@ -686,6 +1325,75 @@ pub fn hit_test(window: &gdk::Window, cx: f64, cy: f64) -> WindowEdge {
   }
 }
 
+/// GTK only draws the shadow of the client-side decorations, so undecorated windows with a shadow
+/// stay decorated with an empty titlebar. The shadow needs a visual with an alpha channel.
+pub(crate) fn apply_decorations(window: &gtk::Window, decorations: bool, shadow: bool) {
+  let empty_titlebar = !decorations && shadow;
+  if empty_titlebar != window.titlebar().is_some() {
+    // The titlebar can't be replaced while the window is realized.
+    let visible = window.is_visible();
+    window.hide();
+    window.unrealize();
+    if empty_titlebar {
+      if let Some(visual) = window.screen().and_then(|screen| screen.rgba_visual()) {
+        window.set_visual(Some(&visual));
+      }
+      window.set_titlebar(Some(&gtk::Box::new(Orientation::Horizontal, 0)));
+    } else {
+      window.set_titlebar(None::<&gtk::Widget>);
+    }
+    if visible {
+      window.show();
+    }
+  }
+  window.set_decorated(decorations || empty_titlebar);
+}
+
+/// Updates the progress of the application through the Unity `LauncherEntry` D-Bus API,
+/// which is supported by most docks and task managers on Linux.
+pub(crate) fn update_launcher_progress(
+  state: ProgressState,
+  progress: Option<u64>,
+) -> Result<(), glib::Error> {
+  let properties = VariantDict::new(None);
+  properties.insert_value(
+    "progress-visible",
+    &(state != ProgressState::None).to_variant(),
+  );
+  if let Some(progress) = progress {
+    properties.insert_value("progress", &(progress as f64 / 100.0).to_variant());
+  }
+
+  update_launcher_entry(properties)
+}
+
+/// Updates the count badge of the application through the Unity `LauncherEntry` D-Bus API.
+pub(crate) fn update_launcher_count(count: Option<u64>) -> Result<(), glib::Error> {
+  let properties = VariantDict::new(None);
+  properties.insert_value("count-visible", &count.is_some().to_variant());
+  properties.insert_value("count", &(count.unwrap_or(0) as i64).to_variant());
+
+  update_launcher_entry(properties)
+}
+
+fn update_launcher_entry(properties: VariantDict) -> Result<(), glib::Error> {
+  let connection = gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>)?;
+  let app_uri = format!(
+    "application://{}.desktop",
+    glib::prgname()
+      .map(|name| name.to_string())
+      .unwrap_or_default()
+  );
+
+  connection.emit_signal(
+    None,
+    "/",
+    "com.canonical.Unity.LauncherEntry",
+    "Update",
+    Some(&(app_uri, properties).to_variant()),
+  )
+}
+
 impl Drop for Window {
   fn drop(&mut self) {
     unsafe {
@@ -693,3 +1401,31 @@ impl Drop for Window {
     }
   }
 }
+
+/// Closes a window from any thread once its `CloseResponse` is allowed.
+#[derive(Clone)]
+pub struct CloseHandle {
+  window_id: WindowId,
+  window_requests_tx: glib::Sender<(WindowId, WindowRequest)>,
+}
+
+impl CloseHandle {
+  pub fn new(
+    window_id: WindowId,
+    window_requests_tx: glib::Sender<(WindowId, WindowRequest)>,
+  ) -> Self {
+    CloseHandle {
+      window_id,
+      window_requests_tx,
+    }
+  }
+
+  pub fn close(&self) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::Close))
+    {
+      log::warn!("Fail to send close request: {}", e);
+    }
+  }
+}
@@ -5,15 +5,24 @@ use std::collections::VecDeque;
 
 use cocoa::{
   appkit::{self, NSEvent},
-  base::id,
+  base::{id, nil},
 };
 use objc::{
   declare::ClassDecl,
   runtime::{Class, Object, Sel},
 };
 
-use super::{app_state::AppState, event::EventWrapper, util, DEVICE_ID};
-use crate::event::{DeviceEvent, ElementState, Event};
+use super::{
+  app_state::AppState,
+  event::{create_media_key_event, EventWrapper},
+  raw_input, util,
+  window::get_window_id,
+  DEVICE_ID,
+};
+use crate::{
+  event::{DeviceEvent, ElementState, Event, WindowEvent},
+  window::WindowId,
+};
 
 pub struct AppClass(pub *const Class);
 unsafe impl Send for AppClass {}
@@ -52,13 +61,37 @@ extern "C" fn send_event(this: &Object, _sel: Sel, event: id) {
       let key_window: id = msg_send![this, keyWindow];
       let _: () = msg_send![key_window, sendEvent: event];
     } else {
-      maybe_dispatch_device_event(event);
+      if !AppState::device_events_filtered() {
+        maybe_dispatch_device_event(event);
+      }
+      if event_type == appkit::NSSystemDefined {
+        maybe_dispatch_media_key_event(this, event);
+      }
       let superclass = util::superclass(this);
       let _: () = msg_send![super(this, superclass), sendEvent: event];
     }
   }
 }
 
+// The media keys are sent to the application rather than to its key window.
+unsafe fn maybe_dispatch_media_key_event(this: &Object, event: id) {
+  let key_window: id = msg_send![this, keyWindow];
+  if key_window == nil {
+    return;
+  }
+  if let Some(key_event) = create_media_key_event(event) {
+    AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+      window_id: WindowId(get_window_id(key_window)),
+      event: WindowEvent::KeyboardInput {
+        device_id: DEVICE_ID,
+        event: key_event,
+        is_synthetic: false,
+      },
+      timestamp: util::event_timestamp(event),
+    }));
+  }
+}
+
 unsafe fn maybe_dispatch_device_event(event: id) {
   let event_type = event.eventType();
   match event_type {
@@ -78,6 +111,7 @@ unsafe fn maybe_dispatch_device_event(event: id) {
             axis: 0,
             value: delta_x,
           },
+          timestamp: util::event_timestamp(event),
         }));
       }
 
@@ -88,15 +122,18 @@ unsafe fn maybe_dispatch_device_event(event: id) {
             axis: 1,
             value: delta_y,
           },
+          timestamp: util::event_timestamp(event),
         }));
       }
 
-      if delta_x != 0.0 || delta_y != 0.0 {
+      // The raw motion comes from the HID manager when it could be opened.
+      if (delta_x != 0.0 || delta_y != 0.0) && !raw_input::is_raw_mouse_motion() {
         events.push_back(EventWrapper::StaticEvent(Event::DeviceEvent {
           device_id: DEVICE_ID,
           event: DeviceEvent::MouseMotion {
             delta: (delta_x, delta_y),
           },
+          timestamp: util::event_timestamp(event),
         }));
       }
 
@@ -111,6 +148,7 @@ unsafe fn maybe_dispatch_device_event(event: id) {
           button: event.buttonNumber() as u32,
           state: ElementState::Pressed,
         },
+        timestamp: util::event_timestamp(event),
       }));
 
       AppState::queue_events(events);
@@ -124,6 +162,7 @@ unsafe fn maybe_dispatch_device_event(event: id) {
           button: event.buttonNumber() as u32,
           state: ElementState::Released,
         },
+        timestamp: util::event_timestamp(event),
       }));
 
       AppState::queue_events(events);
@@ -1,9 +1,22 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{platform::macos::ActivationPolicy, platform_impl::platform::app_state::AppState};
+use crate::{
+  event::{Event, SuspendReason},
+  keyboard::KeyboardLayout,
+  platform::macos::ActivationPolicy,
+  platform_impl::platform::{
+    app_state::AppState,
+    event::{self, EventWrapper},
+    ffi,
+  },
+};
 
-use cocoa::base::id;
+use cocoa::{
+  appkit::{NSApp, NSApplicationTerminateReply},
+  base::{id, nil},
+  foundation::NSUInteger,
+};
 use objc::{
   declare::ClassDecl,
   runtime::{Class, Object, Sel},
@@ -22,6 +35,12 @@ pub struct AuxDelegateState {
   pub activation_policy: ActivationPolicy,
 
   pub create_default_menu: bool,
+
+  /// Whether `Event::Suspended` was sent because the application was occluded.
+  pub hidden: bool,
+
+  /// The last keyboard layout, as the input source can change without changing the layout.
+  pub keyboard_layout: Option<KeyboardLayout>,
 }
 
 pub struct AppDelegateClass(pub *const Class);
@@ -40,6 +59,26 @@ lazy_static! {
       sel!(applicationDidFinishLaunching:),
       did_finish_launching as extern "C" fn(&Object, Sel, id),
     );
+    decl.add_method(
+      sel!(applicationShouldTerminate:),
+      should_terminate as extern "C" fn(&Object, Sel, id) -> NSUInteger,
+    );
+    decl.add_method(
+      sel!(applicationDidChangeOcclusionState:),
+      did_change_occlusion_state as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(workspaceWillSleep:),
+      workspace_will_sleep as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(workspaceDidWake:),
+      workspace_did_wake as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(selectedKeyboardInputSourceChanged:),
+      selected_keyboard_input_source_changed as extern "C" fn(&Object, Sel, id),
+    );
     decl.add_ivar::<*mut c_void>(AUX_DELEGATE_STATE_NAME);
 
     AppDelegateClass(decl.register())
@@ -62,14 +101,44 @@ extern "C" fn new(class: &Class, _: Sel) -> id {
       Box::into_raw(Box::new(RefCell::new(AuxDelegateState {
         activation_policy: ActivationPolicy::Regular,
         create_default_menu: true,
+        hidden: false,
+        keyboard_layout: event::current_keyboard_layout(),
       }))) as *mut c_void,
     );
+
+    // The sleep notifications are only posted to the notification center of the workspace.
+    let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+    let notification_center: id = msg_send![workspace, notificationCenter];
+    let () = msg_send![notification_center,
+      addObserver: this
+      selector: sel!(workspaceWillSleep:)
+      name: ffi::NSWorkspaceWillSleepNotification
+      object: nil];
+    let () = msg_send![notification_center,
+      addObserver: this
+      selector: sel!(workspaceDidWake:)
+      name: ffi::NSWorkspaceDidWakeNotification
+      object: nil];
+
+    // The input source is selected for the whole system, so its changes are distributed.
+    let notification_center: id = msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+    let () = msg_send![notification_center,
+      addObserver: this
+      selector: sel!(selectedKeyboardInputSourceChanged:)
+      name: ffi::kTISNotifySelectedKeyboardInputSourceChanged as id
+      object: nil];
     this
   }
 }
 
 extern "C" fn dealloc(this: &Object, _: Sel) {
   unsafe {
+    let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+    let notification_center: id = msg_send![workspace, notificationCenter];
+    let () = msg_send![notification_center, removeObserver: this];
+    let notification_center: id = msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+    let () = msg_send![notification_center, removeObserver: this];
+
     let state_ptr: *mut c_void = *(this.get_ivar(AUX_DELEGATE_STATE_NAME));
     // As soon as the box is constructed it is immediately dropped, releasing the underlying
     // memory
@@ -82,3 +151,60 @@ extern "C" fn did_finish_launching(this: &Object, _: Sel, _: id) {
   AppState::launched(this);
   trace!("Completed `applicationDidFinishLaunching`");
 }
+
+// Sent on `Cmd+Q`, when quitting from the dock, or when the user logs out.
+extern "C" fn should_terminate(_: &Object, _: Sel, _: id) -> NSUInteger {
+  trace!("Triggered `applicationShouldTerminate`");
+  let reply = match AppState::request_exit() {
+    // The event loop exits by itself if the exit wasn't prevented, so it sends `LoopDestroyed`.
+    Some(_) => NSApplicationTerminateReply::NSTerminateCancel,
+    None => NSApplicationTerminateReply::NSTerminateNow,
+  };
+  trace!("Completed `applicationShouldTerminate`");
+  reply as NSUInteger
+}
+
+// App Nap can only throttle applications that are occluded, so the occlusion state of the
+// application tells when it may be napping.
+extern "C" fn did_change_occlusion_state(this: &Object, _: Sel, _: id) {
+  trace!("Triggered `applicationDidChangeOcclusionState`");
+  let occlusion_state: NSUInteger = unsafe { msg_send![NSApp(), occlusionState] };
+  let hidden = occlusion_state & ffi::NSApplicationOcclusionStateVisible == 0;
+
+  let mut aux_state = unsafe { get_aux_state_mut(this) };
+  if aux_state.hidden != hidden {
+    aux_state.hidden = hidden;
+    let event = if hidden {
+      Event::Suspended(SuspendReason::Hidden)
+    } else {
+      Event::Resumed(SuspendReason::Hidden)
+    };
+    AppState::queue_event(EventWrapper::StaticEvent(event));
+  }
+  trace!("Completed `applicationDidChangeOcclusionState`");
+}
+
+extern "C" fn workspace_will_sleep(_: &Object, _: Sel, _: id) {
+  AppState::queue_event(EventWrapper::StaticEvent(Event::Suspended(
+    SuspendReason::Sleep,
+  )));
+}
+
+extern "C" fn workspace_did_wake(_: &Object, _: Sel, _: id) {
+  AppState::queue_event(EventWrapper::StaticEvent(Event::Resumed(
+    SuspendReason::Sleep,
+  )));
+}
+
+extern "C" fn selected_keyboard_input_source_changed(this: &Object, _: Sel, _: id) {
+  let keyboard_layout = event::current_keyboard_layout();
+  let mut aux_state = unsafe { get_aux_state_mut(this) };
+  if aux_state.keyboard_layout != keyboard_layout {
+    aux_state.keyboard_layout = keyboard_layout.clone();
+    if let Some(keyboard_layout) = keyboard_layout {
+      AppState::queue_event(EventWrapper::StaticEvent(Event::KeyboardLayoutChanged(
+        keyboard_layout,
+      )));
+    }
+  }
+}
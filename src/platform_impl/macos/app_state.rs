@@ -60,11 +60,14 @@ pub trait EventHandler: Debug {
   // Not sure probably it should accept Event<'static, Never>
   fn handle_nonuser_event(&mut self, event: Event<'_, Never>, control_flow: &mut ControlFlow);
   fn handle_user_events(&mut self, control_flow: &mut ControlFlow);
+  /// Sends `ExitRequested`, and exits unless it's prevented. Returns whether it was prevented.
+  fn handle_exit_requested(&mut self, control_flow: &mut ControlFlow) -> bool;
 }
 
 struct EventLoopHandler<T: 'static> {
   callback: Weak<RefCell<dyn FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow)>>,
-  will_exit: bool,
+  /// The code of the first `ControlFlow::ExitWithCode`, which can't be changed afterwards.
+  exit_code: Option<i32>,
   window_target: Rc<RootWindowTarget<T>>,
 }
 
@@ -101,33 +104,52 @@ impl<T> EventHandler for EventLoopHandler<T> {
   fn handle_nonuser_event(&mut self, event: Event<'_, Never>, control_flow: &mut ControlFlow) {
     self.with_callback(|this, mut callback| {
       (callback)(event.userify(), &this.window_target, control_flow);
-      this.will_exit |= *control_flow == ControlFlow::Exit;
-      if this.will_exit {
-        *control_flow = ControlFlow::Exit;
+      match this.exit_code {
+        Some(exit_code) => *control_flow = ControlFlow::ExitWithCode(exit_code),
+        None => this.exit_code = control_flow.exit_code(),
       }
     });
   }
 
   fn handle_user_events(&mut self, control_flow: &mut ControlFlow) {
     self.with_callback(|this, mut callback| {
-      let mut will_exit = this.will_exit;
-      for event in this.window_target.p.receiver.try_iter() {
+      let mut exit_code = this.exit_code;
+      while let Some(event) = this.window_target.p.user_events.pop() {
         (callback)(Event::UserEvent(event), &this.window_target, control_flow);
-        will_exit |= *control_flow == ControlFlow::Exit;
-        if will_exit {
-          *control_flow = ControlFlow::Exit;
+        match exit_code {
+          Some(exit_code) => *control_flow = ControlFlow::ExitWithCode(exit_code),
+          None => exit_code = control_flow.exit_code(),
         }
       }
-      this.will_exit = will_exit;
+      this.exit_code = exit_code;
     });
   }
+
+  fn handle_exit_requested(&mut self, control_flow: &mut ControlFlow) -> bool {
+    let mut prevent = false;
+    self.handle_nonuser_event(
+      Event::ExitRequested {
+        prevent: &mut prevent,
+      },
+      control_flow,
+    );
+    if !prevent && self.exit_code.is_none() {
+      self.exit_code = Some(0);
+      *control_flow = ControlFlow::Exit;
+    }
+    prevent
+  }
 }
 
 #[derive(Default)]
 struct Handler {
   ready: AtomicBool,
+  running: AtomicBool,
+  stop_when_cleared: AtomicBool,
+  device_events_filtered: AtomicBool,
   in_callback: AtomicBool,
   dialog_is_closing: AtomicBool,
+  exit_requested: AtomicBool,
   control_flow: Mutex<ControlFlow>,
   control_flow_prev: Mutex<ControlFlow>,
   start_time: Mutex<Option<Instant>>,
@@ -161,8 +183,8 @@ impl Handler {
     self.ready.store(true, Ordering::Release);
   }
 
-  fn should_exit(&self) -> bool {
-    *self.control_flow.lock().unwrap() == ControlFlow::Exit
+  fn exit_code(&self) -> Option<i32> {
+    self.control_flow.lock().unwrap().exit_code()
   }
 
   fn get_control_flow_and_update_prev(&self) -> ControlFlow {
@@ -209,9 +231,18 @@ impl Handler {
         }
         EventWrapper::EventProxy(proxy) => self.handle_proxy(proxy, callback),
       }
+    } else if self.running.load(Ordering::Acquire) {
+      // Between two calls of `pump_events`, the events are kept for the next one.
+      self.events().push_back(wrapper);
     }
   }
 
+  fn handle_exit_requested(&self) -> Option<bool> {
+    let mut callback = self.callback.lock().unwrap();
+    let callback = callback.as_mut()?;
+    Some(callback.handle_exit_requested(&mut *self.control_flow.lock().unwrap()))
+  }
+
   fn handle_user_events(&self) {
     if let Some(ref mut callback) = *self.callback.lock().unwrap() {
       callback.handle_user_events(&mut *self.control_flow.lock().unwrap());
@@ -233,6 +264,7 @@ impl Handler {
         scale_factor,
         new_inner_size,
       },
+      timestamp: Instant::now(),
     };
 
     callback.handle_nonuser_event(event, &mut *self.control_flow.lock().unwrap());
@@ -267,16 +299,91 @@ impl AppState {
   ) {
     *HANDLER.callback.lock().unwrap() = Some(Box::new(EventLoopHandler {
       callback,
-      will_exit: false,
+      exit_code: None,
       window_target,
     }));
   }
 
-  pub fn exit() {
+  /// Sends `LoopDestroyed`, and returns the exit code of the event loop.
+  pub fn exit() -> i32 {
     HANDLER.set_in_callback(true);
     HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::LoopDestroyed));
     HANDLER.set_in_callback(false);
     HANDLER.callback.lock().unwrap().take();
+    let exit_code = HANDLER.exit_code().unwrap_or(0);
+
+    // The event loop may be run again, which starts over with the default control flow.
+    HANDLER.running.store(false, Ordering::Release);
+    *HANDLER.control_flow.lock().unwrap() = ControlFlow::default();
+    *HANDLER.control_flow_prev.lock().unwrap() = ControlFlow::default();
+    exit_code
+  }
+
+  /// Sends `ExitRequested`. Returns whether the exit was prevented, or `None` if the event
+  /// handler can't be asked, e.g. between two calls of `pump_events`.
+  pub fn request_exit() -> Option<bool> {
+    if HANDLER.get_in_callback() {
+      return None;
+    }
+    HANDLER.set_in_callback(true);
+    let prevent = HANDLER.handle_exit_requested();
+    HANDLER.set_in_callback(false);
+    prevent
+  }
+
+  /// Sends `ExitRequested` the next time the events are cleared.
+  pub fn queue_exit_request() {
+    HANDLER.exit_requested.store(true, Ordering::Release);
+    unsafe {
+      let rl = CFRunLoopGetMain();
+      CFRunLoopWakeUp(rl);
+    }
+  }
+
+  /// Removes the callback without exiting, when `pump_events` returns.
+  pub fn clear_callback() {
+    HANDLER.callback.lock().unwrap().take();
+  }
+
+  pub fn is_launched() -> bool {
+    HANDLER.is_ready()
+  }
+
+  /// Returns the exit code if the control flow is `ControlFlow::ExitWithCode`.
+  pub fn exit_code() -> Option<i32> {
+    HANDLER.exit_code()
+  }
+
+  /// Stops the application the next time the events are cleared, so `pump_events` returns once
+  /// the pending events are handled.
+  pub fn set_stop_when_cleared(stop: bool) {
+    HANDLER.stop_when_cleared.store(stop, Ordering::Release);
+  }
+
+  pub fn set_device_events_filtered(filtered: bool) {
+    HANDLER.device_events_filtered.store(filtered, Ordering::Release);
+  }
+
+  pub fn device_events_filtered() -> bool {
+    HANDLER.device_events_filtered.load(Ordering::Acquire)
+  }
+
+  /// Sends `StartCause::Init` when the event loop runs again after exiting, since
+  /// `applicationDidFinishLaunching` is only sent to the first run.
+  pub fn restart() {
+    if HANDLER.is_ready() && !HANDLER.running.swap(true, Ordering::AcqRel) {
+      // The delegate belongs to a new event loop if the previous one was dropped.
+      let app_delegate: id = unsafe { msg_send![NSApp(), delegate] };
+      if app_delegate != nil {
+        apply_activation_policy(unsafe { &*app_delegate });
+      }
+      HANDLER.waker().start();
+      HANDLER.set_in_callback(true);
+      HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::NewEvents(
+        StartCause::Init,
+      )));
+      HANDLER.set_in_callback(false);
+    }
   }
 
   pub fn launched(app_delegate: &Object) {
@@ -288,6 +395,7 @@ impl AppState {
       ns_app.activateIgnoringOtherApps_(YES);
     };
     HANDLER.set_ready();
+    HANDLER.running.store(true, Ordering::Release);
     HANDLER.waker().start();
     HANDLER.set_in_callback(true);
     HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::NewEvents(
@@ -323,7 +431,7 @@ impl AppState {
           }
         }
       }
-      ControlFlow::Exit => StartCause::Poll, //panic!("unexpected `ControlFlow::Exit`"),
+      ControlFlow::ExitWithCode(_) => StartCause::Poll, //panic!("unexpected `ControlFlow::Exit`"),
     };
     HANDLER.set_in_callback(true);
     HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::NewEvents(cause)));
@@ -373,14 +481,23 @@ impl AppState {
       for event in HANDLER.take_events() {
         HANDLER.handle_nonuser_event(event);
       }
+      // Kept for the next call of `pump_events` if there's no callback.
+      if HANDLER.exit_requested.load(Ordering::Acquire) && HANDLER.handle_exit_requested().is_some()
+      {
+        HANDLER.exit_requested.store(false, Ordering::Release);
+      }
       HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::MainEventsCleared));
       for window_id in HANDLER.should_redraw() {
         HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::RedrawRequested(window_id)));
       }
       HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(Event::RedrawEventsCleared));
+      let control_flow = *HANDLER.control_flow.lock().unwrap();
+      if let Some(event) = Event::idle(control_flow) {
+        HANDLER.handle_nonuser_event(EventWrapper::StaticEvent(event));
+      }
       HANDLER.set_in_callback(false);
     }
-    if HANDLER.should_exit() {
+    if HANDLER.exit_code().is_some() {
       unsafe {
         let app: id = NSApp();
         let windows: id = msg_send![app, windows];
@@ -415,9 +532,16 @@ impl AppState {
         }
       };
     }
+    if HANDLER.stop_when_cleared.load(Ordering::Acquire) {
+      unsafe {
+        let app: id = NSApp();
+        let () = msg_send![app, stop: nil];
+        post_dummy_event(app);
+      }
+    }
     HANDLER.update_start_time();
     match HANDLER.get_old_and_new_control_flow() {
-      (ControlFlow::Exit, _) | (_, ControlFlow::Exit) => (),
+      (ControlFlow::ExitWithCode(_), _) | (_, ControlFlow::ExitWithCode(_)) => (),
       (old, new) if old == new => (),
       (_, ControlFlow::Wait) => HANDLER.waker().stop(),
       (_, ControlFlow::WaitUntil(instant)) => HANDLER.waker().start_at(instant),
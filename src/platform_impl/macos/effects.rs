@@ -0,0 +1,115 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use cocoa::{
+  appkit::{NSView, NSViewHeightSizable, NSViewWidthSizable},
+  base::{id, nil},
+  foundation::{NSInteger, NSUInteger},
+};
+use objc::runtime::{BOOL, NO};
+
+use crate::{
+  platform::macos::{NSVisualEffectBlendingMode, NSVisualEffectMaterial, NSVisualEffectState},
+  platform_impl::platform::util,
+  window::Effect,
+};
+
+// `NSWindowBelow`
+const NS_WINDOW_BELOW: NSInteger = -1;
+
+// Used to find the effect view among the subviews of the content view.
+const VISUAL_EFFECT_VIEW_IDENTIFIER: &str = "TaoVisualEffectView";
+
+/// The raw `NSVisualEffectView` properties of an effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisualEffect {
+  pub material: NSInteger,
+  pub blending_mode: NSInteger,
+  pub state: NSInteger,
+}
+
+impl VisualEffect {
+  pub fn new(
+    material: NSVisualEffectMaterial,
+    blending_mode: NSVisualEffectBlendingMode,
+    state: NSVisualEffectState,
+  ) -> Self {
+    VisualEffect {
+      material: material as NSInteger,
+      blending_mode: blending_mode as NSInteger,
+      state: state as NSInteger,
+    }
+  }
+}
+
+impl From<Effect> for VisualEffect {
+  fn from(effect: Effect) -> Self {
+    let material = match effect {
+      Effect::Blur => NSVisualEffectMaterial::HudWindow,
+      Effect::Acrylic => NSVisualEffectMaterial::Popover,
+      Effect::Mica => NSVisualEffectMaterial::UnderWindowBackground,
+    };
+
+    VisualEffect::new(
+      material,
+      NSVisualEffectBlendingMode::BehindWindow,
+      NSVisualEffectState::Active,
+    )
+  }
+}
+
+// Adds, updates or removes the `NSVisualEffectView` drawn below every other subview of `ns_view`.
+// Must be called on the main thread.
+pub unsafe fn set_visual_effect(ns_view: id, effect: Option<VisualEffect>) {
+  let identifier = util::ns_string_id_ref(VISUAL_EFFECT_VIEW_IDENTIFIER);
+  let mut effect_view = find_effect_view(ns_view, *identifier);
+
+  let effect = match effect {
+    Some(effect) => effect,
+    None => {
+      if effect_view != nil {
+        let () = msg_send![effect_view, removeFromSuperview];
+      }
+      return;
+    }
+  };
+
+  if effect_view == nil {
+    effect_view = msg_send![class!(NSVisualEffectView), alloc];
+    effect_view = msg_send![effect_view, initWithFrame: NSView::bounds(ns_view)];
+    let () = msg_send![
+      effect_view,
+      setAutoresizingMask: NSViewWidthSizable | NSViewHeightSizable
+    ];
+    let () = msg_send![effect_view, setIdentifier: *identifier];
+    let () = msg_send![
+      ns_view,
+      addSubview: effect_view
+      positioned: NS_WINDOW_BELOW
+      relativeTo: nil
+    ];
+    let () = msg_send![effect_view, release];
+  }
+
+  let () = msg_send![effect_view, setMaterial: effect.material];
+  let () = msg_send![effect_view, setBlendingMode: effect.blending_mode];
+  let () = msg_send![effect_view, setState: effect.state];
+}
+
+unsafe fn find_effect_view(ns_view: id, identifier: id) -> id {
+  let subviews: id = msg_send![ns_view, subviews];
+  let count: NSUInteger = msg_send![subviews, count];
+
+  for i in 0..count {
+    let subview: id = msg_send![subviews, objectAtIndex: i];
+    let subview_identifier: id = msg_send![subview, identifier];
+    if subview_identifier != nil {
+      let is_equal: BOOL = msg_send![subview_identifier, isEqualToString: identifier];
+      if is_equal != NO {
+        return subview;
+      }
+    }
+  }
+
+  nil
+}
@@ -1,19 +1,26 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::HashSet, ffi::c_void, os::raw::c_ushort, sync::Mutex};
+use std::{collections::HashSet, ffi::c_void, os::raw::c_ushort, sync::Mutex, time::Duration};
 
 use cocoa::{
   appkit::{NSEvent, NSEventModifierFlags},
   base::id,
+  foundation::{NSInteger, NSUInteger},
 };
 
-use core_foundation::{base::CFRelease, data::CFDataGetBytePtr};
+use core_foundation::{
+  base::{CFRelease, TCFType},
+  data::CFDataGetBytePtr,
+  string::{CFString, CFStringRef},
+};
 
 use crate::{
   dpi::LogicalSize,
   event::{ElementState, Event, KeyEvent},
-  keyboard::{Key, KeyCode, KeyLocation, ModifiersState, NativeKeyCode},
+  keyboard::{
+    Key, KeyCode, KeyLocation, KeyRepeatInfo, KeyboardLayout, ModifiersState, NativeKeyCode,
+  },
   platform_impl::platform::{
     ffi,
     util::{ns_string_to_rust, IdRef, Never},
@@ -24,7 +31,7 @@ lazy_static! {
   static ref KEY_STRINGS: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
 }
 
-fn insert_or_get_key_str(string: String) -> &'static str {
+pub fn insert_or_get_key_str(string: String) -> &'static str {
   let mut string_set = KEY_STRINGS.lock().unwrap();
   if let Some(contained) = string_set.get(string.as_str()) {
     return contained;
@@ -58,6 +65,39 @@ pub struct KeyEventExtra {
   pub key_without_modifiers: Key<'static>,
 }
 
+/// Returns the layout of the keyboard, identified by the id of its input source.
+pub fn current_keyboard_layout() -> Option<KeyboardLayout> {
+  unsafe {
+    let input_source = ffi::TISCopyCurrentKeyboardLayoutInputSource();
+    if input_source.is_null() {
+      return None;
+    }
+    let id =
+      ffi::TISGetInputSourceProperty(input_source, ffi::kTISPropertyInputSourceID) as CFStringRef;
+    let layout = if id.is_null() {
+      None
+    } else {
+      Some(KeyboardLayout {
+        id: CFString::wrap_under_get_rule(id).to_string(),
+      })
+    };
+    CFRelease(input_source as *mut c_void);
+    layout
+  }
+}
+
+/// Returns the auto-repeat of the keyboard settings.
+pub fn key_repeat_info() -> KeyRepeatInfo {
+  unsafe {
+    let delay: f64 = msg_send![class!(NSEvent), keyRepeatDelay];
+    let interval: f64 = msg_send![class!(NSEvent), keyRepeatInterval];
+    KeyRepeatInfo {
+      delay: Duration::from_secs_f64(delay.max(0.0)),
+      rate: if interval > 0.0 { 1.0 / interval } else { 0.0 },
+    }
+  }
+}
+
 pub fn get_modifierless_char(scancode: u16) -> Key<'static> {
   let mut string = [0; 16];
   let input_source;
@@ -114,6 +154,30 @@ pub fn get_modifierless_char(scancode: u16) -> Key<'static> {
   Key::Character(insert_or_get_key_str(chars))
 }
 
+/// Returns the key of a scancode in the current layout, without modifiers.
+pub fn key_from_scancode(scancode: u16) -> Option<Key<'static>> {
+  let code = KeyCode::from_scancode(scancode as u32);
+  if let KeyCode::Unidentified(_) = code {
+    return None;
+  }
+  let key = match code_to_key(code, scancode) {
+    Key::Unidentified(_) => get_modifierless_char(scancode),
+    key => key,
+  };
+  match key {
+    Key::Unidentified(_) => None,
+    key => Some(key),
+  }
+}
+
+/// Returns the scancode of the key typing `key` in the current layout, without modifiers.
+pub fn scancode_from_key(key: &Key<'_>) -> Option<u32> {
+  // The virtual key codes are below 128, with the numpad after the keys typing the same.
+  (0..0x80)
+    .find(|&scancode| key_from_scancode(scancode).as_ref() == Some(key))
+    .map(u32::from)
+}
+
 fn get_logical_key_char(ns_event: id, modifierless_chars: &str) -> Key<'static> {
   let characters: id = unsafe { msg_send![ns_event, charactersIgnoringModifiers] };
   let string = unsafe { ns_string_to_rust(characters) };
@@ -191,6 +255,7 @@ pub fn create_key_event(
     repeat: is_repeat,
     state,
     text,
+    compose_state: None,
     platform_specific: KeyEventExtra {
       text_with_all_modifiers,
       key_without_modifiers,
@@ -198,6 +263,62 @@ pub fn create_key_event(
   }
 }
 
+// The subtype of the system-defined events of the media keys, and their keys, from
+// `IOKit/hidsystem/ev_keymap.h`.
+const NX_SUBTYPE_AUX_CONTROL_BUTTONS: i16 = 8;
+const NX_KEYTYPE_SOUND_UP: NSInteger = 0;
+const NX_KEYTYPE_SOUND_DOWN: NSInteger = 1;
+const NX_KEYTYPE_MUTE: NSInteger = 7;
+const NX_KEYTYPE_PLAY: NSInteger = 16;
+const NX_KEYTYPE_NEXT: NSInteger = 17;
+const NX_KEYTYPE_PREVIOUS: NSInteger = 18;
+const NX_KEYTYPE_FAST: NSInteger = 19;
+const NX_KEYTYPE_REWIND: NSInteger = 20;
+const NX_KEYDOWN: NSInteger = 0xA;
+
+/// Returns the key event of a media key, which is sent to the application as a system-defined
+/// event instead of a key event.
+pub fn create_media_key_event(ns_event: id) -> Option<KeyEvent> {
+  let (subtype, data): (i16, NSInteger) =
+    unsafe { (msg_send![ns_event, subtype], msg_send![ns_event, data1]) };
+  if subtype != NX_SUBTYPE_AUX_CONTROL_BUTTONS {
+    return None;
+  }
+  // The key is in the high half of the data, and its flags in the low half.
+  let key_type = (data & 0xFFFF_0000) >> 16;
+  let state = if (data & 0xFF00) >> 8 == NX_KEYDOWN {
+    ElementState::Pressed
+  } else {
+    ElementState::Released
+  };
+  let (physical_key, logical_key) = match key_type {
+    NX_KEYTYPE_SOUND_UP => (KeyCode::AudioVolumeUp, Key::AudioVolumeUp),
+    NX_KEYTYPE_SOUND_DOWN => (KeyCode::AudioVolumeDown, Key::AudioVolumeDown),
+    NX_KEYTYPE_MUTE => (KeyCode::AudioVolumeMute, Key::AudioVolumeMute),
+    NX_KEYTYPE_PLAY => (KeyCode::MediaPlayPause, Key::MediaPlayPause),
+    // The keys of the Apple keyboards send `NX_KEYTYPE_FAST` and `NX_KEYTYPE_REWIND`, which all
+    // the applications treat as the next and previous tracks.
+    NX_KEYTYPE_NEXT | NX_KEYTYPE_FAST => (KeyCode::MediaTrackNext, Key::MediaTrackNext),
+    NX_KEYTYPE_PREVIOUS | NX_KEYTYPE_REWIND => {
+      (KeyCode::MediaTrackPrevious, Key::MediaTrackPrevious)
+    }
+    _ => return None,
+  };
+  Some(KeyEvent {
+    location: KeyLocation::Standard,
+    logical_key: logical_key.clone(),
+    physical_key,
+    repeat: data & 0x1 != 0,
+    state,
+    text: None,
+    compose_state: None,
+    platform_specific: KeyEventExtra {
+      text_with_all_modifiers: None,
+      key_without_modifiers: logical_key,
+    },
+  })
+}
+
 pub fn code_to_key(code: KeyCode, scancode: u16) -> Key<'static> {
   match code {
     KeyCode::Enter => Key::Enter,
@@ -308,7 +429,16 @@ pub fn extra_function_key_to_code(scancode: u16, string: &str) -> KeyCode {
 }
 
 pub fn event_mods(event: id) -> ModifiersState {
-  let flags = unsafe { NSEvent::modifierFlags(event) };
+  modifiers_from_flags(unsafe { NSEvent::modifierFlags(event) })
+}
+
+/// Returns the modifier flags currently held, which aren't tied to an event.
+pub fn current_modifier_flags() -> NSEventModifierFlags {
+  let bits: NSUInteger = unsafe { msg_send![class!(NSEvent), modifierFlags] };
+  NSEventModifierFlags::from_bits_truncate(bits)
+}
+
+pub fn modifiers_from_flags(flags: NSEventModifierFlags) -> ModifiersState {
   let mut m = ModifiersState::empty();
   m.set(
     ModifiersState::SHIFT,
@@ -4,37 +4,59 @@
 use std::{
   any::Any,
   cell::{Cell, RefCell},
-  collections::VecDeque,
+  collections::{HashMap, VecDeque},
   marker::PhantomData,
   mem,
   os::raw::c_void,
   panic::{catch_unwind, resume_unwind, RefUnwindSafe, UnwindSafe},
   process, ptr,
   rc::{Rc, Weak},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::Duration,
 };
 
 use cocoa::{
-  appkit::{NSApp, NSEventType::NSApplicationDefined},
-  base::{id, nil, YES},
-  foundation::{NSAutoreleasePool, NSPoint},
+  appkit::{NSApp, NSEventModifierFlags, NSEventType::NSApplicationDefined},
+  base::{id, nil, NO, YES},
+  foundation::{NSAutoreleasePool, NSPoint, NSUInteger},
 };
-use crossbeam_channel::{self as channel, Receiver, Sender};
 use scopeguard::defer;
 
 use crate::{
-  event::Event,
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootWindowTarget},
+  error::OsError as RootOsError,
+  event::{DeviceInfo, Event},
+  event_loop::{
+    ControlFlow, DeviceEventFilter, EventLoopClosed, EventLoopWindowTarget as RootWindowTarget,
+    Priority, SendEventError, TimerId,
+  },
+  keyboard::{Key, KeyRepeatInfo, LockKey, ModifiersState},
   monitor::MonitorHandle as RootMonitorHandle,
+  platform::{
+    pump_events::PumpStatus,
+    unix_signals::{ExitSignal, SignalAction},
+  },
   platform_impl::platform::{
     app::APP_CLASS,
     app_delegate::APP_DELEGATE_CLASS,
     app_state::AppState,
+    event,
     monitor::{self, MonitorHandle},
     observer::*,
-    util::IdRef,
+    progress_bar, raw_input,
+    signals::SignalSource,
+    util::{self, IdRef},
+    DeviceId, OsError, VirtualDesktop,
   },
+  user_event_queue::UserEventQueue,
+  window::{ProgressState, UserAttentionType},
 };
 
+// What `CFRunLoopRun` passes to `CFRunLoopRunInMode` to wait without a timeout.
+const DISTANT_FUTURE: f64 = 1.0e10;
+
 #[derive(Default)]
 pub struct PanicInfo {
   inner: Cell<Option<Box<dyn Any + Send + 'static>>>,
@@ -64,14 +86,16 @@ impl PanicInfo {
 }
 
 pub struct EventLoopWindowTarget<T: 'static> {
-  pub sender: Sender<T>, // this is only here to be cloned elsewhere
-  pub receiver: Receiver<T>,
+  pub user_events: Arc<UserEventQueue<T>>,
+  timers: RefCell<HashMap<TimerId, Timer>>,
 }
 
 impl<T> Default for EventLoopWindowTarget<T> {
   fn default() -> Self {
-    let (sender, receiver) = channel::unbounded();
-    EventLoopWindowTarget { sender, receiver }
+    EventLoopWindowTarget {
+      user_events: Arc::new(UserEventQueue::new()),
+      timers: Default::default(),
+    }
   }
 }
 
@@ -86,10 +110,95 @@ impl<T: 'static> EventLoopWindowTarget<T> {
     let monitor = monitor::primary_monitor();
     Some(RootMonitorHandle { inner: monitor })
   }
+
+  #[inline]
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    Vec::new()
+  }
+
+  #[inline]
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  #[inline]
+  pub fn key_from_scancode(&self, scancode: u32) -> Option<Key<'static>> {
+    if scancode > u16::MAX as u32 {
+      return None;
+    }
+    event::key_from_scancode(scancode as u16)
+  }
+
+  #[inline]
+  pub fn scancode_from_key(&self, key: &Key<'_>) -> Option<u32> {
+    event::scancode_from_key(key)
+  }
+
+  #[inline]
+  pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+    Some(event::key_repeat_info())
+  }
+
+  #[inline]
+  pub fn modifiers_state(&self) -> ModifiersState {
+    event::modifiers_from_flags(event::current_modifier_flags())
+  }
+
+  #[inline]
+  pub fn lock_key_state(&self, key: LockKey) -> Option<bool> {
+    match key {
+      LockKey::CapsLock => {
+        Some(event::current_modifier_flags().contains(NSEventModifierFlags::NSAlphaShiftKeyMask))
+      }
+      // Macs have no scroll lock, and their num lock is handled by the keyboard.
+      _ => None,
+    }
+  }
+
+  #[inline]
+  pub fn set_progress_bar(&self, state: ProgressState, progress: Option<u64>) {
+    progress_bar::set_progress_indicator(state, progress);
+  }
+
+  #[inline]
+  pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
+    unsafe { util::request_user_attention(request_type) };
+  }
+
+  #[inline]
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    // The previous timer with this id is stopped when it's dropped.
+    self
+      .timers
+      .borrow_mut()
+      .insert(id, Timer::new(interval, id));
+  }
+
+  #[inline]
+  pub fn cancel_timer(&self, id: TimerId) {
+    self.timers.borrow_mut().remove(&id);
+  }
+
+  #[inline]
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  #[inline]
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    AppState::set_device_events_filtered(filter == DeviceEventFilter::Always);
+    raw_input::set_unfocused_filtered(filter == DeviceEventFilter::Unfocused);
+  }
+
+  pub fn device_info(&self, device_id: DeviceId) -> Option<DeviceInfo> {
+    raw_input::device_info(device_id)
+  }
 }
 
+// There is a single `NSApplication`, which can only drive one event loop at a time.
+static EVENT_LOOP_EXISTS: AtomicBool = AtomicBool::new(false);
+
 pub struct EventLoop<T: 'static> {
   pub(crate) delegate: IdRef,
+  _observers: ControlFlowObservers,
 
   window_target: Rc<RootWindowTarget<T>>,
   panic_info: Rc<PanicInfo>,
@@ -101,14 +210,26 @@ pub struct EventLoop<T: 'static> {
   /// into a strong reference in order to call the callback but then the
   /// strong reference should be dropped as soon as possible.
   _callback: Option<Rc<RefCell<dyn FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow)>>>,
+
+  /// The sources of the signals turned into events.
+  signal_sources: HashMap<ExitSignal, SignalSource>,
 }
 
 impl<T> EventLoop<T> {
   pub fn new() -> Self {
+    Self::try_new().unwrap()
+  }
+
+  pub fn try_new() -> Result<Self, RootOsError> {
     let delegate = unsafe {
       if !msg_send![class!(NSThread), isMainThread] {
         panic!("On macOS, `EventLoop` must be created on the main thread!");
       }
+      if EVENT_LOOP_EXISTS.swap(true, Ordering::AcqRel) {
+        return Err(os_error!(OsError::CreationError(
+          "Only one `EventLoop` can exist at a time"
+        )));
+      }
 
       // This must be done before `NSApp()` (equivalent to sending
       // `sharedApplication`) is called anywhere else, or we'll end up
@@ -122,17 +243,20 @@ impl<T> EventLoop<T> {
       let _: () = msg_send![pool, drain];
       delegate
     };
+    raw_input::start_hid_input();
     let panic_info: Rc<PanicInfo> = Default::default();
-    setup_control_flow_observers(Rc::downgrade(&panic_info));
-    EventLoop {
+    let observers = setup_control_flow_observers(Rc::downgrade(&panic_info));
+    Ok(EventLoop {
       delegate,
+      _observers: observers,
       window_target: Rc::new(RootWindowTarget {
         p: Default::default(),
         _marker: PhantomData,
       }),
       panic_info,
       _callback: None,
-    }
+      signal_sources: HashMap::new(),
+    })
   }
 
   pub fn window_target(&self) -> &RootWindowTarget<T> {
@@ -143,11 +267,12 @@ impl<T> EventLoop<T> {
   where
     F: 'static + FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow),
   {
-    self.run_return(callback);
-    process::exit(0);
+    let exit_code = self.run_return(callback);
+    monitor::restore_display_mode();
+    process::exit(exit_code);
   }
 
-  pub fn run_return<F>(&mut self, callback: F)
+  pub fn run_return<F>(&mut self, callback: F) -> i32
   where
     F: FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow),
   {
@@ -164,7 +289,7 @@ impl<T> EventLoop<T> {
 
     self._callback = Some(Rc::clone(&callback));
 
-    unsafe {
+    let exit_code = unsafe {
       let pool = NSAutoreleasePool::new(nil);
       defer!(pool.drain());
       let app = NSApp();
@@ -176,19 +301,122 @@ impl<T> EventLoop<T> {
       mem::drop(callback);
 
       AppState::set_callback(weak_cb, Rc::clone(&self.window_target));
+      AppState::restart();
       let () = msg_send![app, run];
 
       if let Some(panic) = self.panic_info.take() {
         drop(self._callback.take());
+        monitor::restore_display_mode();
         resume_unwind(panic);
       }
-      AppState::exit();
-    }
+      AppState::exit()
+    };
     drop(self._callback.take());
+    exit_code
+  }
+
+  pub fn pump_events<F>(&mut self, timeout: Option<Duration>, callback: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow),
+  {
+    // See `run_return` for the safety of this transmute.
+    let callback = unsafe {
+      mem::transmute::<
+        Rc<RefCell<dyn FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow)>>,
+        Rc<RefCell<dyn FnMut(Event<'_, T>, &RootWindowTarget<T>, &mut ControlFlow)>>,
+      >(Rc::new(RefCell::new(callback)))
+    };
+
+    self._callback = Some(Rc::clone(&callback));
+
+    let status = unsafe {
+      let pool = NSAutoreleasePool::new(nil);
+      defer!(pool.drain());
+      let app = NSApp();
+      assert_ne!(app, nil);
+
+      let weak_cb: Weak<_> = Rc::downgrade(&callback);
+      mem::drop(callback);
+
+      AppState::set_callback(weak_cb, Rc::clone(&self.window_target));
+      AppState::restart();
+
+      // The application is only launched by its first run, which doesn't wait.
+      if AppState::is_launched() {
+        let distant_past: id = msg_send![class!(NSDate), distantPast];
+        let pending_event: id = msg_send![app,
+          nextEventMatchingMask: NSUInteger::max_value()
+          untilDate: distant_past
+          inMode: kCFRunLoopDefaultMode as id
+          dequeue: NO];
+        if pending_event == nil {
+          // Returns once a source, like the one of the window server, is handled.
+          CFRunLoopRunInMode(
+            kCFRunLoopDefaultMode,
+            timeout.map_or(DISTANT_FUTURE, |timeout| timeout.as_secs_f64()),
+            1,
+          );
+        }
+      }
+
+      // Handles the pending events, and stops before the run loop waits again.
+      AppState::set_stop_when_cleared(true);
+      let () = msg_send![app, run];
+      AppState::set_stop_when_cleared(false);
+
+      if let Some(panic) = self.panic_info.take() {
+        drop(self._callback.take());
+        monitor::restore_display_mode();
+        resume_unwind(panic);
+      }
+
+      if AppState::exit_code().is_some() {
+        PumpStatus::Exit(AppState::exit())
+      } else {
+        AppState::clear_callback();
+        PumpStatus::Continue
+      }
+    };
+    drop(self._callback.take());
+    status
+  }
+
+  pub fn set_signal_action(&mut self, signal: ExitSignal, action: Option<SignalAction<T>>)
+  where
+    T: Clone,
+  {
+    // The previous source restores the default action when it's dropped.
+    self.signal_sources.remove(&signal);
+    let handler: Box<dyn Fn()> = match action {
+      Some(SignalAction::ExitRequested) => Box::new(AppState::queue_exit_request),
+      Some(SignalAction::UserEvent(event)) => {
+        let proxy = self.create_proxy();
+        Box::new(move || {
+          if let Err(e) = proxy.send_event(event.clone()) {
+            warn!("Failed to send signal event to event loop: {}", e);
+          }
+        })
+      }
+      None => return,
+    };
+    match SignalSource::new(signal.signum(), handler) {
+      Some(source) => {
+        self.signal_sources.insert(signal, source);
+      }
+      None => warn!("Failed to create the dispatch source of {:?}", signal),
+    }
   }
 
   pub fn create_proxy(&self) -> Proxy<T> {
-    Proxy::new(self.window_target.p.sender.clone())
+    Proxy::new(self.window_target.p.user_events.clone())
+  }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self
+      .window_target
+      .p
+      .user_events
+      .set_capacity(priority, capacity);
   }
 }
 
@@ -243,7 +471,7 @@ pub fn stop_app_on_panic<F: FnOnce() -> R + UnwindSafe, R>(
 }
 
 pub struct Proxy<T> {
-  sender: Sender<T>,
+  user_events: Arc<UserEventQueue<T>>,
   source: CFRunLoopSourceRef,
 }
 
@@ -260,12 +488,12 @@ impl<T> Drop for Proxy<T> {
 
 impl<T> Clone for Proxy<T> {
   fn clone(&self) -> Self {
-    Proxy::new(self.sender.clone())
+    Proxy::new(self.user_events.clone())
   }
 }
 
 impl<T> Proxy<T> {
-  fn new(sender: Sender<T>) -> Self {
+  fn new(user_events: Arc<UserEventQueue<T>>) -> Self {
     unsafe {
       // just wake up the eventloop
       extern "C" fn event_loop_proxy_handler(_: *mut c_void) {}
@@ -279,15 +507,34 @@ impl<T> Proxy<T> {
       CFRunLoopAddSource(rl, source, kCFRunLoopCommonModes);
       CFRunLoopWakeUp(rl);
 
-      Proxy { sender, source }
+      Proxy {
+        user_events,
+        source,
+      }
     }
   }
 
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
     self
-      .sender
-      .send(event)
-      .map_err(|channel::SendError(x)| EventLoopClosed(x))?;
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.user_events.push(event, priority, bounded)?;
     unsafe {
       // let the main thread know there's a new event
       CFRunLoopSourceSignal(self.source);
@@ -296,4 +543,27 @@ impl<T> Proxy<T> {
     }
     Ok(())
   }
+
+  /// Wakes the event loop up without a user event, which emits `NewEvents` and
+  /// `MainEventsCleared`.
+  pub fn wake_up(&self) {
+    unsafe {
+      CFRunLoopWakeUp(CFRunLoopGetMain());
+    }
+  }
+}
+
+impl<T> Drop for EventLoop<T> {
+  fn drop(&mut self) {
+    unsafe {
+      // The application doesn't retain its delegate, which is released with the event loop.
+      let app = NSApp();
+      let delegate: id = msg_send![app, delegate];
+      if delegate == *self.delegate {
+        let () = msg_send![app, setDelegate: nil];
+      }
+    }
+    self.window_target.p.user_events.close();
+    EVENT_LOOP_EXISTS.store(false, Ordering::Release);
+  }
 }
@@ -113,6 +113,7 @@ pub const kCGNumberOfWindowLevelKeys: NSInteger = 20;
 
 #[derive(Debug, Clone, Copy)]
 pub enum NSWindowLevel {
+  BelowNormalWindowLevel = (kCGBaseWindowLevelKey - 1) as _,
   NSNormalWindowLevel = kCGBaseWindowLevelKey as _,
   NSFloatingWindowLevel = kCGFloatingWindowLevelKey as _,
   NSTornOffMenuWindowLevel = kCGTornOffMenuWindowLevelKey as _,
@@ -185,6 +186,7 @@ extern "C" {
   pub fn CGRestorePermanentDisplayConfiguration();
   pub fn CGDisplayCapture(display: CGDirectDisplayID) -> CGError;
   pub fn CGDisplayRelease(display: CGDirectDisplayID) -> CGError;
+  pub fn CGReleaseAllDisplays() -> CGError;
   pub fn CGConfigureDisplayFadeEffect(
     config: CGDisplayConfigRef,
     fadeOutSeconds: CGDisplayFadeInterval,
@@ -243,9 +245,19 @@ pub const kUCKeyActionDisplay: u16 = 3;
 #[allow(non_upper_case_globals)]
 pub const kUCKeyTranslateNoDeadKeysMask: OptionBits = 1;
 
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+  pub static NSWorkspaceWillSleepNotification: id;
+  pub static NSWorkspaceDidWakeNotification: id;
+}
+
+pub const NSApplicationOcclusionStateVisible: NSUInteger = 1 << 1;
+
 #[link(name = "Carbon", kind = "framework")]
 extern "C" {
   pub static kTISPropertyUnicodeKeyLayoutData: CFStringRef;
+  pub static kTISPropertyInputSourceID: CFStringRef;
+  pub static kTISNotifySelectedKeyboardInputSourceChanged: CFStringRef;
 
   #[allow(non_snake_case)]
   pub fn TISGetInputSourceProperty(
@@ -0,0 +1,173 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The keyboard grab of `Window::set_keyboard_grab`. The shortcuts of the system like Cmd+Tab and
+// the media keys are handled by the window server and the Dock before they reach the application,
+// so an event tap of the session takes the keyboard events while a grabbing window is the key
+// window, and sends them to the application itself. The event tap needs the accessibility
+// permission of the application.
+use std::{cell::RefCell, ffi::c_void, ptr};
+
+use cocoa::{
+  appkit::NSApp,
+  base::{id, nil},
+};
+use core_foundation::{
+  base::TCFType,
+  mach_port::{CFMachPort, CFMachPortRef},
+  runloop::{kCFRunLoopCommonModes, CFRunLoop},
+};
+use dispatch::Queue;
+
+type CGEventRef = *mut c_void;
+type CGEventTapProxy = *mut c_void;
+type CGEventTapCallBack = extern "C" fn(
+  proxy: CGEventTapProxy,
+  event_type: u32,
+  event: CGEventRef,
+  user_info: *mut c_void,
+) -> CGEventRef;
+
+const K_CG_SESSION_EVENT_TAP: u32 = 1;
+const K_CG_HEAD_INSERT_EVENT_TAP: u32 = 0;
+const K_CG_EVENT_TAP_OPTION_DEFAULT: u32 = 0;
+const K_CG_EVENT_KEY_DOWN: u32 = 10;
+const K_CG_EVENT_KEY_UP: u32 = 11;
+const K_CG_EVENT_FLAGS_CHANGED: u32 = 12;
+// The events of the media keys.
+const NX_SYSDEFINED: u32 = 14;
+const NX_SUBTYPE_AUX_CONTROL_BUTTONS: i16 = 8;
+const K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+const K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+  fn CGEventTapCreate(
+    tap: u32,
+    place: u32,
+    options: u32,
+    events_of_interest: u64,
+    callback: CGEventTapCallBack,
+    user_info: *mut c_void,
+  ) -> CFMachPortRef;
+  fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+}
+
+thread_local! {
+  // The event tap, created by the first grab, and the windows grabbing the keyboard.
+  static KEYBOARD_GRAB: RefCell<(Option<CFMachPort>, Vec<usize>)> = RefCell::new((None, Vec::new()));
+}
+
+/// Grabs the keyboard for `ns_window` while it's the key window, or releases it.
+pub fn set_keyboard_grab(ns_window: id, grab: bool) -> Result<(), &'static str> {
+  let window = ns_window as usize;
+  if unsafe { msg_send![class!(NSThread), isMainThread] } {
+    set_keyboard_grab_main(window, grab)
+  } else {
+    Queue::main().exec_sync(move || set_keyboard_grab_main(window, grab))
+  }
+}
+
+/// Releases the grab of a window being dropped, from any thread.
+pub fn release_keyboard_grab(ns_window: id) {
+  let window = ns_window as usize;
+  Queue::main().exec_async(move || {
+    let _ = set_keyboard_grab_main(window, false);
+  });
+}
+
+fn set_keyboard_grab_main(window: usize, grab: bool) -> Result<(), &'static str> {
+  KEYBOARD_GRAB.with(|keyboard_grab| {
+    let (tap, windows) = &mut *keyboard_grab.borrow_mut();
+    if grab {
+      if tap.is_none() {
+        *tap = Some(create_event_tap()?);
+      }
+      if !windows.contains(&window) {
+        windows.push(window);
+      }
+    } else {
+      windows.retain(|&w| w != window);
+    }
+    if let Some(tap) = tap {
+      unsafe { CGEventTapEnable(tap.as_concrete_TypeRef(), !windows.is_empty()) };
+    }
+    Ok(())
+  })
+}
+
+fn create_event_tap() -> Result<CFMachPort, &'static str> {
+  let events = [
+    K_CG_EVENT_KEY_DOWN,
+    K_CG_EVENT_KEY_UP,
+    K_CG_EVENT_FLAGS_CHANGED,
+    NX_SYSDEFINED,
+  ]
+  .iter()
+  .fold(0, |mask, event| mask | 1 << event);
+  unsafe {
+    let tap = CGEventTapCreate(
+      K_CG_SESSION_EVENT_TAP,
+      K_CG_HEAD_INSERT_EVENT_TAP,
+      K_CG_EVENT_TAP_OPTION_DEFAULT,
+      events,
+      event_tap_callback,
+      ptr::null_mut(),
+    );
+    if tap.is_null() {
+      return Err(
+        "Failed to create the event tap, the application needs the accessibility permission",
+      );
+    }
+    let tap = CFMachPort::wrap_under_create_rule(tap);
+    let source = tap
+      .create_runloop_source(0)
+      .map_err(|_| "Failed to create the run loop source of the event tap")?;
+    CFRunLoop::get_main().add_source(&source, kCFRunLoopCommonModes);
+    Ok(tap)
+  }
+}
+
+extern "C" fn event_tap_callback(
+  _proxy: CGEventTapProxy,
+  event_type: u32,
+  event: CGEventRef,
+  _user_info: *mut c_void,
+) -> CGEventRef {
+  if event_type == K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT
+    || event_type == K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT
+  {
+    // The tap is disabled by the system when the application doesn't handle the events fast
+    // enough, like while it's busy.
+    KEYBOARD_GRAB.with(|keyboard_grab| {
+      if let (Some(tap), windows) = &*keyboard_grab.borrow() {
+        unsafe { CGEventTapEnable(tap.as_concrete_TypeRef(), !windows.is_empty()) };
+      }
+    });
+    return event;
+  }
+
+  unsafe {
+    let app = NSApp();
+    let active: bool = msg_send![app, isActive];
+    let key_window: id = msg_send![app, keyWindow];
+    let grabbed =
+      KEYBOARD_GRAB.with(|keyboard_grab| keyboard_grab.borrow().1.contains(&(key_window as usize)));
+    if !active || !grabbed {
+      return event;
+    }
+    let ns_event: id = msg_send![class!(NSEvent), eventWithCGEvent: event];
+    if ns_event == nil {
+      return event;
+    }
+    if event_type == NX_SYSDEFINED {
+      let subtype: i16 = msg_send![ns_event, subtype];
+      if subtype != NX_SUBTYPE_AUX_CONTROL_BUTTONS {
+        return event;
+      }
+    }
+    // The application receives the event as if the window server had sent it.
+    let _: () = msg_send![app, sendEvent: ns_event];
+    ptr::null_mut()
+  }
+}
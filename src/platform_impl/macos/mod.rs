@@ -7,18 +7,26 @@ mod app;
 mod app_delegate;
 mod app_state;
 mod clipboard;
+mod effects;
 mod event;
 mod event_loop;
 mod ffi;
 mod global_shortcut;
+mod keyboard_grab;
 mod keycode;
 mod menu;
 mod monitor;
 mod observer;
+mod progress_bar;
+mod raw_input;
+mod signals;
+#[cfg(feature = "test")]
+mod synthetic_input;
 #[cfg(feature = "tray")]
 mod system_tray;
 mod util;
 mod view;
+mod vsync;
 mod window;
 mod window_delegate;
 
@@ -30,13 +38,16 @@ pub use self::system_tray::{SystemTray, SystemTrayBuilder};
 pub use self::{
   app_delegate::{get_aux_state_mut, AuxDelegateState},
   clipboard::Clipboard,
-  event::KeyEventExtra,
+  effects::VisualEffect,
+  event::{current_keyboard_layout, KeyEventExtra},
   event_loop::{EventLoop, EventLoopWindowTarget, Proxy as EventLoopProxy},
   global_shortcut::{GlobalShortcut, ShortcutManager},
   keycode::{keycode_from_scancode, keycode_to_scancode},
   menu::{Menu, MenuItemAttributes},
   monitor::{MonitorHandle, VideoMode},
-  window::{Id as WindowId, Parent, PlatformSpecificWindowBuilderAttributes, UnownedWindow},
+  window::{
+    CloseHandle, Id as WindowId, Parent, PlatformSpecificWindowBuilderAttributes, UnownedWindow,
+  },
 };
 use crate::{
   error::OsError as RootOsError, event::DeviceId as RootDeviceId, window::WindowAttributes,
@@ -45,16 +56,21 @@ use crate::{
 pub(crate) use crate::icon::NoIcon as PlatformIcon;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct DeviceId;
+pub struct DeviceId(usize);
 
 impl DeviceId {
   pub unsafe fn dummy() -> Self {
-    DeviceId
+    DeviceId(0)
   }
 }
 
-// Constant device ID; to be removed when if backend is updated to report real device IDs.
-pub(crate) const DEVICE_ID: RootDeviceId = RootDeviceId(DeviceId);
+// Spaces have no public API, so there is never a handle to one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VirtualDesktop;
+
+// Constant device ID of the mice and keyboards; the HID devices are identified by the address of
+// their `IOHIDDevice`.
+pub(crate) const DEVICE_ID: RootDeviceId = RootDeviceId(DeviceId(0));
 
 pub struct Window {
   window: Arc<UnownedWindow>,
@@ -1,7 +1,11 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::VecDeque, fmt};
+use std::{
+  collections::VecDeque,
+  fmt,
+  sync::atomic::{AtomicBool, Ordering},
+};
 
 use super::{ffi, util};
 use crate::{
@@ -29,6 +33,7 @@ pub struct VideoMode {
   pub(crate) size: (u32, u32),
   pub(crate) bit_depth: u16,
   pub(crate) refresh_rate: u16,
+  pub(crate) refresh_rate_millihertz: u32,
   pub(crate) monitor: MonitorHandle,
   pub(crate) native_mode: NativeDisplayMode,
 }
@@ -59,6 +64,7 @@ impl std::fmt::Debug for VideoMode {
       .field("size", &self.size)
       .field("bit_depth", &self.bit_depth)
       .field("refresh_rate", &self.refresh_rate)
+      .field("refresh_rate_millihertz", &self.refresh_rate_millihertz)
       .field("monitor", &self.monitor)
       .finish()
   }
@@ -98,6 +104,10 @@ impl VideoMode {
     self.refresh_rate
   }
 
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate_millihertz
+  }
+
   pub fn monitor(&self) -> RootMonitorHandle {
     RootMonitorHandle {
       inner: self.monitor.clone(),
@@ -105,6 +115,26 @@ impl VideoMode {
   }
 }
 
+// Set while a display is captured with a video mode set by exclusive fullscreen, so that
+// the original mode can be restored if the event loop exits or panics before the window
+// leaves fullscreen.
+static DISPLAY_MODE_CHANGED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_display_mode_changed() {
+  DISPLAY_MODE_CHANGED.store(true, Ordering::SeqCst);
+}
+
+/// Restores the permanent display configuration and releases every captured display,
+/// if a video mode was changed. Must be called on the main thread.
+pub(crate) fn restore_display_mode() {
+  if DISPLAY_MODE_CHANGED.swap(false, Ordering::SeqCst) {
+    unsafe {
+      ffi::CGRestorePermanentDisplayConfiguration();
+      ffi::CGReleaseAllDisplays();
+    }
+  }
+}
+
 #[derive(Clone)]
 pub struct MonitorHandle(CGDirectDisplayID);
 
@@ -239,7 +269,7 @@ impl MonitorHandle {
       // This value is indefinite if an invalid display link was specified
       assert!(time.flags & kCVTimeIsIndefinite == 0);
 
-      time.timeScale as i64 / time.timeValue
+      time.timeScale as i64 * 1000 / time.timeValue
     };
 
     let monitor = self.clone();
@@ -261,11 +291,11 @@ impl MonitorHandle {
       };
 
       modes.into_iter().map(move |mode| {
-        let cg_refresh_rate = ffi::CGDisplayModeGetRefreshRate(mode).round() as i64;
+        let cg_refresh_rate = (ffi::CGDisplayModeGetRefreshRate(mode) * 1000.0).round() as i64;
 
         // CGDisplayModeGetRefreshRate returns 0.0 for any display that
         // isn't a CRT
-        let refresh_rate = if cg_refresh_rate > 0 {
+        let refresh_rate_millihertz = if cg_refresh_rate > 0 {
           cg_refresh_rate
         } else {
           cv_refresh_rate
@@ -288,7 +318,8 @@ impl MonitorHandle {
             ffi::CGDisplayModeGetPixelWidth(mode) as u32,
             ffi::CGDisplayModeGetPixelHeight(mode) as u32,
           ),
-          refresh_rate: refresh_rate as u16,
+          refresh_rate: ((refresh_rate_millihertz + 500) / 1000) as u16,
+          refresh_rate_millihertz: refresh_rate_millihertz as u32,
           bit_depth,
           monitor: monitor.clone(),
           native_mode: NativeDisplayMode(mode),
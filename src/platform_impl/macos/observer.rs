@@ -7,21 +7,32 @@ use std::{
   panic::{AssertUnwindSafe, UnwindSafe},
   ptr,
   rc::Weak,
-  time::Instant,
+  time::{Duration, Instant},
 };
 
-use crate::platform_impl::platform::{
-  app_state::AppState,
-  event_loop::{stop_app_on_panic, PanicInfo},
-  ffi,
+use crate::{
+  event::Event,
+  event_loop::TimerId,
+  platform_impl::platform::{
+    app_state::AppState,
+    event::EventWrapper,
+    event_loop::{stop_app_on_panic, PanicInfo},
+    ffi,
+  },
 };
 
 #[link(name = "CoreFoundation", kind = "framework")]
 extern "C" {
   pub static kCFRunLoopCommonModes: CFRunLoopMode;
+  pub static kCFRunLoopDefaultMode: CFRunLoopMode;
 
   pub fn CFRunLoopGetMain() -> CFRunLoopRef;
   pub fn CFRunLoopWakeUp(rl: CFRunLoopRef);
+  pub fn CFRunLoopRunInMode(
+    mode: CFRunLoopMode,
+    seconds: CFTimeInterval,
+    returnAfterSourceHandled: ffi::Boolean,
+  ) -> i32;
 
   pub fn CFRunLoopObserverCreate(
     allocator: CFAllocatorRef,
@@ -36,6 +47,7 @@ extern "C" {
     observer: CFRunLoopObserverRef,
     mode: CFRunLoopMode,
   );
+  pub fn CFRunLoopObserverInvalidate(observer: CFRunLoopObserverRef);
 
   pub fn CFRunLoopTimerCreate(
     allocator: CFAllocatorRef,
@@ -99,7 +111,17 @@ pub type CFRunLoopObserverCallBack =
   extern "C" fn(observer: CFRunLoopObserverRef, activity: CFRunLoopActivity, info: *mut c_void);
 pub type CFRunLoopTimerCallBack = extern "C" fn(timer: CFRunLoopTimerRef, info: *mut c_void);
 
-pub enum CFRunLoopTimerContext {}
+/// This mirrors the struct with the same name from Core Foundation.
+/// https://developer.apple.com/documentation/corefoundation/cfrunlooptimercontext?language=objc
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct CFRunLoopTimerContext {
+  pub version: CFIndex,
+  pub info: *mut c_void,
+  pub retain: Option<extern "C" fn(info: *const c_void) -> *const c_void>,
+  pub release: Option<extern "C" fn(info: *const c_void)>,
+  pub copyDescription: Option<extern "C" fn(info: *const c_void) -> CFStringRef>,
+}
 
 /// This mirrors the struct with the same name from Core Foundation.
 /// https://developer.apple.com/documentation/corefoundation/cfrunloopobservercontext?language=objc
@@ -203,7 +225,7 @@ impl RunLoop {
     priority: CFIndex,
     handler: CFRunLoopObserverCallBack,
     context: *mut CFRunLoopObserverContext,
-  ) {
+  ) -> CFRunLoopObserverRef {
     let observer = CFRunLoopObserverCreate(
       ptr::null_mut(),
       flags,
@@ -213,31 +235,57 @@ impl RunLoop {
       context,
     );
     CFRunLoopAddObserver(self.0, observer, kCFRunLoopCommonModes);
+    observer
+  }
+}
+
+/// The run loop observers of an event loop, removed when it's dropped so that another event loop
+/// can be created afterwards.
+pub struct ControlFlowObservers {
+  observers: [CFRunLoopObserverRef; 2],
+  panic_info: *const PanicInfo,
+}
+
+impl Drop for ControlFlowObservers {
+  fn drop(&mut self) {
+    unsafe {
+      for observer in &self.observers {
+        CFRunLoopObserverInvalidate(*observer);
+        CFRelease(*observer as _);
+      }
+      // Releases the weak reference given to the observers.
+      drop(Weak::from_raw(self.panic_info));
+    }
   }
 }
 
-pub fn setup_control_flow_observers(panic_info: Weak<PanicInfo>) {
+pub fn setup_control_flow_observers(panic_info: Weak<PanicInfo>) -> ControlFlowObservers {
   unsafe {
+    let panic_info = Weak::into_raw(panic_info);
     let mut context = CFRunLoopObserverContext {
-      info: Weak::into_raw(panic_info) as *mut _,
+      info: panic_info as *mut _,
       version: 0,
       retain: None,
       release: None,
       copyDescription: None,
     };
     let run_loop = RunLoop::get();
-    run_loop.add_observer(
+    let begin_observer = run_loop.add_observer(
       kCFRunLoopEntry | kCFRunLoopAfterWaiting,
       CFIndex::min_value(),
       control_flow_begin_handler,
       &mut context as *mut _,
     );
-    run_loop.add_observer(
+    let end_observer = run_loop.add_observer(
       kCFRunLoopExit | kCFRunLoopBeforeWaiting,
       CFIndex::max_value(),
       control_flow_end_handler,
       &mut context as *mut _,
     );
+    ControlFlowObservers {
+      observers: [begin_observer, end_observer],
+      panic_info,
+    }
   }
 }
 
@@ -299,3 +347,49 @@ impl EventLoopWaker {
     }
   }
 }
+
+/// A timer of the main run loop started by `EventLoopWindowTarget::set_timer`, which is stopped
+/// when it's dropped.
+pub struct Timer {
+  timer: CFRunLoopTimerRef,
+}
+
+impl Timer {
+  pub fn new(interval: Duration, id: TimerId) -> Timer {
+    extern "C" fn fire(_timer: CFRunLoopTimerRef, info: *mut c_void) {
+      let id = TimerId(info as usize as u32);
+      AppState::queue_event(EventWrapper::StaticEvent(Event::Timer(id)));
+    }
+    unsafe {
+      // The context is copied by the timer, which keeps the id as its info.
+      let mut context = CFRunLoopTimerContext {
+        version: 0,
+        info: id.0 as usize as *mut c_void,
+        retain: None,
+        release: None,
+        copyDescription: None,
+      };
+      let interval = interval.as_secs_f64();
+      let timer = CFRunLoopTimerCreate(
+        ptr::null_mut(),
+        CFAbsoluteTimeGetCurrent() + interval,
+        interval,
+        0,
+        0,
+        fire,
+        &mut context,
+      );
+      CFRunLoopAddTimer(CFRunLoopGetMain(), timer, kCFRunLoopCommonModes);
+      Timer { timer }
+    }
+  }
+}
+
+impl Drop for Timer {
+  fn drop(&mut self) {
+    unsafe {
+      CFRunLoopTimerInvalidate(self.timer);
+      CFRelease(self.timer as _);
+    }
+  }
+}
@@ -0,0 +1,88 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use cocoa::{
+  appkit::NSApp,
+  base::{id, nil},
+  foundation::{NSPoint, NSRect, NSSize, NSUInteger},
+};
+use dispatch::Queue;
+use objc::runtime::{NO, YES};
+
+use crate::window::ProgressState;
+
+// `NSProgressIndicatorStyleBar`
+const BAR_STYLE: NSUInteger = 0;
+const INDICATOR_HEIGHT: f64 = 16.0;
+
+// The dock tile is shared by the whole application and, like every other AppKit
+// object, must only be touched from the main thread.
+pub fn set_progress_indicator(state: ProgressState, progress: Option<u64>) {
+  Queue::main().exec_async(move || unsafe {
+    let dock_tile: id = msg_send![NSApp(), dockTile];
+    if dock_tile == nil {
+      return;
+    }
+
+    let indicator = progress_indicator(dock_tile);
+    match state {
+      ProgressState::None => {
+        let () = msg_send![indicator, stopAnimation: nil];
+        let () = msg_send![indicator, setHidden: YES];
+      }
+      ProgressState::Indeterminate => {
+        let () = msg_send![indicator, setIndeterminate: YES];
+        let () = msg_send![indicator, startAnimation: nil];
+        let () = msg_send![indicator, setHidden: NO];
+      }
+      _ => {
+        let () = msg_send![indicator, stopAnimation: nil];
+        let () = msg_send![indicator, setIndeterminate: NO];
+        if let Some(progress) = progress {
+          let () = msg_send![indicator, setDoubleValue: progress as f64];
+        }
+        let () = msg_send![indicator, setHidden: NO];
+      }
+    }
+
+    let () = msg_send![dock_tile, display];
+  });
+}
+
+// Returns the progress indicator of the dock tile, creating it on first use.
+unsafe fn progress_indicator(dock_tile: id) -> id {
+  let size: NSSize = msg_send![dock_tile, size];
+  let mut content_view: id = msg_send![dock_tile, contentView];
+
+  if content_view == nil {
+    // A custom content view replaces the application icon, so we draw it ourselves.
+    let frame = NSRect::new(NSPoint::new(0.0, 0.0), size);
+    content_view = msg_send![class!(NSImageView), alloc];
+    content_view = msg_send![content_view, initWithFrame: frame];
+    let icon: id = msg_send![NSApp(), applicationIconImage];
+    let () = msg_send![content_view, setImage: icon];
+    let () = msg_send![dock_tile, setContentView: content_view];
+    let () = msg_send![content_view, release];
+  }
+
+  let subviews: id = msg_send![content_view, subviews];
+  let count: NSUInteger = msg_send![subviews, count];
+  if count > 0 {
+    return msg_send![subviews, objectAtIndex: 0 as NSUInteger];
+  }
+
+  let frame = NSRect::new(
+    NSPoint::new(0.0, 0.0),
+    NSSize::new(size.width, INDICATOR_HEIGHT),
+  );
+  let indicator: id = msg_send![class!(NSProgressIndicator), alloc];
+  let indicator: id = msg_send![indicator, initWithFrame: frame];
+  let () = msg_send![indicator, setStyle: BAR_STYLE];
+  let () = msg_send![indicator, setMinValue: 0.0f64];
+  let () = msg_send![indicator, setMaxValue: 100.0f64];
+  let () = msg_send![indicator, setHidden: YES];
+  let () = msg_send![content_view, addSubview: indicator];
+  let () = msg_send![indicator, release];
+
+  indicator
+}
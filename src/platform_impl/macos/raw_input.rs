@@ -0,0 +1,297 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The device events from the HID manager: the raw motion of the mice, from the HID values of their
+// X and Y axes before the acceleration of the cursor, and the axes and the buttons of the joysticks
+// and the multi-axis controllers. The HID manager is scheduled on the main run loop, so its
+// callbacks queue the device events like the other events of the application.
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  ffi::c_void,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Once,
+  },
+};
+
+use cocoa::{appkit::NSApp, base::id};
+use core_foundation::{
+  array::{CFArray, CFArrayRef},
+  base::{CFAllocatorRef, CFIndex, CFType, CFTypeRef, TCFType},
+  dictionary::CFDictionary,
+  number::CFNumber,
+  string::{CFString, CFStringRef},
+};
+
+use super::{
+  app_state::AppState,
+  event::EventWrapper,
+  observer::{kCFRunLoopCommonModes, CFRunLoopGetMain, CFRunLoopMode, CFRunLoopRef},
+  DeviceId, DEVICE_ID,
+};
+use crate::event::{DeviceEvent, DeviceId as RootDeviceId, DeviceInfo, ElementState, Event};
+
+type IOHIDManagerRef = *mut c_void;
+type IOHIDDeviceRef = *mut c_void;
+type IOHIDValueRef = *mut c_void;
+type IOHIDElementRef = *mut c_void;
+type IOReturn = i32;
+
+type IOHIDValueCallback =
+  extern "C" fn(context: *mut c_void, result: IOReturn, sender: *mut c_void, value: IOHIDValueRef);
+type IOHIDDeviceCallback = extern "C" fn(
+  context: *mut c_void,
+  result: IOReturn,
+  sender: *mut c_void,
+  device: IOHIDDeviceRef,
+);
+
+const K_IO_RETURN_SUCCESS: IOReturn = 0;
+const K_IOHID_OPTIONS_TYPE_NONE: u32 = 0;
+const K_HID_PAGE_GENERIC_DESKTOP: u32 = 0x01;
+const K_HID_PAGE_BUTTON: u32 = 0x09;
+const K_HID_USAGE_GD_MOUSE: u32 = 0x02;
+const K_HID_USAGE_GD_JOYSTICK: u32 = 0x04;
+const K_HID_USAGE_GD_MULTI_AXIS_CONTROLLER: u32 = 0x08;
+const K_HID_USAGE_GD_X: u32 = 0x30;
+const K_HID_USAGE_GD_Y: u32 = 0x31;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+  fn IOHIDManagerCreate(allocator: CFAllocatorRef, options: u32) -> IOHIDManagerRef;
+  fn IOHIDManagerSetDeviceMatchingMultiple(manager: IOHIDManagerRef, multiple: CFArrayRef);
+  fn IOHIDManagerRegisterDeviceMatchingCallback(
+    manager: IOHIDManagerRef,
+    callback: IOHIDDeviceCallback,
+    context: *mut c_void,
+  );
+  fn IOHIDManagerRegisterDeviceRemovalCallback(
+    manager: IOHIDManagerRef,
+    callback: IOHIDDeviceCallback,
+    context: *mut c_void,
+  );
+  fn IOHIDManagerRegisterInputValueCallback(
+    manager: IOHIDManagerRef,
+    callback: IOHIDValueCallback,
+    context: *mut c_void,
+  );
+  fn IOHIDManagerScheduleWithRunLoop(
+    manager: IOHIDManagerRef,
+    run_loop: CFRunLoopRef,
+    run_loop_mode: CFRunLoopMode,
+  );
+  fn IOHIDManagerOpen(manager: IOHIDManagerRef, options: u32) -> IOReturn;
+  fn IOHIDDeviceConformsTo(device: IOHIDDeviceRef, usage_page: u32, usage: u32) -> u8;
+  fn IOHIDDeviceGetProperty(device: IOHIDDeviceRef, key: CFStringRef) -> CFTypeRef;
+  fn IOHIDValueGetElement(value: IOHIDValueRef) -> IOHIDElementRef;
+  fn IOHIDValueGetIntegerValue(value: IOHIDValueRef) -> CFIndex;
+  fn IOHIDElementGetDevice(element: IOHIDElementRef) -> IOHIDDeviceRef;
+  fn IOHIDElementGetUsagePage(element: IOHIDElementRef) -> u32;
+  fn IOHIDElementGetUsage(element: IOHIDElementRef) -> u32;
+}
+
+static START: Once = Once::new();
+static STARTED: AtomicBool = AtomicBool::new(false);
+// The HID values are received whether the application is active or not, unlike the mouse events.
+static UNFOCUSED_FILTERED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+  // The connected devices other than the mice, by the address of their `IOHIDDevice`.
+  static DEVICES: RefCell<HashMap<usize, DeviceInfo>> = RefCell::new(HashMap::new());
+}
+
+/// Starts receiving the HID values of the mice, joysticks and multi-axis controllers, once for the
+/// whole process. Must be called on the main thread.
+pub fn start_hid_input() {
+  START.call_once(|| unsafe {
+    // The manager is never released, so its callbacks keep being called.
+    let manager = IOHIDManagerCreate(std::ptr::null(), K_IOHID_OPTIONS_TYPE_NONE);
+    if manager.is_null() {
+      return;
+    }
+    let matching: Vec<_> = [
+      K_HID_USAGE_GD_MOUSE,
+      K_HID_USAGE_GD_JOYSTICK,
+      K_HID_USAGE_GD_MULTI_AXIS_CONTROLLER,
+    ]
+    .iter()
+    .map(|&usage| {
+      CFDictionary::from_CFType_pairs(&[
+        (
+          CFString::new("DeviceUsagePage").as_CFType(),
+          CFNumber::from(K_HID_PAGE_GENERIC_DESKTOP as i32).as_CFType(),
+        ),
+        (
+          CFString::new("DeviceUsage").as_CFType(),
+          CFNumber::from(usage as i32).as_CFType(),
+        ),
+      ])
+    })
+    .collect();
+    let matching = CFArray::from_CFTypes(&matching);
+    IOHIDManagerSetDeviceMatchingMultiple(manager, matching.as_concrete_TypeRef());
+    IOHIDManagerRegisterDeviceMatchingCallback(
+      manager,
+      device_added_callback,
+      std::ptr::null_mut(),
+    );
+    IOHIDManagerRegisterDeviceRemovalCallback(
+      manager,
+      device_removed_callback,
+      std::ptr::null_mut(),
+    );
+    IOHIDManagerRegisterInputValueCallback(manager, input_value_callback, std::ptr::null_mut());
+    IOHIDManagerScheduleWithRunLoop(manager, CFRunLoopGetMain(), kCFRunLoopCommonModes);
+    if IOHIDManagerOpen(manager, K_IOHID_OPTIONS_TYPE_NONE) == K_IO_RETURN_SUCCESS {
+      STARTED.store(true, Ordering::Release);
+    } else {
+      warn!("Failed to open the HID manager, the mouse motion will be accelerated");
+    }
+  });
+}
+
+/// Whether the raw motion of the mice is received, otherwise it comes from the mouse events.
+pub fn is_raw_mouse_motion() -> bool {
+  STARTED.load(Ordering::Acquire)
+}
+
+/// Whether the raw motion is dropped while the application isn't active.
+pub fn set_unfocused_filtered(filtered: bool) {
+  UNFOCUSED_FILTERED.store(filtered, Ordering::Release);
+}
+
+/// The identity of a connected HID device.
+pub fn device_info(device_id: DeviceId) -> Option<DeviceInfo> {
+  DEVICES.with(|devices| devices.borrow().get(&device_id.0).cloned())
+}
+
+fn is_mouse(device: IOHIDDeviceRef) -> bool {
+  unsafe { IOHIDDeviceConformsTo(device, K_HID_PAGE_GENERIC_DESKTOP, K_HID_USAGE_GD_MOUSE) != 0 }
+}
+
+fn device_property(device: IOHIDDeviceRef, key: &str) -> Option<CFType> {
+  unsafe {
+    let key = CFString::new(key);
+    let property = IOHIDDeviceGetProperty(device, key.as_concrete_TypeRef());
+    if property.is_null() {
+      None
+    } else {
+      Some(CFType::wrap_under_get_rule(property))
+    }
+  }
+}
+
+fn device_id_property(device: IOHIDDeviceRef, key: &str) -> Option<u16> {
+  device_property(device, key)?
+    .downcast::<CFNumber>()?
+    .to_i32()
+    .map(|id| id as u16)
+}
+
+fn queue_device_event(device: IOHIDDeviceRef, event: DeviceEvent) {
+  AppState::queue_event(EventWrapper::StaticEvent(Event::DeviceEvent {
+    device_id: RootDeviceId(DeviceId(device as usize)),
+    event,
+    timestamp: std::time::Instant::now(),
+  }));
+}
+
+extern "C" fn device_added_callback(
+  _context: *mut c_void,
+  _result: IOReturn,
+  _sender: *mut c_void,
+  device: IOHIDDeviceRef,
+) {
+  // The mice have a single id, shared with their mouse events.
+  if is_mouse(device) {
+    return;
+  }
+  let info = DeviceInfo {
+    name: device_property(device, "Product")
+      .and_then(|name| name.downcast::<CFString>())
+      .map(|name| name.to_string()),
+    vendor_id: device_id_property(device, "VendorID"),
+    product_id: device_id_property(device, "ProductID"),
+  };
+  DEVICES.with(|devices| devices.borrow_mut().insert(device as usize, info));
+  if !AppState::device_events_filtered() {
+    queue_device_event(device, DeviceEvent::Added);
+  }
+}
+
+extern "C" fn device_removed_callback(
+  _context: *mut c_void,
+  _result: IOReturn,
+  _sender: *mut c_void,
+  device: IOHIDDeviceRef,
+) {
+  let removed = DEVICES.with(|devices| devices.borrow_mut().remove(&(device as usize)));
+  if removed.is_some() && !AppState::device_events_filtered() {
+    queue_device_event(device, DeviceEvent::Removed);
+  }
+}
+
+extern "C" fn input_value_callback(
+  _context: *mut c_void,
+  _result: IOReturn,
+  _sender: *mut c_void,
+  value: IOHIDValueRef,
+) {
+  if AppState::device_events_filtered() {
+    return;
+  }
+  if UNFOCUSED_FILTERED.load(Ordering::Acquire) {
+    let active: bool = unsafe {
+      let app: id = NSApp();
+      msg_send![app, isActive]
+    };
+    if !active {
+      return;
+    }
+  }
+  let (device, page, usage, value) = unsafe {
+    let element = IOHIDValueGetElement(value);
+    (
+      IOHIDElementGetDevice(element),
+      IOHIDElementGetUsagePage(element),
+      IOHIDElementGetUsage(element),
+      IOHIDValueGetIntegerValue(value),
+    )
+  };
+
+  if !is_mouse(device) {
+    let event = match page {
+      K_HID_PAGE_GENERIC_DESKTOP => DeviceEvent::Motion {
+        axis: usage,
+        value: value as f64,
+      },
+      K_HID_PAGE_BUTTON => DeviceEvent::Button {
+        button: usage,
+        state: if value != 0 {
+          ElementState::Pressed
+        } else {
+          ElementState::Released
+        },
+      },
+      _ => return,
+    };
+    queue_device_event(device, event);
+    return;
+  }
+
+  let value = value as f64;
+  let delta = match (page, usage) {
+    (K_HID_PAGE_GENERIC_DESKTOP, K_HID_USAGE_GD_X) => (value, 0.0),
+    (K_HID_PAGE_GENERIC_DESKTOP, K_HID_USAGE_GD_Y) => (0.0, value),
+    _ => return,
+  };
+  if delta == (0.0, 0.0) {
+    return;
+  }
+  AppState::queue_event(EventWrapper::StaticEvent(Event::DeviceEvent {
+    device_id: DEVICE_ID,
+    event: DeviceEvent::MouseMotion { delta },
+    timestamp: std::time::Instant::now(),
+  }));
+}
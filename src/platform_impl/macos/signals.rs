@@ -0,0 +1,85 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The signals turned into events are received by dispatch sources on the main queue, so their
+// handlers are called on the main thread. A dispatch source only observes its signal, so the
+// default action of the signal is ignored while the source exists.
+use std::{ffi::c_void, os::raw::c_ulong};
+
+use dispatch::ffi::{
+  dispatch_get_main_queue, dispatch_object_s, dispatch_queue_t, dispatch_release, dispatch_resume,
+  dispatch_set_context, dispatch_set_finalizer_f,
+};
+
+#[allow(non_camel_case_types)]
+type dispatch_source_t = *mut dispatch_object_s;
+
+#[link(name = "System", kind = "dylib")]
+extern "C" {
+  static _dispatch_source_type_signal: dispatch_object_s;
+
+  fn dispatch_source_create(
+    type_: *const dispatch_object_s,
+    handle: usize,
+    mask: c_ulong,
+    queue: dispatch_queue_t,
+  ) -> dispatch_source_t;
+  fn dispatch_source_set_event_handler_f(
+    source: dispatch_source_t,
+    handler: extern "C" fn(*mut c_void),
+  );
+  fn dispatch_source_cancel(source: dispatch_source_t);
+}
+
+type Handler = Box<dyn Fn()>;
+
+/// Calls its handler on the main thread each time the process receives its signal, instead of
+/// the default action of the signal.
+pub struct SignalSource {
+  source: dispatch_source_t,
+  signum: i32,
+}
+
+impl SignalSource {
+  /// Returns `None` if the dispatch source couldn't be created.
+  pub fn new(signum: i32, handler: Handler) -> Option<Self> {
+    unsafe {
+      let source = dispatch_source_create(
+        &_dispatch_source_type_signal,
+        signum as usize,
+        0,
+        dispatch_get_main_queue(),
+      );
+      if source.is_null() {
+        return None;
+      }
+      let context = Box::into_raw(Box::new(handler));
+      dispatch_set_context(source, context as *mut c_void);
+      dispatch_set_finalizer_f(source, drop_handler);
+      dispatch_source_set_event_handler_f(source, call_handler);
+      libc::signal(signum, libc::SIG_IGN);
+      dispatch_resume(source);
+      Some(SignalSource { source, signum })
+    }
+  }
+}
+
+impl Drop for SignalSource {
+  fn drop(&mut self) {
+    unsafe {
+      libc::signal(self.signum, libc::SIG_DFL);
+      // The handler is dropped by the finalizer, once the source is cancelled and released.
+      dispatch_source_cancel(self.source);
+      dispatch_release(self.source);
+    }
+  }
+}
+
+extern "C" fn call_handler(context: *mut c_void) {
+  let handler = unsafe { &*(context as *const Handler) };
+  handler();
+}
+
+extern "C" fn drop_handler(context: *mut c_void) {
+  unsafe { drop(Box::from_raw(context as *mut Handler)) };
+}
@@ -0,0 +1,201 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The synthetic input of `Window::inject_event`. The events are created as the events of the window
+// server, and converted to the `NSEvent`s which the window dispatches to the view, so the keys are
+// translated by the current keyboard layout. The modifiers pressed by the synthetic keys are kept
+// for the next synthetic events.
+use std::{cell::Cell, ffi::c_void};
+
+use cocoa::{
+  base::{id, nil},
+  foundation::{NSPoint, NSRect, NSSize},
+};
+use core_graphics::{display::CGDisplay, geometry::CGPoint};
+use dispatch::Queue;
+
+use crate::{
+  error::{ExternalError, NotSupportedError},
+  event::{ElementState, MouseButton},
+  keyboard::KeyCode,
+  test::SyntheticEvent,
+};
+
+type CGEventRef = *mut c_void;
+type CGEventSourceRef = *mut c_void;
+
+const K_CG_EVENT_LEFT_MOUSE_DOWN: u32 = 1;
+const K_CG_EVENT_LEFT_MOUSE_UP: u32 = 2;
+const K_CG_EVENT_RIGHT_MOUSE_DOWN: u32 = 3;
+const K_CG_EVENT_RIGHT_MOUSE_UP: u32 = 4;
+const K_CG_EVENT_MOUSE_MOVED: u32 = 5;
+const K_CG_EVENT_FLAGS_CHANGED: u32 = 12;
+const K_CG_EVENT_OTHER_MOUSE_DOWN: u32 = 25;
+const K_CG_EVENT_OTHER_MOUSE_UP: u32 = 26;
+const K_CG_SCROLL_EVENT_UNIT_LINE: u32 = 1;
+// The fields of the window under the pointer, which give the window of the converted `NSEvent`.
+const K_CG_MOUSE_EVENT_WINDOW_UNDER_MOUSE_POINTER: u32 = 91;
+const K_CG_MOUSE_EVENT_WINDOW_UNDER_MOUSE_POINTER_THAT_CAN_HANDLE_THIS_EVENT: u32 = 92;
+const K_CG_EVENT_FLAG_MASK_SHIFT: u64 = 0x0002_0000;
+const K_CG_EVENT_FLAG_MASK_CONTROL: u64 = 0x0004_0000;
+const K_CG_EVENT_FLAG_MASK_ALTERNATE: u64 = 0x0008_0000;
+const K_CG_EVENT_FLAG_MASK_COMMAND: u64 = 0x0010_0000;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+  fn CGEventCreateKeyboardEvent(
+    source: CGEventSourceRef,
+    keycode: u16,
+    key_down: bool,
+  ) -> CGEventRef;
+  fn CGEventCreateMouseEvent(
+    source: CGEventSourceRef,
+    mouse_type: u32,
+    location: CGPoint,
+    button: u32,
+  ) -> CGEventRef;
+  fn CGEventCreateScrollWheelEvent(
+    source: CGEventSourceRef,
+    units: u32,
+    wheel_count: u32,
+    wheel1: i32,
+    ...
+  ) -> CGEventRef;
+  fn CGEventSetType(event: CGEventRef, event_type: u32);
+  fn CGEventSetFlags(event: CGEventRef, flags: u64);
+  fn CGEventSetIntegerValueField(event: CGEventRef, field: u32, value: i64);
+  fn CFRelease(cf: *const c_void);
+}
+
+thread_local! {
+  // The modifiers pressed by the synthetic keys.
+  static FLAGS: Cell<u64> = Cell::new(0);
+}
+
+/// Sends a synthetic event to `ns_window`, from any thread.
+pub fn inject_event(
+  ns_window: id,
+  ns_view: id,
+  event: SyntheticEvent,
+) -> Result<(), ExternalError> {
+  let (window, view) = (ns_window as usize, ns_view as usize);
+  let injected = if unsafe { msg_send![class!(NSThread), isMainThread] } {
+    inject_event_main(window as id, view as id, event)
+  } else {
+    Queue::main().exec_sync(move || inject_event_main(window as id, view as id, event))
+  };
+  if injected {
+    Ok(())
+  } else {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+}
+
+/// Returns whether the event is supported.
+fn inject_event_main(ns_window: id, ns_view: id, event: SyntheticEvent) -> bool {
+  unsafe {
+    let cg_event = match event {
+      SyntheticEvent::Key {
+        physical_key,
+        state,
+      } => {
+        let scancode = match physical_key.to_scancode() {
+          Some(scancode) => scancode,
+          None => return false,
+        };
+        let pressed = state == ElementState::Pressed;
+        let cg_event = CGEventCreateKeyboardEvent(std::ptr::null_mut(), scancode as u16, pressed);
+        if let Some(mask) = modifier_mask(physical_key) {
+          FLAGS.with(|flags| {
+            if pressed {
+              flags.set(flags.get() | mask)
+            } else {
+              flags.set(flags.get() & !mask)
+            }
+          });
+          CGEventSetType(cg_event, K_CG_EVENT_FLAGS_CHANGED);
+        }
+        cg_event
+      }
+      SyntheticEvent::CursorMoved { position } => {
+        let location = screen_location(ns_window, ns_view, position.x, position.y);
+        CGEventCreateMouseEvent(std::ptr::null_mut(), K_CG_EVENT_MOUSE_MOVED, location, 0)
+      }
+      SyntheticEvent::MouseInput {
+        button,
+        state,
+        position,
+      } => {
+        let pressed = state == ElementState::Pressed;
+        let (mouse_type, button) = match (button, pressed) {
+          (MouseButton::Left, true) => (K_CG_EVENT_LEFT_MOUSE_DOWN, 0),
+          (MouseButton::Left, false) => (K_CG_EVENT_LEFT_MOUSE_UP, 0),
+          (MouseButton::Right, true) => (K_CG_EVENT_RIGHT_MOUSE_DOWN, 1),
+          (MouseButton::Right, false) => (K_CG_EVENT_RIGHT_MOUSE_UP, 1),
+          (MouseButton::Middle, true) => (K_CG_EVENT_OTHER_MOUSE_DOWN, 2),
+          (MouseButton::Middle, false) => (K_CG_EVENT_OTHER_MOUSE_UP, 2),
+          _ => return false,
+        };
+        let location = screen_location(ns_window, ns_view, position.x, position.y);
+        CGEventCreateMouseEvent(std::ptr::null_mut(), mouse_type, location, button)
+      }
+      // The horizontal sign of macOS is the inverse of tao.
+      SyntheticEvent::MouseWheel { lines } => CGEventCreateScrollWheelEvent(
+        std::ptr::null_mut(),
+        K_CG_SCROLL_EVENT_UNIT_LINE,
+        2,
+        lines.1.round() as i32,
+        -lines.0.round() as i32,
+      ),
+      SyntheticEvent::Touch { .. } => return false,
+    };
+    if cg_event.is_null() {
+      return false;
+    }
+    CGEventSetFlags(cg_event, FLAGS.with(Cell::get));
+    let window_number: i64 = msg_send![ns_window, windowNumber];
+    for field in [
+      K_CG_MOUSE_EVENT_WINDOW_UNDER_MOUSE_POINTER,
+      K_CG_MOUSE_EVENT_WINDOW_UNDER_MOUSE_POINTER_THAT_CAN_HANDLE_THIS_EVENT,
+    ] {
+      CGEventSetIntegerValueField(cg_event, field, window_number);
+    }
+    let ns_event: id = msg_send![class!(NSEvent), eventWithCGEvent: cg_event];
+    CFRelease(cg_event as *const c_void);
+    if ns_event == nil {
+      return false;
+    }
+    if let SyntheticEvent::MouseWheel { .. } = event {
+      // The window sends the scrolls to the view under the pointer, which may be elsewhere.
+      let _: () = msg_send![ns_view, scrollWheel: ns_event];
+    } else {
+      let _: () = msg_send![ns_window, sendEvent: ns_event];
+    }
+    true
+  }
+}
+
+fn modifier_mask(key: KeyCode) -> Option<u64> {
+  match key {
+    KeyCode::ShiftLeft | KeyCode::ShiftRight => Some(K_CG_EVENT_FLAG_MASK_SHIFT),
+    KeyCode::ControlLeft | KeyCode::ControlRight => Some(K_CG_EVENT_FLAG_MASK_CONTROL),
+    KeyCode::AltLeft | KeyCode::AltRight => Some(K_CG_EVENT_FLAG_MASK_ALTERNATE),
+    KeyCode::SuperLeft | KeyCode::SuperRight => Some(K_CG_EVENT_FLAG_MASK_COMMAND),
+    _ => None,
+  }
+}
+
+/// The location on the screen of a physical position in the view, with the origin of the screens
+/// of Core Graphics at the top-left corner of the main screen.
+unsafe fn screen_location(ns_window: id, ns_view: id, x: f64, y: f64) -> CGPoint {
+  let scale_factor: f64 = msg_send![ns_window, backingScaleFactor];
+  let frame: NSRect = msg_send![ns_view, frame];
+  let view_point = NSPoint::new(x / scale_factor, frame.size.height - y / scale_factor);
+  let window_point: NSPoint = msg_send![ns_view, convertPoint: view_point toView: nil];
+  let screen_rect: NSRect = msg_send![
+    ns_window,
+    convertRectToScreen: NSRect::new(window_point, NSSize::new(0.0, 0.0))
+  ];
+  let main_height = CGDisplay::main().bounds().size.height;
+  CGPoint::new(screen_rect.origin.x, main_height - screen_rect.origin.y)
+}
@@ -7,9 +7,12 @@ use std::{
 };
 
 use cocoa::{
-  appkit::{CGFloat, NSScreen, NSWindow, NSWindowStyleMask},
+  appkit::{
+    CGFloat, NSApp, NSColor, NSScreen, NSWindow, NSWindowCollectionBehavior, NSWindowOrderingMode,
+    NSWindowStyleMask,
+  },
   base::{id, nil},
-  foundation::{NSPoint, NSSize, NSString},
+  foundation::{NSInteger, NSPoint, NSRect, NSSize, NSString},
 };
 use dispatch::Queue;
 use menu::Menu;
@@ -19,8 +22,15 @@ use objc::{
 };
 
 use crate::{
-  dpi::LogicalSize,
-  platform_impl::platform::{ffi, menu, util::IdRef, window::SharedState},
+  dpi::{LogicalPosition, LogicalSize},
+  platform_impl::platform::{
+    effects::{self, VisualEffect},
+    ffi, menu,
+    util::{self, IdRef},
+    view,
+    window::SharedState,
+  },
+  window::{Color, DragRegion, Theme, TitlebarButtons},
 };
 
 // Unsafe wrapper type that allows us to dispatch things that aren't Send.
@@ -94,6 +104,106 @@ pub unsafe fn set_level_async(ns_window: id, level: ffi::NSWindowLevel) {
   });
 }
 
+// `setCollectionBehavior:` isn't thread-safe.
+pub unsafe fn set_visible_on_all_spaces_async(ns_window: id, visible: bool) {
+  let ns_window = MainThreadSafe(ns_window);
+  Queue::main().exec_async(move || {
+    let mut behavior = ns_window.collectionBehavior();
+    if visible {
+      // `MoveToActiveSpace` can't be combined with `CanJoinAllSpaces`
+      behavior.remove(NSWindowCollectionBehavior::NSWindowCollectionBehaviorMoveToActiveSpace);
+      behavior.insert(NSWindowCollectionBehavior::NSWindowCollectionBehaviorCanJoinAllSpaces);
+    } else {
+      behavior.remove(NSWindowCollectionBehavior::NSWindowCollectionBehaviorCanJoinAllSpaces);
+    }
+    ns_window.setCollectionBehavior_(behavior);
+  });
+}
+
+// `NSAppearance` is part of AppKit, so it must be set from the main thread.
+pub unsafe fn set_appearance_async(ns_window: id, theme: Option<Theme>) {
+  let ns_window = MainThreadSafe(ns_window);
+  Queue::main().exec_async(move || {
+    let appearance: id = match theme {
+      Some(theme) => {
+        let name = util::ns_string_id_ref(match theme {
+          Theme::Light => "NSAppearanceNameAqua",
+          Theme::Dark => "NSAppearanceNameDarkAqua",
+        });
+        msg_send![class!(NSAppearance), appearanceNamed: *name]
+      }
+      // The window follows the appearance of the application again.
+      None => nil,
+    };
+    let () = msg_send![*ns_window, setAppearance: appearance];
+  });
+}
+
+// The titlebar is tinted by the background color of the window showing through it.
+pub unsafe fn set_titlebar_color_async(
+  ns_window: id,
+  color: Option<Color>,
+  titlebar_transparent: bool,
+  transparent: bool,
+) {
+  let ns_window = MainThreadSafe(ns_window);
+  Queue::main().exec_async(move || {
+    let background_color: id = match color {
+      Some(color) => {
+        ns_window.setTitlebarAppearsTransparent_(YES);
+        NSColor::colorWithSRGBRed_green_blue_alpha_(
+          nil,
+          color.r as CGFloat / 255.0,
+          color.g as CGFloat / 255.0,
+          color.b as CGFloat / 255.0,
+          color.a as CGFloat / 255.0,
+        )
+      }
+      None => {
+        ns_window.setTitlebarAppearsTransparent_(if titlebar_transparent { YES } else { NO });
+        if transparent {
+          NSColor::clearColor(nil)
+        } else {
+          msg_send![class!(NSColor), windowBackgroundColor]
+        }
+      }
+    };
+    ns_window.setBackgroundColor_(background_color);
+  });
+}
+
+// `NSView` isn't thread-safe, so the effect view must be managed from the main thread.
+pub unsafe fn set_visual_effect_async(ns_view: id, effect: Option<VisualEffect>) {
+  let ns_view = MainThreadSafe(ns_view);
+  Queue::main().exec_async(move || {
+    effects::set_visual_effect(*ns_view, effect);
+  });
+}
+
+// Child windows must be managed from the main thread.
+pub unsafe fn set_owner_async(ns_window: id, owner: Option<id>) {
+  let ns_window = MainThreadSafe(ns_window);
+  let owner = MainThreadSafe(owner);
+  Queue::main().exec_async(move || {
+    let parent: id = msg_send![*ns_window, parentWindow];
+    if parent != nil {
+      let () = msg_send![parent, removeChildWindow: *ns_window];
+    }
+    if let Some(owner) = *owner {
+      let () =
+        msg_send![owner, addChildWindow: *ns_window ordered: NSWindowOrderingMode::NSWindowAbove];
+    }
+  });
+}
+
+// The drag regions are read by the view from the main thread.
+pub unsafe fn set_drag_regions_async(ns_view: id, regions: Vec<DragRegion>) {
+  let ns_view = MainThreadSafe(ns_view);
+  Queue::main().exec_async(move || {
+    view::set_drag_regions(*ns_view, regions);
+  });
+}
+
 // `toggleFullScreen` is thread-safe, but our additional logic to account for
 // window styles isn't.
 pub unsafe fn toggle_full_screen_async(
@@ -182,6 +292,52 @@ pub unsafe fn set_maximized_async(
   });
 }
 
+// Like `set_maximized_async`, but sets the frame directly to the visible frame of
+// `ns_screen`, which moves the window to that screen and zooms it at once.
+pub unsafe fn set_maximized_on_async(
+  ns_window: id,
+  ns_screen: id,
+  is_zoomed: bool,
+  shared_state: Weak<Mutex<SharedState>>,
+) {
+  let ns_window = MainThreadSafe(ns_window);
+  let ns_screen = MainThreadSafe(ns_screen);
+  let shared_state = MainThreadSafe(shared_state);
+  Queue::main().exec_async(move || {
+    if let Some(shared_state) = shared_state.upgrade() {
+      trace!("Locked shared state in `set_maximized_on`");
+      let mut shared_state_lock = shared_state.lock().unwrap();
+      if shared_state_lock.fullscreen.is_some() {
+        trace!("Unlocked shared state in `set_maximized_on`");
+        return;
+      }
+
+      // The standard frame is centered on the new screen, so the window stays there
+      // when it is restored.
+      let standard_frame = if is_zoomed {
+        shared_state_lock.saved_standard_frame()
+      } else {
+        NSWindow::frame(*ns_window)
+      };
+      let visible_frame = NSScreen::visibleFrame(*ns_screen);
+      let size = NSSize::new(
+        standard_frame.size.width.min(visible_frame.size.width),
+        standard_frame.size.height.min(visible_frame.size.height),
+      );
+      let origin = NSPoint::new(
+        visible_frame.origin.x + (visible_frame.size.width - size.width) / 2.0,
+        visible_frame.origin.y + (visible_frame.size.height - size.height) / 2.0,
+      );
+      shared_state_lock.standard_frame = Some(NSRect::new(origin, size));
+      shared_state_lock.maximized = true;
+      trace!("Unlocked shared state in `set_maximized_on`");
+      drop(shared_state_lock);
+
+      ns_window.setFrame_display_(visible_frame, YES);
+    }
+  });
+}
+
 // `orderOut:` isn't thread-safe. Calling it from another thread actually works,
 // but with an odd delay.
 pub unsafe fn order_out_async(ns_window: id) {
@@ -200,6 +356,40 @@ pub unsafe fn make_key_and_order_front_async(ns_window: id) {
   });
 }
 
+// `orderFront:` isn't thread-safe.
+pub unsafe fn order_front_async(ns_window: id) {
+  let ns_window = MainThreadSafe(ns_window);
+  Queue::main().exec_async(move || {
+    ns_window.orderFront_(nil);
+  });
+}
+
+// `orderWindow:relativeTo:` isn't thread-safe.
+pub unsafe fn order_relative_to_async(ns_window: id, mode: NSWindowOrderingMode, other: IdRef) {
+  let ns_window = MainThreadSafe(ns_window);
+  let other = MainThreadSafe(other);
+  Queue::main().exec_async(move || {
+    let window_number: NSInteger = msg_send![**other, windowNumber];
+    ns_window.orderFrontWindow_relativeTo_(mode, window_number);
+  });
+}
+
+// The titlebar buttons can only be moved from the main thread.
+pub unsafe fn set_traffic_light_inset_async(ns_window: id, inset: LogicalPosition<f64>) {
+  let ns_window = MainThreadSafe(ns_window);
+  Queue::main().exec_async(move || {
+    util::position_traffic_lights(*ns_window, inset);
+  });
+}
+
+// The titlebar buttons can only be changed from the main thread.
+pub unsafe fn set_titlebar_buttons_async(ns_window: id, buttons: TitlebarButtons) {
+  let ns_window = MainThreadSafe(ns_window);
+  Queue::main().exec_async(move || {
+    util::set_titlebar_buttons(*ns_window, buttons);
+  });
+}
+
 // `setTitle:` isn't thread-safe. Calling it from another thread invalidates the
 // window drag regions, which throws an exception when not done in the main
 // thread
@@ -241,7 +431,21 @@ pub unsafe fn close_async(ns_window: IdRef) {
   let ns_window = MainThreadSafe(ns_window);
   Queue::main().exec_async(move || {
     autoreleasepool(move || {
+      // Sheets have to be ended before closing, or their parent stays blocked
+      let sheet_parent: id = msg_send![**ns_window, sheetParent];
+      if sheet_parent != nil {
+        let () = msg_send![sheet_parent, endSheet: **ns_window];
+      }
       ns_window.close();
     });
   });
 }
+
+// The dock tile is shared by the whole application, `None` removes its badge.
+pub fn set_badge_label_async(label: Option<String>) {
+  Queue::main().exec_async(move || unsafe {
+    let dock_tile: id = msg_send![NSApp(), dockTile];
+    let label = label.map(|label| util::ns_string_id_ref(&label));
+    let () = msg_send![dock_tile, setBadgeLabel: label.as_ref().map_or(nil, |label| **label)];
+  });
+}
@@ -9,17 +9,27 @@ pub use self::{cursor::*, r#async::*};
 use std::{
   ops::{BitAnd, Deref},
   slice, str,
+  sync::atomic::{AtomicIsize, Ordering},
+  time::{Duration, Instant},
 };
 
 use cocoa::{
-  appkit::{NSApp, NSWindowStyleMask},
+  appkit::{
+    NSApp, NSRequestUserAttentionType, NSView, NSWindow, NSWindowButton, NSWindowStyleMask,
+  },
   base::{id, nil},
-  foundation::{NSAutoreleasePool, NSPoint, NSRect, NSString, NSUInteger},
+  foundation::{
+    NSAutoreleasePool, NSInteger, NSPoint, NSRect, NSString, NSTimeInterval, NSUInteger,
+  },
 };
 use core_graphics::display::CGDisplay;
-use objc::runtime::{Class, Object, Sel, BOOL, YES};
+use objc::runtime::{Class, Object, Sel, BOOL, NO, YES};
 
-use crate::{dpi::LogicalPosition, platform_impl::platform::ffi};
+use crate::{
+  dpi::LogicalPosition,
+  platform_impl::platform::ffi,
+  window::{TitlebarButtons, UserAttentionType},
+};
 
 // Replace with `!` once stable
 #[derive(Debug)]
@@ -122,6 +132,24 @@ pub fn window_position(position: LogicalPosition<f64>) -> NSPoint {
   )
 }
 
+/// Converts the timestamp of `event`, in seconds since the system started, to an `Instant`.
+pub unsafe fn event_timestamp(event: id) -> Instant {
+  let now = Instant::now();
+  if event == nil {
+    return now;
+  }
+  let timestamp: NSTimeInterval = msg_send![event, timestamp];
+  let process_info: id = msg_send![class!(NSProcessInfo), processInfo];
+  let uptime: NSTimeInterval = msg_send![process_info, systemUptime];
+  let elapsed = uptime - timestamp;
+  if !elapsed.is_finite() || elapsed <= 0.0 {
+    return now;
+  }
+  now
+    .checked_sub(Duration::from_secs_f64(elapsed))
+    .unwrap_or(now)
+}
+
 pub unsafe fn ns_string_id_ref(s: &str) -> IdRef {
   IdRef::new(NSString::alloc(nil).init_str(s))
 }
@@ -146,6 +174,74 @@ pub unsafe fn app_name() -> Option<id> {
   }
 }
 
+// The identifier returned by `requestUserAttention:`, needed to cancel the request.
+static USER_ATTENTION_REQUEST: AtomicIsize = AtomicIsize::new(0);
+
+/// Bounces the dock icon, cancelling the bounce requested previously. `None` only cancels it.
+/// The request is shared by the whole application and is ignored while it is active.
+pub unsafe fn request_user_attention(request_type: Option<UserAttentionType>) {
+  let app = NSApp();
+
+  let previous_request = USER_ATTENTION_REQUEST.swap(0, Ordering::SeqCst);
+  if previous_request != 0 {
+    let () = msg_send![app, cancelUserAttentionRequest: previous_request as NSInteger];
+  }
+
+  if let Some(request_type) = request_type {
+    let request_type = match request_type {
+      UserAttentionType::Critical => NSRequestUserAttentionType::NSCriticalRequest,
+      UserAttentionType::Informational => NSRequestUserAttentionType::NSInformationalRequest,
+    };
+    let request: NSInteger = msg_send![app, requestUserAttention: request_type];
+    USER_ATTENTION_REQUEST.store(request as isize, Ordering::SeqCst);
+  }
+}
+
+// Moves the close, minimize and zoom buttons so that the close button is `inset` away from the top
+// left corner of the window. The titlebar container is made taller to leave room for them.
+pub unsafe fn position_traffic_lights(ns_window: id, inset: LogicalPosition<f64>) {
+  let close = ns_window.standardWindowButton_(NSWindowButton::NSWindowCloseButton);
+  let miniaturize = ns_window.standardWindowButton_(NSWindowButton::NSWindowMiniaturizeButton);
+  let zoom = ns_window.standardWindowButton_(NSWindowButton::NSWindowZoomButton);
+  if close == nil || miniaturize == nil || zoom == nil {
+    return;
+  }
+
+  let titlebar_view: id = msg_send![close, superview];
+  let titlebar_container: id = msg_send![titlebar_view, superview];
+  let close_frame = NSView::frame(close);
+  let titlebar_height = close_frame.size.height + inset.y;
+  let mut titlebar_frame = NSView::frame(titlebar_container);
+  titlebar_frame.size.height = titlebar_height;
+  titlebar_frame.origin.y = NSWindow::frame(ns_window).size.height - titlebar_height;
+  let () = msg_send![titlebar_container, setFrame: titlebar_frame];
+
+  let spacing = NSView::frame(miniaturize).origin.x - close_frame.origin.x;
+  for (i, button) in [close, miniaturize, zoom].iter().enumerate() {
+    let mut origin = NSView::frame(*button).origin;
+    origin.x = inset.x + i as f64 * spacing;
+    let () = msg_send![*button, setFrameOrigin: origin];
+  }
+}
+
+pub unsafe fn set_titlebar_buttons(ns_window: id, buttons: TitlebarButtons) {
+  for (button, flag) in &[
+    (
+      NSWindowButton::NSWindowMiniaturizeButton,
+      TitlebarButtons::MINIMIZE,
+    ),
+    (
+      NSWindowButton::NSWindowZoomButton,
+      TitlebarButtons::MAXIMIZE,
+    ),
+    (NSWindowButton::NSWindowCloseButton, TitlebarButtons::CLOSE),
+  ] {
+    let button = ns_window.standardWindowButton_(*button);
+    let hidden = if buttons.contains(*flag) { NO } else { YES };
+    let () = msg_send![button, setHidden: hidden];
+  }
+}
+
 pub unsafe fn superclass<'a>(this: &'a Object) -> &'a Class {
   let superclass: id = msg_send![this, superclass];
   &*(superclass as *const _)
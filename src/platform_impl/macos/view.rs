@@ -7,6 +7,7 @@ use std::{
   os::raw::*,
   slice, str,
   sync::{Arc, Mutex, Weak},
+  time::Instant,
 };
 
 use cocoa::{
@@ -20,20 +21,23 @@ use objc::{
 };
 
 use crate::{
-  dpi::LogicalPosition,
+  dpi::{LogicalPosition, LogicalSize, PhysicalPosition},
   event::{
-    DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
+    CloseResponse, ComposeState, DeviceEvent, ElementState, Event, FocusDirection, Ime,
+    ImeUnderline, MouseButton, MouseScrollDelta, PenHover, PenInput, TouchPhase, WindowEvent,
   },
-  keyboard::{KeyCode, ModifiersState},
+  keyboard::{Key, KeyCode, ModifiersState},
   platform_impl::platform::{
     app_state::AppState,
-    event::{code_to_key, create_key_event, event_mods, get_scancode, EventWrapper},
+    event::{
+      code_to_key, create_key_event, event_mods, get_scancode, insert_or_get_key_str, EventWrapper,
+    },
     ffi::*,
     util::{self, IdRef},
-    window::get_window_id,
+    window::{get_window_id, CloseHandle},
     DEVICE_ID,
   },
-  window::WindowId,
+  window::{DragRegion, DragRegionKind, WindowId},
 };
 
 pub struct CursorState {
@@ -53,7 +57,11 @@ impl Default for CursorState {
 pub(super) struct ViewState {
   ns_window: id,
   pub cursor_state: Arc<Mutex<CursorState>>,
-  ime_spot: Option<(f64, f64)>,
+  /// The area of the text being edited, in screen coordinates.
+  ime_cursor_area: Option<NSRect>,
+  ime_allowed: bool,
+  /// Set with `Window::start_text_input`, the entered text being sent with `ReceivedText`.
+  text_input: bool,
 
   /// This is true when we are currently modifying a marked text
   /// using ime. When the text gets commited, this is set to false.
@@ -63,12 +71,30 @@ pub(super) struct ViewState {
   /// If a key-press does not cause an ime event, that means
   /// that the key-press cancelled the ime session. (Except arrow keys)
   key_triggered_ime: bool,
+
+  /// This is true when the marked text comes from dead keys instead of an IME. The text
+  /// finishing the sequence is then stored in `composed_text` and sent with the key event.
+  in_dead_key_sequence: bool,
+  composed_text: Option<String>,
+
+  /// The IME events sent while `interpretKeyEvents` runs, which are dropped if the marked text
+  /// turns out to come from dead keys.
+  interpreting_key: bool,
+  pending_ime_events: Vec<Ime>,
   // Not Needed Anymore
   //raw_characters: Option<String>,
   is_key_down: bool,
   pub(super) modifiers: ModifiersState,
   phys_modifiers: HashSet<KeyCode>,
   tracking_rect: Option<NSInteger>,
+  drag_regions: Vec<DragRegion>,
+  /// The pressure and the stage of the last `TouchpadPressure` sent.
+  touchpad_pressure: (f32, i64),
+  /// Whether the pen in proximity of the tablet is its eraser end.
+  pen_inverted: bool,
+  /// Set with `Window::set_mouse_capture`, the motion outside of the view is then reported without
+  /// a button pressed.
+  mouse_captured: bool,
 }
 
 impl ViewState {
@@ -83,13 +109,23 @@ pub fn new_view(ns_window: id) -> (IdRef, Weak<Mutex<CursorState>>) {
   let state = ViewState {
     ns_window,
     cursor_state,
-    ime_spot: None,
+    ime_cursor_area: None,
+    ime_allowed: true,
+    text_input: false,
     in_ime_preedit: false,
     key_triggered_ime: false,
+    in_dead_key_sequence: false,
+    composed_text: None,
+    interpreting_key: false,
+    pending_ime_events: Vec::new(),
     is_key_down: false,
     modifiers: Default::default(),
     phys_modifiers: Default::default(),
     tracking_rect: None,
+    drag_regions: Vec::new(),
+    touchpad_pressure: (0.0, 0),
+    pen_inverted: false,
+    mouse_captured: false,
   };
   unsafe {
     // This is free'd in `dealloc`
@@ -102,17 +138,105 @@ pub fn new_view(ns_window: id) -> (IdRef, Weak<Mutex<CursorState>>) {
   }
 }
 
-pub unsafe fn set_ime_position(ns_view: id, input_context: id, x: f64, y: f64) {
+pub unsafe fn set_drag_regions(ns_view: id, regions: Vec<DragRegion>) {
+  let state_ptr: *mut c_void = *(*ns_view).get_mut_ivar("taoState");
+  let state = &mut *(state_ptr as *mut ViewState);
+  state.drag_regions = regions;
+}
+
+pub unsafe fn set_ime_cursor_area(
+  ns_view: id,
+  input_context: id,
+  position: LogicalPosition<f64>,
+  size: LogicalSize<f64>,
+) {
   let state_ptr: *mut c_void = *(*ns_view).get_mut_ivar("taoState");
   let state = &mut *(state_ptr as *mut ViewState);
   let content_rect =
     NSWindow::contentRectForFrameRect_(state.ns_window, NSWindow::frame(state.ns_window));
   let base_x = content_rect.origin.x as f64;
   let base_y = (content_rect.origin.y + content_rect.size.height) as f64;
-  state.ime_spot = Some((base_x + x, base_y - y));
+  state.ime_cursor_area = Some(NSRect::new(
+    NSPoint::new(
+      (base_x + position.x) as _,
+      (base_y - position.y - size.height) as _,
+    ),
+    NSSize::new(size.width as _, size.height as _),
+  ));
   let _: () = msg_send![input_context, invalidateCharacterCoordinates];
 }
 
+pub unsafe fn set_text_input(ns_view: id, active: bool) {
+  let state_ptr: *mut c_void = *(*ns_view).get_mut_ivar("taoState");
+  let state = &mut *(state_ptr as *mut ViewState);
+  state.text_input = active;
+}
+
+pub unsafe fn set_mouse_captured(ns_view: id, captured: bool) {
+  let state_ptr: *mut c_void = *(*ns_view).get_mut_ivar("taoState");
+  let state = &mut *(state_ptr as *mut ViewState);
+  state.mouse_captured = captured;
+}
+
+pub unsafe fn set_ime_allowed(ns_view: id, input_context: id, allowed: bool) {
+  let state_ptr: *mut c_void = *(*ns_view).get_mut_ivar("taoState");
+  let state = &mut *(state_ptr as *mut ViewState);
+  if state.ime_allowed == allowed {
+    return;
+  }
+  state.ime_allowed = allowed;
+  if !allowed && state.in_ime_preedit {
+    // The keys aren't interpreted anymore, so the marked text could never be committed.
+    clear_marked_text(&mut *ns_view);
+    let _: () = msg_send![input_context, discardMarkedText];
+    state.in_ime_preedit = false;
+    if state.in_dead_key_sequence {
+      state.in_dead_key_sequence = false;
+    } else {
+      queue_ime_event(state, Ime::preedit(String::new()));
+      queue_ime_event(state, Ime::Disabled);
+    }
+  }
+}
+
+/// Sends an IME event, or keeps it until the key being interpreted is known to not be a dead key.
+fn queue_ime_event(state: &mut ViewState, ime: Ime) {
+  if state.interpreting_key {
+    state.pending_ime_events.push(ime);
+  } else {
+    let window_id = WindowId(get_window_id(state.ns_window));
+    let text = match &ime {
+      Ime::Commit(text) if state.text_input => Some(text.clone()),
+      _ => None,
+    };
+    AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+      window_id,
+      event: WindowEvent::Ime(ime),
+      timestamp: Instant::now(),
+    }));
+    if let Some(text) = text {
+      AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+        window_id,
+        event: WindowEvent::ReceivedText(text),
+        timestamp: Instant::now(),
+      }));
+    }
+  }
+}
+
+/// Returns the byte offset of the UTF-16 offset `offset` of `text`, as the ranges of the text input
+/// client are in UTF-16 code units.
+fn utf16_to_byte_offset(text: &str, offset: NSUInteger) -> usize {
+  let mut utf16_offset = 0;
+  for (byte_offset, c) in text.char_indices() {
+    if utf16_offset >= offset as usize {
+      return byte_offset;
+    }
+    utf16_offset += c.len_utf16();
+  }
+  text.len()
+}
+
 fn is_arrow_key(keycode: KeyCode) -> bool {
   matches!(
     keycode,
@@ -254,6 +378,10 @@ lazy_static! {
       sel!(mouseDragged:),
       mouse_dragged as extern "C" fn(&Object, Sel, id),
     );
+    decl.add_method(
+      sel!(tabletProximity:),
+      tablet_proximity as extern "C" fn(&Object, Sel, id),
+    );
     decl.add_method(
       sel!(rightMouseDragged:),
       right_mouse_dragged as extern "C" fn(&Object, Sel, id),
@@ -278,6 +406,18 @@ lazy_static! {
       sel!(pressureChangeWithEvent:),
       pressure_change_with_event as extern "C" fn(&Object, Sel, id),
     );
+    decl.add_method(
+      sel!(magnifyWithEvent:),
+      magnify_with_event as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(rotateWithEvent:),
+      rotate_with_event as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(swipeWithEvent:),
+      swipe_with_event as extern "C" fn(&Object, Sel, id),
+    );
     decl.add_method(
       sel!(_wantsKeyDownForEvent:),
       wants_key_down_for_event as extern "C" fn(&Object, Sel, id) -> BOOL,
@@ -456,21 +596,55 @@ extern "C" fn set_marked_text(
   this: &mut Object,
   _sel: Sel,
   string: id,
-  _selected_range: NSRange,
+  selected_range: NSRange,
   _replacement_range: NSRange,
 ) {
   trace!("Triggered `setMarkedText`");
   unsafe {
     let marked_text_ref = clear_marked_text(this);
     let has_attr = msg_send![string, isKindOfClass: class!(NSAttributedString)];
-    if has_attr {
+    let characters = if has_attr {
       marked_text_ref.initWithAttributedString(string);
+      msg_send![string, string]
     } else {
       marked_text_ref.initWithString(string);
+      string
     };
 
     let state_ptr: *mut c_void = *this.get_ivar("taoState");
     let state = &mut *(state_ptr as *mut ViewState);
+    if !state.in_dead_key_sequence {
+      if !state.in_ime_preedit {
+        queue_ime_event(state, Ime::Enabled);
+      }
+      let text = util::ns_string_to_rust(characters);
+      // The selected range is the one being converted, or the caret when it's empty.
+      let start = utf16_to_byte_offset(&text, selected_range.location);
+      let end = utf16_to_byte_offset(&text, selected_range.location + selected_range.length);
+      let mut underlines = Vec::new();
+      for &(range, is_target) in &[
+        ((0, start), false),
+        ((start, end), true),
+        ((end, text.len()), false),
+      ] {
+        if range.0 < range.1 {
+          underlines.push(ImeUnderline { range, is_target });
+        }
+      }
+      let cursor = if text.is_empty() {
+        None
+      } else {
+        Some((start, end))
+      };
+      queue_ime_event(
+        state,
+        Ime::Preedit {
+          text,
+          cursor,
+          underlines,
+        },
+      );
+    }
     state.in_ime_preedit = true;
     state.key_triggered_ime = true;
   }
@@ -520,15 +694,15 @@ extern "C" fn first_rect_for_character_range(
     trace!("Triggered `firstRectForCharacterRange`");
     let state_ptr: *mut c_void = *this.get_ivar("taoState");
     let state = &mut *(state_ptr as *mut ViewState);
-    let (x, y) = state.ime_spot.unwrap_or_else(|| {
+    let rect = state.ime_cursor_area.unwrap_or_else(|| {
       let content_rect =
         NSWindow::contentRectForFrameRect_(state.ns_window, NSWindow::frame(state.ns_window));
       let x = content_rect.origin.x;
       let y = util::bottom_left_to_top_left(content_rect);
-      (x, y)
+      NSRect::new(NSPoint::new(x as _, y as _), NSSize::new(0.0, 0.0))
     });
     trace!("Completed `firstRectForCharacterRange`");
-    NSRect::new(NSPoint::new(x as _, y as _), NSSize::new(0.0, 0.0))
+    rect
   }
 }
 
@@ -558,21 +732,36 @@ extern "C" fn insert_text(this: &Object, _sel: Sel, string: id, _replacement_ran
     //let event: id = msg_send![NSApp(), currentEvent];
 
     // We only send the IME text input here. The text coming from the
-    // keyboard is handled by `key_down`
-    if state.in_ime_preedit {
-      AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
-        window_id: WindowId(get_window_id(state.ns_window)),
-        event: WindowEvent::ReceivedImeText(string),
-      }));
+    // keyboard is handled by `key_down`, like the one finishing a dead key sequence.
+    if state.in_ime_preedit && state.in_dead_key_sequence {
+      state.composed_text = Some(string);
+      state.in_ime_preedit = false;
+      state.key_triggered_ime = true;
+    } else if state.in_ime_preedit {
+      queue_ime_event(state, Ime::preedit(String::new()));
+      queue_ime_event(state, Ime::Commit(string));
+      queue_ime_event(state, Ime::Disabled);
       state.in_ime_preedit = false;
       state.key_triggered_ime = true;
+    } else if !state.interpreting_key {
+      // The text inserted without typing, e.g. from the character palette.
+      queue_ime_event(state, Ime::Enabled);
+      queue_ime_event(state, Ime::Commit(string));
+      queue_ime_event(state, Ime::Disabled);
     }
   }
   trace!("Completed `insertText`");
 }
 
-extern "C" fn do_command_by_selector(_this: &Object, _sel: Sel, _command: Sel) {
+extern "C" fn do_command_by_selector(this: &Object, _sel: Sel, command: Sel) {
   trace!("Triggered `doCommandBySelector`");
+  // The key bindings of the system give the commands of the focus traversal.
+  if command == sel!(insertTab:) {
+    focus_traversal(this, FocusDirection::Forward);
+  } else if command == sel!(insertBacktab:) {
+    focus_traversal(this, FocusDirection::Backward);
+  }
+
   // TODO: (Artur) all these inputs seem to trigger a key event with the correct text
   // content so this is not needed anymore, it seems.
 
@@ -677,6 +866,7 @@ fn update_potentially_stale_modifiers(state: &mut ViewState, event: id) {
     AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
       window_id: WindowId(get_window_id(state.ns_window)),
       event: WindowEvent::ModifiersChanged(state.modifiers),
+      timestamp: util::event_timestamp(event),
     }));
   }
 }
@@ -698,6 +888,12 @@ extern "C" fn key_down(this: &mut Object, _sel: Sel, event: id) {
     update_potentially_stale_modifiers(state, event);
 
     let pass_along = !is_repeat || !state.is_key_down;
+    let was_preediting = state.in_ime_preedit;
+    // The keys aren't interpreted while the IME isn't allowed, which also disables the dead keys.
+    let pass_along = pass_along && state.ime_allowed;
+    if !state.ime_allowed {
+      state.key_triggered_ime = false;
+    }
     if pass_along {
       // See below for why we do this.
       clear_marked_text(this);
@@ -707,12 +903,14 @@ extern "C" fn key_down(this: &mut Object, _sel: Sel, event: id) {
       // So, we don't give repeats the opportunity to trigger that, since otherwise our hack will cause some
       // keys to generate twice as many characters.
       let array: id = msg_send![class!(NSArray), arrayWithObject: event];
+      state.interpreting_key = true;
       let () = msg_send![this, interpretKeyEvents: array];
+      state.interpreting_key = false;
     }
     // The `interpretKeyEvents` above, may invoke `set_marked_text` or `insert_text`,
     // if the event corresponds to an IME event.
     let in_ime = state.key_triggered_ime;
-    let key_event = create_key_event(event, true, is_repeat, in_ime, None);
+    let mut key_event = create_key_event(event, true, is_repeat, in_ime, None);
     let is_arrow_key = is_arrow_key(key_event.physical_key);
     if pass_along {
       // The `interpretKeyEvents` above, may invoke `set_marked_text` or `insert_text`,
@@ -726,8 +924,27 @@ extern "C" fn key_down(this: &mut Object, _sel: Sel, event: id) {
         // In this case we should cancel the IME session.
         let () = msg_send![this, unmarkText];
         state.in_ime_preedit = false;
+        if !state.in_dead_key_sequence {
+          queue_ime_event(state, Ime::preedit(String::new()));
+          queue_ime_event(state, Ime::Disabled);
+        }
+      }
+      key_event.compose_state =
+        dead_key_compose_state(state, &key_event.logical_key, was_preediting);
+      let pending_ime_events = std::mem::take(&mut state.pending_ime_events);
+      if key_event.compose_state.is_some() {
+        key_event.text = state.composed_text.take().map(insert_or_get_key_str);
+      } else {
+        for ime in pending_ime_events {
+          queue_ime_event(state, ime);
+        }
       }
     }
+    let received_text = if state.text_input {
+      key_event.received_text(state.modifiers)
+    } else {
+      None
+    };
     let window_event = Event::WindowEvent {
       window_id,
       event: WindowEvent::KeyboardInput {
@@ -735,12 +952,54 @@ extern "C" fn key_down(this: &mut Object, _sel: Sel, event: id) {
         event: key_event,
         is_synthetic: false,
       },
+      timestamp: util::event_timestamp(event),
     };
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
+    if let Some(text) = received_text {
+      AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+        window_id,
+        event: WindowEvent::ReceivedText(text),
+        timestamp: util::event_timestamp(event),
+      }));
+    }
   }
   trace!("Completed `keyDown`");
 }
 
+/// Returns the part of a pressed key in a dead key sequence, after it was interpreted.
+fn dead_key_compose_state(
+  state: &mut ViewState,
+  logical_key: &Key<'_>,
+  was_preediting: bool,
+) -> Option<ComposeState> {
+  if let Key::Dead(_) = logical_key {
+    // The dead key marks its character, unless it's typed inside of an IME session.
+    if state.in_ime_preedit && (state.in_dead_key_sequence || !was_preediting) {
+      state.in_dead_key_sequence = true;
+      return Some(ComposeState::Composing);
+    }
+  }
+  if !state.in_dead_key_sequence {
+    return None;
+  }
+  match &state.composed_text {
+    Some(text) => {
+      state.in_dead_key_sequence = false;
+      // The dead keys are followed by the character of this key when they can't be combined.
+      Some(if text.chars().count() == 1 {
+        ComposeState::Composed
+      } else {
+        ComposeState::Cancelled
+      })
+    }
+    None if state.in_ime_preedit => Some(ComposeState::Composing),
+    None => {
+      state.in_dead_key_sequence = false;
+      Some(ComposeState::Cancelled)
+    }
+  }
+}
+
 extern "C" fn key_up(this: &Object, _sel: Sel, event: id) {
   trace!("Triggered `keyUp`");
   unsafe {
@@ -758,6 +1017,7 @@ extern "C" fn key_up(this: &Object, _sel: Sel, event: id) {
         event: create_key_event(event, false, false, false, None),
         is_synthetic: false,
       },
+      timestamp: util::event_timestamp(event),
     };
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
   }
@@ -879,12 +1139,14 @@ extern "C" fn flags_changed(this: &Object, _sel: Sel, ns_event: id) {
       AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
         window_id,
         event,
+        timestamp: util::event_timestamp(ns_event),
       }));
     }
 
     AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
       window_id,
       event: WindowEvent::ModifiersChanged(state.modifiers),
+      timestamp: util::event_timestamp(ns_event),
     }));
   }
   trace!("Completed `flagsChanged`");
@@ -912,6 +1174,19 @@ extern "C" fn insert_back_tab(this: &Object, _sel: Sel, _sender: id) {
   }
 }
 
+fn focus_traversal(this: &Object, direction: FocusDirection) {
+  unsafe {
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+    let event: id = msg_send![NSApp(), currentEvent];
+    AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+      window_id: WindowId(get_window_id(state.ns_window)),
+      event: WindowEvent::FocusTraversal(direction),
+      timestamp: util::event_timestamp(event),
+    }));
+  }
+}
+
 // Allows us to receive Cmd-. (the shortcut for closing a dialog)
 // https://bugs.eclipse.org/bugs/show_bug.cgi?id=300620#c6
 extern "C" fn cancel_operation(this: &Object, _sel: Sel, _sender: id) {
@@ -934,6 +1209,7 @@ extern "C" fn cancel_operation(this: &Object, _sel: Sel, _sender: id) {
         event: create_key_event(event, true, false, false, Some(key)),
         is_synthetic: false,
       },
+      timestamp: util::event_timestamp(event),
     };
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
   }
@@ -946,6 +1222,8 @@ fn mouse_click(this: &Object, event: id, button: MouseButton, button_state: Elem
     let state = &mut *(state_ptr as *mut ViewState);
 
     update_potentially_stale_modifiers(state, event);
+    // The releases have the count of their presses, which is 0 for the synthesized events.
+    let click_count: NSInteger = msg_send![event, clickCount];
 
     let window_event = Event::WindowEvent {
       window_id: WindowId(get_window_id(state.ns_window)),
@@ -953,22 +1231,86 @@ fn mouse_click(this: &Object, event: id, button: MouseButton, button_state: Elem
         device_id: DEVICE_ID,
         state: button_state,
         button,
+        click_count: click_count.max(1) as u32,
         modifiers: event_mods(event),
       },
+      timestamp: util::event_timestamp(event),
     };
 
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
   }
 }
 
+// Performs the native action of the drag region under the cursor, if any.
+// Returns whether the event was handled.
+fn drag_region_mouse_down(this: &Object, event: id) -> bool {
+  unsafe {
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+
+    if state.drag_regions.is_empty() {
+      return false;
+    }
+
+    let view: id = this as *const _ as *mut _;
+    let view_point = view.convertPoint_fromView_(event.locationInWindow(), nil);
+    let view_rect = NSView::frame(view);
+    let scale_factor = state.get_scale_factor();
+    let position = LogicalPosition::new(
+      view_point.x as f64,
+      view_rect.size.height as f64 - view_point.y as f64,
+    )
+    .to_physical(scale_factor);
+
+    let kind = state
+      .drag_regions
+      .iter()
+      .rev()
+      .find(|region| region.contains(position, scale_factor))
+      .map(|region| region.kind);
+    match kind {
+      Some(DragRegionKind::Caption) => {
+        let () = msg_send![state.ns_window, performWindowDragWithEvent: event];
+      }
+      Some(DragRegionKind::MinimizeButton) => {
+        let () = msg_send![state.ns_window, miniaturize: nil];
+      }
+      Some(DragRegionKind::MaximizeButton) => {
+        let () = msg_send![state.ns_window, zoom: nil];
+      }
+      Some(DragRegionKind::CloseButton) => {
+        let window_id = WindowId(get_window_id(state.ns_window));
+        AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+          window_id,
+          event: WindowEvent::CloseRequested {
+            response: CloseResponse::new(window_id, CloseHandle::new(state.ns_window)),
+          },
+          timestamp: util::event_timestamp(event),
+        }));
+      }
+      // A resize can't be started from the content view, the native borders handle it.
+      Some(DragRegionKind::Resize(_)) | None => return false,
+    }
+
+    true
+  }
+}
+
 extern "C" fn mouse_down(this: &Object, _sel: Sel, event: id) {
+  if drag_region_mouse_down(this, event) {
+    return;
+  }
   mouse_motion(this, event);
   mouse_click(this, event, MouseButton::Left, ElementState::Pressed);
+  pen_input(this, event, TouchPhase::Started);
 }
 
 extern "C" fn mouse_up(this: &Object, _sel: Sel, event: id) {
   mouse_motion(this, event);
   mouse_click(this, event, MouseButton::Left, ElementState::Released);
+  pen_input(this, event, TouchPhase::Ended);
+  // The pressure events may stop before the touchpad is fully released on quick clicks.
+  send_touchpad_pressure(this, event, 0.0, 0);
 }
 
 extern "C" fn right_mouse_down(this: &Object, _sel: Sel, event: id) {
@@ -983,12 +1325,25 @@ extern "C" fn right_mouse_up(this: &Object, _sel: Sel, event: id) {
 
 extern "C" fn other_mouse_down(this: &Object, _sel: Sel, event: id) {
   mouse_motion(this, event);
-  mouse_click(this, event, MouseButton::Middle, ElementState::Pressed);
+  let button = other_mouse_button(event);
+  mouse_click(this, event, button, ElementState::Pressed);
 }
 
 extern "C" fn other_mouse_up(this: &Object, _sel: Sel, event: id) {
   mouse_motion(this, event);
-  mouse_click(this, event, MouseButton::Middle, ElementState::Released);
+  let button = other_mouse_button(event);
+  mouse_click(this, event, button, ElementState::Released);
+}
+
+/// The button of `otherMouseDown:` and `otherMouseUp:`, which are sent for the buttons after the
+/// left and right ones.
+fn other_mouse_button(event: id) -> MouseButton {
+  match unsafe { event.buttonNumber() } {
+    2 => MouseButton::Middle,
+    3 => MouseButton::Back,
+    4 => MouseButton::Forward,
+    button => MouseButton::Other(button as u16),
+  }
 }
 
 fn mouse_motion(this: &Object, event: id) {
@@ -1009,8 +1364,9 @@ fn mouse_motion(this: &Object, event: id) {
       || view_point.y > view_rect.size.height
     {
       let mouse_buttons_down: NSInteger = msg_send![class!(NSEvent), pressedMouseButtons];
-      if mouse_buttons_down == 0 {
-        // Point is outside of the client area (view) and no buttons are pressed
+      if mouse_buttons_down == 0 && !state.mouse_captured {
+        // Point is outside of the client area (view), no buttons are pressed and the mouse isn't
+        // captured
         return;
       }
     }
@@ -1028,6 +1384,7 @@ fn mouse_motion(this: &Object, event: id) {
         position: logical_position.to_physical(state.get_scale_factor()),
         modifiers: event_mods(event),
       },
+      timestamp: util::event_timestamp(event),
     };
 
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
@@ -1036,10 +1393,127 @@ fn mouse_motion(this: &Object, event: id) {
 
 extern "C" fn mouse_moved(this: &Object, _sel: Sel, event: id) {
   mouse_motion(this, event);
+  pen_hover(this, event);
 }
 
 extern "C" fn mouse_dragged(this: &Object, _sel: Sel, event: id) {
   mouse_motion(this, event);
+  pen_input(this, event, TouchPhase::Moved);
+}
+
+// `NSTabletPointEventSubtype`, the subtype of the mouse events sent by a tablet.
+const TABLET_POINT_EVENT_SUBTYPE: i16 = 1;
+// `NSEraserPointingDevice`
+const ERASER_POINTING_DEVICE: NSUInteger = 3;
+// `NSPenLowerSideMask | NSPenUpperSideMask`
+const PEN_SIDE_BUTTONS_MASK: NSUInteger = 2 | 4;
+
+extern "C" fn tablet_proximity(this: &Object, _sel: Sel, event: id) {
+  trace!("Triggered `tabletProximity`");
+  unsafe {
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+
+    let entering: BOOL = msg_send![event, isEnteringProximity];
+    let device_type: NSUInteger = msg_send![event, pointingDeviceType];
+    state.pen_inverted = entering == YES && device_type == ERASER_POINTING_DEVICE;
+
+    let window_event = Event::WindowEvent {
+      window_id: WindowId(get_window_id(state.ns_window)),
+      event: WindowEvent::PenProximity {
+        device_id: DEVICE_ID,
+        in_range: entering == YES,
+      },
+      timestamp: util::event_timestamp(event),
+    };
+    AppState::queue_event(EventWrapper::StaticEvent(window_event));
+  }
+  trace!("Completed `tabletProximity`");
+}
+
+// Sends `PenInput` for the mouse events coming from a tablet.
+fn pen_input(this: &Object, event: id, phase: TouchPhase) {
+  unsafe {
+    let subtype: i16 = msg_send![event, subtype];
+    if subtype != TABLET_POINT_EVENT_SUBTYPE {
+      return;
+    }
+
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+    let view: id = this as *const _ as *mut _;
+
+    let window_point = event.locationInWindow();
+    let view_point = view.convertPoint_fromView_(window_point, nil);
+    let view_rect = NSView::frame(view);
+    let location = LogicalPosition::new(
+      view_point.x as f64,
+      view_rect.size.height as f64 - view_point.y as f64,
+    );
+
+    // The tilt goes from -1 to 1, and the rotation is counterclockwise.
+    let tilt = event.tilt();
+    let rotation: f32 = msg_send![event, rotation];
+    let buttons: NSUInteger = msg_send![event, buttonMask];
+
+    let window_event = Event::WindowEvent {
+      window_id: WindowId(get_window_id(state.ns_window)),
+      event: WindowEvent::PenInput(PenInput {
+        device_id: DEVICE_ID,
+        phase,
+        location: location.to_physical(state.get_scale_factor()),
+        pressure: Some(event.pressure() as f64),
+        tilt: Some((tilt.x as f64 * 90.0, tilt.y as f64 * 90.0)),
+        twist: Some((360.0 - rotation as f64) % 360.0),
+        barrel_button: buttons & PEN_SIDE_BUTTONS_MASK != 0,
+        inverted: state.pen_inverted,
+        eraser: state.pen_inverted,
+      }),
+      timestamp: util::event_timestamp(event),
+    };
+
+    AppState::queue_event(EventWrapper::StaticEvent(window_event));
+  }
+}
+
+// Sends `PenHover` for the mouse motion coming from a tablet, while the pen doesn't touch it.
+fn pen_hover(this: &Object, event: id) {
+  unsafe {
+    let subtype: i16 = msg_send![event, subtype];
+    if subtype != TABLET_POINT_EVENT_SUBTYPE {
+      return;
+    }
+
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+    let view: id = this as *const _ as *mut _;
+
+    let window_point = event.locationInWindow();
+    let view_point = view.convertPoint_fromView_(window_point, nil);
+    let view_rect = NSView::frame(view);
+    let location = LogicalPosition::new(
+      view_point.x as f64,
+      view_rect.size.height as f64 - view_point.y as f64,
+    );
+
+    let tilt = event.tilt();
+    let buttons: NSUInteger = msg_send![event, buttonMask];
+
+    let window_event = Event::WindowEvent {
+      window_id: WindowId(get_window_id(state.ns_window)),
+      event: WindowEvent::PenHover(PenHover {
+        device_id: DEVICE_ID,
+        location: location.to_physical(state.get_scale_factor()),
+        distance: None,
+        tilt: Some((tilt.x as f64 * 90.0, tilt.y as f64 * 90.0)),
+        barrel_button: buttons & PEN_SIDE_BUTTONS_MASK != 0,
+        inverted: state.pen_inverted,
+      }),
+      timestamp: util::event_timestamp(event),
+    };
+
+    AppState::queue_event(EventWrapper::StaticEvent(window_event));
+  }
 }
 
 extern "C" fn right_mouse_dragged(this: &Object, _sel: Sel, event: id) {
@@ -1050,7 +1524,7 @@ extern "C" fn other_mouse_dragged(this: &Object, _sel: Sel, event: id) {
   mouse_motion(this, event);
 }
 
-extern "C" fn mouse_entered(this: &Object, _sel: Sel, _event: id) {
+extern "C" fn mouse_entered(this: &Object, _sel: Sel, event: id) {
   trace!("Triggered `mouseEntered`");
   unsafe {
     let state_ptr: *mut c_void = *this.get_ivar("taoState");
@@ -1061,6 +1535,7 @@ extern "C" fn mouse_entered(this: &Object, _sel: Sel, _event: id) {
       event: WindowEvent::CursorEntered {
         device_id: DEVICE_ID,
       },
+      timestamp: util::event_timestamp(event),
     };
 
     AppState::queue_event(EventWrapper::StaticEvent(enter_event));
@@ -1068,7 +1543,7 @@ extern "C" fn mouse_entered(this: &Object, _sel: Sel, _event: id) {
   trace!("Completed `mouseEntered`");
 }
 
-extern "C" fn mouse_exited(this: &Object, _sel: Sel, _event: id) {
+extern "C" fn mouse_exited(this: &Object, _sel: Sel, event: id) {
   trace!("Triggered `mouseExited`");
   unsafe {
     let state_ptr: *mut c_void = *this.get_ivar("taoState");
@@ -1079,6 +1554,7 @@ extern "C" fn mouse_exited(this: &Object, _sel: Sel, _event: id) {
       event: WindowEvent::CursorLeft {
         device_id: DEVICE_ID,
       },
+      timestamp: util::event_timestamp(event),
     };
 
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
@@ -1105,15 +1581,25 @@ extern "C" fn scroll_wheel(this: &Object, _sel: Sel, event: id) {
         MouseScrollDelta::LineDelta(x as f32, y as f32)
       }
     };
-    let phase = match event.phase() {
+    // The momentum has its own phase, the phase of the scroll being none meanwhile.
+    let momentum_phase = event.momentumPhase();
+    let momentum = momentum_phase != NSEventPhase::NSEventPhaseNone;
+    let ns_phase = if momentum {
+      momentum_phase
+    } else {
+      event.phase()
+    };
+    let phase = match ns_phase {
       NSEventPhase::NSEventPhaseMayBegin | NSEventPhase::NSEventPhaseBegan => TouchPhase::Started,
       NSEventPhase::NSEventPhaseEnded => TouchPhase::Ended,
+      NSEventPhase::NSEventPhaseCancelled => TouchPhase::Cancelled,
       _ => TouchPhase::Moved,
     };
 
     let device_event = Event::DeviceEvent {
       device_id: DEVICE_ID,
       event: DeviceEvent::MouseWheel { delta },
+      timestamp: util::event_timestamp(event),
     };
 
     let state_ptr: *mut c_void = *this.get_ivar("taoState");
@@ -1127,8 +1613,10 @@ extern "C" fn scroll_wheel(this: &Object, _sel: Sel, event: id) {
         device_id: DEVICE_ID,
         delta,
         phase,
+        momentum,
         modifiers: event_mods(event),
       },
+      timestamp: util::event_timestamp(event),
     };
 
     AppState::queue_event(EventWrapper::StaticEvent(device_event));
@@ -1142,12 +1630,24 @@ extern "C" fn pressure_change_with_event(this: &Object, _sel: Sel, event: id) {
 
   mouse_motion(this, event);
 
+  let (pressure, stage) = unsafe { (event.pressure(), event.stage()) };
+  send_touchpad_pressure(this, event, pressure, stage);
+  trace!("Completed `pressureChangeWithEvent`");
+}
+
+/// Sends `TouchpadPressure` with the pressure clamped between 0 and 1 and the stage between 0 and
+/// 2, unless they didn't change since the last one.
+fn send_touchpad_pressure(this: &Object, event: id, pressure: f32, stage: i64) {
   unsafe {
     let state_ptr: *mut c_void = *this.get_ivar("taoState");
     let state = &mut *(state_ptr as *mut ViewState);
 
-    let pressure = event.pressure();
-    let stage = event.stage();
+    let pressure = pressure.max(0.0).min(1.0);
+    let stage = stage.max(0).min(2);
+    if state.touchpad_pressure == (pressure, stage) {
+      return;
+    }
+    state.touchpad_pressure = (pressure, stage);
 
     let window_event = Event::WindowEvent {
       window_id: WindowId(get_window_id(state.ns_window)),
@@ -1156,11 +1656,95 @@ extern "C" fn pressure_change_with_event(this: &Object, _sel: Sel, event: id) {
         pressure,
         stage,
       },
+      timestamp: util::event_timestamp(event),
     };
 
     AppState::queue_event(EventWrapper::StaticEvent(window_event));
   }
-  trace!("Completed `pressureChangeWithEvent`");
+}
+
+/// The phase of a magnify or rotate gesture, `None` for the events that aren't part of one.
+fn gesture_phase(event: id) -> Option<TouchPhase> {
+  match unsafe { event.phase() } {
+    NSEventPhase::NSEventPhaseBegan => Some(TouchPhase::Started),
+    NSEventPhase::NSEventPhaseChanged => Some(TouchPhase::Moved),
+    NSEventPhase::NSEventPhaseEnded => Some(TouchPhase::Ended),
+    NSEventPhase::NSEventPhaseCancelled => Some(TouchPhase::Cancelled),
+    _ => None,
+  }
+}
+
+extern "C" fn magnify_with_event(this: &Object, _sel: Sel, event: id) {
+  trace!("Triggered `magnifyWithEvent`");
+
+  unsafe {
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+
+    if let Some(phase) = gesture_phase(event) {
+      let window_event = Event::WindowEvent {
+        window_id: WindowId(get_window_id(state.ns_window)),
+        event: WindowEvent::PinchGesture {
+          device_id: DEVICE_ID,
+          delta: event.magnification(),
+          phase,
+        },
+        timestamp: util::event_timestamp(event),
+      };
+
+      AppState::queue_event(EventWrapper::StaticEvent(window_event));
+    }
+  }
+  trace!("Completed `magnifyWithEvent`");
+}
+
+extern "C" fn rotate_with_event(this: &Object, _sel: Sel, event: id) {
+  trace!("Triggered `rotateWithEvent`");
+
+  unsafe {
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+
+    if let Some(phase) = gesture_phase(event) {
+      let window_event = Event::WindowEvent {
+        window_id: WindowId(get_window_id(state.ns_window)),
+        event: WindowEvent::RotationGesture {
+          device_id: DEVICE_ID,
+          delta: event.rotation(),
+          phase,
+        },
+        timestamp: util::event_timestamp(event),
+      };
+
+      AppState::queue_event(EventWrapper::StaticEvent(window_event));
+    }
+  }
+  trace!("Completed `rotateWithEvent`");
+}
+
+extern "C" fn swipe_with_event(this: &Object, _sel: Sel, event: id) {
+  trace!("Triggered `swipeWithEvent`");
+
+  unsafe {
+    let state_ptr: *mut c_void = *this.get_ivar("taoState");
+    let state = &mut *(state_ptr as *mut ViewState);
+
+    // macOS gives positive deltas for the swipes to the left and up, the inverse of tao.
+    let delta = PhysicalPosition::new(-event.deltaX(), -event.deltaY());
+
+    let window_event = Event::WindowEvent {
+      window_id: WindowId(get_window_id(state.ns_window)),
+      event: WindowEvent::SwipeGesture {
+        device_id: DEVICE_ID,
+        delta,
+        phase: TouchPhase::Ended,
+      },
+      timestamp: util::event_timestamp(event),
+    };
+
+    AppState::queue_event(EventWrapper::StaticEvent(window_event));
+  }
+  trace!("Completed `swipeWithEvent`");
 }
 
 // Allows us to receive Ctrl-Tab and Ctrl-Esc.
@@ -0,0 +1,101 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The redraws of the windows with `RedrawPacing::VSync` are queued by the callback of a display
+// link, which is called on its own thread at each refresh of the active displays. The display
+// link only runs while there are redraws to queue.
+use std::{ffi::c_void, ptr, sync::Mutex};
+
+use core_video_sys::{kCVReturnSuccess, CVDisplayLinkRef, CVOptionFlags, CVReturn, CVTimeStamp};
+use dispatch::Queue;
+
+use crate::{platform_impl::platform::app_state::AppState, window::WindowId};
+
+type CVDisplayLinkOutputCallback = unsafe extern "C" fn(
+  display_link: CVDisplayLinkRef,
+  in_now: *const CVTimeStamp,
+  in_output_time: *const CVTimeStamp,
+  flags_in: CVOptionFlags,
+  flags_out: *mut CVOptionFlags,
+  display_link_context: *mut c_void,
+) -> CVReturn;
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern "C" {
+  fn CVDisplayLinkCreateWithActiveCGDisplays(display_link_out: *mut CVDisplayLinkRef) -> CVReturn;
+  fn CVDisplayLinkSetOutputCallback(
+    display_link: CVDisplayLinkRef,
+    callback: CVDisplayLinkOutputCallback,
+    user_info: *mut c_void,
+  ) -> CVReturn;
+  fn CVDisplayLinkStart(display_link: CVDisplayLinkRef) -> CVReturn;
+  fn CVDisplayLinkStop(display_link: CVDisplayLinkRef) -> CVReturn;
+  fn CVDisplayLinkIsRunning(display_link: CVDisplayLinkRef) -> u8;
+}
+
+struct Pacer {
+  /// Created with the first request, and never released.
+  display_link: CVDisplayLinkRef,
+  /// The windows to redraw at the next refresh.
+  windows: Vec<WindowId>,
+}
+
+// The display link is thread-safe.
+unsafe impl Send for Pacer {}
+
+lazy_static! {
+  static ref PACER: Mutex<Pacer> = Mutex::new(Pacer {
+    display_link: ptr::null_mut(),
+    windows: Vec::new(),
+  });
+}
+
+/// Redraws `window_id` at the next refresh of the displays. Can be called from any thread.
+///
+/// Returns `false` if the display link couldn't be created, and the redraw wasn't queued.
+pub fn request_redraw(window_id: WindowId) -> bool {
+  let mut pacer = PACER.lock().unwrap();
+  unsafe {
+    if pacer.display_link.is_null() {
+      let mut display_link = ptr::null_mut();
+      if CVDisplayLinkCreateWithActiveCGDisplays(&mut display_link) != kCVReturnSuccess {
+        warn!("Failed to create the display link of the vsync redraws");
+        return false;
+      }
+      CVDisplayLinkSetOutputCallback(display_link, output_callback, ptr::null_mut());
+      pacer.display_link = display_link;
+    }
+    if CVDisplayLinkIsRunning(pacer.display_link) == 0 {
+      CVDisplayLinkStart(pacer.display_link);
+    }
+  }
+  if !pacer.windows.contains(&window_id) {
+    pacer.windows.push(window_id);
+  }
+  true
+}
+
+unsafe extern "C" fn output_callback(
+  _display_link: CVDisplayLinkRef,
+  _in_now: *const CVTimeStamp,
+  _in_output_time: *const CVTimeStamp,
+  _flags_in: CVOptionFlags,
+  _flags_out: *mut CVOptionFlags,
+  _display_link_context: *mut c_void,
+) -> CVReturn {
+  let windows = std::mem::take(&mut PACER.lock().unwrap().windows);
+  if windows.is_empty() {
+    // Stopping the display link from its own callback can deadlock, and a redraw may be requested
+    // again before the stop runs on the main thread.
+    Queue::main().exec_async(|| {
+      let pacer = PACER.lock().unwrap();
+      if pacer.windows.is_empty() {
+        CVDisplayLinkStop(pacer.display_link);
+      }
+    });
+  }
+  for window_id in windows {
+    AppState::queue_redraw(window_id);
+  }
+  kCVReturnSuccess
+}
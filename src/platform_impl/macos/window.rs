@@ -10,6 +10,7 @@ use std::{
     atomic::{AtomicBool, Ordering},
     Arc, Mutex, Weak,
   },
+  time::{Duration, Instant},
 };
 
 use crate::{
@@ -17,30 +18,46 @@ use crate::{
     LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position, Size, Size::Logical,
   },
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
+  event::{Event, WindowEvent},
   icon::Icon,
   monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
-  platform::macos::WindowExtMacOS,
+  platform::macos::{
+    NSVisualEffectBlendingMode, NSVisualEffectMaterial, NSVisualEffectState, WindowExtMacOS,
+  },
   platform_impl::platform::{
     app_state::{AppState, INTERRUPT_EVENT_LOOP_EXIT},
-    ffi, menu,
+    effects::{self, VisualEffect},
+    event::EventWrapper,
+    ffi, keyboard_grab, menu,
     monitor::{self, MonitorHandle, VideoMode},
+    progress_bar,
     util::{self, IdRef},
     view::{self, new_view, CursorState},
+    vsync,
     window_delegate::new_delegate,
-    OsError,
+    OsError, VirtualDesktop,
+  },
+  window::{
+    AspectRatio, Color, CursorGrabMode, CursorIcon, DragRegion, Edges, Effect, Fullscreen,
+    ProgressState, RedrawPacing, Theme, TitlebarButtons, UserAttentionType, WindowAttributes,
+    WindowId as RootWindowId, WindowImage, WindowLevel, WindowType,
   },
-  window::{CursorIcon, Fullscreen, UserAttentionType, WindowAttributes, WindowId as RootWindowId},
 };
 use cocoa::{
   appkit::{
-    self, CGFloat, NSApp, NSApplication, NSApplicationPresentationOptions, NSColor,
-    NSRequestUserAttentionType, NSScreen, NSView, NSWindow, NSWindowButton, NSWindowOrderingMode,
-    NSWindowStyleMask,
+    self, CGFloat, NSApp, NSApplication, NSApplicationPresentationOptions, NSColor, NSEvent,
+    NSEventType, NSScreen, NSView, NSWindow, NSWindowButton, NSWindowCollectionBehavior,
+    NSWindowOrderingMode, NSWindowStyleMask,
   },
   base::{id, nil},
-  foundation::{NSAutoreleasePool, NSDictionary, NSPoint, NSRect, NSSize},
+  foundation::{NSAutoreleasePool, NSDictionary, NSInteger, NSPoint, NSRect, NSSize, NSUInteger},
+};
+use core_graphics::{
+  display::{CGDisplay, CGDisplayMode},
+  geometry::{CGPoint, CGRect, CGSize},
+  window::{self as cg_window, CGWindowID},
 };
-use core_graphics::display::{CGDisplay, CGDisplayMode};
+use dispatch::Queue;
 use objc::{
   declare::ClassDecl,
   runtime::{Class, Object, Sel, BOOL, NO, YES},
@@ -63,6 +80,24 @@ pub fn get_window_id(window_cocoa_id: id) -> Id {
   Id(window_cocoa_id as *const Object as _)
 }
 
+/// Closes a window from any thread once its `CloseResponse` is allowed.
+#[derive(Clone)]
+pub struct CloseHandle(Arc<IdRef>);
+
+unsafe impl Send for CloseHandle {}
+unsafe impl Sync for CloseHandle {}
+
+impl CloseHandle {
+  pub fn new(ns_window: id) -> Self {
+    CloseHandle(Arc::new(IdRef::retain(ns_window)))
+  }
+
+  pub fn close(&self) {
+    // `close` doesn't go through `windowShouldClose:`, so the request isn't sent again.
+    unsafe { util::close_async(IdRef::retain(**self.0)) };
+  }
+}
+
 #[non_exhaustive]
 #[derive(Clone)]
 pub enum Parent {
@@ -82,6 +117,9 @@ pub struct PlatformSpecificWindowBuilderAttributes {
   pub resize_increments: Option<LogicalSize<f64>>,
   pub disallow_hidpi: bool,
   pub has_shadow: bool,
+  pub vibrancy: Option<VisualEffect>,
+  pub traffic_light_inset: Option<LogicalPosition<f64>>,
+  pub tabbing_identifier: Option<String>,
 }
 
 impl Default for PlatformSpecificWindowBuilderAttributes {
@@ -98,6 +136,9 @@ impl Default for PlatformSpecificWindowBuilderAttributes {
       resize_increments: None,
       disallow_hidpi: false,
       has_shadow: true,
+      vibrancy: None,
+      traffic_light_inset: None,
+      tabbing_identifier: None,
     }
   }
 }
@@ -190,7 +231,29 @@ fn create_window(
       masks |= NSWindowStyleMask::NSFullSizeContentViewWindowMask;
     }
 
-    let ns_window: id = msg_send![WINDOW_CLASS.0, alloc];
+    // Only panels can be non-activating, other windows activate the app when clicked.
+    let is_panel = !attrs.focusable
+      || matches!(
+        attrs.window_type,
+        WindowType::Utility | WindowType::Tooltip | WindowType::Popup
+      );
+    if !attrs.focusable {
+      // `NSWindowStyleMaskNonactivatingPanel`
+      masks |= NSWindowStyleMask::from_bits_unchecked(1 << 7);
+    }
+    if attrs.window_type == WindowType::Utility
+      && masks.contains(NSWindowStyleMask::NSTitledWindowMask)
+    {
+      // `NSWindowStyleMaskUtilityWindow`, which gives panels a thin titlebar
+      masks |= NSWindowStyleMask::from_bits_unchecked(1 << 4);
+    }
+
+    let window_class = if is_panel {
+      PANEL_CLASS.0
+    } else {
+      WINDOW_CLASS.0
+    };
+    let ns_window: id = msg_send![window_class, alloc];
     let ns_window = IdRef::new(ns_window.initWithContentRect_styleMask_backing_defer_(
       frame,
       masks,
@@ -200,6 +263,12 @@ fn create_window(
     let res = ns_window.non_nil().map(|ns_window| {
       let title = util::ns_string_id_ref(&attrs.title);
       ns_window.setReleasedWhenClosed_(NO);
+      if !attrs.focusable {
+        (**ns_window).set_ivar::<BOOL>("taoUnfocusable", YES);
+      }
+      if attrs.minimize_to_tray {
+        (**ns_window).set_ivar::<BOOL>("taoMinimizeToTray", YES);
+      }
       ns_window.setTitle_(*title);
       ns_window.setAcceptsMouseMovedEvents_(YES);
 
@@ -220,15 +289,33 @@ fn create_window(
           let _: () = msg_send![button, setHidden: YES];
         }
       }
+      if attrs.titlebar_buttons != TitlebarButtons::all() {
+        util::set_titlebar_buttons(*ns_window, attrs.titlebar_buttons);
+      }
       if pl_attrs.movable_by_window_background {
         ns_window.setMovableByWindowBackground_(YES);
       }
+      if let Some(identifier) = &pl_attrs.tabbing_identifier {
+        set_tabbing_identifier(*ns_window, identifier);
+      }
+
+      let level = match attrs.window_type {
+        WindowType::Utility | WindowType::Splash => Some(ffi::NSWindowLevel::NSFloatingWindowLevel),
+        WindowType::Tooltip => Some(ffi::NSWindowLevel::NSStatusWindowLevel),
+        WindowType::Popup => Some(ffi::NSWindowLevel::NSPopUpMenuWindowLevel),
+        WindowType::Normal => None,
+      };
+      if let Some(level) = level {
+        let () = msg_send![*ns_window, setLevel: level];
+        // Auxiliary windows are left out of the window cycle
+        ns_window.setCollectionBehavior_(
+          ns_window.collectionBehavior()
+            | NSWindowCollectionBehavior::NSWindowCollectionBehaviorIgnoresCycle,
+        );
+      }
 
-      if attrs.always_on_top {
-        let _: () = msg_send![
-          *ns_window,
-          setLevel: ffi::NSWindowLevel::NSFloatingWindowLevel
-        ];
+      if attrs.window_level != WindowLevel::Normal {
+        let _: () = msg_send![*ns_window, setLevel: ns_window_level(attrs.window_level)];
       }
 
       if let Some(increments) = pl_attrs.resize_increments {
@@ -239,8 +326,19 @@ fn create_window(
         }
       }
 
-      if let Parent::ChildOf(parent) = pl_attrs.parent {
-        let _: () = msg_send![parent as id, addChildWindow: *ns_window ordered: NSWindowOrderingMode::NSWindowAbove];
+      let foreign_parent = attrs.foreign_parent.and_then(|parent| foreign_parent_window(parent));
+      match (&pl_attrs.parent, foreign_parent, attrs.parent_window) {
+        (Parent::ChildOf(parent), _, _) => {
+          let _: () = msg_send![*parent as id, addChildWindow: *ns_window ordered: NSWindowOrderingMode::NSWindowAbove];
+        }
+        (Parent::None, Some(parent), _) => {
+          let _: () = msg_send![parent, addChildWindow: *ns_window ordered: NSWindowOrderingMode::NSWindowAbove];
+        }
+        // Modal windows are attached to their parent as a sheet instead
+        (Parent::None, None, Some(parent)) if !attrs.modal => {
+          let _: () = msg_send![(parent.0).0 as id, addChildWindow: *ns_window ordered: NSWindowOrderingMode::NSWindowAbove];
+        }
+        _ => (),
       }
 
       if !pl_attrs.has_shadow {
@@ -269,19 +367,231 @@ lazy_static! {
     let mut decl = ClassDecl::new("TaoWindow", window_superclass).unwrap();
     decl.add_method(
       sel!(canBecomeMainWindow),
-      util::yes as extern "C" fn(&Object, Sel) -> BOOL,
+      can_become_key_window as extern "C" fn(&Object, Sel) -> BOOL,
+    );
+    decl.add_method(
+      sel!(canBecomeKeyWindow),
+      can_become_key_window as extern "C" fn(&Object, Sel) -> BOOL,
+    );
+    decl.add_method(
+      sel!(sendEvent:),
+      send_event as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(performMiniaturize:),
+      perform_miniaturize as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(respondsToSelector:),
+      responds_to_selector as extern "C" fn(&Object, Sel, Sel) -> BOOL,
+    );
+    decl.add_method(
+      sel!(newWindowForTab:),
+      new_window_for_tab as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(animationResizeTime:),
+      animation_resize_time as extern "C" fn(&Object, Sel, NSRect) -> f64,
+    );
+    decl.add_ivar::<BOOL>("taoDisabled");
+    decl.add_ivar::<BOOL>("taoUnfocusable");
+    decl.add_ivar::<BOOL>("taoMinimizeToTray");
+    decl.add_ivar::<BOOL>("taoNewTab");
+    decl.add_ivar::<BOOL>("taoNoAnimations");
+    WindowClass(decl.register())
+  };
+  static ref PANEL_CLASS: WindowClass = unsafe {
+    let panel_superclass = class!(NSPanel);
+    let mut decl = ClassDecl::new("TaoPanel", panel_superclass).unwrap();
+    decl.add_method(
+      sel!(canBecomeMainWindow),
+      can_become_key_window as extern "C" fn(&Object, Sel) -> BOOL,
     );
     decl.add_method(
       sel!(canBecomeKeyWindow),
-      util::yes as extern "C" fn(&Object, Sel) -> BOOL,
+      can_become_key_window as extern "C" fn(&Object, Sel) -> BOOL,
+    );
+    decl.add_method(
+      sel!(sendEvent:),
+      send_event as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(performMiniaturize:),
+      perform_miniaturize as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(respondsToSelector:),
+      responds_to_selector as extern "C" fn(&Object, Sel, Sel) -> BOOL,
     );
+    decl.add_method(
+      sel!(newWindowForTab:),
+      new_window_for_tab as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(animationResizeTime:),
+      animation_resize_time as extern "C" fn(&Object, Sel, NSRect) -> f64,
+    );
+    decl.add_ivar::<BOOL>("taoDisabled");
+    decl.add_ivar::<BOOL>("taoUnfocusable");
+    decl.add_ivar::<BOOL>("taoMinimizeToTray");
+    decl.add_ivar::<BOOL>("taoNewTab");
+    decl.add_ivar::<BOOL>("taoNoAnimations");
     WindowClass(decl.register())
   };
 }
 
+// Windows created with `WindowBuilder::with_focusable(false)` never become key.
+extern "C" fn can_become_key_window(this: &Object, _: Sel) -> BOOL {
+  unsafe {
+    let unfocusable: BOOL = *this.get_ivar("taoUnfocusable");
+    if unfocusable == YES {
+      NO
+    } else {
+      YES
+    }
+  }
+}
+
+// Drops the input events of windows disabled with `Window::set_enabled`.
+extern "C" fn send_event(this: &Object, _: Sel, event: id) {
+  unsafe {
+    let disabled: BOOL = *this.get_ivar("taoDisabled");
+    if disabled == YES && is_input_event(event) {
+      return;
+    }
+
+    let superclass = util::superclass(this);
+    let () = msg_send![super(this, superclass), sendEvent: event];
+  }
+}
+
+// The minimize button and the "Minimize" menu item go through `performMiniaturize:`, unlike
+// `Window::set_minimized`, so only the requests of the user are sent to the tray.
+extern "C" fn perform_miniaturize(this: &Object, _: Sel, sender: id) {
+  unsafe {
+    let minimize_to_tray: BOOL = *this.get_ivar("taoMinimizeToTray");
+    if minimize_to_tray == YES {
+      let () = msg_send![this, orderOut: nil];
+      AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+        window_id: RootWindowId(get_window_id(this as *const Object as id)),
+        event: WindowEvent::MinimizedToTray,
+        timestamp: Instant::now(),
+      }));
+      return;
+    }
+
+    let superclass = util::superclass(this);
+    let () = msg_send![super(this, superclass), performMiniaturize: sender];
+  }
+}
+
+// The tab bar only shows its "+" button when the window responds to `newWindowForTab:`, so it's
+// hidden for the windows that didn't opt into tabbing with an identifier.
+extern "C" fn responds_to_selector(this: &Object, _: Sel, selector: Sel) -> BOOL {
+  unsafe {
+    if selector == sel!(newWindowForTab:) {
+      return *this.get_ivar("taoNewTab");
+    }
+
+    let superclass = util::superclass(this);
+    msg_send![super(this, superclass), respondsToSelector: selector]
+  }
+}
+
+extern "C" fn new_window_for_tab(this: &Object, _: Sel, _sender: id) {
+  AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+    window_id: RootWindowId(get_window_id(this as *const Object as id)),
+    event: WindowEvent::NewTabRequested,
+    timestamp: Instant::now(),
+  }));
+}
+
+// `zoom:` and the other animated frame changes take as long as this returns, so returning zero
+// makes them immediate for the windows that disabled their animations.
+extern "C" fn animation_resize_time(this: &Object, _: Sel, new_frame: NSRect) -> f64 {
+  unsafe {
+    let no_animations: BOOL = *this.get_ivar("taoNoAnimations");
+    if no_animations == YES {
+      return 0.0;
+    }
+
+    let superclass = util::superclass(this);
+    msg_send![super(this, superclass), animationResizeTime: new_frame]
+  }
+}
+
+unsafe fn set_tabbing_identifier(ns_window: id, identifier: &str) {
+  let identifier = util::ns_string_id_ref(identifier);
+  let () = msg_send![ns_window, setTabbingIdentifier: *identifier];
+  (*ns_window).set_ivar::<BOOL>("taoNewTab", YES);
+}
+
+// The `NSWindow` of a handle from another toolkit, which may only have set its `NSView`.
+unsafe fn foreign_parent_window(handle: RawWindowHandle) -> Option<id> {
+  match handle {
+    RawWindowHandle::MacOS(handle) if !handle.ns_window.is_null() => Some(handle.ns_window as id),
+    RawWindowHandle::MacOS(handle) if !handle.ns_view.is_null() => {
+      let ns_window: id = msg_send![handle.ns_view as id, window];
+      if ns_window == nil {
+        None
+      } else {
+        Some(ns_window)
+      }
+    }
+    _ => None,
+  }
+}
+
+fn ns_window_level(level: WindowLevel) -> ffi::NSWindowLevel {
+  match level {
+    WindowLevel::AlwaysOnBottom => ffi::NSWindowLevel::BelowNormalWindowLevel,
+    WindowLevel::Normal => ffi::NSWindowLevel::NSNormalWindowLevel,
+    WindowLevel::Floating => ffi::NSWindowLevel::NSFloatingWindowLevel,
+    WindowLevel::ModalPanel => ffi::NSWindowLevel::NSModalPanelWindowLevel,
+    WindowLevel::PopUpMenu => ffi::NSWindowLevel::NSPopUpMenuWindowLevel,
+    WindowLevel::ScreenSaver => ffi::NSWindowLevel::NSScreenSaverWindowLevel,
+  }
+}
+
+/// Queues the `RedrawRequested` event of `window_id`, at the next refresh of the displays with
+/// `RedrawPacing::VSync`. Can be called from any thread.
+fn redraw(window_id: RootWindowId, pacing: RedrawPacing) {
+  if pacing != RedrawPacing::VSync || !vsync::request_redraw(window_id) {
+    AppState::queue_redraw(window_id);
+  }
+}
+
+unsafe fn is_input_event(event: id) -> bool {
+  matches!(
+    event.eventType(),
+    NSEventType::NSLeftMouseDown
+      | NSEventType::NSLeftMouseUp
+      | NSEventType::NSRightMouseDown
+      | NSEventType::NSRightMouseUp
+      | NSEventType::NSOtherMouseDown
+      | NSEventType::NSOtherMouseUp
+      | NSEventType::NSMouseMoved
+      | NSEventType::NSLeftMouseDragged
+      | NSEventType::NSRightMouseDragged
+      | NSEventType::NSOtherMouseDragged
+      | NSEventType::NSKeyDown
+      | NSEventType::NSKeyUp
+      | NSEventType::NSFlagsChanged
+      | NSEventType::NSScrollWheel
+      | NSEventType::NSEventTypeGesture
+      | NSEventType::NSEventTypeMagnify
+      | NSEventType::NSEventTypeSwipe
+      | NSEventType::NSEventTypeRotate
+      | NSEventType::NSEventTypeBeginGesture
+      | NSEventType::NSEventTypeEndGesture
+      | NSEventType::NSEventTypePressure
+  )
+}
+
 #[derive(Default)]
 pub struct SharedState {
   pub resizable: bool,
+  pub resizable_edges: Edges,
   pub fullscreen: Option<Fullscreen>,
   // This is true between windowWillEnterFullScreen and windowDidEnterFullScreen
   // or windowWillExitFullScreen and windowDidExitFullScreen.
@@ -298,6 +608,13 @@ pub struct SharedState {
   /// restored upon exiting it
   save_presentation_opts: Option<NSApplicationPresentationOptions>,
   pub saved_desktop_display_mode: Option<(CGDisplay, CGDisplayMode)>,
+  pub traffic_light_inset: Option<LogicalPosition<f64>>,
+  /// Restored by `set_titlebar_color` when the color is removed.
+  pub titlebar_transparent: bool,
+  pub transparent: bool,
+  pub kiosk: bool,
+  /// Used by `request_redraw`.
+  pub redraw_pacing: RedrawPacing,
 }
 
 impl SharedState {
@@ -320,6 +637,7 @@ impl From<WindowAttributes> for SharedState {
       // identical, resulting in a no-op.
       fullscreen: None,
       maximized: attribs.maximized,
+      transparent: attribs.transparent,
       ..Default::default()
     }
   }
@@ -366,6 +684,9 @@ impl UnownedWindow {
     unsafe {
       ns_window.setContentView_(*ns_view);
       ns_window.setInitialFirstResponder_(*ns_view);
+      if let Some(vibrancy) = pl_attribs.vibrancy {
+        effects::set_visual_effect(*ns_view, Some(vibrancy));
+      }
     }
 
     let input_context = unsafe { util::create_input_context(*ns_view) };
@@ -401,6 +722,10 @@ impl UnownedWindow {
     // indirectly by us calling `set_fullscreen` below, causing handlers in
     // `WindowDelegate` to update the state.
     let fullscreen = win_attribs.fullscreen.take();
+    let sheet_parent = match (&pl_attribs.parent, win_attribs.parent_window) {
+      (Parent::None, Some(parent)) if win_attribs.modal => Some((parent.0).0 as id),
+      _ => None,
+    };
     let maximized = win_attribs.maximized;
     let visible = win_attribs.visible;
     let decorations = win_attribs.decorations;
@@ -420,13 +745,22 @@ impl UnownedWindow {
 
     let delegate = new_delegate(&window, fullscreen.is_some());
 
+    window.shared_state.lock().unwrap().titlebar_transparent = pl_attribs.titlebar_transparent;
+    if let Some(inset) = pl_attribs.traffic_light_inset {
+      window.set_traffic_light_inset(inset);
+    }
+
     // Set fullscreen mode after we setup everything
     window.set_fullscreen(fullscreen);
 
     // Setting the window as key has to happen *after* we set the fullscreen
     // state, since otherwise we'll briefly see the window at normal size
     // before it transitions.
-    if visible {
+    if let Some(sheet_parent) = sheet_parent {
+      unsafe {
+        let () = msg_send![sheet_parent, beginSheet: *window.ns_window completionHandler: nil];
+      }
+    } else if visible {
       // Tightly linked with `app_state::window_activation_hack`
       unsafe { window.ns_window.makeKeyAndOrderFront_(nil) };
     }
@@ -471,6 +805,11 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn show_without_activating(&self) {
+    unsafe { util::order_front_async(*self.ns_window) };
+  }
+
   #[inline]
   // Shortener for set_visible(true)
   pub fn set_focus(&self) {
@@ -482,8 +821,52 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn raise(&self) {
+    unsafe {
+      let is_minimized: BOOL = msg_send![*self.ns_window, isMiniaturized];
+      let is_visible: BOOL = msg_send![*self.ns_window, isVisible];
+      // `orderFront:` would also show a hidden window
+      if is_minimized == NO && is_visible == YES {
+        util::order_front_async(*self.ns_window);
+      }
+    }
+  }
+
+  #[inline]
+  pub fn set_above(&self, window: &UnownedWindow) {
+    self.order_relative_to(window, NSWindowOrderingMode::NSWindowAbove);
+  }
+
+  #[inline]
+  pub fn set_below(&self, window: &UnownedWindow) {
+    self.order_relative_to(window, NSWindowOrderingMode::NSWindowBelow);
+  }
+
+  fn order_relative_to(&self, window: &UnownedWindow, mode: NSWindowOrderingMode) {
+    unsafe {
+      let is_minimized: BOOL = msg_send![*self.ns_window, isMiniaturized];
+      let is_visible: BOOL = msg_send![*self.ns_window, isVisible];
+      // `orderWindow:relativeTo:` would also show a hidden window
+      if is_minimized == NO && is_visible == YES {
+        util::order_relative_to_async(*self.ns_window, mode, window.ns_window.clone());
+      }
+    }
+  }
+
   pub fn request_redraw(&self) {
-    AppState::queue_redraw(RootWindowId(self.id()));
+    let pacing = self.shared_state.lock().unwrap().redraw_pacing;
+    redraw(RootWindowId(self.id()), pacing);
+  }
+
+  pub fn request_redraw_after(&self, delay: Duration) {
+    let window_id = RootWindowId(self.id());
+    let pacing = self.shared_state.lock().unwrap().redraw_pacing;
+    Queue::main().exec_after(delay, move || redraw(window_id, pacing));
+  }
+
+  pub fn set_redraw_pacing(&self, pacing: RedrawPacing) {
+    self.shared_state.lock().unwrap().redraw_pacing = pacing;
   }
 
   pub fn outer_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
@@ -564,6 +947,31 @@ impl UnownedWindow {
     }
   }
 
+  pub fn set_resizable_edges(&self, edges: Edges) {
+    trace!("Locked shared state in `set_resizable_edges`");
+    self.shared_state.lock().unwrap().resizable_edges = edges;
+    trace!("Unlocked shared state in `set_resizable_edges`");
+  }
+
+  pub fn set_titlebar_buttons(&self, buttons: TitlebarButtons) {
+    unsafe { util::set_titlebar_buttons_async(*self.ns_window, buttons) };
+  }
+
+  pub fn set_aspect_ratio(&self, aspect_ratio: Option<AspectRatio>) {
+    unsafe {
+      match aspect_ratio {
+        Some(aspect_ratio) => self.ns_window.setContentAspectRatio_(NSSize::new(
+          aspect_ratio.width as CGFloat,
+          aspect_ratio.height as CGFloat,
+        )),
+        // Setting the resize increments clears the aspect ratio.
+        None => self
+          .ns_window
+          .setContentResizeIncrements_(NSSize::new(1.0, 1.0)),
+      }
+    }
+  }
+
   #[inline]
   pub fn set_resizable(&self, resizable: bool) {
     let fullscreen = {
@@ -597,12 +1005,38 @@ impl UnownedWindow {
   }
 
   #[inline]
-  pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
-    // TODO: Do this for real https://stackoverflow.com/a/40922095/5435443
-    CGDisplay::associate_mouse_and_mouse_cursor_position(!grab)
+  pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+    let associate = match mode {
+      CursorGrabMode::None => true,
+      // TODO: Do this for real https://stackoverflow.com/a/40922095/5435443
+      CursorGrabMode::Confined => {
+        return Err(ExternalError::NotSupported(NotSupportedError::new()))
+      }
+      // The mouse events keep their deltas while the cursor doesn't move.
+      CursorGrabMode::Locked => false,
+    };
+    CGDisplay::associate_mouse_and_mouse_cursor_position(associate)
       .map_err(|status| ExternalError::Os(os_error!(OsError::CGError(status))))
   }
 
+  #[cfg(feature = "test")]
+  #[inline]
+  pub fn inject_event(&self, event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    super::synthetic_input::inject_event(*self.ns_window, *self.ns_view, event)
+  }
+
+  #[inline]
+  pub fn set_mouse_capture(&self, capture: bool) -> Result<(), ExternalError> {
+    unsafe { view::set_mouse_captured(*self.ns_view, capture) };
+    Ok(())
+  }
+
+  #[inline]
+  pub fn set_keyboard_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    keyboard_grab::set_keyboard_grab(*self.ns_window, grab)
+      .map_err(|e| ExternalError::Os(os_error!(OsError::CreationError(e))))
+  }
+
   #[inline]
   pub fn set_cursor_visible(&self, visible: bool) {
     if let Some(cursor_access) = self.cursor_state.upgrade() {
@@ -619,6 +1053,24 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    unsafe {
+      let _: () = msg_send![*self.ns_window, setIgnoresMouseEvents: if ignore { YES } else { NO }];
+    }
+
+    Ok(())
+  }
+
+  #[inline]
+  pub fn set_mouse_coalescing(&self, enabled: bool) {
+    // The coalescing of `NSEvent` is a setting of the application.
+    unsafe {
+      let _: () =
+        msg_send![class!(NSEvent), setMouseCoalescingEnabled: if enabled { YES } else { NO }];
+    }
+  }
+
   #[inline]
   pub fn scale_factor(&self) -> f64 {
     unsafe { NSWindow::backingScaleFactor(*self.ns_window) as _ }
@@ -652,6 +1104,11 @@ impl UnownedWindow {
     Ok(())
   }
 
+  #[inline]
+  pub fn set_drag_region(&self, regions: Vec<DragRegion>) {
+    unsafe { util::set_drag_regions_async(*self.ns_view, regions) };
+  }
+
   pub(crate) fn is_zoomed(&self) -> bool {
     // because `isZoomed` doesn't work if the window's borderless,
     // we make it resizable temporalily.
@@ -704,6 +1161,20 @@ impl UnownedWindow {
     self.set_maximized(maximized);
   }
 
+  // AppKit lays the titlebar buttons out again when the window is resized or its style mask
+  // changes, so they have to be moved back afterwards.
+  pub(crate) fn update_traffic_lights(&self) {
+    let shared_state = self.shared_state.lock().unwrap();
+    // The titlebar is in a separate window in fullscreen
+    if shared_state.fullscreen.is_some() || shared_state.in_fullscreen_transition {
+      return;
+    }
+    if let Some(inset) = shared_state.traffic_light_inset {
+      drop(shared_state);
+      unsafe { util::set_traffic_light_inset_async(*self.ns_window, inset) };
+    }
+  }
+
   #[inline]
   pub fn set_minimized(&self, minimized: bool) {
     let is_minimized: BOOL = unsafe { msg_send![*self.ns_window, isMiniaturized] };
@@ -739,17 +1210,85 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn set_maximized_on(&self, monitor: RootMonitorHandle) {
+    let ns_screen = match monitor.inner.ns_screen() {
+      Some(ns_screen) => ns_screen,
+      None => return,
+    };
+    unsafe {
+      util::set_maximized_on_async(
+        *self.ns_window,
+        ns_screen,
+        self.is_zoomed(),
+        Arc::downgrade(&self.shared_state),
+      );
+    }
+  }
+
   #[inline]
   pub fn fullscreen(&self) -> Option<Fullscreen> {
     let shared_state_lock = self.shared_state.lock().unwrap();
     shared_state_lock.fullscreen.clone()
   }
 
+  #[inline]
+  pub fn set_kiosk(&self, kiosk: bool) {
+    let mut shared_state_lock = self.shared_state.lock().unwrap();
+    if shared_state_lock.kiosk == kiosk {
+      return;
+    }
+    shared_state_lock.kiosk = kiosk;
+    drop(shared_state_lock);
+
+    // The presentation options saved by the simple fullscreen are restored when it's exited.
+    if !self.set_simple_fullscreen(kiosk) && kiosk {
+      warn!("`Window::set_kiosk` is ignored in native fullscreen");
+      self.shared_state.lock().unwrap().kiosk = false;
+      return;
+    }
+    if kiosk {
+      unsafe {
+        NSApp().setPresentationOptions_(
+          NSApplicationPresentationOptions::NSApplicationPresentationHideDock
+            | NSApplicationPresentationOptions::NSApplicationPresentationHideMenuBar
+            | NSApplicationPresentationOptions::NSApplicationPresentationDisableAppleMenu
+            | NSApplicationPresentationOptions::NSApplicationPresentationDisableProcessSwitching
+            | NSApplicationPresentationOptions::NSApplicationPresentationDisableForceQuit
+            | NSApplicationPresentationOptions::NSApplicationPresentationDisableSessionTermination
+            | NSApplicationPresentationOptions::NSApplicationPresentationDisableHideApplication,
+        );
+      }
+    }
+    self.set_window_level(if kiosk {
+      WindowLevel::Floating
+    } else {
+      WindowLevel::Normal
+    });
+  }
+
   #[inline]
   pub fn is_maximized(&self) -> bool {
     self.is_zoomed()
   }
 
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    // Saved by `set_maximized`, the frame of the window before it was zoomed.
+    let frame = self.shared_state.lock().unwrap().standard_frame?;
+    let content_rect = unsafe { NSWindow::contentRectForFrameRect_(*self.ns_window, frame) };
+    let scale_factor = self.scale_factor();
+    let position =
+      LogicalPosition::new(frame.origin.x as f64, util::bottom_left_to_top_left(frame));
+    let size = LogicalSize::new(
+      content_rect.size.width as f64,
+      content_rect.size.height as f64,
+    );
+    Some((
+      position.to_physical(scale_factor),
+      size.to_physical(scale_factor),
+    ))
+  }
+
   #[inline]
   pub fn is_visible(&self) -> bool {
     let is_visible: BOOL = unsafe { msg_send![*self.ns_window, isVisible] };
@@ -762,6 +1301,22 @@ impl UnownedWindow {
     is_resizable == YES
   }
 
+  #[inline]
+  pub fn set_enabled(&self, enabled: bool) {
+    unsafe {
+      let disabled = if enabled { NO } else { YES };
+      (**self.ns_window).set_ivar::<BOOL>("taoDisabled", disabled);
+    }
+  }
+
+  #[inline]
+  pub fn is_enabled(&self) -> bool {
+    unsafe {
+      let disabled: BOOL = *(**self.ns_window).get_ivar("taoDisabled");
+      disabled != YES
+    }
+  }
+
   #[inline]
   pub fn is_decorated(&self) -> bool {
     let current_mask = unsafe { self.ns_window.styleMask() };
@@ -869,6 +1424,7 @@ impl UnownedWindow {
           std::ptr::null(),
         );
         assert!(result == ffi::kCGErrorSuccess, "failed to set video mode");
+        monitor::set_display_mode_changed();
 
         // After the display has been configured, fade back in
         // asynchronously
@@ -944,6 +1500,11 @@ impl UnownedWindow {
     }
   }
 
+  pub fn set_fullscreen_video_mode(&self, video_mode: RootVideoMode) -> Result<(), ExternalError> {
+    self.set_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
+    Ok(())
+  }
+
   #[inline]
   pub fn set_decorations(&self, decorations: bool) {
     if decorations != self.decorations.load(Ordering::Acquire) {
@@ -984,13 +1545,76 @@ impl UnownedWindow {
   }
 
   #[inline]
-  pub fn set_always_on_top(&self, always_on_top: bool) {
-    let level = if always_on_top {
-      ffi::NSWindowLevel::NSFloatingWindowLevel
-    } else {
-      ffi::NSWindowLevel::NSNormalWindowLevel
-    };
-    unsafe { util::set_level_async(*self.ns_window, level) };
+  pub fn set_owner(&self, owner: Option<Id>) {
+    unsafe { util::set_owner_async(*self.ns_window, owner.map(|owner| owner.0 as id)) };
+  }
+
+  #[inline]
+  pub fn set_shadow(&self, shadow: bool) {
+    self.set_has_shadow(shadow);
+  }
+
+  #[inline]
+  pub fn set_opacity(&self, opacity: f32) {
+    unsafe { self.ns_window.setAlphaValue_(opacity as CGFloat) };
+  }
+
+  #[inline]
+  pub fn set_content_protected(&self, protected: bool) {
+    // `NSWindowSharingNone` and `NSWindowSharingReadOnly`
+    let sharing_type: NSUInteger = if protected { 0 } else { 1 };
+    unsafe {
+      let () = msg_send![*self.ns_window, setSharingType: sharing_type];
+    }
+  }
+
+  #[inline]
+  pub fn set_window_level(&self, level: WindowLevel) {
+    unsafe { util::set_level_async(*self.ns_window, ns_window_level(level)) };
+  }
+
+  #[inline]
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  #[inline]
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    let is_on_active_space: BOOL = unsafe { msg_send![*self.ns_window, isOnActiveSpace] };
+    is_on_active_space == YES
+  }
+
+  #[inline]
+  pub fn move_to_virtual_desktop(&self, _desktop: &VirtualDesktop) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  #[inline]
+  pub fn set_visible_on_all_workspaces(&self, visible: bool) {
+    unsafe { util::set_visible_on_all_spaces_async(*self.ns_window, visible) };
+  }
+
+  #[inline]
+  pub fn set_theme(&self, theme: Option<Theme>) {
+    unsafe { util::set_appearance_async(*self.ns_window, theme) };
+  }
+
+  #[inline]
+  pub fn set_background_effect(&self, effect: Option<Effect>) {
+    unsafe { util::set_visual_effect_async(*self.ns_view, effect.map(Into::into)) };
+  }
+
+  #[inline]
+  pub fn set_skip_taskbar(&self, _skip: bool) {
+    warn!("`Window::set_skip_taskbar` is ignored on macOS")
+  }
+
+  #[inline]
+  pub fn set_minimize_to_tray(&self, minimize_to_tray: bool) {
+    unsafe {
+      let minimize_to_tray = if minimize_to_tray { YES } else { NO };
+      (**self.ns_window).set_ivar::<BOOL>("taoMinimizeToTray", minimize_to_tray);
+    }
   }
 
   #[inline]
@@ -1006,30 +1630,41 @@ impl UnownedWindow {
   }
 
   #[inline]
-  pub fn set_ime_position(&self, spot: Position) {
+  pub fn set_ime_cursor_area(&self, position: Position, size: Size) {
     let scale_factor = self.scale_factor();
-    let logical_spot = spot.to_logical(scale_factor);
     unsafe {
-      view::set_ime_position(
+      view::set_ime_cursor_area(
         *self.ns_view,
         *self.input_context,
-        logical_spot.x,
-        logical_spot.y,
+        position.to_logical(scale_factor),
+        size.to_logical(scale_factor),
       );
     }
   }
 
+  #[inline]
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    unsafe { view::set_ime_allowed(*self.ns_view, *self.input_context, allowed) };
+  }
+
+  #[inline]
+  pub fn set_text_input(&self, active: bool) {
+    unsafe { view::set_text_input(*self.ns_view, active) };
+  }
+
   #[inline]
   pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
-    let ns_request_type = request_type.map(|ty| match ty {
-      UserAttentionType::Critical => NSRequestUserAttentionType::NSCriticalRequest,
-      UserAttentionType::Informational => NSRequestUserAttentionType::NSInformationalRequest,
-    });
-    unsafe {
-      if let Some(ty) = ns_request_type {
-        NSApp().requestUserAttention_(ty);
-      }
-    }
+    unsafe { util::request_user_attention(request_type) };
+  }
+
+  #[inline]
+  pub fn set_progress_bar(&self, state: ProgressState, progress: Option<u64>) {
+    progress_bar::set_progress_indicator(state, progress);
+  }
+
+  #[inline]
+  pub fn set_badge_count(&self, count: Option<u64>) {
+    util::set_badge_label_async(count.map(|count| count.to_string()));
   }
 
   #[inline]
@@ -1044,6 +1679,61 @@ impl UnownedWindow {
     true
   }
 
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    let (window_number, content_rect) = unsafe {
+      let window_number: NSInteger = msg_send![*self.ns_window, windowNumber];
+      let content_rect =
+        NSWindow::contentRectForFrameRect_(*self.ns_window, NSWindow::frame(*self.ns_window));
+      (window_number, content_rect)
+    };
+    // The bounds are in screen coordinates with the origin at the top-left corner.
+    let bounds = CGRect::new(
+      &CGPoint::new(
+        content_rect.origin.x,
+        util::bottom_left_to_top_left(content_rect),
+      ),
+      &CGSize::new(content_rect.size.width, content_rect.size.height),
+    );
+    let image = cg_window::create_image(
+      bounds,
+      cg_window::kCGWindowListOptionIncludingWindow,
+      window_number as CGWindowID,
+      cg_window::kCGWindowImageBoundsIgnoreFraming | cg_window::kCGWindowImageBestResolution,
+    )
+    .filter(|image| image.bits_per_pixel() == 32)
+    .ok_or_else(|| {
+      ExternalError::Os(os_error!(OsError::CreationError(
+        "Couldn't capture the window"
+      )))
+    })?;
+
+    let width = image.width();
+    let height = image.height();
+    let bytes_per_row = image.bytes_per_row();
+    let data = image.data();
+
+    // The pixels are in the premultiplied BGRA format.
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in data.bytes().chunks(bytes_per_row).take(height) {
+      for pixel in row[..width * 4].chunks_exact(4) {
+        let alpha = pixel[3];
+        let unpremultiply = |color: u8| match alpha {
+          0 => 0,
+          255 => color,
+          _ => (color as u32 * 255 / alpha as u32).min(255) as u8,
+        };
+        rgba.extend_from_slice(&[
+          unpremultiply(pixel[2]),
+          unpremultiply(pixel[1]),
+          unpremultiply(pixel[0]),
+          alpha,
+        ]);
+      }
+    }
+
+    Ok(WindowImage::new(width as u32, height as u32, rgba))
+  }
+
   #[inline]
   // Allow directly accessing the current monitor internally without unwrapping.
   pub(crate) fn current_monitor_inner(&self) -> RootMonitorHandle {
@@ -1199,6 +1889,87 @@ impl WindowExtMacOS for UnownedWindow {
         .setHasShadow_(if has_shadow { YES } else { NO })
     }
   }
+
+  #[inline]
+  fn set_vibrancy(
+    &self,
+    material: NSVisualEffectMaterial,
+    blending_mode: NSVisualEffectBlendingMode,
+    state: NSVisualEffectState,
+  ) {
+    let effect = VisualEffect::new(material, blending_mode, state);
+    unsafe { util::set_visual_effect_async(*self.ns_view, Some(effect)) };
+  }
+
+  #[inline]
+  fn remove_vibrancy(&self) {
+    unsafe { util::set_visual_effect_async(*self.ns_view, None) };
+  }
+
+  #[inline]
+  fn set_traffic_light_inset(&self, inset: LogicalPosition<f64>) {
+    self.shared_state.lock().unwrap().traffic_light_inset = Some(inset);
+    self.update_traffic_lights();
+  }
+
+  #[inline]
+  fn set_titlebar_color(&self, color: Option<Color>) {
+    let shared_state = self.shared_state.lock().unwrap();
+    unsafe {
+      util::set_titlebar_color_async(
+        *self.ns_window,
+        color,
+        shared_state.titlebar_transparent,
+        shared_state.transparent,
+      )
+    };
+  }
+
+  #[inline]
+  fn tabbing_identifier(&self) -> String {
+    unsafe {
+      let identifier: id = msg_send![*self.ns_window, tabbingIdentifier];
+      util::ns_string_to_rust(identifier)
+    }
+  }
+
+  #[inline]
+  fn set_tabbing_identifier(&self, identifier: &str) {
+    unsafe { set_tabbing_identifier(*self.ns_window, identifier) }
+  }
+
+  #[inline]
+  fn select_next_tab(&self) {
+    unsafe {
+      let () = msg_send![*self.ns_window, selectNextTab: nil];
+    }
+  }
+
+  #[inline]
+  fn select_previous_tab(&self) {
+    unsafe {
+      let () = msg_send![*self.ns_window, selectPreviousTab: nil];
+    }
+  }
+
+  #[inline]
+  fn merge_all_windows(&self) {
+    unsafe {
+      let () = msg_send![*self.ns_window, mergeAllWindows: nil];
+    }
+  }
+
+  #[inline]
+  fn set_transition_animations(&self, enabled: bool) {
+    unsafe {
+      let no_animations = if enabled { NO } else { YES };
+      (**self.ns_window).set_ivar::<BOOL>("taoNoAnimations", no_animations);
+
+      // NSWindowAnimationBehaviorDefault and NSWindowAnimationBehaviorNone
+      let behavior: NSInteger = if enabled { 0 } else { 2 };
+      let () = msg_send![*self.ns_window, setAnimationBehavior: behavior];
+    }
+  }
 }
 
 impl Drop for UnownedWindow {
@@ -1206,6 +1977,7 @@ impl Drop for UnownedWindow {
     trace!("Dropping `UnownedWindow` ({:?})", self as *mut _);
     // Close the window if it has not yet been closed.
     if *self.ns_window != nil {
+      keyboard_grab::release_keyboard_grab(*self.ns_window);
       unsafe { util::close_async(self.ns_window.clone()) };
     }
   }
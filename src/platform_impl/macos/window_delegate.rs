@@ -5,12 +5,13 @@ use std::{
   f64,
   os::raw::c_void,
   sync::{atomic::Ordering, Arc, Weak},
+  time::Instant,
 };
 
 use cocoa::{
   appkit::{self, NSApplicationPresentationOptions, NSView, NSWindow},
   base::{id, nil},
-  foundation::{NSAutoreleasePool, NSUInteger},
+  foundation::{NSAutoreleasePool, NSSize, NSUInteger},
 };
 use objc::{
   declare::ClassDecl,
@@ -19,18 +20,21 @@ use objc::{
 
 use crate::{
   dpi::{LogicalPosition, LogicalSize},
-  event::{Event, WindowEvent},
+  event::{CloseResponse, Event, FullscreenTransition, WindowEvent},
   keyboard::ModifiersState,
   platform_impl::platform::{
     app_state::{AppState, INTERRUPT_EVENT_LOOP_EXIT},
     event::{EventProxy, EventWrapper},
     util::{self, IdRef},
     view::ViewState,
-    window::{get_window_id, UnownedWindow},
+    window::{get_window_id, CloseHandle, UnownedWindow},
   },
-  window::{Fullscreen, WindowId},
+  window::{Edges, Fullscreen, WindowId},
 };
 
+// `NSWindowOcclusionStateVisible`
+const NS_WINDOW_OCCLUSION_STATE_VISIBLE: NSUInteger = 1 << 1;
+
 pub struct WindowDelegateState {
   ns_window: IdRef, // never changes
   ns_view: IdRef,   // never changes
@@ -80,6 +84,7 @@ impl WindowDelegateState {
     let event = Event::WindowEvent {
       window_id: WindowId(get_window_id(*self.ns_window)),
       event,
+      timestamp: Instant::now(),
     };
     AppState::queue_event(EventWrapper::StaticEvent(event));
   }
@@ -167,6 +172,10 @@ lazy_static! {
       sel!(windowDidResize:),
       window_did_resize as extern "C" fn(&Object, Sel, id),
     );
+    decl.add_method(
+      sel!(windowWillResize:toSize:),
+      window_will_resize_to_size as extern "C" fn(&Object, Sel, id, NSSize) -> NSSize,
+    );
     decl.add_method(
       sel!(windowDidMove:),
       window_did_move as extern "C" fn(&Object, Sel, id),
@@ -230,6 +239,10 @@ lazy_static! {
       sel!(windowDidFailToEnterFullScreen:),
       window_did_fail_to_enter_fullscreen as extern "C" fn(&Object, Sel, id),
     );
+    decl.add_method(
+      sel!(windowDidChangeOcclusionState:),
+      window_did_change_occlusion_state as extern "C" fn(&Object, Sel, id),
+    );
 
     decl.add_ivar::<*mut c_void>("taoState");
     WindowDelegateClass(decl.register())
@@ -267,7 +280,12 @@ extern "C" fn init_with_tao(this: &Object, _sel: Sel, state: *mut c_void) -> id
 
 extern "C" fn window_should_close(this: &Object, _: Sel, _: id) -> BOOL {
   trace!("Triggered `windowShouldClose:`");
-  with_state(this, |state| state.emit_event(WindowEvent::CloseRequested));
+  with_state(this, |state| {
+    let window_id = WindowId(get_window_id(*state.ns_window));
+    state.emit_event(WindowEvent::CloseRequested {
+      response: CloseResponse::new(window_id, CloseHandle::new(*state.ns_window)),
+    })
+  });
   trace!("Completed `windowShouldClose:`");
   NO
 }
@@ -291,10 +309,39 @@ extern "C" fn window_did_resize(this: &Object, _: Sel, _: id) {
   with_state(this, |state| {
     state.emit_resize_event();
     state.emit_move_event();
+    state.with_window(|window| window.update_traffic_lights());
   });
   trace!("Completed `windowDidResize:`");
 }
 
+// AppKit can't restrict the resize to some of the edges of the window, so we keep the width or
+// the height when none of their edges are resizable.
+extern "C" fn window_will_resize_to_size(this: &Object, _: Sel, _: id, size: NSSize) -> NSSize {
+  trace!("Triggered `windowWillResize:toSize:`");
+  let mut size = size;
+  with_state(this, |state| {
+    let edges = state
+      .with_window(|window| {
+        let shared_state = window.shared_state.lock().unwrap();
+        if shared_state.fullscreen.is_some() || shared_state.in_fullscreen_transition {
+          Edges::all()
+        } else {
+          shared_state.resizable_edges
+        }
+      })
+      .unwrap_or_default();
+    let current_size = unsafe { NSWindow::frame(*state.ns_window).size };
+    if !edges.intersects(Edges::HORIZONTAL) {
+      size.width = current_size.width;
+    }
+    if !edges.intersects(Edges::VERTICAL) {
+      size.height = current_size.height;
+    }
+  });
+  trace!("Completed `windowWillResize:toSize:`");
+  size
+}
+
 // This won't be triggered if the move was part of a resize.
 extern "C" fn window_did_move(this: &Object, _: Sel, _: id) {
   trace!("Triggered `windowDidMove:`");
@@ -460,7 +507,10 @@ extern "C" fn window_will_enter_fullscreen(this: &Object, _: Sel, _: id) {
       }
       shared_state.in_fullscreen_transition = true;
       trace!("Unlocked shared state in `window_will_enter_fullscreen`");
-    })
+    });
+    state.emit_event(WindowEvent::FullscreenTransition(
+      FullscreenTransition::Entering,
+    ));
   });
   trace!("Completed `windowWillEnterFullscreen:`");
 }
@@ -478,6 +528,9 @@ extern "C" fn window_will_exit_fullscreen(this: &Object, _: Sel, _: id) {
       shared_state.in_fullscreen_transition = true;
       trace!("Unlocked shared state in `window_will_exit_fullscreen`");
     });
+    state.emit_event(WindowEvent::FullscreenTransition(
+      FullscreenTransition::Exiting,
+    ));
   });
   trace!("Completed `windowWillExitFullScreen:`");
 }
@@ -509,6 +562,9 @@ extern "C" fn window_did_enter_fullscreen(this: &Object, _: Sel, _: id) {
   trace!("Triggered `windowDidEnterFullscreen:`");
   with_state(this, |state| {
     state.initial_fullscreen = false;
+    state.emit_event(WindowEvent::FullscreenTransition(
+      FullscreenTransition::Entered,
+    ));
     state.with_window(|window| {
       trace!("Locked shared state in `window_did_enter_fullscreen`");
       let mut shared_state = window.shared_state.lock().unwrap();
@@ -538,10 +594,14 @@ extern "C" fn window_did_exit_fullscreen(this: &Object, _: Sel, _: id) {
       let target_fullscreen = shared_state.target_fullscreen.take();
       trace!("Unlocked shared state in `window_did_exit_fullscreen`");
       drop(shared_state);
+      window.update_traffic_lights();
       if let Some(target_fullscreen) = target_fullscreen {
         window.set_fullscreen(target_fullscreen);
       }
-    })
+    });
+    state.emit_event(WindowEvent::FullscreenTransition(
+      FullscreenTransition::Exited,
+    ));
   });
   trace!("Completed `windowDidExitFullscreen:`");
 }
@@ -572,6 +632,9 @@ extern "C" fn window_did_fail_to_enter_fullscreen(this: &Object, _: Sel, _: id)
       shared_state.target_fullscreen = None;
       trace!("Unlocked shared state in `window_did_fail_to_enter_fullscreen`");
     });
+    state.emit_event(WindowEvent::FullscreenTransition(
+      FullscreenTransition::Exited,
+    ));
     if state.initial_fullscreen {
       let _: () = unsafe {
         msg_send![*state.ns_window,
@@ -586,3 +649,16 @@ extern "C" fn window_did_fail_to_enter_fullscreen(this: &Object, _: Sel, _: id)
   });
   trace!("Completed `windowDidFailToEnterFullscreen:`");
 }
+
+/// Invoked when the window becomes fully covered, minimized or moved to another space, and when
+/// part of it becomes visible again.
+extern "C" fn window_did_change_occlusion_state(this: &Object, _: Sel, _: id) {
+  trace!("Triggered `windowDidChangeOcclusionState:`");
+  with_state(this, |state| {
+    let occlusion_state: NSUInteger = unsafe { msg_send![*state.ns_window, occlusionState] };
+    state.emit_event(WindowEvent::Occluded(
+      occlusion_state & NS_WINDOW_OCCLUSION_STATE_VISIBLE == 0,
+    ));
+  });
+  trace!("Completed `windowDidChangeOcclusionState:`");
+}
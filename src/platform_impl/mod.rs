@@ -3,19 +3,50 @@
 
 pub use self::platform::*;
 
-#[cfg(target_os = "windows")]
+#[cfg(all(
+  feature = "headless",
+  any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[path = "headless/mod.rs"]
+mod platform;
+#[cfg(all(not(feature = "headless"), target_os = "windows"))]
 #[path = "windows/mod.rs"]
 mod platform;
-#[cfg(any(
-  target_os = "linux",
-  target_os = "dragonfly",
-  target_os = "freebsd",
-  target_os = "netbsd",
-  target_os = "openbsd"
+#[cfg(all(
+  not(feature = "headless"),
+  not(feature = "wayland"),
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
 ))]
 #[path = "linux/mod.rs"]
 mod platform;
-#[cfg(target_os = "macos")]
+#[cfg(all(
+  not(feature = "headless"),
+  feature = "wayland",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[path = "wayland/mod.rs"]
+mod platform;
+#[cfg(all(not(feature = "headless"), target_os = "macos"))]
 #[path = "macos/mod.rs"]
 mod platform;
 #[cfg(target_os = "android")]
@@ -24,6 +55,9 @@ mod platform;
 #[cfg(target_os = "ios")]
 #[path = "ios/mod.rs"]
 mod platform;
+#[cfg(target_arch = "wasm32")]
+#[path = "web/mod.rs"]
+mod platform;
 
 #[cfg(all(
   not(target_os = "ios"),
@@ -35,5 +69,18 @@ mod platform;
   not(target_os = "freebsd"),
   not(target_os = "netbsd"),
   not(target_os = "openbsd"),
+  not(target_arch = "wasm32"),
 ))]
 compile_error!("The platform you're compiling for is not supported by tao");
+
+#[cfg(all(
+  not(any(feature = "headless", feature = "wayland", feature = "gtk-backend")),
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+compile_error!("tao needs the `gtk-backend` or the `wayland` feature on Linux and the BSDs");
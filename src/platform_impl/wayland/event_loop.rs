@@ -0,0 +1,1130 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::{HashMap, VecDeque},
+  fmt,
+  marker::PhantomData,
+  os::unix::io::{AsRawFd, RawFd},
+  ptr,
+  sync::{
+    atomic::{AtomicBool, AtomicI32, Ordering},
+    Arc, Mutex, Weak,
+  },
+  time::{Duration, Instant},
+};
+
+use wayland_client::{
+  delegate_noop,
+  globals::{registry_queue_init, BindError, GlobalListContents},
+  protocol::{
+    wl_buffer::WlBuffer,
+    wl_compositor::WlCompositor,
+    wl_output::WlOutput,
+    wl_region::WlRegion,
+    wl_registry::{self, WlRegistry},
+    wl_seat::WlSeat,
+    wl_shm::WlShm,
+    wl_shm_pool::WlShmPool,
+    wl_surface::WlSurface,
+  },
+  Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_cursor::CursorTheme;
+use wayland_protocols::{
+  wp::{
+    fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    keyboard_shortcuts_inhibit::zv1::client::{
+      zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1,
+      zwp_keyboard_shortcuts_inhibitor_v1::ZwpKeyboardShortcutsInhibitorV1,
+    },
+    pointer_constraints::zv1::client::{
+      zwp_confined_pointer_v1::ZwpConfinedPointerV1, zwp_locked_pointer_v1::ZwpLockedPointerV1,
+      zwp_pointer_constraints_v1::ZwpPointerConstraintsV1,
+    },
+    pointer_gestures::zv1::client::zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+    relative_pointer::zv1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1,
+    text_input::zv3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+    viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+  },
+  xdg::{
+    decoration::zv1::client::{
+      zxdg_decoration_manager_v1::ZxdgDecorationManagerV1,
+      zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1,
+    },
+    shell::client::xdg_wm_base::{self, XdgWmBase},
+  },
+};
+
+use crate::{
+  error::OsError as RootOsError,
+  event::{DeviceId as RootDeviceId, Event, StartCause, WindowEvent},
+  event_loop::{
+    self, ControlFlow, DeviceEventFilter, EventLoopClosed, Priority, SendEventError, TimerId,
+  },
+  keyboard::{Key, KeyRepeatInfo, KeyboardLayout, LockKey, ModifiersState},
+  monitor::MonitorHandle as RootMonitorHandle,
+  platform::{
+    pump_events::PumpStatus,
+    unix_signals::{ExitSignal, SignalAction},
+  },
+  user_event_queue::UserEventQueue,
+  window::WindowId as RootWindowId,
+};
+
+use super::{
+  monitor::{MonitorHandle, OutputData, OutputInfo},
+  seat::{Input, SeatState},
+  window::{WindowId, WindowShared},
+  DeviceId, OsError, VirtualDesktop,
+};
+
+/// The globals bound when the event loop is created, besides the outputs and seats which come and
+/// go.
+pub(crate) struct Globals {
+  pub(crate) compositor: WlCompositor,
+  pub(crate) shm: WlShm,
+  pub(crate) wm_base: XdgWmBase,
+  pub(crate) decoration_manager: Option<ZxdgDecorationManagerV1>,
+  pub(crate) viewporter: Option<WpViewporter>,
+  pub(crate) fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
+  pub(crate) pointer_constraints: Option<ZwpPointerConstraintsV1>,
+  pub(crate) relative_pointer_manager: Option<ZwpRelativePointerManagerV1>,
+  pub(crate) pointer_gestures: Option<ZwpPointerGesturesV1>,
+  pub(crate) text_input_manager: Option<ZwpTextInputManagerV3>,
+  pub(crate) keyboard_shortcuts_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1>,
+}
+
+lazy_static! {
+  /// The backend of the event loop, for the functions that don't take it.
+  static ref BACKEND: Mutex<Weak<Backend>> = Mutex::new(Weak::new());
+}
+
+/// Returns the layout of the keyboard of the seat, once the compositor sent it.
+pub fn current_keyboard_layout() -> Option<KeyboardLayout> {
+  let backend = BACKEND.lock().unwrap().upgrade()?;
+  let layout = backend.input.lock().unwrap().keyboard_layout.clone();
+  layout
+}
+
+/// The state shared by the event loop with its windows and proxies.
+pub(crate) struct Backend {
+  pub(crate) conn: Connection,
+  pub(crate) qh: QueueHandle<State>,
+  pub(crate) globals: Globals,
+  pending: Mutex<Pending>,
+  /// A pipe waking up the event loop while it waits for the compositor.
+  wake_pipe: (RawFd, RawFd),
+  pub(crate) windows: Mutex<HashMap<WindowId, Weak<WindowShared>>>,
+  pub(crate) monitors: Mutex<Vec<MonitorHandle>>,
+  pub(crate) input: Mutex<Input>,
+  /// The cursor themes, by the scale of their cursors.
+  pub(crate) cursor_themes: Mutex<HashMap<u32, CursorTheme>>,
+}
+
+/// The events waiting for the next iteration of the event loop.
+struct Pending {
+  /// The events of the compositor, which never are `Event::UserEvent`.
+  events: VecDeque<Event<'static, ()>>,
+  redraws: Vec<WindowId>,
+  /// The redraws requested with a delay, with the time they're due.
+  delayed_redraws: Vec<(Instant, WindowId)>,
+  /// The new scale factors of the windows, sent as `WindowEvent::ScaleFactorChanged`.
+  scale_changes: Vec<(WindowId, f64)>,
+  /// Whether `Event::ExitRequested` is to be sent.
+  exit_requested: bool,
+  woken: bool,
+}
+
+impl Pending {
+  fn is_empty(&self) -> bool {
+    self.events.is_empty()
+      && self.redraws.is_empty()
+      && self.scale_changes.is_empty()
+      && !self.exit_requested
+      && !self.woken
+  }
+
+  /// Moves the delayed redraws due at `now` to `redraws`.
+  fn take_due_redraws(&mut self, now: Instant) {
+    let redraws = &mut self.redraws;
+    self.delayed_redraws.retain(|&(due, window_id)| {
+      if due > now {
+        return true;
+      }
+      if !redraws.contains(&window_id) {
+        redraws.push(window_id);
+      }
+      false
+    });
+  }
+
+  fn next_delayed_redraw(&self) -> Option<Instant> {
+    self.delayed_redraws.iter().map(|&(due, _)| due).min()
+  }
+}
+
+impl Backend {
+  pub(crate) fn push(&self, event: Event<'static, ()>) {
+    self.pending.lock().unwrap().events.push_back(event);
+    self.notify();
+  }
+
+  pub(crate) fn push_window_event(&self, window_id: WindowId, event: WindowEvent<'static>) {
+    // A disabled window doesn't receive the input of the user.
+    let is_input = matches!(
+      event,
+      WindowEvent::KeyboardInput { .. }
+        | WindowEvent::ReceivedText(_)
+        | WindowEvent::Ime(_)
+        | WindowEvent::CursorMoved { .. }
+        | WindowEvent::MouseInput { .. }
+        | WindowEvent::MouseWheel { .. }
+        | WindowEvent::Touch(_)
+        | WindowEvent::TouchFrame(_)
+        | WindowEvent::PinchGesture { .. }
+        | WindowEvent::RotationGesture { .. }
+        | WindowEvent::SwipeGesture { .. }
+    );
+    if is_input
+      && self
+        .window(window_id)
+        .is_some_and(|window| !window.enabled())
+    {
+      return;
+    }
+    self.push(Event::WindowEvent {
+      window_id: RootWindowId(window_id),
+      event,
+      timestamp: Instant::now(),
+    });
+  }
+
+  pub(crate) fn push_scale_change(&self, window_id: WindowId, scale_factor: f64) {
+    self
+      .pending
+      .lock()
+      .unwrap()
+      .scale_changes
+      .push((window_id, scale_factor));
+    self.notify();
+  }
+
+  fn request_exit(&self) {
+    self.pending.lock().unwrap().exit_requested = true;
+    self.notify();
+  }
+
+  pub(crate) fn request_redraw(&self, window_id: WindowId) {
+    let mut pending = self.pending.lock().unwrap();
+    if !pending.redraws.contains(&window_id) {
+      pending.redraws.push(window_id);
+    }
+    drop(pending);
+    self.notify();
+  }
+
+  pub(crate) fn request_redraw_after(&self, window_id: WindowId, delay: Duration) {
+    self
+      .pending
+      .lock()
+      .unwrap()
+      .delayed_redraws
+      .push((Instant::now() + delay, window_id));
+    self.notify();
+  }
+
+  fn wake_up(&self) {
+    self.pending.lock().unwrap().woken = true;
+    self.notify();
+  }
+
+  /// Wakes up the event loop if it's waiting for the compositor.
+  fn notify(&self) {
+    wake(self.wake_pipe.1);
+  }
+
+  /// Empties the wake pipe, once the event loop is awake.
+  fn drain_wake_pipe(&self) {
+    let mut buf = [0u8; 64];
+    while unsafe { libc::read(self.wake_pipe.0, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+  }
+
+  /// Sends the requests of the windows, which are otherwise only sent by the event loop.
+  pub(crate) fn flush(&self) {
+    if let Err(e) = self.conn.flush() {
+      log::warn!("Failed to send the requests to the compositor: {}", e);
+    }
+  }
+
+  pub(crate) fn window(&self, window_id: WindowId) -> Option<Arc<WindowShared>> {
+    self
+      .windows
+      .lock()
+      .unwrap()
+      .get(&window_id)
+      .and_then(Weak::upgrade)
+  }
+
+  pub(crate) fn monitor(&self, output: &WlOutput) -> Option<MonitorHandle> {
+    self
+      .monitors
+      .lock()
+      .unwrap()
+      .iter()
+      .find(|monitor| monitor.output == *output)
+      .cloned()
+  }
+
+  /// The id of the devices of the seat, or a dummy one before the seat is bound.
+  pub(crate) fn device_id(&self) -> RootDeviceId {
+    let seat = self
+      .input
+      .lock()
+      .unwrap()
+      .seat
+      .as_ref()
+      .map(|&(name, _)| name);
+    RootDeviceId(DeviceId(seat.unwrap_or(0)))
+  }
+
+  /// Binds the outputs and the seat, the other globals are only bound once.
+  fn bind_global(&self, registry: &WlRegistry, name: u32, interface: &str, version: u32) {
+    match interface {
+      "wl_output" => {
+        let info = Arc::new(Mutex::new(OutputInfo::default()));
+        let output = registry.bind::<WlOutput, _, _>(
+          name,
+          version.min(4),
+          &self.qh,
+          OutputData {
+            name,
+            info: info.clone(),
+          },
+        );
+        self
+          .monitors
+          .lock()
+          .unwrap()
+          .push(MonitorHandle::new(output, info));
+      }
+      // The backend only uses the first seat.
+      "wl_seat" => {
+        let mut input = self.input.lock().unwrap();
+        if input.seat.is_none() {
+          let seat = registry.bind::<WlSeat, _, _>(name, version.min(7), &self.qh, name);
+          input.seat = Some((name, seat));
+        }
+      }
+      _ => (),
+    }
+  }
+}
+
+impl Drop for Backend {
+  fn drop(&mut self) {
+    unsafe {
+      libc::close(self.wake_pipe.0);
+      libc::close(self.wake_pipe.1);
+    }
+  }
+}
+
+impl fmt::Debug for Backend {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad("Backend { .. }")
+  }
+}
+
+fn wake(fd: RawFd) {
+  // The pipe being full already wakes up the event loop.
+  unsafe {
+    libc::write(fd, [0u8].as_ptr() as *const _, 1);
+  }
+}
+
+/// The state the events of the compositor are dispatched to.
+pub(crate) struct State {
+  pub(crate) backend: Arc<Backend>,
+  pub(crate) seat: SeatState,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+  fn event(
+    state: &mut Self,
+    registry: &WlRegistry,
+    event: wl_registry::Event,
+    _: &GlobalListContents,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    match event {
+      wl_registry::Event::Global {
+        name,
+        interface,
+        version,
+      } => state
+        .backend
+        .bind_global(registry, name, &interface, version),
+      wl_registry::Event::GlobalRemove { name } => {
+        let mut monitors = state.backend.monitors.lock().unwrap();
+        if let Some(index) = monitors.iter().position(|monitor| {
+          monitor
+            .output
+            .data::<OutputData>()
+            .map_or(false, |data| data.name == name)
+        }) {
+          let monitor = monitors.remove(index);
+          if monitor.output.version() >= 3 {
+            monitor.output.release();
+          }
+        }
+        drop(monitors);
+
+        let mut input = state.backend.input.lock().unwrap();
+        if input.seat.as_ref().map_or(false, |&(seat, _)| seat == name) {
+          let (_, seat) = input.seat.take().unwrap();
+          *input = Input::default();
+          drop(input);
+          state.seat.release();
+          if seat.version() >= 5 {
+            seat.release();
+          }
+        }
+      }
+      _ => (),
+    }
+  }
+}
+
+impl Dispatch<XdgWmBase, ()> for State {
+  fn event(
+    _: &mut Self,
+    wm_base: &XdgWmBase,
+    event: xdg_wm_base::Event,
+    _: &(),
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    if let xdg_wm_base::Event::Ping { serial } = event {
+      wm_base.pong(serial);
+    }
+  }
+}
+
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: WlRegion);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ignore WlShm);
+delegate_noop!(State: ignore WlBuffer);
+// The surfaces of the cursors, the ones of the windows have their id as data.
+delegate_noop!(State: ignore WlSurface);
+delegate_noop!(State: ZxdgDecorationManagerV1);
+delegate_noop!(State: ignore ZxdgToplevelDecorationV1);
+delegate_noop!(State: WpViewporter);
+delegate_noop!(State: WpViewport);
+delegate_noop!(State: WpFractionalScaleManagerV1);
+delegate_noop!(State: ZwpPointerConstraintsV1);
+delegate_noop!(State: ignore ZwpConfinedPointerV1);
+delegate_noop!(State: ignore ZwpLockedPointerV1);
+delegate_noop!(State: ZwpRelativePointerManagerV1);
+delegate_noop!(State: ZwpPointerGesturesV1);
+delegate_noop!(State: ZwpTextInputManagerV3);
+delegate_noop!(State: ZwpKeyboardShortcutsInhibitManagerV1);
+delegate_noop!(State: ignore ZwpKeyboardShortcutsInhibitorV1);
+
+// The signals are received by a handler which can't allocate nor lock, so it only flags them and
+// wakes up the event loop through its pipe.
+static SIGNAL_WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+static SIGNALS_RECEIVED: [AtomicBool; 3] = [
+  AtomicBool::new(false),
+  AtomicBool::new(false),
+  AtomicBool::new(false),
+];
+const SIGNALS: [ExitSignal; 3] = [
+  ExitSignal::Terminate,
+  ExitSignal::Interrupt,
+  ExitSignal::Hangup,
+];
+
+fn signal_index(signal: ExitSignal) -> usize {
+  match signal {
+    ExitSignal::Terminate => 0,
+    ExitSignal::Interrupt => 1,
+    ExitSignal::Hangup => 2,
+  }
+}
+
+extern "C" fn on_signal(signum: libc::c_int) {
+  if let Some(signal) = SIGNALS.iter().find(|signal| signal.signum() == signum) {
+    SIGNALS_RECEIVED[signal_index(*signal)].store(true, Ordering::Release);
+  }
+  let fd = SIGNAL_WAKE_FD.load(Ordering::Acquire);
+  if fd >= 0 {
+    wake(fd);
+  }
+}
+
+fn set_signal_handler(signal: ExitSignal, handle: bool) {
+  unsafe {
+    let mut action: libc::sigaction = std::mem::zeroed();
+    action.sa_sigaction = if handle {
+      on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t
+    } else {
+      libc::SIG_DFL
+    };
+    action.sa_flags = libc::SA_RESTART;
+    libc::sigemptyset(&mut action.sa_mask);
+    libc::sigaction(signal.signum(), &action, ptr::null_mut());
+  }
+}
+
+pub struct EventLoop<T: 'static> {
+  window_target: event_loop::EventLoopWindowTarget<T>,
+  event_queue: EventQueue<State>,
+  state: State,
+  user_events: Arc<UserEventQueue<T>>,
+  signal_actions: HashMap<ExitSignal, Box<dyn Fn()>>,
+  control_flow: ControlFlow,
+  running: bool,
+  /// Set once the connection to the compositor is lost, which exits the event loop.
+  connection_lost: bool,
+}
+
+impl<T: 'static> EventLoop<T> {
+  pub fn new() -> Self {
+    Self::try_new().unwrap()
+  }
+
+  pub fn try_new() -> Result<Self, RootOsError> {
+    let error = |message: String| os_error!(OsError(message));
+    let conn = Connection::connect_to_env()
+      .map_err(|e| error(format!("Failed to connect to the compositor: {}", e)))?;
+    let (globals, mut event_queue) = registry_queue_init::<State>(&conn)
+      .map_err(|e| error(format!("Failed to list the globals: {}", e)))?;
+    let qh = event_queue.handle();
+
+    let bind_error = |interface: &'static str| {
+      move |e: BindError| {
+        error(format!(
+          "The compositor doesn't support {}: {}",
+          interface, e
+        ))
+      }
+    };
+    let bound_globals = Globals {
+      compositor: globals
+        .bind(&qh, 4..=5, ())
+        .map_err(bind_error("wl_compositor"))?,
+      shm: globals.bind(&qh, 1..=1, ()).map_err(bind_error("wl_shm"))?,
+      wm_base: globals
+        .bind(&qh, 1..=2, ())
+        .map_err(bind_error("xdg_wm_base"))?,
+      decoration_manager: globals.bind(&qh, 1..=1, ()).ok(),
+      viewporter: globals.bind(&qh, 1..=1, ()).ok(),
+      fractional_scale_manager: globals.bind(&qh, 1..=1, ()).ok(),
+      pointer_constraints: globals.bind(&qh, 1..=1, ()).ok(),
+      relative_pointer_manager: globals.bind(&qh, 1..=1, ()).ok(),
+      pointer_gestures: globals.bind(&qh, 1..=1, ()).ok(),
+      text_input_manager: globals.bind(&qh, 1..=1, ()).ok(),
+      keyboard_shortcuts_inhibit_manager: globals.bind(&qh, 1..=1, ()).ok(),
+    };
+
+    let mut wake_pipe = [0; 2];
+    if unsafe { libc::pipe2(wake_pipe.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+      return Err(error(format!(
+        "Failed to create the pipe of the event loop: {}",
+        std::io::Error::last_os_error()
+      )));
+    }
+
+    let backend = Arc::new(Backend {
+      conn,
+      qh,
+      globals: bound_globals,
+      pending: Mutex::new(Pending {
+        events: VecDeque::new(),
+        redraws: Vec::new(),
+        delayed_redraws: Vec::new(),
+        scale_changes: Vec::new(),
+        exit_requested: false,
+        woken: false,
+      }),
+      wake_pipe: (wake_pipe[0], wake_pipe[1]),
+      windows: Mutex::new(HashMap::new()),
+      monitors: Mutex::new(Vec::new()),
+      input: Mutex::new(Input::default()),
+      cursor_themes: Mutex::new(HashMap::new()),
+    });
+    *BACKEND.lock().unwrap() = Arc::downgrade(&backend);
+    globals.contents().with_list(|list| {
+      for global in list {
+        backend.bind_global(
+          globals.registry(),
+          global.name,
+          &global.interface,
+          global.version,
+        );
+      }
+    });
+
+    let mut state = State {
+      backend: backend.clone(),
+      seat: SeatState::new(),
+    };
+    // The first roundtrip receives the state of the outputs and the capabilities of the seat, the
+    // second one the devices of the seat.
+    for _ in 0..2 {
+      event_queue
+        .roundtrip(&mut state)
+        .map_err(|e| error(format!("Failed to receive the state of the globals: {}", e)))?;
+    }
+
+    Ok(EventLoop {
+      window_target: event_loop::EventLoopWindowTarget {
+        p: EventLoopWindowTarget {
+          backend,
+          timers: RefCell::new(HashMap::new()),
+          device_event_filter: Cell::new(DeviceEventFilter::default()),
+          _marker: PhantomData,
+        },
+        _marker: PhantomData,
+      },
+      event_queue,
+      state,
+      user_events: Arc::new(UserEventQueue::new()),
+      signal_actions: HashMap::new(),
+      control_flow: ControlFlow::default(),
+      running: false,
+      connection_lost: false,
+    })
+  }
+
+  pub fn run<F>(mut self, event_handler: F) -> !
+  where
+    F: 'static + FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    let exit_code = self.run_return(event_handler);
+    ::std::process::exit(exit_code);
+  }
+
+  pub fn run_return<F>(&mut self, mut event_handler: F) -> i32
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    loop {
+      if let PumpStatus::Exit(exit_code) = self.pump_events(None, &mut event_handler) {
+        return exit_code;
+      }
+    }
+  }
+
+  pub fn pump_events<F>(&mut self, timeout: Option<Duration>, mut event_handler: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    let start_cause = if self.running {
+      match self.wait(timeout) {
+        Some(start_cause) => start_cause,
+        None => return PumpStatus::Continue,
+      }
+    } else {
+      self.running = true;
+      self.control_flow = ControlFlow::default();
+      StartCause::Init
+    };
+
+    self.dispatch_events(start_cause, &mut event_handler);
+
+    if self.connection_lost && self.control_flow.exit_code().is_none() {
+      self.control_flow = ControlFlow::ExitWithCode(1);
+    }
+    if let Some(exit_code) = self.control_flow.exit_code() {
+      self.call_event_handler(&mut event_handler, Event::LoopDestroyed);
+      self.running = false;
+      PumpStatus::Exit(exit_code)
+    } else {
+      PumpStatus::Continue
+    }
+  }
+
+  /// Waits for new events as the control flow asks, for up to `timeout`.
+  ///
+  /// Returns `None` if `timeout` elapsed first.
+  fn wait(&mut self, timeout: Option<Duration>) -> Option<StartCause> {
+    let start = Instant::now();
+    let requested_resume = match self.control_flow {
+      ControlFlow::Poll | ControlFlow::ExitWithCode(_) => {
+        // The events the compositor already sent are read without waiting.
+        self.read_events(Some(Duration::from_millis(0)));
+        self.dispatch_pending();
+        self.handle_signals();
+        self
+          .state
+          .seat
+          .repeat_key(&self.window_target.p.backend, Instant::now());
+        return Some(StartCause::Poll);
+      }
+      ControlFlow::Wait => None,
+      ControlFlow::WaitUntil(requested_resume) => Some(requested_resume),
+    };
+    let next_timer = self.window_target.p.next_timer();
+
+    loop {
+      self.dispatch_pending();
+      self.handle_signals();
+      let now = Instant::now();
+      self
+        .state
+        .seat
+        .repeat_key(&self.window_target.p.backend, now);
+
+      let next_delayed_redraw = {
+        let mut pending = self.window_target.p.backend.pending.lock().unwrap();
+        pending.take_due_redraws(now);
+        if !pending.is_empty()
+          || next_timer.map_or(false, |timer| now >= timer)
+          || self.connection_lost
+        {
+          return Some(StartCause::WaitCancelled {
+            start,
+            requested_resume,
+          });
+        }
+        pending.next_delayed_redraw()
+      };
+      if let Some(requested_resume) = requested_resume.filter(|resume| now >= *resume) {
+        return Some(StartCause::ResumeTimeReached {
+          start,
+          requested_resume,
+        });
+      }
+      let deadline = [
+        requested_resume,
+        next_timer,
+        next_delayed_redraw,
+        self.state.seat.next_key_repeat(),
+        timeout.map(|timeout| start + timeout),
+      ]
+      .iter()
+      .flatten()
+      .min()
+      .copied();
+      match deadline {
+        Some(deadline) if now >= deadline => return None,
+        Some(deadline) => self.read_events(Some(deadline - now)),
+        None => self.read_events(None),
+      }
+    }
+  }
+
+  /// Dispatches the events of the compositor that were already read.
+  fn dispatch_pending(&mut self) {
+    if self.connection_lost {
+      return;
+    }
+    if let Err(e) = self.event_queue.dispatch_pending(&mut self.state) {
+      log::error!("Lost the connection to the Wayland compositor: {}", e);
+      self.connection_lost = true;
+    }
+  }
+
+  /// Reads the events of the compositor, waiting up to `timeout` for them or for a wake up.
+  fn read_events(&mut self, timeout: Option<Duration>) {
+    let backend = &self.window_target.p.backend;
+    if let Err(e) = self.event_queue.flush() {
+      log::warn!("Failed to send the requests to the compositor: {}", e);
+    }
+    // The events already queued are dispatched first.
+    let guard = match self.event_queue.prepare_read() {
+      Some(guard) => guard,
+      None => return,
+    };
+
+    let mut fds = [
+      libc::pollfd {
+        fd: guard.connection_fd().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+      },
+      libc::pollfd {
+        fd: backend.wake_pipe.0,
+        events: libc::POLLIN,
+        revents: 0,
+      },
+    ];
+    let timeout = timeout.map_or(-1, |timeout| {
+      // Rounded up, so the deadline has passed once the wait times out.
+      let millis = timeout.as_millis() + (timeout.subsec_nanos() % 1_000_000 != 0) as u128;
+      millis.min(i32::MAX as u128) as i32
+    });
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+
+    if ready > 0 && fds[0].revents != 0 {
+      if let Err(e) = guard.read() {
+        log::error!("Lost the connection to the Wayland compositor: {}", e);
+        self.connection_lost = true;
+      }
+    } else {
+      drop(guard);
+    }
+    if ready > 0 && fds[1].revents != 0 {
+      backend.drain_wake_pipe();
+    }
+  }
+
+  /// Runs the actions of the signals received since the last call.
+  fn handle_signals(&self) {
+    for (signal, action) in &self.signal_actions {
+      if SIGNALS_RECEIVED[signal_index(*signal)].swap(false, Ordering::AcqRel) {
+        action();
+      }
+    }
+  }
+
+  fn dispatch_events<F>(&mut self, start_cause: StartCause, event_handler: &mut F)
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    self.dispatch_pending();
+    self.handle_signals();
+    self.call_event_handler(event_handler, Event::NewEvents(start_cause));
+
+    for id in self.window_target.p.take_due_timers() {
+      self.call_event_handler(event_handler, Event::Timer(id));
+    }
+
+    let backend = self.window_target.p.backend.clone();
+    let scale_changes = std::mem::take(&mut backend.pending.lock().unwrap().scale_changes);
+    for (window_id, scale_factor) in scale_changes {
+      let window = match backend.window(window_id) {
+        Some(window) => window,
+        None => continue,
+      };
+      #[allow(clippy::float_cmp)]
+      if window.scale_factor() == scale_factor {
+        continue;
+      }
+      let mut new_inner_size = window.logical_size().to_physical(scale_factor);
+      self.call_event_handler(
+        event_handler,
+        Event::WindowEvent {
+          window_id: RootWindowId(window_id),
+          event: WindowEvent::ScaleFactorChanged {
+            scale_factor,
+            new_inner_size: &mut new_inner_size,
+          },
+          timestamp: Instant::now(),
+        },
+      );
+      if let Some(size) = window.set_scale_factor(scale_factor, new_inner_size) {
+        backend.push_window_event(window_id, WindowEvent::Resized(size));
+      }
+      super::window::update_cursor(&backend, &window);
+      backend.request_redraw(window_id);
+    }
+
+    let (events, exit_requested) = {
+      let mut pending = backend.pending.lock().unwrap();
+      pending.woken = false;
+      (
+        std::mem::take(&mut pending.events),
+        std::mem::take(&mut pending.exit_requested),
+      )
+    };
+    let device_event_filter = self.window_target.p.device_event_filter.get();
+    for event in events {
+      if let Event::DeviceEvent { .. } = event {
+        if device_event_filter == DeviceEventFilter::Always {
+          continue;
+        }
+      }
+      if let Ok(event) = event.map_nonuser_event() {
+        self.call_event_handler(event_handler, event);
+      }
+    }
+
+    while let Some(event) = self.user_events.pop() {
+      self.call_event_handler(event_handler, Event::UserEvent(event));
+    }
+
+    if exit_requested && self.control_flow.exit_code().is_none() {
+      let mut prevent = false;
+      self.call_event_handler(
+        event_handler,
+        Event::ExitRequested {
+          prevent: &mut prevent,
+        },
+      );
+      if !prevent && self.control_flow.exit_code().is_none() {
+        self.control_flow = ControlFlow::Exit;
+      }
+    }
+
+    self.call_event_handler(event_handler, Event::MainEventsCleared);
+
+    let redraws = {
+      let mut pending = backend.pending.lock().unwrap();
+      pending.take_due_redraws(Instant::now());
+      std::mem::take(&mut pending.redraws)
+    };
+    for window_id in redraws {
+      self.call_event_handler(
+        event_handler,
+        Event::RedrawRequested(RootWindowId(window_id)),
+      );
+    }
+
+    self.call_event_handler(event_handler, Event::RedrawEventsCleared);
+
+    if let Some(event) = Event::idle(self.control_flow) {
+      self.call_event_handler(event_handler, event);
+    }
+
+    // The requests of the handler are sent before waiting.
+    backend.flush();
+  }
+
+  /// Calls `event_handler`, keeping the control flow at `ControlFlow::ExitWithCode` once it's set.
+  fn call_event_handler<F>(&mut self, event_handler: &mut F, event: Event<'_, T>)
+  where
+    F: FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    if self.control_flow.exit_code().is_none() {
+      event_handler(event, &self.window_target, &mut self.control_flow);
+    } else {
+      let mut control_flow = self.control_flow;
+      event_handler(event, &self.window_target, &mut control_flow);
+    }
+  }
+
+  pub fn window_target(&self) -> &event_loop::EventLoopWindowTarget<T> {
+    &self.window_target
+  }
+
+  pub fn set_signal_action(&mut self, signal: ExitSignal, action: Option<SignalAction<T>>)
+  where
+    T: Clone,
+  {
+    let action: Box<dyn Fn()> = match action {
+      Some(SignalAction::ExitRequested) => {
+        let backend = self.window_target.p.backend.clone();
+        Box::new(move || backend.request_exit())
+      }
+      Some(SignalAction::UserEvent(event)) => {
+        let proxy = self.create_proxy();
+        Box::new(move || {
+          if let Err(e) = proxy.send_event(event.clone()) {
+            log::warn!("Failed to send signal event to event loop: {}", e);
+          }
+        })
+      }
+      None => {
+        if self.signal_actions.remove(&signal).is_some() {
+          set_signal_handler(signal, false);
+        }
+        return;
+      }
+    };
+    SIGNAL_WAKE_FD.store(self.window_target.p.backend.wake_pipe.1, Ordering::Release);
+    set_signal_handler(signal, true);
+    self.signal_actions.insert(signal, action);
+  }
+
+  pub fn create_proxy(&self) -> EventLoopProxy<T> {
+    EventLoopProxy {
+      user_events: self.user_events.clone(),
+      backend: self.window_target.p.backend.clone(),
+    }
+  }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.user_events.set_capacity(priority, capacity);
+  }
+}
+
+impl<T: 'static> Drop for EventLoop<T> {
+  fn drop(&mut self) {
+    for signal in self.signal_actions.keys() {
+      set_signal_handler(*signal, false);
+    }
+    SIGNAL_WAKE_FD.store(-1, Ordering::Release);
+    self.user_events.close();
+  }
+}
+
+pub struct EventLoopProxy<T: 'static> {
+  user_events: Arc<UserEventQueue<T>>,
+  backend: Arc<Backend>,
+}
+
+impl<T> EventLoopProxy<T> {
+  pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+    self
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.user_events.push(event, priority, bounded)?;
+    self.backend.wake_up();
+    Ok(())
+  }
+
+  pub fn wake_up(&self) {
+    self.backend.wake_up();
+  }
+}
+
+impl<T> Clone for EventLoopProxy<T> {
+  fn clone(&self) -> Self {
+    EventLoopProxy {
+      user_events: self.user_events.clone(),
+      backend: self.backend.clone(),
+    }
+  }
+}
+
+struct Timer {
+  interval: Duration,
+  deadline: Instant,
+}
+
+pub struct EventLoopWindowTarget<T: 'static> {
+  pub(crate) backend: Arc<Backend>,
+  timers: RefCell<HashMap<TimerId, Timer>>,
+  device_event_filter: Cell<DeviceEventFilter>,
+  _marker: PhantomData<T>,
+}
+
+impl<T: 'static> EventLoopWindowTarget<T> {
+  // Wayland has no primary monitor, so it's the first one the compositor announced.
+  pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
+    self
+      .backend
+      .monitors
+      .lock()
+      .unwrap()
+      .first()
+      .map(|monitor| RootMonitorHandle {
+        inner: monitor.clone(),
+      })
+  }
+
+  pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
+    self
+      .backend
+      .monitors
+      .lock()
+      .unwrap()
+      .iter()
+      .cloned()
+      .collect()
+  }
+
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    Vec::new()
+  }
+
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn key_from_scancode(&self, scancode: u32) -> Option<Key<'static>> {
+    let input = self.backend.input.lock().unwrap();
+    input
+      .layout_keys
+      .iter()
+      .find(|(keycode, _)| *keycode == scancode)
+      .map(|(_, key)| key.clone())
+  }
+
+  pub fn scancode_from_key(&self, key: &Key<'_>) -> Option<u32> {
+    let input = self.backend.input.lock().unwrap();
+    // The keycodes of the numpad come after the keys typing the same.
+    input
+      .layout_keys
+      .iter()
+      .find(|(_, layout_key)| layout_key == key)
+      .map(|(keycode, _)| *keycode)
+  }
+
+  pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+    self.backend.input.lock().unwrap().key_repeat_info
+  }
+
+  pub fn modifiers_state(&self) -> ModifiersState {
+    self.backend.input.lock().unwrap().modifiers
+  }
+
+  pub fn lock_key_state(&self, key: LockKey) -> Option<bool> {
+    let input = self.backend.input.lock().unwrap();
+    input
+      .locked_keys
+      .as_ref()
+      .map(|locked_keys| locked_keys.contains(&key))
+  }
+
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    let timer = Timer {
+      interval,
+      deadline: Instant::now() + interval,
+    };
+    self.timers.borrow_mut().insert(id, timer);
+  }
+
+  pub fn cancel_timer(&self, id: TimerId) {
+    self.timers.borrow_mut().remove(&id);
+  }
+
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    self.device_event_filter.set(filter);
+  }
+
+  pub fn device_info(&self, _device_id: DeviceId) -> Option<crate::event::DeviceInfo> {
+    None
+  }
+
+  fn next_timer(&self) -> Option<Instant> {
+    self
+      .timers
+      .borrow()
+      .values()
+      .map(|timer| timer.deadline)
+      .min()
+  }
+
+  /// Returns the timers that are due, in the order of their deadlines, and restarts them.
+  fn take_due_timers(&self) -> Vec<TimerId> {
+    let now = Instant::now();
+    let mut timers = self.timers.borrow_mut();
+    let mut due = timers
+      .iter_mut()
+      .filter(|(_, timer)| timer.deadline <= now)
+      .map(|(id, timer)| {
+        let deadline = timer.deadline;
+        // A timer that fell behind skips the ticks it missed instead of firing them in a burst.
+        timer.deadline = (deadline + timer.interval).max(now);
+        (deadline, *id)
+      })
+      .collect::<Vec<_>>();
+    due.sort();
+    due.into_iter().map(|(_, id)| id).collect()
+  }
+}
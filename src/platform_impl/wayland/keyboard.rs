@@ -0,0 +1,365 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{keycode::keysym_to_dead_key, keycode_from_scancode, KeyEventExtra};
+use crate::{
+  event::{ComposeState, ElementState, KeyEvent},
+  keyboard::{Key, KeyCode, KeyLocation, KeyboardLayout, LockKey, ModifiersState, NativeKeyCode},
+};
+use std::{collections::HashSet, env, ffi::OsString, os::unix::io::RawFd, ptr, slice, sync::Mutex};
+use xkbcommon::xkb::{self, compose, keysyms::*};
+
+lazy_static! {
+  static ref KEY_STRINGS: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+}
+
+fn insert_or_get_key_str(string: String) -> &'static str {
+  let mut string_set = KEY_STRINGS.lock().unwrap();
+  if let Some(contained) = string_set.get(string.as_str()) {
+    return contained;
+  }
+  let static_str = Box::leak(string.into_boxed_str());
+  string_set.insert(static_str);
+  static_str
+}
+
+/// The keymap of a keyboard and the state of its modifiers and layout.
+pub(crate) struct Keyboard {
+  state: xkb::State,
+  keymap: xkb::Keymap,
+  /// The dead keys and compose sequences in progress, if the locale has a compose table.
+  compose: Option<compose::State>,
+}
+
+impl Keyboard {
+  /// Compiles the keymap the compositor shared in the `size` bytes of `fd`.
+  pub(crate) fn from_fd(fd: RawFd, size: usize) -> Option<Self> {
+    let keymap = unsafe { read_keymap(fd, size) }?;
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    let keymap = xkb::Keymap::new_from_string(
+      &context,
+      keymap,
+      xkb::KEYMAP_FORMAT_TEXT_V1,
+      xkb::KEYMAP_COMPILE_NO_FLAGS,
+    )?;
+    let state = xkb::State::new(&keymap);
+    let compose = compose::Table::new_from_locale(&context, &locale(), compose::COMPILE_NO_FLAGS)
+      .map(|table| compose::State::new(&table, compose::STATE_NO_FLAGS))
+      .ok();
+    Some(Keyboard {
+      state,
+      keymap,
+      compose,
+    })
+  }
+
+  pub(crate) fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+    self
+      .state
+      .update_mask(depressed, latched, locked, 0, 0, group);
+  }
+
+  pub(crate) fn modifiers(&self) -> ModifiersState {
+    let mut modifiers = ModifiersState::empty();
+    for &(name, modifier) in &[
+      (xkb::MOD_NAME_SHIFT, ModifiersState::SHIFT),
+      (xkb::MOD_NAME_CTRL, ModifiersState::CONTROL),
+      (xkb::MOD_NAME_ALT, ModifiersState::ALT),
+      (xkb::MOD_NAME_LOGO, ModifiersState::SUPER),
+    ] {
+      if self
+        .state
+        .mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE)
+      {
+        modifiers |= modifier;
+      }
+    }
+    modifiers
+  }
+
+  /// Whether the lock key is on, which is its modifier for Caps and Num Lock, and its LED for
+  /// Scroll Lock that has no standard modifier.
+  pub(crate) fn lock_key_state(&self, key: LockKey) -> bool {
+    match key {
+      LockKey::CapsLock => self
+        .state
+        .mod_name_is_active(xkb::MOD_NAME_CAPS, xkb::STATE_MODS_EFFECTIVE),
+      LockKey::NumLock => self
+        .state
+        .mod_name_is_active(xkb::MOD_NAME_NUM, xkb::STATE_MODS_EFFECTIVE),
+      LockKey::ScrollLock => self.state.led_name_is_active(xkb::LED_NAME_SCROLL),
+    }
+  }
+
+  /// The layout of the group the keys are typed with.
+  pub(crate) fn layout(&self) -> KeyboardLayout {
+    let layout = self.state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE);
+    KeyboardLayout {
+      id: self.keymap.layout_get_name(layout).to_owned(),
+    }
+  }
+
+  /// Returns the keys of the current layout without modifiers, by their X keycode.
+  pub(crate) fn layout_keys(&self) -> Vec<(u32, Key<'static>)> {
+    let layout = self.state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE);
+    (self.keymap.min_keycode()..=self.keymap.max_keycode())
+      .filter_map(|keycode| {
+        let keysym = *self
+          .keymap
+          .key_get_syms_by_level(keycode, layout, 0)
+          .first()?;
+        match keysym_to_key(keysym, keycode) {
+          Key::Unidentified(_) => None,
+          key => Some((keycode, key)),
+        }
+      })
+      .collect()
+  }
+
+  /// Whether the key is repeated while it's held, `key` being an evdev code.
+  pub(crate) fn repeats(&self, key: u32) -> bool {
+    self.keymap.key_repeats(key + 8)
+  }
+
+  /// Cancels the sequence in progress, when the keyboard leaves the window.
+  pub(crate) fn reset_compose(&mut self) {
+    if let Some(compose) = &mut self.compose {
+      compose.reset();
+    }
+  }
+
+  /// Feeds a pressed keysym to the compose state, returning the part it takes in a sequence and
+  /// the composed text.
+  fn feed_compose(&mut self, keysym: u32) -> Option<(ComposeState, Option<&'static str>)> {
+    let compose = self.compose.as_mut()?;
+    // The modifiers are ignored, without interrupting the sequence.
+    if let compose::FeedResult::Ignored = compose.feed(keysym) {
+      return None;
+    }
+    match compose.status() {
+      compose::Status::Composing => Some((ComposeState::Composing, None)),
+      compose::Status::Composed => {
+        let text = compose.utf8().and_then(non_empty);
+        compose.reset();
+        Some((ComposeState::Composed, text))
+      }
+      compose::Status::Cancelled => {
+        compose.reset();
+        Some((ComposeState::Cancelled, None))
+      }
+      compose::Status::Nothing => None,
+    }
+  }
+
+  /// Builds the event of a key, `key` being the evdev code sent by the compositor.
+  pub(crate) fn key_event(&mut self, key: u32, state: ElementState, repeat: bool) -> KeyEvent {
+    // The keycodes of xkb are the ones of X, offset by 8 from the evdev codes.
+    let keycode = key + 8;
+    let physical_key = keycode_from_scancode(keycode);
+    let keysym = self.state.key_get_one_sym(keycode);
+
+    let layout = self.state.key_get_layout(keycode);
+    let keysym_without_modifiers = self
+      .keymap
+      .key_get_syms_by_level(keycode, layout, 0)
+      .first()
+      .copied()
+      .unwrap_or(keysym);
+
+    let (mut text, text_with_all_modifiers) = if state == ElementState::Pressed {
+      (
+        non_empty(xkb::keysym_to_utf8(keysym)),
+        non_empty(self.state.key_get_utf8(keycode)),
+      )
+    } else {
+      (None, None)
+    };
+
+    let compose_state = match state {
+      ElementState::Pressed => self.feed_compose(keysym),
+      _ => None,
+    };
+    let compose_state = compose_state.map(|(compose_state, composed_text)| {
+      // The key cancelling a sequence keeps its own text, the keys before it being dropped.
+      if compose_state != ComposeState::Cancelled {
+        text = composed_text;
+      }
+      compose_state
+    });
+
+    KeyEvent {
+      physical_key,
+      logical_key: keysym_to_dead_key(keysym).unwrap_or_else(|| keysym_to_key(keysym, keycode)),
+      text,
+      location: keysym_to_location(keysym),
+      state,
+      repeat,
+      compose_state,
+      platform_specific: KeyEventExtra {
+        text_with_all_modifiers,
+        key_without_modifiers: match keysym_to_dead_key(keysym_without_modifiers) {
+          // A dead key is the character of its accent, like on the other platforms.
+          Some(Key::Dead(Some(accent))) => {
+            Key::Character(insert_or_get_key_str(accent.to_string()))
+          }
+          _ => keysym_to_key(keysym_without_modifiers, keycode),
+        },
+      },
+    }
+  }
+}
+
+/// Returns the locale of the compose table, the one of the typed characters.
+fn locale() -> OsString {
+  ["LC_ALL", "LC_CTYPE", "LANG"]
+    .iter()
+    .filter_map(|name| env::var_os(name))
+    .find(|locale| !locale.is_empty())
+    .unwrap_or_else(|| "C".into())
+}
+
+/// Copies the keymap out of the memory shared by the compositor.
+unsafe fn read_keymap(fd: RawFd, size: usize) -> Option<String> {
+  let map = libc::mmap(
+    ptr::null_mut(),
+    size,
+    libc::PROT_READ,
+    libc::MAP_PRIVATE,
+    fd,
+    0,
+  );
+  if map == libc::MAP_FAILED {
+    log::warn!("Failed to map the keymap of the keyboard");
+    return None;
+  }
+  let bytes = slice::from_raw_parts(map as *const u8, size);
+  // The keymap is a null-terminated string.
+  let keymap = bytes.split(|&byte| byte == 0).next().unwrap_or_default();
+  let keymap = String::from_utf8_lossy(keymap).into_owned();
+  libc::munmap(map, size);
+  Some(keymap)
+}
+
+/// Returns the text of a key, which xkbcommon gives as an empty string for the keys without text.
+fn non_empty(text: String) -> Option<&'static str> {
+  let text = text.trim_end_matches('\0');
+  if text.is_empty() {
+    None
+  } else {
+    Some(insert_or_get_key_str(text.to_owned()))
+  }
+}
+
+fn keysym_to_key(keysym: u32, keycode: u32) -> Key<'static> {
+  keysym_to_named_key(keysym).unwrap_or_else(|| {
+    match std::char::from_u32(xkb::keysym_to_utf32(keysym)) {
+      Some(key) if key >= ' ' && key != '\x7f' => {
+        Key::Character(insert_or_get_key_str(key.to_string()))
+      }
+      _ => Key::Unidentified(NativeKeyCode::Gtk(keycode as u16)),
+    }
+  })
+}
+
+#[allow(non_upper_case_globals)]
+fn keysym_to_named_key(keysym: u32) -> Option<Key<'static>> {
+  Some(match keysym {
+    KEY_Escape => Key::Escape,
+    KEY_BackSpace => Key::Backspace,
+    KEY_Tab | KEY_ISO_Left_Tab => Key::Tab,
+    KEY_Return => Key::Enter,
+    KEY_Control_L | KEY_Control_R => Key::Control,
+    KEY_Alt_L | KEY_Alt_R => Key::Alt,
+    KEY_Shift_L | KEY_Shift_R => Key::Shift,
+    KEY_Super_L | KEY_Super_R => Key::Super,
+    KEY_Caps_Lock => Key::CapsLock,
+    KEY_F1 => Key::F1,
+    KEY_F2 => Key::F2,
+    KEY_F3 => Key::F3,
+    KEY_F4 => Key::F4,
+    KEY_F5 => Key::F5,
+    KEY_F6 => Key::F6,
+    KEY_F7 => Key::F7,
+    KEY_F8 => Key::F8,
+    KEY_F9 => Key::F9,
+    KEY_F10 => Key::F10,
+    KEY_F11 => Key::F11,
+    KEY_F12 => Key::F12,
+
+    KEY_Print => Key::PrintScreen,
+    KEY_Scroll_Lock => Key::ScrollLock,
+    // Pause/Break not audio.
+    KEY_Pause => Key::Pause,
+
+    KEY_Insert => Key::Insert,
+    KEY_Delete => Key::Delete,
+    KEY_Home => Key::Home,
+    KEY_End => Key::End,
+    KEY_Page_Up => Key::PageUp,
+    KEY_Page_Down => Key::PageDown,
+    KEY_Num_Lock => Key::NumLock,
+
+    KEY_Up => Key::ArrowUp,
+    KEY_Down => Key::ArrowDown,
+    KEY_Left => Key::ArrowLeft,
+    KEY_Right => Key::ArrowRight,
+    KEY_Clear => Key::Clear,
+
+    KEY_Menu => Key::ContextMenu,
+    KEY_XF86WakeUp => Key::WakeUp,
+    KEY_XF86Launch0 => Key::LaunchApplication1,
+    KEY_XF86Launch1 => Key::LaunchApplication2,
+    KEY_ISO_Level3_Shift => Key::AltGraph,
+
+    KEY_KP_Begin => Key::Clear,
+    KEY_KP_Delete => Key::Delete,
+    KEY_KP_Down => Key::ArrowDown,
+    KEY_KP_End => Key::End,
+    KEY_KP_Enter => Key::Enter,
+    KEY_KP_F1 => Key::F1,
+    KEY_KP_F2 => Key::F2,
+    KEY_KP_F3 => Key::F3,
+    KEY_KP_F4 => Key::F4,
+    KEY_KP_Home => Key::Home,
+    KEY_KP_Insert => Key::Insert,
+    KEY_KP_Left => Key::ArrowLeft,
+    KEY_KP_Page_Down => Key::PageDown,
+    KEY_KP_Page_Up => Key::PageUp,
+    KEY_KP_Right => Key::ArrowRight,
+    KEY_KP_Tab => Key::Tab,
+    KEY_KP_Up => Key::ArrowUp,
+
+    KEY_XF86AudioPlay => Key::MediaPlayPause,
+    KEY_XF86AudioPause => Key::MediaPause,
+    KEY_XF86AudioStop => Key::MediaStop,
+    KEY_XF86AudioNext => Key::MediaTrackNext,
+    KEY_XF86AudioPrev => Key::MediaTrackPrevious,
+    KEY_XF86AudioRecord => Key::MediaRecord,
+    KEY_XF86AudioRewind => Key::MediaRewind,
+    KEY_XF86AudioForward => Key::MediaFastForward,
+    KEY_XF86AudioRaiseVolume => Key::AudioVolumeUp,
+    KEY_XF86AudioLowerVolume => Key::AudioVolumeDown,
+    KEY_XF86AudioMute => Key::AudioVolumeMute,
+    _ => return None,
+  })
+}
+
+#[allow(non_upper_case_globals)]
+fn keysym_to_location(keysym: u32) -> KeyLocation {
+  match keysym {
+    KEY_Control_L | KEY_Shift_L | KEY_Alt_L | KEY_Super_L | KEY_Meta_L => KeyLocation::Left,
+    KEY_Control_R | KEY_Shift_R | KEY_Alt_R | KEY_Super_R | KEY_Meta_R => KeyLocation::Right,
+    KEY_KP_0 | KEY_KP_1 | KEY_KP_2 | KEY_KP_3 | KEY_KP_4 | KEY_KP_5 | KEY_KP_6 | KEY_KP_7
+    | KEY_KP_8 | KEY_KP_9 | KEY_KP_Add | KEY_KP_Begin | KEY_KP_Decimal | KEY_KP_Delete
+    | KEY_KP_Divide | KEY_KP_Down | KEY_KP_End | KEY_KP_Enter | KEY_KP_Equal | KEY_KP_F1
+    | KEY_KP_F2 | KEY_KP_F3 | KEY_KP_F4 | KEY_KP_Home | KEY_KP_Insert | KEY_KP_Left
+    | KEY_KP_Multiply | KEY_KP_Page_Down | KEY_KP_Page_Up | KEY_KP_Right | KEY_KP_Separator
+    | KEY_KP_Space | KEY_KP_Subtract | KEY_KP_Tab | KEY_KP_Up => KeyLocation::Numpad,
+    _ => KeyLocation::Standard,
+  }
+}
+
+/// Returns the physical key of a raw key event, `key` being an evdev code.
+pub(crate) fn physical_key(key: u32) -> KeyCode {
+  keycode_from_scancode(key + 8)
+}
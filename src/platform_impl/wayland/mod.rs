@@ -0,0 +1,193 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! A backend talking to the Wayland compositor directly, used instead of the GTK one with the
+//! `wayland` feature.
+//!
+//! The windows are xdg-shell toplevels, decorated by the compositor through xdg-decoration when
+//! it supports it. The fractional scale factors come from wp-fractional-scale and are applied
+//! with wp-viewporter, `Window::set_cursor_grab` confines the pointer with pointer-constraints,
+//! `DeviceEvent::MouseMotion` comes from relative-pointer, and the input methods are used through
+//! text-input-v3. The backend doesn't draw client-side decorations, and has no menus, system tray,
+//! global shortcuts or clipboard.
+
+#[cfg(feature = "tray")]
+use crate::system_tray::SystemTray as RootSystemTray;
+use crate::{
+  accelerator::{Accelerator, AcceleratorId},
+  global_shortcut::{GlobalShortcut as RootGlobalShortcut, ShortcutManagerError},
+  keyboard::Key,
+  menu::{CustomMenuItem, MenuId, MenuItem, MenuType},
+};
+use std::fmt;
+
+mod clipboard;
+mod event_loop;
+mod keyboard;
+#[path = "../linux/keycode.rs"]
+mod keycode;
+mod monitor;
+mod seat;
+mod window;
+
+pub use self::{
+  clipboard::Clipboard,
+  event_loop::{current_keyboard_layout, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+  keycode::{keycode_from_scancode, keycode_to_scancode},
+  monitor::{MonitorHandle, VideoMode},
+  window::{CloseHandle, PlatformSpecificWindowBuilderAttributes, Window, WindowId},
+};
+
+pub(crate) use crate::icon::NoIcon as PlatformIcon;
+
+// Unsupported: Wayland has no native menus, they would have to be popups drawn by the application.
+#[derive(Debug, Clone)]
+pub struct MenuItemAttributes;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct KeyEventExtra {
+  pub text_with_all_modifiers: Option<&'static str>,
+  pub key_without_modifiers: Key<'static>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Menu;
+
+impl Default for Menu {
+  fn default() -> Self {
+    Menu::new()
+  }
+}
+
+impl Menu {
+  pub fn new() -> Self {
+    Menu {}
+  }
+  pub fn new_popup_menu() -> Self {
+    Self::new()
+  }
+  pub fn add_item(
+    &mut self,
+    _menu_id: MenuId,
+    _title: &str,
+    _accelerator: Option<Accelerator>,
+    _enabled: bool,
+    _selected: bool,
+    _menu_type: MenuType,
+  ) -> CustomMenuItem {
+    CustomMenuItem(MenuItemAttributes {})
+  }
+  pub fn add_submenu(&mut self, _title: &str, _enabled: bool, _submenu: Menu) {}
+  pub fn add_native_item(
+    &mut self,
+    _item: MenuItem,
+    _menu_type: MenuType,
+  ) -> Option<CustomMenuItem> {
+    None
+  }
+}
+
+impl MenuItemAttributes {
+  pub fn id(self) -> MenuId {
+    MenuId::EMPTY
+  }
+  pub fn set_enabled(&mut self, _is_enabled: bool) {}
+  pub fn set_title(&mut self, _title: &str) {}
+  pub fn set_selected(&mut self, _is_selected: bool) {}
+  pub fn set_icon(&mut self, _icon: Vec<u8>) {}
+}
+
+/// The seat of the input devices, identified by the name of its global.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeviceId(u32);
+
+impl DeviceId {
+  pub fn dummy() -> Self {
+    DeviceId(0)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VirtualDesktop;
+
+#[derive(Clone, Debug)]
+pub struct OsError(pub(crate) String);
+
+impl fmt::Display for OsError {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+    write!(fmt, "Wayland error: {}", self.0)
+  }
+}
+
+// Wayland leaves the global shortcuts to the compositor.
+#[derive(Debug)]
+pub struct ShortcutManager;
+
+impl ShortcutManager {
+  pub(crate) fn new<T>(_window_target: &crate::event_loop::EventLoopWindowTarget<T>) -> Self {
+    ShortcutManager
+  }
+
+  pub(crate) fn register(
+    &mut self,
+    _accelerator: Accelerator,
+  ) -> Result<RootGlobalShortcut, ShortcutManagerError> {
+    Err(ShortcutManagerError::InvalidAccelerator(
+      "Global shortcuts are not supported on Wayland".into(),
+    ))
+  }
+
+  pub(crate) fn unregister_all(&mut self) -> Result<(), ShortcutManagerError> {
+    Ok(())
+  }
+
+  pub(crate) fn unregister(
+    &self,
+    _shortcut: RootGlobalShortcut,
+  ) -> Result<(), ShortcutManagerError> {
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalShortcut {
+  pub(crate) accelerator: Accelerator,
+}
+
+impl GlobalShortcut {
+  pub fn id(&self) -> AcceleratorId {
+    self.accelerator.clone().id()
+  }
+}
+
+#[cfg(feature = "tray")]
+pub struct SystemTrayBuilder;
+
+#[cfg(feature = "tray")]
+impl SystemTrayBuilder {
+  // The tray of the GTK backend needs GTK, so the icon is ignored.
+  #[inline]
+  pub fn new<I>(_icon: I, _tray_menu: Option<Menu>) -> Self {
+    SystemTrayBuilder
+  }
+
+  #[inline]
+  pub fn build<T: 'static>(
+    self,
+    _window_target: &crate::event_loop::EventLoopWindowTarget<T>,
+  ) -> Result<RootSystemTray, crate::error::OsError> {
+    Err(os_error!(OsError(
+      "The system tray is not supported on Wayland".into()
+    )))
+  }
+}
+
+#[cfg(feature = "tray")]
+pub struct SystemTray;
+
+#[cfg(feature = "tray")]
+impl SystemTray {
+  pub fn set_icon<I>(&mut self, _icon: I) {}
+
+  pub fn set_menu(&mut self, _tray_menu: &Menu) {}
+}
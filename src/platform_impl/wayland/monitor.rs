@@ -0,0 +1,226 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  cmp::Ordering,
+  hash::{Hash, Hasher},
+  sync::{Arc, Mutex},
+};
+
+use wayland_client::{
+  protocol::wl_output::{self, WlOutput},
+  Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+
+use crate::{
+  dpi::{PhysicalPosition, PhysicalSize},
+  monitor,
+};
+
+use super::event_loop::State;
+
+/// The user data of an output, its info being shared with its `MonitorHandle`.
+pub(crate) struct OutputData {
+  /// The name of the global of the output.
+  pub(crate) name: u32,
+  pub(crate) info: Arc<Mutex<OutputInfo>>,
+}
+
+/// The state of an output, updated by its events.
+#[derive(Debug, Default)]
+pub(crate) struct OutputInfo {
+  pub(crate) name: Option<String>,
+  pub(crate) make: String,
+  pub(crate) model: String,
+  pub(crate) position: PhysicalPosition<i32>,
+  pub(crate) modes: Vec<Mode>,
+  pub(crate) scale: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Mode {
+  pub(crate) size: PhysicalSize<u32>,
+  pub(crate) refresh_rate_millihertz: u32,
+  pub(crate) current: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct MonitorHandle {
+  pub(crate) output: WlOutput,
+  pub(crate) info: Arc<Mutex<OutputInfo>>,
+}
+
+impl MonitorHandle {
+  pub(crate) fn new(output: WlOutput, info: Arc<Mutex<OutputInfo>>) -> Self {
+    MonitorHandle { output, info }
+  }
+
+  fn protocol_id(&self) -> u32 {
+    self.output.id().protocol_id()
+  }
+
+  pub fn name(&self) -> Option<String> {
+    let info = self.info.lock().unwrap();
+    info
+      .name
+      .clone()
+      .or_else(|| Some(format!("{} {}", info.make, info.model)))
+  }
+
+  pub fn size(&self) -> PhysicalSize<u32> {
+    let info = self.info.lock().unwrap();
+    info
+      .modes
+      .iter()
+      .find(|mode| mode.current)
+      .map_or_else(|| PhysicalSize::new(0, 0), |mode| mode.size)
+  }
+
+  pub fn position(&self) -> PhysicalPosition<i32> {
+    self.info.lock().unwrap().position
+  }
+
+  pub fn scale_factor(&self) -> f64 {
+    self.info.lock().unwrap().scale.max(1) as f64
+  }
+
+  pub fn video_modes(&self) -> impl Iterator<Item = monitor::VideoMode> {
+    let modes = self.info.lock().unwrap().modes.clone();
+    let monitor = self.clone();
+    modes.into_iter().map(move |mode| monitor::VideoMode {
+      video_mode: VideoMode {
+        size: mode.size.into(),
+        bit_depth: 32,
+        refresh_rate_millihertz: mode.refresh_rate_millihertz,
+        monitor: monitor.clone(),
+      },
+    })
+  }
+}
+
+// The outputs are identified by their proxy, the info changes over time.
+impl PartialEq for MonitorHandle {
+  fn eq(&self, other: &Self) -> bool {
+    self.output == other.output
+  }
+}
+
+impl Eq for MonitorHandle {}
+
+impl Hash for MonitorHandle {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.protocol_id().hash(state)
+  }
+}
+
+impl PartialOrd for MonitorHandle {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for MonitorHandle {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.protocol_id().cmp(&other.protocol_id())
+  }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VideoMode {
+  size: (u32, u32),
+  bit_depth: u16,
+  refresh_rate_millihertz: u32,
+  monitor: MonitorHandle,
+}
+
+impl VideoMode {
+  pub fn size(&self) -> PhysicalSize<u32> {
+    self.size.into()
+  }
+
+  pub fn bit_depth(&self) -> u16 {
+    self.bit_depth
+  }
+
+  pub fn refresh_rate(&self) -> u16 {
+    ((self.refresh_rate_millihertz + 500) / 1000) as u16
+  }
+
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate_millihertz
+  }
+
+  pub fn monitor(&self) -> monitor::MonitorHandle {
+    monitor::MonitorHandle {
+      inner: self.monitor.clone(),
+    }
+  }
+}
+
+impl Dispatch<WlOutput, OutputData> for State {
+  fn event(
+    state: &mut Self,
+    _: &WlOutput,
+    event: wl_output::Event,
+    data: &OutputData,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let mut info = data.info.lock().unwrap();
+    match event {
+      wl_output::Event::Geometry {
+        x, y, make, model, ..
+      } => {
+        info.position = PhysicalPosition::new(x, y);
+        info.make = make;
+        info.model = model;
+      }
+      wl_output::Event::Mode {
+        flags,
+        width,
+        height,
+        refresh,
+      } => {
+        let current = match flags {
+          WEnum::Value(flags) => flags.contains(wl_output::Mode::Current),
+          WEnum::Unknown(_) => false,
+        };
+        let size = PhysicalSize::new(width.max(0) as u32, height.max(0) as u32);
+        if current {
+          for mode in &mut info.modes {
+            mode.current = false;
+          }
+        }
+        let mode = Mode {
+          size,
+          refresh_rate_millihertz: refresh.max(0) as u32,
+          current,
+        };
+        match info.modes.iter_mut().find(|known| {
+          known.size == size && known.refresh_rate_millihertz == mode.refresh_rate_millihertz
+        }) {
+          Some(known) => known.current = current,
+          None => info.modes.push(mode),
+        }
+      }
+      wl_output::Event::Scale { factor } => info.scale = factor,
+      wl_output::Event::Name { name } => info.name = Some(name),
+      wl_output::Event::Done => {
+        drop(info);
+        // The windows follow the new scale of the output, the unchanged ones are skipped later.
+        let windows = state
+          .backend
+          .windows
+          .lock()
+          .unwrap()
+          .values()
+          .filter_map(|window| window.upgrade())
+          .collect::<Vec<_>>();
+        for window in windows {
+          window.update_output_scale(&state.backend);
+        }
+      }
+      _ => (),
+    }
+  }
+}
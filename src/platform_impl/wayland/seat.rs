@@ -0,0 +1,1031 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  collections::HashMap,
+  os::unix::io::AsRawFd,
+  time::{Duration, Instant},
+};
+
+use wayland_client::{
+  protocol::{
+    wl_keyboard::{self, KeymapFormat, WlKeyboard},
+    wl_pointer::{self, WlPointer},
+    wl_seat::{self, Capability, WlSeat},
+    wl_surface::WlSurface,
+    wl_touch::{self, WlTouch},
+  },
+  Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::wp::{
+  pointer_gestures::zv1::client::{
+    zwp_pointer_gesture_pinch_v1::{self, ZwpPointerGesturePinchV1},
+    zwp_pointer_gesture_swipe_v1::{self, ZwpPointerGestureSwipeV1},
+  },
+  relative_pointer::zv1::client::zwp_relative_pointer_v1::{self, ZwpRelativePointerV1},
+  text_input::zv3::client::zwp_text_input_v3::{self, ZwpTextInputV3},
+};
+
+use crate::{
+  click_count::{ClickCounter, DEFAULT_DISTANCE, DEFAULT_INTERVAL},
+  dpi::{LogicalPosition, PhysicalPosition},
+  event::{
+    DeviceEvent, ElementState, Event, Ime, ImeUnderline, KeyEvent, MouseButton, MouseScrollDelta,
+    RawKeyEvent, Touch, TouchPhase, WindowEvent,
+  },
+  keyboard::{Key, KeyRepeatInfo, KeyboardLayout, LockKey, ModifiersState},
+};
+
+use super::{
+  event_loop::{Backend, State},
+  keyboard::{self, Keyboard},
+  window::{self, WindowId},
+};
+
+/// The input objects of the seat used by the windows.
+#[derive(Default)]
+pub(crate) struct Input {
+  pub(crate) seat: Option<(u32, WlSeat)>,
+  pub(crate) pointer: Option<WlPointer>,
+  pub(crate) cursor_surface: Option<WlSurface>,
+  /// The window under the pointer, with the serial of the `enter` event needed to set the cursor.
+  pub(crate) pointer_focus: Option<(WindowId, u32)>,
+  /// The serial of the last button press or touch, needed to move the windows.
+  pub(crate) press_serial: Option<u32>,
+  pub(crate) text_input: Option<ZwpTextInputV3>,
+  /// The window that receives the text of the input method.
+  pub(crate) ime_focus: Option<WindowId>,
+  /// Whether the last preedit sent wasn't empty, so it's cleared before a commit.
+  pub(crate) has_ime_preedit: bool,
+  pub(crate) keyboard_layout: Option<KeyboardLayout>,
+  /// The keys of the layout without modifiers, by their X keycode, ordered by keycode.
+  pub(crate) layout_keys: Vec<(u32, Key<'static>)>,
+  /// The auto-repeat sent by the compositor with the keyboard.
+  pub(crate) key_repeat_info: Option<KeyRepeatInfo>,
+  /// The modifiers of the keyboard, kept after the windows lose the focus.
+  pub(crate) modifiers: ModifiersState,
+  /// The lock keys that are on, unknown before the compositor sends a keymap.
+  pub(crate) locked_keys: Option<Vec<LockKey>>,
+}
+
+/// The state of the devices of the seat, only used while dispatching their events.
+pub(crate) struct SeatState {
+  keyboard: Option<WlKeyboard>,
+  touch: Option<WlTouch>,
+  relative_pointer: Option<ZwpRelativePointerV1>,
+  swipe_gesture: Option<ZwpPointerGestureSwipeV1>,
+  pinch_gesture: Option<ZwpPointerGesturePinchV1>,
+  /// The window of the current swipe.
+  swipe_focus: Option<WindowId>,
+  /// The window of the current pinch, with its scale relative to its start.
+  pinch_focus: Option<(WindowId, f64)>,
+  keymap: Option<Keyboard>,
+  modifiers: ModifiersState,
+  keyboard_focus: Option<WindowId>,
+  repeat_info: RepeatInfo,
+  repeat: Option<KeyRepeat>,
+  pointer_focus: Option<WindowId>,
+  /// The position of the pointer on its window, in logical pixels.
+  pointer_position: (f64, f64),
+  click_counter: ClickCounter,
+  scroll: Scroll,
+  /// Whether the fingers are scrolling on a touchpad, until their `axis_stop`.
+  finger_scrolling: bool,
+  touches: HashMap<i32, (WindowId, PhysicalPosition<f64>)>,
+  /// The touches since the last `frame` of the touch, sent as `TouchFrame`.
+  touch_frame: Vec<(WindowId, Touch)>,
+  /// The preedit and the commit of the text input, applied by its `done` event.
+  ime_preedit: Option<(String, i32, i32)>,
+  ime_commit: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+struct RepeatInfo {
+  /// The keys repeated per second, or 0 when the keys aren't repeated.
+  rate: i32,
+  delay: Duration,
+}
+
+struct KeyRepeat {
+  key: u32,
+  window_id: WindowId,
+  deadline: Instant,
+}
+
+/// The scrolling of a pointer frame, in lines and in logical pixels.
+#[derive(Default)]
+struct Scroll {
+  lines: (f64, f64),
+  pixels: (f64, f64),
+  /// Whether the scrolling comes from fingers, which start and stop scrolling.
+  finger: bool,
+  /// Whether the fingers stopped scrolling.
+  stop: bool,
+}
+
+impl SeatState {
+  pub(crate) fn new() -> Self {
+    SeatState {
+      keyboard: None,
+      touch: None,
+      relative_pointer: None,
+      swipe_gesture: None,
+      pinch_gesture: None,
+      swipe_focus: None,
+      pinch_focus: None,
+      keymap: None,
+      modifiers: ModifiersState::empty(),
+      keyboard_focus: None,
+      repeat_info: RepeatInfo {
+        rate: 25,
+        delay: Duration::from_millis(600),
+      },
+      repeat: None,
+      pointer_focus: None,
+      pointer_position: (0.0, 0.0),
+      click_counter: ClickCounter::default(),
+      scroll: Scroll::default(),
+      finger_scrolling: false,
+      touches: HashMap::new(),
+      touch_frame: Vec::new(),
+      ime_preedit: None,
+      ime_commit: None,
+    }
+  }
+
+  pub(crate) fn next_key_repeat(&self) -> Option<Instant> {
+    self.repeat.as_ref().map(|repeat| repeat.deadline)
+  }
+
+  /// Sends the repeats of the held key that are due at `now`.
+  pub(crate) fn repeat_key(&mut self, backend: &Backend, now: Instant) {
+    let (keymap, repeat) = match (&mut self.keymap, &mut self.repeat) {
+      (Some(keymap), Some(repeat)) if repeat.deadline <= now => (keymap, repeat),
+      _ => return,
+    };
+    let interval = Duration::from_secs(1) / self.repeat_info.rate as u32;
+    // A repeat that fell behind is sent once instead of in a burst.
+    repeat.deadline = (repeat.deadline + interval).max(now);
+    let event = keymap.key_event(repeat.key, ElementState::Pressed, true);
+    push_key_event(backend, repeat.window_id, event, self.modifiers);
+  }
+
+  /// Stores the layout of the keymap, sending `Event::KeyboardLayoutChanged` when it changed.
+  ///
+  /// The keys of the layout are stored again if it changed or `keymap_changed` is `true`.
+  fn update_layout(&self, backend: &Backend, keymap_changed: bool) {
+    let keymap = match &self.keymap {
+      Some(keymap) => keymap,
+      None => return,
+    };
+    let layout = keymap.layout();
+    let mut input = backend.input.lock().unwrap();
+    let previous = input.keyboard_layout.replace(layout.clone());
+    let changed = previous.as_ref() != Some(&layout);
+    if changed || keymap_changed {
+      input.layout_keys = keymap.layout_keys();
+    }
+    drop(input);
+    // The first layout is the one the application starts with.
+    if changed && previous.is_some() {
+      backend.push(Event::KeyboardLayoutChanged(layout));
+    }
+  }
+
+  /// Releases the devices of the seat, when it's removed.
+  pub(crate) fn release(&mut self) {
+    if let Some(keyboard) = self.keyboard.take() {
+      release_keyboard(keyboard);
+    }
+    if let Some(touch) = self.touch.take() {
+      release_touch(touch);
+    }
+    if let Some(relative_pointer) = self.relative_pointer.take() {
+      relative_pointer.destroy();
+    }
+    self.release_gestures();
+    self.repeat = None;
+    self.keyboard_focus = None;
+    self.pointer_focus = None;
+  }
+
+  /// Releases the gestures of the pointer, when it's removed.
+  fn release_gestures(&mut self) {
+    if let Some(swipe_gesture) = self.swipe_gesture.take() {
+      swipe_gesture.destroy();
+    }
+    if let Some(pinch_gesture) = self.pinch_gesture.take() {
+      pinch_gesture.destroy();
+    }
+    self.swipe_focus = None;
+    self.pinch_focus = None;
+  }
+
+  /// Converts a position in the logical coordinates of a surface to physical ones.
+  fn physical_position(
+    backend: &Backend,
+    window_id: WindowId,
+    x: f64,
+    y: f64,
+  ) -> PhysicalPosition<f64> {
+    let scale_factor = backend
+      .window(window_id)
+      .map_or(1.0, |window| window.scale_factor());
+    LogicalPosition::new(x, y).to_physical(scale_factor)
+  }
+
+  /// Sends the scrolling accumulated in the current pointer frame.
+  fn send_scroll(&mut self, backend: &Backend) {
+    let scroll = std::mem::take(&mut self.scroll);
+    let window_id = match self.pointer_focus {
+      Some(window_id) => window_id,
+      None => return,
+    };
+    // Wayland scrolls down and right with positive values, the other way around from tao.
+    let delta = if scroll.lines != (0.0, 0.0) {
+      MouseScrollDelta::LineDelta(-scroll.lines.0 as f32, -scroll.lines.1 as f32)
+    } else if scroll.pixels != (0.0, 0.0) {
+      MouseScrollDelta::PixelDelta(Self::physical_position(
+        backend,
+        window_id,
+        -scroll.pixels.0,
+        -scroll.pixels.1,
+      ))
+    } else if scroll.stop && self.finger_scrolling {
+      MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, 0.0))
+    } else {
+      return;
+    };
+    let phase = if scroll.stop && self.finger_scrolling {
+      self.finger_scrolling = false;
+      TouchPhase::Ended
+    } else if scroll.finger && !std::mem::replace(&mut self.finger_scrolling, true) {
+      TouchPhase::Started
+    } else {
+      TouchPhase::Moved
+    };
+    #[allow(deprecated)]
+    backend.push_window_event(
+      window_id,
+      WindowEvent::MouseWheel {
+        device_id: backend.device_id(),
+        delta,
+        phase,
+        momentum: false,
+        modifiers: self.modifiers,
+      },
+    );
+  }
+
+  fn send_touch(
+    backend: &Backend,
+    frame: &mut Vec<(WindowId, Touch)>,
+    window_id: WindowId,
+    id: i32,
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+  ) {
+    let touch = Touch {
+      device_id: backend.device_id(),
+      phase,
+      location,
+      force: None,
+      id: id as u64,
+    };
+    backend.push_window_event(window_id, WindowEvent::Touch(touch));
+    frame.push((window_id, touch));
+  }
+
+  /// Sends the touches of a frame to their windows.
+  fn send_touch_frame(backend: &Backend, frame: &mut Vec<(WindowId, Touch)>) {
+    while let Some(&(window_id, _)) = frame.first() {
+      let (touches, rest): (Vec<_>, Vec<_>) = frame
+        .drain(..)
+        .partition(|&(touch_window_id, _)| touch_window_id == window_id);
+      *frame = rest;
+      backend.push_window_event(
+        window_id,
+        WindowEvent::TouchFrame(touches.into_iter().map(|(_, touch)| touch).collect()),
+      );
+    }
+  }
+}
+
+fn release_keyboard(keyboard: WlKeyboard) {
+  if keyboard.version() >= 3 {
+    keyboard.release();
+  }
+}
+
+fn release_touch(touch: WlTouch) {
+  if touch.version() >= 3 {
+    touch.release();
+  }
+}
+
+fn release_pointer(pointer: WlPointer) {
+  if pointer.version() >= 3 {
+    pointer.release();
+  }
+}
+
+/// Sends a key event, followed by its text if the window's text input is started.
+fn push_key_event(
+  backend: &Backend,
+  window_id: WindowId,
+  event: KeyEvent,
+  modifiers: ModifiersState,
+) {
+  let text = match backend.window(window_id) {
+    Some(window) if window.text_input() => event.received_text(modifiers),
+    _ => None,
+  };
+  backend.push_window_event(
+    window_id,
+    WindowEvent::KeyboardInput {
+      device_id: backend.device_id(),
+      event,
+      is_synthetic: false,
+    },
+  );
+  if let Some(text) = text {
+    backend.push_window_event(window_id, WindowEvent::ReceivedText(text));
+  }
+}
+
+/// Returns the window of a surface, `None` for the surfaces of the cursors.
+fn surface_window(surface: &WlSurface) -> Option<WindowId> {
+  surface.data::<WindowId>().copied()
+}
+
+impl Dispatch<WlSeat, u32> for State {
+  fn event(
+    state: &mut Self,
+    seat: &WlSeat,
+    event: wl_seat::Event,
+    name: &u32,
+    _: &Connection,
+    qh: &QueueHandle<Self>,
+  ) {
+    let capabilities = match event {
+      wl_seat::Event::Capabilities {
+        capabilities: WEnum::Value(capabilities),
+      } => capabilities,
+      _ => return,
+    };
+    let globals = &state.backend.globals;
+    let mut input = state.backend.input.lock().unwrap();
+
+    if capabilities.contains(Capability::Pointer) {
+      if input.pointer.is_none() {
+        let pointer = seat.get_pointer(qh, *name);
+        state.seat.relative_pointer = globals
+          .relative_pointer_manager
+          .as_ref()
+          .map(|manager| manager.get_relative_pointer(&pointer, qh, *name));
+        if let Some(pointer_gestures) = &globals.pointer_gestures {
+          state.seat.swipe_gesture = Some(pointer_gestures.get_swipe_gesture(&pointer, qh, *name));
+          state.seat.pinch_gesture = Some(pointer_gestures.get_pinch_gesture(&pointer, qh, *name));
+        }
+        input.cursor_surface = Some(globals.compositor.create_surface(qh, ()));
+        input.pointer = Some(pointer);
+      }
+    } else if let Some(pointer) = input.pointer.take() {
+      release_pointer(pointer);
+      if let Some(relative_pointer) = state.seat.relative_pointer.take() {
+        relative_pointer.destroy();
+      }
+      state.seat.release_gestures();
+      if let Some(cursor_surface) = input.cursor_surface.take() {
+        cursor_surface.destroy();
+      }
+      input.pointer_focus = None;
+      state.seat.pointer_focus = None;
+    }
+
+    if capabilities.contains(Capability::Keyboard) {
+      if state.seat.keyboard.is_none() {
+        state.seat.keyboard = Some(seat.get_keyboard(qh, *name));
+        input.text_input = globals
+          .text_input_manager
+          .as_ref()
+          .map(|manager| manager.get_text_input(seat, qh, *name));
+      }
+    } else if let Some(keyboard) = state.seat.keyboard.take() {
+      release_keyboard(keyboard);
+      if let Some(text_input) = input.text_input.take() {
+        text_input.destroy();
+      }
+      state.seat.repeat = None;
+      state.seat.keyboard_focus = None;
+      input.ime_focus = None;
+    }
+
+    if capabilities.contains(Capability::Touch) {
+      if state.seat.touch.is_none() {
+        state.seat.touch = Some(seat.get_touch(qh, *name));
+      }
+    } else if let Some(touch) = state.seat.touch.take() {
+      release_touch(touch);
+      state.seat.touches.clear();
+      state.seat.touch_frame.clear();
+    }
+  }
+}
+
+impl Dispatch<WlPointer, u32> for State {
+  fn event(
+    state: &mut Self,
+    pointer: &WlPointer,
+    event: wl_pointer::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    let seat = &mut state.seat;
+    let device_id = backend.device_id();
+    match event {
+      wl_pointer::Event::Enter {
+        serial,
+        surface,
+        surface_x,
+        surface_y,
+      } => {
+        let window_id = match surface_window(&surface) {
+          Some(window_id) => window_id,
+          None => return,
+        };
+        seat.pointer_focus = Some(window_id);
+        seat.pointer_position = (surface_x, surface_y);
+        backend.input.lock().unwrap().pointer_focus = Some((window_id, serial));
+        if let Some(window) = backend.window(window_id) {
+          window::update_cursor(backend, &window);
+        }
+        backend.push_window_event(window_id, WindowEvent::CursorEntered { device_id });
+        #[allow(deprecated)]
+        backend.push_window_event(
+          window_id,
+          WindowEvent::CursorMoved {
+            device_id,
+            position: SeatState::physical_position(backend, window_id, surface_x, surface_y),
+            modifiers: seat.modifiers,
+          },
+        );
+      }
+      wl_pointer::Event::Leave { .. } => {
+        backend.input.lock().unwrap().pointer_focus = None;
+        if let Some(window_id) = seat.pointer_focus.take() {
+          backend.push_window_event(window_id, WindowEvent::CursorLeft { device_id });
+        }
+      }
+      wl_pointer::Event::Motion {
+        surface_x,
+        surface_y,
+        ..
+      } => {
+        seat.pointer_position = (surface_x, surface_y);
+        if let Some(window_id) = seat.pointer_focus {
+          #[allow(deprecated)]
+          backend.push_window_event(
+            window_id,
+            WindowEvent::CursorMoved {
+              device_id,
+              position: SeatState::physical_position(backend, window_id, surface_x, surface_y),
+              modifiers: seat.modifiers,
+            },
+          );
+        }
+      }
+      wl_pointer::Event::Button {
+        serial,
+        button,
+        state: button_state,
+        ..
+      } => {
+        let window_id = match seat.pointer_focus {
+          Some(window_id) => window_id,
+          None => return,
+        };
+        let state = match button_state {
+          WEnum::Value(wl_pointer::ButtonState::Pressed) => {
+            backend.input.lock().unwrap().press_serial = Some(serial);
+            ElementState::Pressed
+          }
+          _ => ElementState::Released,
+        };
+        // The buttons are the `BTN_*` codes of evdev.
+        let button = match button {
+          0x110 => MouseButton::Left,
+          0x111 => MouseButton::Right,
+          0x112 => MouseButton::Middle,
+          // `BTN_SIDE` and `BTN_BACK`, `BTN_EXTRA` and `BTN_FORWARD`.
+          0x113 | 0x116 => MouseButton::Back,
+          0x114 | 0x115 => MouseButton::Forward,
+          button => MouseButton::Other(button as u16),
+        };
+        // A press in a drag region moves, resizes or acts on the window instead.
+        if state == ElementState::Pressed && button == MouseButton::Left {
+          let (x, y) = seat.pointer_position;
+          if backend.window(window_id).is_some_and(|window| {
+            window.press_drag_region(backend, LogicalPosition::new(x, y), serial)
+          }) {
+            return;
+          }
+        }
+        // The compositors don't share a double-click time, so the one of GTK is used.
+        let click_count = match state {
+          ElementState::Pressed => seat.click_counter.press(
+            button,
+            Instant::now(),
+            seat.pointer_position,
+            DEFAULT_INTERVAL,
+            (DEFAULT_DISTANCE, DEFAULT_DISTANCE),
+          ),
+          _ => seat.click_counter.release(button),
+        };
+        #[allow(deprecated)]
+        backend.push_window_event(
+          window_id,
+          WindowEvent::MouseInput {
+            device_id,
+            state,
+            button,
+            click_count,
+            modifiers: seat.modifiers,
+          },
+        );
+      }
+      wl_pointer::Event::Axis { axis, value, .. } => {
+        match axis {
+          WEnum::Value(wl_pointer::Axis::HorizontalScroll) => seat.scroll.pixels.0 += value,
+          WEnum::Value(wl_pointer::Axis::VerticalScroll) => seat.scroll.pixels.1 += value,
+          _ => (),
+        }
+        // The pointers before version 5 have no frames, every event is sent on its own.
+        if pointer.version() < 5 {
+          seat.send_scroll(backend);
+        }
+      }
+      wl_pointer::Event::AxisDiscrete { axis, discrete } => match axis {
+        WEnum::Value(wl_pointer::Axis::HorizontalScroll) => seat.scroll.lines.0 += discrete as f64,
+        WEnum::Value(wl_pointer::Axis::VerticalScroll) => seat.scroll.lines.1 += discrete as f64,
+        _ => (),
+      },
+      wl_pointer::Event::AxisSource {
+        axis_source: WEnum::Value(axis_source),
+      } => {
+        seat.scroll.finger = matches!(
+          axis_source,
+          wl_pointer::AxisSource::Finger | wl_pointer::AxisSource::Continuous
+        )
+      }
+      wl_pointer::Event::AxisStop { .. } => seat.scroll.stop = true,
+      wl_pointer::Event::Frame => seat.send_scroll(backend),
+      _ => (),
+    }
+  }
+}
+
+impl Dispatch<WlKeyboard, u32> for State {
+  fn event(
+    state: &mut Self,
+    _: &WlKeyboard,
+    event: wl_keyboard::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    let seat = &mut state.seat;
+    let device_id = backend.device_id();
+    match event {
+      wl_keyboard::Event::Keymap { format, fd, size } => {
+        if format == WEnum::Value(KeymapFormat::XkbV1) {
+          seat.keymap = Keyboard::from_fd(fd.as_raw_fd(), size as usize);
+          seat.update_layout(backend, true);
+        } else {
+          log::warn!("The keymap of the keyboard has an unknown format");
+        }
+      }
+      wl_keyboard::Event::Enter { surface, .. } => {
+        if let Some(window_id) = surface_window(&surface) {
+          seat.keyboard_focus = Some(window_id);
+          backend.push_window_event(window_id, WindowEvent::Focused(true));
+        }
+      }
+      wl_keyboard::Event::Leave { .. } => {
+        seat.repeat = None;
+        if let Some(keymap) = &mut seat.keymap {
+          keymap.reset_compose();
+        }
+        if let Some(window_id) = seat.keyboard_focus.take() {
+          if !seat.modifiers.is_empty() {
+            seat.modifiers = ModifiersState::empty();
+            backend.push_window_event(
+              window_id,
+              WindowEvent::ModifiersChanged(ModifiersState::empty()),
+            );
+          }
+          backend.push_window_event(window_id, WindowEvent::Focused(false));
+        }
+      }
+      wl_keyboard::Event::Key {
+        key,
+        state: key_state,
+        ..
+      } => {
+        let (keymap, window_id) = match (&mut seat.keymap, seat.keyboard_focus) {
+          (Some(keymap), Some(window_id)) => (keymap, window_id),
+          _ => return,
+        };
+        let state = match key_state {
+          WEnum::Value(wl_keyboard::KeyState::Pressed) => ElementState::Pressed,
+          _ => ElementState::Released,
+        };
+        backend.push(Event::DeviceEvent {
+          device_id,
+          event: DeviceEvent::Key(RawKeyEvent {
+            physical_key: keyboard::physical_key(key),
+            state,
+          }),
+          timestamp: Instant::now(),
+        });
+        let event = keymap.key_event(key, state, false);
+        push_key_event(backend, window_id, event, seat.modifiers);
+
+        if state == ElementState::Pressed {
+          if seat.repeat_info.rate > 0 && keymap.repeats(key) {
+            seat.repeat = Some(KeyRepeat {
+              key,
+              window_id,
+              deadline: Instant::now() + seat.repeat_info.delay,
+            });
+          }
+        } else if seat
+          .repeat
+          .as_ref()
+          .map_or(false, |repeat| repeat.key == key)
+        {
+          seat.repeat = None;
+        }
+      }
+      wl_keyboard::Event::Modifiers {
+        mods_depressed,
+        mods_latched,
+        mods_locked,
+        group,
+        ..
+      } => {
+        let keymap = match &mut seat.keymap {
+          Some(keymap) => keymap,
+          None => return,
+        };
+        keymap.update_mask(mods_depressed, mods_latched, mods_locked, group);
+        let modifiers = keymap.modifiers();
+        {
+          let mut input = backend.input.lock().unwrap();
+          input.modifiers = modifiers;
+          input.locked_keys = Some(
+            [LockKey::CapsLock, LockKey::NumLock, LockKey::ScrollLock]
+              .iter()
+              .copied()
+              .filter(|&key| keymap.lock_key_state(key))
+              .collect(),
+          );
+        }
+        if modifiers != seat.modifiers {
+          seat.modifiers = modifiers;
+          if let Some(window_id) = seat.keyboard_focus {
+            backend.push_window_event(window_id, WindowEvent::ModifiersChanged(modifiers));
+          }
+        }
+        // The group is the layout.
+        seat.update_layout(backend, false);
+      }
+      wl_keyboard::Event::RepeatInfo { rate, delay } => {
+        seat.repeat_info = RepeatInfo {
+          rate,
+          delay: Duration::from_millis(delay.max(0) as u64),
+        };
+        if rate <= 0 {
+          seat.repeat = None;
+        }
+        backend.input.lock().unwrap().key_repeat_info = Some(KeyRepeatInfo {
+          delay: seat.repeat_info.delay,
+          rate: rate.max(0) as f64,
+        });
+      }
+      _ => (),
+    }
+  }
+}
+
+impl Dispatch<WlTouch, u32> for State {
+  fn event(
+    state: &mut Self,
+    _: &WlTouch,
+    event: wl_touch::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    let touches = &mut state.seat.touches;
+    let frame = &mut state.seat.touch_frame;
+    match event {
+      wl_touch::Event::Down {
+        serial,
+        surface,
+        id,
+        x,
+        y,
+        ..
+      } => {
+        let window_id = match surface_window(&surface) {
+          Some(window_id) => window_id,
+          None => return,
+        };
+        backend.input.lock().unwrap().press_serial = Some(serial);
+        let location = SeatState::physical_position(backend, window_id, x, y);
+        touches.insert(id, (window_id, location));
+        SeatState::send_touch(backend, frame, window_id, id, TouchPhase::Started, location);
+      }
+      wl_touch::Event::Motion { id, x, y, .. } => {
+        if let Some((window_id, location)) = touches.get_mut(&id) {
+          *location = SeatState::physical_position(backend, *window_id, x, y);
+          SeatState::send_touch(backend, frame, *window_id, id, TouchPhase::Moved, *location);
+        }
+      }
+      wl_touch::Event::Up { id, .. } => {
+        if let Some((window_id, location)) = touches.remove(&id) {
+          SeatState::send_touch(backend, frame, window_id, id, TouchPhase::Ended, location);
+        }
+      }
+      wl_touch::Event::Cancel => {
+        frame.clear();
+        for (id, (window_id, location)) in touches.drain() {
+          SeatState::send_touch(
+            backend,
+            frame,
+            window_id,
+            id,
+            TouchPhase::Cancelled,
+            location,
+          );
+        }
+        // The cancellation isn't followed by a frame.
+        SeatState::send_touch_frame(backend, frame);
+      }
+      wl_touch::Event::Frame => SeatState::send_touch_frame(backend, frame),
+      _ => (),
+    }
+  }
+}
+
+impl Dispatch<ZwpRelativePointerV1, u32> for State {
+  fn event(
+    state: &mut Self,
+    _: &ZwpRelativePointerV1,
+    event: zwp_relative_pointer_v1::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    if let zwp_relative_pointer_v1::Event::RelativeMotion {
+      dx_unaccel,
+      dy_unaccel,
+      ..
+    } = event
+    {
+      let backend = &state.backend;
+      backend.push(Event::DeviceEvent {
+        device_id: backend.device_id(),
+        event: DeviceEvent::MouseMotion {
+          delta: (dx_unaccel, dy_unaccel),
+        },
+        timestamp: Instant::now(),
+      });
+    }
+  }
+}
+
+/// The phase of a gesture ending, which is cancelled when the fingers didn't complete it.
+fn gesture_end_phase(cancelled: i32) -> TouchPhase {
+  if cancelled != 0 {
+    TouchPhase::Cancelled
+  } else {
+    TouchPhase::Ended
+  }
+}
+
+impl Dispatch<ZwpPointerGestureSwipeV1, u32> for State {
+  fn event(
+    state: &mut Self,
+    _: &ZwpPointerGestureSwipeV1,
+    event: zwp_pointer_gesture_swipe_v1::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    let device_id = backend.device_id();
+    let (window_id, delta, phase) = match event {
+      zwp_pointer_gesture_swipe_v1::Event::Begin { surface, .. } => {
+        state.seat.swipe_focus = surface_window(&surface);
+        match state.seat.swipe_focus {
+          Some(window_id) => (window_id, (0.0, 0.0), TouchPhase::Started),
+          None => return,
+        }
+      }
+      zwp_pointer_gesture_swipe_v1::Event::Update { dx, dy, .. } => match state.seat.swipe_focus {
+        Some(window_id) => (window_id, (dx, dy), TouchPhase::Moved),
+        None => return,
+      },
+      zwp_pointer_gesture_swipe_v1::Event::End { cancelled, .. } => {
+        match state.seat.swipe_focus.take() {
+          Some(window_id) => (window_id, (0.0, 0.0), gesture_end_phase(cancelled)),
+          None => return,
+        }
+      }
+      _ => return,
+    };
+    let delta = SeatState::physical_position(backend, window_id, delta.0, delta.1);
+    backend.push_window_event(
+      window_id,
+      WindowEvent::SwipeGesture {
+        device_id,
+        delta,
+        phase,
+      },
+    );
+  }
+}
+
+impl Dispatch<ZwpPointerGesturePinchV1, u32> for State {
+  fn event(
+    state: &mut Self,
+    _: &ZwpPointerGesturePinchV1,
+    event: zwp_pointer_gesture_pinch_v1::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    let device_id = backend.device_id();
+    let (window_id, delta, rotation, phase) = match event {
+      zwp_pointer_gesture_pinch_v1::Event::Begin { surface, .. } => {
+        state.seat.pinch_focus = surface_window(&surface).map(|window_id| (window_id, 1.0));
+        match state.seat.pinch_focus {
+          Some((window_id, _)) => (window_id, 0.0, 0.0, TouchPhase::Started),
+          None => return,
+        }
+      }
+      zwp_pointer_gesture_pinch_v1::Event::Update {
+        scale, rotation, ..
+      } => match &mut state.seat.pinch_focus {
+        Some((window_id, previous_scale)) => {
+          let delta = scale - std::mem::replace(previous_scale, scale);
+          // The rotation is clockwise.
+          (*window_id, delta, -rotation, TouchPhase::Moved)
+        }
+        None => return,
+      },
+      zwp_pointer_gesture_pinch_v1::Event::End { cancelled, .. } => {
+        match state.seat.pinch_focus.take() {
+          Some((window_id, _)) => (window_id, 0.0, 0.0, gesture_end_phase(cancelled)),
+          None => return,
+        }
+      }
+      _ => return,
+    };
+    backend.push_window_event(
+      window_id,
+      WindowEvent::PinchGesture {
+        device_id,
+        delta,
+        phase,
+      },
+    );
+    backend.push_window_event(
+      window_id,
+      WindowEvent::RotationGesture {
+        device_id,
+        delta: rotation as f32,
+        phase,
+      },
+    );
+  }
+}
+
+impl Dispatch<ZwpTextInputV3, u32> for State {
+  fn event(
+    state: &mut Self,
+    text_input: &ZwpTextInputV3,
+    event: zwp_text_input_v3::Event,
+    _: &u32,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    match event {
+      zwp_text_input_v3::Event::Enter { surface } => {
+        let window_id = match surface_window(&surface) {
+          Some(window_id) => window_id,
+          None => return,
+        };
+        backend.input.lock().unwrap().ime_focus = Some(window_id);
+        if let Some(window) = backend.window(window_id) {
+          if window::enable_ime(text_input, &window) {
+            backend.push_window_event(window_id, WindowEvent::Ime(Ime::Enabled));
+          }
+        }
+      }
+      zwp_text_input_v3::Event::Leave { .. } => {
+        let (window_id, has_preedit) = {
+          let mut input = backend.input.lock().unwrap();
+          let has_preedit = std::mem::replace(&mut input.has_ime_preedit, false);
+          (input.ime_focus.take(), has_preedit)
+        };
+        text_input.disable();
+        text_input.commit();
+        // The window disallowing the IME already sent `Disabled`.
+        if let Some(window_id) = window_id {
+          if backend
+            .window(window_id)
+            .map_or(false, |window| window.ime_allowed())
+          {
+            if has_preedit {
+              backend.push_window_event(window_id, WindowEvent::Ime(Ime::preedit(String::new())));
+            }
+            backend.push_window_event(window_id, WindowEvent::Ime(Ime::Disabled));
+          }
+        }
+      }
+      zwp_text_input_v3::Event::PreeditString {
+        text,
+        cursor_begin,
+        cursor_end,
+      } => {
+        state.seat.ime_preedit = text.map(|text| (text, cursor_begin, cursor_end));
+      }
+      zwp_text_input_v3::Event::CommitString { text } => state.seat.ime_commit = text,
+      zwp_text_input_v3::Event::Done { .. } => {
+        let seat = &mut state.seat;
+        // The preedit is replaced by each `done`, being cleared if none was sent.
+        let preedit = seat
+          .ime_preedit
+          .take()
+          .filter(|(text, ..)| !text.is_empty());
+        let commit = seat.ime_commit.take();
+        let window_id = match backend.input.lock().unwrap().ime_focus {
+          Some(window_id) => window_id,
+          None => return,
+        };
+        // The text sent before the input method was disabled is dropped.
+        if !backend
+          .window(window_id)
+          .map_or(false, |window| window.ime_allowed())
+        {
+          return;
+        }
+        let mut input = backend.input.lock().unwrap();
+        if input.has_ime_preedit && (preedit.is_none() || commit.is_some()) {
+          input.has_ime_preedit = false;
+          backend.push_window_event(window_id, WindowEvent::Ime(Ime::preedit(String::new())));
+        }
+        if let Some(text) = commit {
+          backend.push_window_event(window_id, WindowEvent::Ime(Ime::Commit(text.clone())));
+          if backend
+            .window(window_id)
+            .map_or(false, |window| window.text_input())
+          {
+            backend.push_window_event(window_id, WindowEvent::ReceivedText(text));
+          }
+        }
+        if let Some((text, cursor_begin, cursor_end)) = preedit {
+          input.has_ime_preedit = true;
+          // The cursor is in bytes, and hidden when it's negative.
+          let cursor = if cursor_begin < 0 || cursor_end < 0 {
+            None
+          } else {
+            Some((cursor_begin as usize, cursor_end as usize))
+          };
+          let underlines = vec![ImeUnderline {
+            range: (0, text.len()),
+            is_target: false,
+          }];
+          backend.push_window_event(
+            window_id,
+            WindowEvent::Ime(Ime::Preedit {
+              text,
+              cursor,
+              underlines,
+            }),
+          );
+        }
+      }
+      _ => (),
+    }
+  }
+}
@@ -0,0 +1,1339 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  collections::{hash_map::Entry, VecDeque},
+  convert::TryFrom,
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+  },
+  time::Duration,
+};
+
+use raw_window_handle::{unix::WaylandHandle, RawWindowHandle};
+use wayland_client::{
+  protocol::{
+    wl_callback::{self, WlCallback},
+    wl_output::WlOutput,
+    wl_surface::{self, WlSurface},
+  },
+  Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_cursor::CursorTheme;
+use wayland_protocols::{
+  wp::{
+    fractional_scale::v1::client::wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+    keyboard_shortcuts_inhibit::zv1::client::zwp_keyboard_shortcuts_inhibitor_v1::ZwpKeyboardShortcutsInhibitorV1,
+    pointer_constraints::zv1::client::{
+      zwp_confined_pointer_v1::ZwpConfinedPointerV1, zwp_locked_pointer_v1::ZwpLockedPointerV1,
+      zwp_pointer_constraints_v1::Lifetime,
+    },
+    text_input::zv3::client::zwp_text_input_v3::{ContentHint, ContentPurpose, ZwpTextInputV3},
+    viewporter::client::wp_viewport::WpViewport,
+  },
+  xdg::{
+    decoration::zv1::client::zxdg_toplevel_decoration_v1::{self, ZxdgToplevelDecorationV1},
+    shell::client::{
+      xdg_surface::{self, XdgSurface},
+      xdg_toplevel::{self, XdgToplevel},
+    },
+  },
+};
+
+use crate::{
+  dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position, Size},
+  error::{ExternalError, NotSupportedError, OsError as RootOsError},
+  event::{CloseResponse, Ime, WindowEvent},
+  icon::Icon,
+  monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
+  window::{
+    AspectRatio, CursorGrabMode, CursorIcon, DragRegion, DragRegionKind, Edges, Effect, Fullscreen,
+    ProgressState, RedrawPacing, ResizeDirection, Theme, TitlebarButtons, UserAttentionType,
+    WindowAttributes, WindowId as RootWindowId, WindowImage, WindowLevel,
+  },
+};
+
+use super::{
+  event_loop::{Backend, EventLoopWindowTarget, State},
+  monitor::MonitorHandle,
+  Menu, OsError, VirtualDesktop,
+};
+
+/// The size of the windows created without an inner size.
+const DEFAULT_INNER_SIZE: LogicalSize<u32> = LogicalSize {
+  width: 800,
+  height: 600,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WindowId(pub(crate) u32);
+
+impl WindowId {
+  pub fn dummy() -> Self {
+    WindowId(u32::MAX)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PlatformSpecificWindowBuilderAttributes;
+
+/// The state of a toplevel sent by `xdg_toplevel::configure`, applied by `xdg_surface::configure`.
+#[derive(Default)]
+struct Configure {
+  size: Option<LogicalSize<u32>>,
+  maximized: bool,
+  fullscreen: bool,
+}
+
+pub(crate) struct WindowState {
+  /// The size of the surface, in the logical coordinates of the compositor.
+  logical_size: LogicalSize<u32>,
+  scale_factor: f64,
+  /// The size of the last `Resized` event.
+  reported_size: Option<PhysicalSize<u32>>,
+  outputs: Vec<WlOutput>,
+  min_inner_size: Option<Size>,
+  max_inner_size: Option<Size>,
+  resizable: bool,
+  decorated: bool,
+  maximized: bool,
+  fullscreen: Option<Fullscreen>,
+  visible: bool,
+  /// Whether the window receives the input of the user.
+  enabled: bool,
+  drag_regions: Vec<DragRegion>,
+  redraw_pacing: RedrawPacing,
+  /// Whether a `wl_surface::frame` callback is pending, for `RedrawPacing::VSync`.
+  frame_pending: bool,
+  cursor_icon: CursorIcon,
+  cursor_visible: bool,
+  confined_pointer: Option<ZwpConfinedPointerV1>,
+  locked_pointer: Option<ZwpLockedPointerV1>,
+  /// Inhibits the shortcuts of the compositor while the window has the keyboard focus.
+  shortcuts_inhibitor: Option<ZwpKeyboardShortcutsInhibitorV1>,
+  ime_allowed: bool,
+  /// Set with `Window::start_text_input`, the entered text being sent with `ReceivedText`.
+  text_input: bool,
+  ime_cursor_area: Option<(LogicalPosition<i32>, LogicalSize<i32>)>,
+  pending_configure: Option<Configure>,
+  destroyed: bool,
+}
+
+impl WindowState {
+  fn physical_size(&self) -> PhysicalSize<u32> {
+    self.logical_size.to_physical(self.scale_factor)
+  }
+
+  /// Clamps `size` to the minimum and maximum inner sizes of the window.
+  fn clamp(&self, size: LogicalSize<u32>) -> LogicalSize<u32> {
+    let mut size = size;
+    if let Some(min_size) = self.min_inner_size {
+      let min_size = min_size.to_logical::<u32>(self.scale_factor);
+      size.width = size.width.max(min_size.width);
+      size.height = size.height.max(min_size.height);
+    }
+    if let Some(max_size) = self.max_inner_size {
+      let max_size = max_size.to_logical::<u32>(self.scale_factor);
+      size.width = size.width.min(max_size.width);
+      size.height = size.height.min(max_size.height);
+    }
+    size
+  }
+
+  /// Returns the size of the last `Resized` event if it changed since.
+  fn take_resize(&mut self) -> Option<PhysicalSize<u32>> {
+    let size = self.physical_size();
+    if self.reported_size == Some(size) {
+      None
+    } else {
+      self.reported_size = Some(size);
+      Some(size)
+    }
+  }
+}
+
+/// The objects of a window, shared with the event loop that dispatches their events.
+pub(crate) struct WindowShared {
+  id: WindowId,
+  surface: WlSurface,
+  xdg_surface: XdgSurface,
+  toplevel: XdgToplevel,
+  decoration: Option<ZxdgToplevelDecorationV1>,
+  viewport: Option<WpViewport>,
+  fractional_scale: Option<WpFractionalScaleV1>,
+  state: Mutex<WindowState>,
+}
+
+impl WindowShared {
+  pub(crate) fn scale_factor(&self) -> f64 {
+    self.state.lock().unwrap().scale_factor
+  }
+
+  pub(crate) fn logical_size(&self) -> LogicalSize<u32> {
+    self.state.lock().unwrap().logical_size
+  }
+
+  pub(crate) fn ime_allowed(&self) -> bool {
+    self.state.lock().unwrap().ime_allowed
+  }
+
+  pub(crate) fn text_input(&self) -> bool {
+    self.state.lock().unwrap().text_input
+  }
+
+  pub(crate) fn enabled(&self) -> bool {
+    self.state.lock().unwrap().enabled
+  }
+
+  /// Handles a press of the left button at `position`, in logical pixels, if it's in a drag
+  /// region of the window.
+  ///
+  /// Returns whether the press was handled, in which case it isn't sent to the application.
+  pub(crate) fn press_drag_region(
+    &self,
+    backend: &Arc<Backend>,
+    position: LogicalPosition<f64>,
+    serial: u32,
+  ) -> bool {
+    let (kind, maximized) = {
+      let state = self.state.lock().unwrap();
+      // The regions are ignored while the compositor decorates the window.
+      if !state.enabled || (state.decorated && self.decoration.is_some()) {
+        return false;
+      }
+      let position = position.to_physical(state.scale_factor);
+      let kind = state
+        .drag_regions
+        .iter()
+        .rev()
+        .find(|region| region.contains(position, state.scale_factor))
+        .map(|region| region.kind);
+      (kind, state.maximized)
+    };
+    let kind = match kind {
+      Some(kind) => kind,
+      None => return false,
+    };
+
+    if kind == DragRegionKind::CloseButton {
+      backend.push_window_event(
+        self.id,
+        WindowEvent::CloseRequested {
+          response: CloseResponse::new(
+            RootWindowId(self.id),
+            CloseHandle::new(backend.clone(), self.id),
+          ),
+        },
+      );
+      return true;
+    }
+
+    {
+      let input = backend.input.lock().unwrap();
+      let seat = match &input.seat {
+        Some((_, seat)) => seat,
+        None => return false,
+      };
+      match kind {
+        DragRegionKind::Caption => self.toplevel._move(seat, serial),
+        DragRegionKind::Resize(direction) => {
+          self.toplevel.resize(seat, serial, resize_edge(direction))
+        }
+        DragRegionKind::MinimizeButton => self.toplevel.set_minimized(),
+        DragRegionKind::MaximizeButton if maximized => self.toplevel.unset_maximized(),
+        DragRegionKind::MaximizeButton => self.toplevel.set_maximized(),
+        DragRegionKind::CloseButton => unreachable!(),
+      }
+    }
+    backend.flush();
+    true
+  }
+
+  /// Applies a scale factor, with the inner size chosen by the `ScaleFactorChanged` event.
+  ///
+  /// Returns the size of the `Resized` event to send.
+  pub(crate) fn set_scale_factor(
+    &self,
+    scale_factor: f64,
+    inner_size: PhysicalSize<u32>,
+  ) -> Option<PhysicalSize<u32>> {
+    let mut state = self.state.lock().unwrap();
+    state.scale_factor = scale_factor;
+    state.logical_size = inner_size.to_logical(scale_factor);
+    match &self.viewport {
+      Some(_) => self.set_viewport_destination(&state),
+      // Without a viewport the buffers are scaled by whole factors.
+      None => self.surface.set_buffer_scale(scale_factor.round() as i32),
+    }
+    state.take_resize()
+  }
+
+  fn set_viewport_destination(&self, state: &WindowState) {
+    if let Some(viewport) = &self.viewport {
+      viewport.set_destination(
+        state.logical_size.width as i32,
+        state.logical_size.height as i32,
+      );
+    }
+  }
+
+  /// Applies the minimum and maximum sizes, which are also how a window is made unresizable.
+  fn apply_size_limits(&self, state: &WindowState) {
+    let (min_size, max_size) = if state.resizable {
+      let to_logical = |size: Option<Size>| {
+        size.map_or((0, 0), |size| {
+          let size = size.to_logical::<u32>(state.scale_factor);
+          (size.width as i32, size.height as i32)
+        })
+      };
+      (
+        to_logical(state.min_inner_size),
+        to_logical(state.max_inner_size),
+      )
+    } else {
+      let size = (
+        state.logical_size.width as i32,
+        state.logical_size.height as i32,
+      );
+      (size, size)
+    };
+    self.toplevel.set_min_size(min_size.0, min_size.1);
+    self.toplevel.set_max_size(max_size.0, max_size.1);
+  }
+
+  fn apply_fullscreen(&self, fullscreen: &Option<Fullscreen>) {
+    match fullscreen {
+      // The compositor chooses the video mode, so an exclusive fullscreen only picks the output.
+      Some(Fullscreen::Exclusive(video_mode)) => {
+        let monitor = video_mode.monitor();
+        self.toplevel.set_fullscreen(Some(&monitor.inner.output));
+      }
+      Some(Fullscreen::Borderless(monitor)) => self
+        .toplevel
+        .set_fullscreen(monitor.as_ref().map(|monitor| &monitor.inner.output)),
+      None => self.toplevel.unset_fullscreen(),
+    }
+  }
+
+  /// The scale factor of the outputs the window is on, used without wp-fractional-scale.
+  fn output_scale_factor(&self, backend: &Backend) -> Option<f64> {
+    let state = self.state.lock().unwrap();
+    state
+      .outputs
+      .iter()
+      .filter_map(|output| backend.monitor(output))
+      .map(|monitor| monitor.scale_factor())
+      .fold(None, |max, scale_factor| {
+        Some(max.map_or(scale_factor, |max: f64| max.max(scale_factor)))
+      })
+  }
+
+  /// Follows the scale factor of the outputs, when the compositor doesn't send a preferred one.
+  pub(crate) fn update_output_scale(&self, backend: &Backend) {
+    if self.fractional_scale.is_some() {
+      return;
+    }
+    if let Some(scale_factor) = self.output_scale_factor(backend) {
+      backend.push_scale_change(self.id, scale_factor);
+    }
+  }
+
+  fn configure(&self, backend: &Backend, serial: u32) {
+    self.xdg_surface.ack_configure(serial);
+    let mut state = self.state.lock().unwrap();
+    let configure = state.pending_configure.take().unwrap_or_default();
+    if let Some(size) = configure.size {
+      state.logical_size = size;
+      self.set_viewport_destination(&state);
+      if !state.resizable {
+        self.apply_size_limits(&state);
+      }
+    }
+    state.maximized = configure.maximized;
+    if !configure.fullscreen {
+      state.fullscreen = None;
+    } else if state.fullscreen.is_none() {
+      state.fullscreen = Some(Fullscreen::Borderless(None));
+    }
+    if let Some(size) = state.take_resize() {
+      backend.push_window_event(self.id, WindowEvent::Resized(size));
+    }
+    drop(state);
+    // The window is only mapped, or resized, once the application draws it.
+    backend.request_redraw(self.id);
+  }
+}
+
+/// The id of the next window, counted across the event loops since the ids are global to the
+/// process, e.g. for the redraws of `crate::redraw`.
+static NEXT_WINDOW_ID: AtomicU32 = AtomicU32::new(0);
+
+pub struct Window {
+  shared: Arc<WindowShared>,
+  backend: Arc<Backend>,
+}
+
+impl Window {
+  pub fn new<T: 'static>(
+    event_loop_window_target: &EventLoopWindowTarget<T>,
+    attributes: WindowAttributes,
+    _pl_attribs: PlatformSpecificWindowBuilderAttributes,
+  ) -> Result<Self, RootOsError> {
+    let backend = event_loop_window_target.backend.clone();
+    let globals = &backend.globals;
+    let qh = &backend.qh;
+    let id = WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed));
+
+    let surface = globals.compositor.create_surface(qh, id);
+    let xdg_surface = globals.wm_base.get_xdg_surface(&surface, qh, id);
+    let toplevel = xdg_surface.get_toplevel(qh, id);
+    let decoration = globals
+      .decoration_manager
+      .as_ref()
+      .map(|manager| manager.get_toplevel_decoration(&toplevel, qh, ()));
+    // The fractional scale factors need a viewport to scale the buffers down.
+    let (viewport, fractional_scale) =
+      match (&globals.viewporter, &globals.fractional_scale_manager) {
+        (Some(viewporter), Some(manager)) => (
+          Some(viewporter.get_viewport(&surface, qh, ())),
+          Some(manager.get_fractional_scale(&surface, qh, id)),
+        ),
+        _ => (None, None),
+      };
+
+    // The scale factor is only known once the window is on an output.
+    let scale_factor = 1.0;
+    let mut state = WindowState {
+      logical_size: DEFAULT_INNER_SIZE,
+      scale_factor,
+      reported_size: None,
+      outputs: Vec::new(),
+      min_inner_size: attributes.min_inner_size,
+      max_inner_size: attributes.max_inner_size,
+      resizable: attributes.resizable,
+      decorated: attributes.decorations,
+      maximized: attributes.maximized,
+      fullscreen: attributes.fullscreen,
+      visible: attributes.visible,
+      enabled: true,
+      drag_regions: Vec::new(),
+      redraw_pacing: RedrawPacing::default(),
+      frame_pending: false,
+      cursor_icon: CursorIcon::Default,
+      cursor_visible: true,
+      confined_pointer: None,
+      locked_pointer: None,
+      shortcuts_inhibitor: None,
+      ime_allowed: true,
+      text_input: false,
+      ime_cursor_area: None,
+      pending_configure: None,
+      destroyed: false,
+    };
+    state.logical_size = state.clamp(
+      attributes
+        .inner_size
+        .map_or(DEFAULT_INNER_SIZE, |size| size.to_logical(scale_factor)),
+    );
+
+    let shared = Arc::new(WindowShared {
+      id,
+      surface,
+      xdg_surface,
+      toplevel,
+      decoration,
+      viewport,
+      fractional_scale,
+      state: Mutex::new(state),
+    });
+
+    {
+      let state = shared.state.lock().unwrap();
+      shared.toplevel.set_title(attributes.title);
+      if let Some(app_id) = std::env::current_exe().ok().and_then(|path| {
+        path
+          .file_stem()
+          .map(|name| name.to_string_lossy().into_owned())
+      }) {
+        shared.toplevel.set_app_id(app_id);
+      }
+      if let Some(parent) = attributes
+        .parent_window
+        .and_then(|parent| backend.window(parent.0))
+      {
+        shared.toplevel.set_parent(Some(&parent.toplevel));
+      }
+      shared.apply_size_limits(&state);
+      if state.maximized {
+        shared.toplevel.set_maximized();
+      }
+      if state.fullscreen.is_some() {
+        shared.apply_fullscreen(&state.fullscreen);
+      }
+      if let Some(decoration) = &shared.decoration {
+        decoration.set_mode(decoration_mode(state.decorated));
+      }
+      shared.set_viewport_destination(&state);
+      // The compositor configures the window after a first commit without a buffer.
+      if state.visible {
+        shared.surface.commit();
+      }
+    }
+
+    backend
+      .windows
+      .lock()
+      .unwrap()
+      .insert(id, Arc::downgrade(&shared));
+    backend.flush();
+
+    Ok(Window { shared, backend })
+  }
+
+  pub fn id(&self) -> WindowId {
+    self.shared.id
+  }
+
+  pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
+    self
+      .backend
+      .monitors
+      .lock()
+      .unwrap()
+      .first()
+      .map(|monitor| RootMonitorHandle {
+        inner: monitor.clone(),
+      })
+  }
+
+  pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
+    self
+      .backend
+      .monitors
+      .lock()
+      .unwrap()
+      .iter()
+      .cloned()
+      .collect()
+  }
+
+  pub fn current_monitor(&self) -> Option<RootMonitorHandle> {
+    let output = self.shared.state.lock().unwrap().outputs.first().cloned();
+    output
+      .and_then(|output| self.backend.monitor(&output))
+      .map(|inner| RootMonitorHandle { inner })
+      .or_else(|| self.primary_monitor())
+  }
+
+  pub fn scale_factor(&self) -> f64 {
+    self.shared.scale_factor()
+  }
+
+  pub fn request_redraw(&self) {
+    let mut state = self.shared.state.lock().unwrap();
+    // The frame callbacks of a hidden window are never sent.
+    if state.redraw_pacing == RedrawPacing::VSync && state.visible && !state.destroyed {
+      if !state.frame_pending {
+        state.frame_pending = true;
+        self.shared.surface.frame(&self.backend.qh, self.shared.id);
+        self.shared.surface.commit();
+        self.backend.flush();
+      }
+      return;
+    }
+    drop(state);
+    self.backend.request_redraw(self.shared.id);
+  }
+
+  pub fn request_redraw_after(&self, delay: Duration) {
+    self.backend.request_redraw_after(self.shared.id, delay);
+  }
+
+  pub fn set_redraw_pacing(&self, pacing: RedrawPacing) {
+    self.shared.state.lock().unwrap().redraw_pacing = pacing;
+  }
+
+  // The clients don't know where their windows are on Wayland.
+  pub fn inner_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
+    Err(NotSupportedError::new())
+  }
+
+  pub fn outer_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
+    Err(NotSupportedError::new())
+  }
+
+  pub fn set_outer_position(&self, _position: Position) {}
+
+  pub fn inner_size(&self) -> PhysicalSize<u32> {
+    self.shared.state.lock().unwrap().physical_size()
+  }
+
+  pub fn set_inner_size(&self, size: Size) {
+    let mut state = self.shared.state.lock().unwrap();
+    let size = state.clamp(size.to_logical(state.scale_factor));
+    if state.logical_size == size {
+      return;
+    }
+    state.logical_size = size;
+    self.shared.set_viewport_destination(&state);
+    if !state.resizable {
+      self.shared.apply_size_limits(&state);
+    }
+    if let Some(size) = state.take_resize() {
+      self
+        .backend
+        .push_window_event(self.shared.id, WindowEvent::Resized(size));
+    }
+    drop(state);
+    self.backend.request_redraw(self.shared.id);
+    self.backend.flush();
+  }
+
+  // The decorations are drawn by the compositor, outside of the surface.
+  pub fn outer_size(&self) -> PhysicalSize<u32> {
+    self.inner_size()
+  }
+
+  pub fn set_min_inner_size(&self, min_size: Option<Size>) {
+    let mut state = self.shared.state.lock().unwrap();
+    state.min_inner_size = min_size;
+    self.shared.apply_size_limits(&state);
+    self.shared.surface.commit();
+    self.backend.flush();
+  }
+
+  pub fn set_max_inner_size(&self, max_size: Option<Size>) {
+    let mut state = self.shared.state.lock().unwrap();
+    state.max_inner_size = max_size;
+    self.shared.apply_size_limits(&state);
+    self.shared.surface.commit();
+    self.backend.flush();
+  }
+
+  pub fn set_aspect_ratio(&self, _: Option<AspectRatio>) {}
+
+  pub fn set_resizable_edges(&self, _: Edges) {}
+
+  pub fn set_titlebar_buttons(&self, _: TitlebarButtons) {}
+
+  pub fn set_title(&self, title: &str) {
+    self.shared.toplevel.set_title(title.to_owned());
+    self.backend.flush();
+  }
+
+  pub fn set_menu(&self, _menu: Option<Menu>) {}
+
+  pub fn set_visible(&self, visible: bool) {
+    let mut state = self.shared.state.lock().unwrap();
+    if state.visible == visible || state.destroyed {
+      return;
+    }
+    state.visible = visible;
+    if !visible {
+      // A surface without a buffer is unmapped, and configured again on its next commit.
+      self.shared.surface.attach(None, 0, 0);
+      // The pending frame callback isn't sent to an unmapped surface.
+      state.frame_pending = false;
+    }
+    self.shared.surface.commit();
+    self.backend.flush();
+  }
+
+  pub fn show_without_activating(&self) {
+    self.set_visible(true);
+  }
+
+  // todo: activate the windows with xdg-activation
+  pub fn set_focus(&self) {}
+
+  pub fn raise(&self) {}
+
+  pub fn set_above(&self, _window: &Window) {}
+
+  pub fn set_below(&self, _window: &Window) {}
+
+  pub fn set_resizable(&self, resizable: bool) {
+    let mut state = self.shared.state.lock().unwrap();
+    state.resizable = resizable;
+    self.shared.apply_size_limits(&state);
+    self.shared.surface.commit();
+    self.backend.flush();
+  }
+
+  pub fn set_minimized(&self, minimized: bool) {
+    // The windows can't be restored by the clients.
+    if minimized {
+      self.shared.toplevel.set_minimized();
+      self.backend.flush();
+    }
+  }
+
+  pub fn set_maximized(&self, maximized: bool) {
+    if maximized {
+      self.shared.toplevel.set_maximized();
+    } else {
+      self.shared.toplevel.unset_maximized();
+    }
+    self.backend.flush();
+  }
+
+  pub fn set_maximized_on(&self, _monitor: RootMonitorHandle) {
+    self.set_maximized(true);
+  }
+
+  pub fn is_maximized(&self) -> bool {
+    self.shared.state.lock().unwrap().maximized
+  }
+
+  // The compositor picks the size of the window when it is unmaximized.
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    None
+  }
+
+  pub fn is_visible(&self) -> bool {
+    self.shared.state.lock().unwrap().visible
+  }
+
+  pub fn is_resizable(&self) -> bool {
+    self.shared.state.lock().unwrap().resizable
+  }
+
+  pub fn is_decorated(&self) -> bool {
+    self.shared.state.lock().unwrap().decorated
+  }
+
+  pub fn set_enabled(&self, enabled: bool) {
+    self.shared.state.lock().unwrap().enabled = enabled;
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.shared.enabled()
+  }
+
+  pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+    self.shared.apply_fullscreen(&fullscreen);
+    self.shared.state.lock().unwrap().fullscreen = fullscreen;
+    self.backend.flush();
+  }
+
+  pub fn set_fullscreen_video_mode(&self, _video_mode: RootVideoMode) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn fullscreen(&self) -> Option<Fullscreen> {
+    self.shared.state.lock().unwrap().fullscreen.clone()
+  }
+
+  pub fn set_kiosk(&self, _kiosk: bool) {}
+
+  pub fn set_decorations(&self, decorations: bool) {
+    self.shared.state.lock().unwrap().decorated = decorations;
+    if let Some(decoration) = &self.shared.decoration {
+      decoration.set_mode(decoration_mode(decorations));
+      self.backend.flush();
+    }
+  }
+
+  pub fn set_shadow(&self, _shadow: bool) {}
+
+  pub fn set_opacity(&self, _opacity: f32) {}
+
+  pub fn set_content_protected(&self, _protected: bool) {}
+
+  pub fn set_owner(&self, owner: Option<WindowId>) {
+    let owner = owner.and_then(|owner| self.backend.window(owner));
+    self
+      .shared
+      .toplevel
+      .set_parent(owner.as_ref().map(|owner| &owner.toplevel));
+    self.backend.flush();
+  }
+
+  pub fn set_window_level(&self, _level: WindowLevel) {}
+
+  pub fn set_visible_on_all_workspaces(&self, _visible: bool) {}
+
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    true
+  }
+
+  pub fn move_to_virtual_desktop(&self, _: &VirtualDesktop) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_skip_taskbar(&self, _skip: bool) {}
+
+  pub fn set_minimize_to_tray(&self, _minimize_to_tray: bool) {}
+
+  pub fn set_theme(&self, _theme: Option<Theme>) {}
+
+  pub fn set_background_effect(&self, _effect: Option<Effect>) {}
+
+  pub fn set_window_icon(&self, _window_icon: Option<Icon>) {}
+
+  pub fn set_ime_cursor_area(&self, position: Position, size: Size) {
+    let scale_factor = self.scale_factor();
+    let area = (
+      position.to_logical(scale_factor),
+      size.to_logical(scale_factor),
+    );
+    let ime_allowed = {
+      let mut state = self.shared.state.lock().unwrap();
+      state.ime_cursor_area = Some(area);
+      state.ime_allowed
+    };
+    let input = self.backend.input.lock().unwrap();
+    if let (Some(text_input), Some(ime_focus)) = (&input.text_input, input.ime_focus) {
+      if ime_focus == self.shared.id && ime_allowed {
+        update_ime_cursor_area(text_input, &self.shared);
+        text_input.commit();
+        self.backend.flush();
+      }
+    }
+  }
+
+  pub fn set_text_input(&self, active: bool) {
+    self.shared.state.lock().unwrap().text_input = active;
+  }
+
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    {
+      let mut state = self.shared.state.lock().unwrap();
+      if state.ime_allowed == allowed {
+        return;
+      }
+      state.ime_allowed = allowed;
+    }
+    let mut input = self.backend.input.lock().unwrap();
+    let text_input = match (&input.text_input, input.ime_focus) {
+      (Some(text_input), Some(ime_focus)) if ime_focus == self.shared.id => text_input.clone(),
+      _ => return,
+    };
+    let window_id = self.shared.id;
+    if allowed {
+      if enable_ime(&text_input, &self.shared) {
+        self
+          .backend
+          .push_window_event(window_id, WindowEvent::Ime(Ime::Enabled));
+      }
+    } else {
+      text_input.disable();
+      text_input.commit();
+      if std::mem::replace(&mut input.has_ime_preedit, false) {
+        self
+          .backend
+          .push_window_event(window_id, WindowEvent::Ime(Ime::preedit(String::new())));
+      }
+      self
+        .backend
+        .push_window_event(window_id, WindowEvent::Ime(Ime::Disabled));
+    }
+    self.backend.flush();
+  }
+
+  pub fn request_user_attention(&self, _request_type: Option<UserAttentionType>) {}
+
+  pub fn set_progress_bar(&self, _state: ProgressState, _progress: Option<u64>) {}
+
+  pub fn set_badge_count(&self, _count: Option<u64>) {}
+
+  pub fn hide_menu(&self) {}
+
+  pub fn show_menu(&self) {}
+
+  pub fn is_menu_visible(&self) -> bool {
+    false
+  }
+
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_cursor_icon(&self, cursor: CursorIcon) {
+    self.shared.state.lock().unwrap().cursor_icon = cursor;
+    update_cursor(&self.backend, &self.shared);
+  }
+
+  // The clients can't move the pointer on Wayland.
+  pub fn set_cursor_position(&self, _: Position) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+    let constraints = match &self.backend.globals.pointer_constraints {
+      Some(constraints) => constraints,
+      None => return Err(ExternalError::NotSupported(NotSupportedError::new())),
+    };
+    let pointer = self.backend.input.lock().unwrap().pointer.clone();
+    let mut state = self.shared.state.lock().unwrap();
+    let current = match (&state.confined_pointer, &state.locked_pointer) {
+      (Some(_), _) => CursorGrabMode::Confined,
+      (_, Some(_)) => CursorGrabMode::Locked,
+      (None, None) => CursorGrabMode::None,
+    };
+    if mode == current {
+      return Ok(());
+    }
+    if let Some(confined_pointer) = state.confined_pointer.take() {
+      confined_pointer.destroy();
+    }
+    if let Some(locked_pointer) = state.locked_pointer.take() {
+      locked_pointer.destroy();
+    }
+    if mode != CursorGrabMode::None {
+      let pointer = pointer
+        .ok_or_else(|| ExternalError::Os(os_error!(OsError("The seat has no pointer".into()))))?;
+      let (surface, qh) = (&self.shared.surface, &self.backend.qh);
+      // The relative pointer keeps reporting the motion while the pointer is locked.
+      match mode {
+        CursorGrabMode::Confined => {
+          state.confined_pointer =
+            Some(constraints.confine_pointer(surface, &pointer, None, Lifetime::Persistent, qh, ()))
+        }
+        _ => {
+          state.locked_pointer =
+            Some(constraints.lock_pointer(surface, &pointer, None, Lifetime::Persistent, qh, ()))
+        }
+      }
+    }
+    self.backend.flush();
+    Ok(())
+  }
+
+  #[cfg(feature = "test")]
+  pub fn inject_event(&self, _event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_mouse_capture(&self, _capture: bool) -> Result<(), ExternalError> {
+    // The compositors only send the pointer outside of the surfaces while a button is pressed.
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_keyboard_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    let manager = match &self.backend.globals.keyboard_shortcuts_inhibit_manager {
+      Some(manager) => manager,
+      None => return Err(ExternalError::NotSupported(NotSupportedError::new())),
+    };
+    let seat = self.backend.input.lock().unwrap().seat.clone();
+    let mut state = self.shared.state.lock().unwrap();
+    if grab == state.shortcuts_inhibitor.is_some() {
+      return Ok(());
+    }
+    if let Some(inhibitor) = state.shortcuts_inhibitor.take() {
+      inhibitor.destroy();
+    } else {
+      let (_, seat) =
+        seat.ok_or_else(|| ExternalError::Os(os_error!(OsError("There's no seat".into()))))?;
+      state.shortcuts_inhibitor =
+        Some(manager.inhibit_shortcuts(&self.shared.surface, &seat, &self.backend.qh, ()));
+    }
+    self.backend.flush();
+    Ok(())
+  }
+
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    if ignore {
+      let region = self
+        .backend
+        .globals
+        .compositor
+        .create_region(&self.backend.qh, ());
+      self.shared.surface.set_input_region(Some(&region));
+      region.destroy();
+    } else {
+      self.shared.surface.set_input_region(None);
+    }
+    self.shared.surface.commit();
+    self.backend.flush();
+    Ok(())
+  }
+
+  pub fn set_cursor_visible(&self, visible: bool) {
+    self.shared.state.lock().unwrap().cursor_visible = visible;
+    update_cursor(&self.backend, &self.shared);
+  }
+
+  pub fn set_mouse_coalescing(&self, _: bool) {}
+
+  pub fn drag_window(&self) -> Result<(), ExternalError> {
+    let input = self.backend.input.lock().unwrap();
+    match (&input.seat, input.press_serial) {
+      (Some((_, seat)), Some(serial)) => {
+        self.shared.toplevel._move(seat, serial);
+        self.backend.flush();
+        Ok(())
+      }
+      _ => Err(ExternalError::Os(os_error!(OsError(
+        "The window can only be dragged after a press".into()
+      )))),
+    }
+  }
+
+  pub fn set_drag_region(&self, regions: Vec<DragRegion>) {
+    self.shared.state.lock().unwrap().drag_regions = regions;
+  }
+
+  pub fn raw_window_handle(&self) -> RawWindowHandle {
+    let mut handle = WaylandHandle::empty();
+    handle.surface = self.shared.surface.id().as_ptr() as *mut _;
+    handle.display = self.backend.conn.backend().display_ptr() as *mut _;
+    RawWindowHandle::Wayland(handle)
+  }
+}
+
+impl Drop for Window {
+  fn drop(&mut self) {
+    self.backend.windows.lock().unwrap().remove(&self.shared.id);
+    let mut state = self.shared.state.lock().unwrap();
+    if let Some(confined_pointer) = state.confined_pointer.take() {
+      confined_pointer.destroy();
+    }
+    if let Some(locked_pointer) = state.locked_pointer.take() {
+      locked_pointer.destroy();
+    }
+    if let Some(inhibitor) = state.shortcuts_inhibitor.take() {
+      inhibitor.destroy();
+    }
+    if let Some(fractional_scale) = &self.shared.fractional_scale {
+      fractional_scale.destroy();
+    }
+    if let Some(viewport) = &self.shared.viewport {
+      viewport.destroy();
+    }
+    if let Some(decoration) = &self.shared.decoration {
+      decoration.destroy();
+    }
+    self.shared.toplevel.destroy();
+    self.shared.xdg_surface.destroy();
+    self.shared.surface.destroy();
+    if !state.destroyed {
+      state.destroyed = true;
+      self
+        .backend
+        .push_window_event(self.shared.id, WindowEvent::Destroyed);
+    }
+    self.backend.flush();
+  }
+}
+
+/// Destroys a window once its `CloseResponse` is allowed.
+#[derive(Clone)]
+pub struct CloseHandle {
+  backend: Arc<Backend>,
+  window_id: WindowId,
+}
+
+impl CloseHandle {
+  pub(crate) fn new(backend: Arc<Backend>, window_id: WindowId) -> Self {
+    CloseHandle { backend, window_id }
+  }
+
+  pub fn close(&self) {
+    let window = match self.backend.window(self.window_id) {
+      Some(window) => window,
+      None => return,
+    };
+    let mut state = window.state.lock().unwrap();
+    if state.destroyed {
+      return;
+    }
+    state.destroyed = true;
+    state.visible = false;
+    window.surface.attach(None, 0, 0);
+    window.surface.commit();
+    self.backend.flush();
+    self
+      .backend
+      .push_window_event(self.window_id, WindowEvent::Destroyed);
+  }
+}
+
+fn decoration_mode(decorated: bool) -> zxdg_toplevel_decoration_v1::Mode {
+  // The backend doesn't draw decorations, so the windows drawn by the client are undecorated.
+  if decorated {
+    zxdg_toplevel_decoration_v1::Mode::ServerSide
+  } else {
+    zxdg_toplevel_decoration_v1::Mode::ClientSide
+  }
+}
+
+/// Enables the input method for a window focused by it, returning `false` if the window
+/// disallows it.
+pub(crate) fn enable_ime(text_input: &ZwpTextInputV3, window: &WindowShared) -> bool {
+  if !window.ime_allowed() {
+    return false;
+  }
+  text_input.enable();
+  text_input.set_content_type(ContentHint::None, ContentPurpose::Normal);
+  update_ime_cursor_area(text_input, window);
+  text_input.commit();
+  true
+}
+
+/// Tells the input method where the text is edited, without committing it.
+fn update_ime_cursor_area(text_input: &ZwpTextInputV3, window: &WindowShared) {
+  if let Some((position, size)) = window.state.lock().unwrap().ime_cursor_area {
+    text_input.set_cursor_rectangle(position.x, position.y, size.width, size.height);
+  }
+}
+
+/// Sets the cursor of a window, if it's under the pointer.
+pub(crate) fn update_cursor(backend: &Backend, window: &WindowShared) {
+  let input = backend.input.lock().unwrap();
+  let (pointer, serial, surface) =
+    match (&input.pointer, input.pointer_focus, &input.cursor_surface) {
+      (Some(pointer), Some((window_id, serial)), Some(surface)) if window_id == window.id => {
+        (pointer, serial, surface)
+      }
+      _ => return,
+    };
+  let state = window.state.lock().unwrap();
+  if !state.cursor_visible {
+    pointer.set_cursor(serial, None, 0, 0);
+    backend.flush();
+    return;
+  }
+
+  // The cursor surface is scaled by whole factors, rounded up to stay sharp.
+  let scale = state.scale_factor.ceil().max(1.0) as u32;
+  let mut cursor_themes = backend.cursor_themes.lock().unwrap();
+  let theme = match cursor_themes.entry(scale) {
+    Entry::Occupied(entry) => entry.into_mut(),
+    Entry::Vacant(entry) => {
+      match CursorTheme::load(
+        &backend.conn,
+        backend.globals.shm.clone(),
+        cursor_size() * scale,
+      ) {
+        Ok(theme) => entry.insert(theme),
+        Err(e) => {
+          log::warn!("Failed to load the cursor theme: {}", e);
+          return;
+        }
+      }
+    }
+  };
+  let name = cursor_name(state.cursor_icon);
+  let name = if theme.get_cursor(name).is_some() {
+    name
+  } else {
+    "left_ptr"
+  };
+  let cursor = match theme.get_cursor(name) {
+    Some(cursor) => cursor,
+    None => return,
+  };
+
+  let image = &cursor[0];
+  let (width, height) = image.dimensions();
+  let (hotspot_x, hotspot_y) = image.hotspot();
+  surface.set_buffer_scale(scale as i32);
+  surface.attach(Some(&**image), 0, 0);
+  surface.damage_buffer(0, 0, width as i32, height as i32);
+  surface.commit();
+  pointer.set_cursor(
+    serial,
+    Some(surface),
+    (hotspot_x / scale) as i32,
+    (hotspot_y / scale) as i32,
+  );
+  backend.flush();
+}
+
+/// The size of the cursors in logical pixels, from the environment like the other clients.
+fn cursor_size() -> u32 {
+  std::env::var("XCURSOR_SIZE")
+    .ok()
+    .and_then(|size| size.parse().ok())
+    .unwrap_or(24)
+}
+
+fn cursor_name(cursor: CursorIcon) -> &'static str {
+  match cursor {
+    CursorIcon::Default | CursorIcon::Arrow => "default",
+    CursorIcon::Crosshair => "crosshair",
+    CursorIcon::Hand => "pointer",
+    CursorIcon::Move => "move",
+    CursorIcon::Text => "text",
+    CursorIcon::Wait => "wait",
+    CursorIcon::Help => "help",
+    CursorIcon::Progress => "progress",
+    CursorIcon::NotAllowed => "not-allowed",
+    CursorIcon::ContextMenu => "context-menu",
+    CursorIcon::Cell => "cell",
+    CursorIcon::VerticalText => "vertical-text",
+    CursorIcon::Alias => "alias",
+    CursorIcon::Copy => "copy",
+    CursorIcon::NoDrop => "no-drop",
+    CursorIcon::Grab => "grab",
+    CursorIcon::Grabbing => "grabbing",
+    CursorIcon::AllScroll => "all-scroll",
+    CursorIcon::ZoomIn => "zoom-in",
+    CursorIcon::ZoomOut => "zoom-out",
+    CursorIcon::EResize => "e-resize",
+    CursorIcon::NResize => "n-resize",
+    CursorIcon::NeResize => "ne-resize",
+    CursorIcon::NwResize => "nw-resize",
+    CursorIcon::SResize => "s-resize",
+    CursorIcon::SeResize => "se-resize",
+    CursorIcon::SwResize => "sw-resize",
+    CursorIcon::WResize => "w-resize",
+    CursorIcon::EwResize => "ew-resize",
+    CursorIcon::NsResize => "ns-resize",
+    CursorIcon::NeswResize => "nesw-resize",
+    CursorIcon::NwseResize => "nwse-resize",
+    CursorIcon::ColResize => "col-resize",
+    CursorIcon::RowResize => "row-resize",
+  }
+}
+
+fn resize_edge(direction: ResizeDirection) -> xdg_toplevel::ResizeEdge {
+  match direction {
+    ResizeDirection::East => xdg_toplevel::ResizeEdge::Right,
+    ResizeDirection::North => xdg_toplevel::ResizeEdge::Top,
+    ResizeDirection::NorthEast => xdg_toplevel::ResizeEdge::TopRight,
+    ResizeDirection::NorthWest => xdg_toplevel::ResizeEdge::TopLeft,
+    ResizeDirection::South => xdg_toplevel::ResizeEdge::Bottom,
+    ResizeDirection::SouthEast => xdg_toplevel::ResizeEdge::BottomRight,
+    ResizeDirection::SouthWest => xdg_toplevel::ResizeEdge::BottomLeft,
+    ResizeDirection::West => xdg_toplevel::ResizeEdge::Left,
+  }
+}
+
+impl Dispatch<WlSurface, WindowId> for State {
+  fn event(
+    state: &mut Self,
+    _: &WlSurface,
+    event: wl_surface::Event,
+    window_id: &WindowId,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let window = match state.backend.window(*window_id) {
+      Some(window) => window,
+      None => return,
+    };
+    {
+      let mut window_state = window.state.lock().unwrap();
+      match event {
+        wl_surface::Event::Enter { output } => window_state.outputs.push(output),
+        wl_surface::Event::Leave { output } => window_state.outputs.retain(|o| *o != output),
+        _ => return,
+      }
+    }
+    window.update_output_scale(&state.backend);
+  }
+}
+
+impl Dispatch<WlCallback, WindowId> for State {
+  fn event(
+    state: &mut Self,
+    _: &WlCallback,
+    event: wl_callback::Event,
+    window_id: &WindowId,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    // The frame callback of `RedrawPacing::VSync`.
+    if let wl_callback::Event::Done { .. } = event {
+      if let Some(window) = state.backend.window(*window_id) {
+        window.state.lock().unwrap().frame_pending = false;
+        state.backend.request_redraw(*window_id);
+      }
+    }
+  }
+}
+
+impl Dispatch<XdgSurface, WindowId> for State {
+  fn event(
+    state: &mut Self,
+    _: &XdgSurface,
+    event: xdg_surface::Event,
+    window_id: &WindowId,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    if let xdg_surface::Event::Configure { serial } = event {
+      if let Some(window) = state.backend.window(*window_id) {
+        window.configure(&state.backend, serial);
+      }
+    }
+  }
+}
+
+impl Dispatch<XdgToplevel, WindowId> for State {
+  fn event(
+    state: &mut Self,
+    _: &XdgToplevel,
+    event: xdg_toplevel::Event,
+    window_id: &WindowId,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    let backend = &state.backend;
+    match event {
+      xdg_toplevel::Event::Configure {
+        width,
+        height,
+        states,
+      } => {
+        let window = match backend.window(*window_id) {
+          Some(window) => window,
+          None => return,
+        };
+        // The states are an array of `u32`, and a size of 0 leaves it to the client.
+        let states = states
+          .chunks_exact(4)
+          .map(|state| u32::from_ne_bytes([state[0], state[1], state[2], state[3]]))
+          .filter_map(|state| xdg_toplevel::State::try_from(state).ok())
+          .collect::<Vec<_>>();
+        let configure = Configure {
+          size: if width > 0 && height > 0 {
+            Some(LogicalSize::new(width as u32, height as u32))
+          } else {
+            None
+          },
+          maximized: states.contains(&xdg_toplevel::State::Maximized),
+          fullscreen: states.contains(&xdg_toplevel::State::Fullscreen),
+        };
+        window.state.lock().unwrap().pending_configure = Some(configure);
+      }
+      xdg_toplevel::Event::Close => {
+        backend.push_window_event(
+          *window_id,
+          WindowEvent::CloseRequested {
+            response: CloseResponse::new(
+              RootWindowId(*window_id),
+              CloseHandle::new(backend.clone(), *window_id),
+            ),
+          },
+        );
+      }
+      _ => (),
+    }
+  }
+}
+
+impl Dispatch<WpFractionalScaleV1, WindowId> for State {
+  fn event(
+    state: &mut Self,
+    _: &WpFractionalScaleV1,
+    event: wp_fractional_scale_v1::Event,
+    window_id: &WindowId,
+    _: &Connection,
+    _: &QueueHandle<Self>,
+  ) {
+    if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+      // The scale is sent in 120ths.
+      state
+        .backend
+        .push_scale_change(*window_id, scale as f64 / 120.0);
+    }
+  }
+}
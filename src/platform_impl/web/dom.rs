@@ -0,0 +1,166 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use instant::Instant;
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{Document, EventTarget};
+
+pub fn window() -> web_sys::Window {
+  web_sys::window().expect("Failed to obtain the `window` of the page")
+}
+
+pub fn document() -> Document {
+  window()
+    .document()
+    .expect("Failed to obtain the `document` of the page")
+}
+
+pub fn scale_factor() -> f64 {
+  window().device_pixel_ratio()
+}
+
+/// Converts the `timeStamp` of an event, in milliseconds on the clock of `performance.now()`, to
+/// an `Instant`.
+pub fn event_timestamp(event: &web_sys::Event) -> Instant {
+  let now = Instant::now();
+  let age = window()
+    .performance()
+    .map(|performance| performance.now() - event.time_stamp())
+    .unwrap_or_default();
+  if age > 0.0 {
+    now
+      .checked_sub(Duration::from_secs_f64(age / 1000.0))
+      .unwrap_or(now)
+  } else {
+    now
+  }
+}
+
+/// Listens to the events of a target until it's dropped.
+pub struct EventListener {
+  target: EventTarget,
+  event_type: &'static str,
+  closure: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl EventListener {
+  pub fn new<E, F>(target: &EventTarget, event_type: &'static str, mut handler: F) -> Self
+  where
+    E: JsCast,
+    F: 'static + FnMut(E),
+  {
+    let closure =
+      Closure::wrap(
+        Box::new(move |event: web_sys::Event| handler(event.unchecked_into()))
+          as Box<dyn FnMut(web_sys::Event)>,
+      );
+    target
+      .add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
+      .expect("Failed to add an event listener");
+    EventListener {
+      target: target.clone(),
+      event_type,
+      closure,
+    }
+  }
+}
+
+impl Drop for EventListener {
+  fn drop(&mut self) {
+    let _ = self
+      .target
+      .remove_event_listener_with_callback(self.event_type, self.closure.as_ref().unchecked_ref());
+  }
+}
+
+// The closures given to the browser may be dropped while they run, e.g. when the event loop
+// exits from an animation frame, since `wasm-bindgen` frees them once they return.
+
+/// Calls a function on the next animation frame, unless it's dropped before.
+pub struct AnimationFrame {
+  handle: i32,
+  _closure: Closure<dyn FnMut()>,
+}
+
+impl AnimationFrame {
+  pub fn new<F: 'static + FnOnce()>(f: F) -> Self {
+    let closure = Closure::once(f);
+    let handle = window()
+      .request_animation_frame(closure.as_ref().unchecked_ref())
+      .expect("Failed to request an animation frame");
+    AnimationFrame {
+      handle,
+      _closure: closure,
+    }
+  }
+}
+
+impl Drop for AnimationFrame {
+  fn drop(&mut self) {
+    let _ = window().cancel_animation_frame(self.handle);
+  }
+}
+
+/// Calls a function once `delay` elapsed, unless it's dropped before.
+pub struct Timeout {
+  handle: i32,
+  _closure: Closure<dyn FnMut()>,
+}
+
+impl Timeout {
+  pub fn new<F: 'static + FnOnce()>(delay: Duration, f: F) -> Self {
+    let closure = Closure::once(f);
+    let handle = window()
+      .set_timeout_with_callback_and_timeout_and_arguments_0(
+        closure.as_ref().unchecked_ref(),
+        duration_millis(delay),
+      )
+      .expect("Failed to set a timeout");
+    Timeout {
+      handle,
+      _closure: closure,
+    }
+  }
+}
+
+impl Drop for Timeout {
+  fn drop(&mut self) {
+    window().clear_timeout_with_handle(self.handle);
+  }
+}
+
+/// Calls a function every `interval` until it's dropped.
+pub struct Interval {
+  handle: i32,
+  _closure: Closure<dyn FnMut()>,
+}
+
+impl Interval {
+  pub fn new<F: 'static + FnMut()>(interval: Duration, f: F) -> Self {
+    let closure = Closure::wrap(Box::new(f) as Box<dyn FnMut()>);
+    let handle = window()
+      .set_interval_with_callback_and_timeout_and_arguments_0(
+        closure.as_ref().unchecked_ref(),
+        duration_millis(interval),
+      )
+      .expect("Failed to set an interval");
+    Interval {
+      handle,
+      _closure: closure,
+    }
+  }
+}
+
+impl Drop for Interval {
+  fn drop(&mut self) {
+    window().clear_interval_with_handle(self.handle);
+  }
+}
+
+/// The browsers count the delays in whole milliseconds.
+fn duration_millis(duration: Duration) -> i32 {
+  let millis = duration.as_millis() + (duration.subsec_nanos() % 1_000_000 != 0) as u128;
+  millis.min(i32::MAX as u128) as i32
+}
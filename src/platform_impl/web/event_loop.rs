@@ -0,0 +1,595 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::{HashMap, VecDeque},
+  marker::PhantomData,
+  mem,
+  rc::Rc,
+  sync::Arc,
+  time::Duration,
+};
+
+use instant::Instant;
+use web_sys::HtmlCanvasElement;
+
+use crate::{
+  error::OsError as RootOsError,
+  event::{Event, StartCause, WindowEvent},
+  event_loop::{
+    self, ControlFlow, DeviceEventFilter, EventLoopClosed, Priority, SendEventError, TimerId,
+  },
+  monitor::MonitorHandle as RootMonitorHandle,
+  user_event_queue::UserEventQueue,
+  window::WindowId as RootWindowId,
+};
+
+use super::{
+  dom::{AnimationFrame, EventListener, Interval, Timeout},
+  monitor::MonitorHandle,
+  window, VirtualDesktop, WindowId,
+};
+
+type EventHandler<T> = dyn FnMut(Event<'_, T>, &mut ControlFlow);
+
+/// The state of the event loop, shared by the callbacks it gives to the browser.
+///
+/// The browser calls back into the event loop, so each callback runs a full iteration from
+/// `Event::NewEvents` to `Event::RedrawEventsCleared` with the events queued until then.
+pub struct Shared<T: 'static>(Rc<Execution<T>>);
+
+struct Execution<T: 'static> {
+  handler: RefCell<Option<Box<EventHandler<T>>>>,
+  control_flow: Cell<ControlFlow>,
+  /// Set during an iteration, the events sent meanwhile are dispatched by it or the next one.
+  running: Cell<bool>,
+  rerun: Cell<bool>,
+  destroyed: Cell<bool>,
+  wait_start: Cell<Instant>,
+  events: RefCell<VecDeque<Event<'static, T>>>,
+  /// The windows whose scale factor changed, with their canvas.
+  scale_changes: RefCell<VecDeque<(WindowId, f64, HtmlCanvasElement)>>,
+  user_queue: Arc<UserEventQueue<T>>,
+  redraw_pending: RefCell<Vec<WindowId>>,
+  redraw_timeouts: RefCell<HashMap<WindowId, Timeout>>,
+  animation_frame: RefCell<Option<AnimationFrame>>,
+  timeout: RefCell<Option<Timeout>>,
+  rerun_timeout: RefCell<Option<Timeout>>,
+  timers: RefCell<HashMap<TimerId, Interval>>,
+  listeners: RefCell<HashMap<WindowId, Vec<EventListener>>>,
+}
+
+impl<T> Clone for Shared<T> {
+  fn clone(&self) -> Self {
+    Shared(self.0.clone())
+  }
+}
+
+impl<T: 'static> Shared<T> {
+  fn new(user_queue: Arc<UserEventQueue<T>>) -> Self {
+    Shared(Rc::new(Execution {
+      handler: RefCell::new(None),
+      control_flow: Cell::new(ControlFlow::default()),
+      running: Cell::new(false),
+      rerun: Cell::new(false),
+      destroyed: Cell::new(false),
+      wait_start: Cell::new(Instant::now()),
+      events: RefCell::new(VecDeque::new()),
+      scale_changes: RefCell::new(VecDeque::new()),
+      user_queue,
+      redraw_pending: RefCell::new(Vec::new()),
+      redraw_timeouts: RefCell::new(HashMap::new()),
+      animation_frame: RefCell::new(None),
+      timeout: RefCell::new(None),
+      rerun_timeout: RefCell::new(None),
+      timers: RefCell::new(HashMap::new()),
+      listeners: RefCell::new(HashMap::new()),
+    }))
+  }
+
+  fn set_handler(&self, handler: Box<EventHandler<T>>) {
+    *self.0.handler.borrow_mut() = Some(handler);
+    self.run_iteration(StartCause::Init, false);
+  }
+
+  pub fn send_event(&self, event: Event<'static, T>) {
+    if self.0.destroyed.get() {
+      return;
+    }
+    self.0.events.borrow_mut().push_back(event);
+    self.wake();
+  }
+
+  pub fn send_scale_change(
+    &self,
+    window_id: WindowId,
+    scale_factor: f64,
+    canvas: HtmlCanvasElement,
+  ) {
+    if self.0.destroyed.get() {
+      return;
+    }
+    self
+      .0
+      .scale_changes
+      .borrow_mut()
+      .push_back((window_id, scale_factor, canvas));
+    self.wake();
+  }
+
+  /// Runs an iteration for the events queued, unless one is running or the event loop isn't
+  /// running yet.
+  fn wake(&self) {
+    if self.0.running.get() {
+      self.0.rerun.set(true);
+      return;
+    }
+    let requested_resume = match self.0.control_flow.get() {
+      ControlFlow::WaitUntil(requested_resume) => Some(requested_resume),
+      _ => None,
+    };
+    self.run_iteration(
+      StartCause::WaitCancelled {
+        start: self.0.wait_start.get(),
+        requested_resume,
+      },
+      false,
+    );
+  }
+
+  pub fn request_redraw(&self, window_id: WindowId) {
+    if self.0.destroyed.get() {
+      return;
+    }
+    let mut redraw_pending = self.0.redraw_pending.borrow_mut();
+    if !redraw_pending.contains(&window_id) {
+      redraw_pending.push(window_id);
+    }
+    drop(redraw_pending);
+    self.request_animation_frame();
+  }
+
+  pub fn request_redraw_after(&self, window_id: WindowId, delay: Duration) {
+    if self.0.destroyed.get() {
+      return;
+    }
+    let runner = self.clone();
+    let timeout = Timeout::new(delay, move || {
+      runner.0.redraw_timeouts.borrow_mut().remove(&window_id);
+      runner.request_redraw(window_id);
+    });
+    self
+      .0
+      .redraw_timeouts
+      .borrow_mut()
+      .insert(window_id, timeout);
+  }
+
+  /// The `RedrawRequested` events are only emitted by the iterations run on an animation frame.
+  fn request_animation_frame(&self) {
+    if self.0.animation_frame.borrow().is_some() {
+      return;
+    }
+    let runner = self.clone();
+    let animation_frame = AnimationFrame::new(move || {
+      runner.0.animation_frame.borrow_mut().take();
+      let cause = match runner.0.control_flow.get() {
+        ControlFlow::Poll => StartCause::Poll,
+        ControlFlow::WaitUntil(requested_resume) => StartCause::WaitCancelled {
+          start: runner.0.wait_start.get(),
+          requested_resume: Some(requested_resume),
+        },
+        _ => StartCause::WaitCancelled {
+          start: runner.0.wait_start.get(),
+          requested_resume: None,
+        },
+      };
+      runner.run_iteration(cause, true);
+    });
+    *self.0.animation_frame.borrow_mut() = Some(animation_frame);
+  }
+
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    if self.0.destroyed.get() {
+      return;
+    }
+    let runner = self.clone();
+    let timer = Interval::new(interval, move || runner.send_event(Event::Timer(id)));
+    self.0.timers.borrow_mut().insert(id, timer);
+  }
+
+  pub fn cancel_timer(&self, id: TimerId) {
+    self.0.timers.borrow_mut().remove(&id);
+  }
+
+  pub fn add_listeners(&self, window_id: WindowId, listeners: Vec<EventListener>) {
+    if self.0.destroyed.get() {
+      return;
+    }
+    self.0.listeners.borrow_mut().insert(window_id, listeners);
+  }
+
+  pub fn remove_window(&self, window_id: WindowId) {
+    let listeners = self.0.listeners.borrow_mut().remove(&window_id);
+    drop(listeners);
+    self
+      .0
+      .redraw_pending
+      .borrow_mut()
+      .retain(|id| *id != window_id);
+    self.0.redraw_timeouts.borrow_mut().remove(&window_id);
+    self.send_event(Event::WindowEvent {
+      window_id: RootWindowId(window_id),
+      event: WindowEvent::Destroyed,
+      timestamp: Instant::now(),
+    });
+  }
+
+  fn run_iteration(&self, cause: StartCause, animation_frame: bool) {
+    if self.0.running.get() || self.0.destroyed.get() || self.0.handler.borrow().is_none() {
+      return;
+    }
+    self.0.running.set(true);
+
+    self.handle(Event::NewEvents(cause));
+    loop {
+      let scale_change = self.0.scale_changes.borrow_mut().pop_front();
+      if let Some((window_id, scale_factor, canvas)) = scale_change {
+        let mut new_inner_size = window::canvas_logical_size(&canvas).to_physical(scale_factor);
+        self.handle(Event::WindowEvent {
+          window_id: RootWindowId(window_id),
+          event: WindowEvent::ScaleFactorChanged {
+            scale_factor,
+            new_inner_size: &mut new_inner_size,
+          },
+          timestamp: Instant::now(),
+        });
+        window::set_canvas_size(&canvas, new_inner_size.into(), scale_factor);
+        self.send_event(Event::WindowEvent {
+          window_id: RootWindowId(window_id),
+          event: WindowEvent::Resized(new_inner_size),
+          timestamp: Instant::now(),
+        });
+        continue;
+      }
+      let event = self.0.events.borrow_mut().pop_front();
+      if let Some(event) = event {
+        self.handle(event);
+        continue;
+      }
+      match self.0.user_queue.pop() {
+        Some(event) => self.handle(Event::UserEvent(event)),
+        None => break,
+      }
+    }
+    self.handle(Event::MainEventsCleared);
+
+    if animation_frame {
+      let redraw_pending = mem::take(&mut *self.0.redraw_pending.borrow_mut());
+      for window_id in redraw_pending {
+        self.handle(Event::RedrawRequested(RootWindowId(window_id)));
+      }
+    }
+    self.handle(Event::RedrawEventsCleared);
+
+    if let Some(event) = Event::idle(self.0.control_flow.get()) {
+      self.handle(event);
+    }
+
+    self.0.running.set(false);
+    self.apply_control_flow();
+  }
+
+  fn handle(&self, event: Event<'_, T>) {
+    let mut handler = self.0.handler.borrow_mut();
+    let handler = match handler.as_mut() {
+      Some(handler) => handler,
+      None => return,
+    };
+    let mut control_flow = self.0.control_flow.get();
+    if let ControlFlow::ExitWithCode(code) = control_flow {
+      handler(event, &mut ControlFlow::ExitWithCode(code));
+    } else {
+      handler(event, &mut control_flow);
+      self.0.control_flow.set(control_flow);
+    }
+  }
+
+  fn apply_control_flow(&self) {
+    let start = Instant::now();
+    self.0.wait_start.set(start);
+    self.0.timeout.borrow_mut().take();
+
+    match self.0.control_flow.get() {
+      ControlFlow::Poll => self.request_animation_frame(),
+      ControlFlow::Wait => (),
+      ControlFlow::WaitUntil(requested_resume) => {
+        let delay = if requested_resume <= start {
+          Duration::default()
+        } else {
+          requested_resume - start
+        };
+        let runner = self.clone();
+        let timeout = Timeout::new(delay, move || {
+          runner.0.timeout.borrow_mut().take();
+          runner.run_iteration(
+            StartCause::ResumeTimeReached {
+              start,
+              requested_resume,
+            },
+            false,
+          );
+        });
+        *self.0.timeout.borrow_mut() = Some(timeout);
+      }
+      ControlFlow::ExitWithCode(_) => {
+        self.destroy();
+        return;
+      }
+    }
+
+    if !self.0.redraw_pending.borrow().is_empty() {
+      self.request_animation_frame();
+    }
+    // The events sent after the queue was drained, e.g. while handling `RedrawRequested`.
+    if self.0.rerun.replace(false) {
+      let runner = self.clone();
+      let timeout = Timeout::new(Duration::default(), move || {
+        runner.0.rerun_timeout.borrow_mut().take();
+        runner.wake();
+      });
+      *self.0.rerun_timeout.borrow_mut() = Some(timeout);
+    }
+  }
+
+  fn destroy(&self) {
+    self.handle(Event::LoopDestroyed);
+    self.0.destroyed.set(true);
+    self.0.user_queue.close();
+    self.0.handler.borrow_mut().take();
+    self.0.events.borrow_mut().clear();
+    self.0.scale_changes.borrow_mut().clear();
+    self.0.redraw_pending.borrow_mut().clear();
+    self.0.redraw_timeouts.borrow_mut().clear();
+    self.0.animation_frame.borrow_mut().take();
+    self.0.timeout.borrow_mut().take();
+    self.0.rerun_timeout.borrow_mut().take();
+    self.0.timers.borrow_mut().clear();
+    self.0.listeners.borrow_mut().clear();
+  }
+}
+
+/// The part of the event loop used by the windows, which doesn't depend on the type of the user
+/// events.
+pub(crate) trait WindowRunner {
+  fn send_window_event(&self, window_id: WindowId, event: WindowEvent<'static>, timestamp: Instant);
+
+  fn send_scale_change(&self, window_id: WindowId, scale_factor: f64, canvas: HtmlCanvasElement);
+
+  fn request_redraw(&self, window_id: WindowId);
+
+  fn request_redraw_after(&self, window_id: WindowId, delay: Duration);
+
+  fn add_listeners(&self, window_id: WindowId, listeners: Vec<EventListener>);
+
+  fn remove_window(&self, window_id: WindowId);
+}
+
+impl<T: 'static> WindowRunner for Shared<T> {
+  fn send_window_event(
+    &self,
+    window_id: WindowId,
+    event: WindowEvent<'static>,
+    timestamp: Instant,
+  ) {
+    self.send_event(Event::WindowEvent {
+      window_id: RootWindowId(window_id),
+      event,
+      timestamp,
+    });
+  }
+
+  fn send_scale_change(&self, window_id: WindowId, scale_factor: f64, canvas: HtmlCanvasElement) {
+    Shared::send_scale_change(self, window_id, scale_factor, canvas)
+  }
+
+  fn request_redraw(&self, window_id: WindowId) {
+    Shared::request_redraw(self, window_id)
+  }
+
+  fn request_redraw_after(&self, window_id: WindowId, delay: Duration) {
+    Shared::request_redraw_after(self, window_id, delay)
+  }
+
+  fn add_listeners(&self, window_id: WindowId, listeners: Vec<EventListener>) {
+    Shared::add_listeners(self, window_id, listeners)
+  }
+
+  fn remove_window(&self, window_id: WindowId) {
+    Shared::remove_window(self, window_id)
+  }
+}
+
+pub struct EventLoop<T: 'static> {
+  window_target: event_loop::EventLoopWindowTarget<T>,
+  user_queue: Arc<UserEventQueue<T>>,
+}
+
+impl<T: 'static> EventLoop<T> {
+  pub fn try_new() -> Result<Self, RootOsError> {
+    Ok(Self::new())
+  }
+
+  pub fn new() -> Self {
+    let user_queue = Arc::new(UserEventQueue::new());
+    EventLoop {
+      window_target: event_loop::EventLoopWindowTarget {
+        p: EventLoopWindowTarget {
+          runner: Shared::new(user_queue.clone()),
+        },
+        _marker: PhantomData,
+      },
+      user_queue,
+    }
+  }
+
+  pub fn run<F>(self, mut event_handler: F) -> !
+  where
+    F: 'static + FnMut(Event<'_, T>, &event_loop::EventLoopWindowTarget<T>, &mut ControlFlow),
+  {
+    let window_target = event_loop::EventLoopWindowTarget {
+      p: self.window_target.p.clone(),
+      _marker: PhantomData,
+    };
+    self
+      .window_target
+      .p
+      .runner
+      .set_handler(Box::new(move |event, control_flow| {
+        event_handler(event, &window_target, control_flow)
+      }));
+
+    // The browser calls back into the event loop with the events of the page once `main`
+    // returns, so the stack is unwound without dropping the event loop.
+    wasm_bindgen::throw_str(
+      "Using exceptions for control flow, don't mind me. This isn't actually an error!",
+    );
+  }
+
+  pub fn window_target(&self) -> &event_loop::EventLoopWindowTarget<T> {
+    &self.window_target
+  }
+
+  pub fn create_proxy(&self) -> EventLoopProxy<T> {
+    EventLoopProxy {
+      queue: self.user_queue.clone(),
+      runner: self.window_target.p.runner.clone(),
+    }
+  }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.user_queue.set_capacity(priority, capacity);
+  }
+}
+
+pub struct EventLoopProxy<T: 'static> {
+  queue: Arc<UserEventQueue<T>>,
+  runner: Shared<T>,
+}
+
+// wasm32-unknown-unknown runs the page on a single thread, so the proxy never leaves it.
+unsafe impl<T: Send> Send for EventLoopProxy<T> {}
+
+impl<T: 'static> EventLoopProxy<T> {
+  pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+    self
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    self.queue.push(event, priority, bounded)?;
+    self.runner.wake();
+    Ok(())
+  }
+
+  pub fn wake_up(&self) {
+    self.runner.wake();
+  }
+}
+
+impl<T> Clone for EventLoopProxy<T> {
+  fn clone(&self) -> Self {
+    EventLoopProxy {
+      queue: self.queue.clone(),
+      runner: self.runner.clone(),
+    }
+  }
+}
+
+pub struct EventLoopWindowTarget<T: 'static> {
+  pub(crate) runner: Shared<T>,
+}
+
+impl<T> Clone for EventLoopWindowTarget<T> {
+  fn clone(&self) -> Self {
+    EventLoopWindowTarget {
+      runner: self.runner.clone(),
+    }
+  }
+}
+
+impl<T: 'static> EventLoopWindowTarget<T> {
+  pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
+    Some(RootMonitorHandle {
+      inner: MonitorHandle,
+    })
+  }
+
+  pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
+    let mut v = VecDeque::with_capacity(1);
+    v.push_back(MonitorHandle);
+    v
+  }
+
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    Vec::new()
+  }
+
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn key_from_scancode(&self, _scancode: u32) -> Option<crate::keyboard::Key<'static>> {
+    None
+  }
+
+  pub fn scancode_from_key(&self, _key: &crate::keyboard::Key<'_>) -> Option<u32> {
+    None
+  }
+
+  pub fn key_repeat_info(&self) -> Option<crate::keyboard::KeyRepeatInfo> {
+    None
+  }
+
+  pub fn modifiers_state(&self) -> crate::keyboard::ModifiersState {
+    crate::keyboard::ModifiersState::empty()
+  }
+
+  pub fn lock_key_state(&self, _key: crate::keyboard::LockKey) -> Option<bool> {
+    None
+  }
+
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    self.runner.set_timer(interval, id)
+  }
+
+  pub fn cancel_timer(&self, id: TimerId) {
+    self.runner.cancel_timer(id)
+  }
+
+  pub fn set_timer_resolution(&self, _resolution: Option<Duration>) {}
+
+  pub fn set_device_event_filter(&self, _filter: DeviceEventFilter) {}
+
+  pub fn device_info(
+    &self,
+    _device_id: crate::platform_impl::DeviceId,
+  ) -> Option<crate::event::DeviceInfo> {
+    None
+  }
+}
@@ -0,0 +1,118 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(target_arch = "wasm32")]
+
+// A window is a `<canvas>` of the page, and the event loop is driven by the callbacks of the
+// browser: the listeners of the page events, `requestAnimationFrame` for the redraws, and
+// `setTimeout` for `ControlFlow::WaitUntil`.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+  accelerator::Accelerator,
+  menu::{CustomMenuItem, MenuId, MenuItem, MenuType},
+};
+
+mod clipboard;
+mod dom;
+mod event_loop;
+mod keyboard;
+mod monitor;
+#[cfg(feature = "test")]
+mod synthetic_input;
+mod window;
+
+pub use self::{
+  clipboard::Clipboard,
+  event_loop::{EventLoop, EventLoopProxy, EventLoopWindowTarget},
+  keyboard::{current_keyboard_layout, keycode_from_scancode, keycode_to_scancode},
+  monitor::{MonitorHandle, VideoMode},
+  window::{PlatformSpecificWindowBuilderAttributes, Window, WindowId},
+};
+
+// todo: render a menubar in the page
+#[derive(Debug, Clone)]
+pub struct MenuItemAttributes;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct KeyEventExtra {}
+
+#[derive(Debug, Clone)]
+pub struct Menu;
+
+impl Default for Menu {
+  fn default() -> Self {
+    Menu::new()
+  }
+}
+
+impl Menu {
+  pub fn new() -> Self {
+    Menu {}
+  }
+  pub fn new_popup_menu() -> Self {
+    Self::new()
+  }
+  pub fn add_item(
+    &mut self,
+    _menu_id: MenuId,
+    _title: &str,
+    _accelerator: Option<Accelerator>,
+    _enabled: bool,
+    _selected: bool,
+    _menu_type: MenuType,
+  ) -> CustomMenuItem {
+    CustomMenuItem(MenuItemAttributes {})
+  }
+  pub fn add_submenu(&mut self, _title: &str, _enabled: bool, _submenu: Menu) {}
+  pub fn add_native_item(
+    &mut self,
+    _item: MenuItem,
+    _menu_type: MenuType,
+  ) -> Option<CustomMenuItem> {
+    None
+  }
+}
+
+impl MenuItemAttributes {
+  pub fn id(self) -> MenuId {
+    MenuId::EMPTY
+  }
+  pub fn set_enabled(&mut self, _is_enabled: bool) {}
+  pub fn set_title(&mut self, _title: &str) {}
+  pub fn set_selected(&mut self, _is_selected: bool) {}
+  pub fn set_icon(&mut self, _icon: Vec<u8>) {}
+}
+
+/// The `pointerId` of the pointer events.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeviceId(i32);
+
+impl DeviceId {
+  pub fn dummy() -> Self {
+    DeviceId(0)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VirtualDesktop;
+
+// The canvases can't be closed by the user.
+#[derive(Clone)]
+pub struct CloseHandle;
+
+impl CloseHandle {
+  pub fn close(&self) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct OsError(pub(crate) String);
+
+impl Display for OsError {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+    write!(fmt, "{}", self.0)
+  }
+}
+
+pub(crate) use crate::icon::NoIcon as PlatformIcon;
@@ -0,0 +1,82 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+  dpi::{PhysicalPosition, PhysicalSize},
+  monitor,
+};
+
+use super::dom;
+
+/// The screen showing the page, the browsers don't expose the other ones.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MonitorHandle;
+
+impl MonitorHandle {
+  pub fn name(&self) -> Option<String> {
+    Some("Browser".to_owned())
+  }
+
+  pub fn size(&self) -> PhysicalSize<u32> {
+    let screen = match dom::window().screen() {
+      Ok(screen) => screen,
+      Err(_) => return PhysicalSize::new(0, 0),
+    };
+    let width = screen.width().unwrap_or_default() as f64;
+    let height = screen.height().unwrap_or_default() as f64;
+    PhysicalSize::from_logical::<_, f64>((width, height), self.scale_factor())
+  }
+
+  pub fn position(&self) -> PhysicalPosition<i32> {
+    (0, 0).into()
+  }
+
+  pub fn scale_factor(&self) -> f64 {
+    dom::scale_factor()
+  }
+
+  pub fn video_modes(&self) -> impl Iterator<Item = monitor::VideoMode> {
+    // FIXME this is not the real refresh rate, the browsers don't expose it
+    let video_mode = monitor::VideoMode {
+      video_mode: VideoMode {
+        size: self.size().into(),
+        bit_depth: 32,
+        refresh_rate: 60,
+        monitor: self.clone(),
+      },
+    };
+    std::iter::once(video_mode)
+  }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VideoMode {
+  size: (u32, u32),
+  bit_depth: u16,
+  refresh_rate: u16,
+  monitor: MonitorHandle,
+}
+
+impl VideoMode {
+  pub fn size(&self) -> PhysicalSize<u32> {
+    self.size.into()
+  }
+
+  pub fn bit_depth(&self) -> u16 {
+    self.bit_depth
+  }
+
+  pub fn refresh_rate(&self) -> u16 {
+    self.refresh_rate
+  }
+
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate as u32 * 1000
+  }
+
+  pub fn monitor(&self) -> monitor::MonitorHandle {
+    monitor::MonitorHandle {
+      inner: self.monitor.clone(),
+    }
+  }
+}
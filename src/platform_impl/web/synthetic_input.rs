@@ -0,0 +1,189 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The synthetic input of `Window::inject_event`, dispatched to the canvas as the DOM events of the
+// browser so the listeners of the window handle them. The modifiers and the buttons pressed by the
+// synthetic events are kept for the next ones.
+use std::cell::Cell;
+
+use web_sys::{
+  HtmlCanvasElement, KeyboardEvent, KeyboardEventInit, PointerEvent, PointerEventInit, WheelEvent,
+  WheelEventInit,
+};
+
+use super::{dom, keyboard, OsError};
+use crate::{
+  dpi::PhysicalPosition,
+  error::{ExternalError, NotSupportedError},
+  event::{ElementState, MouseButton, TouchPhase},
+  keyboard::{KeyCode, ModifiersState},
+  test::SyntheticEvent,
+};
+
+// The id of the mouse in the pointer events.
+const MOUSE_POINTER_ID: i32 = 1;
+
+thread_local! {
+  // The modifiers and the mouse buttons pressed by the synthetic events.
+  static MODIFIERS: Cell<ModifiersState> = Cell::new(ModifiersState::empty());
+  static BUTTONS: Cell<u16> = Cell::new(0);
+}
+
+pub fn inject_event(
+  canvas: &HtmlCanvasElement,
+  event: SyntheticEvent,
+) -> Result<(), ExternalError> {
+  let modifiers = MODIFIERS.with(Cell::get);
+  let dom_event: web_sys::Event = match event {
+    SyntheticEvent::Key {
+      physical_key,
+      state,
+    } => {
+      let code = keyboard::code(physical_key)
+        .ok_or_else(|| ExternalError::NotSupported(NotSupportedError::new()))?;
+      let pressed = state == ElementState::Pressed;
+      let modifiers = match modifier(physical_key) {
+        Some(modifier) => {
+          let mut modifiers = modifiers;
+          modifiers.set(modifier, pressed);
+          MODIFIERS.with(|cell| cell.set(modifiers));
+          modifiers
+        }
+        None => modifiers,
+      };
+      let mut init = KeyboardEventInit::new();
+      init
+        .code(&code)
+        .key("Unidentified")
+        .shift_key(modifiers.shift_key())
+        .ctrl_key(modifiers.control_key())
+        .alt_key(modifiers.alt_key())
+        .meta_key(modifiers.super_key())
+        .bubbles(true)
+        .cancelable(true);
+      let event_type = if pressed { "keydown" } else { "keyup" };
+      KeyboardEvent::new_with_keyboard_event_init_dict(event_type, &init)
+        .map_err(os_error)?
+        .into()
+    }
+    SyntheticEvent::CursorMoved { position } => {
+      let mut init = pointer_init(canvas, MOUSE_POINTER_ID, "mouse", position, modifiers);
+      init.button(-1).buttons(BUTTONS.with(Cell::get));
+      PointerEvent::new_with_event_init_dict("pointermove", &init)
+        .map_err(os_error)?
+        .into()
+    }
+    SyntheticEvent::MouseInput {
+      button,
+      state,
+      position,
+    } => {
+      let button = match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+        MouseButton::Back => 3,
+        MouseButton::Forward => 4,
+        MouseButton::Other(button) => button as i16,
+      };
+      // The bits of `buttons` swap the middle and the right buttons.
+      let bit = match button {
+        1 => 1 << 2,
+        2 => 1 << 1,
+        button => 1u16.checked_shl(button as u32).unwrap_or(0),
+      };
+      let pressed = state == ElementState::Pressed;
+      let buttons = BUTTONS.with(|buttons| {
+        if pressed {
+          buttons.set(buttons.get() | bit)
+        } else {
+          buttons.set(buttons.get() & !bit)
+        }
+        buttons.get()
+      });
+      let mut init = pointer_init(canvas, MOUSE_POINTER_ID, "mouse", position, modifiers);
+      init.button(button).buttons(buttons);
+      let event_type = if pressed { "pointerdown" } else { "pointerup" };
+      PointerEvent::new_with_event_init_dict(event_type, &init)
+        .map_err(os_error)?
+        .into()
+    }
+    SyntheticEvent::MouseWheel { lines } => {
+      let mut init = WheelEventInit::new();
+      // Positive deltas scroll the page down, the opposite of `MouseScrollDelta`.
+      init
+        .delta_x(-lines.0 as f64)
+        .delta_y(-lines.1 as f64)
+        .delta_mode(WheelEvent::DOM_DELTA_LINE)
+        .shift_key(modifiers.shift_key())
+        .ctrl_key(modifiers.control_key())
+        .alt_key(modifiers.alt_key())
+        .meta_key(modifiers.super_key())
+        .bubbles(true)
+        .cancelable(true);
+      WheelEvent::new_with_event_init_dict("wheel", &init)
+        .map_err(os_error)?
+        .into()
+    }
+    SyntheticEvent::Touch {
+      id,
+      phase,
+      location,
+    } => {
+      let (event_type, buttons) = match phase {
+        TouchPhase::Started => ("pointerdown", 1),
+        TouchPhase::Moved => ("pointermove", 1),
+        TouchPhase::Ended => ("pointerup", 0),
+        TouchPhase::Cancelled => ("pointercancel", 0),
+      };
+      let mut init = pointer_init(canvas, id as i32, "touch", location, modifiers);
+      init.button(0).buttons(buttons).pressure(0.5);
+      PointerEvent::new_with_event_init_dict(event_type, &init)
+        .map_err(os_error)?
+        .into()
+    }
+  };
+  canvas.dispatch_event(&dom_event).map_err(os_error)?;
+  Ok(())
+}
+
+fn os_error(error: wasm_bindgen::JsValue) -> ExternalError {
+  ExternalError::Os(os_error!(OsError(format!("{:?}", error))))
+}
+
+fn modifier(key: KeyCode) -> Option<ModifiersState> {
+  match key {
+    KeyCode::ShiftLeft | KeyCode::ShiftRight => Some(ModifiersState::SHIFT),
+    KeyCode::ControlLeft | KeyCode::ControlRight => Some(ModifiersState::CONTROL),
+    KeyCode::AltLeft | KeyCode::AltRight => Some(ModifiersState::ALT),
+    KeyCode::SuperLeft | KeyCode::SuperRight => Some(ModifiersState::SUPER),
+    _ => None,
+  }
+}
+
+/// The pointer event of a physical position in the canvas, whose event coordinates are logical and
+/// relative to the page.
+fn pointer_init(
+  canvas: &HtmlCanvasElement,
+  pointer_id: i32,
+  pointer_type: &str,
+  position: PhysicalPosition<f64>,
+  modifiers: ModifiersState,
+) -> PointerEventInit {
+  let rect = canvas.get_bounding_client_rect();
+  let position = position.to_logical::<f64>(dom::scale_factor());
+  let mut init = PointerEventInit::new();
+  init
+    .pointer_id(pointer_id)
+    .pointer_type(pointer_type)
+    .is_primary(true)
+    .client_x((rect.left() + position.x) as i32)
+    .client_y((rect.top() + position.y) as i32)
+    .shift_key(modifiers.shift_key())
+    .ctrl_key(modifiers.control_key())
+    .alt_key(modifiers.alt_key())
+    .meta_key(modifiers.super_key())
+    .bubbles(true)
+    .cancelable(true);
+  init
+}
@@ -0,0 +1,921 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::VecDeque,
+  rc::Rc,
+  sync::atomic::{AtomicU32, Ordering},
+  time::Duration,
+};
+
+use instant::Instant;
+use wasm_bindgen::JsCast;
+use web_sys::{FocusEvent, HtmlCanvasElement, KeyboardEvent, PointerEvent, WheelEvent};
+
+use crate::{
+  click_count::{ClickCounter, DEFAULT_DISTANCE, DEFAULT_INTERVAL},
+  dpi::{LogicalSize, PhysicalPosition, PhysicalSize, Position, Size},
+  error::{ExternalError, NotSupportedError, OsError as RootOsError},
+  event::{
+    DeviceId as RootDeviceId, ElementState, FocusDirection, Force, MouseButton, MouseScrollDelta,
+    PenHover, PenInput, Touch, TouchPhase, WindowEvent,
+  },
+  icon::Icon,
+  keyboard::ModifiersState,
+  monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
+  window::{
+    AspectRatio, CursorGrabMode, CursorIcon, DragRegion, Edges, Effect, Fullscreen, ProgressState,
+    RedrawPacing, Theme, TitlebarButtons, UserAttentionType, WindowAttributes, WindowImage,
+    WindowLevel,
+  },
+};
+
+use super::{
+  dom::{self, EventListener},
+  event_loop::{EventLoopWindowTarget, WindowRunner},
+  keyboard,
+  monitor::MonitorHandle,
+  DeviceId, Menu, OsError, VirtualDesktop,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WindowId(u32);
+
+impl WindowId {
+  pub fn dummy() -> Self {
+    WindowId(0)
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlatformSpecificWindowBuilderAttributes {
+  pub(crate) canvas: Option<HtmlCanvasElement>,
+}
+
+pub struct Window {
+  id: WindowId,
+  canvas: HtmlCanvasElement,
+  /// Whether the canvas was created for the window, and is removed from the page with it.
+  owns_canvas: bool,
+  runner: Rc<dyn WindowRunner>,
+  cursor_icon: Cell<CursorIcon>,
+  cursor_visible: Cell<bool>,
+  /// Set with `Window::start_text_input`, the entered text being sent with `ReceivedText`.
+  text_input: Rc<Cell<bool>>,
+}
+
+// wasm32-unknown-unknown runs the page on a single thread, so the window never leaves it.
+unsafe impl Send for Window {}
+unsafe impl Sync for Window {}
+
+impl Window {
+  pub fn new<T: 'static>(
+    target: &EventLoopWindowTarget<T>,
+    attributes: WindowAttributes,
+    pl_attribs: PlatformSpecificWindowBuilderAttributes,
+  ) -> Result<Self, RootOsError> {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+    let id = WindowId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+    let document = dom::document();
+    let (canvas, owns_canvas) = match pl_attribs.canvas {
+      Some(canvas) => (canvas, false),
+      None => {
+        let canvas = document
+          .create_element("canvas")
+          .map_err(|e| os_error!(OsError(format!("{:?}", e))))?
+          .unchecked_into::<HtmlCanvasElement>();
+        (canvas, true)
+      }
+    };
+
+    // The canvas needs to be focusable to receive the keyboard events.
+    if !canvas.has_attribute("tabindex") {
+      let _ = canvas.set_attribute("tabindex", "0");
+    }
+    // Identifies the canvas in the `raw_window_handle::web::WebHandle` of the window.
+    let _ = canvas.set_attribute("data-raw-handle", &id.0.to_string());
+
+    let runner: Rc<dyn WindowRunner> = Rc::new(target.runner.clone());
+    let text_input = Rc::new(Cell::new(false));
+    runner.add_listeners(
+      id,
+      listeners(id, &canvas, runner.clone(), text_input.clone()),
+    );
+
+    let window = Window {
+      id,
+      canvas,
+      owns_canvas,
+      runner,
+      cursor_icon: Cell::new(CursorIcon::Default),
+      cursor_visible: Cell::new(true),
+      text_input,
+    };
+
+    if let Some(size) = attributes.inner_size {
+      window.set_inner_size(size);
+    }
+    window.set_title(&attributes.title);
+    window.set_visible(attributes.visible);
+    if owns_canvas {
+      let body = document
+        .body()
+        .ok_or_else(|| os_error!(OsError("The page has no body".into())))?;
+      body
+        .append_child(&window.canvas)
+        .map_err(|e| os_error!(OsError(format!("{:?}", e))))?;
+    }
+    if attributes.fullscreen.is_some() {
+      window.set_fullscreen(attributes.fullscreen);
+    }
+
+    Ok(window)
+  }
+
+  pub fn id(&self) -> WindowId {
+    self.id
+  }
+
+  pub fn canvas(&self) -> HtmlCanvasElement {
+    self.canvas.clone()
+  }
+
+  pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
+    Some(RootMonitorHandle {
+      inner: MonitorHandle,
+    })
+  }
+
+  pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
+    let mut v = VecDeque::with_capacity(1);
+    v.push_back(MonitorHandle);
+    v
+  }
+
+  pub fn current_monitor(&self) -> Option<RootMonitorHandle> {
+    Some(RootMonitorHandle {
+      inner: MonitorHandle,
+    })
+  }
+
+  pub fn scale_factor(&self) -> f64 {
+    dom::scale_factor()
+  }
+
+  pub fn request_redraw(&self) {
+    self.runner.request_redraw(self.id);
+  }
+
+  pub fn request_redraw_after(&self, delay: Duration) {
+    self.runner.request_redraw_after(self.id, delay);
+  }
+
+  // The redraws always follow `requestAnimationFrame`.
+  pub fn set_redraw_pacing(&self, _pacing: RedrawPacing) {}
+
+  pub fn inner_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
+    self.outer_position()
+  }
+
+  pub fn outer_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
+    let rect = self.canvas.get_bounding_client_rect();
+    Ok(PhysicalPosition::from_logical::<_, f64>((rect.x(), rect.y()), self.scale_factor()).cast())
+  }
+
+  pub fn set_outer_position(&self, _position: Position) {
+    warn!("`Window::set_outer_position` is ignored on the web");
+  }
+
+  pub fn inner_size(&self) -> PhysicalSize<u32> {
+    PhysicalSize::new(self.canvas.width(), self.canvas.height())
+  }
+
+  pub fn set_inner_size(&self, size: Size) {
+    let scale_factor = self.scale_factor();
+    set_canvas_size(&self.canvas, size, scale_factor);
+    self.runner.send_window_event(
+      self.id,
+      WindowEvent::Resized(size.to_physical(scale_factor)),
+      Instant::now(),
+    );
+  }
+
+  pub fn outer_size(&self) -> PhysicalSize<u32> {
+    self.inner_size()
+  }
+
+  pub fn set_min_inner_size(&self, _: Option<Size>) {}
+
+  pub fn set_max_inner_size(&self, _: Option<Size>) {}
+
+  pub fn set_aspect_ratio(&self, _: Option<AspectRatio>) {}
+
+  pub fn set_resizable_edges(&self, _: Edges) {}
+
+  pub fn set_titlebar_buttons(&self, _: TitlebarButtons) {}
+
+  pub fn set_title(&self, title: &str) {
+    dom::document().set_title(title);
+  }
+
+  pub fn set_menu(&self, _menu: Option<Menu>) {}
+
+  pub fn set_visible(&self, visible: bool) {
+    let display = if visible { "" } else { "none" };
+    let _ = self.canvas.style().set_property("display", display);
+  }
+
+  pub fn show_without_activating(&self) {
+    self.set_visible(true);
+  }
+
+  pub fn set_focus(&self) {
+    let _ = self.canvas.focus();
+  }
+
+  pub fn raise(&self) {}
+
+  pub fn set_above(&self, _window: &Window) {}
+
+  pub fn set_below(&self, _window: &Window) {}
+
+  pub fn set_resizable(&self, _resizable: bool) {}
+
+  pub fn set_minimized(&self, _minimized: bool) {}
+
+  pub fn set_maximized(&self, _maximized: bool) {}
+
+  pub fn set_maximized_on(&self, _monitor: RootMonitorHandle) {}
+
+  pub fn is_maximized(&self) -> bool {
+    false
+  }
+
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    None
+  }
+
+  pub fn is_visible(&self) -> bool {
+    self
+      .canvas
+      .style()
+      .get_property_value("display")
+      .map(|display| display != "none")
+      .unwrap_or(true)
+  }
+
+  pub fn is_resizable(&self) -> bool {
+    false
+  }
+
+  pub fn is_decorated(&self) -> bool {
+    false
+  }
+
+  pub fn set_enabled(&self, _enabled: bool) {}
+
+  pub fn is_enabled(&self) -> bool {
+    true
+  }
+
+  pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+    let document = dom::document();
+    if fullscreen.is_some() {
+      // Browsers only allow it while handling an input of the user.
+      if let Err(e) = self.canvas.request_fullscreen() {
+        warn!("Failed to enter fullscreen: {:?}", e);
+      }
+    } else if self.fullscreen().is_some() {
+      document.exit_fullscreen();
+    }
+  }
+
+  pub fn set_fullscreen_video_mode(&self, _video_mode: RootVideoMode) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn fullscreen(&self) -> Option<Fullscreen> {
+    let fullscreen_element = dom::document().fullscreen_element()?;
+    let canvas: &web_sys::Element = &self.canvas;
+    if fullscreen_element == *canvas {
+      Some(Fullscreen::Borderless(self.current_monitor()))
+    } else {
+      None
+    }
+  }
+
+  pub fn set_kiosk(&self, _kiosk: bool) {}
+
+  pub fn set_decorations(&self, _decorations: bool) {}
+
+  pub fn set_shadow(&self, _shadow: bool) {}
+
+  pub fn set_opacity(&self, opacity: f32) {
+    let _ = self
+      .canvas
+      .style()
+      .set_property("opacity", &opacity.to_string());
+  }
+
+  pub fn set_content_protected(&self, _protected: bool) {}
+
+  pub fn set_owner(&self, _owner: Option<WindowId>) {}
+
+  pub fn set_window_level(&self, _level: WindowLevel) {}
+
+  pub fn set_visible_on_all_workspaces(&self, _visible: bool) {}
+
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    None
+  }
+
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    true
+  }
+
+  pub fn move_to_virtual_desktop(&self, _: &VirtualDesktop) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_skip_taskbar(&self, _skip: bool) {}
+
+  pub fn set_minimize_to_tray(&self, _minimize_to_tray: bool) {}
+
+  pub fn set_theme(&self, _theme: Option<Theme>) {}
+
+  pub fn set_background_effect(&self, _effect: Option<Effect>) {}
+
+  pub fn set_window_icon(&self, _window_icon: Option<Icon>) {}
+
+  pub fn set_ime_cursor_area(&self, _position: Position, _size: Size) {}
+
+  pub fn set_ime_allowed(&self, _allowed: bool) {}
+
+  pub fn set_text_input(&self, active: bool) {
+    self.text_input.set(active);
+  }
+
+  pub fn request_user_attention(&self, _request_type: Option<UserAttentionType>) {}
+
+  pub fn set_progress_bar(&self, _state: ProgressState, _progress: Option<u64>) {}
+
+  pub fn set_badge_count(&self, _count: Option<u64>) {}
+
+  pub fn hide_menu(&self) {}
+
+  pub fn show_menu(&self) {}
+
+  pub fn is_menu_visible(&self) -> bool {
+    false
+  }
+
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_cursor_icon(&self, cursor: CursorIcon) {
+    self.cursor_icon.set(cursor);
+    self.update_cursor();
+  }
+
+  pub fn set_cursor_position(&self, _: Position) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+    match mode {
+      CursorGrabMode::None => dom::document().exit_pointer_lock(),
+      // The browsers can't confine the pointer without locking it.
+      CursorGrabMode::Confined => {
+        return Err(ExternalError::NotSupported(NotSupportedError::new()))
+      }
+      CursorGrabMode::Locked => self.canvas.request_pointer_lock(),
+    }
+    Ok(())
+  }
+
+  #[cfg(feature = "test")]
+  pub fn inject_event(&self, event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    super::synthetic_input::inject_event(&self.canvas, event)
+  }
+
+  pub fn set_mouse_capture(&self, _capture: bool) -> Result<(), ExternalError> {
+    // The pointer can only be captured while its buttons are pressed, which it is already.
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_keyboard_grab(&self, _grab: bool) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    let pointer_events = if ignore { "none" } else { "" };
+    let _ = self
+      .canvas
+      .style()
+      .set_property("pointer-events", pointer_events);
+    Ok(())
+  }
+
+  pub fn set_mouse_coalescing(&self, _enabled: bool) {}
+
+  pub fn set_cursor_visible(&self, visible: bool) {
+    self.cursor_visible.set(visible);
+    self.update_cursor();
+  }
+
+  fn update_cursor(&self) {
+    let cursor = if self.cursor_visible.get() {
+      cursor_name(self.cursor_icon.get())
+    } else {
+      "none"
+    };
+    let _ = self.canvas.style().set_property("cursor", cursor);
+  }
+
+  pub fn drag_window(&self) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
+  pub fn set_drag_region(&self, _regions: Vec<DragRegion>) {}
+
+  pub fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+    let mut handle = raw_window_handle::web::WebHandle::empty();
+    handle.id = self.id.0;
+    raw_window_handle::RawWindowHandle::Web(handle)
+  }
+}
+
+impl Drop for Window {
+  fn drop(&mut self) {
+    self.runner.remove_window(self.id);
+    if self.owns_canvas {
+      self.canvas.remove();
+    }
+  }
+}
+
+/// Returns the size of the canvas on the page.
+pub(crate) fn canvas_logical_size(canvas: &HtmlCanvasElement) -> LogicalSize<f64> {
+  let rect = canvas.get_bounding_client_rect();
+  LogicalSize::new(rect.width(), rect.height())
+}
+
+/// Sets the size of the canvas on the page, and the size of its backing store in physical pixels.
+pub(crate) fn set_canvas_size(canvas: &HtmlCanvasElement, size: Size, scale_factor: f64) {
+  let physical_size = size.to_physical::<u32>(scale_factor);
+  let logical_size = size.to_logical::<f64>(scale_factor);
+  canvas.set_width(physical_size.width);
+  canvas.set_height(physical_size.height);
+  let style = canvas.style();
+  let _ = style.set_property("width", &format!("{}px", logical_size.width));
+  let _ = style.set_property("height", &format!("{}px", logical_size.height));
+}
+
+fn cursor_name(cursor: CursorIcon) -> &'static str {
+  match cursor {
+    CursorIcon::Default | CursorIcon::Arrow => "default",
+    CursorIcon::Crosshair => "crosshair",
+    CursorIcon::Hand => "pointer",
+    CursorIcon::Move => "move",
+    CursorIcon::Text => "text",
+    CursorIcon::Wait => "wait",
+    CursorIcon::Help => "help",
+    CursorIcon::Progress => "progress",
+    CursorIcon::NotAllowed => "not-allowed",
+    CursorIcon::ContextMenu => "context-menu",
+    CursorIcon::Cell => "cell",
+    CursorIcon::VerticalText => "vertical-text",
+    CursorIcon::Alias => "alias",
+    CursorIcon::Copy => "copy",
+    CursorIcon::NoDrop => "no-drop",
+    CursorIcon::Grab => "grab",
+    CursorIcon::Grabbing => "grabbing",
+    CursorIcon::AllScroll => "all-scroll",
+    CursorIcon::ZoomIn => "zoom-in",
+    CursorIcon::ZoomOut => "zoom-out",
+    CursorIcon::EResize => "e-resize",
+    CursorIcon::NResize => "n-resize",
+    CursorIcon::NeResize => "ne-resize",
+    CursorIcon::NwResize => "nw-resize",
+    CursorIcon::SResize => "s-resize",
+    CursorIcon::SeResize => "se-resize",
+    CursorIcon::SwResize => "sw-resize",
+    CursorIcon::WResize => "w-resize",
+    CursorIcon::EwResize => "ew-resize",
+    CursorIcon::NsResize => "ns-resize",
+    CursorIcon::NeswResize => "nesw-resize",
+    CursorIcon::NwseResize => "nwse-resize",
+    CursorIcon::ColResize => "col-resize",
+    CursorIcon::RowResize => "row-resize",
+  }
+}
+
+/// Listens to the events of the canvas and of the page that the window emits.
+fn listeners(
+  id: WindowId,
+  canvas: &HtmlCanvasElement,
+  runner: Rc<dyn WindowRunner>,
+  text_input: Rc<Cell<bool>>,
+) -> Vec<EventListener> {
+  let modifiers = Rc::new(Cell::new(ModifiersState::empty()));
+  let scale_factor = Rc::new(Cell::new(dom::scale_factor()));
+  // The browsers don't count the clicks of the pointer events.
+  let click_counter = Rc::new(RefCell::new(ClickCounter::default()));
+
+  let send = {
+    let runner = runner.clone();
+    Rc::new(move |event: WindowEvent<'static>, timestamp: Instant| {
+      runner.send_window_event(id, event, timestamp)
+    })
+  };
+  // Emits `ModifiersChanged` before the events whose modifiers differ from the previous ones.
+  let update_modifiers = {
+    let send = send.clone();
+    let modifiers = modifiers.clone();
+    Rc::new(move |state: ModifiersState, timestamp: Instant| {
+      if modifiers.replace(state) != state {
+        send(WindowEvent::ModifiersChanged(state), timestamp);
+      }
+    })
+  };
+
+  // Sends the pointer events, every touch being a frame of its own.
+  let send_pointer = {
+    let send = send.clone();
+    Rc::new(move |event: WindowEvent<'static>, timestamp: Instant| {
+      let touch = match &event {
+        WindowEvent::Touch(touch) => Some(*touch),
+        _ => None,
+      };
+      send(event, timestamp);
+      if let Some(touch) = touch {
+        send(WindowEvent::TouchFrame(vec![touch]), timestamp);
+      }
+    })
+  };
+
+  let mut listeners = Vec::new();
+
+  {
+    let send = send.clone();
+    let send_pointer = send_pointer.clone();
+    let click_counter = click_counter.clone();
+    let update_modifiers = update_modifiers.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "pointermove",
+      move |event: PointerEvent| {
+        let timestamp = dom::event_timestamp(&event);
+        update_modifiers(mouse_modifiers(&event), timestamp);
+        send_pointer(
+          pointer_event(&event, TouchPhase::Moved, timestamp, &click_counter),
+          timestamp,
+        );
+        if let Some(pen) = pen_input(&event, TouchPhase::Moved) {
+          send(WindowEvent::PenInput(pen), timestamp);
+        } else if let Some(pen) = pen_hover(&event) {
+          send(WindowEvent::PenHover(pen), timestamp);
+        }
+      },
+    ));
+  }
+  {
+    let send = send.clone();
+    let send_pointer = send_pointer.clone();
+    let click_counter = click_counter.clone();
+    let update_modifiers = update_modifiers.clone();
+    let capture_target = canvas.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "pointerdown",
+      move |event: PointerEvent| {
+        let timestamp = dom::event_timestamp(&event);
+        // Keeps receiving the events of the pointer while it's dragged out of the canvas.
+        let _ = capture_target.set_pointer_capture(event.pointer_id());
+        update_modifiers(mouse_modifiers(&event), timestamp);
+        send_pointer(
+          pointer_event(&event, TouchPhase::Started, timestamp, &click_counter),
+          timestamp,
+        );
+        if let Some(pen) = pen_input(&event, TouchPhase::Started) {
+          send(WindowEvent::PenInput(pen), timestamp);
+        }
+      },
+    ));
+  }
+  {
+    let send = send.clone();
+    let send_pointer = send_pointer.clone();
+    let click_counter = click_counter.clone();
+    let update_modifiers = update_modifiers.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "pointerup",
+      move |event: PointerEvent| {
+        let timestamp = dom::event_timestamp(&event);
+        update_modifiers(mouse_modifiers(&event), timestamp);
+        send_pointer(
+          pointer_event(&event, TouchPhase::Ended, timestamp, &click_counter),
+          timestamp,
+        );
+        if let Some(pen) = pen_input(&event, TouchPhase::Ended) {
+          send(WindowEvent::PenInput(pen), timestamp);
+        }
+      },
+    ));
+  }
+  {
+    let send = send.clone();
+    let send_pointer = send_pointer.clone();
+    let click_counter = click_counter.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "pointercancel",
+      move |event: PointerEvent| {
+        let timestamp = dom::event_timestamp(&event);
+        if event.pointer_type() == "touch" {
+          send_pointer(
+            pointer_event(&event, TouchPhase::Cancelled, timestamp, &click_counter),
+            timestamp,
+          );
+        } else if let Some(pen) = pen_input(&event, TouchPhase::Cancelled) {
+          send(WindowEvent::PenInput(pen), timestamp);
+        }
+      },
+    ));
+  }
+  {
+    let send = send.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "pointerenter",
+      move |event: PointerEvent| {
+        if event.pointer_type() != "touch" {
+          let device_id = RootDeviceId(DeviceId(event.pointer_id()));
+          let timestamp = dom::event_timestamp(&event);
+          send(WindowEvent::CursorEntered { device_id }, timestamp);
+          if event.pointer_type() == "pen" {
+            send(
+              WindowEvent::PenProximity {
+                device_id,
+                in_range: true,
+              },
+              timestamp,
+            );
+          }
+        }
+      },
+    ));
+  }
+  {
+    let send = send.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "pointerleave",
+      move |event: PointerEvent| {
+        if event.pointer_type() != "touch" {
+          let device_id = RootDeviceId(DeviceId(event.pointer_id()));
+          let timestamp = dom::event_timestamp(&event);
+          if event.pointer_type() == "pen" {
+            send(
+              WindowEvent::PenProximity {
+                device_id,
+                in_range: false,
+              },
+              timestamp,
+            );
+          }
+          send(WindowEvent::CursorLeft { device_id }, timestamp);
+        }
+      },
+    ));
+  }
+  {
+    let send = send.clone();
+    let update_modifiers = update_modifiers.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      "wheel",
+      move |event: WheelEvent| {
+        // Keeps the page from scrolling.
+        event.prevent_default();
+        let timestamp = dom::event_timestamp(&event);
+        let modifiers = mouse_modifiers(&event);
+        update_modifiers(modifiers, timestamp);
+        // Positive deltas scroll the page down, the opposite of `MouseScrollDelta`.
+        let (x, y) = (-event.delta_x(), -event.delta_y());
+        let delta = match event.delta_mode() {
+          WheelEvent::DOM_DELTA_PIXEL => MouseScrollDelta::PixelDelta(
+            PhysicalPosition::from_logical::<_, f64>((x, y), dom::scale_factor()),
+          ),
+          _ => MouseScrollDelta::LineDelta(x as f32, y as f32),
+        };
+        #[allow(deprecated)]
+        send(
+          WindowEvent::MouseWheel {
+            device_id: RootDeviceId(DeviceId::dummy()),
+            delta,
+            phase: TouchPhase::Moved,
+            momentum: false,
+            modifiers,
+          },
+          timestamp,
+        );
+      },
+    ));
+  }
+  let dead_key_pending = Rc::new(Cell::new(false));
+  for &(event_type, state) in &[
+    ("keydown", ElementState::Pressed),
+    ("keyup", ElementState::Released),
+  ] {
+    let send = send.clone();
+    let update_modifiers = update_modifiers.clone();
+    let dead_key_pending = dead_key_pending.clone();
+    let text_input = text_input.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      event_type,
+      move |event: KeyboardEvent| {
+        // Keeps the browser from handling the keys, e.g. scrolling with the arrows.
+        event.prevent_default();
+        let timestamp = dom::event_timestamp(&event);
+        let modifiers = keyboard::modifiers(&event);
+        update_modifiers(modifiers, timestamp);
+        let key_event = keyboard::key_event(&event, state, &dead_key_pending);
+        let text = if text_input.get() {
+          key_event.received_text(modifiers)
+        } else {
+          None
+        };
+        send(
+          WindowEvent::KeyboardInput {
+            device_id: RootDeviceId(DeviceId::dummy()),
+            event: key_event,
+            is_synthetic: false,
+          },
+          timestamp,
+        );
+        if let Some(text) = text {
+          send(WindowEvent::ReceivedText(text), timestamp);
+        }
+        // The keys are kept from the browser, which would move the focus out of the canvas.
+        if state == ElementState::Pressed
+          && event.code() == "Tab"
+          && !(modifiers.control_key() || modifiers.alt_key() || modifiers.super_key())
+        {
+          let direction = if modifiers.shift_key() {
+            FocusDirection::Backward
+          } else {
+            FocusDirection::Forward
+          };
+          send(WindowEvent::FocusTraversal(direction), timestamp);
+        }
+      },
+    ));
+  }
+  for &(event_type, focused) in &[("focus", true), ("blur", false)] {
+    let send = send.clone();
+    listeners.push(EventListener::new(
+      canvas,
+      event_type,
+      move |event: FocusEvent| send(WindowEvent::Focused(focused), dom::event_timestamp(&event)),
+    ));
+  }
+  {
+    // The page is resized when it's zoomed or moved to a monitor with another scale factor.
+    let canvas = canvas.clone();
+    listeners.push(EventListener::new(
+      &dom::window(),
+      "resize",
+      move |_: web_sys::Event| {
+        let new_scale_factor = dom::scale_factor();
+        if scale_factor.replace(new_scale_factor) != new_scale_factor {
+          runner.send_scale_change(id, new_scale_factor, canvas.clone());
+        }
+      },
+    ));
+  }
+
+  listeners
+}
+
+// The buttons of the pen contact, its barrel button and its eraser.
+const CONTACT: u16 = 1;
+const BARREL: u16 = 2;
+const ERASER: u16 = 32;
+
+/// Turns the events of a pen touching the canvas into a `PenInput`.
+fn pen_input(event: &PointerEvent, phase: TouchPhase) -> Option<PenInput> {
+  let buttons = event.buttons();
+  if event.pointer_type() != "pen" || (phase == TouchPhase::Moved && buttons & CONTACT == 0) {
+    return None;
+  }
+  Some(PenInput {
+    device_id: RootDeviceId(DeviceId(event.pointer_id())),
+    phase,
+    location: PhysicalPosition::from_logical::<_, f64>(
+      (event.offset_x() as f64, event.offset_y() as f64),
+      dom::scale_factor(),
+    ),
+    pressure: Some(event.pressure() as f64),
+    tilt: Some((event.tilt_x() as f64, event.tilt_y() as f64)),
+    twist: Some(event.twist() as f64),
+    barrel_button: buttons & BARREL != 0,
+    inverted: buttons & ERASER != 0,
+    eraser: buttons & ERASER != 0,
+  })
+}
+
+/// Turns the motion of a pen above the canvas into a `PenHover`.
+fn pen_hover(event: &PointerEvent) -> Option<PenHover> {
+  let buttons = event.buttons();
+  if event.pointer_type() != "pen" || buttons & CONTACT != 0 {
+    return None;
+  }
+  Some(PenHover {
+    device_id: RootDeviceId(DeviceId(event.pointer_id())),
+    location: PhysicalPosition::from_logical::<_, f64>(
+      (event.offset_x() as f64, event.offset_y() as f64),
+      dom::scale_factor(),
+    ),
+    distance: None,
+    tilt: Some((event.tilt_x() as f64, event.tilt_y() as f64)),
+    barrel_button: buttons & BARREL != 0,
+    inverted: buttons & ERASER != 0,
+  })
+}
+
+fn mouse_modifiers(event: &web_sys::MouseEvent) -> ModifiersState {
+  let mut modifiers = ModifiersState::empty();
+  modifiers.set(ModifiersState::SHIFT, event.shift_key());
+  modifiers.set(ModifiersState::CONTROL, event.ctrl_key());
+  modifiers.set(ModifiersState::ALT, event.alt_key());
+  modifiers.set(ModifiersState::SUPER, event.meta_key());
+  modifiers
+}
+
+/// Turns a pointer event into a `Touch` for the touches, and into a mouse event otherwise.
+#[allow(deprecated)]
+fn pointer_event(
+  event: &PointerEvent,
+  phase: TouchPhase,
+  timestamp: Instant,
+  click_counter: &RefCell<ClickCounter>,
+) -> WindowEvent<'static> {
+  let device_id = RootDeviceId(DeviceId(event.pointer_id()));
+  let position = PhysicalPosition::from_logical::<_, f64>(
+    (event.offset_x() as f64, event.offset_y() as f64),
+    dom::scale_factor(),
+  );
+  if event.pointer_type() == "touch" {
+    return WindowEvent::Touch(Touch {
+      device_id,
+      phase,
+      location: position,
+      force: Some(Force::Normalized(event.pressure() as f64)),
+      id: event.pointer_id() as u64,
+    });
+  }
+
+  let modifiers = mouse_modifiers(event);
+  let state = match phase {
+    TouchPhase::Started => ElementState::Pressed,
+    TouchPhase::Ended => ElementState::Released,
+    _ => {
+      return WindowEvent::CursorMoved {
+        device_id,
+        position,
+        modifiers,
+      }
+    }
+  };
+  let button = match event.button() {
+    0 => MouseButton::Left,
+    1 => MouseButton::Middle,
+    2 => MouseButton::Right,
+    3 => MouseButton::Back,
+    4 => MouseButton::Forward,
+    button => MouseButton::Other(button as u16),
+  };
+  let click_count = match state {
+    ElementState::Pressed => click_counter.borrow_mut().press(
+      button,
+      timestamp,
+      (event.offset_x() as f64, event.offset_y() as f64),
+      DEFAULT_INTERVAL,
+      (DEFAULT_DISTANCE, DEFAULT_DISTANCE),
+    ),
+    ElementState::Released => click_counter.borrow().release(button),
+  };
+  WindowEvent::MouseInput {
+    device_id,
+    state,
+    button,
+    click_count,
+    modifiers,
+  }
+}
@@ -0,0 +1,136 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The taskbar has no badge of its own, so the count is drawn as an overlay icon:
+// a red disc with the count written in white, like the badges of the dock on macOS.
+use std::{ffi::OsStr, iter::once, mem, os::windows::ffi::OsStrExt, ptr, slice};
+
+use winapi::{
+  ctypes::c_void,
+  shared::windef::RECT,
+  um::{wingdi, winuser},
+};
+
+use crate::platform_impl::platform::icon::WinIcon;
+
+// The overlay is rendered at 16x16, so a multiple of that stays sharp when scaled.
+const SIZE: i32 = 32;
+const BADGE_COLOR: (u8, u8, u8) = (0xE8, 0x11, 0x23);
+
+pub fn badge_icon(count: u64) -> Option<WinIcon> {
+  let text = if count > 99 {
+    "99+".to_string()
+  } else {
+    count.to_string()
+  };
+  let coverage = unsafe { render_text(&text)? };
+
+  let center = SIZE as f64 / 2.0;
+  let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+  for y in 0..SIZE {
+    for x in 0..SIZE {
+      let distance = (x as f64 + 0.5 - center).hypot(y as f64 + 0.5 - center);
+      let disc = (center - distance + 0.5).max(0.0).min(1.0);
+      let text = coverage[(y * SIZE + x) as usize] as f64 / 255.0;
+
+      let blend = |color: u8| (color as f64 * (1.0 - text) + 255.0 * text).round() as u8;
+      rgba.extend_from_slice(&[
+        blend(BADGE_COLOR.0),
+        blend(BADGE_COLOR.1),
+        blend(BADGE_COLOR.2),
+        (disc * 255.0).round() as u8,
+      ]);
+    }
+  }
+
+  WinIcon::from_rgba(rgba, SIZE as u32, SIZE as u32).ok()
+}
+
+// Draws `text` in white on black and returns the intensity of every pixel, which is used as the
+// coverage of the text since GDI doesn't write an alpha channel.
+unsafe fn render_text(text: &str) -> Option<Vec<u8>> {
+  let hdc = wingdi::CreateCompatibleDC(ptr::null_mut());
+  if hdc.is_null() {
+    return None;
+  }
+
+  let mut info: wingdi::BITMAPINFO = mem::zeroed();
+  info.bmiHeader.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as _;
+  info.bmiHeader.biWidth = SIZE;
+  // A negative height makes the bitmap top-down, like the RGBA buffer of `WinIcon::from_rgba`.
+  info.bmiHeader.biHeight = -SIZE;
+  info.bmiHeader.biPlanes = 1;
+  info.bmiHeader.biBitCount = 32;
+  info.bmiHeader.biCompression = wingdi::BI_RGB;
+
+  let mut bits: *mut c_void = ptr::null_mut();
+  let bitmap = wingdi::CreateDIBSection(
+    hdc,
+    &info,
+    wingdi::DIB_RGB_COLORS,
+    &mut bits,
+    ptr::null_mut(),
+    0,
+  );
+  if bitmap.is_null() {
+    wingdi::DeleteDC(hdc);
+    return None;
+  }
+  let old_bitmap = wingdi::SelectObject(hdc, bitmap as _);
+
+  let face_name = OsStr::new("Segoe UI")
+    .encode_wide()
+    .chain(once(0))
+    .collect::<Vec<_>>();
+  let font_height = if text.len() > 2 { 14 } else { 20 };
+  let font = wingdi::CreateFontW(
+    -font_height,
+    0,
+    0,
+    0,
+    wingdi::FW_BOLD,
+    0,
+    0,
+    0,
+    wingdi::DEFAULT_CHARSET,
+    wingdi::OUT_DEFAULT_PRECIS,
+    wingdi::CLIP_DEFAULT_PRECIS,
+    wingdi::ANTIALIASED_QUALITY,
+    wingdi::DEFAULT_PITCH | wingdi::FF_SWISS,
+    face_name.as_ptr(),
+  );
+  let old_font = wingdi::SelectObject(hdc, font as _);
+
+  wingdi::SetTextColor(hdc, wingdi::RGB(255, 255, 255));
+  wingdi::SetBkMode(hdc, wingdi::TRANSPARENT as _);
+  let text = OsStr::new(text).encode_wide().collect::<Vec<_>>();
+  let mut rect = RECT {
+    left: 0,
+    top: 0,
+    right: SIZE,
+    bottom: SIZE,
+  };
+  winuser::DrawTextW(
+    hdc,
+    text.as_ptr(),
+    text.len() as _,
+    &mut rect,
+    winuser::DT_CENTER | winuser::DT_VCENTER | winuser::DT_SINGLELINE | winuser::DT_NOPREFIX,
+  );
+  wingdi::GdiFlush();
+
+  // The pixels are in the BGRA format.
+  let pixels = slice::from_raw_parts(bits as *const u8, (SIZE * SIZE * 4) as usize);
+  let coverage = pixels
+    .chunks_exact(4)
+    .map(|pixel| pixel[0].max(pixel[1]).max(pixel[2]))
+    .collect();
+
+  wingdi::SelectObject(hdc, old_font);
+  wingdi::DeleteObject(font as _);
+  wingdi::SelectObject(hdc, old_bitmap);
+  wingdi::DeleteObject(bitmap as _);
+  wingdi::DeleteDC(hdc);
+
+  Some(coverage)
+}
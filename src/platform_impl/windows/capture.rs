@@ -0,0 +1,87 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The client area is rendered into a DIB section with `PrintWindow`, which asks the window to
+// paint itself instead of reading the screen, so covered windows are captured as well.
+use std::{io, mem, ptr, slice};
+
+use winapi::{
+  ctypes::c_void,
+  shared::windef::{HWND, RECT},
+  um::{wingdi, winuser},
+};
+
+// Returns the width, the height and the RGBA pixels of the client area of `hwnd`.
+pub fn capture_client_area(hwnd: HWND) -> Result<(u32, u32, Vec<u8>), io::Error> {
+  unsafe {
+    let mut rect: RECT = mem::zeroed();
+    if winuser::GetClientRect(hwnd, &mut rect) == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+    if width <= 0 || height <= 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::Other,
+        "the window has no client area to capture",
+      ));
+    }
+
+    let hdc = wingdi::CreateCompatibleDC(ptr::null_mut());
+    if hdc.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+
+    let mut info: wingdi::BITMAPINFO = mem::zeroed();
+    info.bmiHeader.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as _;
+    info.bmiHeader.biWidth = width;
+    // A negative height makes the bitmap top-down, like the rows of the returned image.
+    info.bmiHeader.biHeight = -height;
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = wingdi::BI_RGB;
+
+    let mut bits: *mut c_void = ptr::null_mut();
+    let bitmap = wingdi::CreateDIBSection(
+      hdc,
+      &info,
+      wingdi::DIB_RGB_COLORS,
+      &mut bits,
+      ptr::null_mut(),
+      0,
+    );
+    if bitmap.is_null() {
+      let error = io::Error::last_os_error();
+      wingdi::DeleteDC(hdc);
+      return Err(error);
+    }
+    let old_bitmap = wingdi::SelectObject(hdc, bitmap as _);
+
+    // `PW_RENDERFULLCONTENT` is needed for the content composed with DirectComposition, like the
+    // one of WebView2, which would be black otherwise.
+    let result = if winuser::PrintWindow(
+      hwnd,
+      hdc,
+      winuser::PW_CLIENTONLY | winuser::PW_RENDERFULLCONTENT,
+    ) == 0
+    {
+      Err(io::Error::last_os_error())
+    } else {
+      wingdi::GdiFlush();
+
+      // The pixels are in the BGRA format, and GDI leaves the alpha channel undefined.
+      let pixels = slice::from_raw_parts(bits as *const u8, (width * height * 4) as usize);
+      let mut rgba = Vec::with_capacity(pixels.len());
+      for pixel in pixels.chunks_exact(4) {
+        rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+      }
+      Ok((width as u32, height as u32, rgba))
+    };
+
+    wingdi::SelectObject(hdc, old_bitmap);
+    wingdi::DeleteObject(bitmap as _);
+    wingdi::DeleteDC(hdc);
+
+    result
+  }
+}
@@ -11,13 +11,13 @@ use winapi::{
     windef::HWND,
     winerror::S_OK,
   },
-  um::{libloaderapi, uxtheme, winuser},
+  um::{dwmapi, libloaderapi, uxtheme, winuser},
 };
 
 use crate::{platform_impl::platform::util, window::Theme};
 
 lazy_static! {
-    static ref WIN10_BUILD_VERSION: Option<DWORD> = {
+    pub static ref WIN10_BUILD_VERSION: Option<DWORD> = {
         // FIXME: RtlGetVersion is a documented windows API,
         // should be part of winapi!
 
@@ -94,6 +94,7 @@ pub fn try_theme(hwnd: HWND, preferred_theme: Option<Theme>) -> Theme {
     let status = unsafe { uxtheme::SetWindowTheme(hwnd, theme_name as _, std::ptr::null()) };
 
     if status == S_OK && set_dark_mode_for_window(hwnd, is_dark_mode) {
+      set_dark_titlebar(hwnd, is_dark_mode);
       return theme;
     }
   }
@@ -145,6 +146,31 @@ fn set_dark_mode_for_window(hwnd: HWND, is_dark_mode: bool) -> bool {
   }
 }
 
+// The documented way to get a dark titlebar. Windows 10 builds before 20H1 use an undocumented
+// value for the attribute.
+fn set_dark_titlebar(hwnd: HWND, is_dark_mode: bool) {
+  const DWMWA_USE_IMMERSIVE_DARK_MODE: DWORD = 20;
+  const DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1: DWORD = 19;
+  // Windows 10 20H1 Insider Preview
+  const IMMERSIVE_DARK_MODE_BUILD_VERSION: DWORD = 18985;
+
+  let attribute = if WIN10_BUILD_VERSION.unwrap_or_default() >= IMMERSIVE_DARK_MODE_BUILD_VERSION {
+    DWMWA_USE_IMMERSIVE_DARK_MODE
+  } else {
+    DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1
+  };
+
+  let value = is_dark_mode as BOOL;
+  unsafe {
+    dwmapi::DwmSetWindowAttribute(
+      hwnd,
+      attribute,
+      &value as *const _ as _,
+      std::mem::size_of_val(&value) as _,
+    );
+  }
+}
+
 fn should_use_dark_mode() -> bool {
   should_apps_use_dark_mode() && !is_high_contrast()
 }
@@ -7,6 +7,7 @@ use std::{
   path::PathBuf,
   ptr,
   sync::atomic::{AtomicUsize, Ordering},
+  time::Instant,
 };
 
 use winapi::{
@@ -101,6 +102,7 @@ impl FileDropHandler {
       drop_handler.send_event(Event::WindowEvent {
         window_id: SuperWindowId(WindowId(drop_handler.window)),
         event: HoveredFile(filename),
+        timestamp: Instant::now(),
       });
     });
     drop_handler.hovered_is_valid = hdrop.is_some();
@@ -133,6 +135,7 @@ impl FileDropHandler {
       drop_handler.send_event(Event::WindowEvent {
         window_id: SuperWindowId(WindowId(drop_handler.window)),
         event: HoveredFileCancelled,
+        timestamp: Instant::now(),
       });
     }
 
@@ -152,6 +155,7 @@ impl FileDropHandler {
       drop_handler.send_event(Event::WindowEvent {
         window_id: SuperWindowId(WindowId(drop_handler.window)),
         event: DroppedFile(filename),
+        timestamp: Instant::now(),
       });
     });
     if let Some(hdrop) = hdrop {
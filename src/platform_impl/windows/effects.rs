@@ -0,0 +1,218 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// Background effects of DWM. Windows 11 exposes them as documented window attributes,
+// while Windows 10 only has the undocumented accent policy of `SetWindowCompositionAttribute`.
+use winapi::{
+  shared::{
+    basetsd::SIZE_T,
+    minwindef::{BOOL, DWORD},
+    ntdef::PVOID,
+    windef::HWND,
+    winerror::S_OK,
+  },
+  um::{dwmapi, uxtheme::MARGINS},
+};
+
+use crate::{
+  platform::windows::CornerPreference,
+  platform_impl::platform::dark_mode::WIN10_BUILD_VERSION,
+  window::{Color, Effect},
+};
+
+// Windows 11
+const WIN11_BUILD_VERSION: DWORD = 22000;
+// Windows 11 22H2, the first version with `DWMWA_SYSTEMBACKDROP_TYPE`
+const SYSTEM_BACKDROP_BUILD_VERSION: DWORD = 22523;
+// Windows 10 October 2018 Update
+const ACRYLIC_BUILD_VERSION: DWORD = 17763;
+
+const DWMWA_TRANSITIONS_FORCEDISABLED: DWORD = 3;
+const DWMWA_CLOAK: DWORD = 13;
+const DWMWA_CLOAKED: DWORD = 14;
+const DWMWA_WINDOW_CORNER_PREFERENCE: DWORD = 33;
+const DWMWA_CAPTION_COLOR: DWORD = 35;
+const DWMWA_TEXT_COLOR: DWORD = 36;
+const DWMWA_SYSTEMBACKDROP_TYPE: DWORD = 38;
+const DWMWA_MICA_EFFECT: DWORD = 1029;
+
+const DWMSBT_NONE: DWORD = 1;
+const DWMSBT_MAINWINDOW: DWORD = 2;
+const DWMSBT_TRANSIENTWINDOW: DWORD = 3;
+
+const DWMWCP_DEFAULT: DWORD = 0;
+const DWMWCP_DONOTROUND: DWORD = 1;
+const DWMWCP_ROUND: DWORD = 2;
+const DWMWCP_ROUNDSMALL: DWORD = 3;
+
+// Restores the color of the system theme.
+const DWMWA_COLOR_DEFAULT: DWORD = 0xFFFF_FFFF;
+
+const WCA_ACCENT_POLICY: DWORD = 19;
+
+const ACCENT_DISABLED: DWORD = 0;
+const ACCENT_ENABLE_BLURBEHIND: DWORD = 3;
+const ACCENT_ENABLE_ACRYLICBLURBEHIND: DWORD = 4;
+
+// The accent color is in the `AABBGGRR` format. Acrylic needs a non-zero alpha to be drawn.
+const ACRYLIC_TINT: DWORD = 0x0100_0000;
+
+#[allow(non_snake_case)]
+#[repr(C)]
+struct ACCENT_POLICY {
+  AccentState: DWORD,
+  AccentFlags: DWORD,
+  GradientColor: DWORD,
+  AnimationId: DWORD,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+struct WINDOWCOMPOSITIONATTRIBDATA {
+  Attrib: DWORD,
+  pvData: PVOID,
+  cbData: SIZE_T,
+}
+
+pub fn set_background_effect(hwnd: HWND, effect: Option<Effect>) {
+  let build = WIN10_BUILD_VERSION.unwrap_or_default();
+
+  let backdrop = match effect {
+    Some(Effect::Mica) if build >= SYSTEM_BACKDROP_BUILD_VERSION => DWMSBT_MAINWINDOW,
+    Some(Effect::Acrylic) if build >= SYSTEM_BACKDROP_BUILD_VERSION => DWMSBT_TRANSIENTWINDOW,
+    _ => DWMSBT_NONE,
+  };
+  let mica = effect == Some(Effect::Mica)
+    && build >= WIN11_BUILD_VERSION
+    && build < SYSTEM_BACKDROP_BUILD_VERSION;
+  let dwm_effect = backdrop != DWMSBT_NONE || mica;
+
+  let accent = match effect {
+    _ if dwm_effect => ACCENT_DISABLED,
+    Some(Effect::Acrylic) | Some(Effect::Mica) if build >= ACRYLIC_BUILD_VERSION => {
+      ACCENT_ENABLE_ACRYLICBLURBEHIND
+    }
+    Some(_) => ACCENT_ENABLE_BLURBEHIND,
+    None => ACCENT_DISABLED,
+  };
+
+  unsafe {
+    if build >= SYSTEM_BACKDROP_BUILD_VERSION {
+      set_dwm_attribute(hwnd, DWMWA_SYSTEMBACKDROP_TYPE, backdrop);
+    } else if build >= WIN11_BUILD_VERSION {
+      set_dwm_attribute(hwnd, DWMWA_MICA_EFFECT, mica as DWORD);
+    }
+
+    // DWM draws its backdrops in the frame, so it has to cover the whole window.
+    let margin = if dwm_effect { -1 } else { 0 };
+    let margins = MARGINS {
+      cxLeftWidth: margin,
+      cxRightWidth: margin,
+      cyTopHeight: margin,
+      cyBottomHeight: margin,
+    };
+    dwmapi::DwmExtendFrameIntoClientArea(hwnd, &margins);
+
+    set_accent_policy(hwnd, accent);
+  }
+}
+
+pub fn set_corner_preference(hwnd: HWND, preference: CornerPreference) {
+  if WIN10_BUILD_VERSION.unwrap_or_default() < WIN11_BUILD_VERSION {
+    return;
+  }
+
+  let preference = match preference {
+    CornerPreference::Default => DWMWCP_DEFAULT,
+    CornerPreference::DoNotRound => DWMWCP_DONOTROUND,
+    CornerPreference::Round => DWMWCP_ROUND,
+    CornerPreference::RoundSmall => DWMWCP_ROUNDSMALL,
+  };
+
+  unsafe { set_dwm_attribute(hwnd, DWMWA_WINDOW_CORNER_PREFERENCE, preference) };
+}
+
+pub fn set_caption_color(hwnd: HWND, color: Option<Color>) {
+  set_color_attribute(hwnd, DWMWA_CAPTION_COLOR, color);
+}
+
+pub fn set_text_color(hwnd: HWND, color: Option<Color>) {
+  set_color_attribute(hwnd, DWMWA_TEXT_COLOR, color);
+}
+
+fn set_color_attribute(hwnd: HWND, attribute: DWORD, color: Option<Color>) {
+  if WIN10_BUILD_VERSION.unwrap_or_default() < WIN11_BUILD_VERSION {
+    return;
+  }
+
+  // A `COLORREF`, in the `0x00BBGGRR` format.
+  let value = match color {
+    Some(color) => color.r as DWORD | (color.g as DWORD) << 8 | (color.b as DWORD) << 16,
+    None => DWMWA_COLOR_DEFAULT,
+  };
+
+  unsafe { set_dwm_attribute(hwnd, attribute, value) };
+}
+
+pub fn set_cloaked(hwnd: HWND, cloaked: bool) {
+  unsafe { set_dwm_attribute(hwnd, DWMWA_CLOAK, cloaked as DWORD) };
+}
+
+pub fn set_transitions_disabled(hwnd: HWND, disabled: bool) {
+  unsafe { set_dwm_attribute(hwnd, DWMWA_TRANSITIONS_FORCEDISABLED, disabled as DWORD) };
+}
+
+// Whether the window is cloaked by the application, the shell (e.g. on another virtual desktop)
+// or by Windows itself.
+pub fn is_cloaked(hwnd: HWND) -> bool {
+  let mut cloaked: DWORD = 0;
+  let result = unsafe {
+    dwmapi::DwmGetWindowAttribute(
+      hwnd,
+      DWMWA_CLOAKED,
+      &mut cloaked as *mut _ as _,
+      std::mem::size_of_val(&cloaked) as _,
+    )
+  };
+  result == S_OK && cloaked != 0
+}
+
+unsafe fn set_dwm_attribute(hwnd: HWND, attribute: DWORD, value: DWORD) {
+  dwmapi::DwmSetWindowAttribute(
+    hwnd,
+    attribute,
+    &value as *const _ as _,
+    std::mem::size_of_val(&value) as _,
+  );
+}
+
+unsafe fn set_accent_policy(hwnd: HWND, accent_state: DWORD) {
+  type SetWindowCompositionAttribute =
+    unsafe extern "system" fn(HWND, *mut WINDOWCOMPOSITIONATTRIBDATA) -> BOOL;
+
+  lazy_static! {
+    static ref SET_WINDOW_COMPOSITION_ATTRIBUTE: Option<SetWindowCompositionAttribute> =
+      get_function!("user32.dll", SetWindowCompositionAttribute);
+  }
+
+  if let Some(set_window_composition_attribute) = *SET_WINDOW_COMPOSITION_ATTRIBUTE {
+    let mut policy = ACCENT_POLICY {
+      AccentState: accent_state,
+      AccentFlags: 2,
+      GradientColor: if accent_state == ACCENT_ENABLE_ACRYLICBLURBEHIND {
+        ACRYLIC_TINT
+      } else {
+        0
+      },
+      AnimationId: 0,
+    };
+
+    let mut data = WINDOWCOMPOSITIONATTRIBDATA {
+      Attrib: WCA_ACCENT_POLICY,
+      pvData: &mut policy as *mut _ as _,
+      cbData: std::mem::size_of_val(&policy) as _,
+    };
+
+    set_window_composition_attribute(hwnd, &mut data as *mut _);
+  }
+}
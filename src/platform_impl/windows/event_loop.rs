@@ -5,11 +5,10 @@
 
 mod runner;
 
-use crossbeam_channel::{self as channel, Receiver, Sender};
 use parking_lot::Mutex;
 use std::{
-  cell::Cell,
-  collections::VecDeque,
+  cell::{Cell, RefCell},
+  collections::{hash_map::Entry, HashMap, VecDeque},
   marker::PhantomData,
   mem, panic, ptr,
   rc::Rc,
@@ -21,13 +20,16 @@ use winapi::{
   ctypes::c_int,
   shared::{
     basetsd::{DWORD_PTR, UINT_PTR},
-    minwindef::{BOOL, DWORD, HIWORD, INT, LOWORD, LPARAM, LRESULT, UINT, WORD, WPARAM},
+    minwindef::{
+      BOOL, DWORD, FALSE, HIWORD, HKL, INT, LOWORD, LPARAM, LRESULT, TRUE, UINT, WORD, WPARAM,
+    },
     windef::{HWND, POINT, RECT},
     windowsx, winerror,
   },
   um::{
-    commctrl, libloaderapi, ole2, processthreadsapi, winbase,
-    winnt::{HANDLE, LONG, LPCSTR, SHORT},
+    commctrl, handleapi, libloaderapi, ole2, processthreadsapi, shellapi, synchapi, sysinfoapi,
+    timeapi, winbase,
+    winnt::{self, HANDLE, LARGE_INTEGER, LONG, LPCSTR, SHORT},
     winuser::{self, RAWINPUT},
   },
 };
@@ -35,24 +37,40 @@ use winapi::{
 use crate::{
   accelerator::AcceleratorId,
   dpi::{PhysicalPosition, PhysicalSize},
-  event::{DeviceEvent, Event, Force, RawKeyEvent, Touch, TouchPhase, WindowEvent},
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
-  keyboard::{KeyCode, ModifiersState},
+  error::OsError as RootOsError,
+  event::{
+    CloseResponse, DeviceEvent, DeviceInfo, Event, Force, FullscreenTransition, Ime, MouseButton,
+    PenHover, PenInput, RawKeyEvent, SuspendReason, Touch, TouchPhase, WindowEvent,
+  },
+  event_loop::{
+    ControlFlow, DeviceEventFilter, EventLoopClosed, EventLoopWindowTarget as RootELW, Priority,
+    SendEventError, TimerId,
+  },
+  keyboard::{Key, KeyCode, KeyRepeatInfo, LockKey, ModifiersState},
   monitor::MonitorHandle as RootMonitorHandle,
+  platform::pump_events::PumpStatus,
   platform_impl::platform::{
     accelerator,
     dark_mode::try_theme,
     dpi::{become_dpi_aware, dpi_to_scale_factor, enable_non_client_dpi_scaling},
     drop_handler::FileDropHandler,
-    keyboard::is_msg_keyboard_related,
-    keyboard_layout::LAYOUT_CACHE,
+    effects,
+    keyboard::{self, is_msg_keyboard_related},
+    keyboard_grab,
+    keyboard_layout::{self, LAYOUT_CACHE},
+    kiosk,
     minimal_ime::is_msg_ime_related,
     monitor::{self, MonitorHandle},
-    raw_input, util,
+    raw_input,
+    touch_keyboard::TouchKeyboardHandler,
+    util,
+    virtual_desktop::{self, VirtualDesktop},
+    window::{drag_region_hit_test, redraw_window, restrict_hit_test, REDRAW_TIMER_ID},
     window_state::{CursorFlags, WindowFlags, WindowState},
-    wrap_device_id, WindowId, DEVICE_ID,
+    wrap_device_id, CloseHandle, DeviceId, WindowId, DEVICE_ID,
   },
-  window::{Fullscreen, WindowId as RootWindowId},
+  user_event_queue::UserEventQueue,
+  window::{Edges, Fullscreen, WindowId as RootWindowId},
 };
 use runner::{EventLoopRunner, EventLoopRunnerShared};
 
@@ -93,6 +111,7 @@ pub(crate) struct SubclassInput<T: 'static> {
   pub window_state: Arc<Mutex<WindowState>>,
   pub event_loop_runner: EventLoopRunnerShared<T>,
   pub file_drop_handler: Option<FileDropHandler>,
+  pub touch_keyboard_handler: Option<TouchKeyboardHandler>,
   pub subclass_removed: Cell<bool>,
   pub recurse_depth: Cell<u32>,
 }
@@ -105,7 +124,9 @@ impl<T> SubclassInput<T> {
 
 struct ThreadMsgTargetSubclassInput<T: 'static> {
   event_loop_runner: EventLoopRunnerShared<T>,
-  user_event_receiver: Receiver<T>,
+  user_events: Arc<UserEventQueue<T>>,
+  /// The HID devices which sent input, by handle.
+  hid_devices: RefCell<HashMap<usize, raw_input::HidDevice>>,
 }
 
 impl<T> ThreadMsgTargetSubclassInput<T> {
@@ -122,14 +143,21 @@ pub(crate) enum ProcResult {
 }
 
 pub struct EventLoop<T: 'static> {
-  thread_msg_sender: Sender<T>,
+  user_events: Arc<UserEventQueue<T>>,
+  msg_hook: Option<MsgHook>,
   window_target: RootELW<T>,
 }
 
+/// Sees the messages of the event loop thread before they are dispatched, and returns whether it
+/// consumed them.
+pub type MsgHook = Box<dyn FnMut(*const winuser::MSG) -> bool>;
+
 pub struct EventLoopWindowTarget<T: 'static> {
   thread_id: DWORD,
   thread_msg_target: HWND,
   pub(crate) runner_shared: EventLoopRunnerShared<T>,
+  /// The period given to `timeBeginPeriod` by `set_timer_resolution`, in milliseconds.
+  timer_resolution: Cell<Option<UINT>>,
 }
 
 macro_rules! main_thread_check {
@@ -148,6 +176,10 @@ macro_rules! main_thread_check {
 }
 
 impl<T: 'static> EventLoop<T> {
+  pub fn try_new() -> Result<Self, RootOsError> {
+    Ok(Self::new())
+  }
+
   pub fn new() -> EventLoop<T> {
     main_thread_check!("new_any_thread");
 
@@ -176,16 +208,18 @@ impl<T: 'static> EventLoop<T> {
 
     let runner_shared = Rc::new(EventLoopRunner::new(thread_msg_target, wait_thread_id));
 
-    let thread_msg_sender = subclass_event_target_window(thread_msg_target, runner_shared.clone());
-    raw_input::register_all_mice_and_keyboards_for_raw_input(thread_msg_target);
+    let user_events = subclass_event_target_window(thread_msg_target, runner_shared.clone());
+    raw_input::register_all_devices_for_raw_input(thread_msg_target, DeviceEventFilter::default());
 
     EventLoop {
-      thread_msg_sender,
+      user_events,
+      msg_hook: None,
       window_target: RootELW {
         p: EventLoopWindowTarget {
           thread_id,
           thread_msg_target,
           runner_shared,
+          timer_resolution: Cell::new(None),
         },
         _marker: PhantomData,
       },
@@ -196,15 +230,20 @@ impl<T: 'static> EventLoop<T> {
     &self.window_target
   }
 
+  pub fn set_msg_hook(&mut self, msg_hook: MsgHook) {
+    self.msg_hook = Some(msg_hook);
+  }
+
   pub fn run<F>(mut self, event_handler: F) -> !
   where
     F: 'static + FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
   {
-    self.run_return(event_handler);
-    ::std::process::exit(0);
+    let exit_code = self.run_return(event_handler);
+    monitor::restore_display_mode();
+    ::std::process::exit(exit_code);
   }
 
-  pub fn run_return<F>(&mut self, mut event_handler: F)
+  pub fn run_return<F>(&mut self, mut event_handler: F) -> i32
   where
     F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
   {
@@ -221,56 +260,147 @@ impl<T: 'static> EventLoop<T> {
     }
 
     let runner = &self.window_target.p.runner_shared;
+    let msg_hook = &mut self.msg_hook;
 
-    unsafe {
+    let exit_code = unsafe {
       let mut msg = mem::zeroed();
 
       runner.poll();
       'main: loop {
         if 0 == winuser::GetMessageW(&mut msg, ptr::null_mut(), 0, 0) {
-          break 'main;
+          break 'main msg.wParam as i32;
         }
 
-        // global accelerator
-        if msg.message == winuser::WM_HOTKEY {
-          let event_loop_runner = self.window_target.p.runner_shared.clone();
-          event_loop_runner
-            .send_event(Event::GlobalShortcutEvent(AcceleratorId(msg.wParam as u16)));
+        dispatch_message(runner, msg_hook, &mut msg);
+
+        if let ControlFlow::ExitWithCode(code) = runner.control_flow() {
+          if !runner.handling_events() {
+            break 'main code;
+          }
         }
+      }
+    };
 
-        // window accelerator
-        let accels = accelerator::find_accels(winuser::GetAncestor(msg.hwnd, winuser::GA_ROOT));
-        let translated = accels.map_or(false, |it| {
-          winuser::TranslateAcceleratorW(msg.hwnd, it.handle(), &mut msg) != 0
+    unsafe {
+      runner.loop_destroyed();
+    }
+    runner.reset_runner();
+    exit_code
+  }
+
+  pub fn pump_events<F>(&mut self, timeout: Option<Duration>, mut event_handler: F) -> PumpStatus
+  where
+    F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
+  {
+    let event_loop_windows_ref = &self.window_target;
+
+    unsafe {
+      self
+        .window_target
+        .p
+        .runner_shared
+        .set_event_handler(move |event, control_flow| {
+          event_handler(event, event_loop_windows_ref, control_flow)
         });
-        if !translated {
-          winuser::TranslateMessage(&mut msg);
-          winuser::DispatchMessageW(&mut msg);
-        }
+    }
+
+    let runner = &self.window_target.p.runner_shared;
+    let msg_hook = &mut self.msg_hook;
+
+    let exit_code = unsafe {
+      let mut msg = mem::zeroed();
 
-        if let Err(payload) = runner.take_panic_error() {
-          runner.reset_runner();
-          panic::resume_unwind(payload);
+      if !runner.initialized() {
+        runner.poll();
+      }
+
+      if winuser::PeekMessageW(&mut msg, ptr::null_mut(), 0, 0, winuser::PM_NOREMOVE) == 0 {
+        winuser::MsgWaitForMultipleObjectsEx(
+          0,
+          ptr::null(),
+          timeout.map_or(winbase::INFINITE, dur2timeout),
+          winuser::QS_ALLINPUT,
+          winuser::MWMO_INPUTAVAILABLE,
+        );
+      }
+
+      let mut exit_code = None;
+      while winuser::PeekMessageW(&mut msg, ptr::null_mut(), 0, 0, winuser::PM_REMOVE) != 0 {
+        if msg.message == winuser::WM_QUIT {
+          exit_code = Some(msg.wParam as i32);
+          break;
         }
 
-        if runner.control_flow() == ControlFlow::Exit && !runner.handling_events() {
-          break 'main;
+        dispatch_message(runner, msg_hook, &mut msg);
+
+        if let ControlFlow::ExitWithCode(code) = runner.control_flow() {
+          if !runner.handling_events() {
+            exit_code = Some(code);
+            break;
+          }
         }
       }
-    }
+      exit_code
+    };
 
-    unsafe {
-      runner.loop_destroyed();
+    if let Some(exit_code) = exit_code {
+      unsafe {
+        runner.loop_destroyed();
+      }
+      runner.reset_runner();
+      PumpStatus::Exit(exit_code)
+    } else {
+      // The handler borrows `event_handler`, which doesn't outlive this call.
+      runner.clear_event_handler();
+      PumpStatus::Continue
     }
-    runner.reset_runner();
   }
 
   pub fn create_proxy(&self) -> EventLoopProxy<T> {
     EventLoopProxy {
       target_window: self.window_target.p.thread_msg_target,
-      event_send: self.thread_msg_sender.clone(),
+      user_events: self.user_events.clone(),
+    }
+  }
+
+  pub fn set_user_event_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.user_events.set_capacity(priority, capacity);
+  }
+}
+
+/// Dispatches a message of the event loop thread to its window, after handling the message hook
+/// and the global and window accelerators.
+unsafe fn dispatch_message<T>(
+  runner: &EventLoopRunnerShared<T>,
+  msg_hook: &mut Option<MsgHook>,
+  msg: &mut winuser::MSG,
+) {
+  // The hook may dispatch the messages it consumes itself, e.g. with `IsDialogMessageW`, so the
+  // panics of the event handler are still checked afterwards.
+  let consumed = msg_hook.as_mut().map_or(false, |msg_hook| msg_hook(msg));
+
+  if !consumed {
+    // global accelerator
+    if msg.message == winuser::WM_HOTKEY {
+      runner.send_event(Event::GlobalShortcutEvent(AcceleratorId(msg.wParam as u16)));
+    }
+
+    // window accelerator
+    let accels = accelerator::find_accels(winuser::GetAncestor(msg.hwnd, winuser::GA_ROOT));
+    let translated = accels.map_or(false, |it| {
+      winuser::TranslateAcceleratorW(msg.hwnd, it.handle(), msg) != 0
+    });
+    if !translated {
+      winuser::TranslateMessage(msg);
+      winuser::DispatchMessageW(msg);
     }
   }
+
+  if let Err(payload) = runner.take_panic_error() {
+    runner.reset_runner();
+    monitor::restore_display_mode();
+    panic::resume_unwind(payload);
+  }
 }
 
 impl<T> EventLoopWindowTarget<T> {
@@ -291,6 +421,77 @@ impl<T> EventLoopWindowTarget<T> {
     let monitor = monitor::primary_monitor();
     Some(RootMonitorHandle { inner: monitor })
   }
+
+  pub fn virtual_desktops(&self) -> Vec<VirtualDesktop> {
+    virtual_desktop::virtual_desktops()
+  }
+
+  pub fn current_virtual_desktop(&self) -> Option<VirtualDesktop> {
+    virtual_desktop::current_virtual_desktop()
+  }
+
+  pub fn key_from_scancode(&self, scancode: u32) -> Option<Key<'static>> {
+    keyboard_layout::key_from_scancode(scancode)
+  }
+
+  pub fn scancode_from_key(&self, key: &Key<'_>) -> Option<u32> {
+    keyboard_layout::scancode_from_key(key)
+  }
+
+  pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+    keyboard::key_repeat_info()
+  }
+
+  pub fn modifiers_state(&self) -> ModifiersState {
+    LAYOUT_CACHE.lock().unwrap().get_agnostic_mods()
+  }
+
+  pub fn lock_key_state(&self, key: LockKey) -> Option<bool> {
+    Some(keyboard::lock_key_state(key))
+  }
+
+  pub fn set_timer(&self, interval: Duration, id: TimerId) {
+    unsafe {
+      // Setting a timer with the id of an existing one replaces it.
+      winuser::SetTimer(
+        self.thread_msg_target,
+        id.0 as usize,
+        dur2timeout(interval),
+        None,
+      );
+    }
+  }
+
+  pub fn cancel_timer(&self, id: TimerId) {
+    unsafe {
+      winuser::KillTimer(self.thread_msg_target, id.0 as usize);
+    }
+  }
+
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    raw_input::register_all_devices_for_raw_input(self.thread_msg_target, filter);
+  }
+
+  pub fn device_info(&self, device_id: DeviceId) -> Option<DeviceInfo> {
+    device_id.info()
+  }
+
+  pub fn set_timer_resolution(&self, resolution: Option<Duration>) {
+    unsafe {
+      if let Some(period) = self.timer_resolution.take() {
+        timeapi::timeEndPeriod(period);
+      }
+      if let Some(resolution) = resolution {
+        let period = dur2timeout(resolution).max(1);
+        // TIMERR_NOERROR
+        if timeapi::timeBeginPeriod(period) == 0 {
+          self.timer_resolution.set(Some(period));
+        } else {
+          warn!("Failed to set the timer resolution to {}ms", period);
+        }
+      }
+    }
+  }
 }
 
 fn main_thread_id() -> DWORD {
@@ -338,6 +539,8 @@ fn wait_thread(parent_thread_id: DWORD, msg_window_id: HWND) {
       cur_thread_id as LPARAM,
     );
 
+    let timer = create_wait_timer();
+
     let mut wait_until_opt = None;
     'main: loop {
       // Zeroing out the message ensures that the `WaitUntilInstantBox` doesn't get
@@ -347,6 +550,9 @@ fn wait_thread(parent_thread_id: DWORD, msg_window_id: HWND) {
 
       if wait_until_opt.is_some() {
         if 0 != winuser::PeekMessageW(&mut msg, ptr::null_mut(), 0, 0, winuser::PM_REMOVE) {
+          if msg.message == winuser::WM_QUIT {
+            break 'main;
+          }
           winuser::TranslateMessage(&mut msg);
           winuser::DispatchMessageW(&mut msg);
         }
@@ -368,17 +574,33 @@ fn wait_thread(parent_thread_id: DWORD, msg_window_id: HWND) {
       if let Some(wait_until) = wait_until_opt {
         let now = Instant::now();
         if now < wait_until {
-          // MsgWaitForMultipleObjects tends to overshoot just a little bit. We subtract
-          // 1 millisecond from the requested time and spinlock for the remainder to
-          // compensate for that.
-          let resume_reason = winuser::MsgWaitForMultipleObjectsEx(
-            0,
-            ptr::null(),
-            dur2timeout(wait_until - now).saturating_sub(1),
-            winuser::QS_ALLEVENTS,
-            winuser::MWMO_INPUTAVAILABLE,
-          );
-          if resume_reason == winerror::WAIT_TIMEOUT {
+          let resume_time_reached = if timer.is_null() {
+            // MsgWaitForMultipleObjects tends to overshoot just a little bit. We subtract
+            // 1 millisecond from the requested time and spinlock for the remainder to
+            // compensate for that.
+            let resume_reason = winuser::MsgWaitForMultipleObjectsEx(
+              0,
+              ptr::null(),
+              dur2timeout(wait_until - now).saturating_sub(1),
+              winuser::QS_ALLEVENTS,
+              winuser::MWMO_INPUTAVAILABLE,
+            );
+            resume_reason == winerror::WAIT_TIMEOUT
+          } else {
+            // Negative due times are relative to now.
+            let mut due_time: LARGE_INTEGER = mem::zeroed();
+            *due_time.QuadPart_mut() = -dur2intervals(wait_until - now);
+            synchapi::SetWaitableTimer(timer, &due_time, 0, None, ptr::null_mut(), FALSE);
+            let resume_reason = winuser::MsgWaitForMultipleObjectsEx(
+              1,
+              &timer,
+              winbase::INFINITE,
+              winuser::QS_ALLEVENTS,
+              winuser::MWMO_INPUTAVAILABLE,
+            );
+            resume_reason == winbase::WAIT_OBJECT_0
+          };
+          if resume_time_reached {
             winuser::PostMessageW(msg_window_id, *PROCESS_NEW_EVENTS_MSG_ID, 0, 0);
             wait_until_opt = None;
           }
@@ -388,11 +610,116 @@ fn wait_thread(parent_thread_id: DWORD, msg_window_id: HWND) {
         }
       }
     }
+
+    if !timer.is_null() {
+      handleapi::CloseHandle(timer);
+    }
+  }
+}
+
+/// Creates the timer the wait thread waits on for `ControlFlow::WaitUntil`.
+///
+/// A high resolution timer fires within about half a millisecond of its due time, when the
+/// timeout of `MsgWaitForMultipleObjectsEx` is rounded to the system timer interval of up to
+/// 15.6ms. Falls back to a regular waitable timer before Windows 10 1803, and returns a null
+/// handle if no timer can be created.
+fn create_wait_timer() -> HANDLE {
+  const CREATE_WAITABLE_TIMER_HIGH_RESOLUTION: DWORD = 0x00000002;
+  unsafe {
+    let timer = synchapi::CreateWaitableTimerExW(
+      ptr::null_mut(),
+      ptr::null(),
+      CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+      winnt::TIMER_ALL_ACCESS,
+    );
+    if !timer.is_null() {
+      return timer;
+    }
+    synchapi::CreateWaitableTimerExW(ptr::null_mut(), ptr::null(), 0, winnt::TIMER_ALL_ACCESS)
+  }
+}
+
+/// Converts a duration to the 100-nanosecond intervals of waitable timers, rounding up.
+fn dur2intervals(dur: Duration) -> i64 {
+  (dur.as_secs() as i64)
+    .saturating_mul(10_000_000)
+    .saturating_add(((dur.subsec_nanos() + 99) / 100) as i64)
+}
+
+/// Returns when the last message retrieved by the thread was posted, from `GetMessageTime`.
+///
+/// It's only meaningful for the posted messages, e.g. the input messages, since the sent messages
+/// aren't retrieved.
+fn message_timestamp() -> Instant {
+  tick_timestamp(unsafe { winuser::GetMessageTime() } as DWORD)
+}
+
+/// Converts a time in milliseconds since the system started, e.g. from `GetMessageTime`, to an
+/// `Instant`.
+fn tick_timestamp(time: DWORD) -> Instant {
+  let now = Instant::now();
+  // Both are in milliseconds since the system started, and wrap around after 49.7 days.
+  let elapsed = unsafe { sysinfoapi::GetTickCount() }.wrapping_sub(time);
+  now
+    .checked_sub(Duration::from_millis(elapsed as u64))
+    .unwrap_or(now)
+}
+
+/// Returns the positions the cursor went through after `since` and before the position of the
+/// current `WM_MOUSEMOVE` message, oldest first, with their times, from `GetMouseMovePointsEx`.
+unsafe fn mouse_move_history(
+  window: HWND,
+  position: PhysicalPosition<f64>,
+  time: DWORD,
+  since: DWORD,
+) -> Vec<(PhysicalPosition<f64>, DWORD)> {
+  let mut point = POINT {
+    x: position.x as i32,
+    y: position.y as i32,
+  };
+  winuser::ClientToScreen(window, &mut point);
+  let mut current = winuser::MOUSEMOVEPOINT {
+    x: point.x & 0xFFFF,
+    y: point.y & 0xFFFF,
+    time,
+    dwExtraInfo: 0,
+  };
+  let mut buffer: [winuser::MOUSEMOVEPOINT; 64] = mem::zeroed();
+  let count = winuser::GetMouseMovePointsEx(
+    mem::size_of::<winuser::MOUSEMOVEPOINT>() as UINT,
+    &mut current,
+    buffer.as_mut_ptr(),
+    buffer.len() as c_int,
+    winuser::GMMP_USE_DISPLAY_POINTS,
+  );
+  if count <= 0 {
+    return Vec::new();
   }
+
+  // The points are sorted newest first, starting with the current one.
+  let mut history: Vec<_> = buffer[1..count as usize]
+    .iter()
+    .take_while(|p| (p.time.wrapping_sub(since) as i32) > 0)
+    .map(|p| {
+      // The coordinates are 16 bits wide, the ones left of or above the primary monitor are
+      // negative.
+      let mut point = POINT {
+        x: if p.x > 0x7FFF { p.x - 0x10000 } else { p.x },
+        y: if p.y > 0x7FFF { p.y - 0x10000 } else { p.y },
+      };
+      winuser::ScreenToClient(window, &mut point);
+      (
+        PhysicalPosition::new(point.x as f64, point.y as f64),
+        p.time,
+      )
+    })
+    .collect();
+  history.reverse();
+  history
 }
 
 // Implementation taken from https://github.com/rust-lang/rust/blob/db5476571d9b27c862b95c1e64764b0ac8980e23/src/libstd/sys/windows/mod.rs
-fn dur2timeout(dur: Duration) -> DWORD {
+pub(crate) fn dur2timeout(dur: Duration) -> DWORD {
   // Note that a duration is a (u64, u32) (seconds, nanoseconds) pair, and the
   // timeouts in windows APIs are typically u32 milliseconds. To translate, we
   // have two pieces to take care of:
@@ -423,8 +750,18 @@ fn dur2timeout(dur: Duration) -> DWORD {
 
 impl<T> Drop for EventLoop<T> {
   fn drop(&mut self) {
+    self.user_events.close();
+    self.window_target.p.set_timer_resolution(None);
     unsafe {
       winuser::DestroyWindow(self.window_target.p.thread_msg_target);
+      // Stops the wait thread, so an event loop created and dropped on a short-lived thread
+      // doesn't leave it behind.
+      winuser::PostThreadMessageW(
+        self.window_target.p.runner_shared.wait_thread_id(),
+        winuser::WM_QUIT,
+        0,
+        0,
+      );
     }
   }
 }
@@ -487,7 +824,7 @@ type ThreadExecFn = Box<Box<dyn FnMut()>>;
 
 pub struct EventLoopProxy<T: 'static> {
   target_window: HWND,
-  event_send: Sender<T>,
+  user_events: Arc<UserEventQueue<T>>,
 }
 unsafe impl<T: Send + 'static> Send for EventLoopProxy<T> {}
 unsafe impl<T: Send + 'static> Sync for EventLoopProxy<T> {}
@@ -496,22 +833,50 @@ impl<T: 'static> Clone for EventLoopProxy<T> {
   fn clone(&self) -> Self {
     Self {
       target_window: self.target_window,
-      event_send: self.event_send.clone(),
+      user_events: self.user_events.clone(),
     }
   }
 }
 
 impl<T: 'static> EventLoopProxy<T> {
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+    self
+      .send_user_event(event, Priority::Normal, false)
+      .map_err(|e| EventLoopClosed(e.into_inner()))
+  }
+
+  pub fn send_event_with_priority(
+    &self,
+    event: T,
+    priority: Priority,
+  ) -> Result<(), SendEventError<T>> {
+    self.send_user_event(event, priority, true)
+  }
+
+  fn send_user_event(
+    &self,
+    event: T,
+    priority: Priority,
+    bounded: bool,
+  ) -> Result<(), SendEventError<T>> {
+    // Each message dispatches the queued event of the highest priority, so a message posted for
+    // an event that ends up rejected only dispatches an event sooner.
     unsafe {
       if winuser::PostMessageW(self.target_window, *USER_EVENT_MSG_ID, 0, 0) != 0 {
-        self.event_send.send(event).ok();
-        Ok(())
+        self.user_events.push(event, priority, bounded)
       } else {
-        Err(EventLoopClosed(event))
+        Err(SendEventError::Closed(event))
       }
     }
   }
+
+  /// Wakes the event loop up without a user event, which emits `NewEvents` and
+  /// `MainEventsCleared`.
+  pub fn wake_up(&self) {
+    unsafe {
+      winuser::PostMessageW(self.target_window, *WAKE_UP_MSG_ID, 0, 0);
+    }
+  }
 }
 
 type WaitUntilInstantBox = Box<Instant>;
@@ -524,6 +889,13 @@ lazy_static! {
             winuser::RegisterWindowMessageA("Tao::WakeupMsg\0".as_ptr() as LPCSTR)
         }
     };
+    // Message sent by `EventLoopProxy::wake_up` when the thread must process new events without
+    // a user event. WPARAM and LPARAM are unused.
+    static ref WAKE_UP_MSG_ID: u32 = {
+        unsafe {
+            winuser::RegisterWindowMessageA("Tao::WakeUpNoEventMsg\0".as_ptr() as LPCSTR)
+        }
+    };
     // Message sent when we want to execute a closure in the thread.
     // WPARAM contains a Box<Box<dyn FnMut()>> that must be retrieved with `Box::from_raw`,
     // and LPARAM is unused.
@@ -562,11 +934,21 @@ lazy_static! {
             winuser::RegisterWindowMessageA("Tao::DestroyMsg\0".as_ptr() as LPCSTR)
         }
     };
+    // Message sent by a `CloseResponse` when it's allowed. WPARAM is the serial of the window it
+    // was sent for, see `CloseHandle`. LPARAM is unused.
+    pub static ref CLOSE_RESPONSE_MSG_ID: u32 = unsafe {
+        winuser::RegisterWindowMessageA("Tao::CloseResponseMsg\0".as_ptr() as LPCSTR)
+    };
     // WPARAM is a bool specifying the `WindowFlags::MARKER_RETAIN_STATE_ON_SIZE` flag. See the
     // documentation in the `window_state` module for more information.
     pub static ref SET_RETAIN_STATE_ON_SIZE_MSG_ID: u32 = unsafe {
         winuser::RegisterWindowMessageA("Tao::SetRetainMaximized\0".as_ptr() as LPCSTR)
     };
+    // Message sent by a `Window` when it enters or exits fullscreen. WPARAM is the
+    // `FullscreenTransition`, see `fullscreen_transition_from_wparam`.
+    pub static ref FULLSCREEN_TRANSITION_MSG_ID: u32 = unsafe {
+        winuser::RegisterWindowMessageA("Tao::FullscreenTransition\0".as_ptr() as LPCSTR)
+    };
     static ref THREAD_EVENT_TARGET_WINDOW_CLASS: Vec<u16> = unsafe {
 
         let class_name= util::to_wstring("Tao Thread Event Target");
@@ -623,13 +1005,14 @@ fn create_event_target_window() -> HWND {
 fn subclass_event_target_window<T>(
   window: HWND,
   event_loop_runner: EventLoopRunnerShared<T>,
-) -> Sender<T> {
+) -> Arc<UserEventQueue<T>> {
   unsafe {
-    let (tx, rx) = channel::unbounded();
+    let user_events = Arc::new(UserEventQueue::new());
 
     let subclass_input = ThreadMsgTargetSubclassInput {
       event_loop_runner,
-      user_event_receiver: rx,
+      user_events: user_events.clone(),
+      hid_devices: RefCell::new(HashMap::new()),
     };
     let input_ptr = Box::into_raw(Box::new(subclass_input));
     let subclass_result = commctrl::SetWindowSubclass(
@@ -640,7 +1023,7 @@ fn subclass_event_target_window<T>(
     );
     assert_eq!(subclass_result, 1);
 
-    tx
+    user_events
   }
 }
 
@@ -666,13 +1049,44 @@ unsafe fn capture_mouse(window: HWND, window_state: &mut WindowState) {
 /// is outside the window.
 unsafe fn release_mouse(mut window_state: parking_lot::MutexGuard<'_, WindowState>) {
   window_state.mouse.capture_count = window_state.mouse.capture_count.saturating_sub(1);
-  if window_state.mouse.capture_count == 0 {
+  if window_state.mouse.capture_count == 0 && !window_state.mouse.captured {
     // ReleaseCapture() causes a WM_CAPTURECHANGED where we lock the window_state.
     drop(window_state);
     winuser::ReleaseCapture();
   }
 }
 
+/// Counts a button press, with the double-click time of the system and its double-click rectangle
+/// centered on the previous press.
+unsafe fn press_click_count(
+  window_state: &mut WindowState,
+  button: MouseButton,
+  lparam: LPARAM,
+) -> u32 {
+  let position = (
+    windowsx::GET_X_LPARAM(lparam) as f64,
+    windowsx::GET_Y_LPARAM(lparam) as f64,
+  );
+  let interval = Duration::from_millis(winuser::GetDoubleClickTime() as u64);
+  let distance = (
+    winuser::GetSystemMetrics(winuser::SM_CXDOUBLECLK) as f64 / 2.0,
+    winuser::GetSystemMetrics(winuser::SM_CYDOUBLECLK) as f64 / 2.0,
+  );
+  window_state
+    .mouse
+    .click_counter
+    .press(button, message_timestamp(), position, interval, distance)
+}
+
+/// The button of `WM_XBUTTONDOWN` and `WM_XBUTTONUP`.
+fn x_mouse_button(wparam: WPARAM) -> MouseButton {
+  match winuser::GET_XBUTTON_WPARAM(wparam) {
+    winuser::XBUTTON1 => MouseButton::Back,
+    winuser::XBUTTON2 => MouseButton::Forward,
+    xbutton => MouseButton::Other(xbutton),
+  }
+}
+
 const WINDOW_SUBCLASS_ID: UINT_PTR = 0;
 const THREAD_EVENT_TARGET_SUBCLASS_ID: UINT_PTR = 1;
 pub(crate) fn subclass_window<T>(window: HWND, subclass_input: SubclassInput<T>) {
@@ -700,6 +1114,121 @@ fn remove_window_subclass<T: 'static>(window: HWND) {
   assert_eq!(removal_result, 1);
 }
 
+// Whether `hit_test` is one of the caption buttons of an undecorated window,
+// which can only come from its drag regions.
+fn is_undecorated_caption_button(window_state: &Mutex<WindowState>, hit_test: WPARAM) -> bool {
+  matches!(
+    hit_test as LRESULT,
+    winuser::HTMINBUTTON | winuser::HTMAXBUTTON | winuser::HTCLOSE
+  ) && !window_state
+    .lock()
+    .window_flags()
+    .contains(WindowFlags::DECORATIONS)
+}
+
+// Windows doesn't notify windows covered by other windows, so a window is considered occluded
+// while it is hidden, minimized or cloaked, e.g. when it is on another virtual desktop.
+unsafe fn update_occlusion<T>(window: HWND, subclass_input: &SubclassInput<T>) {
+  use crate::event::WindowEvent::Occluded;
+
+  let occluded = winuser::IsWindowVisible(window) == 0
+    || winuser::IsIconic(window) != 0
+    || effects::is_cloaked(window);
+
+  let changed = {
+    let mut window_state = subclass_input.window_state.lock();
+    let changed = window_state.occluded != occluded;
+    window_state.occluded = occluded;
+    changed
+  };
+
+  if changed {
+    subclass_input.send_event(Event::WindowEvent {
+      window_id: RootWindowId(WindowId(window)),
+      event: Occluded(occluded),
+      timestamp: Instant::now(),
+    });
+  }
+}
+
+// An auto-hide taskbar only shows up when the cursor reaches the monitor edge it is docked to,
+// which a window covering the whole monitor prevents. Shrinks `rect`, the work area of a
+// maximized undecorated window, by a pixel on that edge.
+unsafe fn leave_room_for_autohide_taskbar(monitor_rect: &RECT, rect: &mut RECT) {
+  if rect.left != monitor_rect.left
+    || rect.top != monitor_rect.top
+    || rect.right != monitor_rect.right
+    || rect.bottom != monitor_rect.bottom
+  {
+    return;
+  }
+
+  let mut data: shellapi::APPBARDATA = mem::zeroed();
+  data.cbSize = mem::size_of::<shellapi::APPBARDATA>() as DWORD;
+  let state = shellapi::SHAppBarMessage(shellapi::ABM_GETSTATE, &mut data) as UINT;
+  if state & shellapi::ABS_AUTOHIDE == 0 {
+    return;
+  }
+
+  for &edge in &[
+    shellapi::ABE_BOTTOM,
+    shellapi::ABE_LEFT,
+    shellapi::ABE_TOP,
+    shellapi::ABE_RIGHT,
+  ] {
+    data.uEdge = edge;
+    data.rc = *monitor_rect;
+    if shellapi::SHAppBarMessage(shellapi::ABM_GETAUTOHIDEBAREX, &mut data) == 0 {
+      continue;
+    }
+
+    match edge {
+      shellapi::ABE_BOTTOM => rect.bottom -= 1,
+      shellapi::ABE_LEFT => rect.left += 1,
+      shellapi::ABE_TOP => rect.top += 1,
+      _ => rect.right -= 1,
+    }
+    break;
+  }
+}
+
+// Adjusts `rect`, the window rectangle being dragged by `edge`, so that its client area keeps
+// the given aspect ratio. The edges opposite to `edge` stay in place.
+fn constrain_aspect_ratio(window: HWND, edge: WPARAM, rect: &mut RECT, ratio: f64) {
+  let (frame_width, frame_height) =
+    match (util::get_window_rect(window), util::get_client_rect(window)) {
+      (Some(window_rect), Ok(client_rect)) => (
+        (window_rect.right - window_rect.left) - (client_rect.right - client_rect.left),
+        (window_rect.bottom - window_rect.top) - (client_rect.bottom - client_rect.top),
+      ),
+      _ => (0, 0),
+    };
+
+  let mut width = (rect.right - rect.left - frame_width) as f64;
+  let mut height = (rect.bottom - rect.top - frame_height) as f64;
+  match edge as u32 {
+    winuser::WMSZ_LEFT | winuser::WMSZ_RIGHT => height = width / ratio,
+    winuser::WMSZ_TOP | winuser::WMSZ_BOTTOM => width = height * ratio,
+    _ if width / height > ratio => width = height * ratio,
+    _ => height = width / ratio,
+  }
+  let width = width.round() as i32 + frame_width;
+  let height = height.round() as i32 + frame_height;
+
+  match edge as u32 {
+    winuser::WMSZ_TOP | winuser::WMSZ_TOPLEFT | winuser::WMSZ_TOPRIGHT => {
+      rect.top = rect.bottom - height
+    }
+    _ => rect.bottom = rect.top + height,
+  }
+  match edge as u32 {
+    winuser::WMSZ_LEFT | winuser::WMSZ_TOPLEFT | winuser::WMSZ_BOTTOMLEFT => {
+      rect.left = rect.right - width
+    }
+    _ => rect.right = rect.left + width,
+  }
+}
+
 fn normalize_pointer_pressure(pressure: u32) -> Option<Force> {
   match pressure {
     1..=1024 => Some(Force::Normalized(pressure as f64 / 1024.0)),
@@ -707,6 +1236,80 @@ fn normalize_pointer_pressure(pressure: u32) -> Option<Force> {
   }
 }
 
+/// Sends the touches of a frame, after their `Touch` events.
+unsafe fn send_touch_frame<T>(
+  subclass_input: &SubclassInput<T>,
+  window: HWND,
+  touches: Vec<Touch>,
+) {
+  if !touches.is_empty() {
+    subclass_input.send_event(Event::WindowEvent {
+      window_id: RootWindowId(WindowId(window)),
+      event: WindowEvent::TouchFrame(touches),
+      timestamp: message_timestamp(),
+    });
+  }
+}
+
+fn pen_input(
+  pen_info: &winuser::POINTER_PEN_INFO,
+  phase: TouchPhase,
+  location: PhysicalPosition<f64>,
+) -> PenInput {
+  let has = |mask| pen_info.penMask & mask != 0;
+  PenInput {
+    device_id: DEVICE_ID,
+    phase,
+    location,
+    pressure: if has(winuser::PEN_MASK_PRESSURE) {
+      Some(pen_info.pressure.min(1024) as f64 / 1024.0)
+    } else {
+      None
+    },
+    tilt: if has(winuser::PEN_MASK_TILT_X) || has(winuser::PEN_MASK_TILT_Y) {
+      Some((pen_info.tiltX as f64, pen_info.tiltY as f64))
+    } else {
+      None
+    },
+    twist: if has(winuser::PEN_MASK_ROTATION) {
+      Some(pen_info.rotation as f64)
+    } else {
+      None
+    },
+    barrel_button: pen_info.penFlags & winuser::PEN_FLAG_BARREL != 0,
+    inverted: pen_info.penFlags & winuser::PEN_FLAG_INVERTED != 0,
+    eraser: pen_info.penFlags & winuser::PEN_FLAG_ERASER != 0,
+  }
+}
+
+fn pen_hover(pen_info: &winuser::POINTER_PEN_INFO, location: PhysicalPosition<f64>) -> PenHover {
+  let has = |mask| pen_info.penMask & mask != 0;
+  PenHover {
+    device_id: DEVICE_ID,
+    location,
+    distance: None,
+    tilt: if has(winuser::PEN_MASK_TILT_X) || has(winuser::PEN_MASK_TILT_Y) {
+      Some((pen_info.tiltX as f64, pen_info.tiltY as f64))
+    } else {
+      None
+    },
+    barrel_button: pen_info.penFlags & winuser::PEN_FLAG_BARREL != 0,
+    inverted: pen_info.penFlags & winuser::PEN_FLAG_INVERTED != 0,
+  }
+}
+
+fn fullscreen_transition_from_wparam(wparam: WPARAM) -> Option<FullscreenTransition> {
+  [
+    FullscreenTransition::Entering,
+    FullscreenTransition::Entered,
+    FullscreenTransition::Exiting,
+    FullscreenTransition::Exited,
+  ]
+  .iter()
+  .copied()
+  .find(|transition| *transition as WPARAM == wparam)
+}
+
 /// Flush redraw events for Tao's windows.
 ///
 /// Tao's API guarantees that all redraw events will be clustered together and dispatched all at
@@ -767,7 +1370,7 @@ unsafe fn process_control_flow<T: 'static>(runner: &EventLoopRunner<T>) {
         Box::into_raw(WaitUntilInstantBox::new(until)) as LPARAM,
       );
     }
-    ControlFlow::Exit => (),
+    ControlFlow::ExitWithCode(_) => (),
   }
 }
 
@@ -792,6 +1395,7 @@ fn update_modifiers<T>(window: HWND, subclass_input: &SubclassInput<T>) -> Modif
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: ModifiersChanged(modifiers),
+        timestamp: Instant::now(),
       });
     }
   }
@@ -878,13 +1482,25 @@ unsafe fn public_window_callback_inner<T: 'static>(
       // when not appropriate.
       return;
     }
-    let events = {
+    let (events, text_input) = {
       let mut window_state = subclass_input.window_state.lock();
-      window_state
-        .key_event_builder
-        .process_message(window, msg, wparam, lparam, &mut result)
+      let events =
+        window_state
+          .key_event_builder
+          .process_message(window, msg, wparam, lparam, &mut result);
+      // The text is sent with the modifiers of the keypress.
+      let text_input = if window_state.text_input {
+        Some(window_state.modifiers_state)
+      } else {
+        None
+      };
+      (events, text_input)
     };
     for event in events {
+      let text = match text_input {
+        Some(modifiers) if !event.is_synthetic => event.event.received_text(modifiers),
+        _ => None,
+      };
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: KeyboardInput {
@@ -892,7 +1508,15 @@ unsafe fn public_window_callback_inner<T: 'static>(
           event: event.event,
           is_synthetic: event.is_synthetic,
         },
+        timestamp: message_timestamp(),
       });
+      if let Some(text) = text {
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window)),
+          event: WindowEvent::ReceivedText(text),
+          timestamp: message_timestamp(),
+        });
+      }
     }
   };
   subclass_input
@@ -901,22 +1525,35 @@ unsafe fn public_window_callback_inner<T: 'static>(
     .unwrap_or_else(|| result = ProcResult::Value(-1));
 
   let ime_callback = || {
-    use crate::event::WindowEvent::ReceivedImeText;
     let is_ime_related = is_msg_ime_related(msg);
     if !is_ime_related {
       return;
     }
-    let text = {
+    let (events, text_input) = {
       let mut window_state = subclass_input.window_state.lock();
-      window_state
-        .ime_handler
-        .process_message(window, msg, wparam, lparam, &mut result)
+      let events =
+        window_state
+          .ime_handler
+          .process_message(window, msg, wparam, lparam, &mut result);
+      (events, window_state.text_input)
     };
-    if let Some(str) = text {
+    for event in events {
+      let text = match &event {
+        Ime::Commit(text) if text_input => Some(text.clone()),
+        _ => None,
+      };
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
-        event: ReceivedImeText(str),
+        event: WindowEvent::Ime(event),
+        timestamp: message_timestamp(),
       });
+      if let Some(text) = text {
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window)),
+          event: WindowEvent::ReceivedText(text),
+          timestamp: message_timestamp(),
+        });
+      }
     }
   };
   subclass_input
@@ -951,13 +1588,37 @@ unsafe fn public_window_callback_inner<T: 'static>(
       if wparam == winuser::HTCAPTION as _ {
         winuser::PostMessageW(window, winuser::WM_MOUSEMOVE, 0, lparam);
       }
+
+      // The default processing would draw the classic caption buttons over the drag regions,
+      // so the buttons of undecorated windows act on release instead.
+      if is_undecorated_caption_button(&subclass_input.window_state, wparam) {
+        result = ProcResult::Value(0);
+      }
+    }
+
+    winuser::WM_NCLBUTTONUP => {
+      if is_undecorated_caption_button(&subclass_input.window_state, wparam) {
+        let command = match wparam as LRESULT {
+          winuser::HTMINBUTTON => winuser::SC_MINIMIZE,
+          winuser::HTMAXBUTTON if winuser::IsZoomed(window) != 0 => winuser::SC_RESTORE,
+          winuser::HTMAXBUTTON => winuser::SC_MAXIMIZE,
+          _ => winuser::SC_CLOSE,
+        };
+        winuser::PostMessageW(window, winuser::WM_SYSCOMMAND, command as WPARAM, 0);
+        result = ProcResult::Value(0);
+      }
     }
 
     winuser::WM_CLOSE => {
       use crate::event::WindowEvent::CloseRequested;
+      let window_id = RootWindowId(WindowId(window));
+      let serial = subclass_input.window_state.lock().serial;
       subclass_input.send_event(Event::WindowEvent {
-        window_id: RootWindowId(WindowId(window)),
-        event: CloseRequested,
+        window_id,
+        event: CloseRequested {
+          response: CloseResponse::new(window_id, CloseHandle::new(window, serial)),
+        },
+        timestamp: Instant::now(),
       });
       result = ProcResult::Value(0);
     }
@@ -965,9 +1626,16 @@ unsafe fn public_window_callback_inner<T: 'static>(
     winuser::WM_DESTROY => {
       use crate::event::WindowEvent::Destroyed;
       ole2::RevokeDragDrop(window);
+      if subclass_input.window_state.lock().kiosk {
+        kiosk::unblock_shell_shortcuts();
+      }
+      if subclass_input.window_state.lock().keyboard_grab {
+        keyboard_grab::ungrab_keyboard(window);
+      }
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: Destroyed,
+        timestamp: Instant::now(),
       });
       subclass_input.event_loop_runner.remove_window(window);
       result = ProcResult::Value(0);
@@ -990,6 +1658,9 @@ unsafe fn public_window_callback_inner<T: 'static>(
           winuser::RDW_INTERNALPAINT,
         );
       } else {
+        // A window that is painted is at least partially visible.
+        update_occlusion(window, subclass_input);
+
         let managing_redraw = flush_paint_messages(Some(window), &subclass_input.event_loop_runner);
         subclass_input.send_event(Event::RedrawRequested(RootWindowId(WindowId(window))));
         if managing_redraw {
@@ -999,8 +1670,25 @@ unsafe fn public_window_callback_inner<T: 'static>(
       }
     }
 
+    winuser::WM_TIMER if wparam == REDRAW_TIMER_ID => {
+      winuser::KillTimer(window, REDRAW_TIMER_ID);
+      let pacing = subclass_input.window_state.lock().redraw_pacing;
+      redraw_window(window, pacing);
+      result = ProcResult::Value(0);
+    }
+
     winuser::WM_WINDOWPOSCHANGING => {
       let mut window_state = subclass_input.window_state.lock();
+
+      // Keep the window at the bottom of the z-order whenever the system tries to raise it.
+      if window_state
+        .window_flags()
+        .contains(WindowFlags::ALWAYS_ON_BOTTOM)
+      {
+        let window_pos = &mut *(lparam as *mut winuser::WINDOWPOS);
+        window_pos.hwndInsertAfter = winuser::HWND_BOTTOM;
+      }
+
       if let Some(ref mut fullscreen) = window_state.fullscreen {
         let window_pos = &mut *(lparam as *mut winuser::WINDOWPOS);
         let new_rect = RECT {
@@ -1056,13 +1744,28 @@ unsafe fn public_window_callback_inner<T: 'static>(
         subclass_input.send_event(Event::WindowEvent {
           window_id: RootWindowId(WindowId(window)),
           event: Moved(physical_position),
+          timestamp: Instant::now(),
         });
       }
 
+      update_occlusion(window, subclass_input);
+
       // This is necessary for us to still get sent WM_SIZE.
       result = ProcResult::DefSubclassProc;
     }
 
+    winuser::WM_SIZING => {
+      let aspect_ratio = subclass_input.window_state.lock().aspect_ratio;
+      match aspect_ratio {
+        Some(aspect_ratio) if aspect_ratio.width > 0 && aspect_ratio.height > 0 => {
+          let rect = &mut *(lparam as *mut RECT);
+          constrain_aspect_ratio(window, wparam, rect, aspect_ratio.ratio());
+          result = ProcResult::Value(1);
+        }
+        _ => result = ProcResult::DefSubclassProc,
+      }
+    }
+
     winuser::WM_SIZE => {
       use crate::event::WindowEvent::Resized;
       let w = LOWORD(lparam as DWORD) as u32;
@@ -1072,14 +1775,48 @@ unsafe fn public_window_callback_inner<T: 'static>(
       let event = Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: Resized(physical_size),
+        timestamp: Instant::now(),
       };
 
       subclass_input.send_event(event);
+
+      // The modal loop of an interactive resize only dispatches `WM_PAINT` once the system has
+      // shown the window at its new size, stretching or cropping the previous content. Painting
+      // right away redraws the window at the new size in time.
+      let in_size_move = subclass_input
+        .window_state
+        .lock()
+        .window_flags()
+        .contains(WindowFlags::MARKER_IN_SIZE_MOVE);
+      if in_size_move && !subclass_input.event_loop_runner.should_buffer() {
+        winuser::RedrawWindow(
+          window,
+          ptr::null(),
+          ptr::null_mut(),
+          winuser::RDW_INTERNALPAINT | winuser::RDW_UPDATENOW,
+        );
+      }
       result = ProcResult::Value(0);
     }
 
     // this is necessary for us to maintain minimize/restore state
     winuser::WM_SYSCOMMAND => {
+      // Hidden windows have no taskbar button, so hiding the window is enough to send it to the
+      // tray.
+      if wparam == winuser::SC_MINIMIZE && subclass_input.window_state.lock().minimize_to_tray {
+        use crate::event::WindowEvent::MinimizedToTray;
+        WindowState::set_window_flags(subclass_input.window_state.lock(), window, |f| {
+          f.set(WindowFlags::VISIBLE, false)
+        });
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window)),
+          event: MinimizedToTray,
+          timestamp: Instant::now(),
+        });
+        result = ProcResult::Value(0);
+        return;
+      }
+
       if wparam == winuser::SC_RESTORE {
         let mut w = subclass_input.window_state.lock();
         w.set_window_flags_in_place(|f| f.set(WindowFlags::MINIMIZED, false));
@@ -1119,6 +1856,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           event: CursorEntered {
             device_id: DEVICE_ID,
           },
+          timestamp: message_timestamp(),
         });
 
         // Calling TrackMouseEvent in order to receive mouse leave events.
@@ -1133,7 +1871,9 @@ unsafe fn public_window_callback_inner<T: 'static>(
       let x = windowsx::GET_X_LPARAM(lparam) as f64;
       let y = windowsx::GET_Y_LPARAM(lparam) as f64;
       let position = PhysicalPosition::new(x, y);
+      let time = winuser::GetMessageTime() as DWORD;
       let cursor_moved;
+      let last_move_time;
       {
         // handle spurious WM_MOUSEMOVE messages
         // see https://devblogs.microsoft.com/oldnewthing/20031001-00/?p=42343
@@ -1141,9 +1881,30 @@ unsafe fn public_window_callback_inner<T: 'static>(
         let mut w = subclass_input.window_state.lock();
         cursor_moved = w.mouse.last_position != Some(position);
         w.mouse.last_position = Some(position);
+        last_move_time = if w.mouse.coalescing {
+          None
+        } else {
+          w.mouse.last_move_time.replace(time)
+        };
       }
       if cursor_moved {
         let modifiers = update_modifiers(window, subclass_input);
+        // The moves coalesced into this message since the previous one, if coalescing is disabled.
+        let history = match last_move_time {
+          Some(since) => mouse_move_history(window, position, time, since),
+          None => Vec::new(),
+        };
+        for (position, time) in history {
+          subclass_input.send_event(Event::WindowEvent {
+            window_id: RootWindowId(WindowId(window)),
+            event: CursorMoved {
+              device_id: DEVICE_ID,
+              position,
+              modifiers,
+            },
+            timestamp: tick_timestamp(time),
+          });
+        }
         subclass_input.send_event(Event::WindowEvent {
           window_id: RootWindowId(WindowId(window)),
           event: CursorMoved {
@@ -1151,6 +1912,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
             position,
             modifiers,
           },
+          timestamp: tick_timestamp(time),
         });
       }
 
@@ -1164,6 +1926,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
         w.mouse
           .set_cursor_flags(window, |f| f.set(CursorFlags::IN_WINDOW, false))
           .ok();
+        w.mouse.last_move_time = None;
       }
 
       subclass_input.send_event(Event::WindowEvent {
@@ -1171,6 +1934,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
         event: CursorLeft {
           device_id: DEVICE_ID,
         },
+        timestamp: message_timestamp(),
       });
 
       result = ProcResult::Value(0);
@@ -1191,8 +1955,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           delta: LineDelta(0.0, value),
           phase: TouchPhase::Moved,
+          momentum: false,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
 
       result = ProcResult::Value(0);
@@ -1213,8 +1979,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           delta: LineDelta(value, 0.0),
           phase: TouchPhase::Moved,
+          momentum: false,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
 
       result = ProcResult::Value(0);
@@ -1230,6 +1998,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
       use crate::event::{ElementState::Pressed, MouseButton::Left, WindowEvent::MouseInput};
 
       capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = press_click_count(&mut *subclass_input.window_state.lock(), Left, lparam);
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1239,8 +2008,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Left,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
@@ -1248,6 +2019,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     winuser::WM_LBUTTONUP => {
       use crate::event::{ElementState::Released, MouseButton::Left, WindowEvent::MouseInput};
 
+      let click_count = subclass_input
+        .window_state
+        .lock()
+        .mouse
+        .click_counter
+        .release(Left);
       release_mouse(subclass_input.window_state.lock());
 
       let modifiers = update_modifiers(window, subclass_input);
@@ -1258,8 +2035,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Left,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
@@ -1268,6 +2047,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
       use crate::event::{ElementState::Pressed, MouseButton::Right, WindowEvent::MouseInput};
 
       capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = press_click_count(&mut *subclass_input.window_state.lock(), Right, lparam);
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1277,8 +2057,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Right,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
@@ -1286,6 +2068,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     winuser::WM_RBUTTONUP => {
       use crate::event::{ElementState::Released, MouseButton::Right, WindowEvent::MouseInput};
 
+      let click_count = subclass_input
+        .window_state
+        .lock()
+        .mouse
+        .click_counter
+        .release(Right);
       release_mouse(subclass_input.window_state.lock());
 
       let modifiers = update_modifiers(window, subclass_input);
@@ -1296,8 +2084,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Right,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
@@ -1306,6 +2096,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
       use crate::event::{ElementState::Pressed, MouseButton::Middle, WindowEvent::MouseInput};
 
       capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = press_click_count(&mut *subclass_input.window_state.lock(), Middle, lparam);
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1315,8 +2106,10 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Middle,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
@@ -1324,6 +2117,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     winuser::WM_MBUTTONUP => {
       use crate::event::{ElementState::Released, MouseButton::Middle, WindowEvent::MouseInput};
 
+      let click_count = subclass_input
+        .window_state
+        .lock()
+        .mouse
+        .click_counter
+        .release(Middle);
       release_mouse(subclass_input.window_state.lock());
 
       let modifiers = update_modifiers(window, subclass_input);
@@ -1334,17 +2133,20 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Middle,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
 
     winuser::WM_XBUTTONDOWN => {
-      use crate::event::{ElementState::Pressed, MouseButton::Other, WindowEvent::MouseInput};
-      let xbutton = winuser::GET_XBUTTON_WPARAM(wparam);
+      use crate::event::{ElementState::Pressed, WindowEvent::MouseInput};
 
+      let button = x_mouse_button(wparam);
       capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = press_click_count(&mut *subclass_input.window_state.lock(), button, lparam);
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1353,17 +2155,25 @@ unsafe fn public_window_callback_inner<T: 'static>(
         event: MouseInput {
           device_id: DEVICE_ID,
           state: Pressed,
-          button: Other(xbutton),
+          button,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
 
     winuser::WM_XBUTTONUP => {
-      use crate::event::{ElementState::Released, MouseButton::Other, WindowEvent::MouseInput};
-      let xbutton = winuser::GET_XBUTTON_WPARAM(wparam);
+      use crate::event::{ElementState::Released, WindowEvent::MouseInput};
 
+      let button = x_mouse_button(wparam);
+      let click_count = subclass_input
+        .window_state
+        .lock()
+        .mouse
+        .click_counter
+        .release(button);
       release_mouse(subclass_input.window_state.lock());
 
       let modifiers = update_modifiers(window, subclass_input);
@@ -1373,20 +2183,48 @@ unsafe fn public_window_callback_inner<T: 'static>(
         event: MouseInput {
           device_id: DEVICE_ID,
           state: Released,
-          button: Other(xbutton),
+          button,
+          click_count,
           modifiers,
         },
+        timestamp: message_timestamp(),
       });
       result = ProcResult::Value(0);
     }
 
+    winuser::WM_APPCOMMAND => {
+      use crate::event::WindowEvent::KeyboardInput;
+
+      // The media keys of the keyboards are already sent with `WM_KEYDOWN` before their command
+      // reaches the window, unlike the ones of the remotes and the other HID devices.
+      if winuser::GET_DEVICE_LPARAM(lparam) == winuser::FAPPCOMMAND_OEM {
+        let events = keyboard::app_command_key_events(winuser::GET_APPCOMMAND_LPARAM(lparam));
+        if !events.is_empty() {
+          for event in events {
+            subclass_input.send_event(Event::WindowEvent {
+              window_id: RootWindowId(WindowId(window)),
+              event: KeyboardInput {
+                device_id: DEVICE_ID,
+                event,
+                is_synthetic: false,
+              },
+              timestamp: message_timestamp(),
+            });
+          }
+          result = ProcResult::Value(1);
+        }
+      }
+    }
+
     winuser::WM_CAPTURECHANGED => {
       // lparam here is a handle to the window which is gaining mouse capture.
       // If it is the same as our window, then we're essentially retaining the capture. This
       // can happen if `SetCapture` is called on our window when it already has the mouse
       // capture.
       if lparam != window as isize {
-        subclass_input.window_state.lock().mouse.capture_count = 0;
+        let mut window_state = subclass_input.window_state.lock();
+        window_state.mouse.capture_count = 0;
+        window_state.mouse.captured = false;
       }
       result = ProcResult::Value(0);
     }
@@ -1403,6 +2241,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
         mem::size_of::<winuser::TOUCHINPUT>() as INT,
       ) > 0
       {
+        let mut touches = Vec::with_capacity(pcount);
         for input in &inputs {
           let mut location = POINT {
             x: input.x / 100,
@@ -1416,25 +2255,30 @@ unsafe fn public_window_callback_inner<T: 'static>(
           let x = location.x as f64 + (input.x % 100) as f64 / 100f64;
           let y = location.y as f64 + (input.y % 100) as f64 / 100f64;
           let location = PhysicalPosition::new(x, y);
+          let touch = Touch {
+            phase: if input.dwFlags & winuser::TOUCHEVENTF_DOWN != 0 {
+              TouchPhase::Started
+            } else if input.dwFlags & winuser::TOUCHEVENTF_UP != 0 {
+              TouchPhase::Ended
+            } else if input.dwFlags & winuser::TOUCHEVENTF_MOVE != 0 {
+              TouchPhase::Moved
+            } else {
+              continue;
+            },
+            location,
+            force: None, // WM_TOUCH doesn't support pressure information
+            id: input.dwID as u64,
+            device_id: DEVICE_ID,
+          };
           subclass_input.send_event(Event::WindowEvent {
             window_id: RootWindowId(WindowId(window)),
-            event: WindowEvent::Touch(Touch {
-              phase: if input.dwFlags & winuser::TOUCHEVENTF_DOWN != 0 {
-                TouchPhase::Started
-              } else if input.dwFlags & winuser::TOUCHEVENTF_UP != 0 {
-                TouchPhase::Ended
-              } else if input.dwFlags & winuser::TOUCHEVENTF_MOVE != 0 {
-                TouchPhase::Moved
-              } else {
-                continue;
-              },
-              location,
-              force: None, // WM_TOUCH doesn't support pressure information
-              id: input.dwID as u64,
-              device_id: DEVICE_ID,
-            }),
+            event: WindowEvent::Touch(touch),
+            timestamp: message_timestamp(),
           });
+          touches.push(touch);
         }
+        // A message carries the touches of a frame.
+        send_touch_frame(subclass_input, window, touches);
       }
       winuser::CloseTouchInputHandle(htouch);
       result = ProcResult::Value(0);
@@ -1480,90 +2324,117 @@ unsafe fn public_window_callback_inner<T: 'static>(
 
         // https://docs.microsoft.com/en-us/windows/desktop/api/winuser/nf-winuser-getpointerframeinfohistory
         // The information retrieved appears in reverse chronological order, with the most recent entry in the first
-        // row of the returned array
-        for pointer_info in pointer_infos.iter().rev() {
-          let mut device_rect = mem::MaybeUninit::uninit();
-          let mut display_rect = mem::MaybeUninit::uninit();
-
-          if (GetPointerDeviceRects(
-            pointer_info.sourceDevice,
-            device_rect.as_mut_ptr(),
-            display_rect.as_mut_ptr(),
-          )) == 0
-          {
-            continue;
-          }
-
-          let device_rect = device_rect.assume_init();
-          let display_rect = display_rect.assume_init();
+        // row of the returned array. A row holds the pointers of a frame.
+        for frame in pointer_infos.chunks(pointers_count.max(1) as usize).rev() {
+          let mut touches = Vec::with_capacity(frame.len());
+          for pointer_info in frame {
+            let mut device_rect = mem::MaybeUninit::uninit();
+            let mut display_rect = mem::MaybeUninit::uninit();
+
+            if (GetPointerDeviceRects(
+              pointer_info.sourceDevice,
+              device_rect.as_mut_ptr(),
+              display_rect.as_mut_ptr(),
+            )) == 0
+            {
+              continue;
+            }
 
-          // For the most precise himetric to pixel conversion we calculate the ratio between the resolution
-          // of the display device (pixel) and the touch device (himetric).
-          let himetric_to_pixel_ratio_x = (display_rect.right - display_rect.left) as f64
-            / (device_rect.right - device_rect.left) as f64;
-          let himetric_to_pixel_ratio_y = (display_rect.bottom - display_rect.top) as f64
-            / (device_rect.bottom - device_rect.top) as f64;
+            let device_rect = device_rect.assume_init();
+            let display_rect = display_rect.assume_init();
+
+            // For the most precise himetric to pixel conversion we calculate the ratio between the resolution
+            // of the display device (pixel) and the touch device (himetric).
+            let himetric_to_pixel_ratio_x = (display_rect.right - display_rect.left) as f64
+              / (device_rect.right - device_rect.left) as f64;
+            let himetric_to_pixel_ratio_y = (display_rect.bottom - display_rect.top) as f64
+              / (device_rect.bottom - device_rect.top) as f64;
+
+            // ptHimetricLocation's origin is 0,0 even on multi-monitor setups.
+            // On multi-monitor setups we need to translate the himetric location to the rect of the
+            // display device it's attached to.
+            let x = display_rect.left as f64
+              + pointer_info.ptHimetricLocation.x as f64 * himetric_to_pixel_ratio_x;
+            let y = display_rect.top as f64
+              + pointer_info.ptHimetricLocation.y as f64 * himetric_to_pixel_ratio_y;
+
+            let mut location = POINT {
+              x: x.floor() as i32,
+              y: y.floor() as i32,
+            };
 
-          // ptHimetricLocation's origin is 0,0 even on multi-monitor setups.
-          // On multi-monitor setups we need to translate the himetric location to the rect of the
-          // display device it's attached to.
-          let x = display_rect.left as f64
-            + pointer_info.ptHimetricLocation.x as f64 * himetric_to_pixel_ratio_x;
-          let y = display_rect.top as f64
-            + pointer_info.ptHimetricLocation.y as f64 * himetric_to_pixel_ratio_y;
+            if winuser::ScreenToClient(window, &mut location as *mut _) == 0 {
+              continue;
+            }
 
-          let mut location = POINT {
-            x: x.floor() as i32,
-            y: y.floor() as i32,
-          };
+            let phase = if pointer_info.pointerFlags & winuser::POINTER_FLAG_DOWN != 0 {
+              TouchPhase::Started
+            } else if pointer_info.pointerFlags & winuser::POINTER_FLAG_UP != 0 {
+              TouchPhase::Ended
+            } else if pointer_info.pointerFlags & winuser::POINTER_FLAG_UPDATE != 0 {
+              TouchPhase::Moved
+            } else {
+              continue;
+            };
 
-          if winuser::ScreenToClient(window, &mut location as *mut _) == 0 {
-            continue;
-          }
+            let x = location.x as f64 + x.fract();
+            let y = location.y as f64 + y.fract();
+            let location = PhysicalPosition::new(x, y);
 
-          let force = match pointer_info.pointerType {
-            winuser::PT_TOUCH => {
-              let mut touch_info = mem::MaybeUninit::uninit();
-              GET_POINTER_TOUCH_INFO.and_then(|GetPointerTouchInfo| {
-                match GetPointerTouchInfo(pointer_info.pointerId, touch_info.as_mut_ptr()) {
-                  0 => None,
-                  _ => normalize_pointer_pressure(touch_info.assume_init().pressure),
-                }
-              })
-            }
-            winuser::PT_PEN => {
+            if pointer_info.pointerType == winuser::PT_PEN {
               let mut pen_info = mem::MaybeUninit::uninit();
-              GET_POINTER_PEN_INFO.and_then(|GetPointerPenInfo| {
-                match GetPointerPenInfo(pointer_info.pointerId, pen_info.as_mut_ptr()) {
-                  0 => None,
-                  _ => normalize_pointer_pressure(pen_info.assume_init().pressure),
+              let pen_info = match *GET_POINTER_PEN_INFO {
+                Some(GetPointerPenInfo)
+                  if GetPointerPenInfo(pointer_info.pointerId, pen_info.as_mut_ptr()) != 0 =>
+                {
+                  pen_info.assume_init()
                 }
-              })
+                _ => continue,
+              };
+              // The updates of a pen in range which doesn't touch the screen are its hover.
+              let event = if phase == TouchPhase::Moved
+                && pointer_info.pointerFlags & winuser::POINTER_FLAG_INCONTACT == 0
+              {
+                WindowEvent::PenHover(pen_hover(&pen_info, location))
+              } else {
+                WindowEvent::PenInput(pen_input(&pen_info, phase, location))
+              };
+              subclass_input.send_event(Event::WindowEvent {
+                window_id: RootWindowId(WindowId(window)),
+                event,
+                timestamp: message_timestamp(),
+              });
+              continue;
             }
-            _ => None,
-          };
 
-          let x = location.x as f64 + x.fract();
-          let y = location.y as f64 + y.fract();
-          let location = PhysicalPosition::new(x, y);
-          subclass_input.send_event(Event::WindowEvent {
-            window_id: RootWindowId(WindowId(window)),
-            event: WindowEvent::Touch(Touch {
-              phase: if pointer_info.pointerFlags & winuser::POINTER_FLAG_DOWN != 0 {
-                TouchPhase::Started
-              } else if pointer_info.pointerFlags & winuser::POINTER_FLAG_UP != 0 {
-                TouchPhase::Ended
-              } else if pointer_info.pointerFlags & winuser::POINTER_FLAG_UPDATE != 0 {
-                TouchPhase::Moved
-              } else {
-                continue;
-              },
+            let force = match pointer_info.pointerType {
+              winuser::PT_TOUCH => {
+                let mut touch_info = mem::MaybeUninit::uninit();
+                GET_POINTER_TOUCH_INFO.and_then(|GetPointerTouchInfo| {
+                  match GetPointerTouchInfo(pointer_info.pointerId, touch_info.as_mut_ptr()) {
+                    0 => None,
+                    _ => normalize_pointer_pressure(touch_info.assume_init().pressure),
+                  }
+                })
+              }
+              _ => None,
+            };
+
+            let touch = Touch {
+              phase,
               location,
               force,
               id: pointer_info.pointerId as u64,
               device_id: DEVICE_ID,
-            }),
-          });
+            };
+            subclass_input.send_event(Event::WindowEvent {
+              window_id: RootWindowId(WindowId(window)),
+              event: WindowEvent::Touch(touch),
+              timestamp: message_timestamp(),
+            });
+            touches.push(touch);
+          }
+          send_touch_frame(subclass_input, window, touches);
         }
 
         SkipPointerFrameMessages(pointer_id);
@@ -1571,6 +2442,56 @@ unsafe fn public_window_callback_inner<T: 'static>(
       result = ProcResult::Value(0);
     }
 
+    // Sent when a pointer comes in range over the window, and when it leaves the window or its
+    // range.
+    winuser::WM_POINTERENTER | winuser::WM_POINTERLEAVE => {
+      let pointer_id = LOWORD(wparam as DWORD) as UINT;
+      let mut pen_info = mem::MaybeUninit::uninit();
+      if let Some(GetPointerPenInfo) = *GET_POINTER_PEN_INFO {
+        if GetPointerPenInfo(pointer_id, pen_info.as_mut_ptr()) != 0 {
+          subclass_input.send_event(Event::WindowEvent {
+            window_id: RootWindowId(WindowId(window)),
+            event: WindowEvent::PenProximity {
+              device_id: DEVICE_ID,
+              in_range: msg == winuser::WM_POINTERENTER,
+            },
+            timestamp: message_timestamp(),
+          });
+        }
+      }
+    }
+
+    // Sent to the focused window when the user switches to another layout, which is per thread.
+    winuser::WM_INPUTLANGCHANGE => {
+      subclass_input.send_event(Event::KeyboardLayoutChanged(
+        keyboard_layout::keyboard_layout(lparam as HKL),
+      ));
+    }
+
+    // `IsDialogMessage` asks the window whether it handles the key before moving the focus with it.
+    winuser::WM_GETDLGCODE => {
+      use crate::event::{FocusDirection, WindowEvent::FocusTraversal};
+
+      let msg = lparam as *const winuser::MSG;
+      if !msg.is_null()
+        && (*msg).message == winuser::WM_KEYDOWN
+        && (*msg).wParam == winuser::VK_TAB as WPARAM
+        && winuser::GetKeyState(winuser::VK_CONTROL) >= 0
+        && winuser::GetKeyState(winuser::VK_MENU) >= 0
+      {
+        let direction = if winuser::GetKeyState(winuser::VK_SHIFT) < 0 {
+          FocusDirection::Backward
+        } else {
+          FocusDirection::Forward
+        };
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window)),
+          event: FocusTraversal(direction),
+          timestamp: message_timestamp(),
+        });
+      }
+    }
+
     winuser::WM_SETFOCUS => {
       use crate::event::WindowEvent::Focused;
       update_modifiers(window, subclass_input);
@@ -1578,6 +2499,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: Focused(true),
+        timestamp: Instant::now(),
       });
 
       result = ProcResult::Value(0);
@@ -1590,11 +2512,13 @@ unsafe fn public_window_callback_inner<T: 'static>(
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: ModifiersChanged(ModifiersState::empty()),
+        timestamp: Instant::now(),
       });
 
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window)),
         event: Focused(false),
+        timestamp: Instant::now(),
       });
       result = ProcResult::Value(0);
     }
@@ -1735,6 +2659,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           scale_factor: new_scale_factor,
           new_inner_size: &mut new_physical_inner_size,
         },
+        timestamp: Instant::now(),
       });
 
       let dragging_window: bool;
@@ -1865,18 +2790,19 @@ unsafe fn public_window_callback_inner<T: 'static>(
 
       let preferred_theme = subclass_input.window_state.lock().preferred_theme;
 
-      if preferred_theme == None {
-        let new_theme = try_theme(window, preferred_theme);
-        let mut window_state = subclass_input.window_state.lock();
+      // `Window::set_theme` also sends this message, so the theme is applied even if one is
+      // preferred.
+      let new_theme = try_theme(window, preferred_theme);
+      let mut window_state = subclass_input.window_state.lock();
 
-        if window_state.current_theme != new_theme {
-          window_state.current_theme = new_theme;
-          mem::drop(window_state);
-          subclass_input.send_event(Event::WindowEvent {
-            window_id: RootWindowId(WindowId(window)),
-            event: ThemeChanged(new_theme),
-          });
-        }
+      if window_state.current_theme != new_theme {
+        window_state.current_theme = new_theme;
+        mem::drop(window_state);
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window)),
+          event: ThemeChanged(new_theme),
+          timestamp: Instant::now(),
+        });
       }
     }
 
@@ -1884,12 +2810,19 @@ unsafe fn public_window_callback_inner<T: 'static>(
       let win_flags = subclass_input.window_state.lock().window_flags();
 
       if !win_flags.contains(WindowFlags::DECORATIONS) {
-        // adjust the maximized borderless window so it doesn't cover the taskbar
+        // Undecorated windows keep the `WS_CAPTION` and `WS_THICKFRAME` styles, which Aero Snap,
+        // Snap Assist and the window animations rely on. Only the frame those styles add
+        // around the client area is removed here.
         if util::is_maximized(window) {
-          let monitor = monitor::current_monitor(window);
-          if let Ok(monitor_info) = monitor::get_monitor_info(monitor.hmonitor()) {
-            let params = &mut *(lparam as *mut winuser::NCCALCSIZE_PARAMS);
+          // adjust the maximized borderless window so it doesn't cover the taskbar.
+          // When snapped to the top of another monitor, the window isn't on that monitor
+          // yet, so look it up from the proposed rectangle instead.
+          let params = &mut *(lparam as *mut winuser::NCCALCSIZE_PARAMS);
+          let hmonitor =
+            winuser::MonitorFromRect(&params.rgrc[0], winuser::MONITOR_DEFAULTTONEAREST);
+          if let Ok(monitor_info) = monitor::get_monitor_info(hmonitor) {
             params.rgrc[0] = monitor_info.rcWork;
+            leave_room_for_autohide_taskbar(&monitor_info.rcMonitor, &mut params.rgrc[0]);
           }
         }
         result = ProcResult::Value(0); // return 0 here to make the windowo borderless
@@ -1902,7 +2835,6 @@ unsafe fn public_window_callback_inner<T: 'static>(
       if let Some(state) = subclass_input.window_state.try_lock() {
         let win_flags = state.window_flags();
 
-        // Only apply this hit test for borderless windows that wants to be resizable
         if !win_flags.contains(WindowFlags::DECORATIONS) {
           // cursor location
           let (cx, cy) = (
@@ -1910,7 +2842,35 @@ unsafe fn public_window_callback_inner<T: 'static>(
             windowsx::GET_Y_LPARAM(lparam),
           );
 
-          result = ProcResult::Value(crate::platform_impl::hit_test(window, cx, cy));
+          // Only apply the resize hit test for borderless windows that wants to be resizable
+          let mut hit = if state.resizable_borders
+            && win_flags.contains(WindowFlags::RESIZABLE)
+            && !util::is_maximized(window)
+          {
+            crate::platform_impl::hit_test(window, cx, cy, state.resizable_edges)
+          } else {
+            winuser::HTCLIENT
+          };
+
+          // The resize borders take precedence over the drag regions
+          if hit == winuser::HTCLIENT {
+            let mut point = POINT { x: cx, y: cy };
+            winuser::ScreenToClient(window, &mut point);
+            let position = PhysicalPosition::new(point.x as f64, point.y as f64);
+            if let Some(region) = state
+              .snap_layout_button
+              .iter()
+              .chain(state.drag_regions.iter().rev())
+              .find(|region| region.contains(position, state.scale_factor))
+            {
+              hit = drag_region_hit_test(region.kind);
+            }
+          }
+
+          result = ProcResult::Value(hit);
+        } else if state.resizable_edges != Edges::all() {
+          let hit = commctrl::DefSubclassProc(window, msg, wparam, lparam);
+          result = ProcResult::Value(restrict_hit_test(hit, state.resizable_edges));
         } else {
           result = ProcResult::DefSubclassProc;
         }
@@ -1918,7 +2878,17 @@ unsafe fn public_window_callback_inner<T: 'static>(
     }
 
     _ => {
-      if msg == *DESTROY_MSG_ID {
+      if msg == *DESTROY_MSG_ID
+        || (msg == *CLOSE_RESPONSE_MSG_ID && wparam == subclass_input.window_state.lock().serial)
+      {
+        // The owner of a modal window must be enabled before the window is destroyed,
+        // otherwise another application is activated.
+        if subclass_input.window_state.lock().modal {
+          let owner = winuser::GetWindow(window, winuser::GW_OWNER);
+          if !owner.is_null() {
+            winuser::EnableWindow(owner, 1);
+          }
+        }
         winuser::DestroyWindow(window);
         result = ProcResult::Value(0);
       } else if msg == *SET_RETAIN_STATE_ON_SIZE_MSG_ID {
@@ -1927,6 +2897,15 @@ unsafe fn public_window_callback_inner<T: 'static>(
           f.set(WindowFlags::MARKER_RETAIN_STATE_ON_SIZE, wparam != 0)
         });
         result = ProcResult::Value(0);
+      } else if msg == *FULLSCREEN_TRANSITION_MSG_ID {
+        if let Some(transition) = fullscreen_transition_from_wparam(wparam) {
+          subclass_input.send_event(Event::WindowEvent {
+            window_id: RootWindowId(WindowId(window)),
+            event: WindowEvent::FullscreenTransition(transition),
+            timestamp: Instant::now(),
+          });
+        }
+        result = ProcResult::Value(0);
       }
     }
   };
@@ -2009,32 +2988,76 @@ unsafe extern "system" fn thread_event_target_callback<T: 'static>(
     winuser::WM_INPUT_DEVICE_CHANGE => {
       let event = match wparam as _ {
         winuser::GIDC_ARRIVAL => DeviceEvent::Added,
-        winuser::GIDC_REMOVAL => DeviceEvent::Removed,
+        winuser::GIDC_REMOVAL => {
+          subclass_input
+            .hid_devices
+            .borrow_mut()
+            .remove(&(lparam as usize));
+          DeviceEvent::Removed
+        }
         _ => unreachable!(),
       };
 
       subclass_input.send_event(Event::DeviceEvent {
         device_id: wrap_device_id(lparam as _),
         event,
+        timestamp: Instant::now(),
       });
 
       0
     }
 
     winuser::WM_INPUT => {
-      if let Some(data) = raw_input::get_raw_input_data(lparam as _) {
-        handle_raw_input(&subclass_input, data);
+      match raw_input::get_raw_input_data(lparam as _) {
+        Some(data) if data.header.dwType != winuser::RIM_TYPEHID => {
+          handle_raw_input(&subclass_input, data);
+        }
+        // The reports of the HID devices usually don't fit in `RAWINPUT`.
+        _ => {
+          if let Some(input) = raw_input::get_raw_hid_input(lparam as _) {
+            handle_raw_hid_input(&subclass_input, input);
+          }
+        }
       }
 
       commctrl::DefSubclassProc(window, msg, wparam, lparam)
     }
 
+    // The thread event target is a top-level window, so it receives the power broadcasts too.
+    winuser::WM_POWERBROADCAST => {
+      match wparam {
+        winuser::PBT_APMSUSPEND => {
+          subclass_input.send_event(Event::Suspended(SuspendReason::Sleep));
+        }
+        winuser::PBT_APMRESUMEAUTOMATIC => {
+          subclass_input.send_event(Event::Resumed(SuspendReason::Sleep));
+        }
+        _ => (),
+      }
+
+      TRUE as LRESULT
+    }
+
+    // Sent to the top-level windows when the user logs off or shuts down the system.
+    winuser::WM_QUERYENDSESSION => {
+      let prevent = subclass_input.event_loop_runner.request_exit();
+      (!prevent) as LRESULT
+    }
+
     _ if msg == *USER_EVENT_MSG_ID => {
-      if let Ok(event) = subclass_input.user_event_receiver.recv() {
+      if let Some(event) = subclass_input.user_events.pop() {
         subclass_input.send_event(Event::UserEvent(event));
       }
       0
     }
+    winuser::WM_TIMER => {
+      subclass_input.send_event(Event::Timer(TimerId(wparam as u32)));
+      0
+    }
+    _ if msg == *WAKE_UP_MSG_ID => {
+      subclass_input.event_loop_runner.poll();
+      0
+    }
     _ if msg == *EXEC_MSG_ID => {
       let mut function: ThreadExecFn = Box::from_raw(wparam as usize as *mut _);
       function();
@@ -2094,6 +3117,40 @@ unsafe extern "system" fn thread_event_target_callback<T: 'static>(
   result
 }
 
+unsafe fn handle_raw_hid_input<T: 'static>(
+  subclass_input: &Box<ThreadMsgTargetSubclassInput<T>>,
+  input: raw_input::RawHidInput,
+) {
+  // The events are collected first, since the callback could receive another message.
+  let events = {
+    let mut hid_devices = subclass_input.hid_devices.borrow_mut();
+    let device = match hid_devices.entry(input.device as usize) {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => match raw_input::HidDevice::new(input.device) {
+        Some(device) => entry.insert(device),
+        None => return,
+      },
+    };
+    input
+      .reports
+      .into_iter()
+      .flat_map(|mut report| device.parse_report(&mut report))
+      .collect::<Vec<_>>()
+  };
+
+  let device_id = wrap_device_id(input.device as _);
+  for event in events {
+    subclass_input.send_event(Event::DeviceEvent {
+      device_id,
+      event,
+      timestamp: message_timestamp(),
+    });
+  }
+}
+
+// The horizontal wheel of `RAWMOUSE`, which winapi doesn't define.
+const RI_MOUSE_HWHEEL: u16 = 0x0800;
+
 unsafe fn handle_raw_input<T: 'static>(
   subclass_input: &Box<ThreadMsgTargetSubclassInput<T>>,
   data: RAWINPUT,
@@ -2117,6 +3174,7 @@ unsafe fn handle_raw_input<T: 'static>(
         subclass_input.send_event(Event::DeviceEvent {
           device_id,
           event: Motion { axis: 0, value: x },
+          timestamp: message_timestamp(),
         });
       }
 
@@ -2124,6 +3182,7 @@ unsafe fn handle_raw_input<T: 'static>(
         subclass_input.send_event(Event::DeviceEvent {
           device_id,
           event: Motion { axis: 1, value: y },
+          timestamp: message_timestamp(),
         });
       }
 
@@ -2131,6 +3190,7 @@ unsafe fn handle_raw_input<T: 'static>(
         subclass_input.send_event(Event::DeviceEvent {
           device_id,
           event: MouseMotion { delta: (x, y) },
+          timestamp: message_timestamp(),
         });
       }
     }
@@ -2143,6 +3203,18 @@ unsafe fn handle_raw_input<T: 'static>(
         event: MouseWheel {
           delta: LineDelta(0.0, delta),
         },
+        timestamp: message_timestamp(),
+      });
+    }
+
+    if util::has_flag(mouse.usButtonFlags, RI_MOUSE_HWHEEL) {
+      let delta = mouse.usButtonData as SHORT as f32 / winuser::WHEEL_DELTA as f32;
+      subclass_input.send_event(Event::DeviceEvent {
+        device_id,
+        event: MouseWheel {
+          delta: LineDelta(delta, 0.0),
+        },
+        timestamp: message_timestamp(),
       });
     }
 
@@ -2157,6 +3229,7 @@ unsafe fn handle_raw_input<T: 'static>(
         subclass_input.send_event(Event::DeviceEvent {
           device_id,
           event: Button { button, state },
+          timestamp: message_timestamp(),
         });
       }
     }
@@ -2270,6 +3343,7 @@ unsafe fn handle_raw_input<T: 'static>(
         physical_key: code,
         state,
       }),
+      timestamp: message_timestamp(),
     });
   }
 }
@@ -62,7 +62,8 @@ enum RunnerState {
 
 enum BufferedEvent<T: 'static> {
   Event(Event<'static, T>),
-  ScaleFactorChanged(WindowId, f64, PhysicalSize<u32>),
+  ScaleFactorChanged(WindowId, f64, PhysicalSize<u32>, Instant),
+  ExitRequested,
 }
 
 impl<T> EventLoopRunner<T> {
@@ -108,6 +109,12 @@ impl<T> EventLoopRunner<T> {
     control_flow.set(ControlFlow::Poll);
     event_handler.set(None);
   }
+
+  /// Removes the event handler without resetting the runner, so the events sent until the next
+  /// handler is set are buffered.
+  pub(crate) fn clear_event_handler(&self) {
+    self.event_handler.set(None);
+  }
 }
 
 /// State retrieval functions.
@@ -139,6 +146,10 @@ impl<T> EventLoopRunner<T> {
     self.runner_state.get() != RunnerState::Idle
   }
 
+  pub fn initialized(&self) -> bool {
+    self.runner_state.get() != RunnerState::Uninitialized
+  }
+
   pub fn should_buffer(&self) -> bool {
     let handler = self.event_handler.take();
     let should_buffer = handler.is_none();
@@ -227,6 +238,24 @@ impl<T> EventLoopRunner<T> {
     }
   }
 
+  /// Sends `ExitRequested`, and makes the event loop exit unless the event handler prevents it.
+  /// Returns whether the exit was prevented.
+  pub(crate) unsafe fn request_exit(&self) -> bool {
+    if self.should_buffer() {
+      // The event handler can't answer right now, so the exit can't be prevented either.
+      self
+        .event_buffer
+        .borrow_mut()
+        .push_back(BufferedEvent::ExitRequested);
+      false
+    } else {
+      self.move_state_to(RunnerState::HandlingMainEvents);
+      let prevent = self.call_exit_requested();
+      self.dispatch_buffered_events();
+      prevent
+    }
+  }
+
   pub(crate) unsafe fn main_events_cleared(&self) {
     self.move_state_to(RunnerState::HandlingRedrawEvents);
   }
@@ -245,10 +274,10 @@ impl<T> EventLoopRunner<T> {
             let mut event_handler = self.event_handler.take()
                 .expect("either event handler is re-entrant (likely), or no event handler is registered (very unlikely)");
 
-            if control_flow != ControlFlow::Exit {
-                event_handler(event, &mut control_flow);
+            if let ControlFlow::ExitWithCode(code) = control_flow {
+                event_handler(event, &mut ControlFlow::ExitWithCode(code));
             } else {
-                event_handler(event, &mut ControlFlow::Exit);
+                event_handler(event, &mut control_flow);
             }
 
             assert!(self.event_handler.replace(Some(event_handler)).is_none());
@@ -256,6 +285,17 @@ impl<T> EventLoopRunner<T> {
         });
   }
 
+  unsafe fn call_exit_requested(&self) -> bool {
+    let mut prevent = false;
+    self.call_event_handler(Event::ExitRequested {
+      prevent: &mut prevent,
+    });
+    if !prevent && self.control_flow.get().exit_code().is_none() {
+      self.control_flow.set(ControlFlow::Exit);
+    }
+    prevent
+  }
+
   unsafe fn dispatch_buffered_events(&self) {
     loop {
       // We do this instead of using a `while let` loop because if we use a `while let`
@@ -264,6 +304,9 @@ impl<T> EventLoopRunner<T> {
       // `process_event` will fail.
       let buffered_event_opt = self.event_buffer.borrow_mut().pop_front();
       match buffered_event_opt {
+        Some(BufferedEvent::ExitRequested) => {
+          self.call_exit_requested();
+        }
         Some(e) => e.dispatch_event(|e| self.call_event_handler(e)),
         None => break,
       }
@@ -374,10 +417,12 @@ impl<T> EventLoopRunner<T> {
     let start_cause = match (init, self.control_flow()) {
       (true, _) => StartCause::Init,
       (false, ControlFlow::Poll) => StartCause::Poll,
-      (false, ControlFlow::Exit) | (false, ControlFlow::Wait) => StartCause::WaitCancelled {
-        requested_resume: None,
-        start: self.last_events_cleared.get(),
-      },
+      (false, ControlFlow::ExitWithCode(_)) | (false, ControlFlow::Wait) => {
+        StartCause::WaitCancelled {
+          requested_resume: None,
+          start: self.last_events_cleared.get(),
+        }
+      }
       (false, ControlFlow::WaitUntil(requested_resume)) => {
         if Instant::now() < requested_resume {
           StartCause::WaitCancelled {
@@ -404,6 +449,9 @@ impl<T> EventLoopRunner<T> {
 
   unsafe fn call_redraw_events_cleared(&self) {
     self.call_event_handler(Event::RedrawEventsCleared);
+    if let Some(event) = Event::idle(self.control_flow()) {
+      self.call_event_handler(event);
+    }
     self.last_events_cleared.set(Instant::now());
   }
 }
@@ -418,7 +466,8 @@ impl<T> BufferedEvent<T> {
             new_inner_size,
           },
         window_id,
-      } => BufferedEvent::ScaleFactorChanged(window_id, scale_factor, *new_inner_size),
+        timestamp,
+      } => BufferedEvent::ScaleFactorChanged(window_id, scale_factor, *new_inner_size, timestamp),
       event => BufferedEvent::Event(event.to_static().unwrap()),
     }
   }
@@ -426,13 +475,14 @@ impl<T> BufferedEvent<T> {
   pub fn dispatch_event(self, dispatch: impl FnOnce(Event<'_, T>)) {
     match self {
       Self::Event(event) => dispatch(event),
-      Self::ScaleFactorChanged(window_id, scale_factor, mut new_inner_size) => {
+      Self::ScaleFactorChanged(window_id, scale_factor, mut new_inner_size, timestamp) => {
         dispatch(Event::WindowEvent {
           window_id,
           event: WindowEvent::ScaleFactorChanged {
             scale_factor,
             new_inner_size: &mut new_inner_size,
           },
+          timestamp,
         });
         util::set_inner_size_physical(
           (window_id.0).0,
@@ -440,6 +490,7 @@ impl<T> BufferedEvent<T> {
           new_inner_size.height as _,
         );
       }
+      Self::ExitRequested => unreachable!("`ExitRequested` is dispatched by the runner"),
     }
   }
 }
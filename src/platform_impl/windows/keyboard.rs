@@ -3,13 +3,17 @@ use std::{
   collections::HashSet,
   ffi::OsString,
   mem::MaybeUninit,
-  os::{raw::c_int, windows::ffi::OsStringExt},
+  os::{
+    raw::{c_int, c_short},
+    windows::ffi::OsStringExt,
+  },
   sync::MutexGuard,
+  time::Duration,
 };
 
 use winapi::{
   shared::{
-    minwindef::{HKL, LPARAM, UINT, WPARAM},
+    minwindef::{DWORD, HKL, LPARAM, UINT, WPARAM},
     windef::HWND,
   },
   um::winuser,
@@ -18,8 +22,8 @@ use winapi::{
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
-  event::{ElementState, KeyEvent},
-  keyboard::{Key, KeyCode, KeyLocation, NativeKeyCode},
+  event::{ComposeState, ElementState, KeyEvent},
+  keyboard::{Key, KeyCode, KeyLocation, KeyRepeatInfo, LockKey, NativeKeyCode},
   platform_impl::platform::{
     event_loop::ProcResult,
     keyboard_layout::{get_or_insert_str, Layout, LayoutCache, WindowsModifiers, LAYOUT_CACHE},
@@ -34,6 +38,45 @@ pub fn is_msg_keyboard_related(msg: u32) -> bool {
   is_keyboard_msg || msg == WM_SETFOCUS || msg == WM_KILLFOCUS
 }
 
+/// Returns the auto-repeat of the keyboard settings.
+pub(crate) fn key_repeat_info() -> Option<KeyRepeatInfo> {
+  let mut delay: UINT = 0;
+  let mut speed: DWORD = 0;
+  unsafe {
+    if winuser::SystemParametersInfoW(
+      winuser::SPI_GETKEYBOARDDELAY,
+      0,
+      &mut delay as *mut _ as _,
+      0,
+    ) == 0
+      || winuser::SystemParametersInfoW(
+        winuser::SPI_GETKEYBOARDSPEED,
+        0,
+        &mut speed as *mut _ as _,
+        0,
+      ) == 0
+    {
+      return None;
+    }
+  }
+  // The delay goes from 0 (250ms) to 3 (1s), and the speed from 0 (about 2.5 repeats per second)
+  // to 31 (about 30 repeats per second).
+  Some(KeyRepeatInfo {
+    delay: Duration::from_millis(250 * (delay as u64 + 1)),
+    rate: 2.5 + speed as f64 * (30.0 - 2.5) / 31.0,
+  })
+}
+
+/// Returns whether a lock key is toggled on, as of the last keyboard message of the thread.
+pub(crate) fn lock_key_state(key: LockKey) -> bool {
+  let vkey = match key {
+    LockKey::CapsLock => winuser::VK_CAPITAL,
+    LockKey::NumLock => winuser::VK_NUMLOCK,
+    LockKey::ScrollLock => winuser::VK_SCROLL,
+  };
+  unsafe { winuser::GetKeyState(vkey) & 1 != 0 }
+}
+
 pub type ExScancode = u16;
 
 pub struct MessageAsKeyEvent {
@@ -63,10 +106,16 @@ pub struct MessageAsKeyEvent {
 /// text input. The "sequence" only consists of one WM_KEYUP / WM_SYSKEYUP event.
 pub struct KeyEventBuilder {
   event_info: Option<PartialKeyEventInfo>,
+
+  /// Whether a dead key was pressed and the next character will be combined with it.
+  dead_key_pending: bool,
 }
 impl Default for KeyEventBuilder {
   fn default() -> Self {
-    KeyEventBuilder { event_info: None }
+    KeyEventBuilder {
+      event_info: None,
+      dead_key_pending: false,
+    }
   }
 }
 impl KeyEventBuilder {
@@ -157,7 +206,9 @@ impl KeyEventBuilder {
         // this key press
         let event_info = self.event_info.take().unwrap();
         let mut layouts = LAYOUT_CACHE.lock().unwrap();
-        let ev = event_info.finalize(&mut layouts.strings);
+        let mut ev = event_info.finalize(&mut layouts.strings);
+        self.dead_key_pending = true;
+        ev.compose_state = Some(ComposeState::Composing);
         return vec![MessageAsKeyEvent {
           event: ev,
           is_synthetic: false,
@@ -257,7 +308,18 @@ impl KeyEventBuilder {
             let key = layout.get_key(mod_no_ctrl, num_lock_on, vkey, scancode, keycode);
             event_info.text = PartialText::Text(key.to_text());
           }
-          let ev = event_info.finalize(&mut layouts.strings);
+          let mut ev = event_info.finalize(&mut layouts.strings);
+          if std::mem::replace(&mut self.dead_key_pending, false) {
+            // The dead key is followed by the character of this key when they can't be combined.
+            let is_combined = ev
+              .text
+              .map_or(false, |text| text.grapheme_indices(true).count() == 1);
+            ev.compose_state = Some(if is_combined {
+              ComposeState::Composed
+            } else {
+              ComposeState::Cancelled
+            });
+          }
           return vec![MessageAsKeyEvent {
             event: ev,
             is_synthetic: false,
@@ -662,6 +724,7 @@ impl PartialKeyEventInfo {
       location: self.location,
       state: self.key_state,
       repeat: self.is_repeat,
+      compose_state: None,
       platform_specific: KeyEventExtra {
         text_with_all_modifiers: char_with_all_modifiers,
         key_without_modifiers: self.key_without_modifiers,
@@ -788,6 +851,43 @@ fn get_location(scancode: ExScancode, hkl: HKL) -> KeyLocation {
   }
 }
 
+/// The press and the release of the media key of a `WM_APPCOMMAND`, if it's a media command.
+pub(crate) fn app_command_key_events(command: c_short) -> Vec<KeyEvent> {
+  let (physical_key, logical_key) = match command {
+    winuser::APPCOMMAND_VOLUME_MUTE => (KeyCode::AudioVolumeMute, Key::AudioVolumeMute),
+    winuser::APPCOMMAND_VOLUME_DOWN => (KeyCode::AudioVolumeDown, Key::AudioVolumeDown),
+    winuser::APPCOMMAND_VOLUME_UP => (KeyCode::AudioVolumeUp, Key::AudioVolumeUp),
+    winuser::APPCOMMAND_MEDIA_NEXTTRACK => (KeyCode::MediaTrackNext, Key::MediaTrackNext),
+    winuser::APPCOMMAND_MEDIA_PREVIOUSTRACK => {
+      (KeyCode::MediaTrackPrevious, Key::MediaTrackPrevious)
+    }
+    winuser::APPCOMMAND_MEDIA_STOP => (KeyCode::MediaStop, Key::MediaStop),
+    winuser::APPCOMMAND_MEDIA_PLAY_PAUSE => (KeyCode::MediaPlayPause, Key::MediaPlayPause),
+    winuser::APPCOMMAND_MEDIA_PLAY => (KeyCode::MediaPlay, Key::MediaPlay),
+    winuser::APPCOMMAND_MEDIA_PAUSE => (KeyCode::MediaPause, Key::MediaPause),
+    winuser::APPCOMMAND_MEDIA_RECORD => (KeyCode::MediaRecord, Key::MediaRecord),
+    winuser::APPCOMMAND_MEDIA_FAST_FORWARD => (KeyCode::MediaFastForward, Key::MediaFastForward),
+    winuser::APPCOMMAND_MEDIA_REWIND => (KeyCode::MediaRewind, Key::MediaRewind),
+    _ => return Vec::new(),
+  };
+  [ElementState::Pressed, ElementState::Released]
+    .iter()
+    .map(|&state| KeyEvent {
+      physical_key,
+      logical_key: logical_key.clone(),
+      text: None,
+      location: KeyLocation::Standard,
+      state,
+      repeat: false,
+      compose_state: None,
+      platform_specific: KeyEventExtra {
+        text_with_all_modifiers: None,
+        key_without_modifiers: logical_key.clone(),
+      },
+    })
+    .collect()
+}
+
 // used to build accelerators table from Key
 pub(crate) fn key_to_vk(key: &KeyCode) -> Option<i32> {
   Some(match key {
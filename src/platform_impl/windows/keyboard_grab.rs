@@ -0,0 +1,99 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The keyboard grab of `Window::set_keyboard_grab`. The shortcuts of the shell and the media keys
+// are handled before they reach the focused window, so a low-level keyboard hook swallows them
+// while a grabbing window is in the foreground, and posts them to it as regular key messages.
+use std::{cell::RefCell, io, ptr};
+
+use winapi::{
+  ctypes::c_int,
+  shared::{
+    minwindef::{LPARAM, LRESULT, UINT, WPARAM},
+    windef::{HHOOK, HWND},
+  },
+  um::{libloaderapi, winuser},
+};
+
+use super::kiosk;
+
+thread_local! {
+  // The hook and the windows grabbing the keyboard.
+  static KEYBOARD_GRAB: RefCell<(HHOOK, Vec<HWND>)> = RefCell::new((ptr::null_mut(), Vec::new()));
+}
+
+// Must be called from the thread of the event loop, which dispatches the calls of the hook.
+pub fn grab_keyboard(window: HWND) -> Result<(), io::Error> {
+  KEYBOARD_GRAB.with(|grab| {
+    let (hook, windows) = &mut *grab.borrow_mut();
+    if hook.is_null() {
+      *hook = unsafe {
+        winuser::SetWindowsHookExW(
+          winuser::WH_KEYBOARD_LL,
+          Some(keyboard_hook),
+          libloaderapi::GetModuleHandleW(ptr::null()),
+          0,
+        )
+      };
+      if hook.is_null() {
+        return Err(io::Error::last_os_error());
+      }
+    }
+    if !windows.contains(&window) {
+      windows.push(window);
+    }
+    Ok(())
+  })
+}
+
+pub fn ungrab_keyboard(window: HWND) {
+  KEYBOARD_GRAB.with(|grab| {
+    let (hook, windows) = &mut *grab.borrow_mut();
+    windows.retain(|&w| w != window);
+    if windows.is_empty() && !hook.is_null() {
+      unsafe { winuser::UnhookWindowsHookEx(*hook) };
+      *hook = ptr::null_mut();
+    }
+  });
+}
+
+fn is_grabbed(key: &winuser::KBDLLHOOKSTRUCT) -> bool {
+  match key.vkCode as c_int {
+    // The browser, volume, media and launch keys.
+    winuser::VK_BROWSER_BACK..=winuser::VK_LAUNCH_APP2 => true,
+    _ => kiosk::is_shell_shortcut(key),
+  }
+}
+
+unsafe extern "system" fn keyboard_hook(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+  if code == winuser::HC_ACTION {
+    let key = &*(lparam as *const winuser::KBDLLHOOKSTRUCT);
+    let foreground = winuser::GetForegroundWindow();
+    let grabbed = KEYBOARD_GRAB.with(|grab| grab.borrow().1.contains(&foreground));
+    if grabbed && is_grabbed(key) {
+      // The keyboard focus can be on a child window of the foreground window.
+      let focus = winuser::GetFocus();
+      let target = if focus.is_null() { foreground } else { focus };
+      // The repeat count, the scan code and the flags of the key messages.
+      let mut key_lparam = 1 | (key.scanCode & 0xff) << 16;
+      if key.flags & winuser::LLKHF_EXTENDED != 0 {
+        key_lparam |= 1 << 24;
+      }
+      if key.flags & winuser::LLKHF_ALTDOWN != 0 {
+        key_lparam |= 1 << 29;
+      }
+      if key.flags & winuser::LLKHF_UP != 0 {
+        key_lparam |= 3 << 30;
+      }
+      winuser::PostMessageW(
+        target,
+        wparam as UINT,
+        key.vkCode as WPARAM,
+        key_lparam as LPARAM,
+      );
+      return 1;
+    }
+  }
+
+  winuser::CallNextHookEx(ptr::null_mut(), code, wparam, lparam)
+}
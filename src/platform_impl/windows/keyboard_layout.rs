@@ -17,12 +17,59 @@ use winapi::{
 };
 
 use super::keyboard::ExScancode;
-use crate::keyboard::{Key, KeyCode, ModifiersState, NativeKeyCode};
+use crate::keyboard::{Key, KeyCode, KeyboardLayout, ModifiersState, NativeKeyCode};
 
 lazy_static! {
   pub(crate) static ref LAYOUT_CACHE: Mutex<LayoutCache> = Mutex::new(LayoutCache::default());
 }
 
+/// Identifies a layout by its input locale identifier, whose high word is the layout and low word
+/// the language.
+pub(crate) fn keyboard_layout(hkl: HKL) -> KeyboardLayout {
+  KeyboardLayout {
+    id: format!("{:08X}", hkl as usize as u32),
+  }
+}
+
+/// Returns the layout of the calling thread.
+pub fn current_keyboard_layout() -> Option<KeyboardLayout> {
+  Some(keyboard_layout(unsafe { winuser::GetKeyboardLayout(0) }))
+}
+
+/// Returns the key of a scancode in the current layout, without modifiers.
+pub(crate) fn key_from_scancode(scancode: u32) -> Option<Key<'static>> {
+  let mut layouts = LAYOUT_CACHE.lock().unwrap();
+  let (hkl, layout) = layouts.get_current_layout();
+  let vkey =
+    unsafe { winuser::MapVirtualKeyExW(scancode, winuser::MAPVK_VSC_TO_VK_EX, hkl as HKL) };
+  let num_lock_on = unsafe { winuser::GetKeyState(winuser::VK_NUMLOCK) & 1 != 0 };
+  match layout.get_key(
+    WindowsModifiers::empty(),
+    num_lock_on,
+    vkey as c_int,
+    scancode as ExScancode,
+    KeyCode::from_scancode(scancode),
+  ) {
+    Key::Unidentified(_) => None,
+    key => Some(key),
+  }
+}
+
+/// Returns the scancode of the key typing `key` in the current layout, without modifiers.
+pub(crate) fn scancode_from_key(key: &Key<'_>) -> Option<u32> {
+  let mut layouts = LAYOUT_CACHE.lock().unwrap();
+  let (_, layout) = layouts.get_current_layout();
+  layout
+    .keys
+    .get(&WindowsModifiers::empty())?
+    .iter()
+    .filter(|(_, layout_key)| *layout_key == key)
+    .map(|(keycode, _)| *keycode)
+    // The keys of the numpad type the same as other keys, which are preferred.
+    .min_by_key(|keycode| NUMPAD_KEYCODES.contains(keycode))
+    .and_then(KeyCode::to_scancode)
+}
+
 fn key_pressed(vkey: c_int) -> bool {
   unsafe { (winuser::GetKeyState(vkey) & (1 << 15)) == (1 << 15) }
 }
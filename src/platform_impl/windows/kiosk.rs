@@ -0,0 +1,79 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The shortcuts of the shell, like the Windows key or Alt+Tab, are handled before they reach
+// any window, so a low-level keyboard hook is the only way to block them for the kiosk windows.
+// Ctrl+Alt+Del is handled by the secure desktop and can't be blocked by applications.
+use std::{cell::Cell, ptr};
+
+use winapi::{
+  ctypes::c_int,
+  shared::{
+    minwindef::{LPARAM, LRESULT, WPARAM},
+    windef::HHOOK,
+  },
+  um::{libloaderapi, winuser},
+};
+
+thread_local! {
+  // The hook and the number of kiosk windows that need it.
+  static KEYBOARD_HOOK: Cell<(HHOOK, usize)> = Cell::new((ptr::null_mut(), 0));
+}
+
+// Must be called from the thread of the event loop, which dispatches the calls of the hook.
+pub fn block_shell_shortcuts() {
+  KEYBOARD_HOOK.with(|hook| {
+    let (mut handle, count) = hook.get();
+    if count == 0 {
+      handle = unsafe {
+        winuser::SetWindowsHookExW(
+          winuser::WH_KEYBOARD_LL,
+          Some(keyboard_hook),
+          libloaderapi::GetModuleHandleW(ptr::null()),
+          0,
+        )
+      };
+      if handle.is_null() {
+        warn!("Failed to block the shortcuts of the shell for the kiosk mode");
+      }
+    }
+    hook.set((handle, count + 1));
+  });
+}
+
+pub fn unblock_shell_shortcuts() {
+  KEYBOARD_HOOK.with(|hook| match hook.get() {
+    (_, 0) => (),
+    (handle, 1) => {
+      if !handle.is_null() {
+        unsafe { winuser::UnhookWindowsHookEx(handle) };
+      }
+      hook.set((ptr::null_mut(), 0));
+    }
+    (handle, count) => hook.set((handle, count - 1)),
+  });
+}
+
+/// Whether the key of a low-level keyboard hook is part of a shortcut of the shell.
+pub fn is_shell_shortcut(info: &winuser::KBDLLHOOKSTRUCT) -> bool {
+  let alt = info.flags & winuser::LLKHF_ALTDOWN != 0;
+  let ctrl = unsafe { winuser::GetAsyncKeyState(winuser::VK_CONTROL) } < 0;
+
+  match info.vkCode as c_int {
+    winuser::VK_LWIN | winuser::VK_RWIN => true,
+    // Alt+Tab and Ctrl+Alt+Tab switch between windows.
+    winuser::VK_TAB => alt,
+    // Alt+Esc cycles the windows and Ctrl+Esc opens the start menu.
+    winuser::VK_ESCAPE => alt || ctrl,
+    _ => false,
+  }
+}
+
+unsafe extern "system" fn keyboard_hook(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+  if code == winuser::HC_ACTION && is_shell_shortcut(&*(lparam as *const winuser::KBDLLHOOKSTRUCT))
+  {
+    return 1;
+  }
+
+  winuser::CallNextHookEx(ptr::null_mut(), code, wparam, lparam)
+}
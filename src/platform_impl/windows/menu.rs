@@ -11,13 +11,13 @@ use winapi::{
 
 use crate::{
   accelerator::Accelerator,
-  event::{Event, WindowEvent},
+  event::Event,
   keyboard::{KeyCode, ModifiersState},
   menu::{CustomMenuItem, MenuId, MenuItem, MenuType},
   window::WindowId as RootWindowId,
 };
 
-use super::{accelerator::register_accel, keyboard::key_to_vk, util::to_wstring, WindowId};
+use super::{accelerator::register_accel, keyboard::key_to_vk, util::to_wstring};
 
 #[derive(Copy, Clone)]
 struct AccelWrapper(winuser::ACCEL);
@@ -395,10 +395,8 @@ pub(crate) unsafe extern "system" fn subclass_proc(
           winuser::ShowWindow(hwnd, winuser::SW_HIDE);
         }
         CLOSE_ID => {
-          subclass_input.send_event(Event::WindowEvent {
-            window_id: RootWindowId(WindowId(hwnd)),
-            event: WindowEvent::CloseRequested,
-          });
+          // Sent to the window so its `CloseRequested` is created with the window's state.
+          winuser::PostMessageW(hwnd, winuser::WM_CLOSE, 0, 0);
         }
         QUIT_ID => {
           subclass_input.send_event(Event::LoopDestroyed);
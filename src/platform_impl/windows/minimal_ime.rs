@@ -1,93 +1,246 @@
-use std::mem::MaybeUninit;
+use std::{mem, ptr};
 
 use winapi::{
   shared::{
-    minwindef::{LPARAM, WPARAM},
-    windef::HWND,
+    minwindef::{BOOL, DWORD, LPARAM, LPVOID, WPARAM},
+    windef::{HWND, POINT, RECT},
   },
-  um::winuser,
+  um::{
+    imm::{self, CFS_POINT, COMPOSITIONFORM, HIMC},
+    winnt::LONG,
+    winuser,
+  },
+};
+
+use crate::{
+  event::{Ime, ImeUnderline},
+  platform_impl::platform::event_loop::ProcResult,
 };
 
-use crate::platform_impl::platform::event_loop::ProcResult;
+// The composition string, candidate window and context functions and constants, which `winapi`
+// doesn't have.
+#[link(name = "imm32")]
+extern "system" {
+  fn ImmGetCompositionStringW(himc: HIMC, index: DWORD, buf: LPVOID, len: DWORD) -> LONG;
+  fn ImmSetCandidateWindow(himc: HIMC, candidate: *mut CANDIDATEFORM) -> BOOL;
+  fn ImmAssociateContextEx(hwnd: HWND, himc: HIMC, flags: DWORD) -> BOOL;
+  fn ImmNotifyIME(himc: HIMC, action: DWORD, index: DWORD, value: DWORD) -> BOOL;
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct CANDIDATEFORM {
+  dwIndex: DWORD,
+  dwStyle: DWORD,
+  ptCurrentPos: POINT,
+  rcArea: RECT,
+}
+
+const CFS_EXCLUDE: DWORD = 0x0080;
+const IACE_DEFAULT: DWORD = 0x0010;
+const NI_COMPOSITIONSTR: DWORD = 0x0015;
+const CPS_CANCEL: DWORD = 0x0004;
+
+const GCS_COMPSTR: DWORD = 0x0008;
+const GCS_COMPATTR: DWORD = 0x0010;
+const GCS_COMPCLAUSE: DWORD = 0x0020;
+const GCS_CURSORPOS: DWORD = 0x0080;
+const GCS_RESULTSTR: DWORD = 0x0800;
+
+const ATTR_TARGET_CONVERTED: u8 = 0x01;
+const ATTR_TARGET_NOTCONVERTED: u8 = 0x03;
 
 pub fn is_msg_ime_related(msg_kind: u32) -> bool {
-  match msg_kind {
-    winuser::WM_IME_COMPOSITION
-    | winuser::WM_IME_COMPOSITIONFULL
-    | winuser::WM_IME_STARTCOMPOSITION
-    | winuser::WM_IME_ENDCOMPOSITION
-    | winuser::WM_IME_CHAR
-    | winuser::WM_CHAR
-    | winuser::WM_SYSCHAR => true,
-    _ => false,
-  }
+  matches!(
+    msg_kind,
+    winuser::WM_IME_COMPOSITION | winuser::WM_IME_STARTCOMPOSITION | winuser::WM_IME_ENDCOMPOSITION
+  )
 }
 
+#[derive(Default)]
 pub struct MinimalIme {
-  // True if we're currently receiving messages belonging to a finished IME session.
-  getting_ime_text: bool,
+  /// True while a composition is in progress, between `Ime::Enabled` and `Ime::Disabled`.
+  composing: bool,
 
-  utf16parts: Vec<u16>,
-}
-impl Default for MinimalIme {
-  fn default() -> Self {
-    MinimalIme {
-      getting_ime_text: false,
-      utf16parts: Vec::with_capacity(16),
-    }
-  }
+  /// True if the last preedit wasn't empty, so it's cleared when the composition ends.
+  has_preedit: bool,
 }
 impl MinimalIme {
   pub(crate) fn process_message(
     &mut self,
     hwnd: HWND,
     msg_kind: u32,
-    wparam: WPARAM,
-    _lparam: LPARAM,
+    _wparam: WPARAM,
+    lparam: LPARAM,
     result: &mut ProcResult,
-  ) -> Option<String> {
+  ) -> Vec<Ime> {
+    let mut events = Vec::new();
     match msg_kind {
-      winuser::WM_IME_ENDCOMPOSITION => {
-        self.getting_ime_text = true;
-      }
-      winuser::WM_CHAR | winuser::WM_SYSCHAR => {
-        if self.getting_ime_text {
-          *result = ProcResult::Value(0);
-          self.utf16parts.push(wparam as u16);
-
-          let more_char_coming;
-          unsafe {
-            let mut next_msg = MaybeUninit::uninit();
-            let has_message = winuser::PeekMessageW(
-              next_msg.as_mut_ptr(),
-              hwnd,
-              winuser::WM_KEYFIRST,
-              winuser::WM_KEYLAST,
-              winuser::PM_NOREMOVE,
-            );
-            let has_message = has_message != 0;
-            if !has_message {
-              more_char_coming = false;
-            } else {
-              let next_msg = next_msg.assume_init().message;
-              if next_msg == winuser::WM_CHAR || next_msg == winuser::WM_SYSCHAR {
-                more_char_coming = true;
-              } else {
-                more_char_coming = false;
-              }
-            }
+      winuser::WM_IME_STARTCOMPOSITION => self.enable(&mut events),
+      winuser::WM_IME_COMPOSITION => unsafe {
+        let himc = imm::ImmGetContext(hwnd);
+        if himc.is_null() {
+          return events;
+        }
+        self.enable(&mut events);
+        let flags = lparam as DWORD;
+        if flags & GCS_RESULTSTR != 0 {
+          if let Some(text) = composition_string(himc, GCS_RESULTSTR) {
+            self.clear_preedit(&mut events);
+            events.push(Ime::Commit(String::from_utf16_lossy(&text)));
+            // The default window procedure would send the text again with `WM_IME_CHAR`.
+            *result = ProcResult::Value(0);
           }
-          if !more_char_coming {
-            let result = String::from_utf16(&self.utf16parts).ok();
-            self.utf16parts.clear();
-            self.getting_ime_text = false;
-            return result;
+        }
+        if flags & GCS_COMPSTR != 0 {
+          if let Some(text) = composition_string(himc, GCS_COMPSTR) {
+            self.has_preedit = !text.is_empty();
+            events.push(preedit(himc, &text));
           }
         }
+        imm::ImmReleaseContext(hwnd, himc);
+      },
+      winuser::WM_IME_ENDCOMPOSITION => {
+        if self.composing {
+          self.clear_preedit(&mut events);
+          self.composing = false;
+          events.push(Ime::Disabled);
+        }
       }
       _ => (),
     }
+    events
+  }
+
+  fn enable(&mut self, events: &mut Vec<Ime>) {
+    if !self.composing {
+      self.composing = true;
+      events.push(Ime::Enabled);
+    }
+  }
 
+  fn clear_preedit(&mut self, events: &mut Vec<Ime>) {
+    if self.has_preedit {
+      self.has_preedit = false;
+      events.push(Ime::preedit(String::new()));
+    }
+  }
+}
+
+/// Places the composition at the top left of the cursor area and the candidate window next to it,
+/// the area being in physical client coordinates.
+pub(crate) fn set_cursor_area(hwnd: HWND, x: i32, y: i32, width: i32, height: i32) {
+  if unsafe { winuser::GetSystemMetrics(winuser::SM_IMMENABLED) } == 0 {
+    return;
+  }
+  unsafe {
+    let himc = imm::ImmGetContext(hwnd);
+    if himc.is_null() {
+      return;
+    }
+    let mut composition_form = COMPOSITIONFORM {
+      dwStyle: CFS_POINT,
+      ptCurrentPos: POINT { x, y },
+      rcArea: mem::zeroed(),
+    };
+    imm::ImmSetCompositionWindow(himc, &mut composition_form);
+    let mut candidate_form = CANDIDATEFORM {
+      dwIndex: 0,
+      dwStyle: CFS_EXCLUDE,
+      ptCurrentPos: POINT { x, y },
+      rcArea: RECT {
+        left: x,
+        top: y,
+        right: x + width,
+        bottom: y + height,
+      },
+    };
+    ImmSetCandidateWindow(himc, &mut candidate_form);
+    imm::ImmReleaseContext(hwnd, himc);
+  }
+}
+
+/// Associates the window with the default input context, or with none to disable the IME, the
+/// composition in progress being cancelled.
+pub(crate) fn set_ime_allowed(hwnd: HWND, allowed: bool) {
+  unsafe {
+    if allowed {
+      ImmAssociateContextEx(hwnd, ptr::null_mut(), IACE_DEFAULT);
+    } else {
+      let himc = imm::ImmGetContext(hwnd);
+      if !himc.is_null() {
+        ImmNotifyIME(himc, NI_COMPOSITIONSTR, CPS_CANCEL, 0);
+        imm::ImmReleaseContext(hwnd, himc);
+      }
+      ImmAssociateContextEx(hwnd, ptr::null_mut(), 0);
+    }
+  }
+}
+
+/// Returns the bytes of the part `index` of the composition.
+unsafe fn composition_data(himc: HIMC, index: DWORD) -> Option<Vec<u8>> {
+  let size = ImmGetCompositionStringW(himc, index, ptr::null_mut(), 0);
+  if size < 0 {
+    return None;
+  }
+  let mut data = vec![0u8; size as usize];
+  let size = ImmGetCompositionStringW(himc, index, data.as_mut_ptr() as LPVOID, size as DWORD);
+  if size < 0 {
+    return None;
+  }
+  data.truncate(size as usize);
+  Some(data)
+}
+
+/// Returns the UTF-16 code units of the string `index` of the composition.
+unsafe fn composition_string(himc: HIMC, index: DWORD) -> Option<Vec<u16>> {
+  let data = composition_data(himc, index)?;
+  Some(
+    data
+      .chunks_exact(2)
+      .map(|unit| u16::from_ne_bytes([unit[0], unit[1]]))
+      .collect(),
+  )
+}
+
+/// Builds the preedit of the composition string `text`, with its cursor and clauses.
+unsafe fn preedit(himc: HIMC, text: &[u16]) -> Ime {
+  // The offsets of the composition are in UTF-16 code units, the ones of the event in bytes.
+  let byte_offset = |offset: usize| String::from_utf16_lossy(&text[..offset.min(text.len())]).len();
+
+  let cursor = ImmGetCompositionStringW(himc, GCS_CURSORPOS, ptr::null_mut(), 0);
+  let cursor = if text.is_empty() || cursor < 0 {
     None
+  } else {
+    let offset = byte_offset(cursor as usize);
+    Some((offset, offset))
+  };
+
+  // The attributes are one byte per code unit, the clauses their offsets as `DWORD`s.
+  let attributes = composition_data(himc, GCS_COMPATTR).unwrap_or_default();
+  let mut clauses = composition_data(himc, GCS_COMPCLAUSE)
+    .unwrap_or_default()
+    .chunks_exact(4)
+    .map(|offset| u32::from_ne_bytes([offset[0], offset[1], offset[2], offset[3]]) as usize)
+    .collect::<Vec<_>>();
+  if clauses.len() < 2 {
+    clauses = vec![0, text.len()];
+  }
+  let underlines = clauses
+    .windows(2)
+    .filter(|clause| clause[0] < clause[1])
+    .map(|clause| ImeUnderline {
+      range: (byte_offset(clause[0]), byte_offset(clause[1])),
+      is_target: matches!(
+        attributes.get(clause[0]),
+        Some(&ATTR_TARGET_CONVERTED) | Some(&ATTR_TARGET_NOTCONVERTED)
+      ),
+    })
+    .collect();
+
+  Ime::Preedit {
+    text: String::from_utf16_lossy(text),
+    cursor,
+    underlines,
   }
 }
@@ -5,7 +5,10 @@
 
 use winapi::{
   self,
-  shared::windef::{HMENU, HWND},
+  shared::{
+    minwindef::DWORD,
+    windef::{HMENU, HWND},
+  },
 };
 
 pub use self::{
@@ -13,15 +16,22 @@ pub use self::{
   event_loop::{EventLoop, EventLoopProxy, EventLoopWindowTarget},
   global_shortcut::{GlobalShortcut, ShortcutManager},
   icon::WinIcon,
+  keyboard_layout::current_keyboard_layout,
   keycode::{keycode_from_scancode, keycode_to_scancode},
   menu::{Menu, MenuItemAttributes},
   monitor::{MonitorHandle, VideoMode},
-  window::{hit_test, Window},
+  virtual_desktop::VirtualDesktop,
+  window::{hit_test, CloseHandle, Window},
 };
 
 pub use self::icon::WinIcon as PlatformIcon;
 
-use crate::{event::DeviceId as RootDeviceId, icon::Icon, keyboard::Key, window::Theme};
+use crate::{
+  event::{DeviceId as RootDeviceId, DeviceInfo},
+  icon::Icon,
+  keyboard::Key,
+  window::Theme,
+};
 mod accelerator;
 mod global_shortcut;
 mod keycode;
@@ -49,6 +59,9 @@ pub struct PlatformSpecificWindowBuilderAttributes {
   pub no_redirection_bitmap: bool,
   pub drag_and_drop: bool,
   pub preferred_theme: Option<Theme>,
+  pub cloaked: bool,
+  pub class_name: String,
+  pub extra_styles: (DWORD, DWORD),
 }
 
 impl Default for PlatformSpecificWindowBuilderAttributes {
@@ -61,6 +74,9 @@ impl Default for PlatformSpecificWindowBuilderAttributes {
       drag_and_drop: true,
       preferred_theme: None,
       skip_taskbar: false,
+      cloaked: false,
+      class_name: "Window Class".to_string(),
+      extra_styles: (0, 0),
     }
   }
 }
@@ -91,6 +107,14 @@ impl DeviceId {
       None
     }
   }
+
+  pub fn info(&self) -> Option<DeviceInfo> {
+    if self.0 != 0 {
+      raw_input::get_device_info(self.0 as _)
+    } else {
+      None
+    }
+  }
 }
 
 #[non_exhaustive]
@@ -138,16 +162,26 @@ impl WindowId {
 
 #[macro_use]
 mod util;
+mod badge;
+mod capture;
 mod clipboard;
 mod dark_mode;
 mod dpi;
 mod drop_handler;
+mod effects;
 mod event_loop;
 mod icon;
 mod keyboard;
+mod keyboard_grab;
 mod keyboard_layout;
+mod kiosk;
 mod minimal_ime;
 mod monitor;
 mod raw_input;
+#[cfg(feature = "test")]
+mod synthetic_input;
+mod touch_keyboard;
+mod virtual_desktop;
+mod vsync;
 mod window;
 mod window_state;
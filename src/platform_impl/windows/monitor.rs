@@ -6,12 +6,13 @@ use winapi::{
     minwindef::{BOOL, DWORD, LPARAM, TRUE, WORD},
     windef::{HDC, HMONITOR, HWND, LPRECT, POINT},
   },
-  um::{wingdi, winuser},
+  um::{wingdi, winnt::LONG, winuser},
 };
 
 use std::{
   collections::{BTreeSet, VecDeque},
   io, mem, ptr,
+  sync::atomic::{AtomicBool, Ordering},
 };
 
 use super::util;
@@ -77,6 +78,10 @@ impl VideoMode {
     self.refresh_rate
   }
 
+  pub fn refresh_rate_millihertz(&self) -> u32 {
+    self.refresh_rate as u32 * 1000
+  }
+
   pub fn monitor(&self) -> RootMonitorHandle {
     RootMonitorHandle {
       inner: self.monitor.clone(),
@@ -94,6 +99,32 @@ pub struct MonitorHandle(HMONITOR);
 
 unsafe impl Send for MonitorHandle {}
 
+// Set while a monitor runs in a video mode set by exclusive fullscreen, so that the
+// original mode can be restored if the event loop exits or panics before the window
+// leaves fullscreen.
+static DISPLAY_MODE_CHANGED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_display_mode_changed() {
+  DISPLAY_MODE_CHANGED.store(true, Ordering::SeqCst);
+}
+
+/// Restores the display modes stored in the registry, if a video mode was changed.
+pub(crate) fn restore_display_mode() -> LONG {
+  if !DISPLAY_MODE_CHANGED.swap(false, Ordering::SeqCst) {
+    return winuser::DISP_CHANGE_SUCCESSFUL;
+  }
+
+  unsafe {
+    winuser::ChangeDisplaySettingsExW(
+      ptr::null_mut(),
+      ptr::null_mut(),
+      ptr::null_mut(),
+      winuser::CDS_FULLSCREEN,
+      ptr::null_mut(),
+    )
+  }
+}
+
 unsafe extern "system" fn monitor_enum_proc(
   hmonitor: HMONITOR,
   _hdc: HDC,
@@ -2,29 +2,47 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+  collections::HashMap,
   mem::{self, size_of},
-  ptr,
+  ops::RangeInclusive,
+  ptr, slice,
 };
 
 use winapi::{
   ctypes::wchar_t,
   shared::{
-    hidusage::{HID_USAGE_GENERIC_KEYBOARD, HID_USAGE_GENERIC_MOUSE, HID_USAGE_PAGE_GENERIC},
-    minwindef::{TRUE, UINT, USHORT},
+    hidpi::{
+      self, HIDP_CAPS_u, HidP_Input, HIDP_BUTTON_CAPS, HIDP_STATUS_SUCCESS, HIDP_VALUE_CAPS,
+    },
+    hidsdi,
+    hidusage::{
+      HID_USAGE_GENERIC_JOYSTICK, HID_USAGE_GENERIC_KEYBOARD, HID_USAGE_GENERIC_MOUSE,
+      HID_USAGE_PAGE_BUTTON, HID_USAGE_PAGE_GENERIC, USAGE,
+    },
+    minwindef::{BOOLEAN, TRUE, UINT, ULONG, USHORT},
     windef::HWND,
   },
   um::{
-    winnt::HANDLE,
+    fileapi::{self, OPEN_EXISTING},
+    handleapi::{self, INVALID_HANDLE_VALUE},
+    winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, HANDLE},
     winuser::{
       self, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE, RAWINPUTDEVICELIST, RAWINPUTHEADER,
-      RIDEV_DEVNOTIFY, RIDEV_INPUTSINK, RIDI_DEVICEINFO, RIDI_DEVICENAME, RID_DEVICE_INFO,
-      RID_DEVICE_INFO_HID, RID_DEVICE_INFO_KEYBOARD, RID_DEVICE_INFO_MOUSE, RID_INPUT, RIM_TYPEHID,
-      RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+      RIDEV_DEVNOTIFY, RIDEV_INPUTSINK, RIDEV_REMOVE, RIDI_DEVICEINFO, RIDI_DEVICENAME,
+      RIDI_PREPARSEDDATA, RID_DEVICE_INFO, RID_DEVICE_INFO_HID, RID_DEVICE_INFO_KEYBOARD,
+      RID_DEVICE_INFO_MOUSE, RID_INPUT, RIM_TYPEHID, RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
     },
   },
 };
 
-use crate::{event::ElementState, platform_impl::platform::util};
+use crate::{
+  event::{DeviceEvent, DeviceInfo, ElementState},
+  event_loop::DeviceEventFilter,
+  platform_impl::platform::util,
+};
+
+// The 3D mice, which hidusage doesn't define.
+const HID_USAGE_GENERIC_MULTI_AXIS_CONTROLLER: USAGE = 0x08;
 
 #[allow(dead_code)]
 pub fn get_raw_input_device_list() -> Option<Vec<RAWINPUTDEVICELIST>> {
@@ -75,7 +93,6 @@ impl From<RID_DEVICE_INFO> for RawDeviceInfo {
   }
 }
 
-#[allow(dead_code)]
 pub fn get_raw_input_device_info(handle: HANDLE) -> Option<RawDeviceInfo> {
   let mut info: RID_DEVICE_INFO = unsafe { mem::zeroed() };
   let info_size = size_of::<RID_DEVICE_INFO>() as UINT;
@@ -133,6 +150,52 @@ pub fn get_raw_input_device_name(handle: HANDLE) -> Option<String> {
   Some(util::wchar_to_string(&name))
 }
 
+/// The name and the USB ids of a device. The ids are only known for the HID devices, and the name
+/// for the devices of the HID class, mice and keyboards included.
+pub fn get_device_info(handle: HANDLE) -> Option<DeviceInfo> {
+  let (vendor_id, product_id) = match get_raw_input_device_info(handle)? {
+    RawDeviceInfo::Hid(hid) => (Some(hid.dwVendorId as u16), Some(hid.dwProductId as u16)),
+    _ => (None, None),
+  };
+  Some(DeviceInfo {
+    name: get_hid_product_string(handle),
+    vendor_id,
+    product_id,
+  })
+}
+
+fn get_hid_product_string(handle: HANDLE) -> Option<String> {
+  let path = util::to_wstring(&get_raw_input_device_name(handle)?);
+  unsafe {
+    // No access is needed to query the attributes of the device.
+    let file = fileapi::CreateFileW(
+      path.as_ptr(),
+      0,
+      FILE_SHARE_READ | FILE_SHARE_WRITE,
+      ptr::null_mut(),
+      OPEN_EXISTING,
+      0,
+      ptr::null_mut(),
+    );
+    if file == INVALID_HANDLE_VALUE {
+      return None;
+    }
+    // The strings of the HID devices have 126 characters at most.
+    let mut buffer = [0 as wchar_t; 127];
+    let success = hidsdi::HidD_GetProductString(
+      file,
+      buffer.as_mut_ptr() as _,
+      mem::size_of_val(&buffer) as ULONG,
+    );
+    handleapi::CloseHandle(file);
+    if success == 0 {
+      return None;
+    }
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(util::wchar_to_string(&buffer[..len]))
+  }
+}
+
 pub fn register_raw_input_devices(devices: &[RAWINPUTDEVICE]) -> bool {
   let device_size = size_of::<RAWINPUTDEVICE>() as UINT;
 
@@ -143,25 +206,37 @@ pub fn register_raw_input_devices(devices: &[RAWINPUTDEVICE]) -> bool {
   success == TRUE
 }
 
-pub fn register_all_mice_and_keyboards_for_raw_input(window_handle: HWND) -> bool {
+pub fn register_all_devices_for_raw_input(
+  mut window_handle: HWND,
+  filter: DeviceEventFilter,
+) -> bool {
   // RIDEV_DEVNOTIFY: receive hotplug events
   // RIDEV_INPUTSINK: receive events even if we're not in the foreground
-  let flags = RIDEV_DEVNOTIFY | RIDEV_INPUTSINK;
-
-  let devices: [RAWINPUTDEVICE; 2] = [
-    RAWINPUTDEVICE {
-      usUsagePage: HID_USAGE_PAGE_GENERIC,
-      usUsage: HID_USAGE_GENERIC_MOUSE,
-      dwFlags: flags,
-      hwndTarget: window_handle,
-    },
-    RAWINPUTDEVICE {
-      usUsagePage: HID_USAGE_PAGE_GENERIC,
-      usUsage: HID_USAGE_GENERIC_KEYBOARD,
-      dwFlags: flags,
-      hwndTarget: window_handle,
-    },
-  ];
+  // RIDEV_REMOVE: don't receive device events
+  let flags = match filter {
+    DeviceEventFilter::Always => {
+      window_handle = ptr::null_mut();
+      RIDEV_REMOVE
+    }
+    DeviceEventFilter::Unfocused => RIDEV_DEVNOTIFY,
+    DeviceEventFilter::Never => RIDEV_DEVNOTIFY | RIDEV_INPUTSINK,
+  };
+
+  // The gamepads are left to the gamepad APIs.
+  let devices: Vec<RAWINPUTDEVICE> = [
+    HID_USAGE_GENERIC_MOUSE,
+    HID_USAGE_GENERIC_KEYBOARD,
+    HID_USAGE_GENERIC_JOYSTICK,
+    HID_USAGE_GENERIC_MULTI_AXIS_CONTROLLER,
+  ]
+  .iter()
+  .map(|&usage| RAWINPUTDEVICE {
+    usUsagePage: HID_USAGE_PAGE_GENERIC,
+    usUsage: usage,
+    dwFlags: flags,
+    hwndTarget: window_handle,
+  })
+  .collect();
 
   register_raw_input_devices(&devices)
 }
@@ -188,6 +263,242 @@ pub fn get_raw_input_data(handle: HRAWINPUT) -> Option<RAWINPUT> {
   Some(data)
 }
 
+/// The reports of a HID device. A single message carries several reports when the device sends
+/// them faster than they are read.
+pub struct RawHidInput {
+  pub device: HANDLE,
+  pub reports: Vec<Vec<u8>>,
+}
+
+pub fn get_raw_hid_input(handle: HRAWINPUT) -> Option<RawHidInput> {
+  let header_size = size_of::<RAWINPUTHEADER>() as UINT;
+  let mut data_size = 0;
+  let status = unsafe {
+    winuser::GetRawInputData(
+      handle,
+      RID_INPUT,
+      ptr::null_mut(),
+      &mut data_size,
+      header_size,
+    )
+  };
+
+  if status != 0 {
+    return None;
+  }
+
+  // The reports follow the header, so the data is larger than `RAWINPUT`. The buffer is aligned
+  // for the header.
+  let mut buffer: Vec<u64> = vec![0; (data_size as usize + 7) / 8];
+  let status = unsafe {
+    winuser::GetRawInputData(
+      handle,
+      RID_INPUT,
+      buffer.as_mut_ptr() as _,
+      &mut data_size,
+      header_size,
+    )
+  };
+
+  if status == UINT::max_value() || status == 0 {
+    return None;
+  }
+
+  let data = unsafe { &*(buffer.as_ptr() as *const RAWINPUT) };
+  if data.header.dwType != RIM_TYPEHID {
+    return None;
+  }
+
+  let hid = unsafe { data.data.hid() };
+  let size = hid.dwSizeHid as usize;
+  if size == 0 {
+    return None;
+  }
+  let raw_data =
+    unsafe { slice::from_raw_parts(hid.bRawData.as_ptr(), size * hid.dwCount as usize) };
+
+  Some(RawHidInput {
+    device: data.header.hDevice,
+    reports: raw_data.chunks(size).map(<[u8]>::to_vec).collect(),
+  })
+}
+
+/// The axes of the generic desktop page and the buttons of a HID device, which turns its reports
+/// into device events.
+pub struct HidDevice {
+  // Opaque, but aligned like the structures it holds.
+  preparsed_data: Vec<u64>,
+  value_caps: Vec<HIDP_VALUE_CAPS>,
+  button_caps: Vec<HIDP_BUTTON_CAPS>,
+  values: HashMap<(USHORT, USAGE), i32>,
+  buttons: Vec<USAGE>,
+}
+
+impl HidDevice {
+  pub fn new(handle: HANDLE) -> Option<Self> {
+    let mut size = 0;
+    let status = unsafe {
+      winuser::GetRawInputDeviceInfoW(handle, RIDI_PREPARSEDDATA, ptr::null_mut(), &mut size)
+    };
+
+    if status != 0 {
+      return None;
+    }
+
+    let mut preparsed_data: Vec<u64> = vec![0; (size as usize + 7) / 8];
+    let status = unsafe {
+      winuser::GetRawInputDeviceInfoW(
+        handle,
+        RIDI_PREPARSEDDATA,
+        preparsed_data.as_mut_ptr() as _,
+        &mut size,
+      )
+    };
+
+    if status == UINT::max_value() || status == 0 {
+      return None;
+    }
+
+    unsafe {
+      let data = preparsed_data.as_mut_ptr() as _;
+      let mut caps = mem::zeroed();
+      if hidpi::HidP_GetCaps(data, &mut caps) != HIDP_STATUS_SUCCESS {
+        return None;
+      }
+
+      let mut value_caps = vec![mem::zeroed(); caps.NumberInputValueCaps as usize];
+      let mut len = caps.NumberInputValueCaps;
+      if len > 0
+        && hidpi::HidP_GetValueCaps(HidP_Input, value_caps.as_mut_ptr(), &mut len, data)
+          != HIDP_STATUS_SUCCESS
+      {
+        return None;
+      }
+      value_caps.truncate(len as usize);
+      value_caps.retain(|caps: &HIDP_VALUE_CAPS| caps.UsagePage == HID_USAGE_PAGE_GENERIC);
+
+      let mut button_caps = vec![mem::zeroed(); caps.NumberInputButtonCaps as usize];
+      let mut len = caps.NumberInputButtonCaps;
+      if len > 0
+        && hidpi::HidP_GetButtonCaps(HidP_Input, button_caps.as_mut_ptr(), &mut len, data)
+          != HIDP_STATUS_SUCCESS
+      {
+        return None;
+      }
+      button_caps.truncate(len as usize);
+      button_caps.retain(|caps: &HIDP_BUTTON_CAPS| caps.UsagePage == HID_USAGE_PAGE_BUTTON);
+
+      Some(HidDevice {
+        preparsed_data,
+        value_caps,
+        button_caps,
+        values: HashMap::new(),
+        buttons: Vec::new(),
+      })
+    }
+  }
+
+  /// The changes of the axes and the buttons in a report, as `Motion` and `Button` events.
+  pub fn parse_report(&mut self, report: &mut [u8]) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+    let data = self.preparsed_data.as_mut_ptr() as _;
+    let (report_ptr, report_len) = (report.as_mut_ptr() as _, report.len() as ULONG);
+
+    for caps in &self.value_caps {
+      for usage in caps_usages(caps.IsRange, &caps.u) {
+        let mut value: ULONG = 0;
+        let status = unsafe {
+          hidpi::HidP_GetUsageValue(
+            HidP_Input,
+            caps.UsagePage,
+            caps.LinkCollection,
+            usage,
+            &mut value,
+            data,
+            report_ptr,
+            report_len,
+          )
+        };
+        // The usages of the other reports of the device aren't found in this one.
+        if status != HIDP_STATUS_SUCCESS {
+          continue;
+        }
+        let value = logical_value(value, caps.BitSize, caps.LogicalMin < 0);
+        if self.values.insert((caps.LinkCollection, usage), value) != Some(value) {
+          events.push(DeviceEvent::Motion {
+            axis: usage as _,
+            value: value as f64,
+          });
+        }
+      }
+    }
+
+    let mut pressed = Vec::new();
+    let mut found = false;
+    for caps in &self.button_caps {
+      let mut len =
+        unsafe { hidpi::HidP_MaxUsageListLength(HidP_Input, HID_USAGE_PAGE_BUTTON, data) };
+      let mut usages: Vec<USAGE> = vec![0; len as usize];
+      let status = unsafe {
+        hidpi::HidP_GetUsages(
+          HidP_Input,
+          HID_USAGE_PAGE_BUTTON,
+          caps.LinkCollection,
+          usages.as_mut_ptr(),
+          &mut len,
+          data,
+          report_ptr,
+          report_len,
+        )
+      };
+      if status == HIDP_STATUS_SUCCESS {
+        found = true;
+        pressed.extend_from_slice(&usages[..len as usize]);
+      }
+    }
+
+    if found {
+      pressed.sort_unstable();
+      pressed.dedup();
+      for &button in self.buttons.iter().filter(|b| !pressed.contains(b)) {
+        events.push(DeviceEvent::Button {
+          button: button as _,
+          state: ElementState::Released,
+        });
+      }
+      for &button in pressed.iter().filter(|b| !self.buttons.contains(b)) {
+        events.push(DeviceEvent::Button {
+          button: button as _,
+          state: ElementState::Pressed,
+        });
+      }
+      self.buttons = pressed;
+    }
+
+    events
+  }
+}
+
+fn caps_usages(is_range: BOOLEAN, u: &HIDP_CAPS_u) -> RangeInclusive<USAGE> {
+  unsafe {
+    if is_range != 0 {
+      u.Range().UsageMin..=u.Range().UsageMax
+    } else {
+      u.NotRange().Usage..=u.NotRange().Usage
+    }
+  }
+}
+
+/// Sign-extends the value of an axis whose logical minimum is negative.
+fn logical_value(value: ULONG, bit_size: USHORT, signed: bool) -> i32 {
+  let shift = 32u32.saturating_sub(bit_size as u32);
+  if signed && shift > 0 {
+    ((value << shift) as i32) >> shift
+  } else {
+    value as i32
+  }
+}
+
 fn button_flags_to_element_state(
   button_flags: USHORT,
   down_flag: USHORT,
@@ -0,0 +1,192 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The synthetic input of `Window::inject_event`, posted as the messages of the real input so the
+// event loop translates them the same way. The keyboard state of the thread is updated with the
+// synthetic keys, which `TranslateMessage` and the modifiers read.
+use std::io;
+
+use winapi::{
+  ctypes::c_int,
+  shared::{
+    minwindef::{LPARAM, MAKELONG, UINT, WPARAM},
+    windef::{HWND, POINT},
+  },
+  um::winuser,
+};
+
+use crate::{
+  dpi::PhysicalPosition,
+  error::{ExternalError, NotSupportedError},
+  event::{ElementState, MouseButton},
+  test::SyntheticEvent,
+};
+
+// Must be called from the thread of the window, whose keyboard state is updated.
+pub unsafe fn inject_event(window: HWND, event: SyntheticEvent) -> Result<(), ExternalError> {
+  match event {
+    SyntheticEvent::Key {
+      physical_key,
+      state,
+    } => {
+      let scancode = physical_key.to_scancode().ok_or_else(not_supported)?;
+      let vkey = winuser::MapVirtualKeyW(scancode, winuser::MAPVK_VSC_TO_VK_EX) as c_int;
+      if vkey == 0 {
+        return Err(not_supported());
+      }
+      let pressed = state == ElementState::Pressed;
+      set_key_state(vkey, pressed);
+
+      // The repeat count, the scan code and the flags of the key messages.
+      let mut lparam = 1 | (scancode & 0xff) << 16;
+      if scancode & 0xe000 == 0xe000 {
+        lparam |= 1 << 24;
+      }
+      let alt = winuser::GetKeyState(winuser::VK_MENU) < 0;
+      if alt {
+        lparam |= 1 << 29;
+      }
+      if !pressed {
+        lparam |= 3 << 30;
+      }
+      let system = alt || generic_vkey(vkey) == winuser::VK_MENU;
+      let message = match (pressed, system) {
+        (true, false) => winuser::WM_KEYDOWN,
+        (false, false) => winuser::WM_KEYUP,
+        (true, true) => winuser::WM_SYSKEYDOWN,
+        (false, true) => winuser::WM_SYSKEYUP,
+      };
+      post(
+        window,
+        message,
+        generic_vkey(vkey) as WPARAM,
+        lparam as LPARAM,
+      )
+    }
+    SyntheticEvent::CursorMoved { position } => post(
+      window,
+      winuser::WM_MOUSEMOVE,
+      mouse_keys(),
+      position_lparam(position),
+    ),
+    SyntheticEvent::MouseInput {
+      button,
+      state,
+      position,
+    } => {
+      let pressed = state == ElementState::Pressed;
+      let (message, xbutton) = match (button, pressed) {
+        (MouseButton::Left, true) => (winuser::WM_LBUTTONDOWN, 0),
+        (MouseButton::Left, false) => (winuser::WM_LBUTTONUP, 0),
+        (MouseButton::Right, true) => (winuser::WM_RBUTTONDOWN, 0),
+        (MouseButton::Right, false) => (winuser::WM_RBUTTONUP, 0),
+        (MouseButton::Middle, true) => (winuser::WM_MBUTTONDOWN, 0),
+        (MouseButton::Middle, false) => (winuser::WM_MBUTTONUP, 0),
+        (button, pressed) => {
+          let xbutton = match button {
+            MouseButton::Back => winuser::XBUTTON1,
+            MouseButton::Forward => winuser::XBUTTON2,
+            MouseButton::Other(xbutton) => xbutton,
+            _ => return Err(not_supported()),
+          };
+          let message = if pressed {
+            winuser::WM_XBUTTONDOWN
+          } else {
+            winuser::WM_XBUTTONUP
+          };
+          (message, xbutton)
+        }
+      };
+      let wparam = MAKELONG(mouse_keys() as u16, xbutton) as u32 as WPARAM;
+      post(window, message, wparam, position_lparam(position))
+    }
+    SyntheticEvent::MouseWheel { lines } => {
+      // The position of the wheel messages is on the screen.
+      let mut cursor = POINT { x: 0, y: 0 };
+      winuser::GetCursorPos(&mut cursor);
+      let lparam = MAKELONG(cursor.x as u16, cursor.y as u16) as u32 as LPARAM;
+      for (message, lines) in [
+        (winuser::WM_MOUSEWHEEL, lines.1),
+        (winuser::WM_MOUSEHWHEEL, lines.0),
+      ] {
+        if lines != 0.0 {
+          let delta = (lines * winuser::WHEEL_DELTA as f32) as i16;
+          let wparam = MAKELONG(mouse_keys() as u16, delta as u16) as u32 as WPARAM;
+          post(window, message, wparam, lparam)?;
+        }
+      }
+      Ok(())
+    }
+    SyntheticEvent::Touch { .. } => Err(not_supported()),
+  }
+}
+
+fn not_supported() -> ExternalError {
+  ExternalError::NotSupported(NotSupportedError::new())
+}
+
+unsafe fn post(
+  window: HWND,
+  message: UINT,
+  wparam: WPARAM,
+  lparam: LPARAM,
+) -> Result<(), ExternalError> {
+  if winuser::PostMessageW(window, message, wparam, lparam) == 0 {
+    return Err(ExternalError::Os(os_error!(super::OsError::IoError(
+      io::Error::last_os_error()
+    ))));
+  }
+  Ok(())
+}
+
+/// The key of the messages, which don't tell the left and right modifiers apart.
+fn generic_vkey(vkey: c_int) -> c_int {
+  match vkey {
+    winuser::VK_LSHIFT | winuser::VK_RSHIFT => winuser::VK_SHIFT,
+    winuser::VK_LCONTROL | winuser::VK_RCONTROL => winuser::VK_CONTROL,
+    winuser::VK_LMENU | winuser::VK_RMENU => winuser::VK_MENU,
+    vkey => vkey,
+  }
+}
+
+/// Presses or releases `vkey` in the keyboard state of the thread, along with its generic modifier.
+unsafe fn set_key_state(vkey: c_int, pressed: bool) {
+  let mut keys = [0u8; 256];
+  if winuser::GetKeyboardState(keys.as_mut_ptr()) == 0 {
+    return;
+  }
+  let set = |keys: &mut [u8; 256], vkey: c_int, pressed: bool| {
+    if pressed {
+      keys[vkey as usize] |= 0x80;
+    } else {
+      keys[vkey as usize] &= !0x80;
+    }
+  };
+  set(&mut keys, vkey, pressed);
+  let sides = match generic_vkey(vkey) {
+    winuser::VK_SHIFT => Some((winuser::VK_LSHIFT, winuser::VK_RSHIFT)),
+    winuser::VK_CONTROL => Some((winuser::VK_LCONTROL, winuser::VK_RCONTROL)),
+    winuser::VK_MENU => Some((winuser::VK_LMENU, winuser::VK_RMENU)),
+    _ => None,
+  };
+  if let Some((left, right)) = sides {
+    let pressed = (keys[left as usize] | keys[right as usize]) & 0x80 != 0;
+    set(&mut keys, generic_vkey(vkey), pressed);
+  }
+  winuser::SetKeyboardState(keys.as_mut_ptr());
+}
+
+/// The modifiers and the buttons of the mouse messages, from the keyboard state.
+unsafe fn mouse_keys() -> WPARAM {
+  [
+    (winuser::VK_SHIFT, winuser::MK_SHIFT),
+    (winuser::VK_CONTROL, winuser::MK_CONTROL),
+  ]
+  .iter()
+  .filter(|(vkey, _)| winuser::GetKeyState(*vkey) < 0)
+  .fold(0, |keys, (_, mk)| keys | *mk as WPARAM)
+}
+
+fn position_lparam(position: PhysicalPosition<f64>) -> LPARAM {
+  MAKELONG(position.x as i16 as u16, position.y as i16 as u16) as u32 as LPARAM
+}
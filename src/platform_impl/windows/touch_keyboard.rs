@@ -0,0 +1,322 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The touch keyboard of `WindowExtWindows::show_touch_keyboard`. There is no API to show it for the
+// windows which aren't text controls of the system, so it's toggled with the `ITipInvocation` of
+// the process of the touch keyboard, started first if it isn't running. `IFrameworkInputPane` tells
+// where the keyboard is shown, and when it covers the windows.
+#![allow(non_snake_case)]
+
+use std::{
+  env,
+  ffi::OsString,
+  path::PathBuf,
+  ptr,
+  sync::atomic::{AtomicUsize, Ordering},
+  time::Instant,
+};
+
+use winapi::{
+  shared::{
+    guiddef::REFIID,
+    minwindef::{BOOL, DWORD, ULONG},
+    ntdef::HRESULT,
+    windef::{HWND, POINT, RECT},
+    winerror::{E_NOINTERFACE, S_OK},
+  },
+  um::{
+    combaseapi::{CoCreateInstance, CLSCTX_ALL},
+    shellapi::ShellExecuteW,
+    unknwnbase::{IUnknown, IUnknownVtbl},
+    winuser,
+  },
+  Interface, DEFINE_GUID, RIDL,
+};
+
+use crate::{
+  dpi::{PhysicalPosition, PhysicalSize},
+  event::{Event, Rectangle, WindowEvent},
+  platform_impl::platform::{util, window::com_initialized, WindowId},
+  window::WindowId as RootWindowId,
+};
+
+RIDL! {#[uuid(0x37c994e7, 0x432b, 0x4834, 0xa2, 0xf7, 0xdc, 0xe1, 0xf1, 0x3b, 0x83, 0x4b)]
+interface ITipInvocation(ITipInvocationVtbl): IUnknown(IUnknownVtbl) {
+  fn Toggle(
+    wnd: HWND,
+  ) -> HRESULT,
+}}
+
+RIDL! {#[uuid(0x226c537b, 0x1e76, 0x4d9e, 0xa7, 0x60, 0x33, 0xdb, 0x29, 0x92, 0x2f, 0x18)]
+interface IFrameworkInputPaneHandler(IFrameworkInputPaneHandlerVtbl): IUnknown(IUnknownVtbl) {
+  fn Showing(
+    prcInputPaneScreenLocation: *mut RECT,
+    fEnsureFocusedElementInView: BOOL,
+  ) -> HRESULT,
+  fn Hiding(
+    fEnsureFocusedElementInView: BOOL,
+  ) -> HRESULT,
+}}
+
+RIDL! {#[uuid(0x5752238b, 0x24f2, 0x4484, 0xbc, 0xe8, 0x30, 0xa1, 0xf2, 0xe5, 0xef, 0x2d)]
+interface IFrameworkInputPane(IFrameworkInputPaneVtbl): IUnknown(IUnknownVtbl) {
+  fn Advise(
+    pWindow: *mut IUnknown,
+    pHandler: *mut IFrameworkInputPaneHandler,
+    pdwCookie: *mut DWORD,
+  ) -> HRESULT,
+  fn AdviseWithHWND(
+    hwnd: HWND,
+    pHandler: *mut IFrameworkInputPaneHandler,
+    pdwCookie: *mut DWORD,
+  ) -> HRESULT,
+  fn Unadvise(
+    dwCookie: DWORD,
+  ) -> HRESULT,
+  fn Location(
+    prcInputPaneScreenLocation: *mut RECT,
+  ) -> HRESULT,
+}}
+
+DEFINE_GUID! {CLSID_UIHostNoLaunch,
+0x4ce576fa, 0x83dc, 0x4f88, 0x95, 0x1c, 0x9d, 0x07, 0x82, 0xb4, 0xe3, 0x76}
+
+DEFINE_GUID! {CLSID_FrameworkInputPane,
+0xd5120aa3, 0x46ba, 0x44c5, 0x82, 0x2d, 0xca, 0x80, 0x92, 0xc1, 0xfc, 0x72}
+
+/// Shows or hides the touch keyboard. Must be called on a thread of the windows.
+pub fn set_touch_keyboard_visible(visible: bool) {
+  unsafe {
+    com_initialized();
+    if visible == is_touch_keyboard_visible() {
+      return;
+    }
+
+    let mut tip: *mut ITipInvocation = ptr::null_mut();
+    let hr = CoCreateInstance(
+      &CLSID_UIHostNoLaunch,
+      ptr::null_mut(),
+      CLSCTX_ALL,
+      &ITipInvocation::uuidof(),
+      &mut tip as *mut _ as *mut _,
+    );
+    if hr == S_OK && !tip.is_null() {
+      (*tip).Toggle(winuser::GetDesktopWindow());
+      (*tip).Release();
+    } else if visible {
+      // The process of the touch keyboard isn't running, and shows the keyboard when it starts.
+      let path = util::to_wstring(&tabtip_path().to_string_lossy());
+      let operation = util::to_wstring("open");
+      ShellExecuteW(
+        ptr::null_mut(),
+        operation.as_ptr(),
+        path.as_ptr(),
+        ptr::null(),
+        ptr::null(),
+        winuser::SW_SHOWNORMAL,
+      );
+    }
+  }
+}
+
+/// The executable of the touch keyboard, which only exists in the 64-bit program files on 64-bit
+/// systems.
+fn tabtip_path() -> PathBuf {
+  let common_files = env::var_os("CommonProgramW6432")
+    .or_else(|| env::var_os("CommonProgramFiles"))
+    .unwrap_or_else(|| OsString::from("C:\\Program Files\\Common Files"));
+  PathBuf::from(common_files).join("microsoft shared\\ink\\TabTip.exe")
+}
+
+unsafe fn input_pane() -> Option<*mut IFrameworkInputPane> {
+  let mut input_pane: *mut IFrameworkInputPane = ptr::null_mut();
+  let hr = CoCreateInstance(
+    &CLSID_FrameworkInputPane,
+    ptr::null_mut(),
+    CLSCTX_ALL,
+    &IFrameworkInputPane::uuidof(),
+    &mut input_pane as *mut _ as *mut _,
+  );
+  // The input pane was introduced in Windows 8
+  if hr != S_OK || input_pane.is_null() {
+    None
+  } else {
+    Some(input_pane)
+  }
+}
+
+unsafe fn is_touch_keyboard_visible() -> bool {
+  match input_pane() {
+    Some(input_pane) => {
+      let mut rect: RECT = std::mem::zeroed();
+      let hr = (*input_pane).Location(&mut rect);
+      (*input_pane).Release();
+      hr == S_OK && rect.right > rect.left && rect.bottom > rect.top
+    }
+    None => false,
+  }
+}
+
+/// Sends `WindowEvent::TouchKeyboardOcclusion` to a window while it's alive.
+pub struct TouchKeyboardHandler {
+  input_pane: *mut IFrameworkInputPane,
+  cookie: DWORD,
+  data: *mut TouchKeyboardHandlerData,
+}
+
+#[repr(C)]
+struct TouchKeyboardHandlerData {
+  interface: IFrameworkInputPaneHandler,
+  refcount: AtomicUsize,
+  window: HWND,
+  send_event: Box<dyn Fn(Event<'static, ()>)>,
+  /// Whether the window was covered by the keyboard.
+  occluded: bool,
+}
+
+impl TouchKeyboardHandler {
+  /// Returns `None` if the system has no touch keyboard.
+  pub fn new(window: HWND, send_event: Box<dyn Fn(Event<'static, ()>)>) -> Option<Self> {
+    unsafe {
+      com_initialized();
+      let input_pane = input_pane()?;
+      let data = Box::into_raw(Box::new(TouchKeyboardHandlerData {
+        interface: IFrameworkInputPaneHandler {
+          lpVtbl: &HANDLER_VTBL as *const IFrameworkInputPaneHandlerVtbl,
+        },
+        refcount: AtomicUsize::new(1),
+        window,
+        send_event,
+        occluded: false,
+      }));
+      let mut cookie = 0;
+      let hr = (*input_pane).AdviseWithHWND(window, &mut (*data).interface, &mut cookie);
+      if hr != S_OK {
+        (*input_pane).Release();
+        Self::Release(data as *mut IUnknown);
+        return None;
+      }
+      Some(TouchKeyboardHandler {
+        input_pane,
+        cookie,
+        data,
+      })
+    }
+  }
+
+  unsafe extern "system" fn QueryInterface(
+    this: *mut IUnknown,
+    riid: REFIID,
+    ppvObject: *mut *mut winapi::ctypes::c_void,
+  ) -> HRESULT {
+    use winapi::shared::guiddef::IsEqualIID;
+    if IsEqualIID(&*riid, &IUnknown::uuidof())
+      || IsEqualIID(&*riid, &IFrameworkInputPaneHandler::uuidof())
+    {
+      Self::AddRef(this);
+      *ppvObject = this as *mut _;
+      S_OK
+    } else {
+      *ppvObject = ptr::null_mut();
+      E_NOINTERFACE
+    }
+  }
+
+  unsafe extern "system" fn AddRef(this: *mut IUnknown) -> ULONG {
+    let data = Self::from_interface(this);
+    let count = data.refcount.fetch_add(1, Ordering::Release) + 1;
+    count as ULONG
+  }
+
+  unsafe extern "system" fn Release(this: *mut IUnknown) -> ULONG {
+    let data = Self::from_interface(this);
+    let count = data.refcount.fetch_sub(1, Ordering::Release) - 1;
+    if count == 0 {
+      Box::from_raw(data as *mut TouchKeyboardHandlerData);
+    }
+    count as ULONG
+  }
+
+  unsafe extern "system" fn Showing(
+    this: *mut IFrameworkInputPaneHandler,
+    prcInputPaneScreenLocation: *mut RECT,
+    _fEnsureFocusedElementInView: BOOL,
+  ) -> HRESULT {
+    let data = Self::from_interface(this);
+    let area = occluded_area(data.window, &*prcInputPaneScreenLocation);
+    // The keyboard is moved or resized while it's shown.
+    if area.is_some() || data.occluded {
+      data.occluded = area.is_some();
+      data.send(area);
+    }
+    S_OK
+  }
+
+  unsafe extern "system" fn Hiding(
+    this: *mut IFrameworkInputPaneHandler,
+    _fEnsureFocusedElementInView: BOOL,
+  ) -> HRESULT {
+    let data = Self::from_interface(this);
+    if data.occluded {
+      data.occluded = false;
+      data.send(None);
+    }
+    S_OK
+  }
+
+  unsafe fn from_interface<'a, InterfaceT>(
+    this: *mut InterfaceT,
+  ) -> &'a mut TouchKeyboardHandlerData {
+    &mut *(this as *mut _)
+  }
+}
+
+impl TouchKeyboardHandlerData {
+  fn send(&self, area: Option<Rectangle>) {
+    (self.send_event)(Event::WindowEvent {
+      window_id: RootWindowId(WindowId(self.window)),
+      event: WindowEvent::TouchKeyboardOcclusion(area),
+      timestamp: Instant::now(),
+    });
+  }
+}
+
+impl Drop for TouchKeyboardHandler {
+  fn drop(&mut self) {
+    unsafe {
+      (*self.input_pane).Unadvise(self.cookie);
+      (*self.input_pane).Release();
+      Self::Release(self.data as *mut IUnknown);
+    }
+  }
+}
+
+/// The part of the client area of `window` covered by the keyboard at `keyboard`, on the screen.
+unsafe fn occluded_area(window: HWND, keyboard: &RECT) -> Option<Rectangle> {
+  let mut client: RECT = std::mem::zeroed();
+  winuser::GetClientRect(window, &mut client);
+  let mut origin = POINT { x: 0, y: 0 };
+  winuser::ClientToScreen(window, &mut origin);
+  winuser::OffsetRect(&mut client, origin.x, origin.y);
+  let mut area: RECT = std::mem::zeroed();
+  if winuser::IntersectRect(&mut area, &client, keyboard) == 0 {
+    return None;
+  }
+  Some(Rectangle {
+    position: PhysicalPosition::new((area.left - origin.x) as f64, (area.top - origin.y) as f64),
+    size: PhysicalSize::new(
+      (area.right - area.left) as f64,
+      (area.bottom - area.top) as f64,
+    ),
+  })
+}
+
+static HANDLER_VTBL: IFrameworkInputPaneHandlerVtbl = IFrameworkInputPaneHandlerVtbl {
+  parent: IUnknownVtbl {
+    QueryInterface: TouchKeyboardHandler::QueryInterface,
+    AddRef: TouchKeyboardHandler::AddRef,
+    Release: TouchKeyboardHandler::Release,
+  },
+  Showing: TouchKeyboardHandler::Showing,
+  Hiding: TouchKeyboardHandler::Hiding,
+};
@@ -14,7 +14,7 @@ use winapi::{
   ctypes::wchar_t,
   shared::{
     minwindef::{BOOL, DWORD, TRUE, UINT},
-    windef::{DPI_AWARENESS_CONTEXT, HICON, HMONITOR, HWND, LPRECT, RECT},
+    windef::{DPI_AWARENESS_CONTEXT, HICON, HMONITOR, HWND, LPRECT, POINT, RECT},
   },
   um::{
     libloaderapi::{GetProcAddress, LoadLibraryA},
@@ -176,6 +176,13 @@ pub fn get_cursor_clip() -> Result<RECT, io::Error> {
   }
 }
 
+pub fn get_cursor_position() -> Result<POINT, io::Error> {
+  unsafe {
+    let mut point: POINT = mem::zeroed();
+    win_to_err(|| winuser::GetCursorPos(&mut point)).map(|_| point)
+  }
+}
+
 /// Sets the cursor's clip rect.
 ///
 /// Note that calling this will automatically dispatch a `WM_MOUSEMOVE` event.
@@ -0,0 +1,195 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// `IVirtualDesktopManager` can only find and move the windows of this process, and there is no API
+// to list the virtual desktops, so Explorer's registry keys are read for them.
+#![allow(non_snake_case)]
+
+use std::{io, mem, ptr};
+
+use winapi::{
+  shared::{
+    guiddef::{GUID, REFGUID},
+    minwindef::{BOOL, DWORD},
+    ntdef::HRESULT,
+    windef::HWND,
+    winerror::{ERROR_SUCCESS, S_OK},
+  },
+  um::{
+    combaseapi::{CoCreateInstance, CLSCTX_ALL},
+    processthreadsapi::{GetCurrentProcessId, ProcessIdToSessionId},
+    unknwnbase::{IUnknown, IUnknownVtbl},
+    winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_BINARY},
+  },
+  Interface, DEFINE_GUID, RIDL,
+};
+
+use crate::platform_impl::platform::{util, window::com_initialized};
+
+RIDL! {#[uuid(0xa5cd92ff, 0x29be, 0x454c, 0x8d, 0x04, 0xd8, 0x28, 0x79, 0xfb, 0x3f, 0x1b)]
+interface IVirtualDesktopManager(IVirtualDesktopManagerVtbl): IUnknown(IUnknownVtbl) {
+  fn IsWindowOnCurrentVirtualDesktop(
+    topLevelWindow: HWND,
+    onCurrentDesktop: *mut BOOL,
+  ) -> HRESULT,
+  fn GetWindowDesktopId(
+    topLevelWindow: HWND,
+    desktopId: *mut GUID,
+  ) -> HRESULT,
+  fn MoveWindowToDesktop(
+    topLevelWindow: HWND,
+    desktopId: REFGUID,
+  ) -> HRESULT,
+}}
+
+DEFINE_GUID! {CLSID_VirtualDesktopManager,
+0xaa509086, 0x5ca9, 0x4c25, 0x8f, 0x95, 0x58, 0x9d, 0x3c, 0x07, 0xb4, 0x8a}
+
+const VIRTUAL_DESKTOPS_KEY: &str =
+  "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\VirtualDesktops";
+
+/// The GUID of a virtual desktop, in the byte order of the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VirtualDesktop([u8; 16]);
+
+impl VirtualDesktop {
+  fn from_bytes(bytes: &[u8]) -> Self {
+    let mut id = [0; 16];
+    id.copy_from_slice(bytes);
+    VirtualDesktop(id)
+  }
+
+  fn from_guid(guid: GUID) -> Self {
+    VirtualDesktop(unsafe { mem::transmute(guid) })
+  }
+
+  fn guid(&self) -> GUID {
+    unsafe { mem::transmute(self.0) }
+  }
+}
+
+pub fn virtual_desktops() -> Vec<VirtualDesktop> {
+  registry_value(VIRTUAL_DESKTOPS_KEY, "VirtualDesktopIDs")
+    .map(|ids| {
+      ids
+        .chunks_exact(16)
+        .map(VirtualDesktop::from_bytes)
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+pub fn current_virtual_desktop() -> Option<VirtualDesktop> {
+  // Windows 11 keeps the current desktop along with the list, Windows 10 in the key of the session
+  let session_key = unsafe {
+    let mut session_id: DWORD = 0;
+    ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id);
+    format!(
+      "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\SessionInfo\\{}\\VirtualDesktops",
+      session_id
+    )
+  };
+
+  registry_value(VIRTUAL_DESKTOPS_KEY, "CurrentVirtualDesktop")
+    .or_else(|| registry_value(&session_key, "CurrentVirtualDesktop"))
+    .filter(|id| id.len() == 16)
+    .map(|id| VirtualDesktop::from_bytes(&id))
+}
+
+pub fn window_virtual_desktop(hwnd: HWND) -> Option<VirtualDesktop> {
+  with_manager(|manager| unsafe {
+    let mut guid: GUID = mem::zeroed();
+    if manager.GetWindowDesktopId(hwnd, &mut guid) != S_OK {
+      return None;
+    }
+    // Windows that were never shown aren't on any desktop yet
+    let desktop = VirtualDesktop::from_guid(guid);
+    if desktop.0 == [0; 16] {
+      None
+    } else {
+      Some(desktop)
+    }
+  })
+}
+
+pub fn is_on_current_virtual_desktop(hwnd: HWND) -> bool {
+  with_manager(|manager| unsafe {
+    let mut on_current_desktop: BOOL = 0;
+    if manager.IsWindowOnCurrentVirtualDesktop(hwnd, &mut on_current_desktop) == S_OK {
+      Some(on_current_desktop != 0)
+    } else {
+      None
+    }
+  })
+  .unwrap_or(true)
+}
+
+pub fn move_to_virtual_desktop(hwnd: HWND, desktop: VirtualDesktop) -> Result<(), io::Error> {
+  let hr =
+    with_manager(|manager| unsafe { Some(manager.MoveWindowToDesktop(hwnd, &desktop.guid())) })
+      .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "No virtual desktop manager"))?;
+  if hr == S_OK {
+    Ok(())
+  } else {
+    Err(io::Error::from_raw_os_error(hr))
+  }
+}
+
+fn with_manager<T, F: FnOnce(&IVirtualDesktopManager) -> Option<T>>(f: F) -> Option<T> {
+  unsafe {
+    com_initialized();
+
+    let mut manager: *mut IVirtualDesktopManager = ptr::null_mut();
+    let hr = CoCreateInstance(
+      &CLSID_VirtualDesktopManager,
+      ptr::null_mut(),
+      CLSCTX_ALL,
+      &IVirtualDesktopManager::uuidof(),
+      &mut manager as *mut _ as *mut _,
+    );
+    // Virtual desktops were introduced in Windows 10
+    if hr != S_OK || manager.is_null() {
+      return None;
+    }
+
+    let result = f(&*manager);
+    (*manager).Release();
+    result
+  }
+}
+
+fn registry_value(key: &str, value: &str) -> Option<Vec<u8>> {
+  let key = util::to_wstring(key);
+  let value = util::to_wstring(value);
+  unsafe {
+    let mut size: DWORD = 0;
+    let status = RegGetValueW(
+      HKEY_CURRENT_USER,
+      key.as_ptr(),
+      value.as_ptr(),
+      RRF_RT_REG_BINARY,
+      ptr::null_mut(),
+      ptr::null_mut(),
+      &mut size,
+    );
+    if status != ERROR_SUCCESS as _ {
+      return None;
+    }
+
+    let mut data = vec![0; size as usize];
+    let status = RegGetValueW(
+      HKEY_CURRENT_USER,
+      key.as_ptr(),
+      value.as_ptr(),
+      RRF_RT_REG_BINARY,
+      ptr::null_mut(),
+      data.as_mut_ptr() as _,
+      &mut size,
+    );
+    if status != ERROR_SUCCESS as _ {
+      return None;
+    }
+    data.truncate(size as usize);
+    Some(data)
+  }
+}
@@ -0,0 +1,75 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+// The redraws of the windows with `RedrawPacing::VSync` are asked by a thread that waits for the
+// frames of the desktop compositor, so their `WM_PAINT` messages, and the `RedrawRequested` events
+// sent for them, follow the refreshes of the monitor.
+use std::{mem, ptr, sync::Once, thread, time::Duration};
+
+use parking_lot::{Condvar, Mutex};
+use winapi::{
+  shared::windef::HWND,
+  um::{dwmapi, winuser},
+};
+
+/// The frame time used when the desktop composition is disabled, and `DwmFlush` fails.
+const FALLBACK_FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+lazy_static! {
+  static ref PACER: Pacer = Pacer {
+    windows: Mutex::new(Vec::new()),
+    requested: Condvar::new(),
+  };
+}
+
+static START: Once = Once::new();
+
+struct Pacer {
+  /// The windows to redraw at the next frame, as `usize` since `HWND` can't be sent to the thread.
+  windows: Mutex<Vec<usize>>,
+  requested: Condvar,
+}
+
+/// Redraws `hwnd` at the next frame of the compositor. Can be called from any thread.
+pub fn request_redraw(hwnd: HWND) {
+  START.call_once(|| {
+    thread::Builder::new()
+      .name("tao vsync".to_owned())
+      .spawn(run)
+      .expect("Failed to spawn the vsync thread");
+  });
+
+  let mut windows = PACER.windows.lock();
+  if !windows.contains(&(hwnd as usize)) {
+    windows.push(hwnd as usize);
+  }
+  PACER.requested.notify_one();
+}
+
+fn run() {
+  loop {
+    {
+      let mut windows = PACER.windows.lock();
+      while windows.is_empty() {
+        PACER.requested.wait(&mut windows);
+      }
+    }
+
+    if unsafe { dwmapi::DwmFlush() } < 0 {
+      thread::sleep(FALLBACK_FRAME_TIME);
+    }
+
+    let windows = mem::take(&mut *PACER.windows.lock());
+    for hwnd in windows {
+      // Fails harmlessly if the window was destroyed in the meantime.
+      unsafe {
+        winuser::RedrawWindow(
+          hwnd as HWND,
+          ptr::null(),
+          ptr::null_mut(),
+          winuser::RDW_INTERNALPAINT,
+        );
+      }
+    }
+  }
+}
@@ -6,25 +6,25 @@
 use mem::MaybeUninit;
 use parking_lot::Mutex;
 use raw_window_handle::{windows::WindowsHandle, RawWindowHandle};
-use std::{cell::Cell, ffi::OsStr, io, mem, os::windows::ffi::OsStrExt, ptr, sync::Arc};
+use std::{
+  cell::Cell, ffi::OsStr, io, mem, os::windows::ffi::OsStrExt, ptr, sync::Arc, time::Duration,
+};
 
 use crossbeam_channel as channel;
 use winapi::{
   ctypes::c_int,
   shared::{
     basetsd::LONG_PTR,
-    minwindef::{HINSTANCE, LPARAM, LRESULT, UINT, WPARAM},
+    minwindef::{BOOL, DWORD, HINSTANCE, LPARAM, LRESULT, UINT, WPARAM},
     windef::{self, HWND, POINT, POINTS, RECT},
   },
   um::{
     combaseapi::{self, CoCreateInstance, CLSCTX_SERVER},
-    dwmapi,
-    imm::{CFS_POINT, COMPOSITIONFORM},
-    libloaderapi,
+    dwmapi, libloaderapi,
     objbase::COINIT_APARTMENTTHREADED,
     ole2,
     oleidl::LPDROPTARGET,
-    shobjidl_core::{CLSID_TaskbarList, ITaskbarList, ITaskbarList2},
+    shobjidl_core::{self, CLSID_TaskbarList, ITaskbarList, ITaskbarList3},
     wingdi::{CreateRectRgn, DeleteObject},
     winnt::{LPCWSTR, SHORT},
     winuser,
@@ -35,22 +35,35 @@ use winapi::{
 use crate::{
   dpi::{PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
+  event::FullscreenTransition,
   icon::Icon,
   menu::MenuType,
-  monitor::MonitorHandle as RootMonitorHandle,
+  monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
+  platform::windows::CornerPreference,
   platform_impl::platform::{
+    badge, capture,
     dark_mode::try_theme,
     dpi::{dpi_to_scale_factor, hwnd_dpi},
     drop_handler::FileDropHandler,
-    event_loop::{self, EventLoopWindowTarget, DESTROY_MSG_ID},
+    effects,
+    event_loop::{
+      self, dur2timeout, EventLoopWindowTarget, CLOSE_RESPONSE_MSG_ID, DESTROY_MSG_ID,
+      FULLSCREEN_TRANSITION_MSG_ID,
+    },
     icon::{self, IconType},
-    menu, monitor, util,
+    keyboard_grab, kiosk, menu, minimal_ime, monitor,
+    touch_keyboard::{self, TouchKeyboardHandler},
+    util,
+    virtual_desktop::{self, VirtualDesktop},
+    vsync,
     window_state::{CursorFlags, SavedWindow, WindowFlags, WindowState},
     OsError, Parent, PlatformSpecificWindowBuilderAttributes, WindowId,
   },
   window::{
-    CursorIcon, Fullscreen, Theme, UserAttentionType, WindowAttributes, WindowId as RootWindowId,
-    BORDERLESS_RESIZE_INSET,
+    AspectRatio, Color, CursorGrabMode, CursorIcon, DragRegion, DragRegionKind, Edges, Effect,
+    Fullscreen, ProgressState, RedrawPacing, ResizeDirection, Theme, TitlebarButtons,
+    UserAttentionType, WindowAttributes, WindowId as RootWindowId, WindowImage, WindowLevel,
+    WindowType, BORDERLESS_RESIZE_INSET,
   },
 };
 
@@ -122,10 +135,21 @@ impl Window {
           None
         };
 
+        let touch_keyboard_runner = event_loop.runner_shared.clone();
+        let touch_keyboard_handler = TouchKeyboardHandler::new(
+          win.window.0,
+          Box::new(move |event| {
+            if let Ok(e) = event.map_nonuser_event() {
+              touch_keyboard_runner.send_event(e)
+            }
+          }),
+        );
+
         let subclass_input = event_loop::SubclassInput {
           window_state: win.window_state.clone(),
           event_loop_runner: event_loop.runner_shared.clone(),
           file_drop_handler,
+          touch_keyboard_handler,
           subclass_removed: Cell::new(false),
           recurse_depth: Cell::new(0),
         };
@@ -153,7 +177,7 @@ impl Window {
     // Hidden window also skips taskbar, we need to check if it conflicts with skip_taskbar state
     // If it's moving from visible to hidden, we need to unset skip_taskbar
     if prev && !visible && skip_taskbar {
-      self.set_skip_taskbar(false, false);
+      self.set_taskbar_tab(true);
     }
 
     // If it's still the same, there's no need to set it again
@@ -169,7 +193,29 @@ impl Window {
 
     // If it's moving from hidden to visible, we set skip_taskbar back
     if !prev && visible && skip_taskbar {
-      self.set_skip_taskbar(true, false);
+      self.set_taskbar_tab(false);
+    }
+  }
+
+  #[inline]
+  pub fn show_without_activating(&self) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+    let skip_taskbar = window_state.lock().skip_taskbar;
+
+    self.thread_executor.execute_in_thread(move || {
+      // `WindowFlags::apply_diff` would show the window with `SW_SHOW`, which activates it.
+      window_state
+        .lock()
+        .set_window_flags_in_place(|f| f.insert(WindowFlags::VISIBLE));
+      unsafe {
+        winuser::ShowWindow(window.0, winuser::SW_SHOWNOACTIVATE);
+      }
+    });
+
+    // Showing the window adds it to the taskbar again
+    if skip_taskbar {
+      self.set_taskbar_tab(false);
     }
   }
 
@@ -188,17 +234,93 @@ impl Window {
   }
 
   #[inline]
-  pub fn request_redraw(&self) {
+  pub fn raise(&self) {
+    let window_flags = self.window_state.lock().window_flags();
+    if !window_flags.contains(WindowFlags::VISIBLE) || window_flags.contains(WindowFlags::MINIMIZED)
+    {
+      return;
+    }
+
     unsafe {
-      winuser::RedrawWindow(
+      winuser::SetWindowPos(
         self.window.0,
-        ptr::null(),
-        ptr::null_mut(),
-        winuser::RDW_INTERNALPAINT,
+        winuser::HWND_TOP,
+        0,
+        0,
+        0,
+        0,
+        winuser::SWP_NOMOVE | winuser::SWP_NOSIZE | winuser::SWP_NOACTIVATE,
+      );
+    }
+  }
+
+  #[inline]
+  pub fn set_above(&self, window: &Window) {
+    // `SetWindowPos` places a window below the one it's given, so it's given the window
+    // that is right above `window`.
+    let previous = unsafe { winuser::GetWindow(window.window.0, winuser::GW_HWNDPREV) };
+    if previous == self.window.0 {
+      return;
+    }
+    let insert_after = if previous.is_null() {
+      winuser::HWND_TOP
+    } else {
+      previous
+    };
+    self.set_z_order(window, insert_after);
+  }
+
+  #[inline]
+  pub fn set_below(&self, window: &Window) {
+    self.set_z_order(window, window.window.0);
+  }
+
+  fn set_z_order(&self, sibling: &Window, insert_after: HWND) {
+    if sibling.window.0 == self.window.0 {
+      return;
+    }
+    let window_flags = self.window_state.lock().window_flags();
+    let sibling_flags = sibling.window_state.lock().window_flags();
+    if !window_flags.contains(WindowFlags::VISIBLE)
+      || window_flags.contains(WindowFlags::MINIMIZED)
+      // Crossing the topmost band would add or remove `WS_EX_TOPMOST`
+      || window_flags.contains(WindowFlags::ALWAYS_ON_TOP)
+        != sibling_flags.contains(WindowFlags::ALWAYS_ON_TOP)
+    {
+      return;
+    }
+
+    unsafe {
+      winuser::SetWindowPos(
+        self.window.0,
+        insert_after,
+        0,
+        0,
+        0,
+        0,
+        winuser::SWP_NOMOVE | winuser::SWP_NOSIZE | winuser::SWP_NOACTIVATE,
       );
     }
   }
 
+  #[inline]
+  pub fn request_redraw(&self) {
+    redraw_window(self.window.0, self.window_state.lock().redraw_pacing);
+  }
+
+  #[inline]
+  pub fn request_redraw_after(&self, delay: Duration) {
+    unsafe {
+      // The timer is handled by the window procedure, see `REDRAW_TIMER_ID`.
+      winuser::SetTimer(self.window.0, REDRAW_TIMER_ID, dur2timeout(delay), None);
+    }
+  }
+
+  #[inline]
+  pub fn set_redraw_pacing(&self, pacing: RedrawPacing) {
+    self.window_state.lock().redraw_pacing = pacing;
+  }
+
   #[inline]
   pub fn outer_position(&self) -> Result<PhysicalPosition<i32>, NotSupportedError> {
     util::get_window_rect(self.window.0)
@@ -294,6 +416,28 @@ impl Window {
     self.set_inner_size(size.into());
   }
 
+  #[inline]
+  pub fn set_aspect_ratio(&self, aspect_ratio: Option<AspectRatio>) {
+    self.window_state.lock().aspect_ratio = aspect_ratio;
+  }
+
+  #[inline]
+  pub fn set_resizable_edges(&self, edges: Edges) {
+    self.window_state.lock().resizable_edges = edges;
+  }
+
+  #[inline]
+  pub fn set_titlebar_buttons(&self, buttons: TitlebarButtons) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        set_titlebar_buttons_flags(f, buttons)
+      });
+    });
+  }
+
   #[inline]
   pub fn set_resizable(&self, resizable: bool) {
     let window = self.window.clone();
@@ -337,7 +481,7 @@ impl Window {
   }
 
   #[inline]
-  pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
+  pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
     let window = self.window.clone();
     let window_state = Arc::clone(&self.window_state);
     let (tx, rx) = channel::unbounded();
@@ -346,13 +490,71 @@ impl Window {
       let result = window_state
         .lock()
         .mouse
-        .set_cursor_flags(window.0, |f| f.set(CursorFlags::GRABBED, grab))
+        .set_cursor_flags(window.0, |f| {
+          f.set(CursorFlags::GRABBED, mode == CursorGrabMode::Confined);
+          f.set(CursorFlags::LOCKED, mode == CursorGrabMode::Locked);
+        })
         .map_err(|e| ExternalError::Os(os_error!(OsError::IoError(e))));
       let _ = tx.send(result);
     });
     rx.recv().unwrap()
   }
 
+  #[inline]
+  pub fn set_keyboard_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+    let (tx, rx) = channel::unbounded();
+
+    self.thread_executor.execute_in_thread(move || {
+      let mut window_state = window_state.lock();
+      let result = if window_state.keyboard_grab == grab {
+        Ok(())
+      } else if grab {
+        keyboard_grab::grab_keyboard(window.0)
+      } else {
+        keyboard_grab::ungrab_keyboard(window.0);
+        Ok(())
+      };
+      if result.is_ok() {
+        window_state.keyboard_grab = grab;
+      }
+      let _ = tx.send(result.map_err(|e| ExternalError::Os(os_error!(OsError::IoError(e)))));
+    });
+    rx.recv().unwrap()
+  }
+
+  #[cfg(feature = "test")]
+  #[inline]
+  pub fn inject_event(&self, event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    let window = self.window.clone();
+    let (tx, rx) = channel::unbounded();
+
+    self.thread_executor.execute_in_thread(move || {
+      let _ = tx.send(unsafe { super::synthetic_input::inject_event(window.0, event) });
+    });
+    rx.recv().unwrap()
+  }
+
+  #[inline]
+  pub fn set_mouse_capture(&self, capture: bool) -> Result<(), ExternalError> {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || unsafe {
+      let mut window_state = window_state.lock();
+      window_state.mouse.captured = capture;
+      if capture {
+        winuser::SetCapture(window.0);
+      } else if window_state.mouse.capture_count == 0 && winuser::GetCapture() == window.0 {
+        // `ReleaseCapture` sends a `WM_CAPTURECHANGED` which locks the window state.
+        drop(window_state);
+        winuser::ReleaseCapture();
+      }
+    });
+    Ok(())
+  }
+
   #[inline]
   pub fn set_cursor_visible(&self, visible: bool) {
     let window = self.window.clone();
@@ -370,6 +572,27 @@ impl Window {
     rx.recv().unwrap().ok();
   }
 
+  #[inline]
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        f.set(WindowFlags::IGNORE_CURSOR_EVENT, ignore)
+      });
+    });
+
+    Ok(())
+  }
+
+  #[inline]
+  pub fn set_mouse_coalescing(&self, enabled: bool) {
+    let mut window_state = self.window_state.lock();
+    window_state.mouse.coalescing = enabled;
+    window_state.mouse.last_move_time = None;
+  }
+
   #[inline]
   pub fn scale_factor(&self) -> f64 {
     self.window_state.lock().scale_factor
@@ -442,11 +665,83 @@ impl Window {
     util::set_maximized(self.window.0, maximized);
   }
 
+  #[inline]
+  pub fn set_maximized_on(&self, monitor: RootMonitorHandle) {
+    let window = self.window.clone();
+
+    self.thread_executor.execute_in_thread(move || unsafe {
+      let work_area = match monitor::get_monitor_info(monitor.inner.hmonitor()) {
+        Ok(monitor_info) => monitor_info.rcWork,
+        Err(_) => return,
+      };
+      // The restored position of the placement is in workspace coordinates,
+      // which are relative to the work area of the primary monitor.
+      let (offset_x, offset_y) =
+        match monitor::get_monitor_info(monitor::primary_monitor().hmonitor()) {
+          Ok(monitor_info) => (monitor_info.rcWork.left, monitor_info.rcWork.top),
+          Err(_) => (0, 0),
+        };
+
+      let mut placement: winuser::WINDOWPLACEMENT = mem::zeroed();
+      placement.length = mem::size_of::<winuser::WINDOWPLACEMENT>() as u32;
+      winuser::GetWindowPlacement(window.0, &mut placement);
+
+      let normal = placement.rcNormalPosition;
+      let width = (normal.right - normal.left).min(work_area.right - work_area.left);
+      let height = (normal.bottom - normal.top).min(work_area.bottom - work_area.top);
+      let left = work_area.left + (work_area.right - work_area.left - width) / 2 - offset_x;
+      let top = work_area.top + (work_area.bottom - work_area.top - height) / 2 - offset_y;
+      placement.rcNormalPosition = RECT {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+      };
+
+      // `SetWindowPlacement` moves and maximizes the window at once.
+      placement.showCmd = if winuser::IsWindowVisible(window.0) != 0 {
+        winuser::SW_MAXIMIZE as u32
+      } else {
+        winuser::SW_HIDE as u32
+      };
+      winuser::SetWindowPlacement(window.0, &placement);
+    });
+  }
+
   #[inline]
   pub fn is_maximized(&self) -> bool {
     util::is_maximized(self.window.0)
   }
 
+  pub fn normal_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    let mut placement: winuser::WINDOWPLACEMENT = unsafe { mem::zeroed() };
+    placement.length = mem::size_of::<winuser::WINDOWPLACEMENT>() as u32;
+    if unsafe { winuser::GetWindowPlacement(self.window.0, &mut placement) } == 0 {
+      return None;
+    }
+    // The restored position of the placement is in workspace coordinates,
+    // which are relative to the work area of the primary monitor.
+    let (offset_x, offset_y) =
+      match monitor::get_monitor_info(monitor::primary_monitor().hmonitor()) {
+        Ok(monitor_info) => (monitor_info.rcWork.left, monitor_info.rcWork.top),
+        Err(_) => (0, 0),
+      };
+    // The placement is the outer rect, the borders of the frame keep their size when the
+    // window is maximized.
+    let outer_size = self.outer_size();
+    let inner_size = self.inner_size();
+    let normal = placement.rcNormalPosition;
+    let width = (normal.right - normal.left) as u32;
+    let height = (normal.bottom - normal.top) as u32;
+    Some((
+      PhysicalPosition::new(normal.left + offset_x, normal.top + offset_y),
+      PhysicalSize::new(
+        width.saturating_sub(outer_size.width.saturating_sub(inner_size.width)),
+        height.saturating_sub(outer_size.height.saturating_sub(inner_size.height)),
+      ),
+    ))
+  }
+
   #[inline]
   pub fn is_resizable(&self) -> bool {
     let window_state = self.window_state.lock();
@@ -459,6 +754,18 @@ impl Window {
     window_state.window_flags.contains(WindowFlags::DECORATIONS)
   }
 
+  #[inline]
+  pub fn set_enabled(&self, enabled: bool) {
+    unsafe {
+      winuser::EnableWindow(self.window.0, enabled as BOOL);
+    }
+  }
+
+  #[inline]
+  pub fn is_enabled(&self) -> bool {
+    unsafe { winuser::IsWindowEnabled(self.window.0) != 0 }
+  }
+
   #[inline]
   pub fn is_visible(&self) -> bool {
     util::is_visible(self.window.0)
@@ -484,6 +791,19 @@ impl Window {
     drop(window_state_lock);
 
     self.thread_executor.execute_in_thread(move || {
+      // Switching between fullscreen modes or monitors isn't a transition.
+      let transition = match (&old_fullscreen, &fullscreen) {
+        (None, Some(_)) => Some((
+          FullscreenTransition::Entering,
+          FullscreenTransition::Entered,
+        )),
+        (Some(_), None) => Some((FullscreenTransition::Exiting, FullscreenTransition::Exited)),
+        _ => None,
+      };
+      if let Some((start, _)) = transition {
+        send_fullscreen_transition(window.0, start);
+      }
+
       // Change video mode if we're transitioning to or from exclusive
       // fullscreen
       match (&old_fullscreen, &fullscreen) {
@@ -517,18 +837,11 @@ impl Window {
           debug_assert!(res != winuser::DISP_CHANGE_BADPARAM);
           debug_assert!(res != winuser::DISP_CHANGE_FAILED);
           assert_eq!(res, winuser::DISP_CHANGE_SUCCESSFUL);
+          monitor::set_display_mode_changed();
         }
         (&Some(Fullscreen::Exclusive(_)), &None)
         | (&Some(Fullscreen::Exclusive(_)), &Some(Fullscreen::Borderless(_))) => {
-          let res = unsafe {
-            winuser::ChangeDisplaySettingsExW(
-              std::ptr::null_mut(),
-              std::ptr::null_mut(),
-              std::ptr::null_mut(),
-              winuser::CDS_FULLSCREEN,
-              std::ptr::null_mut(),
-            )
-          };
+          let res = monitor::restore_display_mode();
 
           debug_assert!(res != winuser::DISP_CHANGE_BADFLAGS);
           debug_assert!(res != winuser::DISP_CHANGE_BADMODE);
@@ -567,14 +880,17 @@ impl Window {
       // Update window bounds
       match &fullscreen {
         Some(fullscreen) => {
-          // Save window bounds before entering fullscreen
-          let placement = unsafe {
-            let mut placement = mem::zeroed();
-            winuser::GetWindowPlacement(window.0, &mut placement);
-            placement
-          };
-
-          window_state.lock().saved_window = Some(SavedWindow { placement });
+          // Save window bounds before entering fullscreen. When switching between fullscreen
+          // modes or monitors, the bounds saved when entering the first one are kept.
+          if old_fullscreen.is_none() {
+            let placement = unsafe {
+              let mut placement = mem::zeroed();
+              winuser::GetWindowPlacement(window.0, &mut placement);
+              placement
+            };
+
+            window_state.lock().saved_window = Some(SavedWindow { placement });
+          }
 
           let monitor = match &fullscreen {
             Fullscreen::Exclusive(video_mode) => video_mode.monitor(),
@@ -615,6 +931,52 @@ impl Window {
       unsafe {
         taskbar_mark_fullscreen(window.0, fullscreen.is_some());
       }
+
+      if let Some((_, end)) = transition {
+        send_fullscreen_transition(window.0, end);
+      }
+    });
+  }
+
+  pub fn set_fullscreen_video_mode(&self, video_mode: RootVideoMode) -> Result<(), ExternalError> {
+    self.set_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
+    Ok(())
+  }
+
+  #[inline]
+  pub fn set_kiosk(&self, kiosk: bool) {
+    let mut window_state = self.window_state.lock();
+    if window_state.kiosk == kiosk {
+      return;
+    }
+    window_state.kiosk = kiosk;
+    drop(window_state);
+
+    self.set_fullscreen(if kiosk {
+      Some(Fullscreen::Borderless(None))
+    } else {
+      None
+    });
+    self.set_window_level(if kiosk {
+      WindowLevel::Floating
+    } else {
+      WindowLevel::Normal
+    });
+    let mode = if kiosk {
+      CursorGrabMode::Confined
+    } else {
+      CursorGrabMode::None
+    };
+    if let Err(e) = self.set_cursor_grab(mode) {
+      warn!("Failed to confine the cursor of the kiosk window: {}", e);
+    }
+
+    self.thread_executor.execute_in_thread(move || {
+      if kiosk {
+        kiosk::block_shell_shortcuts();
+      } else {
+        kiosk::unblock_shell_shortcuts();
+      }
     });
   }
 
@@ -631,17 +993,159 @@ impl Window {
   }
 
   #[inline]
-  pub fn set_always_on_top(&self, always_on_top: bool) {
+  pub fn set_owner(&self, owner: Option<WindowId>) {
+    let window = self.window.clone();
+    let owner = owner.map_or(0, |owner| owner.0 as LONG_PTR);
+
+    self.thread_executor.execute_in_thread(move || unsafe {
+      // Despite its name, `GWLP_HWNDPARENT` sets the owner of a top-level window
+      winuser::SetWindowLongPtrW(window.0, winuser::GWLP_HWNDPARENT, owner);
+    });
+  }
+
+  #[inline]
+  pub fn set_shadow(&self, shadow: bool) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        f.set(WindowFlags::UNDECORATED_SHADOW, shadow)
+      });
+    });
+  }
+
+  #[inline]
+  pub fn set_content_protected(&self, protected: bool) {
+    // Only available since Windows 10 version 2004
+    const WDA_EXCLUDEFROMCAPTURE: DWORD = 0x00000011;
+
+    unsafe {
+      if !protected {
+        winuser::SetWindowDisplayAffinity(self.window.0, winuser::WDA_NONE);
+      } else if winuser::SetWindowDisplayAffinity(self.window.0, WDA_EXCLUDEFROMCAPTURE) == 0 {
+        winuser::SetWindowDisplayAffinity(self.window.0, winuser::WDA_MONITOR);
+      }
+    }
+  }
+
+  #[inline]
+  pub fn set_opacity(&self, opacity: f32) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        f.set(WindowFlags::LAYERED, opacity < 1.0)
+      });
+
+      // The window stays layered while it ignores cursor events, so its alpha must be reset too.
+      let flags = window_state.lock().window_flags();
+      if flags.intersects(WindowFlags::LAYERED | WindowFlags::IGNORE_CURSOR_EVENT) {
+        let alpha = (opacity * 255.0).round() as u8;
+        unsafe {
+          winuser::SetLayeredWindowAttributes(window.0, 0, alpha, winuser::LWA_ALPHA);
+        }
+      }
+    });
+  }
+
+  #[inline]
+  pub fn set_window_level(&self, level: WindowLevel) {
     let window = self.window.clone();
     let window_state = Arc::clone(&self.window_state);
 
     self.thread_executor.execute_in_thread(move || {
       WindowState::set_window_flags(window_state.lock(), window.0, |f| {
-        f.set(WindowFlags::ALWAYS_ON_TOP, always_on_top)
+        f.set(WindowFlags::ALWAYS_ON_TOP, level > WindowLevel::Normal);
+        f.set(
+          WindowFlags::ALWAYS_ON_BOTTOM,
+          level == WindowLevel::AlwaysOnBottom,
+        );
       });
     });
   }
 
+  #[inline]
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    virtual_desktop::window_virtual_desktop(self.window.0)
+  }
+
+  #[inline]
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    virtual_desktop::is_on_current_virtual_desktop(self.window.0)
+  }
+
+  #[inline]
+  pub fn move_to_virtual_desktop(&self, desktop: &VirtualDesktop) -> Result<(), ExternalError> {
+    virtual_desktop::move_to_virtual_desktop(self.window.0, *desktop)
+      .map_err(|e| ExternalError::Os(os_error!(OsError::IoError(e))))
+  }
+
+  #[inline]
+  pub fn set_visible_on_all_workspaces(&self, _visible: bool) {
+    warn!("`Window::set_visible_on_all_workspaces` is ignored on Windows");
+  }
+
+  #[inline]
+  pub fn set_background_effect(&self, effect: Option<Effect>) {
+    let window = self.window.clone();
+
+    self.thread_executor.execute_in_thread(move || {
+      effects::set_background_effect(window.0, effect);
+    });
+  }
+
+  #[inline]
+  pub fn set_drag_region(&self, regions: Vec<DragRegion>) {
+    self.window_state.lock().drag_regions = regions;
+  }
+
+  #[inline]
+  pub fn set_snap_layout_button_rect(&self, rect: Option<(Position, Size)>) {
+    self.window_state.lock().snap_layout_button =
+      rect.map(|(position, size)| DragRegion::new(position, size, DragRegionKind::MaximizeButton));
+  }
+
+  #[inline]
+  pub fn set_corner_preference(&self, preference: CornerPreference) {
+    effects::set_corner_preference(self.window.0, preference);
+  }
+
+  #[inline]
+  pub fn set_cloaked(&self, cloaked: bool) {
+    effects::set_cloaked(self.window.0, cloaked);
+  }
+
+  #[inline]
+  pub fn set_titlebar_color(&self, color: Option<Color>) {
+    effects::set_caption_color(self.window.0, color);
+  }
+
+  #[inline]
+  pub fn set_titlebar_text_color(&self, color: Option<Color>) {
+    effects::set_text_color(self.window.0, color);
+  }
+
+  #[inline]
+  pub fn set_transition_animations(&self, enabled: bool) {
+    effects::set_transitions_disabled(self.window.0, !enabled);
+  }
+
+  #[inline]
+  pub fn show_touch_keyboard(&self) {
+    self.thread_executor.execute_in_thread(|| {
+      touch_keyboard::set_touch_keyboard_visible(true);
+    });
+  }
+
+  #[inline]
+  pub fn hide_touch_keyboard(&self) {
+    self.thread_executor.execute_in_thread(|| {
+      touch_keyboard::set_touch_keyboard_visible(false);
+    });
+  }
+
   #[inline]
   pub fn current_monitor(&self) -> Option<RootMonitorHandle> {
     Some(RootMonitorHandle {
@@ -673,25 +1177,22 @@ impl Window {
     self.window_state.lock().taskbar_icon = taskbar_icon;
   }
 
-  pub(crate) fn set_ime_position_physical(&self, x: i32, y: i32) {
-    if unsafe { winuser::GetSystemMetrics(winuser::SM_IMMENABLED) } != 0 {
-      let mut composition_form = COMPOSITIONFORM {
-        dwStyle: CFS_POINT,
-        ptCurrentPos: POINT { x, y },
-        rcArea: unsafe { mem::zeroed() },
-      };
-      unsafe {
-        let himc = winapi::um::imm::ImmGetContext(self.window.0);
-        winapi::um::imm::ImmSetCompositionWindow(himc, &mut composition_form);
-        winapi::um::imm::ImmReleaseContext(self.window.0, himc);
-      }
-    }
+  #[inline]
+  pub fn set_ime_cursor_area(&self, position: Position, size: Size) {
+    let scale_factor = self.scale_factor();
+    let (x, y) = position.to_physical::<i32>(scale_factor).into();
+    let (width, height) = size.to_physical::<i32>(scale_factor).into();
+    minimal_ime::set_cursor_area(self.window.0, x, y, width, height);
+  }
+
+  #[inline]
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    minimal_ime::set_ime_allowed(self.window.0, allowed);
   }
 
   #[inline]
-  pub fn set_ime_position(&self, spot: Position) {
-    let (x, y) = spot.to_physical::<i32>(self.scale_factor()).into();
-    self.set_ime_position_physical(x, y);
+  pub fn set_text_input(&self, active: bool) {
+    self.window_state.lock().text_input = active;
   }
 
   #[inline]
@@ -723,11 +1224,65 @@ impl Window {
     });
   }
 
+  #[inline]
+  pub fn set_badge_count(&self, count: Option<u64>) {
+    let window = self.window.clone();
+
+    self.thread_executor.execute_in_thread(move || unsafe {
+      if let Some(task_bar_list) = taskbar_list() {
+        // The taskbar keeps its own copy of the icon.
+        let icon = count.and_then(badge::badge_icon);
+        let handle = icon
+          .as_ref()
+          .map(|icon| icon.as_raw_handle())
+          .unwrap_or(ptr::null_mut());
+        (*task_bar_list).SetOverlayIcon(window.0, handle, ptr::null());
+      }
+    });
+  }
+
+  #[inline]
+  pub fn set_progress_bar(&self, state: ProgressState, progress: Option<u64>) {
+    let window = self.window.clone();
+
+    self.thread_executor.execute_in_thread(move || unsafe {
+      taskbar_set_progress(window.0, state, progress);
+    });
+  }
+
+  #[inline]
+  pub fn set_overlay_icon(&self, icon: Option<Icon>) {
+    let window = self.window.clone();
+
+    self.thread_executor.execute_in_thread(move || unsafe {
+      if let Some(task_bar_list) = taskbar_list() {
+        let handle = icon
+          .as_ref()
+          .map(|icon| icon.inner.as_raw_handle())
+          .unwrap_or(ptr::null_mut());
+        (*task_bar_list).SetOverlayIcon(window.0, handle, ptr::null());
+      }
+    });
+  }
+
   #[inline]
   pub fn theme(&self) -> Theme {
     self.window_state.lock().current_theme
   }
 
+  #[inline]
+  pub fn set_theme(&self, theme: Option<Theme>) {
+    {
+      let mut window_state = self.window_state.lock();
+      if window_state.preferred_theme == theme {
+        return;
+      }
+      window_state.preferred_theme = theme;
+    }
+    // Applies the theme and sends `ThemeChanged` like a change of the system theme.
+    unsafe { winuser::SendMessageW(self.hwnd(), winuser::WM_SETTINGCHANGE, 0, 0) };
+  }
+
   #[inline]
   pub fn hide_menu(&self) {
     unsafe {
@@ -749,6 +1304,13 @@ impl Window {
     unsafe { !winuser::GetMenu(self.hwnd()).is_null() }
   }
 
+  #[inline]
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    capture::capture_client_area(self.hwnd())
+      .map(|(width, height, rgba)| WindowImage::new(width, height, rgba))
+      .map_err(|e| ExternalError::Os(os_error!(OsError::IoError(e))))
+  }
+
   #[inline]
   pub fn reset_dead_keys(&self) {
     // `ToUnicode` consumes the dead-key by default, so we are constructing a fake (but valid)
@@ -770,27 +1332,41 @@ impl Window {
   }
 
   #[inline]
-  pub(crate) fn set_skip_taskbar(&self, skip: bool, state: bool) {
-    // Update self skip_taskbar state if true
-    if state {
-      let mut window_state = self.window_state.lock();
-      window_state.skip_taskbar = skip;
-    }
+  pub fn set_skip_taskbar(&self, skip: bool) {
+    self.window_state.lock().skip_taskbar = skip;
+    self.set_taskbar_tab(!skip);
+  }
 
+  #[inline]
+  pub fn set_minimize_to_tray(&self, minimize_to_tray: bool) {
+    self.window_state.lock().minimize_to_tray = minimize_to_tray;
+  }
+
+  // Adds or removes the taskbar button of the window without touching the `skip_taskbar` state.
+  // This only has an effect on visible windows, as hidden windows never have a taskbar button.
+  fn set_taskbar_tab(&self, visible: bool) {
     if self.is_visible() {
       unsafe {
-        let mut taskbar_list: *mut ITaskbarList = std::mem::zeroed();
-        CoCreateInstance(
+        use winapi::shared::winerror::S_OK;
+
+        com_initialized();
+
+        let mut taskbar_list: *mut ITaskbarList = ptr::null_mut();
+        let hr = CoCreateInstance(
           &CLSID_TaskbarList,
-          std::ptr::null_mut(),
+          ptr::null_mut(),
           CLSCTX_SERVER,
           &ITaskbarList::uuidof(),
           &mut taskbar_list as *mut _ as *mut _,
         );
-        if skip {
-          (*taskbar_list).DeleteTab(self.hwnd() as _);
-        } else {
+        if hr != S_OK || taskbar_list.is_null() {
+          return;
+        }
+
+        if visible {
           (*taskbar_list).AddTab(self.hwnd() as _);
+        } else {
+          (*taskbar_list).DeleteTab(self.hwnd() as _);
         }
         (*taskbar_list).Release();
       }
@@ -809,6 +1385,54 @@ impl Drop for Window {
   }
 }
 
+/// The id of the timer of a redraw delayed by the throttle of the window. Its `WM_TIMER` message
+/// asks the redraw with `redraw_window`.
+pub(crate) const REDRAW_TIMER_ID: usize = 1;
+
+/// Asks a `WM_PAINT` message for `hwnd`, which emits `RedrawRequested`.
+pub(crate) fn redraw_window(hwnd: HWND, pacing: RedrawPacing) {
+  if pacing == RedrawPacing::VSync {
+    vsync::request_redraw(hwnd);
+    return;
+  }
+  unsafe {
+    winuser::RedrawWindow(
+      hwnd,
+      ptr::null(),
+      ptr::null_mut(),
+      winuser::RDW_INTERNALPAINT,
+    );
+  }
+}
+
+/// Closes a window from any thread once its `CloseResponse` is allowed.
+///
+/// The `HWND` of a destroyed window may be reused by another window, so the window only closes
+/// itself if the serial of the handle is still its own.
+#[derive(Clone)]
+pub struct CloseHandle {
+  hwnd: HWND,
+  serial: usize,
+}
+
+unsafe impl Send for CloseHandle {}
+unsafe impl Sync for CloseHandle {}
+
+impl CloseHandle {
+  pub fn new(hwnd: HWND, serial: usize) -> Self {
+    CloseHandle { hwnd, serial }
+  }
+
+  pub fn close(&self) {
+    // Like dropping the `Window`, since it must be destroyed from the thread that created it.
+    unsafe {
+      if winuser::IsWindow(self.hwnd) != 0 {
+        winuser::PostMessageW(self.hwnd, *CLOSE_RESPONSE_MSG_ID, self.serial, 0);
+      }
+    }
+  }
+}
+
 /// A simple non-owning wrapper around a window.
 #[doc(hidden)]
 #[derive(Clone)]
@@ -821,6 +1445,21 @@ pub struct WindowWrapper(HWND);
 unsafe impl Sync for WindowWrapper {}
 unsafe impl Send for WindowWrapper {}
 
+fn set_titlebar_buttons_flags(flags: &mut WindowFlags, buttons: TitlebarButtons) {
+  flags.set(
+    WindowFlags::MINIMIZABLE,
+    buttons.contains(TitlebarButtons::MINIMIZE),
+  );
+  flags.set(
+    WindowFlags::MAXIMIZABLE,
+    buttons.contains(TitlebarButtons::MAXIMIZE),
+  );
+  flags.set(
+    WindowFlags::CLOSABLE,
+    buttons.contains(TitlebarButtons::CLOSE),
+  );
+}
+
 unsafe fn init<T: 'static>(
   attributes: WindowAttributes,
   pl_attribs: PlatformSpecificWindowBuilderAttributes,
@@ -829,11 +1468,22 @@ unsafe fn init<T: 'static>(
   let title = util::to_wstring(&attributes.title);
 
   // registering the window class
-  let class_name = register_window_class(&attributes.window_icon, &pl_attribs.taskbar_icon);
+  let class_name = register_window_class(
+    &pl_attribs.class_name,
+    &attributes.window_icon,
+    &pl_attribs.taskbar_icon,
+  );
 
   let mut window_flags = WindowFlags::empty();
   window_flags.set(WindowFlags::DECORATIONS, attributes.decorations);
-  window_flags.set(WindowFlags::ALWAYS_ON_TOP, attributes.always_on_top);
+  window_flags.set(
+    WindowFlags::ALWAYS_ON_TOP,
+    attributes.window_level > WindowLevel::Normal,
+  );
+  window_flags.set(
+    WindowFlags::ALWAYS_ON_BOTTOM,
+    attributes.window_level == WindowLevel::AlwaysOnBottom,
+  );
   window_flags.set(
     WindowFlags::NO_BACK_BUFFER,
     pl_attribs.no_redirection_bitmap,
@@ -854,20 +1504,42 @@ unsafe fn init<T: 'static>(
       window_flags.set(WindowFlags::POPUP, true);
       Some(parent)
     }
-    Parent::None => {
-      window_flags.set(WindowFlags::ON_TASKBAR, true);
-      None
-    }
+    Parent::None => match (attributes.foreign_parent, attributes.parent_window) {
+      (Some(RawWindowHandle::Windows(handle)), _) if !handle.hwnd.is_null() => {
+        window_flags.set(WindowFlags::CHILD, true);
+        Some(handle.hwnd as HWND)
+      }
+      (_, Some(parent)) => {
+        window_flags.set(WindowFlags::POPUP, true);
+        Some((parent.0).0)
+      }
+      (_, None) => {
+        window_flags.set(WindowFlags::ON_TASKBAR, true);
+        None
+      }
+    },
   };
 
+  if attributes.window_type != WindowType::Normal {
+    window_flags.set(WindowFlags::TOOL_WINDOW, true);
+    // `WS_EX_APPWINDOW` would force a taskbar entry
+    window_flags.set(WindowFlags::ON_TASKBAR, false);
+  }
+  if attributes.window_type == WindowType::Tooltip {
+    window_flags.set(WindowFlags::ALWAYS_ON_TOP, true);
+  }
+  window_flags.set(WindowFlags::NO_ACTIVATE, !attributes.focusable);
+  set_titlebar_buttons_flags(&mut window_flags, attributes.titlebar_buttons);
+
   // creating the real window this time, by using the functions in `extra_functions`
   let real_window = {
     let (style, ex_style) = window_flags.to_window_styles();
+    let (extra_style, extra_ex_style) = pl_attribs.extra_styles;
     let handle = winuser::CreateWindowExW(
-      ex_style,
+      ex_style | extra_ex_style,
       class_name.as_ptr(),
       title.as_ptr() as LPCWSTR,
-      style,
+      style | extra_style,
       winuser::CW_USEDEFAULT,
       winuser::CW_USEDEFAULT,
       winuser::CW_USEDEFAULT,
@@ -917,8 +1589,13 @@ unsafe fn init<T: 'static>(
   // window for the first time).
   let current_theme = try_theme(real_window.0, pl_attribs.preferred_theme);
 
+  // The window must be cloaked before it is shown for the first time.
+  if pl_attribs.cloaked {
+    effects::set_cloaked(real_window.0, true);
+  }
+
   let window_state = {
-    let window_state = WindowState::new(
+    let mut window_state = WindowState::new(
       &attributes,
       pl_attribs.taskbar_icon,
       scale_factor,
@@ -926,6 +1603,7 @@ unsafe fn init<T: 'static>(
       pl_attribs.preferred_theme,
       pl_attribs.skip_taskbar,
     );
+    window_state.extra_styles = pl_attribs.extra_styles;
     let window_state = Arc::new(Mutex::new(window_state));
     WindowState::set_window_flags(window_state.lock(), real_window.0, |f| *f = window_flags);
     window_state
@@ -938,7 +1616,9 @@ unsafe fn init<T: 'static>(
     menu: None,
   };
 
-  win.set_skip_taskbar(pl_attribs.skip_taskbar, false);
+  if pl_attribs.skip_taskbar {
+    win.set_taskbar_tab(false);
+  }
 
   let dimensions = attributes
     .inner_size
@@ -951,6 +1631,14 @@ unsafe fn init<T: 'static>(
   }
   win.set_visible(attributes.visible);
 
+  if attributes.modal {
+    // Re-enabled in `DESTROY_MSG_ID`, before the window is destroyed.
+    let owner = winuser::GetWindow(win.window.0, winuser::GW_OWNER);
+    if !owner.is_null() {
+      winuser::EnableWindow(owner, 0);
+    }
+  }
+
   if attributes.fullscreen.is_some() {
     win.set_fullscreen(attributes.fullscreen);
     force_window_active(win.window.0);
@@ -981,10 +1669,11 @@ unsafe fn init<T: 'static>(
 }
 
 unsafe fn register_window_class(
+  class_name: &str,
   window_icon: &Option<Icon>,
   taskbar_icon: &Option<Icon>,
 ) -> Vec<u16> {
-  let class_name = util::to_wstring("Window Class");
+  let class_name = util::to_wstring(class_name);
 
   let h_icon = taskbar_icon
     .as_ref()
@@ -1072,22 +1761,16 @@ thread_local! {
         }
     };
 
-    static TASKBAR_LIST: Cell<*mut ITaskbarList2> = Cell::new(ptr::null_mut());
+    static TASKBAR_LIST: Cell<*mut ITaskbarList3> = Cell::new(ptr::null_mut());
 }
 
 pub fn com_initialized() {
   COM_INITIALIZED.with(|_| {});
 }
 
-// Reference Implementation:
-// https://github.com/chromium/chromium/blob/f18e79d901f56154f80eea1e2218544285e62623/ui/views/win/fullscreen_handler.cc
-//
-// As per MSDN marking the window as fullscreen should ensure that the
-// taskbar is moved to the bottom of the Z-order when the fullscreen window
-// is activated. If the window is not fullscreen, the Shell falls back to
-// heuristics to determine how the window should be treated, which means
-// that it could still consider the window as fullscreen. :(
-unsafe fn taskbar_mark_fullscreen(handle: HWND, fullscreen: bool) {
+// Returns the `ITaskbarList3` of the current thread, creating it on first use.
+// Returns `None` if the taskbar object could not be created.
+unsafe fn taskbar_list() -> Option<*mut ITaskbarList3> {
   com_initialized();
 
   TASKBAR_LIST.with(|task_bar_list_ptr| {
@@ -1100,22 +1783,67 @@ unsafe fn taskbar_mark_fullscreen(handle: HWND, fullscreen: bool) {
         &CLSID_TaskbarList,
         ptr::null_mut(),
         combaseapi::CLSCTX_ALL,
-        &ITaskbarList2::uuidof(),
+        &ITaskbarList3::uuidof(),
         &mut task_bar_list as *mut _ as *mut _,
       );
 
       if hr != S_OK || (*task_bar_list).HrInit() != S_OK {
         // In some old windows, the taskbar object could not be created, we just ignore it
-        return;
+        return None;
       }
       task_bar_list_ptr.set(task_bar_list)
     }
 
-    task_bar_list = task_bar_list_ptr.get();
-    (*task_bar_list).MarkFullscreenWindow(handle, if fullscreen { 1 } else { 0 });
+    Some(task_bar_list_ptr.get())
   })
 }
 
+// Reference Implementation:
+// https://github.com/chromium/chromium/blob/f18e79d901f56154f80eea1e2218544285e62623/ui/views/win/fullscreen_handler.cc
+//
+// As per MSDN marking the window as fullscreen should ensure that the
+// taskbar is moved to the bottom of the Z-order when the fullscreen window
+// is activated. If the window is not fullscreen, the Shell falls back to
+// heuristics to determine how the window should be treated, which means
+// that it could still consider the window as fullscreen. :(
+unsafe fn taskbar_mark_fullscreen(handle: HWND, fullscreen: bool) {
+  if let Some(task_bar_list) = taskbar_list() {
+    (*task_bar_list).MarkFullscreenWindow(handle, if fullscreen { 1 } else { 0 });
+  }
+}
+
+// Sent synchronously, so the event is queued around the resize events of the transition.
+fn send_fullscreen_transition(handle: HWND, transition: FullscreenTransition) {
+  unsafe {
+    winuser::SendMessageW(
+      handle,
+      *FULLSCREEN_TRANSITION_MSG_ID,
+      transition as WPARAM,
+      0,
+    );
+  }
+}
+
+unsafe fn taskbar_set_progress(handle: HWND, state: ProgressState, progress: Option<u64>) {
+  if let Some(task_bar_list) = taskbar_list() {
+    let flags = match state {
+      ProgressState::None => shobjidl_core::TBPF_NOPROGRESS,
+      ProgressState::Normal => shobjidl_core::TBPF_NORMAL,
+      ProgressState::Indeterminate => shobjidl_core::TBPF_INDETERMINATE,
+      ProgressState::Paused => shobjidl_core::TBPF_PAUSED,
+      ProgressState::Error => shobjidl_core::TBPF_ERROR,
+    };
+    (*task_bar_list).SetProgressState(handle, flags);
+
+    // Setting a value on an indeterminate progress bar switches it back to normal.
+    if let Some(progress) = progress {
+      if !matches!(state, ProgressState::None | ProgressState::Indeterminate) {
+        (*task_bar_list).SetProgressValue(handle, progress, 100);
+      }
+    }
+  }
+}
+
 unsafe fn force_window_active(handle: HWND) {
   // In some situation, calling SetForegroundWindow could not bring up the window,
   // This is a little hack which can "steal" the foreground window permission
@@ -1144,7 +1872,27 @@ unsafe fn force_window_active(handle: HWND) {
   winuser::SetForegroundWindow(handle);
 }
 
-pub fn hit_test(hwnd: HWND, cx: i32, cy: i32) -> LRESULT {
+/// Returns the `WM_NCHITTEST` value of a drag region.
+pub(crate) fn drag_region_hit_test(kind: DragRegionKind) -> LRESULT {
+  match kind {
+    DragRegionKind::Caption => winuser::HTCAPTION,
+    DragRegionKind::Resize(direction) => match direction {
+      ResizeDirection::East => winuser::HTRIGHT,
+      ResizeDirection::North => winuser::HTTOP,
+      ResizeDirection::NorthEast => winuser::HTTOPRIGHT,
+      ResizeDirection::NorthWest => winuser::HTTOPLEFT,
+      ResizeDirection::South => winuser::HTBOTTOM,
+      ResizeDirection::SouthEast => winuser::HTBOTTOMRIGHT,
+      ResizeDirection::SouthWest => winuser::HTBOTTOMLEFT,
+      ResizeDirection::West => winuser::HTLEFT,
+    },
+    DragRegionKind::MinimizeButton => winuser::HTMINBUTTON,
+    DragRegionKind::MaximizeButton => winuser::HTMAXBUTTON,
+    DragRegionKind::CloseButton => winuser::HTCLOSE,
+  }
+}
+
+pub fn hit_test(hwnd: HWND, cx: i32, cy: i32, edges: Edges) -> LRESULT {
   use winapi::shared::minwindef::TRUE;
   use winuser::{
     GetWindowRect, HTBOTTOM, HTBOTTOMLEFT, HTBOTTOMRIGHT, HTCLIENT, HTLEFT, HTNOWHERE, HTRIGHT,
@@ -1176,6 +1924,8 @@ pub fn hit_test(hwnd: HWND, cx: i32, cy: i32) -> LRESULT {
         | (RIGHT * (if cx >= (right - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
         | (TOP * (if cy < (top + BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
         | (BOTTOM * (if cy >= (bottom - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }));
+      // The bits of `Edges` are the same as the constants above.
+      let result = result & edges.bits() as i32;
 
       match result {
         CLIENT => HTCLIENT,
@@ -1194,3 +1944,44 @@ pub fn hit_test(hwnd: HWND, cx: i32, cy: i32) -> LRESULT {
     }
   }
 }
+
+// Turns the resize borders of `hit`, as returned by the default window procedure of a decorated
+// window, into a plain border if they are not part of `edges`.
+pub(crate) fn restrict_hit_test(hit: LRESULT, edges: Edges) -> LRESULT {
+  use winuser::{
+    HTBORDER, HTBOTTOM, HTBOTTOMLEFT, HTBOTTOMRIGHT, HTLEFT, HTRIGHT, HTTOP, HTTOPLEFT, HTTOPRIGHT,
+  };
+
+  const LEFT: i32 = 0b0001;
+  const RIGHT: i32 = 0b0010;
+  const TOP: i32 = 0b0100;
+  const BOTTOM: i32 = 0b1000;
+  const TOPLEFT: i32 = TOP | LEFT;
+  const TOPRIGHT: i32 = TOP | RIGHT;
+  const BOTTOMLEFT: i32 = BOTTOM | LEFT;
+  const BOTTOMRIGHT: i32 = BOTTOM | RIGHT;
+
+  let hit_edges = match hit {
+    HTLEFT => LEFT,
+    HTRIGHT => RIGHT,
+    HTTOP => TOP,
+    HTBOTTOM => BOTTOM,
+    HTTOPLEFT => TOPLEFT,
+    HTTOPRIGHT => TOPRIGHT,
+    HTBOTTOMLEFT => BOTTOMLEFT,
+    HTBOTTOMRIGHT => BOTTOMRIGHT,
+    _ => return hit,
+  };
+
+  match hit_edges & edges.bits() as i32 {
+    LEFT => HTLEFT,
+    RIGHT => HTRIGHT,
+    TOP => HTTOP,
+    BOTTOM => HTBOTTOM,
+    TOPLEFT => HTTOPLEFT,
+    TOPRIGHT => HTTOPRIGHT,
+    BOTTOMLEFT => HTBOTTOMLEFT,
+    BOTTOMRIGHT => HTBOTTOMRIGHT,
+    _ => HTBORDER,
+  }
+}
@@ -2,22 +2,30 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+  click_count::ClickCounter,
   dpi::{PhysicalPosition, Size},
   icon::Icon,
   keyboard::ModifiersState,
   platform_impl::platform::{event_loop, keyboard::KeyEventBuilder, minimal_ime::MinimalIme, util},
-  window::{CursorIcon, Fullscreen, Theme, WindowAttributes},
+  window::{
+    AspectRatio, CursorIcon, DragRegion, Edges, Fullscreen, RedrawPacing, Theme, WindowAttributes,
+  },
 };
 use parking_lot::MutexGuard;
-use std::{io, ptr};
+use std::{
+  io, ptr,
+  sync::atomic::{AtomicUsize, Ordering},
+};
 use winapi::{
   shared::{
     minwindef::DWORD,
     windef::{HWND, RECT},
   },
-  um::winuser,
+  um::{dwmapi, uxtheme::MARGINS, winuser},
 };
 
+static NEXT_SERIAL: AtomicUsize = AtomicUsize::new(0);
+
 /// Contains information about states and the window that the callback is going to use.
 pub struct WindowState {
   pub mouse: MouseProperties,
@@ -25,6 +33,8 @@ pub struct WindowState {
   /// Used by `WM_GETMINMAXINFO`.
   pub min_size: Option<Size>,
   pub max_size: Option<Size>,
+  /// Used by `WM_SIZING`.
+  pub aspect_ratio: Option<AspectRatio>,
 
   pub window_icon: Option<Icon>,
   pub taskbar_icon: Option<Icon>,
@@ -40,10 +50,35 @@ pub struct WindowState {
 
   pub key_event_builder: KeyEventBuilder,
   pub ime_handler: MinimalIme,
+  /// Set with `Window::start_text_input`, the entered text being sent with `ReceivedText`.
+  pub text_input: bool,
 
   pub window_flags: WindowFlags,
+  /// The styles and extended styles added with `WindowBuilderExtWindows::with_window_styles`.
+  pub extra_styles: (DWORD, DWORD),
 
   pub skip_taskbar: bool,
+  pub minimize_to_tray: bool,
+  pub modal: bool,
+  /// Unique to the window in the process, unlike its `HWND` which may be reused after it's
+  /// destroyed. Checked by the `CloseResponse`s of the window.
+  pub serial: usize,
+  /// Set with `Window::set_kiosk`, the keyboard hook is released when the window is destroyed.
+  pub kiosk: bool,
+  /// Set with `Window::set_keyboard_grab`, the grab is released when the window is destroyed.
+  pub keyboard_grab: bool,
+  /// The last state sent with `WindowEvent::Occluded`.
+  pub occluded: bool,
+
+  /// Used by `WM_NCHITTEST` for undecorated windows.
+  pub resizable_borders: bool,
+  pub resizable_edges: Edges,
+  pub drag_regions: Vec<DragRegion>,
+  /// Set with `WindowExtWindows::set_snap_layout_button_rect`.
+  pub snap_layout_button: Option<DragRegion>,
+
+  /// Used by `Window::request_redraw`.
+  pub redraw_pacing: RedrawPacing,
 }
 
 #[derive(Clone)]
@@ -55,8 +90,15 @@ pub struct SavedWindow {
 pub struct MouseProperties {
   pub cursor: CursorIcon,
   pub capture_count: u32,
+  /// Set with `Window::set_mouse_capture`, the capture is kept after the buttons are released.
+  pub captured: bool,
   cursor_flags: CursorFlags,
   pub last_position: Option<PhysicalPosition<f64>>,
+  /// Set with `Window::set_mouse_coalescing`.
+  pub coalescing: bool,
+  /// The time of the last `WM_MOUSEMOVE` message while coalescing is disabled.
+  pub last_move_time: Option<DWORD>,
+  pub click_counter: ClickCounter,
 }
 
 bitflags! {
@@ -64,6 +106,7 @@ bitflags! {
         const GRABBED   = 1 << 0;
         const HIDDEN    = 1 << 1;
         const IN_WINDOW = 1 << 2;
+        const LOCKED    = 1 << 3;
     }
 }
 bitflags! {
@@ -78,6 +121,15 @@ bitflags! {
         const CHILD          = 1 << 7;
         const MAXIMIZED      = 1 << 8;
         const POPUP          = 1 << 14;
+        const ALWAYS_ON_BOTTOM = 1 << 15;
+        const IGNORE_CURSOR_EVENT = 1 << 16;
+        const UNDECORATED_SHADOW = 1 << 17;
+        const TOOL_WINDOW = 1 << 18;
+        const LAYERED = 1 << 19;
+        const NO_ACTIVATE = 1 << 20;
+        const MINIMIZABLE = 1 << 21;
+        const MAXIMIZABLE = 1 << 22;
+        const CLOSABLE = 1 << 23;
 
         /// Marker flag for fullscreen. Should always match `WindowState::fullscreen`, but is
         /// included here to make masking easier.
@@ -112,12 +164,17 @@ impl WindowState {
       mouse: MouseProperties {
         cursor: CursorIcon::default(),
         capture_count: 0,
+        captured: false,
         cursor_flags: CursorFlags::empty(),
         last_position: None,
+        coalescing: true,
+        last_move_time: None,
+        click_counter: ClickCounter::default(),
       },
 
       min_size: attributes.min_inner_size,
       max_size: attributes.max_inner_size,
+      aspect_ratio: None,
 
       window_icon: attributes.window_icon.clone(),
       taskbar_icon,
@@ -132,9 +189,24 @@ impl WindowState {
       high_surrogate: None,
       key_event_builder: KeyEventBuilder::default(),
       ime_handler: MinimalIme::default(),
+      text_input: false,
       window_flags: WindowFlags::empty(),
+      extra_styles: (0, 0),
 
       skip_taskbar,
+      minimize_to_tray: attributes.minimize_to_tray,
+      modal: attributes.modal,
+      serial: NEXT_SERIAL.fetch_add(1, Ordering::Relaxed),
+      kiosk: false,
+      keyboard_grab: false,
+      occluded: !attributes.visible,
+
+      resizable_borders: attributes.resizable_borders,
+      resizable_edges: Edges::all(),
+      drag_regions: Vec::new(),
+      snap_layout_button: None,
+
+      redraw_pacing: RedrawPacing::default(),
     }
   }
 
@@ -149,9 +221,10 @@ impl WindowState {
     let old_flags = this.window_flags;
     f(&mut this.window_flags);
     let new_flags = this.window_flags;
+    let extra_styles = this.extra_styles;
 
     drop(this);
-    old_flags.apply_diff(window, new_flags);
+    old_flags.apply_diff(window, new_flags, extra_styles);
   }
 
   pub fn set_window_flags_in_place<F>(&mut self, f: F)
@@ -198,11 +271,17 @@ impl WindowFlags {
     use winapi::um::winuser::*;
 
     let (mut style, mut style_ex) = (0, 0);
-    style |= WS_CLIPSIBLINGS | WS_CLIPCHILDREN | WS_SYSMENU | WS_CAPTION | WS_MINIMIZEBOX;
+    style |= WS_CLIPSIBLINGS | WS_CLIPCHILDREN | WS_SYSMENU | WS_CAPTION;
     style_ex |= WS_EX_ACCEPTFILES;
 
+    if self.contains(WindowFlags::MINIMIZABLE) {
+      style |= WS_MINIMIZEBOX;
+    }
     if self.contains(WindowFlags::RESIZABLE) {
-      style |= WS_THICKFRAME | WS_MAXIMIZEBOX;
+      style |= WS_THICKFRAME;
+      if self.contains(WindowFlags::MAXIMIZABLE) {
+        style |= WS_MAXIMIZEBOX;
+      }
     }
     if self.contains(WindowFlags::DECORATIONS) {
       style |= WS_BORDER;
@@ -220,6 +299,18 @@ impl WindowFlags {
     if self.contains(WindowFlags::NO_BACK_BUFFER) {
       style_ex |= WS_EX_NOREDIRECTIONBITMAP;
     }
+    if self.contains(WindowFlags::TOOL_WINDOW) {
+      style_ex |= WS_EX_TOOLWINDOW;
+    }
+    if self.contains(WindowFlags::IGNORE_CURSOR_EVENT) {
+      style_ex |= WS_EX_TRANSPARENT | WS_EX_LAYERED;
+    }
+    if self.contains(WindowFlags::LAYERED) {
+      style_ex |= WS_EX_LAYERED;
+    }
+    if self.contains(WindowFlags::NO_ACTIVATE) {
+      style_ex |= WS_EX_NOACTIVATE;
+    }
     if self.contains(WindowFlags::CHILD) {
       style |= WS_CHILD; // This is incompatible with WS_POPUP if that gets added eventually.
     }
@@ -242,7 +333,7 @@ impl WindowFlags {
   }
 
   /// Adjust the window client rectangle to the return value, if present.
-  fn apply_diff(mut self, window: HWND, mut new: WindowFlags) {
+  fn apply_diff(mut self, window: HWND, mut new: WindowFlags, extra_styles: (DWORD, DWORD)) {
     self = self.mask();
     new = new.mask();
 
@@ -256,6 +347,8 @@ impl WindowFlags {
         winuser::ShowWindow(
           window,
           match new.contains(WindowFlags::VISIBLE) {
+            // `WS_EX_NOACTIVATE` only applies to clicks, `SW_SHOW` would still activate the window
+            true if new.contains(WindowFlags::NO_ACTIVATE) => winuser::SW_SHOWNOACTIVATE,
             true => winuser::SW_SHOW,
             false => winuser::SW_HIDE,
           },
@@ -283,6 +376,62 @@ impl WindowFlags {
       }
     }
 
+    if diff.contains(WindowFlags::ALWAYS_ON_BOTTOM) {
+      unsafe {
+        winuser::SetWindowPos(
+          window,
+          match new.contains(WindowFlags::ALWAYS_ON_BOTTOM) {
+            true => winuser::HWND_BOTTOM,
+            false => winuser::HWND_NOTOPMOST,
+          },
+          0,
+          0,
+          0,
+          0,
+          winuser::SWP_ASYNCWINDOWPOS
+            | winuser::SWP_NOMOVE
+            | winuser::SWP_NOSIZE
+            | winuser::SWP_NOACTIVATE,
+        );
+        winuser::InvalidateRgn(window, ptr::null_mut(), 0);
+      }
+    }
+
+    if diff.contains(WindowFlags::CLOSABLE) {
+      // There is no style for the close button, it follows the `SC_CLOSE` item of the system menu
+      unsafe {
+        let system_menu = winuser::GetSystemMenu(window, 0);
+        winuser::EnableMenuItem(
+          system_menu,
+          winuser::SC_CLOSE as _,
+          match new.contains(WindowFlags::CLOSABLE) {
+            true => winuser::MF_BYCOMMAND | winuser::MF_ENABLED,
+            false => winuser::MF_BYCOMMAND | winuser::MF_DISABLED | winuser::MF_GRAYED,
+          },
+        );
+      }
+    }
+
+    if diff.intersects(WindowFlags::DECORATIONS | WindowFlags::UNDECORATED_SHADOW) {
+      // DWM only draws the shadow of windows with a frame,
+      // so undecorated windows keep a 1px frame inside their client area.
+      let margin = match new.contains(WindowFlags::UNDECORATED_SHADOW)
+        && !new.contains(WindowFlags::DECORATIONS)
+      {
+        true => 1,
+        false => 0,
+      };
+      let margins = MARGINS {
+        cxLeftWidth: margin,
+        cxRightWidth: margin,
+        cyTopHeight: margin,
+        cyBottomHeight: margin,
+      };
+      unsafe {
+        dwmapi::DwmExtendFrameIntoClientArea(window, &margins);
+      }
+    }
+
     // Minimize operations should execute after maximize for proper window animations
     if diff.contains(WindowFlags::MINIMIZED) {
       unsafe {
@@ -298,6 +447,7 @@ impl WindowFlags {
 
     if diff != WindowFlags::empty() {
       let (style, style_ex) = new.to_window_styles();
+      let (style, style_ex) = (style | extra_styles.0, style_ex | extra_styles.1);
 
       unsafe {
         winuser::SendMessageW(window, *event_loop::SET_RETAIN_STATE_ON_SIZE_MSG_ID, 1, 0);
@@ -308,6 +458,15 @@ impl WindowFlags {
           winuser::SetWindowLongW(window, winuser::GWL_EXSTYLE, style_ex as _);
         }
 
+        // A layered window is not drawn until its attributes are set,
+        // `Window::set_opacity` sets them itself for `LAYERED` windows.
+        if diff.contains(WindowFlags::IGNORE_CURSOR_EVENT)
+          && new.contains(WindowFlags::IGNORE_CURSOR_EVENT)
+          && !new.contains(WindowFlags::LAYERED)
+        {
+          winuser::SetLayeredWindowAttributes(window, 0, 255, winuser::LWA_ALPHA);
+        }
+
         let mut flags = winuser::SWP_NOZORDER
           | winuser::SWP_NOMOVE
           | winuser::SWP_NOSIZE
@@ -330,14 +489,37 @@ impl WindowFlags {
   }
 }
 
+/// Clips the cursor to the pixel under it to lock it in place, or to the center of the client area
+/// when it's outside of it, since the raw input still reports the motion of the mouse.
+fn locked_cursor_clip(client_rect: RECT) -> Result<RECT, io::Error> {
+  let mut cursor = util::get_cursor_position()?;
+  if cursor.x < client_rect.left
+    || cursor.x >= client_rect.right
+    || cursor.y < client_rect.top
+    || cursor.y >= client_rect.bottom
+  {
+    cursor.x = (client_rect.left + client_rect.right) / 2;
+    cursor.y = (client_rect.top + client_rect.bottom) / 2;
+  }
+  Ok(RECT {
+    left: cursor.x,
+    top: cursor.y,
+    right: cursor.x + 1,
+    bottom: cursor.y + 1,
+  })
+}
+
 impl CursorFlags {
   fn refresh_os_cursor(self, window: HWND) -> Result<(), io::Error> {
     let client_rect = util::get_client_rect(window)?;
 
     if util::is_focused(window) {
-      let cursor_clip = match self.contains(CursorFlags::GRABBED) {
-        true => Some(client_rect),
-        false => None,
+      let cursor_clip = if self.contains(CursorFlags::LOCKED) {
+        Some(locked_cursor_clip(client_rect)?)
+      } else if self.contains(CursorFlags::GRABBED) {
+        Some(client_rect)
+      } else {
+        None
       };
 
       let rect_to_tuple = |rect: RECT| (rect.left, rect.top, rect.right, rect.bottom);
@@ -0,0 +1,166 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! The bookkeeping of the redraws asked with `Window::request_redraw`, shared by the platform
+//! implementations: the requests made while a `RedrawRequested` event is pending coalesce into it,
+//! and the throttle of a window delays the requests made too soon after its previous redraw.
+
+use instant::Instant;
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use crate::window::{RedrawCounters, WindowId};
+
+lazy_static! {
+  static ref WINDOWS: Mutex<HashMap<WindowId, Redraws>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct Redraws {
+  /// Whether a redraw was asked to the platform, and its `RedrawRequested` event wasn't
+  /// dispatched yet.
+  pending: bool,
+  throttle: Option<Duration>,
+  last_redraw: Option<Instant>,
+  counters: RedrawCounters,
+}
+
+/// What a call to `Window::request_redraw` asks to the platform.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Request {
+  Now,
+  /// A redraw once the delay elapsed, to respect the throttle of the window.
+  After(Duration),
+  /// Nothing, the call coalesced into the pending redraw.
+  Coalesced,
+  /// Nothing, the window isn't registered.
+  Unregistered,
+}
+
+pub(crate) fn register(window_id: WindowId) {
+  WINDOWS
+    .lock()
+    .unwrap()
+    .insert(window_id, Redraws::default());
+}
+
+pub(crate) fn unregister(window_id: WindowId) {
+  WINDOWS.lock().unwrap().remove(&window_id);
+}
+
+impl Redraws {
+  fn request(&mut self, now: Instant) -> Request {
+    self.counters.requested += 1;
+    if self.pending {
+      self.counters.coalesced += 1;
+      return Request::Coalesced;
+    }
+    self.pending = true;
+
+    match (self.throttle, self.last_redraw) {
+      (Some(throttle), Some(last_redraw)) if last_redraw + throttle > now => {
+        Request::After(last_redraw + throttle - now)
+      }
+      _ => Request::Now,
+    }
+  }
+
+  fn redrawn(&mut self, now: Instant) {
+    self.pending = false;
+    self.last_redraw = Some(now);
+    self.counters.emitted += 1;
+  }
+}
+
+pub(crate) fn request(window_id: WindowId) -> Request {
+  match WINDOWS.lock().unwrap().get_mut(&window_id) {
+    Some(redraws) => redraws.request(Instant::now()),
+    None => Request::Unregistered,
+  }
+}
+
+/// Records the `RedrawRequested` event of `window_id`, before it's dispatched to the event
+/// handler.
+pub(crate) fn redrawn(window_id: WindowId) {
+  if let Some(redraws) = WINDOWS.lock().unwrap().get_mut(&window_id) {
+    redraws.redrawn(Instant::now());
+  }
+}
+
+/// Forgets the pending redraw of `window_id` once its native window was destroyed, since its
+/// `RedrawRequested` event won't be emitted.
+pub(crate) fn destroyed(window_id: WindowId) {
+  if let Some(redraws) = WINDOWS.lock().unwrap().get_mut(&window_id) {
+    redraws.pending = false;
+  }
+}
+
+pub(crate) fn set_throttle(window_id: WindowId, throttle: Option<Duration>) {
+  if let Some(redraws) = WINDOWS.lock().unwrap().get_mut(&window_id) {
+    redraws.throttle = throttle;
+  }
+}
+
+pub(crate) fn counters(window_id: WindowId) -> RedrawCounters {
+  WINDOWS
+    .lock()
+    .unwrap()
+    .get(&window_id)
+    .map_or_else(RedrawCounters::default, |redraws| redraws.counters)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn requests_coalesce_until_redrawn() {
+    let mut redraws = Redraws::default();
+    let now = Instant::now();
+    assert_eq!(redraws.request(now), Request::Now);
+    assert_eq!(redraws.request(now), Request::Coalesced);
+    assert_eq!(redraws.request(now), Request::Coalesced);
+    redraws.redrawn(now);
+    assert_eq!(redraws.request(now), Request::Now);
+
+    assert_eq!(redraws.counters.requested, 4);
+    assert_eq!(redraws.counters.coalesced, 2);
+    assert_eq!(redraws.counters.emitted, 1);
+  }
+
+  #[test]
+  fn throttle_delays_early_requests() {
+    let mut redraws = Redraws {
+      throttle: Some(Duration::from_millis(16)),
+      ..Default::default()
+    };
+    let start = Instant::now();
+    // The first redraw isn't delayed.
+    assert_eq!(redraws.request(start), Request::Now);
+    redraws.redrawn(start);
+
+    let early = start + Duration::from_millis(10);
+    assert_eq!(
+      redraws.request(early),
+      Request::After(Duration::from_millis(6))
+    );
+    // The delayed redraw is pending, so the requests coalesce into it.
+    assert_eq!(redraws.request(early), Request::Coalesced);
+    redraws.redrawn(start + Duration::from_millis(16));
+
+    let late = start + Duration::from_millis(40);
+    assert_eq!(redraws.request(late), Request::Now);
+  }
+
+  #[test]
+  fn unregistered_windows_are_not_redrawn() {
+    // Only used as a key, the id isn't passed to the platform.
+    let window_id = unsafe { WindowId::dummy() };
+    assert_eq!(request(window_id), Request::Unregistered);
+
+    register(window_id);
+    assert_eq!(request(window_id), Request::Now);
+    unregister(window_id);
+    assert_eq!(request(window_id), Request::Unregistered);
+    assert_eq!(counters(window_id), RedrawCounters::default());
+  }
+}
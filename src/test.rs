@@ -0,0 +1,87 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fabricated input for the end-to-end tests of the applications, enabled by the `test` feature.
+//!
+//! [`Window::inject_event`](crate::window::Window::inject_event) hands a [`SyntheticEvent`] to the
+//! window as an event of the system, so it goes through the same translation as the input of the
+//! user: a key gives the logical key and the text of the current keyboard layout, a press of a
+//! mouse button is counted for the double-clicks, and so on. Unlike the input sent to the system,
+//! e.g. with `SendInput`, it doesn't need any permission and only reaches the window.
+//!
+//! ```no_run
+//! use tao::{
+//!   dpi::PhysicalPosition,
+//!   event::{ElementState, MouseButton},
+//!   event_loop::EventLoop,
+//!   test::SyntheticEvent,
+//!   window::Window,
+//! };
+//!
+//! let event_loop = EventLoop::new();
+//! let window = Window::new(&event_loop).unwrap();
+//! let position = PhysicalPosition::new(10.0, 10.0);
+//! for state in [ElementState::Pressed, ElementState::Released] {
+//!   window
+//!     .inject_event(SyntheticEvent::MouseInput {
+//!       button: MouseButton::Left,
+//!       state,
+//!       position,
+//!     })
+//!     .unwrap();
+//! }
+//! ```
+//!
+//! ## Platform-specific
+//!
+//! - **macOS:** The keys and the buttons are sent to the window right away, the other platforms
+//!   queue them for the event loop.
+//! - **Web:** The logical key of the synthetic keys is `Key::Unidentified`, the browsers only
+//!   translate the real keys.
+//! - **Linux:** Unsupported with the `wayland` feature.
+//! - **Headless:** The events are sent as the window events they would give, the logical key of
+//!   the keys being `Key::Unidentified`.
+//! - **iOS / Android:** Unsupported.
+use crate::{
+  dpi::PhysicalPosition,
+  event::{ElementState, MouseButton, TouchPhase},
+  keyboard::KeyCode,
+};
+
+/// An input event fabricated for a window.
+///
+/// The positions are relative to the top-left corner of the inner area of the window.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntheticEvent {
+  /// A key pressed or released. The modifier keys stay pressed for the next synthetic events
+  /// until they're released.
+  Key {
+    physical_key: KeyCode,
+    state: ElementState,
+  },
+  /// The cursor moved to `position`.
+  CursorMoved { position: PhysicalPosition<f64> },
+  /// A mouse button pressed or released with the cursor at `position`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Only the left, right and middle buttons are supported.
+  MouseInput {
+    button: MouseButton,
+    state: ElementState,
+    position: PhysicalPosition<f64>,
+  },
+  /// The mouse wheel scrolled by lines, positive away from the user and to the right.
+  MouseWheel { lines: (f32, f32) },
+  /// A touch of the finger `id`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows / macOS:** Unsupported.
+  Touch {
+    id: u64,
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+  },
+}
@@ -0,0 +1,139 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! The queue of the user events sent by the `EventLoopProxy`s of an event loop, shared by the
+//! platform implementations.
+
+use std::{collections::VecDeque, fmt, sync::Mutex};
+
+use crate::event_loop::{Priority, SendEventError};
+
+const PRIORITIES: usize = 3;
+
+pub(crate) struct UserEventQueue<T> {
+  queues: Mutex<Queues<T>>,
+}
+
+struct Queues<T> {
+  /// The events of each priority, indexed by `Priority as usize`.
+  events: [VecDeque<T>; PRIORITIES],
+  capacities: [Option<usize>; PRIORITIES],
+  closed: bool,
+}
+
+impl<T> fmt::Debug for UserEventQueue<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad("UserEventQueue { .. }")
+  }
+}
+
+impl<T> UserEventQueue<T> {
+  pub fn new() -> Self {
+    UserEventQueue {
+      queues: Mutex::new(Queues {
+        events: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+        capacities: [None; PRIORITIES],
+        closed: false,
+      }),
+    }
+  }
+
+  /// Queues `event`, failing if the queue of its priority is full when `bounded` is true.
+  pub fn push(&self, event: T, priority: Priority, bounded: bool) -> Result<(), SendEventError<T>> {
+    let mut queues = self.queues.lock().unwrap();
+    if queues.closed {
+      return Err(SendEventError::Closed(event));
+    }
+    let index = priority as usize;
+    if let Some(capacity) = queues.capacities[index] {
+      if bounded && queues.events[index].len() >= capacity {
+        return Err(SendEventError::Full(event));
+      }
+    }
+    queues.events[index].push_back(event);
+    Ok(())
+  }
+
+  /// Takes the oldest event of the highest priority.
+  pub fn pop(&self) -> Option<T> {
+    let mut queues = self.queues.lock().unwrap();
+    queues
+      .events
+      .iter_mut()
+      .rev()
+      .find_map(|events| events.pop_front())
+  }
+
+  pub fn set_capacity(&self, priority: Priority, capacity: Option<usize>) {
+    self.queues.lock().unwrap().capacities[priority as usize] = capacity;
+  }
+
+  /// Makes the following pushes fail, once the event loop was dropped.
+  pub fn close(&self) {
+    let mut queues = self.queues.lock().unwrap();
+    queues.closed = true;
+    for events in queues.events.iter_mut() {
+      events.clear();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn pops_by_priority_then_in_order() {
+    let queue = UserEventQueue::new();
+    queue.push(1, Priority::Low, true).unwrap();
+    queue.push(2, Priority::Normal, true).unwrap();
+    queue.push(3, Priority::High, true).unwrap();
+    queue.push(4, Priority::Normal, true).unwrap();
+    queue.push(5, Priority::High, true).unwrap();
+
+    let events: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+    assert_eq!(events, [3, 5, 2, 4, 1]);
+  }
+
+  #[test]
+  fn full_once_capacity_is_reached() {
+    let queue = UserEventQueue::new();
+    queue.set_capacity(Priority::Normal, Some(2));
+    queue.push(1, Priority::Normal, true).unwrap();
+    queue.push(2, Priority::Normal, true).unwrap();
+    assert!(matches!(
+      queue.push(3, Priority::Normal, true),
+      Err(SendEventError::Full(3))
+    ));
+
+    // The other priorities and the unbounded pushes aren't limited.
+    queue.push(4, Priority::High, true).unwrap();
+    queue.push(5, Priority::Normal, false).unwrap();
+
+    // There is room again once an event was taken.
+    assert_eq!(queue.pop(), Some(4));
+    assert_eq!(queue.pop(), Some(1));
+    assert!(matches!(
+      queue.push(6, Priority::Normal, true),
+      Err(SendEventError::Full(6))
+    ));
+    assert_eq!(queue.pop(), Some(2));
+    queue.push(7, Priority::Normal, true).unwrap();
+
+    // Removing the capacity makes the queue unbounded again.
+    queue.set_capacity(Priority::Normal, None);
+    queue.push(8, Priority::Normal, true).unwrap();
+  }
+
+  #[test]
+  fn closed_queue_rejects_events() {
+    let queue = UserEventQueue::new();
+    queue.push(1, Priority::Normal, true).unwrap();
+    queue.close();
+    assert_eq!(queue.pop(), None);
+    assert!(matches!(
+      queue.push(2, Priority::Normal, true),
+      Err(SendEventError::Closed(2))
+    ));
+  }
+}
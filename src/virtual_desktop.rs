@@ -0,0 +1,31 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+//! Types useful for interacting with the virtual desktops of the user, which are called
+//! workspaces on Linux and Spaces on macOS.
+//!
+//! The virtual desktops are listed by [`EventLoopWindowTarget::virtual_desktops`][loop_get], and
+//! the one a window is on is returned by [`Window::virtual_desktop`][window_get]. A window can be
+//! moved to another one with [`Window::move_to_virtual_desktop`][window_move].
+//!
+//! ## Platform-specific
+//!
+//! - **Windows:** Only the windows of the application can be queried and moved. Windows has no API
+//!   to list the virtual desktops, so they are read from the registry of Explorer, where they may
+//!   be missing until the user creates a second virtual desktop.
+//! - **Linux:** Only supported on X11, following `_NET_WM_DESKTOP`.
+//! - **macOS:** Spaces can't be listed or targeted by applications, only
+//!   [`Window::is_on_current_virtual_desktop`][window_current] is supported.
+//! - **iOS / Android:** Unsupported.
+//!
+//! [loop_get]: crate::event_loop::EventLoopWindowTarget::virtual_desktops
+//! [window_get]: crate::window::Window::virtual_desktop
+//! [window_move]: crate::window::Window::move_to_virtual_desktop
+//! [window_current]: crate::window::Window::is_on_current_virtual_desktop
+use crate::platform_impl;
+
+/// Handle to a virtual desktop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VirtualDesktop {
+  pub(crate) inner: platform_impl::VirtualDesktop,
+}
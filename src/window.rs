@@ -2,15 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! The `Window` struct and associated types.
-use std::fmt;
+use std::{fmt, time::Duration};
+
+use raw_window_handle::RawWindowHandle;
 
 use crate::{
-  dpi::{PhysicalPosition, PhysicalSize, Position, Size},
+  dpi::{LogicalSize, PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError},
   event_loop::EventLoopWindowTarget,
+  layout,
   menu::MenuBar,
   monitor::{MonitorHandle, VideoMode},
-  platform_impl,
+  platform_impl, redraw,
+  virtual_desktop::VirtualDesktop,
 };
 
 pub use crate::icon::{BadIcon, Icon};
@@ -34,7 +38,7 @@ pub use crate::icon::{BadIcon, Icon};
 ///
 ///     match event {
 ///         Event::WindowEvent {
-///             event: WindowEvent::CloseRequested,
+///             event: WindowEvent::CloseRequested { .. },
 ///             ..
 ///         } => *control_flow = ControlFlow::Exit,
 ///         _ => (),
@@ -53,6 +57,8 @@ impl fmt::Debug for Window {
 
 impl Drop for Window {
   fn drop(&mut self) {
+    redraw::unregister(self.id());
+
     // If the window is in exclusive fullscreen, we must restore the desktop
     // video mode (generally this would be done on application exit, but
     // closing the window doesn't necessarily always mean application exit,
@@ -92,6 +98,9 @@ pub struct WindowBuilder {
 
   // Platform-specific configuration.
   pub(crate) platform_specific: platform_impl::PlatformSpecificWindowBuilderAttributes,
+
+  // Applied to `window` once the monitors are known, in `build`.
+  restored_state: Option<WindowState>,
 }
 
 impl fmt::Debug for WindowBuilder {
@@ -130,15 +139,15 @@ pub struct WindowAttributes {
   /// ## Platform-specific
   ///
   /// - **macOS**: The top left corner position of the window content, the window's "inner"
-  /// position. The window title bar will be placed above it.
-  /// The window will be positioned such that it fits on screen, maintaining
-  /// set `inner_size` if any.
-  /// If you need to precisely position the top left corner of the whole window you have to
-  /// use [`Window::set_outer_position`] after creating the window.
+  ///   position. The window title bar will be placed above it.
+  ///   The window will be positioned such that it fits on screen, maintaining
+  ///   set `inner_size` if any.
+  ///   If you need to precisely position the top left corner of the whole window you have to
+  ///   use [`Window::set_outer_position`] after creating the window.
   /// - **Windows**: The top left corner position of the window title bar, the window's "outer"
-  /// position.
-  /// There may be a small gap between this position and the window due to the specifics of the
-  /// Window Manager.
+  ///   position.
+  ///   There may be a small gap between this position and the window due to the specifics of the
+  ///   Window Manager.
   /// - **Linux**: The top left corner of the window, the window's "outer" position.
   /// - **Others**: Ignored.
   ///
@@ -183,10 +192,50 @@ pub struct WindowAttributes {
   /// The default is `true`.
   pub decorations: bool,
 
-  /// Whether the window should always be on top of other windows.
+  /// Whether an undecorated, resizable window can be resized from its edges.
+  ///
+  /// The default is `true`.
+  pub resizable_borders: bool,
+
+  /// The level of the window, which decides whether it's above or below other windows.
+  ///
+  /// The default is [`WindowLevel::Normal`].
+  pub window_level: WindowLevel,
+
+  /// The window that owns this window.
+  ///
+  /// The default is `None`.
+  pub parent_window: Option<WindowId>,
+
+  /// The window of another toolkit or application this window is embedded in.
+  ///
+  /// The default is `None`.
+  pub foreign_parent: Option<RawWindowHandle>,
+
+  /// Whether the window blocks interaction with its parent window while it's open.
+  ///
+  /// The default is `false`.
+  pub modal: bool,
+
+  /// The type of the window.
+  ///
+  /// The default is [`WindowType::Normal`].
+  pub window_type: WindowType,
+
+  /// Whether the window can take the keyboard focus.
+  ///
+  /// The default is `true`.
+  pub focusable: bool,
+
+  /// The buttons shown in the titlebar of the window.
+  ///
+  /// The default is [`TitlebarButtons::all`].
+  pub titlebar_buttons: TitlebarButtons,
+
+  /// Whether minimizing the window hides it instead, see [`Window::set_minimize_to_tray`].
   ///
   /// The default is `false`.
-  pub always_on_top: bool,
+  pub minimize_to_tray: bool,
 
   /// The window icon.
   ///
@@ -214,7 +263,15 @@ impl Default for WindowAttributes {
       visible: true,
       transparent: false,
       decorations: true,
-      always_on_top: false,
+      resizable_borders: true,
+      window_level: WindowLevel::Normal,
+      parent_window: None,
+      foreign_parent: None,
+      modal: false,
+      window_type: WindowType::Normal,
+      focusable: true,
+      titlebar_buttons: TitlebarButtons::all(),
+      minimize_to_tray: false,
       window_icon: None,
       window_menu: None,
     }
@@ -327,6 +384,18 @@ impl WindowBuilder {
     self
   }
 
+  /// Restores a state captured with [`Window::capture_state`], overriding the position, size,
+  /// maximized and fullscreen attributes.
+  ///
+  /// The window is placed on the monitor it was on if it is still available, or else on the
+  /// monitor under its position or the primary monitor. It is moved and shrunk as needed so it
+  /// fits on that monitor.
+  #[inline]
+  pub fn with_restored_state(mut self, state: WindowState) -> Self {
+    self.restored_state = Some(state);
+    self
+  }
+
   /// Sets whether the window will be initially hidden or visible.
   ///
   /// See [`Window::set_visible`] for details.
@@ -356,14 +425,176 @@ impl WindowBuilder {
     self
   }
 
-  /// Sets whether or not the window will always be on top of other windows.
+  /// Sets whether an undecorated, resizable window keeps an invisible resize frame along its
+  /// edges, of [`BORDERLESS_RESIZE_INSET`] pixels inside the window.
+  ///
+  /// The default is `true`.
   ///
-  /// See [`Window::set_always_on_top`] for details.
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** Undecorated resizable windows always keep their native resize frame.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_resizable_borders(mut self, resizable_borders: bool) -> Self {
+    self.window.resizable_borders = resizable_borders;
+    self
+  }
+
+  /// Sets whether or not the window will always be on top of other windows.
   ///
-  /// [`Window::set_always_on_top`]: crate::window::Window::set_always_on_top
+  /// This is a shortcut for [`WindowLevel::Floating`], see [`WindowBuilder::with_window_level`].
   #[inline]
   pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
-    self.window.always_on_top = always_on_top;
+    self.window.window_level = if always_on_top {
+      WindowLevel::Floating
+    } else {
+      WindowLevel::Normal
+    };
+    self
+  }
+
+  /// Sets whether or not the window will always be below other windows.
+  ///
+  /// This is a shortcut for [`WindowLevel::AlwaysOnBottom`], see
+  /// [`WindowBuilder::with_window_level`].
+  #[inline]
+  pub fn with_always_on_bottom(mut self, always_on_bottom: bool) -> Self {
+    self.window.window_level = if always_on_bottom {
+      WindowLevel::AlwaysOnBottom
+    } else {
+      WindowLevel::Normal
+    };
+    self
+  }
+
+  /// Sets the level of the window.
+  ///
+  /// See [`Window::set_window_level`] for details.
+  ///
+  /// [`Window::set_window_level`]: crate::window::Window::set_window_level
+  #[inline]
+  pub fn with_window_level(mut self, level: WindowLevel) -> Self {
+    self.window.window_level = level;
+    self
+  }
+
+  /// Sets the window that owns the window to be created, like a dialog and its main window.
+  /// The window stays above its parent, and is destroyed along with it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The window is an owned window. Ignored if a parent is already set through
+  ///   `WindowBuilderExtWindows`.
+  /// - **Linux:** Sets the transient parent of the window.
+  /// - **macOS:** The window is a child window. Ignored if a parent is already set through
+  ///   `WindowBuilderExtMacOS`.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_parent_window(mut self, parent: &Window) -> Self {
+    self.window.parent_window = Some(parent.id());
+    self
+  }
+
+  /// Embeds the window to be created in a window that isn't owned by tao, e.g. the editor
+  /// window a plugin receives from its host application.
+  ///
+  /// The window is confined to its parent, and its position is relative to it. The handle must
+  /// stay valid as long as the window exists.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The window is a child window of the `hwnd` of the handle. Ignored if a parent
+  ///   is already set through `WindowBuilderExtWindows`.
+  /// - **Linux:** Only X11 windows are supported, given as `Xlib` or `Xcb` handles.
+  /// - **macOS:** The window is a child window of the `ns_window` of the handle, or the window of
+  ///   its `ns_view`. It moves along with its parent, but its position is in screen coordinates.
+  ///   Ignored if a parent is already set through `WindowBuilderExtMacOS`.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_parent(mut self, parent: RawWindowHandle) -> Self {
+    self.window.foreign_parent = Some(parent);
+    self
+  }
+
+  /// Sets whether the window blocks interaction with its parent window, set with
+  /// [`WindowBuilder::with_parent_window`], until it's destroyed.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The owner window is disabled until the window is dropped.
+  /// - **macOS:** The window is shown as a sheet of its parent, regardless of
+  ///   [`WindowBuilder::with_visible`].
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_modal(mut self, modal: bool) -> Self {
+    self.window.modal = modal;
+    self
+  }
+
+  /// Sets the type of the window, so auxiliary windows are treated correctly by the shell.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Every type but [`WindowType::Normal`] is a tool window, which has a thin
+  ///   titlebar and no taskbar or Alt+Tab entry. Tooltips are always on top.
+  /// - **Linux:** Sets the matching `_NET_WM_WINDOW_TYPE`.
+  /// - **macOS:** Utility, tooltip and popup windows are `NSPanel`s, with a matching window level.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_window_type(mut self, window_type: WindowType) -> Self {
+    self.window.window_type = window_type;
+    self
+  }
+
+  /// Sets up the window as a splash screen shown while the application starts: an undecorated
+  /// [`WindowType::Splash`] window above the other windows, without a taskbar entry, that can't
+  /// take the focus. It's centered on the primary monitor unless a position is set.
+  ///
+  /// Once the main window is ready, show it in place of the splash screen with
+  /// [`Window::hand_over_to`].
+  #[inline]
+  pub fn with_splash_screen(mut self) -> Self {
+    self.window.window_type = WindowType::Splash;
+    self.window.decorations = false;
+    self.window.window_level = WindowLevel::Floating;
+    self.window.focusable = false;
+    self
+  }
+
+  /// Sets whether the window can take the keyboard focus.
+  ///
+  /// A window that isn't focusable is never activated, neither when it's shown nor when it's
+  /// clicked, so the focus stays in the window the user is typing into. This is what on-screen
+  /// keyboards, notification popups and tool palettes need.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Uses `WS_EX_NOACTIVATE`. The window still has a taskbar entry unless it is
+  ///   owned or isn't a [`WindowType::Normal`] window.
+  /// - **Linux:** Uses `gtk_window_set_accept_focus`, which the window manager may ignore.
+  /// - **macOS:** The window is a non-activating `NSPanel` that never becomes the key window.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_focusable(mut self, focusable: bool) -> Self {
+    self.window.focusable = focusable;
+    self
+  }
+
+  /// Sets the buttons shown in the titlebar of the window.
+  ///
+  /// See [`Window::set_titlebar_buttons`] for details.
+  #[inline]
+  pub fn with_titlebar_buttons(mut self, buttons: TitlebarButtons) -> Self {
+    self.window.titlebar_buttons = buttons;
+    self
+  }
+
+  /// Sets whether minimizing the window hides it instead, e.g. to keep it in the system tray.
+  ///
+  /// See [`Window::set_minimize_to_tray`] for details.
+  #[inline]
+  pub fn with_minimize_to_tray(mut self, minimize_to_tray: bool) -> Self {
+    self.window.minimize_to_tray = minimize_to_tray;
     self
   }
 
@@ -383,11 +614,28 @@ impl WindowBuilder {
   /// Possible causes of error include denied permission, incompatible system, and lack of memory.
   #[inline]
   pub fn build<T: 'static>(
-    self,
+    mut self,
     window_target: &EventLoopWindowTarget<T>,
   ) -> Result<Window, OsError> {
+    if let Some(state) = self.restored_state.take() {
+      let monitors = window_target.available_monitors().collect::<Vec<_>>();
+      state.restore(&mut self.window, &monitors, window_target.primary_monitor());
+    }
+
+    if self.window.window_type == WindowType::Splash && self.window.position.is_none() {
+      if let Some(monitor) = window_target.primary_monitor() {
+        let size = self
+          .window
+          .inner_size
+          .unwrap_or_else(|| LogicalSize::new(800.0, 600.0).into())
+          .to_physical::<u32>(monitor.scale_factor());
+        self.window.position = Some(layout::center_position(size, &monitor).into());
+      }
+    }
+
     platform_impl::Window::new(&window_target.p, self.window, self.platform_specific).map(
       |window| {
+        redraw::register(WindowId(window.id()));
         window.request_redraw();
         Window { window }
       },
@@ -449,13 +697,63 @@ impl Window {
   /// * While processing a `RedrawRequested` event that was sent during `MainEventsCleared` or any
   ///   directly subsequent `RedrawRequested` event.
   ///
+  /// The calls made while a `RedrawRequested` event is pending coalesce into it, so each frame
+  /// emits at most one `RedrawRequested` event for the window. See [`Window::redraw_counters`].
+  ///
   /// ## Platform-specific
   ///
   /// - **iOS:** Can only be called on the main thread.
-  /// - **Android:** Unsupported.
   #[inline]
   pub fn request_redraw(&self) {
-    self.window.request_redraw()
+    match redraw::request(self.id()) {
+      redraw::Request::Now => self.window.request_redraw(),
+      redraw::Request::After(delay) => self.window.request_redraw_after(delay),
+      redraw::Request::Coalesced | redraw::Request::Unregistered => (),
+    }
+  }
+
+  /// Sets the minimum time between the redraws of the window, or removes it with `None`.
+  ///
+  /// A redraw requested sooner after the previous `RedrawRequested` event is delayed until the
+  /// interval elapsed, e.g. to cap the frame rate of an animation. The redraws asked by the OS
+  /// aren't delayed. The default is `None`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS:** Unsupported, the redraws aren't delayed.
+  #[inline]
+  pub fn set_redraw_throttle(&self, interval: Option<Duration>) {
+    redraw::set_throttle(self.id(), interval)
+  }
+
+  /// Returns the counters of the redraws of the window.
+  ///
+  /// A request that didn't emit a `RedrawRequested` event of its own is counted in
+  /// [`RedrawCounters::coalesced`].
+  #[inline]
+  pub fn redraw_counters(&self) -> RedrawCounters {
+    redraw::counters(self.id())
+  }
+
+  /// Sets when the `RedrawRequested` events asked with [`Window::request_redraw`] are emitted.
+  ///
+  /// With [`RedrawPacing::VSync`], they follow the clock of the compositor, so an animation
+  /// drawn on each `RedrawRequested` is presented once per refresh of the monitor, without
+  /// tearing or stuttering. The default is [`RedrawPacing::Immediate`].
+  ///
+  /// The redraws asked by the OS, e.g. when the window is resized, are emitted right away.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Follows `DwmFlush`. Falls back to 60 frames per second when the desktop
+  ///   composition is disabled.
+  /// - **macOS:** Follows a `CVDisplayLink` of the active displays.
+  /// - **Linux:** Follows the frame clock of GTK, or the frame callbacks of the compositor with
+  ///   the Wayland backend.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_redraw_pacing(&self, pacing: RedrawPacing) {
+    self.window.set_redraw_pacing(pacing)
   }
 }
 
@@ -513,6 +811,21 @@ impl Window {
     self.window.set_outer_position(position.into())
   }
 
+  /// Moves the window down and to the right of `other`, like a new document of a multi-document
+  /// application.
+  ///
+  /// See [`layout::cascade_position`] for how the position is computed.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn cascade_from(&self, other: &Window) {
+    if let Some(position) = layout::cascade_position(other, self.outer_size()) {
+      self.set_outer_position(position);
+    }
+  }
+
   /// Returns the physical size of the window's client area.
   ///
   /// The client area is the content of the window, excluding the title bar and borders.
@@ -574,6 +887,19 @@ impl Window {
   pub fn set_max_inner_size<S: Into<Size>>(&self, max_size: Option<S>) {
     self.window.set_max_inner_size(max_size.map(|s| s.into()))
   }
+
+  /// Sets the aspect ratio the inner size of the window keeps when it is resized by the user.
+  ///
+  /// The current size is left untouched: the ratio only applies to interactive resizes.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The ratio is a hint that the window manager may ignore.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_aspect_ratio(&self, aspect_ratio: Option<AspectRatio>) {
+    self.window.set_aspect_ratio(aspect_ratio)
+  }
 }
 
 /// Misc. attribute functions.
@@ -593,7 +919,6 @@ impl Window {
   /// ## Platform-specific
   ///
   /// - **Windows:** Unsupported.
-
   #[inline]
   pub fn set_menu(&self, menu: Option<MenuBar>) {
     if let Some(menu) = menu {
@@ -615,6 +940,17 @@ impl Window {
     self.window.set_visible(visible)
   }
 
+  /// Shows the window without activating it, so the focus stays on the current window.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Android:** Unsupported.
+  /// - **iOS:** Same as `set_visible(true)`. Can only be called on the main thread.
+  #[inline]
+  pub fn show_without_activating(&self) {
+    self.window.show_without_activating()
+  }
+
   /// Bring the window to front and focus.
   ///
   /// ## Platform-specific
@@ -625,6 +961,66 @@ impl Window {
     self.window.set_focus()
   }
 
+  /// Shows `window` in place of this window, typically a splash screen built with
+  /// [`WindowBuilder::with_splash_screen`], and hides this window.
+  ///
+  /// `window` is moved to the center of the monitor of this window if it's on another one, then
+  /// it's shown and focused below this window, so the window is already drawn when it's revealed.
+  #[inline]
+  pub fn hand_over_to(&self, window: &Window) {
+    if let Some(monitor) = self.current_monitor() {
+      if window.current_monitor().as_ref() != Some(&monitor) {
+        window.set_outer_position(layout::center_position(window.outer_size(), &monitor));
+      }
+    }
+
+    window.set_visible(true);
+    window.set_focus();
+    self.set_visible(false);
+  }
+
+  /// Brings the window above its siblings without activating it or taking the focus.
+  ///
+  /// Does nothing if the window is hidden or minimized.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The window isn't raised above topmost windows unless it's always on top itself.
+  /// - **Linux:** The window manager may ignore this for windows of an inactive application.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn raise(&self) {
+    self.window.raise()
+  }
+
+  /// Moves the window right above `window` in the stacking order, without activating it.
+  ///
+  /// Does nothing if the window is hidden or minimized.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Does nothing if only one of the windows is always on top.
+  /// - **Linux:** The window manager may ignore this.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_above(&self, window: &Window) {
+    self.window.set_above(&window.window)
+  }
+
+  /// Moves the window right below `window` in the stacking order, without activating it.
+  ///
+  /// Does nothing if the window is hidden or minimized.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Does nothing if only one of the windows is always on top.
+  /// - **Linux:** The window manager may ignore this.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_below(&self, window: &Window) {
+    self.window.set_below(&window.window)
+  }
+
   /// Sets whether the window is resizable or not.
   ///
   /// Note that making the window unresizable doesn't exempt you from handling `Resized`, as that event can still be
@@ -644,6 +1040,59 @@ impl Window {
     self.window.set_resizable(resizable)
   }
 
+  /// Sets the edges the user can drag to resize the window, e.g. only [`Edges::HORIZONTAL`] for a
+  /// side panel that can only grow in width. All the edges are resizable by default.
+  ///
+  /// This has no effect if the window is not resizable, see [`Window::set_resizable`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux (decorated) / macOS:** Only whole axes can be restricted: the width is fixed when
+  ///   neither `LEFT` nor `RIGHT` is set, and the height when neither `TOP` nor `BOTTOM` is set.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_resizable_edges(&self, edges: Edges) {
+    self.window.set_resizable_edges(edges)
+  }
+
+  /// Sets the buttons shown in the titlebar of the window. The buttons that are left out are
+  /// hidden, or disabled where they can't be hidden, along with the matching system commands.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The close button can only be disabled, and the minimize and maximize buttons
+  ///   are only hidden when both are left out. The maximize button is also hidden when the window
+  ///   isn't resizable.
+  /// - **Linux:** Relies on the window manager, which may ignore the minimize and maximize buttons.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_titlebar_buttons(&self, buttons: TitlebarButtons) {
+    self.window.set_titlebar_buttons(buttons)
+  }
+
+  /// Enables or disables the window. A disabled window stays visible but doesn't receive
+  /// mouse, keyboard or touch input, e.g. while the application shows its own modal overlay.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The widgets of the window are greyed out. The Wayland backend only drops the
+  ///   input.
+  /// - **Android:** Unsupported.
+  #[inline]
+  pub fn set_enabled(&self, enabled: bool) {
+    self.window.set_enabled(enabled)
+  }
+
+  /// Gets whether the window is enabled, see [`Window::set_enabled`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Android:** Always returns `true`.
+  #[inline]
+  pub fn is_enabled(&self) -> bool {
+    self.window.is_enabled()
+  }
+
   /// Sets the window to minimized or back
   ///
   /// ## Platform-specific
@@ -664,6 +1113,22 @@ impl Window {
     self.window.set_maximized(maximized)
   }
 
+  /// Moves the window to `monitor` and maximizes it there, in a single step so the window isn't
+  /// shown restored on the new monitor first.
+  ///
+  /// When the window is restored, it stays on `monitor`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Hidden windows are only moved, like [`Window::set_maximized`] they are not
+  ///   maximized.
+  /// - **Linux:** An already maximized window has to be restored before it can be moved.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_maximized_on(&self, monitor: &MonitorHandle) {
+    self.window.set_maximized_on(monitor.clone())
+  }
+
   /// Gets the window's current maximized state.
   ///
   /// ## Platform-specific
@@ -674,6 +1139,31 @@ impl Window {
     self.window.is_maximized()
   }
 
+  /// Captures the position, size, maximized and fullscreen state of the window, to restore it
+  /// later with [`WindowBuilder::with_restored_state`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Wayland:** A maximized window is captured with its maximized size.
+  #[inline]
+  pub fn capture_state(&self) -> WindowState {
+    let maximized = self.is_maximized();
+    // A maximized window is captured with the geometry it goes back to when it is unmaximized.
+    let (position, size) = maximized
+      .then(|| self.window.normal_rect())
+      .flatten()
+      .unwrap_or_else(|| (self.outer_position().unwrap_or_default(), self.inner_size()));
+    WindowState {
+      position,
+      size,
+      maximized,
+      fullscreen: self.fullscreen().is_some(),
+      monitor: self
+        .current_monitor()
+        .map(|monitor| MonitorState::from(&monitor)),
+    }
+  }
+
   /// Gets the window's current vibility state.
   ///
   /// ## Platform-specific
@@ -723,11 +1213,35 @@ impl Window {
   /// - **Windows:** Screen saver is disabled in fullscreen mode.
   /// - **Linux:** The window will only fullscreen to current monitor no matter which enum variant.
   /// - **Android:** Unsupported.
+  /// - **Web:** Always borderless, and only while handling an input of the user.
   #[inline]
   pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
     self.window.set_fullscreen(fullscreen)
   }
 
+  /// Sets the window to exclusive fullscreen with the given video mode.
+  ///
+  /// Unlike `set_fullscreen(Some(Fullscreen::Exclusive(video_mode)))`, the video mode is
+  /// first checked against the modes its monitor currently reports, so a stale mode (e.g.
+  /// after the monitor was unplugged or reconfigured) is rejected instead of being applied.
+  /// The original video mode is restored when the window leaves fullscreen, and also when
+  /// the event loop exits or panics.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / Android / Web:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn set_fullscreen_video_mode(&self, video_mode: VideoMode) -> Result<(), ExternalError> {
+    if !video_mode
+      .monitor()
+      .video_modes()
+      .any(|mode| mode == video_mode)
+    {
+      return Err(ExternalError::NotSupported(NotSupportedError::new()));
+    }
+    self.window.set_fullscreen_video_mode(video_mode)
+  }
+
   /// Gets the window's current fullscreen state.
   ///
   /// ## Platform-specific
@@ -739,10 +1253,37 @@ impl Window {
     self.window.fullscreen()
   }
 
+  /// Turns the kiosk mode of the window on or off.
+  ///
+  /// In kiosk mode, the window is made borderless fullscreen on its current monitor and always
+  /// on top, and the shortcuts of the system that switch to other applications are blocked where
+  /// the platform permits it. Leaving the kiosk mode exits fullscreen and removes the always on
+  /// top state, whatever they were before.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The cursor is confined to the window, and the Windows keys, Alt+Tab, Alt+Esc
+  ///   and Ctrl+Esc are blocked for the whole session with a low-level keyboard hook. Ctrl+Alt+Del
+  ///   can't be blocked.
+  /// - **macOS:** Uses the simple fullscreen of `WindowExtMacOS::set_simple_fullscreen`, so the
+  ///   window stays in the current space, and hides the dock and the menu bar while disabling
+  ///   process switching, force quit, logging out and the Apple menu. Does nothing if the window
+  ///   is in native fullscreen.
+  /// - **Linux:** The keyboard and the pointer are grabbed, which keeps the shortcuts of the
+  ///   window manager from working on X11. Wayland compositors don't let clients grab the input.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_kiosk(&self, kiosk: bool) {
+    self.window.set_kiosk(kiosk)
+  }
+
   /// Turn window decorations on or off.
   ///
   /// ## Platform-specific
   ///
+  /// - **Linux:** The Wayland backend doesn't draw decorations itself. Compositors without the
+  ///   `xdg-decoration` protocol, like GNOME's, show the window without a titlebar, so the
+  ///   application draws its own and declares it with [`Window::set_drag_region`].
   /// - **iOS / Android:** Unsupported.
   ///
   /// [`setPrefersStatusBarHidden`]: https://developer.apple.com/documentation/uikit/uiviewcontroller/1621440-prefersstatusbarhidden?language=objc
@@ -751,22 +1292,169 @@ impl Window {
     self.window.set_decorations(decorations)
   }
 
-  /// Change whether or not the window will always be on top of other windows.
+  /// Turn the drop shadow of the window on or off.
   ///
   /// ## Platform-specific
   ///
+  /// - **Windows:** Only affects undecorated windows, which have no shadow by default.
+  ///   Decorated windows always have a shadow.
+  /// - **Linux:** Only affects undecorated windows, which have no shadow by default. The shadow
+  ///   is drawn by GTK with the client-side decorations, and needs a compositor. Toggling it on an
+  ///   undecorated window recreates the underlying `GdkWindow`.
   /// - **iOS / Android:** Unsupported.
   #[inline]
-  pub fn set_always_on_top(&self, always_on_top: bool) {
-    self.window.set_always_on_top(always_on_top)
+  pub fn set_shadow(&self, shadow: bool) {
+    self.window.set_shadow(shadow)
   }
 
-  /// Sets the window icon. On Windows and Linux, this is typically the small icon in the top-left
-  /// corner of the title bar.
+  /// Sets the opacity of the whole window, including its decorations, from `0.0` (fully
+  /// transparent) to `1.0` (fully opaque, the default). Values outside that range are clamped.
   ///
   /// ## Platform-specific
   ///
-  /// - **iOS / Android / macOS:** Unsupported.
+  /// - **Linux:** Requires a compositing window manager.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_opacity(&self, opacity: f32) {
+    self.window.set_opacity(opacity.clamp(0.0, 1.0))
+  }
+
+  /// Prevents the window contents from being captured by other applications, e.g. in
+  /// screenshots, screen recordings or screen sharing.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The window is hidden from captures on Windows 10 version 2004 and later,
+  ///   and captured as a black rectangle on earlier versions.
+  /// - **Linux / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_content_protected(&self, protected: bool) {
+    self.window.set_content_protected(protected)
+  }
+
+  /// Change whether or not the window will always be on top of other windows.
+  ///
+  /// This is a shortcut for [`WindowLevel::Floating`], see [`Window::set_window_level`].
+  #[inline]
+  pub fn set_always_on_top(&self, always_on_top: bool) {
+    self.set_window_level(if always_on_top {
+      WindowLevel::Floating
+    } else {
+      WindowLevel::Normal
+    })
+  }
+
+  /// Change whether or not the window will always be below other windows.
+  ///
+  /// This is a shortcut for [`WindowLevel::AlwaysOnBottom`], see [`Window::set_window_level`].
+  #[inline]
+  pub fn set_always_on_bottom(&self, always_on_bottom: bool) {
+    self.set_window_level(if always_on_bottom {
+      WindowLevel::AlwaysOnBottom
+    } else {
+      WindowLevel::Normal
+    })
+  }
+
+  /// Sets the level of the window, which decides whether it's above or below other windows.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Windows only has topmost windows above the normal ones, so all the levels
+  ///   above [`WindowLevel::Normal`] are topmost. There is no guarantee that a window of
+  ///   [`WindowLevel::AlwaysOnBottom`] will be the bottom most but it will try to be.
+  /// - **Linux:** Uses `_NET_WM_STATE_ABOVE` and `_NET_WM_STATE_BELOW`, which are only hints to
+  ///   the window manager, so all the levels above [`WindowLevel::Normal`] are the same.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_window_level(&self, level: WindowLevel) {
+    self.window.set_window_level(level)
+  }
+
+  /// Change whether or not the window is shown on every workspace (virtual desktop), e.g. for a
+  /// floating mini player.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux**: Uses `_NET_WM_STATE_STICKY`, which is only a hint to the window manager.
+  /// - **Windows / iOS / Android:** Unsupported. Windows only lets the shell pin windows to every
+  ///   virtual desktop.
+  #[inline]
+  pub fn set_visible_on_all_workspaces(&self, visible: bool) {
+    self.window.set_visible_on_all_workspaces(visible)
+  }
+
+  /// Whether to show the window in the taskbar or not. This can be changed at any time, e.g. to
+  /// hide a window from the taskbar while it's minimized to the system tray.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The window is also removed from the pager.
+  /// - **iOS / Android / macOS:** Unsupported.
+  #[inline]
+  pub fn set_skip_taskbar(&self, skip: bool) {
+    self.window.set_skip_taskbar(skip)
+  }
+
+  /// Sets whether minimizing the window hides it instead, which also removes it from the
+  /// taskbar or the dock. [`WindowEvent::MinimizedToTray`] is sent every time this happens, so
+  /// the window can be restored with [`Window::set_visible`], e.g. from the menu of a system tray.
+  ///
+  /// Only the minimize requests of the user are intercepted, [`Window::set_minimized`] still
+  /// minimizes the window.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The window manager can't be stopped from minimizing the window, so it is
+  ///   restored and hidden right after it is minimized, including by [`Window::set_minimized`].
+  /// - **iOS / Android:** Unsupported.
+  ///
+  /// [`WindowEvent::MinimizedToTray`]: crate::event::WindowEvent::MinimizedToTray
+  #[inline]
+  pub fn set_minimize_to_tray(&self, minimize_to_tray: bool) {
+    self.window.set_minimize_to_tray(minimize_to_tray)
+  }
+
+  /// Forces the light or dark theme on the titlebar and the other parts of the window drawn by
+  /// the system. Providing `None` follows the theme of the system again.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Needs Windows 10 October 2018 Update. [`WindowEvent::ThemeChanged`] is sent
+  ///   when the theme of the window changes.
+  /// - **Linux:** GTK has a single theme for the whole application, so every window is affected.
+  /// - **iOS / Android:** Unsupported.
+  ///
+  /// [`WindowEvent::ThemeChanged`]: crate::event::WindowEvent::ThemeChanged
+  #[inline]
+  pub fn set_theme(&self, theme: Option<Theme>) {
+    self.window.set_theme(theme)
+  }
+
+  /// Sets the effect drawn behind the content of the window, or removes it when `None`.
+  ///
+  /// The effect is only visible through the transparent parts of the window, see
+  /// [`WindowBuilder::with_transparent`]. Effects that are not supported by the current system
+  /// fall back to the closest supported one.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** `Mica` requires Windows 11 and falls back to `Acrylic` on Windows 10.
+  ///   `Acrylic` requires Windows 10 October 2018 Update and falls back to `Blur`.
+  /// - **macOS:** Uses an `NSVisualEffectView` with a material matching the effect.
+  /// - **Linux:** Every effect is a `Blur`, which is only supported by KWin on X11.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_background_effect(&self, effect: Option<Effect>) {
+    self.window.set_background_effect(effect)
+  }
+
+  /// Sets the window icon. On Windows and Linux, this is typically the small icon in the top-left
+  /// corner of the title bar.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android / macOS:** Unsupported.
   ///
   /// On Windows, this sets `ICON_SMALL`. The base size for a window icon is 16x16, but it's
   /// recommended to account for screen scaling and pick a multiple of that, i.e. 32x32.
@@ -777,12 +1465,65 @@ impl Window {
 
   /// Sets location of IME candidate box in client area coordinates relative to the top left.
   ///
+  /// This is [`Window::set_ime_cursor_area`] with an empty area.
+  ///
   /// ## Platform-specific
   ///
-  /// - **iOS / Android:** Unsupported.
+  /// - **iOS / Android / Web:** Unsupported.
   #[inline]
   pub fn set_ime_position<P: Into<Position>>(&self, position: P) {
-    self.window.set_ime_position(position.into())
+    self.set_ime_cursor_area(position, PhysicalSize::new(0, 0))
+  }
+
+  /// Sets the area of the text being edited, in client area coordinates relative to the top
+  /// left, which the IME places its candidate box next to without covering it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android / Web:** Unsupported.
+  #[inline]
+  pub fn set_ime_cursor_area<P: Into<Position>, S: Into<Size>>(&self, position: P, size: S) {
+    self
+      .window
+      .set_ime_cursor_area(position.into(), size.into())
+  }
+
+  /// Sets whether the IME can compose text in the window, sending [`WindowEvent::Ime`] events.
+  ///
+  /// The IME is allowed by default. Disallowing it, e.g. in a game, cancels the composition in
+  /// progress and makes the keys only send `KeyboardInput` events.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS:** The dead keys are disabled with the IME.
+  /// - **iOS / Android / Web:** Unsupported.
+  ///
+  /// [`WindowEvent::Ime`]: crate::event::WindowEvent::Ime
+  #[inline]
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    self.window.set_ime_allowed(allowed)
+  }
+
+  /// Starts the text input of the window, sending the text entered into it with
+  /// [`WindowEvent::ReceivedText`] until [`Window::stop_text_input`] is called.
+  ///
+  /// The text input is stopped by default, e.g. for a game, the keys being only sent as
+  /// `KeyboardInput` events. A text field would start it while it has the focus.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Android / iOS:** Unsupported.
+  ///
+  /// [`WindowEvent::ReceivedText`]: crate::event::WindowEvent::ReceivedText
+  #[inline]
+  pub fn start_text_input(&self) {
+    self.window.set_text_input(true)
+  }
+
+  /// Stops the text input of the window started by [`Window::start_text_input`].
+  #[inline]
+  pub fn stop_text_input(&self) {
+    self.window.set_text_input(false)
   }
 
   /// Requests user attention to the window, this has no effect if the application
@@ -795,13 +1536,53 @@ impl Window {
   /// ## Platform-specific
   ///
   /// - **iOS / Android:** Unsupported.
-  /// - **macOS:** `None` has no effect.
+  /// - **macOS:** The request is shared by the whole application, a new request cancels the
+  ///   previous one and `None` cancels it. See also
+  ///   `EventLoopWindowTargetExtMacOS::request_dock_attention`.
   /// - **Linux:** Urgency levels have the same effect.
   #[inline]
   pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
     self.window.request_user_attention(request_type)
   }
 
+  /// Sets the progress indicator shown on the taskbar button or dock icon of the application.
+  ///
+  /// `progress` is a percentage between `0` and `100` and is ignored for
+  /// [`ProgressState::None`] and [`ProgressState::Indeterminate`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Uses the Unity `LauncherEntry` D-Bus API, the application needs a `.desktop`
+  ///   file named after the program name for the launcher to pick it up.
+  ///   `Paused` and `Error` are shown as `Normal`.
+  /// - **macOS:** The progress is drawn on the dock icon and is shared by all windows.
+  ///   `Paused` and `Error` are shown as `Normal`.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_progress_bar(&self, state: ProgressState, progress: Option<u64>) {
+    self
+      .window
+      .set_progress_bar(state, progress.map(|p| p.min(100)))
+  }
+
+  /// Sets the count badge shown on the taskbar button or dock icon of the application, like a
+  /// number of unread messages. `None` removes the badge.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The count is drawn as the overlay icon of the taskbar button, replacing
+  ///   the one set with `WindowExtWindows::set_taskbar_overlay_icon`. Counts above 99 are
+  ///   shown as `99+`.
+  /// - **Linux:** Uses the Unity `LauncherEntry` D-Bus API, see [`Window::set_progress_bar`].
+  /// - **macOS:** The badge is shown on the dock icon and is shared by all windows.
+  /// - **iOS:** The badge is shared by all windows, and requires the user to allow badges
+  ///   through `UNUserNotificationCenter`. `None` and `Some(0)` both remove it.
+  /// - **Android:** Unsupported.
+  #[inline]
+  pub fn set_badge_count(&self, count: Option<u64>) {
+    self.window.set_badge_count(count)
+  }
+
   /// Hides the menu associated with the window
   ///
   /// ## Platform-specific
@@ -831,6 +1612,25 @@ impl Window {
   pub fn is_menu_visible(&self) -> bool {
     self.window.is_menu_visible()
   }
+
+  /// Captures the current content of the window, without its decorations.
+  ///
+  /// The content is read back from what the window system composited, so it also includes the
+  /// content drawn by child windows and webviews.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Uses `PrintWindow`, so windows that are covered by other windows are
+  ///   captured as well. Minimized windows can't be captured.
+  /// - **macOS:** Requires the screen recording permission on macOS 10.15+, without it only the
+  ///   desktop background is captured.
+  /// - **Linux:** Only the visible parts of the window are captured on X11.
+  /// - **Headless:** Returns a transparent image of the inner size of the window.
+  /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn capture_image(&self) -> Result<WindowImage, ExternalError> {
+    self.window.capture_image()
+  }
 }
 
 /// Cursor functions.
@@ -855,18 +1655,72 @@ impl Window {
     self.window.set_cursor_position(position.into())
   }
 
-  /// Grabs the cursor, preventing it from leaving the window.
+  /// Grabs the cursor, confining it to the window or locking it in place, see [`CursorGrabMode`].
   ///
   /// There's no guarantee that the cursor will be hidden. You should
   /// hide it by yourself if you want so.
   ///
+  /// While the cursor is locked, its motion is only reported by `DeviceEvent::MouseMotion`.
+  ///
   /// ## Platform-specific
   ///
-  /// - **macOS:** This locks the cursor in a fixed location, which looks visually awkward.
+  /// - **macOS:** [`CursorGrabMode::Confined`] returns an [`ExternalError::NotSupported`].
+  /// - **Web:** [`CursorGrabMode::Confined`] returns an [`ExternalError::NotSupported`], and
+  ///   [`CursorGrabMode::Locked`] requests the pointer lock of the canvas.
+  /// - **Linux:** Returns an [`ExternalError::NotSupported`] with GTK on Wayland, and with the
+  ///   Wayland backend when the compositor lacks the pointer constraints protocol.
   /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
   #[inline]
-  pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
-    self.window.set_cursor_grab(grab)
+  pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+    self.window.set_cursor_grab(mode)
+  }
+
+  /// Grabs the keyboard while the window is focused, so that the key combinations handled by the
+  /// system, like Alt+Tab, Cmd+Tab or the media keys, are sent to the window instead. This is
+  /// meant for the virtual machine viewers and the remote desktop clients.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Ctrl+Alt+Del can't be grabbed.
+  /// - **macOS:** Returns an [`ExternalError::Os`] when the application doesn't have the
+  ///   accessibility permission.
+  /// - **Linux:** Returns an [`ExternalError::NotSupported`] with GTK on Wayland, and with the
+  ///   Wayland backend when the compositor lacks the keyboard shortcuts inhibit protocol.
+  /// - **iOS / Android / Web:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn set_keyboard_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    self.window.set_keyboard_grab(grab)
+  }
+
+  /// Hands a fabricated input event to the window, which translates it like the events of the
+  /// system, see [`test`](crate::test).
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Returns an [`ExternalError::NotSupported`] with the Wayland backend.
+  /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
+  #[cfg(feature = "test")]
+  #[inline]
+  pub fn inject_event(&self, event: crate::test::SyntheticEvent) -> Result<(), ExternalError> {
+    self.window.inject_event(event)
+  }
+
+  /// Captures the mouse, so that its motion and its buttons are reported to the window even when
+  /// the cursor is outside of it, until the capture is released.
+  ///
+  /// The mouse is always captured from a button press to its release, so the drags started in the
+  /// window end in it.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Only the foreground window receives the mouse outside of it.
+  /// - **macOS:** Only the motion is reported outside of the window, while it's the key window.
+  /// - **Linux:** Returns an [`ExternalError::NotSupported`] on Wayland, where the mouse is only
+  ///   captured during the drags.
+  /// - **iOS / Android / Web:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn set_mouse_capture(&self, capture: bool) -> Result<(), ExternalError> {
+    self.window.set_mouse_capture(capture)
   }
 
   /// Modifies the cursor's visibility.
@@ -884,6 +1738,35 @@ impl Window {
     self.window.set_cursor_visible(visible)
   }
 
+  /// Makes the window ignore all cursor events, letting them pass through to the windows
+  /// below it. This is useful for overlays and HUDs.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    self.window.set_ignore_cursor_events(ignore)
+  }
+
+  /// Sets whether the OS may merge consecutive moves of the pointer into one event.
+  ///
+  /// When disabled, every position the pointer went through is sent as its own
+  /// `WindowEvent::CursorMoved` or `WindowEvent::Touch` event, with the `timestamp` of when it
+  /// was sampled, e.g. for drawing applications. The default is `true`.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Reads the moves back with `GetMouseMovePointsEx`, at most 64 per message.
+  /// - **macOS:** Applies to all the windows of the application.
+  /// - **Linux:** Disables the event compression of GDK.
+  /// - **iOS:** Applies to the touches, with `coalescedTouchesForTouch:`.
+  /// - **Android:** Applies to the touches, from the history of the motion events.
+  #[inline]
+  pub fn set_mouse_coalescing(&self, enabled: bool) {
+    self.window.set_mouse_coalescing(enabled)
+  }
+
   /// Moves the window with the left mouse button until the button is released.
   ///
   /// There's no guarantee that this will work unless the left mouse button was pressed
@@ -897,6 +1780,23 @@ impl Window {
   pub fn drag_window(&self) -> Result<(), ExternalError> {
     self.window.drag_window()
   }
+
+  /// Declares regions of the window that are handled natively, like the caption area and the
+  /// buttons of a custom titlebar. This replaces the regions set previously, and an empty `Vec`
+  /// removes all of them.
+  ///
+  /// Regions are meant for undecorated windows. When they overlap, the last one wins.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows / Linux:** Ignored while the window is decorated, except with the Wayland backend
+  ///   when the compositor doesn't decorate windows.
+  /// - **macOS:** [`DragRegionKind::Resize`] regions are ignored.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_drag_region(&self, regions: Vec<DragRegion>) {
+    self.window.set_drag_region(regions)
+  }
 }
 
 /// Monitor info functions.
@@ -944,6 +1844,37 @@ impl Window {
   }
 }
 
+/// Virtual desktop functions.
+///
+/// See the [`virtual_desktop`](crate::virtual_desktop) module for the platform support.
+impl Window {
+  /// Returns the virtual desktop the window is on.
+  ///
+  /// Returns `None` if it can't be identified, e.g. when the window was never shown or is
+  /// visible on all the virtual desktops.
+  #[inline]
+  pub fn virtual_desktop(&self) -> Option<VirtualDesktop> {
+    self
+      .window
+      .virtual_desktop()
+      .map(|inner| VirtualDesktop { inner })
+  }
+
+  /// Returns whether the window is on the virtual desktop the user is currently on.
+  ///
+  /// Returns `true` if it can't be identified.
+  #[inline]
+  pub fn is_on_current_virtual_desktop(&self) -> bool {
+    self.window.is_on_current_virtual_desktop()
+  }
+
+  /// Moves the window to another virtual desktop.
+  #[inline]
+  pub fn move_to_virtual_desktop(&self, desktop: &VirtualDesktop) -> Result<(), ExternalError> {
+    self.window.move_to_virtual_desktop(&desktop.inner)
+  }
+}
+
 // Safety: objc runtime calls are unsafe
 unsafe impl raw_window_handle::HasRawWindowHandle for Window {
   /// Returns a `raw_window_handle::RawWindowHandle` for the Window
@@ -951,18 +1882,32 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
   /// ## Platform-specific
   ///
   /// - **Android:** Only available after receiving the Resumed event and before Suspended. *If you*
-  /// *try to get the handle outside of that period, this function will panic*!
+  ///   *try to get the handle outside of that period, this function will panic*!
   fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
     self.window.raw_window_handle()
   }
 }
 
+/// How the cursor is grabbed by [`Window::set_cursor_grab`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CursorGrabMode {
+  /// The cursor moves freely.
+  #[default]
+  None,
+  /// The cursor moves, but can't leave the window.
+  Confined,
+  /// The cursor stays in place, e.g. for the camera of a game.
+  Locked,
+}
+
 /// Describes the appearance of the mouse cursor.
 #[non_exhaustive]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CursorIcon {
   /// The platform-dependent default cursor.
+  #[default]
   Default,
   /// A simple crosshair.
   Crosshair,
@@ -1017,12 +1962,6 @@ pub enum CursorIcon {
   RowResize,
 }
 
-impl Default for CursorIcon {
-  fn default() -> Self {
-    CursorIcon::Default
-  }
-}
-
 /// Fullscreen modes.
 #[non_exhaustive]
 #[derive(Clone, Debug, PartialEq)]
@@ -1040,8 +1979,79 @@ pub enum Theme {
   Dark,
 }
 
+/// A color with 8 bits per channel, e.g. to tint the titlebar of a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+  /// The opacity of the color, from transparent at `0` to opaque at `255`.
+  pub a: u8,
+}
+
+impl Color {
+  /// Creates an opaque color.
+  pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+    Color { r, g, b, a: 255 }
+  }
+
+  /// Creates a color with the given opacity.
+  pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+    Color { r, g, b, a }
+  }
+}
+
+/// An image of the content of a window, returned by [`Window::capture_image`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct WindowImage {
+  width: u32,
+  height: u32,
+  rgba: Vec<u8>,
+}
+
+impl WindowImage {
+  pub(crate) fn new(width: u32, height: u32, rgba: Vec<u8>) -> Self {
+    debug_assert_eq!(rgba.len(), (width * height * 4) as usize);
+    WindowImage {
+      width,
+      height,
+      rgba,
+    }
+  }
+
+  /// The width of the image in physical pixels.
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  /// The height of the image in physical pixels.
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+
+  /// The pixels of the image, row by row from the top, as non-premultiplied RGBA with 8 bits
+  /// per channel.
+  pub fn rgba(&self) -> &[u8] {
+    &self.rgba
+  }
+
+  /// Returns the pixels of the image, see [`WindowImage::rgba`].
+  pub fn into_rgba(self) -> Vec<u8> {
+    self.rgba
+  }
+}
+
+impl fmt::Debug for WindowImage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("WindowImage")
+      .field("width", &self.width)
+      .field("height", &self.height)
+      .finish()
+  }
+}
+
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum UserAttentionType {
   /// ## Platform-specific
   /// - **macOS:** Bounces the dock icon until the application is in focus.
@@ -1050,14 +2060,472 @@ pub enum UserAttentionType {
   /// ## Platform-specific
   /// - **macOS:** Bounces the dock icon once.
   /// - **Windows:** Flashes the taskbar button until the application is in focus.
+  #[default]
   Informational,
 }
 
-impl Default for UserAttentionType {
+/// The type of a window, which tells the shell how to treat it.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowType {
+  /// A regular window.
+  #[default]
+  Normal,
+  /// A tool palette or toolbar, which stays above the other windows of the application.
+  Utility,
+  /// A splash screen shown while the application starts.
+  Splash,
+  /// A tooltip.
+  Tooltip,
+  /// A popup, like a dropdown or a context menu.
+  Popup,
+}
+
+/// The level of a window, set with [`Window::set_window_level`].
+///
+/// Windows are shown above the windows of lower levels, and are ordered as usual among the
+/// windows of the same level.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WindowLevel {
+  /// Below the normal windows, like a desktop widget.
+  AlwaysOnBottom,
+  /// The level of regular windows.
+  #[default]
+  Normal,
+  /// Above the normal windows, like a tool palette or a mini player.
+  Floating,
+  /// The level of the modal panels that block the application, like alerts.
+  ModalPanel,
+  /// The level of the popup menus.
+  PopUpMenu,
+  /// Above everything else, like a screen saver.
+  ScreenSaver,
+}
+
+/// A background effect drawn behind the content of the window, set with
+/// [`Window::set_background_effect`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+  /// Blurs the content behind the window.
+  Blur,
+  /// Blurs the content behind the window and adds a noise texture, like the acrylic material
+  /// of Windows.
+  Acrylic,
+  /// Tints the window with the desktop wallpaper, like the mica material of Windows 11.
+  Mica,
+}
+
+/// When the `RedrawRequested` events of a window are emitted, set with
+/// [`Window::set_redraw_pacing`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RedrawPacing {
+  /// Right after the pending events, in the same iteration of the event loop if possible.
+  #[default]
+  Immediate,
+  /// At the next frame of the compositor, so at most once per refresh of the monitor.
+  VSync,
+}
+
+/// The redraws of a window, returned by [`Window::redraw_counters`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RedrawCounters {
+  /// The calls to [`Window::request_redraw`].
+  pub requested: u64,
+  /// The calls to [`Window::request_redraw`] that coalesced into a pending redraw.
+  pub coalesced: u64,
+  /// The `RedrawRequested` events of the window, including the ones asked by the OS.
+  pub emitted: u64,
+}
+
+/// The state of the progress indicator set with [`Window::set_progress_bar`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressState {
+  /// Hides the progress indicator.
+  #[default]
+  None,
+  /// Shows the progress indicator.
+  Normal,
+  /// Shows a progress indicator without a defined value.
+  Indeterminate,
+  /// Shows the progress indicator as paused. Yellow on Windows.
+  Paused,
+  /// Shows the progress indicator as failed. Red on Windows.
+  Error,
+}
+
+/// The state of a window, captured with [`Window::capture_state`] and restored with
+/// [`WindowBuilder::with_restored_state`], typically between two runs of an application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WindowState {
+  /// The position of the top-left corner of the window.
+  ///
+  /// For a maximized window, this is the position it goes back to when it is unmaximized.
+  pub position: PhysicalPosition<i32>,
+  /// The inner size of the window.
+  ///
+  /// For a maximized window, this is the size it goes back to when it is unmaximized.
+  pub size: PhysicalSize<u32>,
+  pub maximized: bool,
+  /// Whether the window was in fullscreen. It is restored as borderless fullscreen.
+  pub fullscreen: bool,
+  /// The monitor the window was on.
+  pub monitor: Option<MonitorState>,
+}
+
+/// A monitor of a [`WindowState`].
+///
+/// Identical monitors can share a name, so a monitor is identified by its name and its area.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MonitorState {
+  /// See [`MonitorHandle::name`].
+  pub name: Option<String>,
+  /// See [`MonitorHandle::position`].
+  pub position: PhysicalPosition<i32>,
+  /// See [`MonitorHandle::size`].
+  pub size: PhysicalSize<u32>,
+}
+
+impl From<&MonitorHandle> for MonitorState {
+  fn from(monitor: &MonitorHandle) -> Self {
+    MonitorState {
+      name: monitor.name(),
+      position: monitor.position(),
+      size: monitor.size(),
+    }
+  }
+}
+
+impl MonitorState {
+  fn contains(&self, position: PhysicalPosition<i32>) -> bool {
+    position.x >= self.position.x
+      && position.y >= self.position.y
+      && position.x < self.position.x + self.size.width as i32
+      && position.y < self.position.y + self.size.height as i32
+  }
+}
+
+impl WindowState {
+  fn restore(
+    &self,
+    attributes: &mut WindowAttributes,
+    monitors: &[MonitorHandle],
+    primary_monitor: Option<MonitorHandle>,
+  ) {
+    let states: Vec<MonitorState> = monitors.iter().map(MonitorState::from).collect();
+    let monitor = self
+      .find_monitor(&states)
+      .map(|index| monitors[index].clone())
+      .or(primary_monitor);
+
+    let (position, size) = self.placement(monitor.as_ref().map(MonitorState::from).as_ref());
+    attributes.position = Some(position.into());
+    attributes.inner_size = Some(size.into());
+    attributes.maximized = self.maximized;
+    attributes.fullscreen = if self.fullscreen {
+      Some(Fullscreen::Borderless(monitor))
+    } else {
+      None
+    };
+  }
+
+  /// Returns the index of the monitor the window was on if it is still available, or else of
+  /// the monitor under its position.
+  fn find_monitor(&self, monitors: &[MonitorState]) -> Option<usize> {
+    self
+      .monitor
+      .as_ref()
+      .and_then(|state| monitors.iter().position(|monitor| monitor == state))
+      .or_else(|| {
+        monitors
+          .iter()
+          .position(|monitor| monitor.contains(self.position))
+      })
+  }
+
+  /// Returns the position and the size of the window, moved and shrunk to fit on `monitor`.
+  fn placement(
+    &self,
+    monitor: Option<&MonitorState>,
+  ) -> (PhysicalPosition<i32>, PhysicalSize<u32>) {
+    let monitor = match monitor {
+      Some(monitor) => monitor,
+      None => return (self.position, self.size),
+    };
+    let origin = monitor.position;
+    let area = monitor.size;
+    let size = PhysicalSize::new(
+      self.size.width.min(area.width),
+      self.size.height.min(area.height),
+    );
+    let position = PhysicalPosition::new(
+      self
+        .position
+        .x
+        .min(origin.x + (area.width - size.width) as i32)
+        .max(origin.x),
+      self
+        .position
+        .y
+        .min(origin.y + (area.height - size.height) as i32)
+        .max(origin.y),
+    );
+    (position, size)
+  }
+}
+
+/// The ratio between the width and the height of the inner size of a window,
+/// see [`Window::set_aspect_ratio`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AspectRatio {
+  pub width: u32,
+  pub height: u32,
+}
+
+impl AspectRatio {
+  pub fn new(width: u32, height: u32) -> Self {
+    AspectRatio { width, height }
+  }
+
+  /// Returns the width divided by the height.
+  pub fn ratio(&self) -> f64 {
+    self.width as f64 / self.height as f64
+  }
+}
+
+bitflags! {
+    /// The edges of a window, see [`Window::set_resizable_edges`].
+    pub struct Edges: u32 {
+        const LEFT   = 1 << 0;
+        const RIGHT  = 1 << 1;
+        const TOP    = 1 << 2;
+        const BOTTOM = 1 << 3;
+        /// The left and right edges, which change the width of the window.
+        const HORIZONTAL = Self::LEFT.bits | Self::RIGHT.bits;
+        /// The top and bottom edges, which change the height of the window.
+        const VERTICAL = Self::TOP.bits | Self::BOTTOM.bits;
+    }
+}
+
+impl Default for Edges {
   fn default() -> Self {
-    UserAttentionType::Informational
+    Edges::all()
+  }
+}
+
+bitflags! {
+    /// The buttons in the titlebar of a window, see [`Window::set_titlebar_buttons`].
+    pub struct TitlebarButtons: u32 {
+        const MINIMIZE = 1 << 0;
+        const MAXIMIZE = 1 << 1;
+        const CLOSE    = 1 << 2;
+    }
+}
+
+impl Default for TitlebarButtons {
+  fn default() -> Self {
+    TitlebarButtons::all()
+  }
+}
+
+/// A group of windows that follow an owner window: they are minimized and restored along with
+/// it, and always stacked above it. Useful for the tool palettes of a main document window.
+///
+/// ## Platform-specific
+///
+/// - **Windows:** The windows of the group are owned windows of the owner.
+/// - **Linux:** The windows of the group are transient for the owner, and share its
+///   `GtkWindowGroup`.
+/// - **macOS:** The windows of the group are child windows of the owner.
+/// - **iOS / Android:** Unsupported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WindowGroup {
+  owner: WindowId,
+}
+
+impl WindowGroup {
+  /// Creates an empty group owned by `owner`.
+  pub fn new(owner: &Window) -> Self {
+    WindowGroup { owner: owner.id() }
+  }
+
+  /// Returns the id of the owner window of the group.
+  pub fn owner(&self) -> WindowId {
+    self.owner
+  }
+
+  /// Adds `window` to the group, removing it from the group it belonged to.
+  pub fn add(&self, window: &Window) {
+    if window.id() != self.owner {
+      window.window.set_owner(Some(self.owner.0));
+    }
+  }
+
+  /// Removes `window` from the group.
+  pub fn remove(&self, window: &Window) {
+    window.window.set_owner(None);
+  }
+}
+
+/// Defines the orientation that a window resize will be performed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResizeDirection {
+  East,
+  North,
+  NorthEast,
+  NorthWest,
+  South,
+  SouthEast,
+  SouthWest,
+  West,
+}
+
+/// What happens when the user interacts with a [`DragRegion`].
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DragRegionKind {
+  /// Dragging the region moves the window, like a titlebar.
+  Caption,
+  /// Dragging the region resizes the window in the given direction.
+  Resize(ResizeDirection),
+  /// Clicking the region minimizes the window.
+  MinimizeButton,
+  /// Clicking the region maximizes or restores the window.
+  MaximizeButton,
+  /// Clicking the region requests the window to close, see [`WindowEvent::CloseRequested`].
+  ///
+  /// [`WindowEvent::CloseRequested`]: crate::event::WindowEvent::CloseRequested
+  CloseButton,
+}
+
+/// A rectangle of the client area of a window that is handled natively,
+/// see [`Window::set_drag_region`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DragRegion {
+  /// The position of the region, relative to the top-left corner of the client area.
+  pub position: Position,
+  pub size: Size,
+  pub kind: DragRegionKind,
+}
+
+impl DragRegion {
+  pub fn new<P: Into<Position>, S: Into<Size>>(position: P, size: S, kind: DragRegionKind) -> Self {
+    DragRegion {
+      position: position.into(),
+      size: size.into(),
+      kind,
+    }
+  }
+
+  /// Returns whether `position`, relative to the top-left corner of the client area, is
+  /// inside the region.
+  pub fn contains(&self, position: PhysicalPosition<f64>, scale_factor: f64) -> bool {
+    let origin: PhysicalPosition<f64> = self.position.to_physical(scale_factor);
+    let size: PhysicalSize<f64> = self.size.to_physical(scale_factor);
+
+    position.x >= origin.x
+      && position.y >= origin.y
+      && position.x < origin.x + size.width
+      && position.y < origin.y + size.height
   }
 }
 
 /// A constant used to determine how much inside the window, the resize handler should appear (only used in Linux(gtk) and Windows).
 pub const BORDERLESS_RESIZE_INSET: i32 = 3;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn monitor(name: &str, x: i32, y: i32, width: u32, height: u32) -> MonitorState {
+    MonitorState {
+      name: Some(name.into()),
+      position: PhysicalPosition::new(x, y),
+      size: PhysicalSize::new(width, height),
+    }
+  }
+
+  fn state(x: i32, y: i32, width: u32, height: u32, monitor: Option<MonitorState>) -> WindowState {
+    WindowState {
+      position: PhysicalPosition::new(x, y),
+      size: PhysicalSize::new(width, height),
+      maximized: false,
+      fullscreen: false,
+      monitor,
+    }
+  }
+
+  #[test]
+  fn find_monitor_by_name_and_area() {
+    let left = monitor("DELL U2720Q", 0, 0, 1920, 1080);
+    let right = monitor("DELL U2720Q", 1920, 0, 1920, 1080);
+    let monitors = [left, right.clone()];
+
+    // The window was moved off its monitor, which is still found by its name and area.
+    let window = state(-3000, 0, 800, 600, Some(right));
+    assert_eq!(window.find_monitor(&monitors), Some(1));
+  }
+
+  #[test]
+  fn find_monitor_gone() {
+    let monitors = [
+      monitor("Built-in", 0, 0, 1440, 900),
+      monitor("External", 1440, 0, 2560, 1440),
+    ];
+    let gone = monitor("Projector", 4000, 0, 1024, 768);
+
+    // The monitor under the position is used instead.
+    let window = state(1500, 100, 800, 600, Some(gone.clone()));
+    assert_eq!(window.find_monitor(&monitors), Some(1));
+
+    // And the primary monitor if there is none.
+    let window = state(4100, 100, 800, 600, Some(gone));
+    assert_eq!(window.find_monitor(&monitors), None);
+  }
+
+  #[test]
+  fn placement_off_screen() {
+    let monitor = monitor("Built-in", 0, 0, 1440, 900);
+
+    let window = state(-500, -200, 800, 600, None);
+    assert_eq!(
+      window.placement(Some(&monitor)),
+      (PhysicalPosition::new(0, 0), PhysicalSize::new(800, 600))
+    );
+
+    let window = state(1400, 850, 800, 600, None);
+    assert_eq!(
+      window.placement(Some(&monitor)),
+      (PhysicalPosition::new(640, 300), PhysicalSize::new(800, 600))
+    );
+  }
+
+  #[test]
+  fn placement_smaller_monitor() {
+    let monitor = monitor("Built-in", 1920, 0, 1280, 720);
+
+    let window = state(2000, 100, 1920, 1080, None);
+    assert_eq!(
+      window.placement(Some(&monitor)),
+      (PhysicalPosition::new(1920, 0), PhysicalSize::new(1280, 720))
+    );
+  }
+
+  #[test]
+  fn placement_without_monitor() {
+    let window = state(-500, 5000, 800, 600, None);
+    assert_eq!(
+      window.placement(None),
+      (
+        PhysicalPosition::new(-500, 5000),
+        PhysicalSize::new(800, 600)
+      )
+    );
+  }
+}
@@ -0,0 +1,283 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "headless")]
+
+use std::time::Duration;
+
+use tao::{
+  event::{ElementState, Event, WindowEvent},
+  event_loop::{ControlFlow, EventLoop, Priority, TimerId},
+  keyboard::{Key, KeyCode},
+  platform::{headless::EventLoopWindowTargetExtHeadless, run_return::EventLoopExtRunReturn},
+  window::{WindowBuilder, WindowId},
+};
+
+#[test]
+fn close_request_destroys_window() {
+  let mut event_loop = EventLoop::new();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  let window_id = window.id();
+  let injector = event_loop.event_injector();
+  injector.request_close(window_id);
+  injector.request_close(window_id);
+
+  let mut events = Vec::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    match event {
+      Event::WindowEvent {
+        window_id: id,
+        event: WindowEvent::CloseRequested { response },
+        ..
+      } => {
+        assert_eq!(id, window_id);
+        // The first request is denied, the second one allowed.
+        if events.is_empty() {
+          response.deny();
+          events.push("denied");
+        } else {
+          response.allow();
+          events.push("allowed");
+        }
+      }
+      Event::WindowEvent {
+        event: WindowEvent::Destroyed,
+        ..
+      } => {
+        events.push("destroyed");
+        *control_flow = ControlFlow::Exit;
+      }
+      _ => (),
+    }
+  });
+
+  assert_eq!(events, ["denied", "allowed", "destroyed"]);
+  assert!(!window.is_visible());
+}
+
+#[test]
+fn destroyed_window_is_not_redrawn() {
+  let mut event_loop = EventLoop::new();
+  // The window is redrawn once it is created.
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  event_loop.event_injector().request_close(window.id());
+
+  let mut redraws = 0;
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    match event {
+      Event::WindowEvent {
+        event: WindowEvent::CloseRequested { response },
+        ..
+      } => {
+        response.allow();
+        window.request_redraw();
+      }
+      Event::WindowEvent {
+        event: WindowEvent::Destroyed,
+        ..
+      } => *control_flow = ControlFlow::Exit,
+      Event::RedrawRequested(_) => redraws += 1,
+      _ => (),
+    }
+  });
+
+  assert_eq!(redraws, 0);
+}
+
+#[test]
+fn text_input_sends_received_text() {
+  let mut event_loop = EventLoop::new();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  let window_id = window.id();
+  let injector = event_loop.event_injector();
+  let press = |key| {
+    injector.inject_keyboard_input(window_id, KeyCode::KeyA, key, ElementState::Pressed);
+  };
+
+  // The keys typed before the text input starts have no text.
+  press(Key::Character("a"));
+  window.start_text_input();
+  press(Key::Character("b"));
+  press(Key::Enter);
+  window.stop_text_input();
+  press(Key::Character("c"));
+  injector.request_exit();
+
+  let mut keys = 0;
+  let mut text = String::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    if let Event::WindowEvent { event, .. } = event {
+      match event {
+        WindowEvent::KeyboardInput { .. } => keys += 1,
+        WindowEvent::ReceivedText(received) => text.push_str(&received),
+        _ => (),
+      }
+    }
+  });
+
+  assert_eq!(keys, 4);
+  assert_eq!(text, "b");
+}
+
+#[test]
+fn timers_fire_until_cancelled() {
+  let mut event_loop = EventLoop::new();
+  let fast = TimerId(1);
+  let slow = TimerId(2);
+
+  let mut fired = Vec::new();
+  event_loop.run_return(|event, window_target, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    match event {
+      Event::NewEvents(tao::event::StartCause::Init) => {
+        window_target.set_timer(Duration::from_millis(5), fast);
+        window_target.set_timer(Duration::from_millis(100), slow);
+      }
+      Event::Timer(id) => {
+        fired.push(id);
+        if id == fast && fired.len() == 3 {
+          window_target.cancel_timer(fast);
+        }
+        if id == slow {
+          *control_flow = ControlFlow::Exit;
+        }
+      }
+      _ => (),
+    }
+  });
+
+  assert_eq!(fired, [fast, fast, fast, slow]);
+}
+
+#[test]
+fn exit_with_code_is_kept() {
+  let mut event_loop = EventLoop::new();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  window.request_redraw();
+
+  let mut loop_destroyed = false;
+  let exit_code = event_loop.run_return(|event, _, control_flow| match event {
+    Event::NewEvents(_) => *control_flow = ControlFlow::ExitWithCode(3),
+    // The handler can't change the control flow once an exit code was set.
+    Event::RedrawRequested(_) => *control_flow = ControlFlow::Wait,
+    Event::LoopDestroyed => loop_destroyed = true,
+    _ => (),
+  });
+
+  assert_eq!(exit_code, 3);
+  assert!(loop_destroyed);
+}
+
+#[test]
+fn user_events_are_sent_by_priority() {
+  let mut event_loop = EventLoop::with_user_event();
+  let proxy = event_loop.create_proxy();
+  proxy.send_event("normal 1").unwrap();
+  proxy
+    .send_event_with_priority("low", Priority::Low)
+    .unwrap();
+  proxy
+    .send_event_with_priority("high", Priority::High)
+    .unwrap();
+  proxy.send_event("normal 2").unwrap();
+
+  // A full queue gives the event back.
+  event_loop.set_user_event_capacity(Priority::Low, Some(1));
+  let error = proxy
+    .send_event_with_priority("dropped", Priority::Low)
+    .unwrap_err();
+  assert_eq!(error.into_inner(), "dropped");
+
+  let mut events = Vec::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    if let Event::UserEvent(event) = event {
+      events.push(event);
+      if events.len() == 4 {
+        *control_flow = ControlFlow::Exit;
+      }
+    }
+  });
+
+  assert_eq!(events, ["high", "normal 1", "normal 2", "low"]);
+}
+
+#[test]
+fn windows_of_event_loops_have_distinct_ids() {
+  let mut first_loop = EventLoop::new();
+  let mut second_loop = EventLoop::new();
+  let first = WindowBuilder::new().build(&first_loop).unwrap();
+  let second = WindowBuilder::new().build(&second_loop).unwrap();
+  assert_ne!(first.id(), second.id());
+
+  // The redraws of a window don't coalesce with the ones of the window of the other loop.
+  let redrawn = |event_loop: &mut EventLoop<()>| {
+    let mut redrawn: Option<WindowId> = None;
+    event_loop.run_return(|event, _, control_flow| {
+      *control_flow = ControlFlow::Wait;
+      if let Event::RedrawRequested(window_id) = event {
+        redrawn = Some(window_id);
+        *control_flow = ControlFlow::Exit;
+      }
+    });
+    redrawn
+  };
+  first.request_redraw();
+  second.request_redraw();
+  assert_eq!(redrawn(&mut first_loop), Some(first.id()));
+  assert_eq!(redrawn(&mut second_loop), Some(second.id()));
+}
+
+#[cfg(feature = "test")]
+#[test]
+fn injected_events_reach_the_window() {
+  use tao::{dpi::PhysicalPosition, event::MouseButton, test::SyntheticEvent};
+
+  let mut event_loop = EventLoop::new();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  let position = PhysicalPosition::new(10.0, 20.0);
+  let inject = |event| window.inject_event(event).unwrap();
+  inject(SyntheticEvent::Key {
+    physical_key: KeyCode::KeyA,
+    state: ElementState::Pressed,
+  });
+  inject(SyntheticEvent::MouseInput {
+    button: MouseButton::Left,
+    state: ElementState::Pressed,
+    position,
+  });
+  event_loop.event_injector().request_exit();
+
+  let mut events = Vec::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    if let Event::WindowEvent {
+      window_id, event, ..
+    } = event
+    {
+      assert_eq!(window_id, window.id());
+      match event {
+        WindowEvent::KeyboardInput { event, .. } => {
+          assert_eq!(event.physical_key, KeyCode::KeyA);
+          events.push("key");
+        }
+        WindowEvent::CursorMoved {
+          position: moved, ..
+        } => {
+          assert_eq!(moved, position);
+          events.push("cursor");
+        }
+        WindowEvent::MouseInput { button, state, .. } => {
+          assert_eq!((button, state), (MouseButton::Left, ElementState::Pressed));
+          events.push("button");
+        }
+        _ => (),
+      }
+    }
+  });
+
+  assert_eq!(events, ["key", "cursor", "button"]);
+}